@@ -0,0 +1,22 @@
+use convlog::tenhou::Log;
+use convlog::tenhou_to_mjai;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+const SAMPLE: &str = include_str!("../testdata/hanchan.json");
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hanchan");
+    group.throughput(Throughput::Bytes(SAMPLE.len() as u64));
+    group.bench_function("parse", |b| {
+        b.iter(|| Log::from_json_str(black_box(SAMPLE)).unwrap());
+    });
+
+    let log = Log::from_json_str(SAMPLE).unwrap();
+    group.bench_function("convert", |b| {
+        b.iter(|| tenhou_to_mjai(black_box(&log)).unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
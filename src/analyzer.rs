@@ -0,0 +1,307 @@
+//! Analyses run over the events of a game as it is replayed, whose results
+//! are written into the output next to the events they are about.
+//!
+//! Crates built on this one implement [`Analyzer`], e.g. for a model of how
+//! dangerous every discard was, and register it with [`Analyzers`]. Between
+//! reading a [`Game`] with an [`Adapter`](crate::ir::Adapter) and writing it
+//! with an [`Emitter`](crate::ir::Emitter), [`Analyzers::annotate`] replays
+//! it once for all of them and keeps what they found in
+//! [`Game::annotations`], which [`Mjai`](crate::ir::Mjai) writes as the
+//! `annotations` of every event.
+//!
+//! The crate comes with the analyzers of [`Builtin`], which `convert
+//! --annotate` registers by name before writing mjai output.
+
+use crate::hand::{shanten, waits};
+use crate::ir::{Game, GameMeta};
+use crate::replay::{self, KyokuState};
+use crate::Event;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+/// An analysis of the events of games.
+pub trait Analyzer {
+    /// The key of its annotations, e.g. `danger`, unique among those
+    /// registered together.
+    fn name(&self) -> &str;
+
+    /// Called before the first event of every game.
+    fn start_game(&mut self, _meta: &GameMeta) {}
+
+    /// The annotation of `event`, if any, given the state of its kyoku
+    /// `before` and `after` it. Events outside of kyokus, `start_game` and
+    /// `end_game`, see the state the last kyoku ended with.
+    fn analyze(&mut self, event: &Event, before: &KyokuState, after: &KyokuState) -> Option<Value>;
+}
+
+/// The analyzers a game is run through, in the order they were registered.
+#[derive(Default)]
+pub struct Analyzers {
+    analyzers: Vec<Box<dyn Analyzer>>,
+}
+
+impl Analyzers {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `analyzer`. One registered under a name already taken replaces
+    /// the annotations of the first.
+    pub fn register(&mut self, analyzer: Box<dyn Analyzer>) -> &mut Self {
+        self.analyzers.push(analyzer);
+        self
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.analyzers.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.analyzers.is_empty()
+    }
+
+    /// Replay `game` and add the annotations of every analyzer to
+    /// [`Game::annotations`], by their names.
+    ///
+    /// # Errors
+    ///
+    /// Fails for events that cannot be replayed, e.g. a discard of a tile
+    /// not in the hand, leaving the annotations as they were.
+    pub fn annotate(&mut self, game: &mut Game) -> replay::Result<()> {
+        for analyzer in &mut self.analyzers {
+            analyzer.start_game(&game.meta);
+        }
+        let mut annotations = game
+            .annotations
+            .clone()
+            .unwrap_or_else(|| vec![BTreeMap::new(); game.events.len()]);
+        annotations.resize_with(game.events.len(), BTreeMap::new);
+        let mut state = KyokuState::default();
+        for (index, event) in game.events.iter().enumerate() {
+            let before = state.clone();
+            state.apply(index, event)?;
+            for analyzer in &mut self.analyzers {
+                if let Some(annotation) = analyzer.analyze(event, &before, &state) {
+                    annotations[index].insert(analyzer.name().to_owned(), annotation);
+                }
+            }
+        }
+        game.annotations = Some(annotations);
+        Ok(())
+    }
+}
+
+impl FromIterator<Builtin> for Analyzers {
+    fn from_iter<I: IntoIterator<Item = Builtin>>(builtins: I) -> Self {
+        let mut analyzers = Self::new();
+        for builtin in builtins {
+            analyzers.register(builtin.analyzer());
+        }
+        analyzers
+    }
+}
+
+/// The analyzers that come with the crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    /// The shanten of the hand a discard leaves, see [`Shanten`].
+    Shanten,
+    /// The waits of the tenpai hand a discard leaves, see [`Waits`].
+    Waits,
+}
+
+impl Builtin {
+    /// The name used on the command line, also the key of its annotations.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Shanten => "shanten",
+            Self::Waits => "waits",
+        }
+    }
+
+    #[must_use]
+    pub fn analyzer(self) -> Box<dyn Analyzer> {
+        match self {
+            Self::Shanten => Box::new(Shanten),
+            Self::Waits => Box::new(Waits),
+        }
+    }
+}
+
+impl FromStr for Builtin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shanten" => Ok(Self::Shanten),
+            "waits" => Ok(Self::Waits),
+            _ => Err(format!(
+                r#"invalid analyzer: {s:?} (expected "shanten" or "waits")"#
+            )),
+        }
+    }
+}
+
+/// Annotates every `dahai` with the shanten of the concealed hand it
+/// leaves, `0` for tenpai.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Shanten;
+
+impl Analyzer for Shanten {
+    fn name(&self) -> &str {
+        Builtin::Shanten.name()
+    }
+
+    fn analyze(&mut self, event: &Event, _: &KyokuState, after: &KyokuState) -> Option<Value> {
+        let Event::Dahai { actor, .. } = *event else {
+            return None;
+        };
+        Some(shanten(&after.players[usize::from(actor)].tehai).into())
+    }
+}
+
+/// Annotates every `dahai` that leaves a tenpai hand with the tiles it
+/// waits on, as mjai names them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Waits;
+
+impl Analyzer for Waits {
+    fn name(&self) -> &str {
+        Builtin::Waits.name()
+    }
+
+    fn analyze(&mut self, event: &Event, _: &KyokuState, after: &KyokuState) -> Option<Value> {
+        let Event::Dahai { actor, .. } = *event else {
+            return None;
+        };
+        let waits = waits(&after.players[usize::from(actor)].tehai);
+        (!waits.is_empty()).then(|| waits.iter().map(|tile| tile.as_str()).collect())
+    }
+}
+
+impl fmt::Debug for Analyzers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.analyzers.iter().map(|a| a.name()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{Adapter, Emitter, Mjai, Tenhou6};
+    use crate::testdata::Fixture;
+    use serde_json::json;
+
+    /// How many tiles safe against every other seat the discarder held.
+    struct SafeTiles;
+
+    impl Analyzer for SafeTiles {
+        fn name(&self) -> &str {
+            "safe_tiles"
+        }
+
+        fn analyze(&mut self, event: &Event, before: &KyokuState, _: &KyokuState) -> Option<Value> {
+            let Event::Dahai { actor, .. } = *event else {
+                return None;
+            };
+            let players = &before.players;
+            let safe = players[usize::from(actor)]
+                .tehai
+                .iter()
+                .filter(|tile| {
+                    (0..3)
+                        .filter(|&seat| seat != usize::from(actor))
+                        .all(|seat| players[seat].safe_tiles.contains(&tile.deaka()))
+                })
+                .count();
+            Some(safe.into())
+        }
+    }
+
+    /// The number of every game it sees.
+    #[derive(Default)]
+    struct Games(u32);
+
+    impl Analyzer for Games {
+        fn name(&self) -> &str {
+            "game"
+        }
+
+        fn start_game(&mut self, _: &GameMeta) {
+            self.0 += 1;
+        }
+
+        fn analyze(&mut self, event: &Event, _: &KyokuState, _: &KyokuState) -> Option<Value> {
+            matches!(event, Event::StartGame { .. }).then(|| self.0.into())
+        }
+    }
+
+    #[test]
+    fn annotate() {
+        let mut analyzers = Analyzers::new();
+        analyzers
+            .register(Box::new(SafeTiles))
+            .register(Box::<Games>::default());
+        assert_eq!(format!("{analyzers:?}"), r#"["safe_tiles", "game"]"#);
+
+        let mut game = Tenhou6::default().read(Fixture::Hanchan.json()).unwrap();
+        analyzers.annotate(&mut game).unwrap();
+        analyzers.annotate(&mut game).unwrap();
+        let annotations = game.annotations.as_ref().unwrap();
+        assert_eq!(annotations.len(), game.events.len());
+        assert_eq!(annotations[0]["game"], 2);
+
+        let mut out = vec![];
+        Mjai::default().emit(&game, &mut out).unwrap();
+        let events: Value = serde_json::from_slice(&out).unwrap();
+        let events = events.as_array().unwrap();
+        let dahai = events.iter().find(|e| e["type"] == "dahai").unwrap();
+        assert!(dahai["annotations"]["safe_tiles"].is_u64(), "{dahai}");
+        assert_eq!(events[0]["annotations"], json!({ "game": 2 }));
+        assert!(events[1].get("annotations").is_none());
+
+        // A draw before the first kyoku started.
+        let mut game = Tenhou6::default().read(Fixture::Hanchan.json()).unwrap();
+        let tsumo = Event::Tsumo {
+            actor: 0,
+            pai: crate::t!(P),
+        };
+        game.events.insert(1, tsumo);
+        analyzers.annotate(&mut game).unwrap_err();
+        assert!(game.annotations.is_none());
+    }
+
+    #[test]
+    fn builtins() {
+        let builtins = ["shanten", "waits"].map(|name| name.parse::<Builtin>().unwrap());
+        assert_eq!(builtins.map(Builtin::name), ["shanten", "waits"]);
+        "danger".parse::<Builtin>().unwrap_err();
+
+        let mut analyzers: Analyzers = builtins.into_iter().collect();
+        let mut game = Tenhou6::default().read(Fixture::Hanchan.json()).unwrap();
+        analyzers.annotate(&mut game).unwrap();
+        let annotations = game.annotations.unwrap();
+        for (event, annotations) in game.events.iter().zip(&annotations) {
+            let Event::Dahai { .. } = event else {
+                assert!(annotations.is_empty());
+                continue;
+            };
+            let shanten = annotations["shanten"].as_i64().unwrap();
+            assert!((0..=6).contains(&shanten), "{shanten}");
+            if let Some(waits) = annotations.get("waits") {
+                assert_eq!(shanten, 0);
+                assert!(!waits.as_array().unwrap().is_empty());
+            }
+        }
+        assert!(annotations.iter().any(|a| a.contains_key("waits")));
+    }
+}
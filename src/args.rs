@@ -1,11 +1,1247 @@
-use clap::Parser;
+use crate::analyzer::Builtin;
+#[cfg(feature = "charts")]
+use crate::charts::{ChartKind, ImageFormat};
+use crate::conv::{AkaNormalization, HaipaiOrder};
+use crate::dataset::{DatasetFormat, DatasetRows};
+use crate::diagnostic::ErrorFormat;
+use crate::generate::GenFormat;
+use crate::locale::Locale;
+use crate::output::{AkaEncoding, JsonStyle, OutputFormat, ShardSize, TableFormat};
+use crate::plugin::Subprocess;
+use crate::ruleset::{Ruleset, TieBreak};
+use crate::schema::SchemaFormat;
+use crate::template::OutputTemplate;
+use crate::tenhou::{GameLength, Room, Rounding, ScoringRules, TsumoPayment, UnknownKeys};
+use crate::{Fields, LobbyFilter};
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+
+/// The exit statuses, see [`ExitStatus`](crate::diagnostic::ExitStatus).
+const EXIT_STATUS: &str = "\
+Exit status:
+  0  success
+  1  any other failure
+  2  wrong command line
+  3  a log could not be parsed
+  4  a log failed to convert or to validate
+  5  reading inputs or writing outputs failed
+  6  some logs of a batch failed, the rest went through";
 
 #[derive(Parser)]
-#[command(name = "Conv")]
+#[command(name = "Conv", after_help = EXIT_STATUS)]
 pub struct ConvCli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Convert tenhou logs to mjai events or text transcripts.
+    Convert(ConvertArgs),
+
+    /// Check that logs convert and that their results follow the sanma
+    /// rules, reporting each problem on stderr.
+    Validate(ValidateArgs),
+
+    /// Check mjai events another tool wrote for a legal sanma game: the
+    /// order of play, no chi, nukidora only of norths and scores that carry
+    /// over from kyoku to kyoku, for debugging other converters.
+    ValidateMjai(ValidateMjaiArgs),
+
+    /// Count the games, kyokus and results in a directory of logs.
+    Stats(StatsArgs),
+
+    /// Copy the logs that pass the selection to another directory as they
+    /// are.
+    Filter(FilterArgs),
+
+    /// Scan the logs once and write what each says of its game, its ID,
+    /// date, players and rules, and how it went as JSON lines, for the
+    /// `--index` flag of the other subcommands to select logs by without
+    /// parsing them.
+    Index(IndexArgs),
+
+    /// Write every kyoku as a tenhou log of its own, named after the input
+    /// file, the index of the kyoku and its round and honba, e.g.
+    /// `game-03-E2-1.json`.
+    Split(SplitArgs),
+
+    /// Rewrite logs in a canonical form, so that logs of the same game
+    /// from different sources are byte for byte the same.
+    Normalize(NormalizeArgs),
+
+    /// Compare two logs, tenhou logs or mjai event arrays alike, event by
+    /// event and report the first difference in each kyoku.
+    Diff(DiffArgs),
+
+    /// Convert a log and compare the result with known good mjai events,
+    /// event by event rather than byte by byte, printing a unified diff of
+    /// every kyoku that differs, for the regression suites of converters.
+    Verify(VerifyArgs),
+
+    /// Write a dataset of every discard of the logs, with the hand and
+    /// table it was made at, e.g. for training discard models.
+    Dataset(DatasetArgs),
+
+    /// Index the kyokus of the logs and filter them interactively by
+    /// player, yaku, outcome and date, listing and exporting the matches,
+    /// for study sessions. Type "help" at the prompt for the commands.
+    Repl(ReplArgs),
+
+    /// Print the JSON Schema of an output, so that pipelines can validate
+    /// and generate code against it.
+    Schema(SchemaArgs),
+
+    /// Play random but legal sanma games from a seed and write them as
+    /// logs, named after their game IDs, for testing and benchmarking.
+    Gen(GenArgs),
+
+    /// Convert the tags of a game tenhou is streaming, read from stdin, to
+    /// mjai events written to stdout one per line as soon as they happen.
+    Live(LiveArgs),
+
+    /// Step through the events of a log, a tenhou log or mjai events, with
+    /// the hands and rivers drawn in the terminal.
+    #[cfg(feature = "tui")]
+    Browse { log: String },
+
+    /// Play a kyoku of a log, a tenhou log or mjai events, in the terminal,
+    /// an event at a time, e.g. for demos.
+    #[cfg(feature = "tui")]
+    Replay(ReplayArgs),
+
+    /// Serve conversions over HTTP at `POST /convert?format=mjai`.
+    #[cfg(feature = "http-server")]
+    Serve {
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+    },
+
+    /// Serve the gRPC service defined in `proto/convlog.proto`.
+    #[cfg(feature = "grpc")]
+    Grpc {
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        #[arg(short, long, default_value_t = 50051)]
+        port: u16,
+    },
+}
+
+/// Which logs of a directory to work on.
+#[derive(Args)]
+pub struct SelectArgs {
+    /// Only take logs from these lobbies, e.g. `1234,5678`, or not from
+    /// those prefixed with `!`, e.g. `!0` to leave out the public lobby.
+    #[arg(long)]
+    pub lobbies: Option<LobbyFilter>,
+
+    /// Only take games played on or after this date in Japan time, e.g.
+    /// `2024-03-01`. Logs without a game ID are left out.
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+
+    /// Only take games played on or before this date in Japan time.
+    #[arg(long)]
+    pub until: Option<NaiveDate>,
+
+    /// Only take games with a player of this name, given once per name to
+    /// take games with any of them.
+    #[arg(long, value_name = "NAME")]
+    pub include_player: Vec<String>,
+
+    /// Leave out games with a player of this name, given once per name.
+    #[arg(long, value_name = "NAME")]
+    pub exclude_player: Vec<String>,
+
+    /// Only take games played in this room, "ippan", "joukyuu", "tokujou" or
+    /// "houou" (or `般`, `上`, `特`, `鳳`), given once per room to take any
+    /// of them.
+    #[arg(long)]
+    pub room: Vec<Room>,
+
+    /// Only take games of this rating segment, e.g. `PF3`, given once per
+    /// segment. Logs without one are left out.
+    #[arg(long, value_name = "SEGMENT")]
+    pub ratingc: Vec<String>,
+
+    /// Only take games whose rule string contains this, e.g. `鳳南` for
+    /// hanchan in the houou room. Logs without a rule string are matched on
+    /// the one their game ID makes.
+    #[arg(long, value_name = "TEXT")]
+    pub rule: Option<String>,
+
+    /// Leave out logs of games already seen in this run, matched by game ID
+    /// or by content.
+    #[arg(long)]
+    pub dedupe: bool,
+
+    /// Leave out games in which tenhou made more of the actions than this
+    /// allows for disconnected seats: the share of the actions made by
+    /// connected seats, e.g. `0.95`.
+    #[arg(long, value_name = "SHARE")]
+    pub min_quality: Option<f64>,
+
+    /// Leave out the logs this index, written by `convlog index` from the
+    /// same input, says fail the flags above without parsing them. Logs
+    /// changed since or not in it are parsed as usual.
+    #[arg(long, value_name = "FILE")]
+    pub index: Option<PathBuf>,
+}
+
+/// How the input directory is gone through, or the input fetched if it is a
+/// URL.
+#[derive(Args, Default)]
+pub struct WalkArgs {
+    /// Also take the logs in subdirectories of the input directory, e.g. an
+    /// archive sharded by date.
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Threads listing the input directory and reading its logs while
+    /// earlier ones are worked on; one per CPU by default.
+    #[arg(long, value_name = "N", default_value_t = 0, hide_default_value = true)]
+    pub walk_threads: usize,
+
+    /// Print how many directories, files and bytes were read and how fast
+    /// on stderr once the input directory is done.
+    #[arg(long)]
+    pub walk_stats: bool,
+
+    /// Keep logs fetched from URL inputs in this directory and take them
+    /// from there on later runs.
+    #[cfg(feature = "remote")]
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// How many times to try fetching a URL input again after a timeout or
+    /// server error.
+    #[cfg(feature = "remote")]
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub retries: u32,
+
+    /// The converter service `majsoul:` inputs are fetched from, a URL with
+    /// `{uuid}` in place of the record.
+    #[cfg(feature = "majsoul")]
+    #[arg(long, value_name = "URL")]
+    pub majsoul_converter: Option<String>,
+}
+
+/// What a batch does once a log fails.
+#[derive(Args, Default)]
+pub struct FailureArgs {
+    /// Stop at the first log that fails, exiting with the status of its
+    /// failure.
+    #[arg(long, overrides_with = "keep_going")]
+    pub fail_fast: bool,
+
+    /// Report every log that fails and go on with the rest, the default.
+    #[arg(long, overrides_with = "fail_fast")]
+    pub keep_going: bool,
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    #[arg(short, long)]
+    pub input: String,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[arg(short, long)]
+    pub output: String,
+
+    #[command(flatten)]
+    pub select: SelectArgs,
+
+    #[command(flatten)]
+    pub failure: FailureArgs,
+
+    /// What to write: "mjai" events, a plain "text" transcript or "csv" with
+    /// a row per event.
+    #[arg(long, default_value = "mjai")]
+    pub format: OutputFormat,
+
+    /// Indent JSON output for reading instead of writing it on one line.
+    #[arg(long)]
+    pub pretty: bool,
+
+    /// Language of tile, round and yaku names in text output: "en" or "ja".
+    #[arg(long, default_value = "en")]
+    pub locale: Locale,
+
+    /// Path of each output relative to `--output`, e.g.
+    /// `{date}/{game_id}.{format}`. Defaults to `{game_id}.json`, or
+    /// `{game_id}.txt` for text and `{game_id}.csv` for csv.
+    #[arg(long)]
+    pub output_template: Option<OutputTemplate>,
+
+    /// Write each output under `year=YYYY/month=MM/` by the month its game
+    /// started and list every file written in `_manifest.jsonl`, for
+    /// data-lake tools to ingest. Later runs into the same directory add to
+    /// the manifest.
+    #[arg(long)]
+    pub partition: bool,
+
+    /// Write the SHA-256 of every output to this file as JSON lines, with
+    /// its path, the input and game ID it came from and the options it was
+    /// converted with, for integrity checks and audits of published
+    /// datasets. Later runs with the same file add to it.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["shard_size", "dry_run"])]
+    pub checksums: Option<PathBuf>,
+
+    /// Treat every log as "hanchan", "tonpuu", "ikkyoku" or a number of
+    /// kyokus instead of telling from its rule string and kyokus.
+    #[arg(long)]
+    pub game_length: Option<GameLength>,
+
+    /// How aka dora are emitted: "keep", "deaka" (plain fives) or "akaize"
+    /// (one aka five of each suit in logs without any).
+    #[arg(long, default_value = "keep")]
+    pub aka: AkaNormalization,
+
+    /// How kept aka dora, and with them every other tile, are written in
+    /// mjai, csv and English text output: "mjai" (`5pr`, `E`), "mpsz" (`0p`,
+    /// `1z`) or "tenhou" (`52`, `41`).
+    #[arg(long, default_value = "mjai")]
+    pub aka_encoding: AkaEncoding,
+
+    /// The order of haipai in `start_kyoku`: "preserve" the order of the
+    /// log, deal order for some sources, or "sort" them.
+    #[arg(long, default_value = "preserve")]
+    pub haipai: HaipaiOrder,
+
+    /// The rules whose tie-break ranks seats tied on the final score in
+    /// `end_game`: "tenhou-sanma", "majsoul-sanma-gold" or
+    /// "mleague-style-3p".
+    #[arg(long, default_value = "tenhou-sanma")]
+    pub rules: Ruleset,
+
+    /// How seats tied on the final score are ranked in `end_game`, instead
+    /// of as the rules do: "seat-order" (closest to the first dealer
+    /// first), or "shared" or "split", where they share the higher rank.
+    #[arg(long)]
+    pub tie_break: Option<TieBreak>,
+
+    /// Overwrite existing output files instead of refusing to.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Print what would be written where, instead of writing anything: each
+    /// output as `write`, `overwrite` or `exists` if `--force` is needed,
+    /// and each log left out by the selection flags or `--incremental` as
+    /// `skip`.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Convert the kyokus of each log in parallel, for very long logs.
+    #[arg(long)]
+    pub parallel_kyokus: bool,
+
+    /// Leave out kyokus that fail to convert, reporting each on stderr,
+    /// instead of failing the whole log.
+    #[arg(long)]
+    pub skip_invalid_kyokus: bool,
+
+    /// Drop the kyokus at the end of logs that did not finish, as games cut
+    /// off by a disconnect have, reporting each repaired log on stderr,
+    /// instead of failing the whole log.
+    #[arg(long)]
+    pub repair: bool,
+
+    /// Write mjai output as `{"envelope": ..., "events": [...]}`, the
+    /// envelope recording the converter and its version, the source format,
+    /// the rules of the game and the options of the conversion, so that
+    /// outputs tell how they were made. Only for `--format mjai`.
+    #[arg(long)]
+    pub envelope: bool,
+
+    /// Add to the envelope the estimated change of the R and dan points of
+    /// every seat, by the formulas of tenhou's sanma ranked games, from the
+    /// `rate` and `dan` of the log and the placements.
+    #[arg(long, requires = "envelope")]
+    pub ratings: bool,
+
+    /// Skip logs converted before into the output directory with the same
+    /// options whose output is still there, as the hashes of the logs in
+    /// `.convlog-cache.json` there record, so that runs over a growing
+    /// archive only convert what is new or changed.
+    #[arg(long)]
+    pub incremental: bool,
+
+    /// Write every output into one `.tar.zst` file at `--output`, under the
+    /// path it would have in the output directory, instead of as files of
+    /// their own, for network filesystems that are slow with many small
+    /// files.
+    #[arg(long, conflicts_with_all = ["partition", "incremental", "dry_run"])]
+    pub bundle: bool,
+
+    /// Resume a run into the same output directory with the same options
+    /// that was interrupted, skipping the inputs it finished, as
+    /// `.convlog-journal.jsonl` there records. Outputs of the inputs it did
+    /// not record are overwritten. Every run keeps that journal until it has
+    /// converted all of its logs.
+    #[arg(long, conflicts_with_all = ["bundle", "shard_size", "dry_run"])]
+    pub resume: bool,
+
+    /// Write the outputs one per line into numbered `shard-NNNNN.jsonl`
+    /// files in the output directory, each holding this many games, e.g.
+    /// `10000`, or up to this many bytes, e.g. `256MiB`, as training
+    /// dataloaders expect. Only for `--format mjai`.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        conflicts_with_all = ["pretty", "output_template", "partition", "incremental", "bundle", "dry_run"]
+    )]
+    pub shard_size: Option<ShardSize>,
+
+    /// Write each output with this program instead, which gets the mjai
+    /// output of the game, with its envelope if asked for, on stdin and
+    /// writes the output to stdout, for formats of one's own. Only for
+    /// `--format mjai`.
+    #[arg(long, value_name = "PROGRAM", conflicts_with = "shard_size")]
+    pub plugin: Option<PathBuf>,
+
+    /// An argument to pass the `--plugin` program, once per argument.
+    #[arg(
+        long,
+        value_name = "ARG",
+        requires = "plugin",
+        allow_hyphen_values = true
+    )]
+    pub plugin_arg: Vec<String>,
+
+    /// The file extension of the outputs of the `--plugin` program.
+    #[arg(long, value_name = "EXT", default_value = "out", requires = "plugin")]
+    pub plugin_extension: String,
+
+    /// Give every mjai and csv event a timestamp this many milliseconds
+    /// after the previous one, as the logs do not record when actions
+    /// happened.
+    #[arg(long, value_name = "MS")]
+    pub pseudo_timestamps: Option<u64>,
+
+    /// Write the per-seat arrays of mjai events, `names`, `scores`, `tehais`
+    /// and `deltas`, with three entries instead of four. Logs in which the
+    /// fourth seat takes part fail instead.
+    #[arg(long)]
+    pub three_seats: bool,
+
+    /// Add to `start_game` what the log records of the game besides names:
+    /// its `game_id`, the `dans` and `rates` of the seats, the `lobby`, the
+    /// `rule` as tenhou names it and the `rules` it stands for, aka dora
+    /// included, so that viewers can show them without the log.
+    #[arg(long)]
+    pub game_meta: bool,
+
+    /// Add to every mjai event within a turn the `turn` of the kyoku it falls
+    /// in, counting calls that skip seats, the `seat` whose turn it is and
+    /// that seat's `junme`.
+    #[arg(long)]
+    pub turn_order: bool,
+
+    /// Add to every mjai `start_kyoku` the whole wall as far as the log
+    /// tells, in the order tiles leave it: the haipai of the three seats,
+    /// the live wall, the rinshan draws and the places left for them, and
+    /// the dora and ura indicators, `null` for the tiles that never showed,
+    /// which `unseen` lists. For simulators that replay kyokus from their
+    /// middle. Only for `--format mjai`.
+    #[arg(long)]
+    pub wall: bool,
+
+    /// Run these analyzers over every game and add what they find about each
+    /// event to its `annotations`: "shanten", the shanten of the hand a
+    /// discard leaves, and "waits", its waits when tenpai, e.g.
+    /// `shanten,waits`. Only for `--format mjai`.
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    pub annotate: Vec<Builtin>,
+
+    /// Keep only these groups of fields in the outputs, for publishing
+    /// datasets: "actions", which is required, "results", "names",
+    /// "ratings", "lobby", "connections" and "game-id", e.g.
+    /// `actions,results`. The rest is left out of `start_game`, `end_game`,
+    /// envelopes and transcripts, and names left out become `Aさん` to
+    /// `Cさん`. File names made from game IDs still have them.
+    #[arg(long, value_name = "LIST", default_value_t = Fields::ALL, hide_default_value = true)]
+    pub fields: Fields,
+
+    /// How to report failures on stderr: "text" or "json", a record with
+    /// the file, kyoku, actor, action index, error code and message per
+    /// line.
+    #[arg(long, default_value = "text")]
+    pub error_format: ErrorFormat,
+}
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    #[arg(short, long)]
+    pub input: String,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub select: SelectArgs,
+
+    #[command(flatten)]
+    pub failure: FailureArgs,
+
+    /// The rules the games were played by: "tenhou-sanma",
+    /// "majsoul-sanma-gold" or "mleague-style-3p". Final results are
+    /// checked against its uma and oka; the flags below override its scoring
+    /// rules.
+    #[arg(long, default_value = "tenhou-sanma")]
+    pub rules: Ruleset,
+
+    /// Round 4 han 30 fu and 3 han 60 fu up to mangan when checking scores,
+    /// as the rules do unless given `--kiriage-mangan=false`.
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub kiriage_mangan: Option<bool>,
+
+    /// What becomes of the missing seat's share of a tsumo: "loss" or
+    /// "split" between the payers.
+    #[arg(long)]
+    pub tsumo: Option<TsumoPayment>,
+
+    /// When payments are rounded up to 100: once worked out ("payment") or
+    /// for every share ("share").
+    #[arg(long)]
+    pub rounding: Option<Rounding>,
+
+    /// How seats tied on the final score are placed when checking final
+    /// results: "seat-order", "shared" or "split".
+    #[arg(long)]
+    pub tie_break: Option<TieBreak>,
+
+    /// Check logs that did not finish up to their last finished kyoku, as
+    /// `convert --repair` converts them.
+    #[arg(long)]
+    pub repair: bool,
+
+    /// How to report problems on stderr: "text" or "json", a record with
+    /// the file, kyoku, actor, action index, error code and message per
+    /// line.
+    #[arg(long, default_value = "text")]
+    pub error_format: ErrorFormat,
+}
+
+#[derive(Args)]
+pub struct ValidateMjaiArgs {
+    /// The mjai events, a JSON array of them or one per line.
+    pub input: String,
+
+    /// How to report the failure on stderr: "text" or "json", a record with
+    /// the file, kyoku, actor, action index, error code and message.
+    #[arg(long, default_value = "text")]
+    pub error_format: ErrorFormat,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    #[arg(short, long)]
+    pub input: String,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub select: SelectArgs,
+
+    /// Indent JSON output for reading instead of writing it on one line.
+    #[arg(long, global = true)]
+    pub pretty: bool,
+
+    /// How seats tied on the final score are placed in reports, instead of
+    /// as the rules do: "seat-order" (closest to the first dealer first),
+    /// "shared" (both take the higher placement and its uma) or "split"
+    /// (both take the higher placement and split the uma).
+    #[arg(long, global = true)]
+    pub tie_break: Option<TieBreak>,
+
+    /// Print a report over the logs instead of the totals, e.g.
+    /// `convlog stats -i logs leaderboard`.
+    #[command(subcommand)]
+    pub report: Option<StatsReport>,
+}
+
+#[derive(Subcommand)]
+pub enum StatsReport {
+    /// Count how often every player discarded every tile at every turn, as
+    /// JSON.
+    Discards,
+
+    /// Count how often every player called and won with open and closed
+    /// hands, as JSON.
+    Calls,
+
+    /// Count how often every player took the dealer seat and kept it, what
+    /// they won and lost as the dealer and whom they dealt in to, as JSON.
+    Dealer,
+
+    /// List every winning hand and its waits in MPSZ shorthand, and the
+    /// chankan, rinshan, haitei or houtei it won by, as CSV, one row per
+    /// hora.
+    Hands,
+
+    /// List every hand right after declaring riichi and its waits in MPSZ
+    /// shorthand as CSV, one row per riichi.
+    Riichi,
+
+    /// List every riichi with its turn, its wait and how it turned out, and
+    /// what the riichi of every player are worth on average, as JSON.
+    RiichiEv,
+
+    /// Count how long every player took over their discards, riichi,
+    /// calls, kans, kita and wins, as JSON, from mjai events with the
+    /// timestamps `live --timestamps` writes. Tenhou logs record no times
+    /// and only count as games.
+    ThinkTimes,
+
+    /// Rank the players of the logs by their total placement points, e.g.
+    /// after a session of a league.
+    Leaderboard(LeaderboardArgs),
+
+    /// Compare two players over the games they both played in: placements,
+    /// games finished above the other and rons off the other.
+    HeadToHead(HeadToHeadArgs),
+
+    /// Follow the dan and R of a player game by game, with the estimated
+    /// dan points and R change of each, from the ranks and rates tenhou
+    /// logs record.
+    Progression(ProgressionArgs),
+
+    /// Group the games into sessions of the same three players played one
+    /// after the other, with the comebacks of every player and how they
+    /// placed after a last place.
+    Sessions(SessionsArgs),
+
+    /// List the yakuman, haneman and above, big kita hands, double riichi
+    /// and multi ron of the logs, each with a link to the kyoku in tenhou's
+    /// viewer, e.g. for highlight reels.
+    Highlights(HighlightsArgs),
+
+    /// Count the points every player paid every other for rons and as
+    /// shares of tsumos, game by game in JSON, to tell who feeds whom.
+    PointFlow(PointFlowArgs),
+
+    /// Write a single HTML page with tables and charts of the logs, e.g. to
+    /// share the results of a player without a server.
+    Dashboard(DashboardArgs),
+
+    /// Write a Markdown report with the leaderboard, metrics per player,
+    /// notable hands and the biggest wins, e.g. for a league's Discord.
+    Markdown(MarkdownArgs),
+
+    /// Write an Excel workbook with a sheet each for the leaderboard, the
+    /// players, their calls and every win, for organizers who keep their
+    /// league in spreadsheets.
+    Xlsx(XlsxArgs),
+
+    /// Draw SVG or PNG charts of the logs: the scores of every game kyoku by
+    /// kyoku, or the placements of every player over all of them.
+    #[cfg(feature = "charts")]
+    Chart(ChartArgs),
+}
+
+#[derive(Args)]
+pub struct LeaderboardArgs {
+    /// The rules whose uma and oka the placement points are counted with:
+    /// "tenhou-sanma", "majsoul-sanma-gold" or "mleague-style-3p".
+    #[arg(long, default_value = "tenhou-sanma")]
+    pub rules: Ruleset,
+
+    /// What to write the table as: "csv", "json" or "markdown".
+    #[arg(long, default_value = "csv")]
+    pub format: TableFormat,
+}
+
+#[derive(Args)]
+pub struct HeadToHeadArgs {
+    pub player: String,
+    pub opponent: String,
+
+    /// The rules whose tie-break places games the two finished tied in.
+    #[arg(long, default_value = "tenhou-sanma")]
+    pub rules: Ruleset,
+
+    /// What to write the table as: "csv", "json" or "markdown".
+    #[arg(long, default_value = "csv")]
+    pub format: TableFormat,
+}
+
+#[derive(Args)]
+pub struct ProgressionArgs {
+    pub player: String,
+
+    /// What to write the table as: "csv", "json" or "markdown".
+    #[arg(long, default_value = "csv")]
+    pub format: TableFormat,
+}
+
+#[derive(Args)]
+pub struct SessionsArgs {
+    /// The rules whose uma and oka the placement points are counted with.
+    #[arg(long, default_value = "tenhou-sanma")]
+    pub rules: Ruleset,
+
+    /// The most minutes between the starts of two games of a session.
+    /// Tenhou logs only tell the hour a game started.
+    #[arg(long, value_name = "MINUTES", default_value_t = 120)]
+    pub gap: u32,
+
+    /// What to write the table as: "csv", "json" or "markdown".
+    #[arg(long, default_value = "csv")]
+    pub format: TableFormat,
+}
+
+#[derive(Args)]
+pub struct HighlightsArgs {
+    /// What to write the table as: "csv", "json" or "markdown".
+    #[arg(long, default_value = "csv")]
+    pub format: TableFormat,
+}
+
+#[derive(Args)]
+pub struct PointFlowArgs {
+    /// What to write the table as: "csv", "json" or "markdown". Only JSON
+    /// has the matrix of every game besides the totals.
+    #[arg(long, default_value = "csv")]
+    pub format: TableFormat,
+}
+
+#[derive(Args)]
+pub struct DashboardArgs {
+    /// The player the page is about, whose row is highlighted in the tables.
+    #[arg(long, value_name = "NAME")]
+    pub player: Option<String>,
+
+    /// The rules whose uma and oka the placement points are counted with.
+    #[arg(long, default_value = "tenhou-sanma")]
+    pub rules: Ruleset,
+}
+
+#[derive(Args)]
+pub struct MarkdownArgs {
+    /// The rules whose uma and oka the placement points are counted with.
+    #[arg(long, default_value = "tenhou-sanma")]
+    pub rules: Ruleset,
+
+    /// How many notable hands and biggest wins to list.
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(Args)]
+pub struct XlsxArgs {
+    /// The rules whose uma and oka the placement points are counted with.
+    #[arg(long, default_value = "tenhou-sanma")]
+    pub rules: Ruleset,
+
+    /// The `.xlsx` file to write.
+    #[arg(short, long)]
+    pub output: String,
+
+    /// Overwrite the output file if it exists instead of refusing to.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[cfg(feature = "charts")]
+#[derive(Args)]
+pub struct ChartArgs {
+    /// What to draw: "scores", an image per game named after its game ID,
+    /// or "placements", one image of all the players.
+    pub kind: ChartKind,
+
+    /// The directory to write the images of "scores" into, or the file to
+    /// write the image of "placements" to.
+    #[arg(short, long)]
+    pub output: String,
+
+    /// The format of the images: "svg" or "png".
+    #[arg(long, default_value = "svg")]
+    pub format: ImageFormat,
+
+    /// The rules whose uma and oka rank the players of "placements".
+    #[arg(long, default_value = "tenhou-sanma")]
+    pub rules: Ruleset,
+
+    /// Overwrite existing images instead of refusing to.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct FilterArgs {
+    #[arg(short, long)]
+    pub input: String,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[arg(short, long)]
+    pub output: String,
+
+    #[command(flatten)]
+    pub select: SelectArgs,
+
+    /// Overwrite existing output files instead of refusing to.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Print what would be written where, instead of writing anything: each
+    /// output as `write`, `overwrite` or `exists` if `--force` is needed,
+    /// and each log left out by the selection flags as `skip`.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Keep only these groups of fields of the logs written, the rest
+    /// left out: "actions", which is required, "results" (`sc`), "names"
+    /// (`name` and `sx`), "ratings" (`dan`, `rate` and `ratingc`), "lobby",
+    /// "connections" and "game-id" (`ref`). Names left out become `Aさん`
+    /// to `Cさん`.
+    #[arg(long, value_name = "LIST", default_value_t = Fields::ALL, hide_default_value = true)]
+    pub fields: Fields,
+}
+
+#[derive(Args)]
+pub struct IndexArgs {
+    #[arg(short, long)]
+    pub input: String,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    /// Where to write the index, e.g. `index.jsonl`. The entries of an
+    /// index already there are kept for the logs that have not changed.
+    #[arg(short, long)]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct SplitArgs {
+    #[arg(short, long)]
+    pub input: String,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[arg(short, long)]
+    pub output: String,
+
+    /// Indent JSON output for reading instead of writing it on one line.
+    #[arg(long)]
+    pub pretty: bool,
+
+    /// Overwrite existing output files instead of refusing to.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Print what would be written where, instead of writing anything: each
+    /// output as `write`, `overwrite` or `exists` if `--force` is needed.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Keep only these groups of fields of the logs written, the rest
+    /// left out: "actions", which is required, "results" (`sc`), "names"
+    /// (`name` and `sx`), "ratings" (`dan`, `rate` and `ratingc`), "lobby",
+    /// "connections" and "game-id" (`ref`). Names left out become `Aさん`
+    /// to `Cさん`.
+    #[arg(long, value_name = "LIST", default_value_t = Fields::ALL, hide_default_value = true)]
+    pub fields: Fields,
+}
+
+#[derive(Args)]
+pub struct DatasetArgs {
+    #[arg(short, long)]
+    pub input: String,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub select: SelectArgs,
+
+    /// The file to write, stdout if not given.
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// What to write the rows as: "jsonl" or, with the parquet feature,
+    /// "parquet".
+    #[arg(long, default_value = "jsonl")]
+    pub format: DatasetFormat,
+
+    /// What a row is: "discards", a discard and the hand and table before
+    /// it, or "opponents", what the discarder sees of its opponents then:
+    /// their riichi, melds, kitas, discards and safe tiles and the tiles it
+    /// has not seen. Opponent rows are only written as JSON lines.
+    #[arg(long, default_value = "discards")]
+    pub rows: DatasetRows,
+
+    /// Overwrite the output file if it exists instead of refusing to.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct ReplArgs {
     #[arg(short, long)]
     pub input: String,
 
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
+    #[command(flatten)]
+    pub select: SelectArgs,
+}
+
+#[derive(Args)]
+pub struct NormalizeArgs {
+    #[arg(short, long)]
+    pub input: String,
+
+    #[command(flatten)]
+    pub walk: WalkArgs,
+
     #[arg(short, long)]
     pub output: String,
-}
\ No newline at end of file
+
+    /// Indent JSON output for reading instead of writing it on one line.
+    #[arg(long)]
+    pub pretty: bool,
+
+    /// Overwrite existing output files instead of refusing to.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Print what would be written where, instead of writing anything: each
+    /// output as `write`, `overwrite` or `exists` if `--force` is needed.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Keep only these groups of fields of the logs written, the rest
+    /// left out: "actions", which is required, "results" (`sc`), "names"
+    /// (`name` and `sx`), "ratings" (`dan`, `rate` and `ratingc`), "lobby",
+    /// "connections" and "game-id" (`ref`). Names left out become `Aさん`
+    /// to `Cさん`.
+    #[arg(long, value_name = "LIST", default_value_t = Fields::ALL, hide_default_value = true)]
+    pub fields: Fields,
+
+    /// Leave the fourth seat out of names, scoreboards, score deltas and
+    /// action tables, the layout of early sanma logs. Logs in which the
+    /// fourth seat takes part fail instead.
+    #[arg(long)]
+    pub three_seats: bool,
+
+    /// What to do with keys of the logs the crate does not know, e.g. ones
+    /// tenhou added since: "drop" them, so that the output stays canonical,
+    /// "keep" them as they are or "deny" them, failing the log.
+    #[arg(long, value_name = "POLICY", default_value = "drop")]
+    pub unknown_keys: UnknownKeys,
+}
+
+#[derive(Args)]
+pub struct GenArgs {
+    /// The same seed plays the same games.
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    #[arg(short = 'n', long, default_value_t = 1)]
+    pub games: u32,
+
+    #[arg(short, long)]
+    pub output: String,
+
+    /// "tenhou6" logs or "mjai" events.
+    #[arg(long, default_value = "tenhou6")]
+    pub format: GenFormat,
+
+    /// Indent JSON output for reading instead of writing it on one line.
+    #[arg(long)]
+    pub pretty: bool,
+
+    /// Overwrite existing output files instead of refusing to.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct LiveArgs {
+    /// Add to every event the `timestamp` it was read at, in milliseconds
+    /// from the start of the game, so that `stats think-times` can tell
+    /// how long the players took over their decisions.
+    #[arg(long)]
+    pub timestamps: bool,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    pub left: String,
+    pub right: String,
+
+    /// Treat aka fives as plain ones.
+    #[arg(long)]
+    pub ignore_aka: bool,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Args)]
+pub struct ReplayArgs {
+    pub log: String,
+
+    /// The kyoku to play, e.g. `S2`, or `S2-1` for one of its honba. The
+    /// first kyoku of the log if not given.
+    #[arg(long)]
+    pub kyoku: Option<String>,
+
+    /// Events played per second.
+    #[arg(long, default_value_t = 2.0)]
+    pub speed: f64,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// The tenhou log to convert.
+    pub input: String,
+
+    /// The mjai events it should convert to, a JSON array of them or one
+    /// per line.
+    #[arg(long)]
+    pub expected: String,
+
+    /// How aka dora are emitted: "keep", "deaka" (plain fives) or "akaize"
+    /// (one aka five of each suit in logs without any).
+    #[arg(long, default_value = "keep")]
+    pub aka: AkaNormalization,
+
+    /// The order of haipai in `start_kyoku`: "preserve" the order of the
+    /// log, deal order for some sources, or "sort" them.
+    #[arg(long, default_value = "preserve")]
+    pub haipai: HaipaiOrder,
+
+    /// Treat aka fives as plain ones.
+    #[arg(long)]
+    pub ignore_aka: bool,
+
+    /// Events shown before and after every difference.
+    #[arg(long, default_value_t = 3)]
+    pub context: usize,
+}
+
+#[derive(Args)]
+pub struct SchemaArgs {
+    /// The output to describe: "mjai" events, "tenhou6" logs or the JSON
+    /// reports of "stats".
+    #[arg(long)]
+    pub format: SchemaFormat,
+
+    /// Indent JSON output for reading instead of writing it on one line.
+    #[arg(long)]
+    pub pretty: bool,
+}
+
+/// The style `--pretty` asks for.
+const fn json_style(pretty: bool) -> JsonStyle {
+    if pretty {
+        JsonStyle::Pretty
+    } else {
+        JsonStyle::Compact
+    }
+}
+
+impl ConvertArgs {
+    #[inline]
+    #[must_use]
+    pub const fn json_style(&self) -> JsonStyle {
+        json_style(self.pretty)
+    }
+
+    /// The emitter of `--plugin`, with its arguments.
+    #[must_use]
+    pub fn plugin(&self) -> Option<Subprocess> {
+        let program = self.plugin.as_ref()?;
+        let plugin = Subprocess::new(program, &self.plugin_extension);
+        Some(self.plugin_arg.iter().fold(plugin, Subprocess::arg))
+    }
+
+    /// The `--tie-break` of `end_game`, that of [`Self::rules`] by default.
+    #[inline]
+    #[must_use]
+    pub fn tie_break(&self) -> TieBreak {
+        self.tie_break.unwrap_or(self.rules.tie_break)
+    }
+}
+
+impl ValidateArgs {
+    #[inline]
+    #[must_use]
+    pub fn scoring_rules(&self) -> ScoringRules {
+        let rules = self.rules.scoring;
+        ScoringRules {
+            kiriage_mangan: self.kiriage_mangan.unwrap_or(rules.kiriage_mangan),
+            tsumo: self.tsumo.unwrap_or(rules.tsumo),
+            rounding: self.rounding.unwrap_or(rules.rounding),
+        }
+    }
+
+    /// [`Self::rules`] with the overrides of its tie-break.
+    #[inline]
+    #[must_use]
+    pub fn ruleset(&self) -> Ruleset {
+        Ruleset {
+            tie_break: self.tie_break.unwrap_or(self.rules.tie_break),
+            ..self.rules
+        }
+    }
+}
+
+impl StatsArgs {
+    #[inline]
+    #[must_use]
+    pub const fn json_style(&self) -> JsonStyle {
+        json_style(self.pretty)
+    }
+
+    /// `rules` with the `--tie-break` of the report, if any.
+    #[inline]
+    #[must_use]
+    pub fn ruleset(&self, rules: Ruleset) -> Ruleset {
+        Ruleset {
+            tie_break: self.tie_break.unwrap_or(rules.tie_break),
+            ..rules
+        }
+    }
+}
+
+impl SchemaArgs {
+    #[inline]
+    #[must_use]
+    pub const fn json_style(&self) -> JsonStyle {
+        json_style(self.pretty)
+    }
+}
+
+impl SplitArgs {
+    #[inline]
+    #[must_use]
+    pub const fn json_style(&self) -> JsonStyle {
+        json_style(self.pretty)
+    }
+}
+
+impl NormalizeArgs {
+    #[inline]
+    #[must_use]
+    pub const fn json_style(&self) -> JsonStyle {
+        json_style(self.pretty)
+    }
+}
+
+impl GenArgs {
+    #[inline]
+    #[must_use]
+    pub const fn json_style(&self) -> JsonStyle {
+        json_style(self.pretty)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn validate_args(args: &[&str]) -> ValidateArgs {
+        let cli = ConvCli::try_parse_from(["convlog", "validate", "-i", "in"].iter().chain(args))
+            .unwrap();
+        match cli.command {
+            Command::Validate(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    fn convert_args(args: &[&str]) -> ConvertArgs {
+        let cli = ConvCli::try_parse_from(
+            ["convlog", "convert", "-i", "in", "-o", "out"]
+                .iter()
+                .chain(args),
+        )
+        .unwrap();
+        match cli.command {
+            Command::Convert(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn subcommands() {
+        let parse =
+            |args: &[&str]| ConvCli::try_parse_from(std::iter::once(&"convlog").chain(args));
+        let args = convert_args(&[]);
+        assert_eq!((args.input.as_str(), args.output.as_str()), ("in", "out"));
+        assert_eq!(validate_args(&[]).input, "in");
+        match parse(&["stats", "-i", "in", "leaderboard"])
+            .unwrap()
+            .command
+        {
+            Command::Stats(args) => {
+                assert!(matches!(args.report, Some(StatsReport::Leaderboard(_))));
+            }
+            _ => unreachable!(),
+        }
+        match parse(&["stats", "-i", "in", "riichi-ev", "--pretty"])
+            .unwrap()
+            .command
+        {
+            Command::Stats(args) => {
+                assert!(matches!(args.report, Some(StatsReport::RiichiEv)) && args.pretty);
+            }
+            _ => unreachable!(),
+        }
+        match parse(&["filter", "-i", "in", "-o", "out", "--dry-run"])
+            .unwrap()
+            .command
+        {
+            Command::Filter(args) => assert!(args.dry_run),
+            _ => unreachable!(),
+        }
+        match parse(&["split", "-i", "in", "-o", "out"]).unwrap().command {
+            Command::Split(args) => assert!(!args.pretty),
+            _ => unreachable!(),
+        }
+
+        // The flat flags of before are gone, and the options of one
+        // subcommand are not taken by another.
+        assert!(parse(&["-i", "in", "-o", "out"]).is_err());
+        assert!(parse(&["convert", "-i", "in"]).is_err());
+        assert!(parse(&["stats", "-i", "in", "--dealer"]).is_err());
+        assert!(parse(&["validate", "-i", "in", "-o", "out"]).is_err());
+        assert!(parse(&["split", "-i", "in", "-o", "out", "--min-quality", "0.9"]).is_err());
+    }
+
+    #[test]
+    fn tie_break() {
+        assert_eq!(convert_args(&[]).tie_break(), TieBreak::SeatOrder);
+        let args = convert_args(&["--rules", "mleague-style-3p"]);
+        assert_eq!(args.tie_break(), Ruleset::MLEAGUE_STYLE_3P.tie_break);
+        let args = convert_args(&["--rules", "mleague-style-3p", "--tie-break", "shared"]);
+        assert_eq!(args.tie_break(), TieBreak::Shared);
+    }
+
+    #[test]
+    fn scoring_rules() {
+        assert!(!validate_args(&[]).scoring_rules().kiriage_mangan);
+        assert!(
+            validate_args(&["--kiriage-mangan"])
+                .scoring_rules()
+                .kiriage_mangan
+        );
+        let args = validate_args(&["--rules", "mleague-style-3p"]);
+        assert!(args.scoring_rules().kiriage_mangan);
+        let args = validate_args(&["--rules", "mleague-style-3p", "--kiriage-mangan=false"]);
+        assert!(!args.scoring_rules().kiriage_mangan);
+    }
+}
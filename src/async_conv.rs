@@ -0,0 +1,181 @@
+//! Conversion of many logs at once on tokio, for services whose time goes to
+//! waiting on files or the network rather than to converting.
+//!
+//! Fetching is async and runs up to a given number of logs concurrently;
+//! converting a log takes milliseconds and is done on the task that fetched
+//! it.
+
+use crate::conv::{tenhou_to_mjai_with_options, ConvertError, ConvertOptions};
+use crate::mjai::Event;
+use crate::tenhou::{Log, TenhouParseError};
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+#[derive(Debug)]
+pub enum AsyncConvertError {
+    Io(io::Error),
+    Parse(TenhouParseError),
+    Convert(Box<ConvertError>),
+}
+
+impl fmt::Display for AsyncConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => fmt::Display::fmt(err, f),
+            Self::Parse(err) => fmt::Display::fmt(err, f),
+            Self::Convert(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl Error for AsyncConvertError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => err.source(),
+            Self::Parse(err) => err.source(),
+            Self::Convert(err) => err.source(),
+        }
+    }
+}
+
+impl From<io::Error> for AsyncConvertError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<TenhouParseError> for AsyncConvertError {
+    fn from(err: TenhouParseError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+impl From<Box<ConvertError>> for AsyncConvertError {
+    fn from(err: Box<ConvertError>) -> Self {
+        Self::Convert(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, AsyncConvertError>;
+
+/// Fetch the log of every id with `fetch` and convert it with `options`,
+/// with at most `concurrency` logs in flight. Results are in the order of
+/// `ids`, whatever order the fetches finish in.
+///
+/// `fetch` is where logs come from, e.g. a file or an HTTP client; it only
+/// has to return the JSON of a log.
+///
+/// # Panics
+///
+/// Must be called from within a tokio runtime, and panics if a fetch does.
+pub async fn convert_all_async<T, F, Fut>(
+    ids: impl IntoIterator<Item = T>,
+    fetch: F,
+    options: &ConvertOptions,
+    concurrency: usize,
+) -> Vec<(T, Result<Vec<Event>>)>
+where
+    T: Clone + Send + 'static,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = io::Result<String>> + Send + 'static,
+{
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+    let options = Arc::new(options.clone());
+    let mut tasks = JoinSet::new();
+    for (index, id) in ids.into_iter().enumerate() {
+        let permit = Arc::clone(&permits)
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+        let fetched = fetch(id.clone());
+        let options = Arc::clone(&options);
+        tasks.spawn(async move {
+            let result = match fetched.await {
+                Ok(json) => convert_json(&json, &options),
+                Err(err) => Err(err.into()),
+            };
+            drop(permit);
+            (index, id, result)
+        });
+    }
+
+    let mut results = Vec::with_capacity(tasks.len());
+    while let Some(joined) = tasks.join_next().await {
+        results.push(joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic())));
+    }
+    results.sort_unstable_by_key(|&(index, ..)| index);
+    results
+        .into_iter()
+        .map(|(_, id, result)| (id, result))
+        .collect()
+}
+
+/// Like [`convert_all_async`] on the `.json` files of `dir`, in order of
+/// their paths.
+///
+/// # Errors
+///
+/// Fails if `dir` cannot be listed. Files that cannot be read or converted
+/// fail on their own in the returned list.
+pub async fn convert_dir_async(
+    dir: impl AsRef<Path>,
+    options: &ConvertOptions,
+    concurrency: usize,
+) -> io::Result<Vec<(PathBuf, Result<Vec<Event>>)>> {
+    let mut paths = vec![];
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "json") && !entry.file_type().await?.is_dir() {
+            paths.push(path);
+        }
+    }
+    paths.sort_unstable();
+
+    Ok(convert_all_async(paths, tokio::fs::read_to_string, options, concurrency).await)
+}
+
+fn convert_json(json: &str, options: &ConvertOptions) -> Result<Vec<Event>> {
+    let log = Log::from_json_str(json)?;
+    tenhou_to_mjai_with_options(&log, options).map_err(|e| Box::new(e).into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::testdata::Fixture;
+
+    #[tokio::test]
+    async fn convert_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixtures = [Fixture::Hanchan, Fixture::MultiRon, Fixture::KanHeavy];
+        for (i, fixture) in fixtures.iter().enumerate() {
+            std::fs::write(dir.path().join(format!("{i}.json")), fixture.json()).unwrap();
+        }
+        std::fs::write(dir.path().join("3.json"), "{").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a log").unwrap();
+
+        let results = convert_dir_async(dir.path(), &ConvertOptions::default(), 2)
+            .await
+            .unwrap();
+        let names: Vec<_> = results
+            .iter()
+            .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["0.json", "1.json", "2.json", "3.json"]);
+        for ((_, result), fixture) in results.iter().zip(fixtures) {
+            let log = Log::from_json_str(fixture.json()).unwrap();
+            assert_eq!(result.as_ref().unwrap(), &tenhou_to_mjai(&log).unwrap());
+        }
+        let err = results[3].1.as_ref().unwrap_err();
+        assert!(matches!(err, AsyncConvertError::Parse(_)), "{err}");
+    }
+}
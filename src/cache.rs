@@ -0,0 +1,132 @@
+//! The state of incremental conversions: the fingerprints of the logs
+//! converted into an output directory and the options they were converted
+//! with, so that a batch job run again over a growing archive only converts
+//! what is new or changed.
+
+use crate::output::write_atomic;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// The name of the state file at the root of an output directory.
+pub const CACHE: &str = ".convlog-cache.json";
+
+/// A hash of `bytes` that stays the same across builds and platforms, unlike
+/// the hashers of the standard library: 64-bit FNV-1a, in hex.
+#[must_use]
+pub fn fingerprint(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct State {
+    /// The [`fingerprint`] of the options the entries were converted with.
+    options: String,
+    /// The output written for every log, relative to the output directory,
+    /// by its [`Log::fingerprint`]. Logs are told apart by the game they
+    /// record rather than where they are or how they are formatted, so that
+    /// archives can be moved, repacked or downloaded again.
+    ///
+    /// [`Log::fingerprint`]: crate::tenhou::Log::fingerprint
+    entries: BTreeMap<String, PathBuf>,
+}
+
+/// The state file of an output directory, read when opened and updated by
+/// each run.
+#[derive(Debug, Clone)]
+pub struct ConversionCache {
+    root: PathBuf,
+    state: State,
+}
+
+impl ConversionCache {
+    /// The state at `root` for conversions with `options`, a description of
+    /// everything that changes the output. It starts empty if there is none
+    /// yet or if it was made with other options, so that everything is
+    /// converted again.
+    pub fn open(root: impl Into<PathBuf>, options: &str) -> io::Result<Self> {
+        let root = root.into();
+        let options = fingerprint(options.as_bytes());
+        let state = match fs::read(root.join(CACHE)) {
+            Ok(bytes) => serde_json::from_slice::<State>(&bytes)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => State::default(),
+            Err(err) => return Err(err),
+        };
+        let state = if state.options == options {
+            state
+        } else {
+            State {
+                options,
+                entries: BTreeMap::new(),
+            }
+        };
+        Ok(Self { root, state })
+    }
+
+    /// The output written for the log with `fingerprint`, relative to the
+    /// root, if it was converted before.
+    #[must_use]
+    pub fn get(&self, fingerprint: &str) -> Option<&Path> {
+        self.state.entries.get(fingerprint).map(PathBuf::as_path)
+    }
+
+    /// Whether the log with `fingerprint` was converted before and its
+    /// output is still there.
+    #[must_use]
+    pub fn is_fresh(&self, fingerprint: &str) -> bool {
+        self.get(fingerprint)
+            .is_some_and(|output| self.root.join(output).exists())
+    }
+
+    /// Record that the log with `fingerprint` was converted to `output`,
+    /// relative to the root.
+    pub fn record(&mut self, fingerprint: &str, output: &Path) {
+        self.state
+            .entries
+            .insert(fingerprint.to_owned(), output.to_owned());
+    }
+
+    /// Write the state back to the root, atomically.
+    pub fn save(&self) -> io::Result<()> {
+        write_atomic(&self.root.join(CACHE), true, |w| {
+            serde_json::to_writer(&mut *w, &self.state)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cache() {
+        assert_eq!(fingerprint(b""), "cbf29ce484222325");
+        assert_eq!(fingerprint(b"a"), "af63dc4c8601ec8c");
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = ConversionCache::open(dir.path(), "mjai").unwrap();
+        let key = fingerprint(b"{}");
+        assert!(!cache.is_fresh(&key));
+        cache.record(&key, Path::new("a.json"));
+        // Not fresh until the output is there.
+        assert!(!cache.is_fresh(&key));
+        fs::write(dir.path().join("a.json"), "[]").unwrap();
+        assert!(cache.is_fresh(&key));
+        assert!(!cache.is_fresh(&fingerprint(b"{ }")));
+        cache.save().unwrap();
+
+        let cache = ConversionCache::open(dir.path(), "mjai").unwrap();
+        assert!(cache.is_fresh(&key));
+        assert_eq!(cache.get(&key), Some(Path::new("a.json")));
+        // Other options convert everything again.
+        let cache = ConversionCache::open(dir.path(), "csv").unwrap();
+        assert_eq!(cache.get(&key), None);
+    }
+}
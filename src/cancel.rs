@@ -0,0 +1,34 @@
+//! Cooperative cancellation of long-running jobs, for embedders that run a
+//! batch on a worker and need to stop it from elsewhere, e.g. a cancel
+//! button or a request that went away.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag shared by every clone of it. Jobs given a token look at it between
+/// units of work, a kyoku or a check, and stop with a `Cancelled` error once
+/// it is set; work already done is not undone.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every job holding a clone of this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Whether `token`, if any, has been cancelled.
+pub(crate) fn is_cancelled(token: Option<&CancelToken>) -> bool {
+    token.is_some_and(CancelToken::is_cancelled)
+}
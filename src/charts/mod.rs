@@ -0,0 +1,446 @@
+//! Charts of the scores of a game and of the placements of players over a
+//! corpus, as SVG or PNG images that need no other tool to publish. Both
+//! are drawn by hand, the PNG on a bitmap with a font of ASCII only.
+
+mod bitmap;
+
+use crate::dashboard::escape;
+use crate::stats::Leaderboard;
+use crate::tenhou::{Log, RuleError};
+use crate::Points;
+use bitmap::{rgb, Bitmap};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// What `stats chart` draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartKind {
+    /// The scores of each game kyoku by kyoku, see [`score_chart`].
+    Scores,
+    /// The placements of every player, see [`placement_chart`].
+    Placements,
+}
+
+impl FromStr for ChartKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scores" => Ok(Self::Scores),
+            "placements" => Ok(Self::Placements),
+            _ => Err(format!(
+                r#"unknown chart {s:?}, expected "scores" or "placements""#
+            )),
+        }
+    }
+}
+
+/// The image format charts are written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+impl ImageFormat {
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Svg => "svg",
+            Self::Png => "png",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "svg" => Ok(Self::Svg),
+            "png" => Ok(Self::Png),
+            _ => Err(format!(
+                r#"unknown image format {s:?}, expected "svg" or "png""#
+            )),
+        }
+    }
+}
+
+const WIDTH: f64 = 640.0;
+/// The height of the plot of a score chart.
+const PLOT_HEIGHT: f64 = 280.0;
+/// Room left of the plot for the labels of its axis, and of a placement
+/// chart for the names.
+const LEFT: f64 = 120.0;
+/// Room above the plot for the legend.
+const TOP: f64 = 32.0;
+/// Room below the plot for the kyokus.
+const BOTTOM: f64 = 40.0;
+/// The points between the grid lines of a score chart.
+const GRID: Points = 10000;
+/// The height of the bar of a player in a placement chart, with the space
+/// to the next.
+const ROW: f64 = 24.0;
+/// The color of the line of every seat, and of every placement.
+const COLORS: [&str; 3] = ["#4a7bb7", "#d9843b", "#5a9e5a"];
+
+/// Where a text is placed relative to its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+#[derive(Debug, Clone)]
+enum Shape {
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        color: &'static str,
+        /// Shown on hover in SVG.
+        title: Option<String>,
+    },
+    /// A line of the grid, a pixel wide.
+    Line {
+        from: (f64, f64),
+        to: (f64, f64),
+        color: &'static str,
+    },
+    /// A line through points, two pixels wide.
+    Polyline {
+        points: Vec<(f64, f64)>,
+        color: &'static str,
+    },
+    /// Black text, `y` its baseline.
+    Text {
+        x: f64,
+        y: f64,
+        anchor: Anchor,
+        text: String,
+    },
+}
+
+/// A chart drawn by [`score_chart`] or [`placement_chart`], on a white
+/// background, ready to be written as an image.
+#[derive(Debug, Clone)]
+pub struct Chart {
+    width: f64,
+    height: f64,
+    shapes: Vec<Shape>,
+}
+
+impl Chart {
+    const fn new(width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            shapes: vec![],
+        }
+    }
+
+    fn text(&mut self, x: f64, y: f64, anchor: Anchor, text: &str) {
+        self.shapes.push(Shape::Text {
+            x,
+            y,
+            anchor,
+            text: text.to_owned(),
+        });
+    }
+
+    /// Add the entry at `index` of the legend along the top.
+    fn legend(&mut self, index: usize, label: &str, color: &'static str) {
+        let left = (index as f64).mul_add(160.0, LEFT);
+        self.shapes.push(Shape::Rect {
+            x: left,
+            y: 8.0,
+            width: 12.0,
+            height: 12.0,
+            color,
+            title: None,
+        });
+        self.text(left + 18.0, 18.0, Anchor::Start, label);
+    }
+
+    /// The chart in `format`.
+    #[must_use]
+    pub fn encode(&self, format: ImageFormat) -> Vec<u8> {
+        match format {
+            ImageFormat::Svg => self.svg().into_bytes(),
+            ImageFormat::Png => self.png(),
+        }
+    }
+
+    #[must_use]
+    pub fn svg(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+            self.width, self.height,
+        );
+        out.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+        for shape in &self.shapes {
+            let _ = match shape {
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                    title: None,
+                } => writeln!(
+                    out,
+                    "<rect x=\"{x:.1}\" y=\"{y:.1}\" width=\"{width:.1}\" height=\"{height:.1}\" fill=\"{color}\"/>",
+                ),
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                    title: Some(title),
+                } => writeln!(
+                    out,
+                    "<rect x=\"{x:.1}\" y=\"{y:.1}\" width=\"{width:.1}\" height=\"{height:.1}\" fill=\"{color}\"><title>{}</title></rect>",
+                    escape(title),
+                ),
+                Shape::Line { from, to, color } => writeln!(
+                    out,
+                    "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{color}\"/>",
+                    from.0, from.1, to.0, to.1,
+                ),
+                Shape::Polyline { points, color } => {
+                    let points: Vec<_> = points
+                        .iter()
+                        .map(|(x, y)| format!("{x:.1},{y:.1}"))
+                        .collect();
+                    writeln!(
+                        out,
+                        "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"2\"/>",
+                        points.join(" "),
+                    )
+                }
+                Shape::Text { x, y, anchor, text } => {
+                    let anchor = match anchor {
+                        Anchor::Start => "",
+                        Anchor::Middle => " text-anchor=\"middle\"",
+                        Anchor::End => " text-anchor=\"end\"",
+                    };
+                    writeln!(
+                        out,
+                        "<text x=\"{x:.1}\" y=\"{y:.1}\"{anchor}>{}</text>",
+                        escape(text),
+                    )
+                }
+            };
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// The chart as a PNG image of its size in pixels.
+    #[must_use]
+    pub fn png(&self) -> Vec<u8> {
+        let mut bitmap = Bitmap::new(self.width.ceil() as usize, self.height.ceil() as usize);
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                    ..
+                } => bitmap.fill_rect(*x, *y, *width, *height, rgb(color)),
+                Shape::Line { from, to, color } => bitmap.line(*from, *to, 1.0, rgb(color)),
+                Shape::Polyline { points, color } => {
+                    for pair in points.windows(2) {
+                        bitmap.line(pair[0], pair[1], 2.0, rgb(color));
+                    }
+                }
+                Shape::Text { x, y, anchor, text } => {
+                    bitmap.text(*x, *y, *anchor, text, [0; 3]);
+                }
+            }
+        }
+        bitmap.png()
+    }
+}
+
+/// Draw the scores of every seat of `log` at the start of every kyoku and
+/// at the end of the game, a line per seat, for a game report.
+///
+/// # Errors
+///
+/// Fails for logs whose final scores overflow, see [`Log::final_scores`].
+pub fn score_chart(log: &Log) -> Result<Chart, RuleError> {
+    let mut labels: Vec<_> = log.kyokus.iter().map(|k| k.meta.short_name()).collect();
+    let mut scores: Vec<_> = log.kyokus.iter().map(|k| k.scoreboard).collect();
+    if let Some(last) = log.final_scores()? {
+        labels.push("End".to_owned());
+        scores.push(last);
+    }
+
+    let values = || scores.iter().flat_map(|s| &s[..3]).copied();
+    let low = values().min().unwrap_or(0).div_euclid(GRID) * GRID;
+    let high = (values().max().unwrap_or(0).div_euclid(GRID) + 1) * GRID;
+    let plot_width = WIDTH - LEFT - 24.0;
+    let x = |i: usize| LEFT + plot_width * i as f64 / (scores.len().max(2) - 1) as f64;
+    let y = |points: Points| TOP + PLOT_HEIGHT * (high - points) as f64 / (high - low) as f64;
+
+    let mut chart = Chart::new(WIDTH, TOP + PLOT_HEIGHT + BOTTOM);
+    for points in (low..=high).step_by(GRID as usize) {
+        chart.shapes.push(Shape::Line {
+            from: (LEFT, y(points)),
+            to: (LEFT + plot_width, y(points)),
+            color: "#dddddd",
+        });
+        chart.text(
+            LEFT - 6.0,
+            y(points) + 4.0,
+            Anchor::End,
+            &points.to_string(),
+        );
+    }
+    for (i, label) in labels.iter().enumerate() {
+        chart.text(x(i), TOP + PLOT_HEIGHT + 20.0, Anchor::Middle, label);
+    }
+    for (seat, color) in COLORS.into_iter().enumerate() {
+        let points = scores
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (x(i), y(s[seat])))
+            .collect();
+        chart.shapes.push(Shape::Polyline { points, color });
+        chart.legend(seat, &log.names[seat], color);
+    }
+    Ok(chart)
+}
+
+/// Draw how often every player of `leaderboard` placed first, second and
+/// third, as a bar of the shares of their games each, in the order of
+/// [`Leaderboard::ranked`].
+#[must_use]
+pub fn placement_chart(leaderboard: &Leaderboard) -> Chart {
+    let ranked = leaderboard.ranked();
+    let bar_width = WIDTH - LEFT - 24.0;
+    let mut chart = Chart::new(WIDTH, ROW.mul_add(ranked.len() as f64, TOP) + 8.0);
+    for (placement, (color, label)) in COLORS.into_iter().zip(["1st", "2nd", "3rd"]).enumerate() {
+        chart.legend(placement, label, color);
+    }
+    for (row, (name, standing)) in ranked.into_iter().enumerate() {
+        let top = ROW.mul_add(row as f64, TOP);
+        chart.text(LEFT - 6.0, top + 14.0, Anchor::End, name);
+        let games = standing.placements.iter().sum::<u32>().max(1);
+        let mut left = LEFT;
+        for (count, color) in standing.placements.iter().zip(COLORS) {
+            let width = bar_width * f64::from(*count) / f64::from(games);
+            chart.shapes.push(Shape::Rect {
+                x: left,
+                y: top,
+                width,
+                height: 18.0,
+                color,
+                title: Some(count.to_string()),
+            });
+            left += width;
+        }
+    }
+    chart
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ruleset::Ruleset;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn score_chart() {
+        let log = Fixture::Hanchan.log();
+        let svg = super::score_chart(&log).unwrap().svg();
+        assert!(svg.starts_with("<svg xmlns="), "{svg}");
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<polyline").count(), 3);
+        // A point per kyoku and one for the end of the game.
+        let line = svg.lines().find(|l| l.starts_with("<polyline")).unwrap();
+        assert_eq!(line.matches(',').count(), log.kyokus.len() + 1);
+        assert!(svg.contains(">E1-0</text>") && svg.contains(">End</text>"));
+        assert!(svg.contains(">つくねん3</text>"));
+        assert!(svg.contains(">30000</text>"));
+    }
+
+    #[test]
+    fn placement_chart() {
+        let mut leaderboard = Leaderboard::new(Ruleset::TENHOU_SANMA);
+        for fixture in Fixture::ALL {
+            leaderboard.add(&fixture.log()).unwrap();
+        }
+        let svg = super::placement_chart(&leaderboard).svg();
+        // The background, a legend entry per placement and three bars for
+        // each of the six players.
+        assert_eq!(svg.matches("<rect").count(), 1 + 3 + 6 * 3);
+        assert!(svg.contains(">Aoi</text>") && svg.contains(">mtk</text>"));
+        assert!(svg.contains(">1st</text>"));
+    }
+
+    #[test]
+    fn png() {
+        use flate2::read::ZlibDecoder;
+        use std::io::Read;
+
+        let chart = super::score_chart(&Fixture::Hanchan.log()).unwrap();
+        let png = chart.encode(ImageFormat::Png);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+        // The chunks, checked against their CRCs.
+        let mut chunks = vec![];
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+            let mut crc = flate2::Crc::new();
+            crc.update(kind);
+            crc.update(data);
+            assert_eq!(crc.sum().to_be_bytes(), rest[8 + len..12 + len]);
+            chunks.push((kind, data));
+            rest = &rest[12 + len..];
+        }
+        let kinds: Vec<_> = chunks.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+        let header = chunks[0].1;
+        let (width, height) = (WIDTH as usize, (TOP + PLOT_HEIGHT + BOTTOM) as usize);
+        assert_eq!(header[..4], (width as u32).to_be_bytes());
+        assert_eq!(header[4..8], (height as u32).to_be_bytes());
+
+        let mut pixels = vec![];
+        ZlibDecoder::new(chunks[1].1)
+            .read_to_end(&mut pixels)
+            .unwrap();
+        assert_eq!(pixels.len(), height * (1 + 3 * width));
+        // A row each, unfiltered, with the lines of every seat and text.
+        let rows: Vec<_> = pixels.chunks(1 + 3 * width).collect();
+        assert!(rows.iter().all(|row| row[0] == 0));
+        let drawn = |color: [u8; 3]| {
+            rows.iter()
+                .any(|row| row[1..].chunks(3).any(|pixel| pixel == color))
+        };
+        for color in COLORS {
+            assert!(drawn(rgb(color)), "{color}");
+        }
+        assert!(drawn([0; 3]));
+    }
+
+    #[test]
+    fn chart_kind() {
+        assert_eq!("scores".parse(), Ok(ChartKind::Scores));
+        assert_eq!("placements".parse(), Ok(ChartKind::Placements));
+        "pie".parse::<ChartKind>().unwrap_err();
+        assert_eq!("png".parse(), Ok(ImageFormat::Png));
+        "gif".parse::<ImageFormat>().unwrap_err();
+    }
+}
@@ -0,0 +1,222 @@
+//! Manifests of the SHA-256 of every output of a batch, with the input it
+//! was converted from and the options it was converted with, so that those
+//! publishing a dataset can prove it intact and how it was made.
+
+use crate::output::write_atomic;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// An output of a batch, one line of a [`ChecksumManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecksumEntry {
+    /// Relative to the output directory, with `/` separators on every
+    /// platform.
+    pub path: String,
+    /// The input the output was converted from, e.g. `logs/a.jsonl:3`.
+    pub source: String,
+    pub game_id: Option<String>,
+    /// In lowercase hex.
+    pub sha256: String,
+    pub bytes: u64,
+    /// The output format, e.g. `mjai`.
+    pub format: String,
+    pub converter_version: String,
+    /// The options of the conversion by their command-line names, as the
+    /// envelope of mjai output records them.
+    pub options: BTreeMap<String, Value>,
+}
+
+/// An output that does not match its [`ChecksumEntry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    Missing(String),
+    Changed(String),
+}
+
+/// The checksums of the outputs of a batch as JSON lines, read when opened
+/// and updated by each run, so that a run adding to an output directory
+/// keeps the entries of those before.
+#[derive(Debug, Clone)]
+pub struct ChecksumManifest {
+    path: PathBuf,
+    entries: Vec<ChecksumEntry>,
+}
+
+impl ChecksumManifest {
+    /// The manifest at `path`, empty if there is none yet.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let mut entries = vec![];
+        match fs::File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    entries.push(serde_json::from_str(&line)?);
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err),
+        }
+        Ok(Self { path, entries })
+    }
+
+    #[must_use]
+    pub fn entries(&self) -> &[ChecksumEntry] {
+        &self.entries
+    }
+
+    /// Record `entry`, replacing the entry of an output written again.
+    pub fn record(&mut self, entry: ChecksumEntry) {
+        match self.entries.iter_mut().find(|e| e.path == entry.path) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Write the manifest back, atomically.
+    pub fn save(&self) -> io::Result<()> {
+        write_atomic(&self.path, true, |w| {
+            for entry in &self.entries {
+                serde_json::to_writer(&mut *w, entry)?;
+                w.write_all(b"\n")?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Hash every output again under `root`, the output directory, and
+    /// return those that are gone or changed.
+    pub fn verify(&self, root: &Path) -> io::Result<Vec<Mismatch>> {
+        let mut mismatches = vec![];
+        for entry in &self.entries {
+            let file = match fs::File::open(root.join(&entry.path)) {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    mismatches.push(Mismatch::Missing(entry.path.clone()));
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            let mut hasher = HashingWriter::new(io::sink());
+            io::copy(&mut BufReader::new(file), &mut hasher)?;
+            if hasher.finish() != (entry.sha256.clone(), entry.bytes) {
+                mismatches.push(Mismatch::Changed(entry.path.clone()));
+            }
+        }
+        Ok(mismatches)
+    }
+}
+
+/// Passes what is written on to the writer it wraps and hashes it on the
+/// way, so that outputs need not be read back to be hashed.
+pub struct HashingWriter<W> {
+    inner: W,
+    context: Context,
+    bytes: u64,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            context: Context::new(&SHA256),
+            bytes: 0,
+        }
+    }
+
+    /// The SHA-256 of what was written, in lowercase hex, and its size.
+    #[must_use]
+    pub fn finish(self) -> (String, u64) {
+        let hex = self
+            .context
+            .finish()
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        (hex, self.bytes)
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.context.update(&buf[..n]);
+        self.bytes += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hashing_writer() {
+        let mut out = vec![];
+        let mut hasher = HashingWriter::new(&mut out);
+        hasher.write_all(b"abc").unwrap();
+        assert_eq!(
+            hasher.finish(),
+            (
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_owned(),
+                3
+            )
+        );
+        assert_eq!(out, b"abc");
+    }
+
+    #[test]
+    fn manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checksums.jsonl");
+        let mut manifest = ChecksumManifest::open(&path).unwrap();
+        assert!(manifest.entries().is_empty());
+
+        for (name, content) in [("a.json", "[]"), ("b.json", "[{}]")] {
+            fs::write(dir.path().join(name), content).unwrap();
+            let mut hasher = HashingWriter::new(io::sink());
+            hasher.write_all(content.as_bytes()).unwrap();
+            let (sha256, bytes) = hasher.finish();
+            manifest.record(ChecksumEntry {
+                path: name.to_owned(),
+                source: format!("logs/{name}"),
+                game_id: None,
+                sha256,
+                bytes,
+                format: "mjai".to_owned(),
+                converter_version: env!("CARGO_PKG_VERSION").to_owned(),
+                options: BTreeMap::from([("aka".to_owned(), Value::from("keep"))]),
+            });
+        }
+        manifest.save().unwrap();
+
+        let manifest = ChecksumManifest::open(&path).unwrap();
+        assert_eq!(manifest.entries().len(), 2);
+        assert_eq!(manifest.entries()[1].options["aka"], "keep");
+        assert_eq!(manifest.verify(dir.path()).unwrap(), []);
+
+        fs::write(dir.path().join("a.json"), "[ ]").unwrap();
+        fs::remove_file(dir.path().join("b.json")).unwrap();
+        assert_eq!(
+            manifest.verify(dir.path()).unwrap(),
+            [
+                Mismatch::Changed("a.json".to_owned()),
+                Mismatch::Missing("b.json".to_owned())
+            ]
+        );
+    }
+}
@@ -1,53 +1,151 @@
+use crate::cancel::{self, CancelToken};
 use crate::mjai::Event;
+use crate::replay::MeldKind;
+use crate::ruleset::{compute_placements, TieBreak};
 use crate::t;
-use crate::tenhou::{ActionItem, EndStatus, Kyoku, Log, TenhouTile};
-use crate::Tile;
+use crate::tenhou::{ActionItem, EndStatus, Kyoku, Log, RuleError, TenhouTile, RIICHI_STICK};
+use crate::{Points, Tile, TileError};
 use std::array;
 use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::panic;
+use std::slice;
+use std::str::FromStr;
+use std::thread;
 
 use ahash::AHashMap;
-use thiserror::Error;
 
-#[derive(Debug, Error)]
+/// A log that could not be converted to mjai events.
+#[derive(Debug)]
+#[non_exhaustive]
 pub enum ConvertError {
-    #[error("invalid naki string: {0:?}")]
     InvalidNaki(String),
 
-    #[error("invalid tile string: {0:?}")]
-    InvalidTile(String),
+    InvalidTile(TileError),
 
-    #[error("insufficient dora indicators: at kyoku {kyoku} honba {honba}")]
-    InsufficientDoraIndicators { kyoku: u8, honba: u8 },
+    InsufficientDoraIndicators {
+        kyoku: u8,
+        honba: u8,
+    },
 
-    #[error(
-        "insufficient take sequence size: \
-        at kyoku {kyoku} honba {honba} for actor {actor}"
-    )]
-    InsufficientTakes { kyoku: u8, honba: u8, actor: u8 },
+    InsufficientTakes {
+        kyoku: u8,
+        honba: u8,
+        actor: u8,
+    },
 
-    #[error(
-        "insufficient discard sequence size: \
-        at kyoku {kyoku} honba {honba} for actor {actor}"
-    )]
-    InsufficientDiscards { kyoku: u8, honba: u8, actor: u8 },
+    InsufficientDiscards {
+        kyoku: u8,
+        honba: u8,
+        actor: u8,
+    },
 
-    #[error("tsumogiri should not exist in discard table")]
     UnexpectedTsumogiri,
 
-    #[error(
-        "unexpected naki: \
-        at kyoku {kyoku} honba {honba} for actor {actor}: \
-        action {action:?}, expected tile {last_discard} \
-        from {last_actor:?}"
-    )]
+    UnexpectedRiichi,
+
     UnexpectedNaki {
-        action: Event,
+        action: Box<Event>,
+        /// The position of the naki among the takes of the actor.
+        index: u8,
         last_discard: Tile,
         last_actor: Option<u8>,
         kyoku: u8,
         honba: u8,
         actor: u8,
     },
+
+    /// The final score of a seat does not fit [`Points`].
+    ScoreOverflow {
+        kyoku: u8,
+        honba: u8,
+        who: u8,
+    },
+
+    Cancelled,
+}
+
+impl ConvertError {
+    /// A stable identifier of the kind of error, e.g.
+    /// `convert.unexpected_naki`, to branch on instead of the message.
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidNaki(_) => "convert.invalid_naki",
+            Self::InvalidTile(_) => "convert.invalid_tile",
+            Self::InsufficientDoraIndicators { .. } => "convert.insufficient_dora_indicators",
+            Self::InsufficientTakes { .. } => "convert.insufficient_takes",
+            Self::InsufficientDiscards { .. } => "convert.insufficient_discards",
+            Self::UnexpectedTsumogiri => "convert.unexpected_tsumogiri",
+            Self::UnexpectedRiichi => "convert.unexpected_riichi",
+            Self::UnexpectedNaki { .. } => "convert.unexpected_naki",
+            Self::ScoreOverflow { .. } => "convert.score_overflow",
+            Self::Cancelled => "convert.cancelled",
+        }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidNaki(naki) => write!(f, "invalid naki string: {naki:?}"),
+            Self::InvalidTile(err) => fmt::Display::fmt(err, f),
+            Self::InsufficientDoraIndicators { kyoku, honba } => write!(
+                f,
+                "insufficient dora indicators: at kyoku {kyoku} honba {honba}"
+            ),
+            Self::InsufficientTakes {
+                kyoku,
+                honba,
+                actor,
+            } => write!(
+                f,
+                "insufficient take sequence size: \
+                at kyoku {kyoku} honba {honba} for actor {actor}"
+            ),
+            Self::InsufficientDiscards {
+                kyoku,
+                honba,
+                actor,
+            } => write!(
+                f,
+                "insufficient discard sequence size: \
+                at kyoku {kyoku} honba {honba} for actor {actor}"
+            ),
+            Self::UnexpectedTsumogiri => f.write_str("tsumogiri should not exist in discard table"),
+            Self::UnexpectedRiichi => f.write_str("riichi should not exist in take table"),
+            Self::UnexpectedNaki {
+                action,
+                index,
+                last_discard,
+                last_actor,
+                kyoku,
+                honba,
+                actor,
+            } => write!(
+                f,
+                "unexpected naki: \
+                at kyoku {kyoku} honba {honba} for actor {actor}: \
+                action {action:?} at take {index}, expected tile {last_discard} \
+                from {last_actor:?}"
+            ),
+            Self::ScoreOverflow { kyoku, honba, who } => write!(
+                f,
+                "score of seat {who} overflows: at kyoku {kyoku} honba {honba}"
+            ),
+            Self::Cancelled => f.write_str("conversion cancelled"),
+        }
+    }
+}
+
+impl Error for ConvertError {}
+
+impl From<TileError> for ConvertError {
+    fn from(err: TileError) -> Self {
+        Self::InvalidTile(err)
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ConvertError>;
@@ -57,24 +155,547 @@ struct BackTrack {
     use_the_first_branch: bool,
 }
 
+/// Options for [`tenhou_to_mjai_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    pub aka: AkaNormalization,
+    pub haipai: HaipaiOrder,
+    /// Convert the kyokus of a log on up to one thread per CPU. Only worth it
+    /// for very long logs; the output is the same either way.
+    pub parallel_kyokus: bool,
+    /// Checked before every kyoku; once cancelled, the remaining kyokus fail
+    /// with [`ConvertError::Cancelled`].
+    pub cancel: Option<CancelToken>,
+    /// How tied seats are ranked in `end_game`.
+    pub tie_break: TieBreak,
+}
+
+/// How aka dora are represented in the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AkaNormalization {
+    /// Emit aka dora as they are recorded.
+    #[default]
+    Keep,
+    /// Replace every aka dora with its plain five and clear `aka_flag`, for
+    /// tools that do not understand aka dora.
+    Deaka,
+    /// Mark one five of pinzu and one of souzu as aka dora in every kyoku of
+    /// a log without any, and set `aka_flag`, for tools that expect the aka
+    /// dora of the rules. The first copy seen becomes aka and stays the
+    /// same tile through the hands, rivers and melds.
+    Akaize,
+}
+
+impl AkaNormalization {
+    /// The name used on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Deaka => "deaka",
+            Self::Akaize => "akaize",
+        }
+    }
+}
+
+impl FromStr for AkaNormalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "deaka" => Ok(Self::Deaka),
+            "akaize" => Ok(Self::Akaize),
+            _ => Err(format!(
+                r#"invalid aka normalization: {s:?} (expected "keep", "deaka" or "akaize")"#
+            )),
+        }
+    }
+}
+
+/// The order of the tiles of `tehais` in `start_kyoku`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HaipaiOrder {
+    /// Keep the order of the log, which is the deal order for some sources
+    /// and sorted for others.
+    #[default]
+    Preserve,
+    /// Sort the tiles as tenhou does, an aka five right after the plain
+    /// fives of its suit, so that the order does not tell the source.
+    Sort,
+}
+
+impl HaipaiOrder {
+    /// The name used on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Preserve => "preserve",
+            Self::Sort => "sort",
+        }
+    }
+}
+
+impl FromStr for HaipaiOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "preserve" => Ok(Self::Preserve),
+            "sort" => Ok(Self::Sort),
+            _ => Err(format!(
+                r#"invalid haipai order: {s:?} (expected "preserve" or "sort")"#
+            )),
+        }
+    }
+}
+
 /// Transform a tenhou.net/6 format log into mjai format.
 pub fn tenhou_to_mjai(log: &Log) -> Result<Vec<Event>> {
+    tenhou_to_mjai_with_options(log, &ConvertOptions::default())
+}
+
+/// Transform a tenhou.net/6 format log into mjai format with `options`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "convert",
+        skip_all,
+        fields(game_id = log.game_id.as_deref(), kyokus = log.kyokus.len()),
+    )
+)]
+pub fn tenhou_to_mjai_with_options(log: &Log, options: &ConvertOptions) -> Result<Vec<Event>> {
+    let kyoku_events = convert_kyokus(&log.kyokus, options)
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+    let standings = final_standings(log)?;
+    Ok(assemble_events(log, options, kyoku_events, standings))
+}
+
+/// A kyoku left out by [`tenhou_to_mjai_skipping_invalid`].
+#[derive(Debug)]
+pub struct SkippedKyoku {
+    /// Position of the kyoku in [`Log::kyokus`].
+    pub index: usize,
+    pub kyoku: u8,
+    pub honba: u8,
+    pub error: ConvertError,
+}
+
+/// Like [`tenhou_to_mjai_with_options`], but kyokus that fail to convert are
+/// left out of the output and returned alongside it, instead of failing the
+/// whole log. Kyokus not reached before [`ConvertOptions::cancel`] was
+/// cancelled are skipped with [`ConvertError::Cancelled`]. A last kyoku that
+/// makes a final score overflow is skipped with
+/// [`ConvertError::ScoreOverflow`], and the game ends without results.
+pub fn tenhou_to_mjai_skipping_invalid(
+    log: &Log,
+    options: &ConvertOptions,
+) -> (Vec<Event>, Vec<SkippedKyoku>) {
+    let mut kyoku_events = vec![];
+    let mut skipped = vec![];
+    let results = convert_kyokus(&log.kyokus, options);
+    for (index, (kyoku, result)) in log.kyokus.iter().zip(results).enumerate() {
+        match result {
+            Ok(events) => kyoku_events.push(events),
+            Err(error) => skipped.push(SkippedKyoku {
+                index,
+                kyoku: kyoku.meta.kyoku_num,
+                honba: kyoku.meta.honba,
+                error,
+            }),
+        }
+    }
+
+    let standings = final_standings(log).unwrap_or_else(|error| {
+        let index = log.kyokus.len() - 1;
+        match skipped.last_mut() {
+            Some(skip) if skip.index == index => skip.error = error,
+            _ => {
+                kyoku_events.pop();
+                let kyoku = &log.kyokus[index];
+                skipped.push(SkippedKyoku {
+                    index,
+                    kyoku: kyoku.meta.kyoku_num,
+                    honba: kyoku.meta.honba,
+                    error,
+                });
+            }
+        }
+        Standings::default()
+    });
+    (
+        assemble_events(log, options, kyoku_events, standings),
+        skipped,
+    )
+}
+
+/// Where [`akaize`] has put the aka five of a suit in a kyoku.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AkaPlace {
+    Unseen,
+    Hand(u8),
+    /// The latest discard, which may still be called.
+    Discard,
+    /// A pon of the seat, which a kakan may add to.
+    Pon(u8),
+    Elsewhere,
+}
+
+/// Mark the first five of pinzu and of souzu seen in every kyoku of
+/// `events` as aka, unless some tile of them is aka already.
+///
+/// Aka fives are kept in hand as long as a plain copy can be given up
+/// instead, so that every event still takes tiles its actor holds.
+pub fn akaize(events: &mut [Event]) {
+    let mut has_aka = false;
+    for ev in events.iter_mut() {
+        ev.map_tiles(|t| {
+            has_aka |= t.is_aka();
+            t
+        });
+    }
+    if has_aka {
+        return;
+    }
+    for kyoku in events.chunk_by_mut(|_, ev| !matches!(ev, Event::StartKyoku { .. })) {
+        for five in [t!(5p), t!(5s)] {
+            akaize_kyoku(kyoku, five);
+        }
+    }
+}
+
+fn akaize_kyoku(events: &mut [Event], five: Tile) {
+    let aka = five.akaize();
+    let mut place = AkaPlace::Unseen;
+    let mut counts = [0_u8; 4];
+    // The seat whose last tsumo was the aka five.
+    let mut drawn = None;
+
+    // Mark the first of `tiles` that is `five` as aka, if it is the aka five
+    // by not having been seen yet.
+    let see = |place: &mut AkaPlace, tiles: &mut [Tile], to: AkaPlace| {
+        if *place == AkaPlace::Unseen {
+            if let Some(t) = tiles.iter_mut().find(|t| **t == five) {
+                *t = aka;
+                *place = to;
+            }
+        }
+    };
+    // Take `tiles` from the hand of `seat`, the aka five among them once no
+    // plain copy is left.
+    let take = |place: &mut AkaPlace, counts: &mut [u8; 4], seat: u8, tiles: &mut [Tile], to| {
+        let taken = tiles.iter().filter(|&&t| t == five).count() as u8;
+        if taken == 0 {
+            return;
+        }
+        let count = &mut counts[seat as usize];
+        *count = count.saturating_sub(taken);
+        if *place == AkaPlace::Hand(seat) && *count == 0 {
+            *place = AkaPlace::Unseen;
+        }
+        see(place, tiles, to);
+    };
+
+    for ev in events {
+        let is_pon = matches!(ev, Event::Pon { .. });
+        match ev {
+            Event::StartKyoku {
+                dora_marker,
+                tehais,
+                ..
+            } => {
+                see(
+                    &mut place,
+                    slice::from_mut(dora_marker),
+                    AkaPlace::Elsewhere,
+                );
+                for (seat, tehai) in (0..).zip(tehais) {
+                    counts[seat as usize] = tehai.iter().filter(|&&t| t == five).count() as u8;
+                    see(&mut place, tehai, AkaPlace::Hand(seat));
+                }
+            }
+            Event::Tsumo { actor, pai } => {
+                if place == AkaPlace::Discard {
+                    place = AkaPlace::Elsewhere;
+                }
+                drawn = None;
+                if *pai == five {
+                    counts[*actor as usize] += 1;
+                    see(&mut place, slice::from_mut(pai), AkaPlace::Hand(*actor));
+                    if *pai == aka {
+                        drawn = Some(*actor);
+                    }
+                }
+            }
+            Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                if place == AkaPlace::Discard {
+                    place = AkaPlace::Elsewhere;
+                }
+                // A tsumogiri of the aka five gives it up even with plain
+                // copies left.
+                if *tsumogiri && drawn == Some(*actor) && *pai == five {
+                    place = AkaPlace::Unseen;
+                }
+                take(
+                    &mut place,
+                    &mut counts,
+                    *actor,
+                    slice::from_mut(pai),
+                    AkaPlace::Discard,
+                );
+                drawn = None;
+            }
+            Event::Chi {
+                actor,
+                pai,
+                consumed,
+                ..
+            }
+            | Event::Pon {
+                actor,
+                pai,
+                consumed,
+                ..
+            } => {
+                let meld = if is_pon {
+                    AkaPlace::Pon(*actor)
+                } else {
+                    AkaPlace::Elsewhere
+                };
+                if place == AkaPlace::Discard && *pai == five {
+                    *pai = aka;
+                    place = meld;
+                }
+                take(&mut place, &mut counts, *actor, consumed, meld);
+            }
+            Event::Daiminkan {
+                actor,
+                pai,
+                consumed,
+                ..
+            } => {
+                if place == AkaPlace::Discard && *pai == five {
+                    *pai = aka;
+                    place = AkaPlace::Elsewhere;
+                }
+                take(
+                    &mut place,
+                    &mut counts,
+                    *actor,
+                    consumed,
+                    AkaPlace::Elsewhere,
+                );
+            }
+            Event::Kakan {
+                actor,
+                pai,
+                consumed,
+            } => {
+                if *pai == five && place == AkaPlace::Pon(*actor) {
+                    place = AkaPlace::Unseen;
+                    see(&mut place, consumed, AkaPlace::Elsewhere);
+                }
+                take(
+                    &mut place,
+                    &mut counts,
+                    *actor,
+                    slice::from_mut(pai),
+                    AkaPlace::Elsewhere,
+                );
+            }
+            Event::Ankan { actor, consumed } => {
+                take(
+                    &mut place,
+                    &mut counts,
+                    *actor,
+                    consumed,
+                    AkaPlace::Elsewhere,
+                );
+            }
+            Event::Dora { dora_marker } => {
+                see(
+                    &mut place,
+                    slice::from_mut(dora_marker),
+                    AkaPlace::Elsewhere,
+                );
+            }
+            Event::Hora {
+                ura_markers: Some(ura_markers),
+                ..
+            } => see(&mut place, ura_markers, AkaPlace::Elsewhere),
+            _ => {}
+        }
+    }
+}
+
+/// The final scores and placement points of a game, as `end_game` reports
+/// them.
+#[derive(Debug, Default)]
+struct Standings {
+    scores: Option<[Points; 4]>,
+    final_points: Option<[Points; 4]>,
+}
+
+/// The [`Standings`] of `log`: those of its final results, or else the
+/// scores its kyokus add up to if they show the game over, with a seat below
+/// zero or the last scheduled kyoku played. A log cut off, or repaired by
+/// dropping the kyokus it was cut off in, gets none.
+fn final_standings(log: &Log) -> Result<Standings> {
+    if let Some(results) = log.final_results {
+        return Ok(Standings {
+            scores: Some(results.map(|r| r.points)),
+            final_points: Some(results.map(|r| (r.pt_delta * 1000.0).round() as Points)),
+        });
+    }
+    let scores = match log.final_scores() {
+        Ok(scores) => scores,
+        Err(RuleError::ScoreOverflow { kyoku, honba, who }) => {
+            return Err(ConvertError::ScoreOverflow { kyoku, honba, who });
+        }
+        Err(_) => None,
+    };
+    let over = |scores: &[Points; 4]| {
+        let Some(last) = log.kyokus.last() else {
+            return false;
+        };
+        let kyoku_num = last.meta.kyoku_num;
+        let played = kyoku_num / 4 * 3 + kyoku_num % 4 + 1;
+        scores[..3].iter().any(|&score| score < 0) || played >= log.game_length.kyokus()
+    };
+    Ok(Standings {
+        scores: scores.filter(|scores| log.repair.is_none() && over(scores)),
+        final_points: None,
+    })
+}
+
+fn assemble_events(
+    log: &Log,
+    options: &ConvertOptions,
+    kyoku_events: Vec<Vec<Event>>,
+    standings: Standings,
+) -> Vec<Event> {
     let mut events = vec![Event::StartGame {
-        kyoku_first: log.game_length as u8,
-        aka_flag: log.has_aka,
+        kyoku_first: log.game_length.kyoku_first(),
+        aka_flag: match options.aka {
+            AkaNormalization::Keep => log.has_aka,
+            AkaNormalization::Deaka => false,
+            AkaNormalization::Akaize => true,
+        },
         names: log.names.clone(),
     }];
+    events.extend(kyoku_events.into_iter().flatten());
 
-    for kyoku in &log.kyokus {
-        let kyoku_events = tenhou_kyoku_to_mjai_events(kyoku)?;
-        events.extend(kyoku_events);
+    match options.aka {
+        AkaNormalization::Keep => {}
+        AkaNormalization::Deaka => {
+            for ev in &mut events {
+                ev.map_tiles(Tile::deaka);
+            }
+        }
+        AkaNormalization::Akaize => akaize(&mut events),
+    }
+    if options.haipai == HaipaiOrder::Sort {
+        for ev in &mut events {
+            if let Event::StartKyoku { tehais, .. } = ev {
+                for tehai in tehais {
+                    tehai.sort_by_key(|&tile| TenhouTile::from(tile).sort_key());
+                }
+            }
+        }
     }
 
-    events.push(Event::EndGame);
-    Ok(events)
+    events.push(end_game(
+        standings.scores,
+        standings.final_points,
+        options.tie_break,
+    ));
+    events
+}
+
+/// The `end_game` event of a sanma game with the final `scores`, if known,
+/// and their ranks, ties ranked by `tie_break`.
+pub(crate) fn end_game(
+    scores: Option<[Points; 4]>,
+    final_points: Option<[Points; 4]>,
+    tie_break: TieBreak,
+) -> Event {
+    let ranks = scores.map(|scores| {
+        let placements = compute_placements([scores[0], scores[1], scores[2]], tie_break);
+        array::from_fn(|seat| placements.get(seat).map_or(0, |&p| p as u8 + 1))
+    });
+    Event::EndGame {
+        scores,
+        ranks,
+        final_points,
+    }
 }
 
-fn tenhou_kyoku_to_mjai_events(kyoku: &Kyoku) -> Result<Vec<Event>> {
+fn convert_kyokus(kyokus: &[Kyoku], options: &ConvertOptions) -> Vec<Result<Vec<Event>>> {
+    if options.parallel_kyokus {
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        kyokus_to_mjai_events_parallel(kyokus, threads, options.cancel.as_ref())
+    } else {
+        kyokus_to_mjai_events(kyokus, options.cancel.as_ref())
+    }
+}
+
+fn kyokus_to_mjai_events(
+    kyokus: &[Kyoku],
+    cancel: Option<&CancelToken>,
+) -> Vec<Result<Vec<Event>>> {
+    kyokus
+        .iter()
+        .map(|kyoku| {
+            if cancel::is_cancelled(cancel) {
+                return Err(ConvertError::Cancelled);
+            }
+            tenhou_kyoku_to_mjai_events(kyoku)
+        })
+        .collect()
+}
+
+/// Like [`kyokus_to_mjai_events`], but with the kyokus split into one
+/// contiguous chunk per thread. Outputs are stitched back in order.
+fn kyokus_to_mjai_events_parallel(
+    kyokus: &[Kyoku],
+    threads: usize,
+    cancel: Option<&CancelToken>,
+) -> Vec<Result<Vec<Event>>> {
+    if threads <= 1 || kyokus.len() < 2 {
+        return kyokus_to_mjai_events(kyokus, cancel);
+    }
+
+    let chunk_size = kyokus.len().div_ceil(threads);
+    thread::scope(|s| {
+        let handles: Vec<_> = kyokus
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move || kyokus_to_mjai_events(chunk, cancel)))
+            .collect();
+
+        // Every thread has to be spawned before the first is joined.
+        let mut ret = Vec::with_capacity(kyokus.len());
+        for handle in handles {
+            ret.extend(handle.join().unwrap_or_else(|e| panic::resume_unwind(e)));
+        }
+        ret
+    })
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "kyoku",
+        level = "debug",
+        skip_all,
+        fields(kyoku = kyoku.meta.kyoku_num, honba = kyoku.meta.honba),
+    )
+)]
+pub(crate) fn tenhou_kyoku_to_mjai_events(kyoku: &Kyoku) -> Result<Vec<Event>> {
     // First of all, transform all takes and discards to events.
     let (take_events, discard_events): (Vec<_>, Vec<_>) = (0..4)
         .map(|a| {
@@ -100,7 +721,7 @@ fn tenhou_kyoku_to_mjai_events(kyoku: &Kyoku) -> Result<Vec<Event>> {
         2 => t!(W),
         _ => t!(N),
     };
-    
+
     let attempt = |backtracks: &mut AHashMap<Tile, BackTrack>| -> Result<Vec<Event>> {
         let mut events = vec![];
 
@@ -118,7 +739,7 @@ fn tenhou_kyoku_to_mjai_events(kyoku: &Kyoku) -> Result<Vec<Event>> {
                 })?,
             oya,
             scores: kyoku.scoreboard,
-            tehais: array::from_fn(|i| kyoku.action_tables[i].haipai.clone()),
+            tehais: array::from_fn(|i| kyoku.action_tables[i].haipai.to_vec()),
         });
 
         let mut discard_sets: Vec<_> = (0..3)
@@ -161,7 +782,8 @@ fn tenhou_kyoku_to_mjai_events(kyoku: &Kyoku) -> Result<Vec<Event>> {
                     || last_actor.is_some_and(|a| a != target || a == actor as u8)
                 {
                     return Err(ConvertError::UnexpectedNaki {
-                        action: take.clone(),
+                        action: Box::new(take.clone()),
+                        index: (take_idxs[actor] - 1) as u8,
                         last_discard,
                         last_actor,
                         kyoku: kyoku.meta.kyoku_num,
@@ -173,7 +795,12 @@ fn tenhou_kyoku_to_mjai_events(kyoku: &Kyoku) -> Result<Vec<Event>> {
 
             // If a reach event was emitted before, set it as accepted now.
             if let Some(actor) = reach_flag.take() {
-                events.push(Event::ReachAccepted { actor: actor as u8 });
+                let mut deltas = [0; 4];
+                deltas[actor] = -RIICHI_STICK;
+                events.push(Event::ReachAccepted {
+                    actor: actor as u8,
+                    deltas: Some(deltas),
+                });
             }
 
             // If the take is daiminkan, immediately consume the next take event
@@ -424,7 +1051,7 @@ fn tenhou_kyoku_to_mjai_events(kyoku: &Kyoku) -> Result<Vec<Event>> {
 
         Ok(events)
     };
-    
+
     let mut first_error = None;
     loop {
         match attempt(&mut backtracks) {
@@ -441,8 +1068,8 @@ fn tenhou_kyoku_to_mjai_events(kyoku: &Kyoku) -> Result<Vec<Event>> {
 
 fn parse_takes_and_discards_to_mjai(
     actor: u8,
-    takes: &[ActionItem],
-    discards: &[ActionItem],
+    takes: &[ActionItem<'_>],
+    discards: &[ActionItem<'_>],
 ) -> Result<(Vec<Event>, Vec<Event>)> {
     let mjai_takes = take_action_to_events(actor, takes)?;
     let mut mjai_discards = discard_action_to_events(actor, discards)?;
@@ -490,11 +1117,189 @@ fn finalize_discards(takes: &[Event], discards: &mut Vec<Event>) {
     }
 }
 
-fn take_action_to_events(actor: u8, takes: &[ActionItem]) -> Result<Vec<Event>> {
+/// A pon as tenhou writes it in a naki string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pon {
+    /// The seat whose discard was called.
+    pub target: u8,
+    /// The tile called, aka or not.
+    pub pai: Tile,
+    pub consumed: [Tile; 2],
+}
+
+impl Pon {
+    /// Parse the naki string of a pon made by `actor`, `None` if it is not a
+    /// pon. The position of the mark tells the seat called from:
+    ///
+    /// - "p252525" => pon 5p from kamicha
+    /// - "12p1212" => pon 2m from toimen
+    /// - "3737p37" => pon 7s from shimocha
+    pub fn parse(actor: u8, naki_string: &str) -> Result<Option<Self>> {
+        let Some(idx) = naki_string.find('p') else {
+            return Ok(None);
+        };
+        let invalid = || ConvertError::InvalidNaki(naki_string.to_owned());
+        if naki_string.len() != 7 {
+            return Err(invalid());
+        }
+        let naki = naki_string.as_bytes();
+        let tile = |at: usize| tiles_from_tenhou_bytes(&naki[at..at + 2]);
+
+        let (offset, pai, consumed) = match idx {
+            0 => (3, tile(1)?, [tile(3)?, tile(5)?]),
+            2 => (2, tile(3)?, [tile(0)?, tile(5)?]),
+            4 => (1, tile(5)?, [tile(0)?, tile(2)?]),
+            _ => return Err(invalid()),
+        };
+        Ok(Some(Self {
+            target: (actor + offset) % 4,
+            pai,
+            consumed,
+        }))
+    }
+
+    #[must_use]
+    pub const fn to_event(self, actor: u8) -> Event {
+        Event::Pon {
+            actor,
+            target: self.target,
+            pai: self.pai,
+            consumed: self.consumed,
+        }
+    }
+}
+
+/// A kan as tenhou writes it in a naki string, typed by how it was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kan {
+    /// e.g. "424242a42" => ankan 2z
+    Ankan { consumed: [Tile; 4] },
+    /// A pon from `target` upgraded with `pai`,
+    /// e.g. "41k414141" => pon 1z from toimen then kan
+    Kakan {
+        target: u8,
+        pai: Tile,
+        consumed: [Tile; 3],
+    },
+    /// e.g. "26m262626" => kan 6p from toimen
+    Daiminkan {
+        target: u8,
+        pai: Tile,
+        consumed: [Tile; 3],
+    },
+}
+
+impl Kan {
+    /// Parse the naki string of a kan made by `actor`, `None` if it is not a
+    /// kan. The position of the mark tells the seat called from, as for pon.
+    pub fn parse(actor: u8, naki_string: &str) -> Result<Option<Self>> {
+        let Some(idx) = naki_string.find(['m', 'k', 'a']) else {
+            return Ok(None);
+        };
+        let invalid = || ConvertError::InvalidNaki(naki_string.to_owned());
+        if naki_string.len() != 9 {
+            return Err(invalid());
+        }
+        let naki = naki_string.as_bytes();
+        let tile = |at: usize| tiles_from_tenhou_bytes(&naki[at..at + 2]);
+        let target = |offset| (actor + offset) % 4;
+
+        let kan = match (naki[idx], idx) {
+            (b'm', 0) => Self::Daiminkan {
+                target: target(3),
+                pai: tile(1)?,
+                consumed: [tile(3)?, tile(5)?, tile(7)?],
+            },
+            (b'm', 2) => Self::Daiminkan {
+                target: target(2),
+                pai: tile(3)?,
+                consumed: [tile(0)?, tile(5)?, tile(7)?],
+            },
+            (b'm', 6) => Self::Daiminkan {
+                target: target(1),
+                pai: tile(7)?,
+                consumed: [tile(0)?, tile(2)?, tile(4)?],
+            },
+            (b'k', 0) => Self::Kakan {
+                target: target(3),
+                pai: tile(1)?,
+                consumed: [tile(3)?, tile(5)?, tile(7)?],
+            },
+            (b'k', 2) => Self::Kakan {
+                target: target(2),
+                pai: tile(3)?,
+                consumed: [tile(0)?, tile(5)?, tile(7)?],
+            },
+            (b'k', 4) => Self::Kakan {
+                target: target(1),
+                pai: tile(5)?,
+                consumed: [tile(0)?, tile(2)?, tile(7)?],
+            },
+            // for ankan, 'a' can only appear at [6]
+            (b'a', 6) => Self::Ankan {
+                consumed: [tile(0)?, tile(2)?, tile(4)?, tile(7)?],
+            },
+            _ => return Err(invalid()),
+        };
+        Ok(Some(kan))
+    }
+
+    /// The tile the kan is of, the called or added one if any.
+    #[must_use]
+    pub const fn pai(&self) -> Tile {
+        match *self {
+            Self::Ankan { consumed } => consumed[3],
+            Self::Kakan { pai, .. } | Self::Daiminkan { pai, .. } => pai,
+        }
+    }
+
+    /// The seat called from, `None` for ankan.
+    #[must_use]
+    pub const fn target(&self) -> Option<u8> {
+        match *self {
+            Self::Ankan { .. } => None,
+            Self::Kakan { target, .. } | Self::Daiminkan { target, .. } => Some(target),
+        }
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> MeldKind {
+        match self {
+            Self::Ankan { .. } => MeldKind::Ankan,
+            Self::Kakan { .. } => MeldKind::Kakan,
+            Self::Daiminkan { .. } => MeldKind::Daiminkan,
+        }
+    }
+
+    #[must_use]
+    pub const fn to_event(self, actor: u8) -> Event {
+        match self {
+            Self::Ankan { consumed } => Event::Ankan { actor, consumed },
+            Self::Kakan { pai, consumed, .. } => Event::Kakan {
+                actor,
+                pai,
+                consumed,
+            },
+            Self::Daiminkan {
+                target,
+                pai,
+                consumed,
+            } => Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            },
+        }
+    }
+}
+
+fn take_action_to_events(actor: u8, takes: &[ActionItem<'_>]) -> Result<Vec<Event>> {
     takes
         .iter()
         .map(|take| match take {
-            ActionItem::Tsumogiri(_) => Err(ConvertError::UnexpectedTsumogiri),
+            ActionItem::Tsumogiri => Err(ConvertError::UnexpectedTsumogiri),
+            ActionItem::Riichi(_) => Err(ConvertError::UnexpectedRiichi),
             &ActionItem::Tile(pai) => Ok(Event::Tsumo { actor, pai }),
             ActionItem::Naki(naki_string) => {
                 let naki = naki_string.as_bytes();
@@ -504,7 +1309,7 @@ fn take_action_to_events(actor: u8, takes: &[ActionItem]) -> Result<Vec<Event>>
                     // you can only chi from kamicha right...?
 
                     if naki_string.len() != 7 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
+                        return Err(ConvertError::InvalidNaki(naki_string.to_string()));
                     }
 
                     // e.g. "c275226" => chi 7p with 06p from kamicha
@@ -517,112 +1322,19 @@ fn take_action_to_events(actor: u8, takes: &[ActionItem]) -> Result<Vec<Event>>
                             tiles_from_tenhou_bytes(&naki[5..7])?,
                         ],
                     })
-                } else if let Some(idx) = naki_string.find('p') {
-                    // pon
-
-                    if naki_string.len() != 7 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                    }
-
-                    match idx {
-                        // from kamicha
-                        // e.g. "p252525" => pon 5p from kamicha
-                        0 => Ok(Event::Pon {
-                            actor,
-                            target: (actor + 3) % 4,
-                            pai: tiles_from_tenhou_bytes(&naki[1..3])?,
-                            consumed: [
-                                tiles_from_tenhou_bytes(&naki[3..5])?,
-                                tiles_from_tenhou_bytes(&naki[5..7])?,
-                            ],
-                        }),
-
-                        // from toimen
-                        // e.g. "12p1212" => pon 2m from toimen
-                        2 => Ok(Event::Pon {
-                            actor,
-                            target: (actor + 2) % 4,
-                            pai: tiles_from_tenhou_bytes(&naki[3..5])?,
-                            consumed: [
-                                tiles_from_tenhou_bytes(&naki[0..2])?,
-                                tiles_from_tenhou_bytes(&naki[5..7])?,
-                            ],
-                        }),
-
-                        // from shimocha
-                        // e.g. "3737p37" => pon 7s from shimocha
-                        4 => Ok(Event::Pon {
-                            actor,
-                            target: (actor + 1) % 4,
-                            pai: tiles_from_tenhou_bytes(&naki[5..7])?,
-                            consumed: [
-                                tiles_from_tenhou_bytes(&naki[0..2])?,
-                                tiles_from_tenhou_bytes(&naki[2..4])?,
-                            ],
-                        }),
-
-                        // ???
-                        _ => Err(ConvertError::InvalidNaki(naki_string.clone())),
-                    }
-                } else if let Some(idx) = naki_string.find('m') {
-                    // daiminkan
-
-                    if naki_string.len() != 9 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                    }
-
-                    match idx {
-                        // from kamicha
-                        // e.g. "m39393939" => kan 9s from kamicha
-                        0 => Ok(Event::Daiminkan {
-                            actor,
-                            target: (actor + 3) % 4,
-                            pai: tiles_from_tenhou_bytes(&naki[1..3])?,
-                            consumed: [
-                                tiles_from_tenhou_bytes(&naki[3..5])?,
-                                tiles_from_tenhou_bytes(&naki[5..7])?,
-                                tiles_from_tenhou_bytes(&naki[7..9])?,
-                            ],
-                        }),
-
-                        // from toimen
-                        // e.g. "26m262626" => kan 6p from toimen
-                        2 => Ok(Event::Daiminkan {
-                            actor,
-                            target: (actor + 2) % 4,
-                            pai: tiles_from_tenhou_bytes(&naki[3..5])?,
-                            consumed: [
-                                tiles_from_tenhou_bytes(&naki[0..2])?,
-                                tiles_from_tenhou_bytes(&naki[5..7])?,
-                                tiles_from_tenhou_bytes(&naki[7..9])?,
-                            ],
-                        }),
-
-                        // from shimocha
-                        // e.g. "131313m13" => kan 3m from shimocha
-                        6 => Ok(Event::Daiminkan {
-                            actor,
-                            target: (actor + 1) % 4,
-                            pai: tiles_from_tenhou_bytes(&naki[7..9])?,
-                            consumed: [
-                                tiles_from_tenhou_bytes(&naki[0..2])?,
-                                tiles_from_tenhou_bytes(&naki[2..4])?,
-                                tiles_from_tenhou_bytes(&naki[4..6])?,
-                            ],
-                        }),
-
-                        // ???
-                        _ => Err(ConvertError::InvalidNaki(naki_string.clone())),
-                    }
+                } else if let Some(pon) = Pon::parse(actor, naki_string)? {
+                    Ok(pon.to_event(actor))
+                } else if let Some(kan @ Kan::Daiminkan { .. }) = Kan::parse(actor, naki_string)? {
+                    Ok(kan.to_event(actor))
                 } else {
-                    Err(ConvertError::InvalidNaki(naki_string.clone()))
+                    Err(ConvertError::InvalidNaki(naki_string.to_string()))
                 }
             }
         })
         .collect()
 }
 
-fn discard_action_to_events(actor: u8, discards: &[ActionItem]) -> Result<Vec<Event>> {
+fn discard_action_to_events(actor: u8, discards: &[ActionItem<'_>]) -> Result<Vec<Event>> {
     let mut ret = vec![];
 
     for discard in discards {
@@ -637,7 +1349,7 @@ fn discard_action_to_events(actor: u8, discards: &[ActionItem]) -> Result<Vec<Ev
                 ret.push(ev);
             }
 
-            ActionItem::Tsumogiri(_) => {
+            ActionItem::Tsumogiri => {
                 let ev = Event::Dahai {
                     actor,
                     pai: t!(?), // must be filled later
@@ -647,87 +1359,28 @@ fn discard_action_to_events(actor: u8, discards: &[ActionItem]) -> Result<Vec<Ev
                 ret.push(ev);
             }
 
+            &ActionItem::Riichi(pai) => {
+                ret.push(Event::Reach { actor });
+                ret.push(Event::Dahai {
+                    actor,
+                    pai: pai.unwrap_or(t!(?)), // must be filled later if it is tsumogiri
+                    tsumogiri: pai.is_none(),
+                });
+            }
+
             ActionItem::Naki(naki_string) => {
                 let naki = naki_string.as_bytes();
 
-                // only ankan, kakan and reach are possible
-                if let Some(idx) = naki_string.find('k') {
-                    // kakan
-
-                    if naki_string.len() != 9 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                    }
-
-                    let ev = match idx {
-                        // previously pon from toimen
-                        // e.g. "k16161616" => pon 6m from kamicha then kan
-                        0 => Event::Kakan {
-                            actor,
-                            pai: tiles_from_tenhou_bytes(&naki[1..3])?,
-                            consumed: [
-                                tiles_from_tenhou_bytes(&naki[3..5])?,
-                                tiles_from_tenhou_bytes(&naki[5..7])?,
-                                tiles_from_tenhou_bytes(&naki[7..9])?,
-                            ],
-                        },
-
-                        // previously pon from toimen
-                        // e.g. "41k414141" => pon 1z from toimen then kan
-                        2 => Event::Kakan {
-                            actor,
-                            pai: tiles_from_tenhou_bytes(&naki[3..5])?,
-                            consumed: [
-                                tiles_from_tenhou_bytes(&naki[0..2])?,
-                                tiles_from_tenhou_bytes(&naki[5..7])?,
-                                tiles_from_tenhou_bytes(&naki[7..9])?,
-                            ],
-                        },
-
-                        // previously pon from shimocha
-                        // e.g. "4646k4646" => pon 6z from shimocha then kan
-                        4 => Event::Kakan {
-                            actor,
-                            pai: tiles_from_tenhou_bytes(&naki[5..7])?,
-                            consumed: [
-                                tiles_from_tenhou_bytes(&naki[0..2])?,
-                                tiles_from_tenhou_bytes(&naki[2..4])?,
-                                tiles_from_tenhou_bytes(&naki[7..9])?,
-                            ],
-                        },
-
-                        // ???
-                        _ => {
-                            return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                        }
-                    };
-
-                    ret.push(ev);
-                } else if naki.contains(&b'a') {
-                    // ankan
-                    // for ankan, 'a' can only appear at [6]
-                    // e.g. "424242a42" => ankan 2z
-
-                    if naki_string.len() != 9 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                    }
-
-                    let pai = tiles_from_tenhou_bytes(&naki[7..9])?;
-                    let ev = Event::Ankan {
-                        actor,
-                        consumed: [
-                            tiles_from_tenhou_bytes(&naki[0..2])?,
-                            tiles_from_tenhou_bytes(&naki[2..4])?,
-                            tiles_from_tenhou_bytes(&naki[4..6])?,
-                            pai,
-                        ],
-                    };
-
-                    ret.push(ev);
+                // only ankan, kakan and nukidora are possible
+                if let Some(kan @ (Kan::Ankan { .. } | Kan::Kakan { .. })) =
+                    Kan::parse(actor, naki_string)?
+                {
+                    ret.push(kan.to_event(actor));
                 } else if naki.contains(&b'f') {
                     // nukidora
 
                     if naki_string.len() != 3 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
+                        return Err(ConvertError::InvalidNaki(naki_string.to_string()));
                     }
 
                     let ev = Event::Nukidora {
@@ -737,25 +1390,7 @@ fn discard_action_to_events(actor: u8, discards: &[ActionItem]) -> Result<Vec<Ev
 
                     ret.push(ev);
                 } else {
-                    // reach
-                    // e.g. "r35" => discard 5s to reach
-
-                    if naki_string.len() != 3 {
-                        return Err(ConvertError::InvalidNaki(naki_string.clone()));
-                    }
-
-                    let pai = if &naki[1..3] == b"60" {
-                        t!(?)
-                    } else {
-                        tiles_from_tenhou_bytes(&naki[1..3])?
-                    };
-
-                    ret.push(Event::Reach { actor });
-                    ret.push(Event::Dahai {
-                        actor,
-                        pai, // must be filled later if it is tsumogiri
-                        tsumogiri: pai == t!(?),
-                    });
+                    return Err(ConvertError::InvalidNaki(naki_string.to_string()));
                 }
             }
         };
@@ -766,18 +1401,34 @@ fn discard_action_to_events(actor: u8, discards: &[ActionItem]) -> Result<Vec<Ev
 
 fn end_kyoku(events: &mut Vec<Event>, kyoku: &Kyoku) {
     match &kyoku.end_status {
-        EndStatus::Hora { details } => {
-            events.extend(details.iter().map(|detail| Event::Hora {
-                actor: detail.who,
-                target: detail.target,
-                deltas: Some(detail.score_deltas),
-                ura_markers: Some(kyoku.ura_indicators.clone()),
+        EndStatus::Hora { .. } => {
+            let pot = Points::from(kyoku.riichi_sticks()) * RIICHI_STICK;
+            events.extend(kyoku.horas().into_iter().enumerate().map(|(i, detail)| {
+                // Only the winner closest to the discarder takes the sticks.
+                let sticks = (i == 0 && pot > 0).then(|| {
+                    let mut sticks = [0; 4];
+                    sticks[detail.who as usize] = pot;
+                    sticks
+                });
+                Event::Hora {
+                    actor: detail.who,
+                    target: detail.target,
+                    deltas: Some(detail.score_deltas),
+                    sticks,
+                    ura_markers: Some(kyoku.ura_indicators.clone().unwrap_or_default()),
+                }
             }));
         }
 
-        EndStatus::Ryukyoku { score_deltas } => {
+        EndStatus::Ryukyoku {
+            score_deltas,
+            reason,
+        } => {
             events.push(Event::Ryukyoku {
+                reason: reason.mjai_reason().map(str::to_owned),
                 deltas: Some(*score_deltas),
+                tenpais: kyoku.tenpais(),
+                kyotaku: Some(kyoku.riichi_sticks() as u8),
             });
         }
     };
@@ -786,13 +1437,455 @@ fn end_kyoku(events: &mut Vec<Event>, kyoku: &Kyoku) {
 }
 
 pub fn tiles_from_tenhou_bytes(b: &[u8]) -> Result<Tile> {
+    const CONTEXT: &str = "tenhou tile code in a naki";
     let s = String::from_utf8_lossy(b);
-    let id: u8 = s
-        .parse()
-        .map_err(|_| ConvertError::InvalidTile(s.clone().into_owned()))?;
-
-    let tenhou_tile =
-        TenhouTile::try_from(id).map_err(|_| ConvertError::InvalidTile(s.clone().into_owned()))?;
-    let tile = Tile::from(tenhou_tile);
-    Ok(tile)
+    let id: u8 = s.parse().map_err(|_| TileError::string(&s, CONTEXT))?;
+    let tenhou_tile = TenhouTile::from_code(id).map_err(|e| e.with_context(CONTEXT))?;
+    Ok(Tile::from(tenhou_tile))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tenhou::{Repair, RyukyokuReason};
+    use crate::testdata::Fixture;
+
+    const SAMPLE: &str = Fixture::Hanchan.json();
+
+    /// The sample log with seat 0 missing all takes in kyokus 3 and later,
+    /// each of which gets its index as honba.
+    fn broken_from_kyoku_3() -> Log {
+        let mut log = Log::from_json_str(SAMPLE).unwrap();
+        for (i, kyoku) in log.kyokus.iter_mut().enumerate().skip(3) {
+            kyoku.action_tables[0].takes = Box::default();
+            kyoku.meta.honba = i as u8;
+        }
+        log
+    }
+
+    #[test]
+    fn parallel_kyokus() {
+        let log = Log::from_json_str(SAMPLE).unwrap();
+        let unwrap_all = |results: Vec<Result<Vec<Event>>>| {
+            results.into_iter().map(Result::unwrap).collect::<Vec<_>>()
+        };
+        let sequential = unwrap_all(kyokus_to_mjai_events(&log.kyokus, None));
+        for threads in [1, 2, 4, 64] {
+            assert_eq!(
+                unwrap_all(kyokus_to_mjai_events_parallel(&log.kyokus, threads, None)),
+                sequential,
+            );
+        }
+
+        let options = ConvertOptions {
+            parallel_kyokus: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            tenhou_to_mjai_with_options(&log, &options).unwrap(),
+            tenhou_to_mjai(&log).unwrap(),
+        );
+
+        // The earliest broken kyoku is reported.
+        let err = tenhou_to_mjai_with_options(&broken_from_kyoku_3(), &options).unwrap_err();
+        assert!(
+            matches!(err, ConvertError::InsufficientTakes { honba: 3, .. }),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn cancel() {
+        let log = Log::from_json_str(SAMPLE).unwrap();
+        let token = CancelToken::new();
+        let mut options = ConvertOptions {
+            cancel: Some(token.clone()),
+            ..Default::default()
+        };
+        tenhou_to_mjai_with_options(&log, &options).unwrap();
+
+        token.cancel();
+        for parallel_kyokus in [false, true] {
+            options.parallel_kyokus = parallel_kyokus;
+            let err = tenhou_to_mjai_with_options(&log, &options).unwrap_err();
+            assert!(matches!(err, ConvertError::Cancelled), "{err}");
+            let (_, skipped) = tenhou_to_mjai_skipping_invalid(&log, &options);
+            assert_eq!(skipped.len(), log.kyokus.len());
+        }
+    }
+
+    #[test]
+    fn haipai_order() {
+        let mut log = Log::from_json_str(SAMPLE).unwrap();
+        log.kyokus[0].action_tables[0].haipai.reverse();
+        let tehai = |options: &ConvertOptions| match &tenhou_to_mjai_with_options(&log, options)
+            .unwrap()[1]
+        {
+            Event::StartKyoku { tehais, .. } => tehais[0].clone(),
+            _ => unreachable!(),
+        };
+
+        let preserved = tehai(&ConvertOptions::default());
+        assert!(preserved.windows(2).all(|w| w[0] >= w[1]));
+        assert_ne!(preserved.first(), preserved.last());
+        let options = ConvertOptions {
+            haipai: "sort".parse().unwrap(),
+            ..Default::default()
+        };
+        let sorted = tehai(&options);
+        assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+
+        let events = tenhou_to_mjai_with_options(&Fixture::Hanchan.log(), &options).unwrap();
+        let tehai = events
+            .iter()
+            .find_map(|event| match event {
+                Event::StartKyoku { tehais, .. } if tehais[0].contains(&t!(5pr)) => {
+                    Some(&tehais[0])
+                }
+                _ => None,
+            })
+            .unwrap();
+        let aka = tehai.iter().position(|&tile| tile == t!(5pr)).unwrap();
+        assert_eq!(
+            (tehai[aka - 1], tehai[aka + 1]),
+            (t!(5p), t!(6p)),
+            "{tehai:?}"
+        );
+    }
+
+    #[test]
+    fn aka_round_trip() {
+        let with = |fixture: Fixture, aka| {
+            let options = ConvertOptions {
+                aka,
+                ..Default::default()
+            };
+            tenhou_to_mjai_with_options(&fixture.log(), &options).unwrap()
+        };
+        let count_aka = |events: &[Event]| {
+            let mut count = 0;
+            for ev in events {
+                ev.clone().map_tiles(|t| {
+                    count += usize::from(t.is_aka());
+                    t
+                });
+            }
+            count
+        };
+        for fixture in Fixture::ALL {
+            let kept = with(fixture, AkaNormalization::Keep);
+            let deaka = with(fixture, AkaNormalization::Deaka);
+            assert_eq!(count_aka(&deaka), 0, "{fixture:?}");
+
+            let mut akaized = deaka.clone();
+            akaize(&mut akaized);
+            if count_aka(&kept) > 0 {
+                // The log already has its aka dora.
+                assert_eq!(with(fixture, AkaNormalization::Akaize), kept);
+            } else {
+                assert_eq!(with(fixture, AkaNormalization::Akaize)[1..], akaized[1..]);
+            }
+            crate::validate::validate_mjai(&akaized).unwrap();
+            assert!(count_aka(&akaized) > 0, "{fixture:?}");
+            for kyoku in crate::replay::split_kyokus(&akaized) {
+                for aka in [t!(5pr), t!(5sr)] {
+                    let (mut seen, mut plain) = (false, false);
+                    for ev in kyoku {
+                        ev.clone().map_tiles(|t| {
+                            seen |= t == aka;
+                            plain |= !seen && t == aka.deaka();
+                            t
+                        });
+                    }
+                    assert!(!plain, "{fixture:?}: a plain five before the aka");
+                }
+            }
+
+            for ev in &mut akaized {
+                ev.map_tiles(Tile::deaka);
+            }
+            assert_eq!(akaized, deaka, "{fixture:?}");
+        }
+    }
+
+    #[test]
+    fn kans() {
+        let ankan = Kan::parse(0, "424242a42").unwrap().unwrap();
+        assert_eq!(
+            ankan,
+            Kan::Ankan {
+                consumed: [t!(S); 4]
+            }
+        );
+        assert_eq!((ankan.kind(), ankan.target()), (MeldKind::Ankan, None));
+        let kakan = Kan::parse(0, "41k414141").unwrap().unwrap();
+        assert_eq!(
+            (kakan.kind(), kakan.target(), kakan.pai()),
+            (MeldKind::Kakan, Some(2), t!(E))
+        );
+        let daiminkan = Kan::parse(1, "26m262626").unwrap().unwrap();
+        assert_eq!(
+            (daiminkan.kind(), daiminkan.target(), daiminkan.pai()),
+            (MeldKind::Daiminkan, Some(3), t!(6p))
+        );
+        assert_eq!(
+            Kan::parse(0, "393939m39").unwrap().unwrap().target(),
+            Some(1)
+        );
+        assert_eq!(Kan::parse(0, "p252525").unwrap(), None);
+        let pon = Pon::parse(1, "52p2525").unwrap().unwrap();
+        assert_eq!(
+            (pon.target, pon.pai, pon.consumed),
+            (3, t!(5p), [t!(5pr), t!(5p)])
+        );
+        assert_eq!(Pon::parse(2, "p525252").unwrap().unwrap().pai, t!(5pr));
+        assert_eq!(Pon::parse(0, "3737p37").unwrap().unwrap().target, 1);
+        Pon::parse(0, "37p37").unwrap_err();
+        Kan::parse(0, "4242a4242").unwrap_err();
+        Kan::parse(0, "26m2626").unwrap_err();
+
+        let log = Fixture::KanHeavy.log();
+        for kyoku in &log.kyokus {
+            let kans = (0..3)
+                .flat_map(|actor| {
+                    let table = &kyoku.action_tables[actor as usize];
+                    table
+                        .takes
+                        .iter()
+                        .chain(&table.discards)
+                        .filter_map(move |item| match item {
+                            ActionItem::Naki(naki) => Kan::parse(actor, naki).unwrap(),
+                            _ => None,
+                        })
+                })
+                .count();
+            assert_eq!(kans, kyoku.kan_count());
+        }
+    }
+
+    #[test]
+    fn tsumogiri_tiles() {
+        let fixtures = [
+            Fixture::Hanchan,
+            Fixture::MultiRon,
+            Fixture::KanHeavy,
+            Fixture::AllLastRenchan,
+        ];
+        let mut tsumogiri = 0;
+        for fixture in fixtures {
+            let log = Log::from_json_str(fixture.json()).unwrap();
+            for ev in tenhou_to_mjai(&log).unwrap() {
+                if let Event::Dahai {
+                    pai, tsumogiri: t, ..
+                } = ev
+                {
+                    assert_ne!(pai, t!(?), "{fixture:?}");
+                    tsumogiri += usize::from(t);
+                }
+            }
+        }
+        assert!(tsumogiri > 0);
+    }
+
+    #[test]
+    fn stick_deltas() {
+        let mut collected = 0;
+        for fixture in Fixture::ALL {
+            let events = tenhou_to_mjai(&fixture.log()).unwrap();
+            // Adding up every delta leads from the scores a kyoku starts with
+            // to the ones the next starts with.
+            let mut scores: Option<[Points; 4]> = None;
+            for event in &events {
+                let deltas = match event {
+                    Event::StartKyoku { scores: start, .. } => {
+                        if let Some(scores) = scores {
+                            assert_eq!(&scores, start, "{fixture:?}");
+                        }
+                        scores = Some(*start);
+                        continue;
+                    }
+                    Event::ReachAccepted { deltas, .. } | Event::Ryukyoku { deltas, .. } => deltas,
+                    Event::Hora { deltas, sticks, .. } => {
+                        if let Some(sticks) = sticks {
+                            collected += sticks.iter().sum::<Points>();
+                        }
+                        deltas
+                    }
+                    _ => continue,
+                };
+                let scores = scores.as_mut().unwrap();
+                for (score, delta) in scores.iter_mut().zip(deltas.unwrap()) {
+                    *score += delta;
+                }
+            }
+        }
+        assert!(collected > 0);
+    }
+
+    #[test]
+    fn end_game() {
+        let mut log = Log::from_json_str(SAMPLE).unwrap();
+        let events = tenhou_to_mjai(&log).unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&Event::EndGame {
+                scores: Some([48100, 30000, 26900, 0]),
+                ranks: Some([1, 2, 3, 0]),
+                final_points: Some([43100, -10000, -33100, 0]),
+            }),
+        );
+
+        // Without `sc` the scores come from the last kyoku.
+        log.final_results = None;
+        let events = tenhou_to_mjai(&log).unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&Event::EndGame {
+                scores: Some([48100, 30000, 26900, 0]),
+                ranks: Some([1, 2, 3, 0]),
+                final_points: None,
+            }),
+        );
+
+        // Neither a game cut off nor a repaired one has results.
+        let no_results = Some(&Event::EndGame {
+            scores: None,
+            ranks: None,
+            final_points: None,
+        });
+        let mut cut = log.clone();
+        cut.kyokus.truncate(2);
+        assert_eq!(tenhou_to_mjai(&cut).unwrap().last(), no_results);
+        let mut repaired = log.clone();
+        repaired.repair = Some(Repair { dropped_kyokus: 1 });
+        assert_eq!(tenhou_to_mjai(&repaired).unwrap().last(), no_results);
+
+        let last = log.kyokus.len() - 1;
+        log.kyokus[last].scoreboard[..3].fill(Points::MAX);
+        let err = tenhou_to_mjai(&log).unwrap_err();
+        assert!(matches!(err, ConvertError::ScoreOverflow { .. }), "{err}");
+        let (events, skipped) = tenhou_to_mjai_skipping_invalid(&log, &ConvertOptions::default());
+        assert_eq!(events.last(), no_results);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].index, last);
+        assert_eq!(skipped[0].error.code(), "convert.score_overflow");
+    }
+
+    #[test]
+    fn ryukyokus() {
+        let json_str = r#"{"log":[[[0,0,0],[35000,35000,35000,0],[47],[],[],[],[],[],[],[],[],[],[],[],[],[],["流局",[-1000,2000,-1000,0]]],[[1,0,0],[35000,35000,35000,0],[47],[],[],[],[],[],[],[],[],[],[],[],[],[],["九種九牌"]]],"name":["","","",""]}"#;
+        let log = Log::from_json_str(json_str).unwrap();
+        let ryukyokus: Vec<_> = log
+            .kyokus
+            .iter()
+            .map(|kyoku| {
+                let mut events = vec![];
+                end_kyoku(&mut events, kyoku);
+                events.remove(0)
+            })
+            .collect();
+        assert_eq!(
+            ryukyokus,
+            [
+                Event::Ryukyoku {
+                    reason: Some("fanpai".to_owned()),
+                    deltas: Some([-1000, 2000, -1000, 0]),
+                    tenpais: Some([false, true, false, false]),
+                    kyotaku: Some(0),
+                },
+                Event::Ryukyoku {
+                    reason: Some("kyushukyuhai".to_owned()),
+                    deltas: Some([0; 4]),
+                    tenpais: None,
+                    kyotaku: Some(0),
+                },
+            ],
+        );
+
+        // An unknown status still ends the kyoku with its deltas.
+        let log = Log::from_json_str(
+            &json_str.replace(r#"["九種九牌"]"#, r#"["途中流局",[0,1000,-1000,0]]"#),
+        )
+        .unwrap();
+        let kyoku = &log.kyokus[1];
+        assert!(matches!(
+            kyoku.end_status,
+            EndStatus::Ryukyoku {
+                reason: RyukyokuReason::Unknown(ref s),
+                ..
+            } if s == "途中流局"
+        ));
+        let mut events = vec![];
+        end_kyoku(&mut events, kyoku);
+        assert_eq!(
+            events[0],
+            Event::Ryukyoku {
+                reason: None,
+                deltas: Some([0, 1000, -1000, 0]),
+                tenpais: None,
+                kyotaku: Some(0),
+            }
+        );
+    }
+
+    #[test]
+    fn skip_invalid() {
+        let log = broken_from_kyoku_3();
+        let (events, skipped) = tenhou_to_mjai_skipping_invalid(&log, &ConvertOptions::default());
+
+        let indices: Vec<_> = skipped.iter().map(|s| s.index).collect();
+        assert_eq!(indices, (3..log.kyokus.len()).collect::<Vec<_>>());
+        assert_eq!(skipped[0].honba, 3);
+        assert!(matches!(
+            skipped[0].error,
+            ConvertError::InsufficientTakes { actor: 0, .. }
+        ));
+
+        let start_kyokus = events
+            .iter()
+            .filter(|ev| matches!(ev, Event::StartKyoku { .. }))
+            .count();
+        assert_eq!(start_kyokus, 3);
+        assert!(matches!(events.last(), Some(Event::EndGame { .. })));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn spans() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Mutex;
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        /// Collects the names of the spans opened.
+        #[derive(Default)]
+        struct Spans {
+            names: Mutex<Vec<&'static str>>,
+            next_id: AtomicU64,
+        }
+
+        impl tracing::Subscriber for Spans {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                self.names.lock().unwrap().push(span.metadata().name());
+                Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let spans = std::sync::Arc::new(Spans::default());
+        tracing::subscriber::with_default(spans.clone(), || {
+            let log = Log::from_json_str(SAMPLE).unwrap();
+            tenhou_to_mjai(&log).unwrap();
+        });
+        let names = spans.names.lock().unwrap();
+        assert_eq!(names[..2], ["parse", "convert"]);
+        assert_eq!(names.iter().filter(|&&n| n == "kyoku").count(), 11);
+    }
 }
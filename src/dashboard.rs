@@ -0,0 +1,294 @@
+//! A single self-contained HTML page summarizing a corpus, with tables and
+//! simple charts, to share results without a server.
+
+use crate::mjai::Event;
+use crate::ruleset::Ruleset;
+use crate::stats::{CallStats, Leaderboard};
+use crate::tenhou::{EndStatus, Log, RuleError};
+use std::fmt::Write;
+
+/// The totals over a corpus that a [`Dashboard`] and a
+/// [`Report`](crate::report::Report) both show, gathered log by log.
+#[derive(Debug, Clone)]
+pub(crate) struct Totals {
+    pub(crate) leaderboard: Leaderboard,
+    pub(crate) calls: CallStats,
+    pub(crate) games: u32,
+    pub(crate) kyokus: u32,
+    pub(crate) tsumos: u32,
+    pub(crate) rons: u32,
+    pub(crate) ryukyokus: u32,
+}
+
+impl Totals {
+    pub(crate) fn new(ruleset: Ruleset) -> Self {
+        Self {
+            leaderboard: Leaderboard::new(ruleset),
+            calls: CallStats::new(),
+            games: 0,
+            kyokus: 0,
+            tsumos: 0,
+            rons: 0,
+            ryukyokus: 0,
+        }
+    }
+
+    pub(crate) fn add(&mut self, log: &Log, events: &[Event]) -> Result<(), RuleError> {
+        self.leaderboard.add(log)?;
+        self.calls.add(log, events);
+        self.games += 1;
+        for kyoku in &log.kyokus {
+            self.kyokus += 1;
+            match &kyoku.end_status {
+                EndStatus::Hora { details } => {
+                    for detail in details {
+                        if detail.is_tsumo() {
+                            self.tsumos += 1;
+                        } else {
+                            self.rons += 1;
+                        }
+                    }
+                }
+                EndStatus::Ryukyoku { .. } => self.ryukyokus += 1,
+            }
+        }
+        Ok(())
+    }
+
+    /// The line under the title, e.g. `3 games, 31 kyokus, placement points
+    /// by tenhou-sanma.`
+    pub(crate) fn summary(&self) -> String {
+        format!(
+            "{} games, {} kyokus, placement points by {}.",
+            self.games,
+            self.kyokus,
+            self.leaderboard.ruleset(),
+        )
+    }
+}
+
+/// The statistics a dashboard shows, gathered log by log.
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    /// The player the page is about, if any; the others only show up in the
+    /// tables.
+    player: Option<String>,
+    totals: Totals,
+}
+
+impl Dashboard {
+    #[must_use]
+    pub fn new(ruleset: Ruleset, player: Option<String>) -> Self {
+        Self {
+            player,
+            totals: Totals::new(ruleset),
+        }
+    }
+
+    /// Count `log` and `events` converted from it.
+    ///
+    /// # Errors
+    ///
+    /// Fails for logs whose final scores overflow, see [`Log::final_scores`].
+    pub fn add(&mut self, log: &Log, events: &[Event]) -> Result<(), RuleError> {
+        self.totals.add(log, events)
+    }
+
+    /// Render the page, styles and charts inline so that it needs nothing
+    /// but a browser.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let title = self.player.as_deref().unwrap_or("convlog statistics");
+
+        let mut out = String::new();
+        out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        let _ = writeln!(out, "<title>{}</title>", escape(title));
+        out.push_str(STYLE);
+        out.push_str("</head>\n<body>\n");
+        let _ = writeln!(out, "<h1>{}</h1>", escape(title));
+        let _ = writeln!(out, "<p>{}</p>", self.totals.summary());
+
+        if let Some(player) = &self.player {
+            self.render_player(&mut out, player);
+        }
+
+        out.push_str("<h2>Results</h2>\n");
+        bar_chart(
+            &mut out,
+            &[
+                ("Tsumo", f64::from(self.totals.tsumos)),
+                ("Ron", f64::from(self.totals.rons)),
+                ("Ryukyoku", f64::from(self.totals.ryukyokus)),
+            ],
+        );
+
+        self.render_leaderboard(&mut out);
+        self.render_calls(&mut out);
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+
+    fn render_player(&self, out: &mut String, player: &str) {
+        let Some(standing) = self.totals.leaderboard.get(player) else {
+            let _ = writeln!(out, "<p>{} played none of these games.</p>", escape(player));
+            return;
+        };
+        out.push_str("<h2>Placements</h2>\n");
+        let [first, second, third] = standing.placements.map(f64::from);
+        bar_chart(out, &[("1st", first), ("2nd", second), ("3rd", third)]);
+        let _ = writeln!(
+            out,
+            "<p>Average placement {:.2}, {:.1} points over {} games.</p>",
+            standing.average_placement().unwrap_or_default(),
+            standing.placement_points(),
+            standing.games,
+        );
+    }
+
+    fn render_leaderboard(&self, out: &mut String) {
+        out.push_str("<h2>Leaderboard</h2>\n<table>\n");
+        out.push_str(
+            "<tr><th>Rank</th><th>Name</th><th>Games</th><th>1st</th><th>2nd</th>\
+             <th>3rd</th><th>Avg. placement</th><th>Points</th></tr>\n",
+        );
+        for (rank, (name, standing)) in (1..).zip(self.totals.leaderboard.ranked()) {
+            let [first, second, third] = standing.placements;
+            let _ = writeln!(
+                out,
+                "<tr{}><td>{rank}</td><td>{}</td><td>{}</td><td>{first}</td><td>{second}</td>\
+                 <td>{third}</td><td>{:.2}</td><td>{:.1}</td></tr>",
+                self.highlight(name),
+                escape(name),
+                standing.games,
+                standing.average_placement().unwrap_or_default(),
+                standing.placement_points(),
+            );
+        }
+        out.push_str("</table>\n");
+    }
+
+    fn render_calls(&self, out: &mut String) {
+        out.push_str("<h2>Calls</h2>\n<table>\n");
+        out.push_str(
+            "<tr><th>Name</th><th>Kyokus</th><th>Calls per kyoku</th><th>Kitas</th>\
+             <th>Open win rate</th><th>Closed win rate</th></tr>\n",
+        );
+        let rate = |rate: Option<f64>| rate.map_or_else(|| "-".to_owned(), percent);
+        for (name, calls) in self.totals.calls.iter() {
+            let _ = writeln!(
+                out,
+                "<tr{}><td>{}</td><td>{}</td><td>{:.2}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                self.highlight(name),
+                escape(name),
+                calls.kyokus,
+                calls.calls_per_kyoku(),
+                calls.kitas,
+                rate(calls.open_win_rate()),
+                rate(calls.closed_win_rate()),
+            );
+        }
+        out.push_str("</table>\n");
+    }
+
+    /// The attribute marking the row of the player the page is about.
+    fn highlight(&self, name: &str) -> &'static str {
+        if self.player.as_deref() == Some(name) {
+            r#" class="player""#
+        } else {
+            ""
+        }
+    }
+}
+
+const STYLE: &str = "<style>
+body { font-family: sans-serif; max-width: 60em; margin: 2em auto; padding: 0 1em; }
+table { border-collapse: collapse; }
+th, td { padding: 0.2em 0.8em; border-bottom: 1px solid #ddd; text-align: right; }
+td:nth-child(2), th:nth-child(2) { text-align: left; }
+tr.player { font-weight: bold; background: #fff6d5; }
+svg text { font-size: 12px; }
+</style>
+";
+
+/// Width of the bars of the longest value in a chart, in pixels.
+const BAR_WIDTH: f64 = 400.0;
+
+/// Write an SVG with a labelled horizontal bar per value.
+fn bar_chart(out: &mut String, bars: &[(&str, f64)]) {
+    let max = bars.iter().map(|&(_, v)| v).fold(0.0, f64::max);
+    let height = bars.len() * 24;
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{height}">"#,
+        BAR_WIDTH + 160.0,
+    );
+    for (i, &(label, value)) in bars.iter().enumerate() {
+        let y = i * 24;
+        let width = if max > 0.0 {
+            value / max * BAR_WIDTH
+        } else {
+            0.0
+        };
+        let _ = writeln!(
+            out,
+            "<text x=\"0\" y=\"{}\">{}</text>\
+             <rect x=\"80\" y=\"{}\" width=\"{width:.1}\" height=\"18\" fill=\"#4a7bb7\"/>\
+             <text x=\"{:.1}\" y=\"{}\">{value}</text>",
+            y + 14,
+            escape(label),
+            y + 2,
+            width + 86.0,
+            y + 14,
+        );
+    }
+    out.push_str("</svg>\n");
+}
+
+fn percent(rate: f64) -> String {
+    format!("{:.1}%", rate * 100.0)
+}
+
+/// Escape `s` for use in HTML text and attributes.
+pub(crate) fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn render() {
+        let log = Fixture::Hanchan.log();
+        let events = tenhou_to_mjai(&log).unwrap();
+        let mut dashboard = Dashboard::new(Ruleset::TENHOU_SANMA, Some("mtk".to_owned()));
+        dashboard.add(&log, &events).unwrap();
+        let html = dashboard.render();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h1>mtk</h1>"));
+        assert!(html.contains("1 games, 11 kyokus"));
+        assert!(html.contains(r#"<tr class="player"><td>1</td><td>mtk</td>"#));
+        assert!(html.contains("Average placement 1.00, 43.1 points"));
+        // Nothing is loaded from elsewhere.
+        assert!(!html.contains("src="));
+        assert!(!html.contains("<link"));
+
+        assert_eq!(
+            escape(r#"<a href="x">&"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;"
+        );
+    }
+}
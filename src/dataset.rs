@@ -0,0 +1,836 @@
+//! Datasets for training models on games, one row per decision, e.g. a
+//! discard model learning which tile was discarded from the hand and table
+//! before it.
+//!
+//! Every row is labelled with how its kyoku ended for the decider, an
+//! [`Outcome`] and the points it won or lost, for rewards without joining
+//! rows with results.
+//!
+//! Rows are written as JSON lines or, with the `parquet` feature, as Parquet
+//! files with a row group per game, by a [`DatasetWriter`]. Their tiles are
+//! in MPSZ shorthand, e.g. `1z` for the east wind and `0p` for the aka five.
+
+use crate::defense::{suji, walls};
+use crate::hand::{mpsz, shanten};
+use crate::replay::{self, turn_order, KyokuState, PlayerState};
+use crate::wall::KINDS;
+use crate::{t, Event, Points, Tile};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// A discard and what the discarder knew when making it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiscardRow {
+    /// The game the discard is from, e.g. its ID.
+    pub game: String,
+    /// The round wind, e.g. `1z`.
+    pub bakaze: String,
+    /// Counts from 1.
+    pub kyoku: u8,
+    pub honba: u8,
+    pub seat: u8,
+    /// The turns of the discarder so far in the kyoku, counting from 1.
+    pub junme: u8,
+    /// The draws left in the live wall.
+    pub tiles_left: u8,
+    /// The concealed tiles before the discard, the one just drawn included,
+    /// in MPSZ shorthand.
+    pub hand: String,
+    /// The calls of the discarder in MPSZ shorthand, separated by spaces.
+    pub melds: String,
+    pub kitas: u8,
+    /// The dora indicators in MPSZ shorthand.
+    pub dora_markers: String,
+    /// The discarder is in riichi, its stick paid.
+    pub riichi: bool,
+    /// The seat after the discarder declared riichi.
+    pub shimocha_riichi: bool,
+    /// The seat before the discarder declared riichi.
+    pub kamicha_riichi: bool,
+    /// The tiles suji against the seat after the discarder, if it declared
+    /// riichi, in MPSZ shorthand.
+    pub shimocha_suji: String,
+    /// The tiles suji against the seat before the discarder, if it declared
+    /// riichi.
+    pub kamicha_suji: String,
+    /// The tiles the discarder sees walls in front of that no ryanmen can
+    /// wait on, see [`Wall`](crate::defense::Wall).
+    pub no_chance: String,
+    /// The tiles the discarder sees walls in front of that a ryanmen waits
+    /// on with one tile left at most.
+    pub one_chance: String,
+    /// The shanten of `hand`, `-1` for a complete one.
+    pub shanten: i8,
+    /// The tile discarded, the label of the row.
+    pub discard: String,
+    pub tsumogiri: bool,
+    /// The discard declares riichi.
+    pub declares_riichi: bool,
+    /// How the kyoku ended for the discarder.
+    pub outcome: Outcome,
+    /// The points the discarder won or lost in the kyoku, riichi sticks
+    /// included.
+    pub delta: Points,
+}
+
+/// How a kyoku ended for a seat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Won, by tsumo or ron.
+    Won,
+    /// Was ronned.
+    DealtIn,
+    /// Paid for the tsumo of another seat.
+    TsumoPaid,
+    /// Was tenpai when the wall ran out.
+    Tenpai,
+    /// Was not tenpai when the wall ran out.
+    Noten,
+    /// The kyoku was drawn by an abortive draw, e.g. by four riichi, or
+    /// ended without a result.
+    #[default]
+    Drawn,
+    /// Another seat won by ron off a third.
+    Uninvolved,
+}
+
+impl Outcome {
+    /// Its name in JSON, e.g. `dealt_in`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Won => "won",
+            Self::DealtIn => "dealt_in",
+            Self::TsumoPaid => "tsumo_paid",
+            Self::Tenpai => "tenpai",
+            Self::Noten => "noten",
+            Self::Drawn => "drawn",
+            Self::Uninvolved => "uninvolved",
+        }
+    }
+}
+
+impl FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Self::Won,
+            Self::DealtIn,
+            Self::TsumoPaid,
+            Self::Tenpai,
+            Self::Noten,
+            Self::Drawn,
+            Self::Uninvolved,
+        ]
+        .into_iter()
+        .find(|outcome| outcome.name() == s)
+        .ok_or_else(|| format!("unknown outcome {s:?}"))
+    }
+}
+
+/// The outcomes and deltas of the seats in a kyoku, as its results come.
+#[derive(Default)]
+pub(crate) struct Labels {
+    pub(crate) outcomes: [Outcome; 4],
+    pub(crate) deltas: [Points; 4],
+}
+
+impl Labels {
+    pub(crate) fn add(&mut self, event: &Event) {
+        let deltas = match *event {
+            Event::ReachAccepted { deltas, .. } => deltas,
+            Event::Hora {
+                actor,
+                target,
+                deltas,
+                ..
+            } => {
+                for (seat, outcome) in self.outcomes.iter_mut().enumerate() {
+                    let seat = seat as u8;
+                    *outcome = match *outcome {
+                        Outcome::Won => Outcome::Won,
+                        _ if seat == actor => Outcome::Won,
+                        // A seat ronned off by one and winning off the same
+                        // discard in a multi ron is impossible, so the first
+                        // match stands.
+                        Outcome::DealtIn => Outcome::DealtIn,
+                        _ if actor == target => Outcome::TsumoPaid,
+                        _ if seat == target => Outcome::DealtIn,
+                        _ => Outcome::Uninvolved,
+                    };
+                }
+                deltas
+            }
+            Event::Ryukyoku {
+                deltas, tenpais, ..
+            } => {
+                if let Some(tenpais) = tenpais {
+                    for (outcome, tenpai) in self.outcomes.iter_mut().zip(tenpais) {
+                        *outcome = if tenpai {
+                            Outcome::Tenpai
+                        } else {
+                            Outcome::Noten
+                        };
+                    }
+                }
+                deltas
+            }
+            _ => None,
+        };
+        for (sum, delta) in self.deltas.iter_mut().zip(deltas.unwrap_or_default()) {
+            *sum += delta;
+        }
+    }
+
+    /// Label `rows`, those of the kyoku.
+    fn apply(&self, rows: &mut [DiscardRow]) {
+        for row in rows {
+            row.outcome = self.outcomes[usize::from(row.seat)];
+            row.delta = self.deltas[usize::from(row.seat)];
+        }
+    }
+}
+
+/// A row for every discard of `events`, the mjai events of a sanma game,
+/// with `game` as its [`DiscardRow::game`].
+///
+/// # Errors
+///
+/// Fails for events that cannot be replayed.
+pub fn discard_rows(game: &str, events: &[Event]) -> replay::Result<Vec<DiscardRow>> {
+    let turns = turn_order(events);
+    let mut state = KyokuState::default();
+    let mut rows = vec![];
+    let mut kyoku_start = 0;
+    let mut labels = Labels::default();
+    for (index, event) in events.iter().enumerate() {
+        if let Event::StartKyoku { .. } = event {
+            labels.apply(&mut rows[kyoku_start..]);
+            kyoku_start = rows.len();
+            labels = Labels::default();
+        }
+        labels.add(event);
+        if let (
+            &Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            },
+            Some(turn),
+        ) = (event, turns[index])
+        {
+            let seat = usize::from(actor);
+            let player = &state.players[seat];
+            let melds: Vec<_> = player.melds.iter().map(|meld| mpsz(&meld.tiles)).collect();
+            let (shimocha, kamicha) = ((actor + 1) % 3, (actor + 2) % 3);
+            let riichi_suji = |seat: u8| {
+                if state.players[usize::from(seat)].riichi_declared {
+                    mpsz(&suji(&state, seat))
+                } else {
+                    String::new()
+                }
+            };
+            let (no_chance, one_chance) = walls(&state, actor);
+            rows.push(DiscardRow {
+                game: game.to_owned(),
+                bakaze: mpsz(&[state.bakaze]),
+                kyoku: state.kyoku,
+                honba: state.honba,
+                seat: actor,
+                junme: turn.junme,
+                tiles_left: state.wall,
+                hand: mpsz(&player.tehai),
+                melds: melds.join(" "),
+                kitas: player.kitas,
+                dora_markers: mpsz(&state.dora_markers),
+                riichi: player.riichi,
+                shimocha_riichi: state.players[usize::from(shimocha)].riichi_declared,
+                kamicha_riichi: state.players[usize::from(kamicha)].riichi_declared,
+                shimocha_suji: riichi_suji(shimocha),
+                kamicha_suji: riichi_suji(kamicha),
+                no_chance: mpsz(&no_chance),
+                one_chance: mpsz(&one_chance),
+                shanten: shanten(&player.tehai),
+                discard: mpsz(&[pai]),
+                tsumogiri,
+                declares_riichi: player.riichi_declared && !player.riichi,
+                outcome: Outcome::default(),
+                delta: 0,
+            });
+        }
+        state.apply(index, event)?;
+    }
+    labels.apply(&mut rows[kyoku_start..]);
+    Ok(rows)
+}
+
+/// What a seat sees of its two opponents at one of its discards, for
+/// opponent models, e.g. one telling how close to tenpai a seat is.
+///
+/// Tiles are counted in arrays of [`KINDS`] by [`Tile::as_usize`] of the
+/// plain tile, aka or not, so that the rows feed any framework as they are.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OpponentRow {
+    /// The game the discard is from, e.g. its ID.
+    pub game: String,
+    /// The round wind, e.g. `1z`.
+    pub bakaze: String,
+    /// Counts from 1.
+    pub kyoku: u8,
+    pub honba: u8,
+    /// The seat discarding, whose view the row is of.
+    pub seat: u8,
+    /// The turns of `seat` so far in the kyoku, counting from 1.
+    pub junme: u8,
+    /// The draws left in the live wall.
+    pub tiles_left: u8,
+    /// The seat after `seat` and the seat before, in that order.
+    pub opponents: [OpponentFeatures; 2],
+    /// The tiles of every kind `seat` has not seen: not in its hand, any
+    /// river or meld, set aside as nukidora or a dora indicator.
+    pub unseen: [u8; KINDS],
+}
+
+/// The features of one opponent of an [`OpponentRow`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OpponentFeatures {
+    pub seat: u8,
+    /// Declared riichi, accepted or not.
+    pub riichi: bool,
+    /// The discards before the one declaring riichi, `None` without riichi.
+    pub riichi_after: Option<u8>,
+    /// The calls in MPSZ shorthand, separated by spaces, ankan included.
+    pub melds: String,
+    /// It called a tile of another seat.
+    pub open: bool,
+    pub kitas: u8,
+    /// The discards of every kind, those called by another seat included.
+    pub discards: [u8; KINDS],
+    pub tsumogiri: u8,
+    /// The kinds of its last discards from the hand, latest first, at most
+    /// [`RECENT_TEDASHI`] of them.
+    pub recent_tedashi: Vec<u8>,
+    /// The kinds safe against it, see [`PlayerState::safe_tiles`].
+    pub safe: [bool; KINDS],
+}
+
+/// How many of the latest discards from the hand [`OpponentFeatures`] keep.
+pub const RECENT_TEDASHI: usize = 6;
+
+/// A row for every discard of `events`, the mjai events of a sanma game,
+/// with what the discarder sees of its opponents, and `game` as its
+/// [`OpponentRow::game`].
+///
+/// # Errors
+///
+/// Fails for events that cannot be replayed.
+pub fn opponent_rows(game: &str, events: &[Event]) -> replay::Result<Vec<OpponentRow>> {
+    let turns = turn_order(events);
+    let mut state = KyokuState::default();
+    let mut rows = vec![];
+    for (index, event) in events.iter().enumerate() {
+        if let (&Event::Dahai { actor, .. }, Some(turn)) = (event, turns[index]) {
+            let opponent = |seat: u8| opponent_features(&state.players[usize::from(seat)], seat);
+            rows.push(OpponentRow {
+                game: game.to_owned(),
+                bakaze: mpsz(&[state.bakaze]),
+                kyoku: state.kyoku,
+                honba: state.honba,
+                seat: actor,
+                junme: turn.junme,
+                tiles_left: state.wall,
+                opponents: [opponent((actor + 1) % 3), opponent((actor + 2) % 3)],
+                unseen: unseen(&state, actor),
+            });
+        }
+        state.apply(index, event)?;
+    }
+    Ok(rows)
+}
+
+fn opponent_features(player: &PlayerState, seat: u8) -> OpponentFeatures {
+    let mut discards = [0; KINDS];
+    for discard in &player.river {
+        count(&mut discards, discard.pai);
+    }
+    let mut safe = [false; KINDS];
+    for tile in player.safe_tiles.iter().filter(|t| !t.is_unknown()) {
+        safe[tile.as_usize()] = true;
+    }
+    let melds: Vec<_> = player.melds.iter().map(|meld| mpsz(&meld.tiles)).collect();
+    OpponentFeatures {
+        seat,
+        riichi: player.riichi_declared,
+        riichi_after: player
+            .river
+            .iter()
+            .position(|d| d.riichi)
+            .map(|at| at as u8),
+        melds: melds.join(" "),
+        open: player.melds.iter().any(|meld| meld.target.is_some()),
+        kitas: player.kitas,
+        discards,
+        tsumogiri: player.river.iter().filter(|d| d.tsumogiri).count() as u8,
+        recent_tedashi: player
+            .river
+            .iter()
+            .rev()
+            .filter(|d| !d.tsumogiri && !d.pai.is_unknown())
+            .take(RECENT_TEDASHI)
+            .map(|d| d.pai.deaka().as_usize() as u8)
+            .collect(),
+        safe,
+    }
+}
+
+/// The tiles of every kind `seat` has not seen in `state`.
+fn unseen(state: &KyokuState, seat: u8) -> [u8; KINDS] {
+    let mut seen = [0; KINDS];
+    for &tile in &state.players[usize::from(seat)].tehai {
+        count(&mut seen, tile);
+    }
+    for player in &state.players {
+        // A called discard is counted with the meld that took it.
+        for discard in player.river.iter().filter(|d| !d.called) {
+            count(&mut seen, discard.pai);
+        }
+        for &tile in player.melds.iter().flat_map(|meld| &meld.tiles) {
+            count(&mut seen, tile);
+        }
+        for _ in 0..player.kitas {
+            count(&mut seen, t!(N));
+        }
+    }
+    for &tile in &state.dora_markers {
+        count(&mut seen, tile);
+    }
+    seen.map(|seen| 4_u8.saturating_sub(seen))
+}
+
+/// Count `tile` in `counts`, unless it is unknown.
+const fn count(counts: &mut [u8; KINDS], tile: Tile) {
+    if !tile.is_unknown() {
+        let kind = tile.deaka().as_usize();
+        counts[kind] = counts[kind].saturating_add(1);
+    }
+}
+
+/// What a dataset is written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DatasetFormat {
+    /// A JSON object per row, one per line.
+    #[default]
+    Jsonl,
+    /// A Parquet file, with the `parquet` feature.
+    Parquet,
+}
+
+impl FromStr for DatasetFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(Self::Jsonl),
+            "parquet" => Ok(Self::Parquet),
+            _ => Err(format!(
+                "unknown format {s:?}, expected \"jsonl\" or \"parquet\""
+            )),
+        }
+    }
+}
+
+/// What a row of a dataset is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DatasetRows {
+    /// A [`DiscardRow`] per discard.
+    #[default]
+    Discards,
+    /// An [`OpponentRow`] per discard, only written as JSON lines.
+    Opponents,
+}
+
+impl FromStr for DatasetRows {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "discards" => Ok(Self::Discards),
+            "opponents" => Ok(Self::Opponents),
+            _ => Err(format!(
+                "unknown rows {s:?}, expected \"discards\" or \"opponents\""
+            )),
+        }
+    }
+}
+
+/// Writes rows to a dataset in a [`DatasetFormat`], a batch at a time.
+pub struct DatasetWriter<W: Write + Send> {
+    inner: Inner<W>,
+}
+
+enum Inner<W: Write + Send> {
+    Jsonl(W),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::file::writer::SerializedFileWriter<W>),
+}
+
+impl<W: Write + Send> DatasetWriter<W> {
+    /// # Errors
+    ///
+    /// Fails for [`DatasetFormat::Parquet`] without the `parquet` feature.
+    pub fn new(w: W, format: DatasetFormat) -> io::Result<Self> {
+        let inner = match format {
+            DatasetFormat::Jsonl => Inner::Jsonl(w),
+            #[cfg(feature = "parquet")]
+            DatasetFormat::Parquet => Inner::Parquet(parquet_writer::new(w)?),
+            #[cfg(not(feature = "parquet"))]
+            DatasetFormat::Parquet => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "parquet output needs the parquet feature",
+                ))
+            }
+        };
+        Ok(Self { inner })
+    }
+
+    /// Write `rows`, as a row group of their own in Parquet.
+    pub fn write(&mut self, rows: &[DiscardRow]) -> io::Result<()> {
+        match &mut self.inner {
+            Inner::Jsonl(w) => {
+                for row in rows {
+                    serde_json::to_writer(&mut *w, row)?;
+                    writeln!(w)?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "parquet")]
+            Inner::Parquet(writer) => parquet_writer::write(writer, rows),
+        }
+    }
+
+    /// Write `rows` as JSON lines.
+    ///
+    /// # Errors
+    ///
+    /// Fails for [`DatasetFormat::Parquet`], whose schema is that of
+    /// [`DiscardRow`].
+    pub fn write_opponents(&mut self, rows: &[OpponentRow]) -> io::Result<()> {
+        match &mut self.inner {
+            Inner::Jsonl(w) => {
+                for row in rows {
+                    serde_json::to_writer(&mut *w, row)?;
+                    writeln!(w)?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "parquet")]
+            Inner::Parquet(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "opponent rows are only written as JSON lines",
+            )),
+        }
+    }
+
+    /// Write what is left, e.g. the footer of a Parquet file, flush and
+    /// return the writer.
+    #[cfg_attr(
+        not(feature = "parquet"),
+        allow(clippy::infallible_destructuring_match)
+    )]
+    pub fn finish(self) -> io::Result<W> {
+        let mut w = match self.inner {
+            Inner::Jsonl(w) => w,
+            #[cfg(feature = "parquet")]
+            Inner::Parquet(writer) => writer.into_inner().map_err(io::Error::other)?,
+        };
+        w.flush()?;
+        Ok(w)
+    }
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_writer {
+    use super::DiscardRow;
+    use std::io::{self, Write};
+    use std::sync::Arc;
+
+    use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int32Type, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    /// The schema of [`DiscardRow`], its fields in order.
+    const SCHEMA: &str = "message discard {
+        required binary game (UTF8);
+        required binary bakaze (UTF8);
+        required int32 kyoku (INT_8);
+        required int32 honba (INT_8);
+        required int32 seat (INT_8);
+        required int32 junme (INT_8);
+        required int32 tiles_left (INT_8);
+        required binary hand (UTF8);
+        required binary melds (UTF8);
+        required int32 kitas (INT_8);
+        required binary dora_markers (UTF8);
+        required boolean riichi;
+        required boolean shimocha_riichi;
+        required boolean kamicha_riichi;
+        required binary shimocha_suji (UTF8);
+        required binary kamicha_suji (UTF8);
+        required binary no_chance (UTF8);
+        required binary one_chance (UTF8);
+        required int32 shanten (INT_8);
+        required binary discard (UTF8);
+        required boolean tsumogiri;
+        required boolean declares_riichi;
+        required binary outcome (UTF8);
+        required int64 delta;
+    }";
+
+    enum Column {
+        Int(Vec<i32>),
+        Long(Vec<i64>),
+        Bool(Vec<bool>),
+        Str(Vec<ByteArray>),
+    }
+
+    pub(super) fn new<W: Write + Send>(w: W) -> io::Result<SerializedFileWriter<W>> {
+        let schema = parse_message_type(SCHEMA).map_err(io::Error::other)?;
+        let props = WriterProperties::builder().build();
+        SerializedFileWriter::new(w, Arc::new(schema), Arc::new(props)).map_err(io::Error::other)
+    }
+
+    pub(super) fn write<W: Write + Send>(
+        writer: &mut SerializedFileWriter<W>,
+        rows: &[DiscardRow],
+    ) -> io::Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let int = |f: fn(&DiscardRow) -> i32| Column::Int(rows.iter().map(f).collect());
+        let bool = |f: fn(&DiscardRow) -> bool| Column::Bool(rows.iter().map(f).collect());
+        let str =
+            |f: fn(&DiscardRow) -> &str| Column::Str(rows.iter().map(|r| f(r).into()).collect());
+        let columns = [
+            str(|r| &r.game),
+            str(|r| &r.bakaze),
+            int(|r| r.kyoku.into()),
+            int(|r| r.honba.into()),
+            int(|r| r.seat.into()),
+            int(|r| r.junme.into()),
+            int(|r| r.tiles_left.into()),
+            str(|r| &r.hand),
+            str(|r| &r.melds),
+            int(|r| r.kitas.into()),
+            str(|r| &r.dora_markers),
+            bool(|r| r.riichi),
+            bool(|r| r.shimocha_riichi),
+            bool(|r| r.kamicha_riichi),
+            str(|r| &r.shimocha_suji),
+            str(|r| &r.kamicha_suji),
+            str(|r| &r.no_chance),
+            str(|r| &r.one_chance),
+            int(|r| r.shanten.into()),
+            str(|r| &r.discard),
+            bool(|r| r.tsumogiri),
+            bool(|r| r.declares_riichi),
+            str(|r| r.outcome.name()),
+            Column::Long(rows.iter().map(|r| r.delta).collect()),
+        ];
+
+        let mut group = writer.next_row_group().map_err(io::Error::other)?;
+        for column in columns {
+            let mut writer = group
+                .next_column()
+                .map_err(io::Error::other)?
+                .expect("a column per field of the schema");
+            let written = match column {
+                Column::Int(values) => writer.typed::<Int32Type>().write_batch(&values, None, None),
+                Column::Long(values) => {
+                    writer.typed::<Int64Type>().write_batch(&values, None, None)
+                }
+                Column::Bool(values) => writer.typed::<BoolType>().write_batch(&values, None, None),
+                Column::Str(values) => writer
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, None, None),
+            };
+            written.map_err(io::Error::other)?;
+            writer.close().map_err(io::Error::other)?;
+        }
+        group.close().map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{Adapter, Tenhou6};
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn discard_rows() {
+        let game = Tenhou6::default().read(Fixture::Hanchan.json()).unwrap();
+        let events = game.events;
+        let rows = super::discard_rows("game", &events).unwrap();
+        let dahais = events
+            .iter()
+            .filter(|e| matches!(e, Event::Dahai { .. }))
+            .count();
+        assert_eq!(rows.len(), dahais);
+
+        let first = &rows[0];
+        assert_eq!((first.kyoku, first.seat, first.junme), (1, 0, 1));
+        assert_eq!(first.hand.chars().filter(char::is_ascii_digit).count(), 14);
+        assert!(rows.iter().all(|r| (-1..=6).contains(&r.shanten)));
+        assert!(rows.iter().any(|r| r.declares_riichi));
+        let declared = rows.iter().position(|r| r.declares_riichi).unwrap();
+        let seat = rows[declared].seat;
+        let next = rows[declared + 1..]
+            .iter()
+            .find(|r| r.seat == (seat + 1) % 3);
+        assert!(next.is_none_or(|r| r.kamicha_riichi || r.kyoku != rows[declared].kyoku));
+        assert!(rows
+            .iter()
+            .all(|r| r.kamicha_riichi || r.kamicha_suji.is_empty()));
+        assert!(rows
+            .iter()
+            .any(|r| !r.kamicha_suji.is_empty() || !r.shimocha_suji.is_empty()));
+
+        let mut out = vec![];
+        let mut writer = DatasetWriter::new(&mut out, DatasetFormat::Jsonl).unwrap();
+        writer.write(&rows).unwrap();
+        writer.finish().unwrap();
+        let lines: Vec<_> = out
+            .split(|&b| b == b'\n')
+            .filter(|l| !l.is_empty())
+            .collect();
+        assert_eq!(lines.len(), rows.len());
+        let value: serde_json::Value = serde_json::from_slice(lines[0]).unwrap();
+        assert_eq!(value["hand"], first.hand);
+        assert_eq!(value["outcome"], first.outcome.name());
+    }
+
+    #[test]
+    fn outcomes() {
+        let game = Tenhou6::default().read(Fixture::MultiRon.json()).unwrap();
+        let rows = super::discard_rows("game", &game.events).unwrap();
+        let mut labels = Labels::default();
+        let mut kyoku = vec![];
+        for event in &game.events {
+            if let Event::StartKyoku { .. } = event {
+                labels = Labels::default();
+            }
+            labels.add(event);
+            if let Event::Hora { actor, target, .. } = *event {
+                if actor != target {
+                    kyoku.push((actor, target));
+                }
+            }
+            if matches!(event, Event::EndKyoku) {
+                if kyoku.len() == 2 {
+                    break;
+                }
+                kyoku.clear();
+            }
+        }
+        // Both winners of the multi ron won off the third seat.
+        let [(a, target), (b, _)] = kyoku[..] else {
+            panic!("no multi ron")
+        };
+        assert_eq!(labels.outcomes[usize::from(a)], Outcome::Won);
+        assert_eq!(labels.outcomes[usize::from(b)], Outcome::Won);
+        assert_eq!(labels.outcomes[usize::from(target)], Outcome::DealtIn);
+        assert!(labels.deltas[usize::from(target)] < 0);
+        assert_eq!(labels.deltas.iter().sum::<Points>(), 0);
+
+        // Every row of a kyoku shares the labels of its seat, which add up
+        // to the deltas of its results.
+        let game = Tenhou6::default().read(Fixture::Hanchan.json()).unwrap();
+        let rows_of_hanchan = super::discard_rows("game", &game.events).unwrap();
+        assert!(rows_of_hanchan
+            .iter()
+            .any(|r| r.outcome == Outcome::Won && r.delta > 0));
+        assert!(rows_of_hanchan
+            .iter()
+            .any(|r| r.outcome == Outcome::TsumoPaid && r.delta < 0));
+        for rows in [rows, rows_of_hanchan] {
+            for pair in rows.windows(2) {
+                let same_kyoku = (pair[0].bakaze.as_str(), pair[0].kyoku, pair[0].honba)
+                    == (pair[1].bakaze.as_str(), pair[1].kyoku, pair[1].honba);
+                if same_kyoku && pair[0].seat == pair[1].seat {
+                    assert_eq!(pair[0].outcome, pair[1].outcome);
+                    assert_eq!(pair[0].delta, pair[1].delta);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn opponent_rows() {
+        let game = Tenhou6::default().read(Fixture::Hanchan.json()).unwrap();
+        let rows = super::opponent_rows("game", &game.events).unwrap();
+        let discards = super::discard_rows("game", &game.events).unwrap();
+        assert_eq!(rows.len(), discards.len());
+
+        // Before any discard, all but the hand and the dora indicator.
+        let first = &rows[0];
+        assert_eq!(
+            first.unseen.iter().map(|&n| usize::from(n)).sum::<usize>(),
+            108 - 14 - 1
+        );
+        assert_eq!(first.opponents.each_ref().map(|o| o.seat), [1, 2]);
+        let second = &rows[1];
+        assert_eq!(second.opponents[1].discards.iter().sum::<u8>(), 1);
+        assert_eq!(second.opponents[1].safe.iter().filter(|&&s| s).count(), 1);
+
+        for (row, discard) in rows.iter().zip(&discards) {
+            assert_eq!((row.seat, row.junme), (discard.seat, discard.junme));
+            assert_eq!(row.opponents[0].riichi, discard.shimocha_riichi);
+            assert_eq!(row.opponents[1].riichi, discard.kamicha_riichi);
+            for opponent in &row.opponents {
+                assert_eq!(opponent.riichi, opponent.riichi_after.is_some());
+                assert!(opponent.recent_tedashi.len() <= RECENT_TEDASHI);
+                assert!(opponent.tsumogiri <= opponent.discards.iter().sum::<u8>());
+            }
+        }
+        assert!(rows.iter().any(|r| r.opponents.iter().any(|o| o.open)));
+
+        let mut out = vec![];
+        let mut writer = DatasetWriter::new(&mut out, DatasetFormat::Jsonl).unwrap();
+        writer.write_opponents(&rows).unwrap();
+        writer.finish().unwrap();
+        let line = out.split(|&b| b == b'\n').next().unwrap();
+        let value: serde_json::Value = serde_json::from_slice(line).unwrap();
+        assert_eq!(value["unseen"].as_array().unwrap().len(), KINDS);
+        assert_eq!(
+            value["opponents"][0]["riichi_after"],
+            serde_json::Value::Null
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let game = Tenhou6::default().read(Fixture::Hanchan.json()).unwrap();
+        let rows = super::discard_rows("game", &game.events).unwrap();
+        let file = tempfile::tempfile().unwrap();
+        let mut writer = DatasetWriter::new(file, DatasetFormat::Parquet).unwrap();
+        writer.write(&rows).unwrap();
+        writer.write(&rows[..10]).unwrap();
+        let file = writer.finish().unwrap();
+
+        let reader = SerializedFileReader::new(file).unwrap();
+        let meta = reader.metadata();
+        assert_eq!(meta.num_row_groups(), 2);
+        assert_eq!(meta.file_metadata().num_rows(), rows.len() as i64 + 10);
+        assert_eq!(meta.file_metadata().schema_descr().num_columns(), 24);
+    }
+}
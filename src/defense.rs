@@ -0,0 +1,227 @@
+//! Classic defensive features of a [`KyokuState`]: how tiles relate to the
+//! discards of a seat, genbutsu or suji, and which tiles walls of visible
+//! tiles (kabe) keep from being waited on with a ryanmen.
+//!
+//! Only pinzu and souzu have runs in sanma, so only they have suji and
+//! walls; 1m and 9m are never suji.
+
+use crate::replay::KyokuState;
+use crate::{t, Tile};
+
+/// How a tile relates to the discards of a seat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Relation {
+    /// The seat discarded it, or passed it after declaring riichi.
+    Genbutsu,
+    /// Every ryanmen that waits on it waits on a genbutsu too, e.g. 4p
+    /// against 1p and 7p.
+    Suji,
+    /// Neither.
+    Unrelated,
+}
+
+/// How a wall of visible tiles keeps a tile from being waited on with a
+/// ryanmen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wall {
+    /// Every ryanmen that waits on it needs a tile all four of which are
+    /// visible.
+    NoChance,
+    /// Every ryanmen that waits on it needs a tile only one of which is
+    /// left.
+    OneChance,
+}
+
+/// How `tile` relates to the discards of the seat `against`.
+#[must_use]
+pub fn relation(state: &KyokuState, against: u8, tile: Tile) -> Relation {
+    let safe = &state.players[usize::from(against)].safe_tiles;
+    let tile = tile.deaka();
+    if safe.binary_search(&tile).is_ok() {
+        return Relation::Genbutsu;
+    }
+    let Some((suit, number)) = suited(tile) else {
+        return Relation::Unrelated;
+    };
+    let safe = |number: u8| {
+        safe.binary_search(&Tile::try_from(suit + number).unwrap())
+            .is_ok()
+    };
+    let below = number < 3 || safe(number - 3);
+    let above = number > 5 || safe(number + 3);
+    if below && above {
+        Relation::Suji
+    } else {
+        Relation::Unrelated
+    }
+}
+
+/// The tiles suji against the seat `against` that are not its genbutsu,
+/// sorted and without aka.
+#[must_use]
+pub fn suji(state: &KyokuState, against: u8) -> Vec<Tile> {
+    suited_tiles()
+        .filter(|&tile| relation(state, against, tile) == Relation::Suji)
+        .collect()
+}
+
+/// How many of every tile the seat `seat` can see, by [`Tile::as_usize`]
+/// of the plain tile: those in its hand, in the rivers and melds of every
+/// seat, and the dora indicators.
+#[must_use]
+pub fn visible(state: &KyokuState, seat: u8) -> [u8; 27] {
+    let mut counts = [0_u8; 27];
+    let player = &state.players[usize::from(seat)];
+    let rivers = state
+        .players
+        .iter()
+        .flat_map(|p| &p.river)
+        .filter(|d| !d.called);
+    let melds = state
+        .players
+        .iter()
+        .flat_map(|p| &p.melds)
+        .flat_map(|m| &m.tiles);
+    let tiles = player
+        .tehai
+        .iter()
+        .chain(rivers.map(|d| &d.pai))
+        .chain(melds)
+        .chain(&state.dora_markers);
+    for tile in tiles.filter(|t| !t.is_unknown()) {
+        counts[tile.deaka().as_usize()] += 1;
+    }
+    counts
+}
+
+/// The wall that keeps `tile` from being waited on with a ryanmen, if any,
+/// given `visible`, the counts of [`visible`].
+#[must_use]
+pub fn wall(visible: &[u8; 27], tile: Tile) -> Option<Wall> {
+    let (suit, number) = suited(tile.deaka())?;
+    let left = |number: u8| 4 - visible[usize::from(suit + number)].min(4);
+    // The ryanmen waiting on the tile: the two above it and the two below,
+    // 0-based, neither of them a terminal.
+    let below = number.checked_sub(2).map(|low| (low, low + 1));
+    let chances = [Some((number + 1, number + 2)), below]
+        .into_iter()
+        .flatten()
+        .filter(|&(low, high)| low >= 1 && high <= 7)
+        .map(|(low, high)| left(low).min(left(high)));
+    match chances.max()? {
+        0 => Some(Wall::NoChance),
+        1 => Some(Wall::OneChance),
+        _ => None,
+    }
+}
+
+/// The tiles the seat `seat` sees walls in front of, by [`Wall`], sorted
+/// and without aka.
+#[must_use]
+pub fn walls(state: &KyokuState, seat: u8) -> (Vec<Tile>, Vec<Tile>) {
+    let visible = visible(state, seat);
+    let (mut no_chance, mut one_chance) = (vec![], vec![]);
+    for tile in suited_tiles() {
+        match wall(&visible, tile) {
+            Some(Wall::NoChance) => no_chance.push(tile),
+            Some(Wall::OneChance) => one_chance.push(tile),
+            None => (),
+        }
+    }
+    (no_chance, one_chance)
+}
+
+/// The ID of the 1 of the suit of `tile` and its 0-based number, for pinzu
+/// and souzu.
+fn suited(tile: Tile) -> Option<(u8, u8)> {
+    (tile.as_u8() <= t!(9s).as_u8()).then(|| (tile.as_u8() / 9 * 9, tile.as_u8() % 9))
+}
+
+/// 1p to 9s.
+fn suited_tiles() -> impl Iterator<Item = Tile> {
+    (t!(1p).as_u8()..=t!(9s).as_u8()).filter_map(|id| Tile::try_from(id).ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Event;
+
+    fn state(events: &[Event]) -> KyokuState {
+        let mut state = KyokuState::default();
+        for (index, event) in events.iter().enumerate() {
+            state.apply(index, event).unwrap();
+        }
+        state
+    }
+
+    fn start(tehais: [Vec<Tile>; 4]) -> Event {
+        Event::StartKyoku {
+            bakaze: t!(E),
+            dora_marker: t!(N),
+            kyoku: 1,
+            honba: 0,
+            kyotaku: 0,
+            oya: 0,
+            scores: [35000, 35000, 35000, 0],
+            tehais,
+        }
+    }
+
+    #[test]
+    fn suji() {
+        let tehais = [
+            t![1p, 4s, 7s, 1m, 9m, E, S, W, N, P, F, C, C].to_vec(),
+            t![2p, 2p, 3p, 3p, 4p, 4p, 5p, 5p, 6p, 6p, 7p, 7p, 8p].to_vec(),
+            t![2s, 2s, 3s, 3s, 4s, 4s, 5s, 5s, 6s, 6s, 7s, 7s, 8s].to_vec(),
+            vec![],
+        ];
+        let state = state(&[
+            start(tehais),
+            Event::Tsumo {
+                actor: 0,
+                pai: t!(1s),
+            },
+            Event::Dahai {
+                actor: 0,
+                pai: t!(4s),
+                tsumogiri: false,
+            },
+            Event::Tsumo {
+                actor: 1,
+                pai: t!(8p),
+            },
+            Event::Dahai {
+                actor: 1,
+                pai: t!(8p),
+                tsumogiri: true,
+            },
+        ]);
+        assert_eq!(relation(&state, 0, t!(4s)), Relation::Genbutsu);
+        assert_eq!(relation(&state, 0, t!(1s)), Relation::Suji);
+        assert_eq!(relation(&state, 0, t!(7s)), Relation::Suji);
+        assert_eq!(relation(&state, 0, t!(5s)), Relation::Unrelated);
+        assert_eq!(relation(&state, 0, t!(1m)), Relation::Unrelated);
+        assert_eq!(super::suji(&state, 0), [t!(1s), t!(7s)]);
+        // 5p is only half suji, 2p not being discarded.
+        assert_eq!(relation(&state, 1, t!(5pr)), Relation::Unrelated);
+        assert!(super::suji(&state, 1).is_empty());
+    }
+
+    #[test]
+    fn walls() {
+        let tehais = [
+            t![3p, 3p, 3p, 3p, 7s, 7s, 7s, E, S, W, N, P, F].to_vec(),
+            t![1p, 1p, 1p, 1p, 9p, 9p, 9p, 9p, 1s, 1s, 1s, 1s, C].to_vec(),
+            t![2p, 2p, 2p, 2p, 9s, 9s, 9s, 9s, 5s, 5s, 5s, 5s, C].to_vec(),
+            vec![],
+        ];
+        let state = state(&[start(tehais)]);
+        let (no_chance, one_chance) = super::walls(&state, 0);
+        // 3p is a wall for 1p and 2p, 7s keeps one chance for 8s and 9s.
+        assert_eq!(no_chance, [t!(1p), t!(2p)]);
+        assert_eq!(one_chance, [t!(8s), t!(9s)]);
+        // Other seats do not see the hand of the first.
+        assert_eq!(super::walls(&state, 1), (vec![], vec![]));
+    }
+}
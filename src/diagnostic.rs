@@ -0,0 +1,747 @@
+//! Errors of batch jobs as structured records, for scripts that run the
+//! command line tool and want more than a line of text per failure.
+
+use crate::conv::ConvertError;
+use crate::hand::HandError;
+use crate::replay::ReplayError;
+use crate::tenhou::{RuleError, ScoreComparison, TenhouParseError};
+use crate::validate::ValidateError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// How failures of a batch are written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// `file: message`, for humans.
+    #[default]
+    Text,
+    /// An [`ErrorRecord`] as JSON, one per line.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                r#"unknown error format {s:?}, expected "text" or "json""#
+            )),
+        }
+    }
+}
+
+impl ErrorFormat {
+    /// Write `err`, which happened on `file`, as a line in this format.
+    pub fn write<W: io::Write>(
+        self,
+        mut w: W,
+        file: &str,
+        err: &(dyn Error + 'static),
+    ) -> io::Result<()> {
+        match self {
+            Self::Text => {
+                writeln!(w, "{file}: {err}")?;
+                // Lines of their own, indented to read as part of the error.
+                if let Some(comparison) = score_comparison(err) {
+                    for line in comparison.to_string().lines() {
+                        writeln!(w, "  {line}")?;
+                    }
+                }
+                Ok(())
+            }
+            Self::Json => {
+                serde_json::to_writer(&mut w, &ErrorRecord::new(file, err))?;
+                writeln!(w)
+            }
+        }
+    }
+}
+
+/// The exit status of the command line tool by what failed, so that scripts
+/// can tell a broken log from a full disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// `0`, everything went through.
+    Success,
+    /// `1`, any failure not below.
+    Other,
+    /// `2`, the command line was wrong, as clap exits with.
+    Usage,
+    /// `3`, a log could not be read as a log.
+    Parse,
+    /// `4`, a log was read but failed to convert or to validate.
+    Invalid,
+    /// `5`, reading inputs or writing outputs failed.
+    Io,
+    /// `6`, a batch went on past failed logs, which it reported on stderr,
+    /// and converted the rest.
+    Partial,
+}
+
+impl ExitStatus {
+    /// The status to exit with after `err`, or after the error of a
+    /// [`FileError`].
+    #[must_use]
+    pub fn of(err: &(dyn Error + 'static)) -> Self {
+        if let Some(err) = err.downcast_ref::<FileError>() {
+            Self::of(&*err.source)
+        } else if let Some(err) = err.downcast_ref::<BatchFailed>() {
+            err.status
+        } else if err.is::<TenhouParseError>() {
+            Self::Parse
+        } else if err.is::<ConvertError>()
+            || err.is::<RuleError>()
+            || err.is::<HandError>()
+            || err.is::<ReplayError>()
+            || err.is::<ValidateError>()
+        {
+            Self::Invalid
+        } else if err.is::<io::Error>() {
+            Self::Io
+        } else {
+            Self::Other
+        }
+    }
+
+    #[must_use]
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Success => 0,
+            Self::Other => 1,
+            Self::Usage => 2,
+            Self::Parse => 3,
+            Self::Invalid => 4,
+            Self::Io => 5,
+            Self::Partial => 6,
+        }
+    }
+}
+
+/// A failure of the command line tool by the part of the crate it came
+/// from, for the exit status and the code of the failure.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CliError {
+    Parse(TenhouParseError),
+    Convert(ConvertError),
+    Rule(RuleError),
+    Hand(HandError),
+    Replay(ReplayError),
+    Validate(ValidateError),
+    Io(io::Error),
+    Batch(BatchFailed),
+    /// One of the others on a file.
+    File {
+        file: String,
+        error: Box<Self>,
+    },
+    /// Anything else, e.g. a wrong combination of options.
+    Other(Box<dyn Error>),
+}
+
+impl CliError {
+    /// A stable identifier of the kind of error as [`ErrorRecord::code`]
+    /// has it, or `batch` for a [`BatchFailed`].
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Parse(err) => err.code(),
+            Self::Convert(err) => err.code(),
+            Self::Rule(err) => rule(err).0,
+            Self::Hand(err) => hand(err).0,
+            Self::Replay(err) => replay(err).0,
+            Self::Validate(err) => validate(err).0,
+            Self::Io(_) => "io",
+            Self::Batch(_) => "batch",
+            Self::File { error, .. } => error.code(),
+            Self::Other(_) => "other",
+        }
+    }
+
+    #[must_use]
+    pub fn exit_status(&self) -> ExitStatus {
+        match self {
+            Self::Parse(_) => ExitStatus::Parse,
+            Self::Convert(_)
+            | Self::Rule(_)
+            | Self::Hand(_)
+            | Self::Replay(_)
+            | Self::Validate(_) => ExitStatus::Invalid,
+            Self::Io(_) => ExitStatus::Io,
+            Self::Batch(err) => err.status,
+            Self::File { error, .. } => error.exit_status(),
+            Self::Other(_) => ExitStatus::Other,
+        }
+    }
+
+    fn inner(&self) -> &(dyn Error + 'static) {
+        match self {
+            Self::Parse(err) => err,
+            Self::Convert(err) => err,
+            Self::Rule(err) => err,
+            Self::Hand(err) => err,
+            Self::Replay(err) => err,
+            Self::Validate(err) => err,
+            Self::Io(err) => err,
+            Self::Batch(err) => err,
+            Self::File { error, .. } => error.inner(),
+            Self::Other(err) => &**err,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Self::File { file, .. } = self {
+            write!(f, "{file}: ")?;
+        }
+        fmt::Display::fmt(self.inner(), f)
+    }
+}
+
+impl Error for CliError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.inner().source()
+    }
+}
+
+impl From<Box<dyn Error>> for CliError {
+    /// Sort `err` by its type, [`Self::Other`] for the types of other
+    /// crates but I/O.
+    fn from(err: Box<dyn Error>) -> Self {
+        let err = match err.downcast::<FileError>() {
+            Ok(err) => {
+                return Self::File {
+                    file: err.file,
+                    error: Box::new(err.source.into()),
+                }
+            }
+            Err(err) => err,
+        };
+        let err = match err.downcast() {
+            Ok(err) => return Self::Parse(*err),
+            Err(err) => err,
+        };
+        let err = match err.downcast() {
+            Ok(err) => return Self::Convert(*err),
+            Err(err) => err,
+        };
+        let err = match err.downcast() {
+            Ok(err) => return Self::Rule(*err),
+            Err(err) => err,
+        };
+        let err = match err.downcast() {
+            Ok(err) => return Self::Hand(*err),
+            Err(err) => err,
+        };
+        let err = match err.downcast() {
+            Ok(err) => return Self::Replay(*err),
+            Err(err) => err,
+        };
+        let err = match err.downcast() {
+            Ok(err) => return Self::Validate(*err),
+            Err(err) => err,
+        };
+        let err = match err.downcast() {
+            Ok(err) => return Self::Io(*err),
+            Err(err) => err,
+        };
+        match err.downcast() {
+            Ok(err) => Self::Batch(*err),
+            Err(err) => Self::Other(err),
+        }
+    }
+}
+
+/// The end of a batch whose failures were already reported one by one,
+/// with the status to exit with.
+#[derive(Debug)]
+pub struct BatchFailed {
+    pub message: String,
+    pub status: ExitStatus,
+}
+
+impl fmt::Display for BatchFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for BatchFailed {}
+
+/// An error on one file, which keeps the error itself as its
+/// [`source`](Error::source), so that the exit status and the code of the
+/// failure are still those of the error.
+#[derive(Debug)]
+pub struct FileError {
+    pub file: String,
+    pub source: Box<dyn Error>,
+}
+
+impl FileError {
+    #[must_use]
+    pub fn new(file: impl Into<String>, source: impl Into<Box<dyn Error>>) -> Self {
+        Self {
+            file: file.into(),
+            source: source.into(),
+        }
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.file, self.source)
+    }
+}
+
+impl Error for FileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// A failure on one file of a batch, with what the error tells of where it
+/// happened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorRecord {
+    pub file: String,
+    /// The kyoku number, counting from 0 for E1, and honba of the kyoku the
+    /// error is about.
+    pub kyoku: Option<u8>,
+    pub honba: Option<u8>,
+    /// The seat the error is about.
+    pub actor: Option<u8>,
+    /// The position of the offending action among the takes or events of
+    /// the kyoku.
+    pub action: Option<usize>,
+    /// A stable identifier of the kind of error, e.g.
+    /// `convert.unexpected_naki`, to branch on instead of the message.
+    pub code: &'static str,
+    pub message: String,
+    /// The recorded and recomputed points of a `rule.score_mismatch`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<ScoreComparison>,
+}
+
+/// The fields of a record known from the error alone.
+#[derive(Default)]
+struct Location {
+    kyoku: Option<u8>,
+    honba: Option<u8>,
+    actor: Option<u8>,
+    action: Option<usize>,
+}
+
+impl Location {
+    const fn kyoku(kyoku: u8, honba: u8) -> Self {
+        Self {
+            kyoku: Some(kyoku),
+            honba: Some(honba),
+            actor: None,
+            action: None,
+        }
+    }
+
+    const fn actor(mut self, actor: u8) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+}
+
+impl ErrorRecord {
+    /// Describe `err`, which happened on `file`. Errors of other crates, e.g.
+    /// of I/O, get the code `other`; a [`FileError`] is described by its
+    /// file and error.
+    #[must_use]
+    pub fn new(file: &str, err: &(dyn Error + 'static)) -> Self {
+        if let Some(err) = err.downcast_ref::<FileError>() {
+            return Self::new(&err.file, &*err.source);
+        }
+        let (code, location) = if let Some(err) = err.downcast_ref::<ConvertError>() {
+            convert(err)
+        } else if let Some(err) = err.downcast_ref::<TenhouParseError>() {
+            parse(err)
+        } else if let Some(err) = err.downcast_ref::<RuleError>() {
+            rule(err)
+        } else if let Some(err) = err.downcast_ref::<HandError>() {
+            hand(err)
+        } else if let Some(err) = err.downcast_ref::<ReplayError>() {
+            replay(err)
+        } else if let Some(err) = err.downcast_ref::<ValidateError>() {
+            validate(err)
+        } else if err.is::<io::Error>() {
+            ("io", Location::default())
+        } else {
+            ("other", Location::default())
+        };
+        Self {
+            file: file.to_owned(),
+            kyoku: location.kyoku,
+            honba: location.honba,
+            actor: location.actor,
+            action: location.action,
+            code,
+            message: err.to_string(),
+            comparison: score_comparison(err).cloned(),
+        }
+    }
+}
+
+fn score_comparison<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a ScoreComparison> {
+    if let Some(err) = err.downcast_ref::<FileError>() {
+        return score_comparison(&*err.source);
+    }
+    let err = match err.downcast_ref::<ValidateError>() {
+        Some(ValidateError::Rule(err)) => err,
+        Some(_) => return None,
+        None => err.downcast_ref::<RuleError>()?,
+    };
+    match err {
+        RuleError::ScoreMismatch { comparison, .. } => Some(comparison),
+        _ => None,
+    }
+}
+
+fn convert(err: &ConvertError) -> (&'static str, Location) {
+    let location = match *err {
+        ConvertError::InsufficientDoraIndicators { kyoku, honba } => Location::kyoku(kyoku, honba),
+        ConvertError::InsufficientTakes {
+            kyoku,
+            honba,
+            actor,
+        }
+        | ConvertError::InsufficientDiscards {
+            kyoku,
+            honba,
+            actor,
+        } => Location::kyoku(kyoku, honba).actor(actor),
+        ConvertError::UnexpectedNaki {
+            index,
+            kyoku,
+            honba,
+            actor,
+            ..
+        } => Location {
+            action: Some(index.into()),
+            ..Location::kyoku(kyoku, honba).actor(actor)
+        },
+        ConvertError::ScoreOverflow { kyoku, honba, who } => {
+            Location::kyoku(kyoku, honba).actor(who)
+        }
+        _ => Location::default(),
+    };
+    (err.code(), location)
+}
+
+fn parse(err: &TenhouParseError) -> (&'static str, Location) {
+    (err.code(), Location::default())
+}
+
+fn rule(err: &RuleError) -> (&'static str, Location) {
+    match *err {
+        RuleError::MisawardedSticks { kyoku, honba, .. } => {
+            ("rule.misawarded_sticks", Location::kyoku(kyoku, honba))
+        }
+        RuleError::MisawardedHonba {
+            kyoku, honba, who, ..
+        } => (
+            "rule.misawarded_honba",
+            Location::kyoku(kyoku, honba).actor(who),
+        ),
+        RuleError::InvalidPayers {
+            kyoku, honba, who, ..
+        } => (
+            "rule.invalid_payers",
+            Location::kyoku(kyoku, honba).actor(who),
+        ),
+        RuleError::LedgerMismatch { kyoku, honba, .. } => {
+            ("rule.ledger_mismatch", Location::kyoku(kyoku, honba))
+        }
+        RuleError::ScoreMismatch {
+            kyoku, honba, who, ..
+        } => (
+            "rule.score_mismatch",
+            Location::kyoku(kyoku, honba).actor(who),
+        ),
+        RuleError::InvalidScore {
+            kyoku, honba, who, ..
+        } => (
+            "rule.invalid_score",
+            Location::kyoku(kyoku, honba).actor(who),
+        ),
+        RuleError::RuleFlagsMismatch { .. } => ("rule.rule_flags_mismatch", Location::default()),
+        RuleError::ScoreOverflow { kyoku, honba, who } => (
+            "rule.score_overflow",
+            Location::kyoku(kyoku, honba).actor(who),
+        ),
+        RuleError::FinalResultMismatch { who, .. } => {
+            let location = Location {
+                actor: Some(who),
+                ..Location::default()
+            };
+            ("rule.final_result_mismatch", location)
+        }
+        RuleError::InvalidIndicators { kyoku, honba, .. } => {
+            ("rule.invalid_indicators", Location::kyoku(kyoku, honba))
+        }
+        RuleError::FourthSeatNamed { .. } => ("rule.fourth_seat_named", Location::default()),
+        RuleError::FourthSeatActs { kyoku, honba } => (
+            "rule.fourth_seat_acts",
+            Location::kyoku(kyoku, honba).actor(3),
+        ),
+    }
+}
+
+fn hand(err: &HandError) -> (&'static str, Location) {
+    match *err {
+        HandError::Convert(ref err) => convert(err),
+        HandError::Replay(ref err) => replay(err),
+        HandError::NoWinningTile { who, kyoku, honba } => (
+            "hand.no_winning_tile",
+            Location::kyoku(kyoku, honba).actor(who),
+        ),
+        HandError::UraWithoutRiichi {
+            who, kyoku, honba, ..
+        } => (
+            "hand.ura_without_riichi",
+            Location::kyoku(kyoku, honba).actor(who),
+        ),
+        HandError::DoraMismatch {
+            who, kyoku, honba, ..
+        } => (
+            "hand.dora_mismatch",
+            Location::kyoku(kyoku, honba).actor(who),
+        ),
+        HandError::UraMismatch {
+            who, kyoku, honba, ..
+        } => (
+            "hand.ura_mismatch",
+            Location::kyoku(kyoku, honba).actor(who),
+        ),
+        HandError::SituationMismatch {
+            who, kyoku, honba, ..
+        } => (
+            "hand.situation_mismatch",
+            Location::kyoku(kyoku, honba).actor(who),
+        ),
+    }
+}
+
+fn replay(err: &ReplayError) -> (&'static str, Location) {
+    match *err {
+        ReplayError::NotStarted { index } => {
+            let location = Location {
+                action: Some(index),
+                ..Location::default()
+            };
+            ("replay.not_started", location)
+        }
+        ReplayError::MissingTile {
+            actor,
+            kyoku,
+            honba,
+            ..
+        } => (
+            "replay.missing_tile",
+            Location::kyoku(kyoku, honba).actor(actor),
+        ),
+        ReplayError::NoPonToAddTo {
+            actor,
+            kyoku,
+            honba,
+            ..
+        } => (
+            "replay.no_pon_to_add_to",
+            Location::kyoku(kyoku, honba).actor(actor),
+        ),
+        ReplayError::WallExhausted {
+            actor,
+            kyoku,
+            honba,
+        } => (
+            "replay.wall_exhausted",
+            Location::kyoku(kyoku, honba).actor(actor),
+        ),
+        ReplayError::NotSanma { kyoku, honba } => {
+            ("replay.not_sanma", Location::kyoku(kyoku, honba))
+        }
+        ReplayError::TooManyTiles { kyoku, honba, .. } => {
+            ("replay.too_many_tiles", Location::kyoku(kyoku, honba))
+        }
+        ReplayError::OutOfTurn {
+            index,
+            actor,
+            kyoku,
+            honba,
+        } => {
+            let location = Location {
+                action: Some(index),
+                ..Location::kyoku(kyoku, honba).actor(actor)
+            };
+            ("replay.out_of_turn", location)
+        }
+        ReplayError::NotDiscarded {
+            actor,
+            kyoku,
+            honba,
+            ..
+        } => (
+            "replay.not_discarded",
+            Location::kyoku(kyoku, honba).actor(actor),
+        ),
+        ReplayError::Chi {
+            actor,
+            kyoku,
+            honba,
+            ..
+        } => ("replay.chi", Location::kyoku(kyoku, honba).actor(actor)),
+        ReplayError::NotKita {
+            actor,
+            kyoku,
+            honba,
+            ..
+        } => (
+            "replay.not_kita",
+            Location::kyoku(kyoku, honba).actor(actor),
+        ),
+    }
+}
+
+fn validate(err: &ValidateError) -> (&'static str, Location) {
+    match *err {
+        ValidateError::Rule(ref err) => rule(err),
+        ValidateError::Hand(ref err) => hand(err),
+        ValidateError::Convert(ref err) => convert(err),
+        ValidateError::Replay(ref err) => replay(err),
+        ValidateError::ScoreMismatch { kyoku, honba, .. } => {
+            ("validate.score_mismatch", Location::kyoku(kyoku, honba))
+        }
+        ValidateError::Cancelled => ("validate.cancelled", Location::default()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::tenhou::{Log, ScoringRules};
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn exit_status() {
+        let err: Box<dyn Error> = Box::new(TenhouParseError::NotThreePlayer);
+        assert_eq!(ExitStatus::of(&*err).code(), 3);
+        let err: Box<dyn Error> = Box::new(ConvertError::UnexpectedRiichi);
+        assert_eq!(ExitStatus::of(&*err), ExitStatus::Invalid);
+        let err: Box<dyn Error> = Box::new(io::Error::other("disk full"));
+        assert_eq!(ExitStatus::of(&*err), ExitStatus::Io);
+        let err: Box<dyn Error> = "no such subcommand".into();
+        assert_eq!(ExitStatus::of(&*err), ExitStatus::Other);
+        let err = FileError::new("a.json", TenhouParseError::NotThreePlayer);
+        assert_eq!(ExitStatus::of(&err), ExitStatus::Parse);
+        assert_eq!(err.to_string(), "a.json: not three-player game");
+        assert!(err.source().unwrap().is::<TenhouParseError>());
+        let err: Box<dyn Error> = Box::new(BatchFailed {
+            message: "2 logs failed to convert".to_owned(),
+            status: ExitStatus::Partial,
+        });
+        assert_eq!(ExitStatus::of(&*err).code(), 6);
+        assert_eq!(err.to_string(), "2 logs failed to convert");
+    }
+
+    #[test]
+    fn cli_error() {
+        let err: Box<dyn Error> = Log::from_json_str("{").unwrap_err().into();
+        let err = CliError::from(err);
+        assert!(matches!(err, CliError::Parse(_)), "{err:?}");
+        assert_eq!(err.code(), "parse.invalid_json");
+        assert_eq!(err.exit_status(), ExitStatus::Parse);
+        assert!(err.source().is_some());
+        let err = CliError::from(Box::<dyn Error>::from(ConvertError::Cancelled));
+        assert_eq!(err.code(), "convert.cancelled");
+        assert_eq!(err.to_string(), "conversion cancelled");
+        let err = CliError::from(Box::<dyn Error>::from(BatchFailed {
+            message: "2 logs failed to convert".to_owned(),
+            status: ExitStatus::Partial,
+        }));
+        assert_eq!(err.exit_status().code(), 6);
+        let err = FileError::new("a.json", ConvertError::Cancelled);
+        let err = CliError::from(Box::<dyn Error>::from(err));
+        assert!(matches!(err, CliError::File { .. }), "{err:?}");
+        assert_eq!(err.code(), "convert.cancelled");
+        assert_eq!(err.exit_status(), ExitStatus::Invalid);
+        assert_eq!(err.to_string(), "a.json: conversion cancelled");
+        let err = CliError::from(Box::<dyn Error>::from("--wall needs --format mjai"));
+        assert_eq!(
+            (err.code(), err.exit_status()),
+            ("other", ExitStatus::Other)
+        );
+    }
+
+    #[test]
+    fn records() {
+        // A pon of a tile nobody discarded.
+        let json = Fixture::Hanchan
+            .json()
+            .replacen("\"4242p42\"", "\"4141p41\"", 1);
+        let log = Log::from_json_str(&json).unwrap();
+        let err: Box<dyn Error> = tenhou_to_mjai(&log).unwrap_err().into();
+        let record = ErrorRecord::new("game.json", &*err);
+        assert_eq!(record.code, "convert.unexpected_naki");
+        assert_eq!((record.kyoku, record.honba), (Some(0), Some(0)));
+        assert_eq!(record.actor, Some(1));
+        assert_eq!(record.action, Some(4));
+        assert_eq!(record.message, err.to_string());
+
+        let err: Box<dyn Error> = Log::from_json_str("{").unwrap_err().into();
+        assert_eq!(ErrorRecord::new("x", &*err).code, "parse.invalid_json");
+        let err = FileError::new("y", err);
+        let record = ErrorRecord::new("x", &err);
+        assert_eq!(
+            (record.file.as_str(), record.code),
+            ("y", "parse.invalid_json")
+        );
+        let err: Box<dyn Error> = "something else".into();
+        assert_eq!(ErrorRecord::new("x", &*err).code, "other");
+
+        let mut line = vec![];
+        ErrorFormat::Json.write(&mut line, "x", &*err).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&line).unwrap();
+        assert_eq!(value["file"], "x");
+        assert_eq!(value["kyoku"], serde_json::Value::Null);
+        let mut line = vec![];
+        ErrorFormat::Text.write(&mut line, "x", &*err).unwrap();
+        assert_eq!(line, b"x: something else\n");
+
+        // A score mismatch comes with the points side by side.
+        let log = Log::from_json_str(
+            r#"{"log":[[[0,0,0],[35000,35000,35000,0],[47],[],[],[],[],[],[],[],[],[],[],[],[],[],["和了",[7700,-7700,0,0],[0,1,0,"30符4飜7700点"]]]],"name":["","","",""]}"#,
+        )
+        .unwrap();
+        let err: Box<dyn Error> = log
+            .check_scores(&ScoringRules::default())
+            .unwrap_err()
+            .into();
+        let record = ErrorRecord::new("x", &*err);
+        assert_eq!(record.code, "rule.score_mismatch");
+        assert_eq!(
+            record.comparison.unwrap().recomputed_deltas,
+            [11600, -11600, 0, 0]
+        );
+        let mut text = vec![];
+        ErrorFormat::Text.write(&mut text, "x", &*err).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(text.lines().count(), 6, "{text}");
+        assert!(
+            text.contains("\n     1     -7700      -11600       -3900\n"),
+            "{text}"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Comparison of two event streams kyoku by kyoku, e.g. to check a new input
+//! adapter against the output of a known good one.
+
+use crate::replay::split_kyokus;
+use crate::{Event, Tile};
+use std::fmt::{self, Write};
+
+/// Where the kyokus at the same index of two streams first differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the kyoku, counting from 0.
+    pub kyoku: usize,
+    /// The round of the kyoku, e.g. `E1-0`, from the first stream if it has
+    /// the kyoku.
+    pub round: String,
+    /// The index of the first differing event within the kyoku.
+    pub index: usize,
+    /// The events at `index`, `None` where a stream has less events.
+    pub left: Option<Event>,
+    pub right: Option<Event>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let event = |e: &Option<Event>| {
+            e.as_ref()
+                .map_or_else(|| "nothing".to_owned(), |e| format!("{e:?}"))
+        };
+        write!(
+            f,
+            "kyoku {} ({}) event {}: {} vs {}",
+            self.kyoku,
+            self.round,
+            self.index,
+            event(&self.left),
+            event(&self.right),
+        )
+    }
+}
+
+/// Options for [`diff_events`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiffOptions {
+    /// Treat aka fives as plain ones.
+    pub ignore_aka: bool,
+}
+
+/// Compare the kyokus of `left` and `right` by index and report the first
+/// differing event of each kyoku that differs, a kyoku only one of them has
+/// included.
+///
+/// The order of tiles in haipai and in consumed tiles of calls does not
+/// count, as formats and sources disagree on it.
+#[must_use]
+pub fn diff_events(left: &[Event], right: &[Event], options: DiffOptions) -> Vec<Divergence> {
+    let mut lefts = split_kyokus(left);
+    let mut rights = split_kyokus(right);
+    let mut ret = vec![];
+    for kyoku in 0.. {
+        let (l, r) = match (lefts.next(), rights.next()) {
+            (None, None) => break,
+            (l, r) => (l.unwrap_or_default(), r.unwrap_or_default()),
+        };
+        let len = l.len().max(r.len());
+        let differs = (0..len).find(|&i| {
+            let normalized = |events: &[Event]| events.get(i).map(|e| normalize(e, options));
+            normalized(l) != normalized(r)
+        });
+        if let Some(index) = differs {
+            let round = l
+                .first()
+                .or_else(|| r.first())
+                .map_or_else(String::new, round);
+            ret.push(Divergence {
+                kyoku,
+                round,
+                index,
+                left: l.get(index).cloned(),
+                right: r.get(index).cloned(),
+            });
+        }
+    }
+    ret
+}
+
+/// The kyoku of `divergence` around where it diverges, as a hunk of a
+/// unified diff of mjai JSON lines: up to `context` events before it that
+/// `left` and `right` share, then the events of the kyoku from there on in
+/// `left`, prefixed with `-`, and in `right`, prefixed with `+`, up to
+/// `context` events past the divergence each.
+#[must_use]
+pub fn unified_diff(
+    left: &[Event],
+    right: &[Event],
+    divergence: &Divergence,
+    context: usize,
+) -> String {
+    let kyoku = |events| {
+        split_kyokus(events)
+            .nth(divergence.kyoku)
+            .unwrap_or_default()
+    };
+    let (l, r) = (kyoku(left), kyoku(right));
+    let index = divergence.index;
+    let mut out = format!(
+        "@@ kyoku {} ({}) event {} @@\n",
+        divergence.kyoku, divergence.round, divergence.index
+    );
+    let mut lines = |prefix: char, events: &[Event]| {
+        for event in events {
+            let json = serde_json::to_string(event).unwrap_or_default();
+            writeln!(out, "{prefix}{json}").ok();
+        }
+    };
+    let shared = if l.len() >= index { l } else { r };
+    lines(' ', &shared[index.saturating_sub(context)..index]);
+    let end = index + context + 1;
+    lines('-', &l[index.min(l.len())..end.min(l.len())]);
+    lines('+', &r[index.min(r.len())..end.min(r.len())]);
+    out
+}
+
+fn normalize(event: &Event, options: DiffOptions) -> Event {
+    let mut event = event.clone();
+    if options.ignore_aka {
+        event.map_tiles(Tile::deaka);
+    }
+    match &mut event {
+        Event::StartKyoku { tehais, .. } => {
+            for tehai in tehais {
+                tehai.sort_unstable();
+            }
+        }
+        Event::Chi { consumed, .. } | Event::Pon { consumed, .. } => consumed.sort_unstable(),
+        Event::Daiminkan { consumed, .. } | Event::Kakan { consumed, .. } => {
+            consumed.sort_unstable();
+        }
+        Event::Ankan { consumed, .. } => consumed.sort_unstable(),
+        _ => (),
+    }
+    event
+}
+
+/// The round of a `start_kyoku` event, e.g. `S2-1`.
+fn round(event: &Event) -> String {
+    match event {
+        Event::StartKyoku {
+            bakaze,
+            kyoku,
+            honba,
+            ..
+        } => format!("{bakaze}{kyoku}-{honba}"),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::t;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn diff() {
+        let left = tenhou_to_mjai(&Fixture::Hanchan.log()).unwrap();
+        let mut right = left.clone();
+        assert_eq!(diff_events(&left, &right, DiffOptions::default()), []);
+
+        // Reordered haipai and consumed tiles do not count.
+        for event in &mut right {
+            match event {
+                Event::StartKyoku { tehais, .. } => tehais[0].reverse(),
+                Event::Pon { consumed, .. } => consumed.reverse(),
+                _ => (),
+            }
+        }
+        assert_eq!(diff_events(&left, &right, DiffOptions::default()), []);
+
+        let (index, _) = right
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| matches!(e, Event::Dahai { .. }))
+            .nth(40)
+            .unwrap();
+        right[index] = Event::Dahai {
+            actor: 0,
+            pai: t!(?),
+            tsumogiri: false,
+        };
+        right.truncate(right.len() - 2);
+        let divergences = diff_events(&left, &right, DiffOptions::default());
+        assert_eq!(divergences.len(), 2);
+        assert_eq!(divergences[1].kyoku, 10);
+        assert_eq!(divergences[1].right, None);
+        assert_eq!(divergences[1].left, Some(Event::EndKyoku));
+        assert!(divergences[0].to_string().contains("vs Dahai"));
+
+        let mut aka = left.clone();
+        for event in &mut aka {
+            event.map_tiles(Tile::deaka);
+        }
+        assert_ne!(diff_events(&left, &aka, DiffOptions::default()), []);
+        let options = DiffOptions { ignore_aka: true };
+        assert_eq!(diff_events(&left, &aka, options), []);
+
+        let diff = unified_diff(&left, &right, &divergences[0], 2);
+        let lines: Vec<_> = diff.lines().collect();
+        assert!(lines[0].starts_with(&format!("@@ kyoku {} (", divergences[0].kyoku)));
+        assert_eq!(lines.len(), 1 + 2 + 3 + 3);
+        assert!(lines[1].starts_with(' ') && lines[3].starts_with('-'));
+        assert_eq!(
+            lines[6],
+            r#"+{"type":"dahai","actor":0,"pai":"?","tsumogiri":false}"#
+        );
+        // A kyoku cut short has less to show.
+        let diff = unified_diff(&left, &right, &divergences[1], 2);
+        assert_eq!(diff.lines().filter(|l| l.starts_with('+')).count(), 0);
+        assert_eq!(diff.lines().filter(|l| l.starts_with('-')).count(), 1);
+    }
+}
@@ -0,0 +1,220 @@
+//! The groups of fields an output keeps, so that datasets can be published
+//! without who played the games and where.
+
+use crate::Event;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A group of the fields of an output that [`Fields`] keeps or leaves out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// The kyokus and the events of play, always kept.
+    Actions,
+    /// The final scores, placements and placement points of the game.
+    Results,
+    /// The names of the players, and their sexes in tenhou logs.
+    Names,
+    /// The dans and rates of the players and the rating segment of the
+    /// lobby.
+    Ratings,
+    Lobby,
+    /// The disconnects and reconnects of the seats.
+    Connections,
+    GameId,
+}
+
+impl Field {
+    pub const ALL: [Self; 7] = [
+        Self::Actions,
+        Self::Results,
+        Self::Names,
+        Self::Ratings,
+        Self::Lobby,
+        Self::Connections,
+        Self::GameId,
+    ];
+
+    /// The name used on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Actions => "actions",
+            Self::Results => "results",
+            Self::Names => "names",
+            Self::Ratings => "ratings",
+            Self::Lobby => "lobby",
+            Self::Connections => "connections",
+            Self::GameId => "game-id",
+        }
+    }
+
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// The fields an output keeps, e.g. `actions,results` to publish the play
+/// of games without who played them and where. Names left out are replaced
+/// by `Aさん` to `Cさん`, so that the output still parses; the rest is left
+/// out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fields(u8);
+
+/// Why a list of fields does not parse, exported as `FieldsParseError`.
+#[derive(Debug)]
+pub enum ParseError {
+    UnknownField(String),
+    NoActions,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownField(name) => write!(
+                f,
+                "unknown field {name:?}, expected one of actions, results, names, \
+                ratings, lobby, connections or game-id"
+            ),
+            Self::NoActions => f.write_str("the actions cannot be left out"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl Fields {
+    pub const ALL: Self = Self((1 << Field::ALL.len()) - 1);
+
+    #[must_use]
+    pub const fn contains(self, field: Field) -> bool {
+        self.0 & field.bit() != 0
+    }
+
+    /// Leave the results out of the `end_game` of `events`.
+    pub fn project_events(self, events: &mut [Event]) {
+        if self.contains(Field::Results) {
+            return;
+        }
+        for event in events {
+            if let Event::EndGame {
+                scores,
+                ranks,
+                final_points,
+            } = event
+            {
+                *scores = None;
+                *ranks = None;
+                *final_points = None;
+            }
+        }
+    }
+}
+
+impl Default for Fields {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl FromStr for Fields {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = Self(0);
+        for name in s.split(',') {
+            let field = Field::ALL
+                .into_iter()
+                .find(|field| field.name() == name.trim())
+                .ok_or_else(|| ParseError::UnknownField(name.to_owned()))?;
+            fields.0 |= field.bit();
+        }
+        if !fields.contains(Field::Actions) {
+            return Err(ParseError::NoActions);
+        }
+        Ok(fields)
+    }
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names = Field::ALL
+            .into_iter()
+            .filter(|&field| self.contains(field))
+            .map(Field::name);
+        if let Some(first) = names.next() {
+            f.write_str(first)?;
+        }
+        for name in names {
+            write!(f, ",{name}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::tenhou::{Log, RawLog};
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn project() {
+        let fields: Fields = "actions, results".parse().unwrap();
+        assert!(fields.contains(Field::Results));
+        assert!(!fields.contains(Field::Names));
+        assert_eq!(fields.to_string(), "actions,results");
+        assert_eq!(
+            Fields::ALL.to_string().parse::<Fields>().unwrap(),
+            Fields::ALL
+        );
+        "results".parse::<Fields>().unwrap_err();
+        "actions,rates".parse::<Fields>().unwrap_err();
+
+        let fields: Fields = "actions".parse().unwrap();
+        let mut log = Fixture::Hanchan.log();
+        log.project(fields);
+        assert_eq!(log.names, ["Aさん", "Bさん", "Cさん", ""]);
+        assert!(log.rate.is_none() && log.ratingc.is_none() && log.game_id.is_none());
+        assert!(log.connections.is_empty() && log.final_results.is_none());
+        let mut events = tenhou_to_mjai(&log).unwrap();
+        fields.project_events(&mut events);
+        assert!(matches!(
+            events.last(),
+            Some(Event::EndGame {
+                scores: None,
+                ranks: None,
+                final_points: None
+            })
+        ));
+
+        let mut raw = RawLog::from_json_str(Fixture::Hanchan.json()).unwrap();
+        raw.project(fields);
+        let value = serde_json::to_value(&raw).unwrap();
+        for key in [
+            "ref",
+            "dan",
+            "rate",
+            "ratingc",
+            "lobby",
+            "sx",
+            "sc",
+            "connection",
+        ] {
+            assert!(value.get(key).is_none(), "{key}");
+        }
+        assert_eq!(value["name"][0], "Aさん");
+        assert_eq!(value["name"][3], "");
+        assert_eq!(value["rule"]["disp"], "三鳳南喰赤");
+        Log::from_json_str(&value.to_string()).unwrap();
+
+        let mut log = Fixture::Hanchan.log();
+        log.rule_disp = "四川麻雀部".to_owned();
+        log.project(fields);
+        assert_eq!(log.rule_disp, "");
+        let mut log = Fixture::Hanchan.log();
+        log.project(fields);
+        assert_eq!(log.rule_disp, "三鳳南喰赤");
+    }
+}
@@ -0,0 +1,800 @@
+//! Random but legal sanma games from a seed, as tenhou.net/6 logs, for
+//! stress-testing downstream tools and benchmarking the converter with as
+//! many logs as needed.
+//!
+//! The games follow tenhou's ranked sanma hanchan: 35000 points returned at
+//! 40000, a west round of sudden death when nobody has reached that after
+//! south 3, the game ending once a seat is below zero, and all last ending
+//! on a win of the dealer on top.
+//!
+//! The players never call. They draw, set aside kita, declare riichi on
+//! tenpai and mostly discard tiles they hold only one of, so that seven
+//! pairs come together often: it is the only hand they win with, as its fu
+//! and yaku follow from its tiles alone. Other complete hands are passed
+//! over, into furiten where the rules say so, as are wins on the first draw
+//! and riichi on the first discard, and no kita is set aside during an
+//! ippatsu, so that no game depends on rules that differ between platforms.
+
+use crate::hand::{is_standard_agari, waits};
+use crate::ruleset::Ruleset;
+use crate::tenhou::{Limit, Payment, Score, ScoringRules, TenhouTile, HONBA, RIICHI_STICK};
+use crate::{t, Points, Tile, SEATS};
+
+use std::str::FromStr;
+
+use chrono::{NaiveDate, TimeDelta};
+use serde_json::{json, Value};
+
+/// The rules the games are played by.
+const RULESET: Ruleset = Ruleset::TENHOU_SANMA;
+/// Four of each of the 27 kinds of sanma.
+const WALL: usize = 108;
+const DEAD_WALL: usize = 14;
+const HAIPAI: usize = 13;
+/// The number of kinds of tiles, indexed by [`Tile::as_usize`].
+const KINDS: usize = 27;
+/// Where the rinshan draws start in the dead wall, after the dora and ura
+/// indicators.
+const RINSHAN: usize = 2;
+/// What tenhou writes for the tile just drawn.
+const TSUMOGIRI: u8 = 60;
+
+/// What `gen` writes the games as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenFormat {
+    /// Logs in tenhou.net/6 format, as generated.
+    Tenhou6,
+    /// The logs converted to mjai events.
+    Mjai,
+}
+
+impl FromStr for GenFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tenhou6" => Ok(Self::Tenhou6),
+            "mjai" => Ok(Self::Mjai),
+            _ => Err(format!(
+                "unknown format {s:?}, expected \"tenhou6\" or \"mjai\""
+            )),
+        }
+    }
+}
+
+/// SplitMix64, small and good enough to shuffle walls, though not for
+/// anything that has to be unpredictable.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    const fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `n`, which must not be zero.
+    const fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    const fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// Plays games from a seed, the same games for the same seed.
+///
+/// ```
+/// use convlog::generate::Generator;
+/// use convlog::tenhou::Log;
+///
+/// let log = Generator::new(7).game();
+/// let log = Log::from_json_str(&log.to_string()).unwrap();
+/// assert!(log.final_results.is_some());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Generator {
+    rng: Rng,
+    played: u32,
+}
+
+impl Generator {
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng(seed),
+            played: 0,
+        }
+    }
+
+    /// Play the next game. Games start an hour apart from midnight of
+    /// 2000-01-01 in Japan time, which their IDs tell.
+    pub fn game(&mut self) -> Value {
+        let start = NaiveDate::from_ymd_opt(2000, 1, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .expect("a valid date")
+            + TimeDelta::hours(i64::from(self.played));
+        self.played += 1;
+        let game_id = format!(
+            "{}gm-00b9-0000-{:08x}",
+            start.format("%Y%m%d%H"),
+            self.rng.next_u64() as u32,
+        );
+
+        let mut scores = [RULESET.start_points; SEATS];
+        let (mut kyoku_num, mut honba, mut kyotaku) = (0, 0, 0);
+        let mut kyokus = vec![];
+        loop {
+            let played = Kyoku::play(&mut self.rng, [kyoku_num, honba, kyotaku], scores);
+            kyokus.push(played.log);
+            scores = played.scores;
+            if played.won {
+                kyotaku = 0;
+                honba = if played.renchan { honba + 1 } else { 0 };
+            } else {
+                kyotaku = played.sticks;
+                honba += 1;
+            }
+            if is_over(kyoku_num, played.renchan, played.won, scores) {
+                break;
+            }
+            if !played.renchan {
+                // The fourth kyoku of every round is skipped in sanma.
+                kyoku_num += if kyoku_num % 4 == 2 { 2 } else { 1 };
+            }
+        }
+
+        // Sticks left on the table go to the top.
+        let top = RULESET
+            .placements(scores)
+            .iter()
+            .position(|&placement| placement == 0)
+            .unwrap_or_default();
+        scores[top] += Points::from(kyotaku) * RIICHI_STICK;
+        let points = RULESET.placement_points(scores);
+        let mut sc = vec![];
+        for seat in 0..SEATS {
+            sc.extend([json!(scores[seat]), json!(points[seat])]);
+        }
+        sc.extend([json!(0), json!(0)]);
+
+        json!({
+            "ver": 2.3,
+            "ref": game_id,
+            "log": kyokus,
+            "ratingc": "PF3",
+            "rule": {"disp": "三鳳南喰赤", "aka53": 1, "aka52": 1, "aka51": 1},
+            "lobby": 0,
+            "sc": sc,
+            "name": ["gen-0", "gen-1", "gen-2", ""],
+        })
+    }
+}
+
+impl Iterator for Generator {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        Some(self.game())
+    }
+}
+
+/// Whether the game ends after the kyoku `kyoku_num`, with the dealer keeping
+/// the seat if `renchan`, by a win if `won`, and `scores` after it.
+fn is_over(kyoku_num: u8, renchan: bool, won: bool, scores: [Points; SEATS]) -> bool {
+    if scores.iter().any(|&score| score < 0) {
+        return true;
+    }
+    let reached = scores.iter().any(|&score| score >= RULESET.return_points);
+    match kyoku_num {
+        0..=5 => false,
+        // All last, whose dealer is seat 2: a win on top ends it.
+        6 if renchan => {
+            won && RULESET.placements(scores)[2] == 0 && scores[2] >= RULESET.return_points
+        }
+        6 => reached,
+        // The west round ends as soon as a seat reaches the return points,
+        // and after west 3 at the latest.
+        _ => reached || (!renchan && kyoku_num == 10),
+    }
+}
+
+/// The state of a seat during a kyoku.
+#[derive(Debug, Clone, Default)]
+struct Player {
+    haipai: Vec<Tile>,
+    hand: Vec<Tile>,
+    takes: Vec<Value>,
+    discards: Vec<Value>,
+    /// Every tile discarded, without aka.
+    discarded: Vec<Tile>,
+    kitas: u8,
+    riichi: bool,
+    ippatsu: bool,
+    /// Until the next discard, for passing a tile the hand waits on.
+    furiten: bool,
+    /// For passing a winning tile after riichi, until the end of the kyoku.
+    riichi_furiten: bool,
+    /// Until the first discard.
+    first_turn: bool,
+}
+
+impl Player {
+    fn is_furiten(&self, waits: &[Tile]) -> bool {
+        self.furiten || self.riichi_furiten || waits.iter().any(|w| self.discarded.contains(w))
+    }
+}
+
+/// How a kyoku ended.
+enum End {
+    Tsumo {
+        who: usize,
+        rinshan: bool,
+        haitei: bool,
+    },
+    /// `winners` in turn order from the discarder.
+    Ron {
+        winners: Vec<usize>,
+        target: usize,
+        pai: Tile,
+        houtei: bool,
+    },
+    Exhaustive,
+}
+
+/// A kyoku played out.
+struct Kyoku {
+    log: Value,
+    /// After the deltas and the riichi sticks deposited.
+    scores: [Points; SEATS],
+    won: bool,
+    renchan: bool,
+    /// Riichi sticks on the table at the end.
+    sticks: u8,
+}
+
+/// What a win is worked out from.
+struct Win<'a> {
+    who: usize,
+    target: usize,
+    tiles: Vec<Tile>,
+    player: &'a Player,
+    rinshan: bool,
+    haitei: bool,
+    houtei: bool,
+}
+
+impl Kyoku {
+    /// Play the kyoku `meta`, that is its number, honba and kyotaku, from
+    /// `scores`.
+    fn play(rng: &mut Rng, meta: [u8; 3], mut scores: [Points; SEATS]) -> Self {
+        let [kyoku_num, honba, kyotaku] = meta;
+        let oya = usize::from(kyoku_num % 4);
+        let start_scores = scores;
+
+        let mut wall = Vec::with_capacity(WALL);
+        for kind in 0..KINDS {
+            let tile = Tile::try_from(kind).expect("a sanma kind");
+            // One of each five of pinzu and souzu is aka.
+            wall.extend([tile.akaize(), tile, tile, tile]);
+        }
+        rng.shuffle(&mut wall);
+        let dead = &wall[WALL - DEAD_WALL..];
+        let (dora, ura) = (dead[0], dead[1]);
+
+        let mut players: [Player; SEATS] = Default::default();
+        for (seat, player) in players.iter_mut().enumerate() {
+            player.haipai = wall[seat * HAIPAI..(seat + 1) * HAIPAI].to_vec();
+            player.haipai.sort_unstable();
+            player.hand.clone_from(&player.haipai);
+            player.first_turn = true;
+        }
+
+        let mut next = SEATS * HAIPAI;
+        let mut live_end = WALL - DEAD_WALL;
+        let mut rinshan = 0;
+        let mut after_kita = false;
+        let mut sticks = kyotaku;
+        let mut seat = oya;
+        let end = loop {
+            let tile = if after_kita {
+                rinshan += 1;
+                dead[RINSHAN + rinshan - 1]
+            } else if next == live_end {
+                break End::Exhaustive;
+            } else {
+                next += 1;
+                wall[next - 1]
+            };
+            let haitei = !after_kita && next == live_end;
+            let ippatsu_open = players.iter().any(|p| p.ippatsu);
+            let player = &mut players[seat];
+            let waited = waits(&player.hand).contains(&tile.deaka());
+            player.takes.push(json!(TenhouTile::from(tile)));
+            player.hand.push(tile);
+            if !player.first_turn && is_seven_pairs(&player.hand) {
+                break End::Tsumo {
+                    who: seat,
+                    rinshan: after_kita,
+                    haitei,
+                };
+            }
+            if player.riichi && waited {
+                player.riichi_furiten = true;
+            }
+
+            let kita = player.hand.iter().position(|&t| t == t!(N));
+            if let Some(index) = kita.filter(|_| {
+                !player.riichi && !ippatsu_open && live_end - next >= 2 && rng.chance(90)
+            }) {
+                player.hand.remove(index);
+                player.discards.push(json!("f44"));
+                player.kitas += 1;
+                // The dead wall is topped up from the end of the live one.
+                live_end -= 1;
+                after_kita = true;
+                continue;
+            }
+            after_kita = false;
+
+            let drawn = player.hand.len() - 1;
+            let can_riichi = !player.riichi
+                && !player.first_turn
+                && scores[seat] >= RIICHI_STICK
+                && live_end - next >= SEATS;
+            let (index, declare) = if player.riichi {
+                (drawn, false)
+            } else {
+                choose_discard(rng, &player.hand, can_riichi)
+            };
+            let pai = player.hand.remove(index);
+            let code = if index == drawn {
+                TSUMOGIRI
+            } else {
+                TenhouTile::from(pai) as u8
+            };
+            player.discards.push(if declare {
+                json!(format!("r{code}"))
+            } else {
+                json!(code)
+            });
+            player.discarded.push(pai.deaka());
+            player.first_turn = false;
+            player.furiten = false;
+            player.ippatsu = false;
+
+            let mut winners = vec![];
+            for distance in 1..SEATS {
+                let other = &mut players[(seat + distance) % SEATS];
+                let waits = waits(&other.hand);
+                if !waits.contains(&pai.deaka()) {
+                    continue;
+                }
+                let mut tiles = other.hand.clone();
+                tiles.push(pai);
+                if !other.is_furiten(&waits) && is_seven_pairs(&tiles) {
+                    winners.push((seat + distance) % SEATS);
+                } else if other.riichi {
+                    other.riichi_furiten = true;
+                } else {
+                    other.furiten = true;
+                }
+            }
+            if !winners.is_empty() {
+                break End::Ron {
+                    winners,
+                    target: seat,
+                    pai,
+                    houtei: next == live_end,
+                };
+            }
+            if declare {
+                let player = &mut players[seat];
+                player.riichi = true;
+                player.ippatsu = true;
+                scores[seat] -= RIICHI_STICK;
+                sticks += 1;
+            }
+            seat = (seat + 1) % SEATS;
+        };
+
+        let mut ura_indicators = vec![];
+        let (results, won, renchan) = match end {
+            End::Tsumo {
+                who,
+                rinshan,
+                haitei,
+            } => {
+                let player = &players[who];
+                let win = Win {
+                    who,
+                    target: who,
+                    tiles: player.hand.clone(),
+                    player,
+                    rinshan,
+                    haitei,
+                    houtei: false,
+                };
+                if player.riichi {
+                    ura_indicators.push(ura);
+                }
+                let (deltas, detail) = win.settle(oya, [dora, ura], honba, sticks);
+                add(&mut scores, deltas);
+                (json!(["和了", deltas, detail]), true, who == oya)
+            }
+            End::Ron {
+                winners,
+                target,
+                pai,
+                houtei,
+            } => {
+                let mut results = vec![json!("和了")];
+                // Honba and riichi sticks go to the winner closest to the
+                // discarder.
+                let (mut honba, mut sticks) = (honba, sticks);
+                for &who in &winners {
+                    let player = &players[who];
+                    let mut tiles = player.hand.clone();
+                    tiles.push(pai);
+                    let win = Win {
+                        who,
+                        target,
+                        tiles,
+                        player,
+                        rinshan: false,
+                        haitei: false,
+                        houtei,
+                    };
+                    if player.riichi && ura_indicators.is_empty() {
+                        ura_indicators.push(ura);
+                    }
+                    let (deltas, detail) = win.settle(oya, [dora, ura], honba, sticks);
+                    add(&mut scores, deltas);
+                    results.extend([json!(deltas), detail]);
+                    (honba, sticks) = (0, 0);
+                }
+                (Value::Array(results), true, winners.contains(&oya))
+            }
+            End::Exhaustive => {
+                let (results, tenpai) = exhaustive(&players, oya);
+                if let Some(deltas) = results.get(1).and_then(Value::as_array) {
+                    for (score, delta) in scores.iter_mut().zip(deltas) {
+                        *score += delta.as_i64().unwrap_or_default();
+                    }
+                }
+                (results, false, tenpai[oya])
+            }
+        };
+
+        let mut log = vec![
+            json!(meta),
+            json!([start_scores[0], start_scores[1], start_scores[2], 0]),
+            json!([TenhouTile::from(dora)]),
+            json!(ura_indicators
+                .into_iter()
+                .map(TenhouTile::from)
+                .collect::<Vec<_>>()),
+        ];
+        for player in players {
+            let haipai: Vec<_> = player.haipai.into_iter().map(TenhouTile::from).collect();
+            log.extend([json!(haipai), json!(player.takes), json!(player.discards)]);
+        }
+        log.extend([json!([]), json!([]), json!([]), results]);
+        Self {
+            log: Value::Array(log),
+            scores,
+            won,
+            renchan,
+            sticks: if won { 0 } else { sticks },
+        }
+    }
+}
+
+fn add(scores: &mut [Points; SEATS], deltas: [Points; 4]) {
+    for (score, delta) in scores.iter_mut().zip(deltas) {
+        *score += delta;
+    }
+}
+
+/// The results of a kyoku whose wall ran out and which seats were tenpai:
+/// nagashi mangan for seats that discarded only terminals and honors, or
+/// else the noten payments.
+fn exhaustive(players: &[Player; SEATS], oya: usize) -> (Value, [bool; SEATS]) {
+    let tenpai = players.each_ref().map(|p| is_tenpai(&p.hand));
+    let nagashi: Vec<_> = (0..SEATS)
+        .filter(|&seat| {
+            let discarded = &players[seat].discarded;
+            !discarded.is_empty() && discarded.iter().all(|t| t.is_yaokyuu())
+        })
+        .collect();
+
+    let mut deltas = [0; 4];
+    let status = if nagashi.is_empty() {
+        let count = tenpai.iter().filter(|&&t| t).count();
+        match count {
+            0 => return (json!(["全員不聴"]), tenpai),
+            SEATS => return (json!(["全員聴牌"]), tenpai),
+            _ => {
+                // 2000 points from the noten seats to the tenpai ones.
+                let (gain, loss) = if count == 1 {
+                    (2000, 1000)
+                } else {
+                    (1000, 2000)
+                };
+                for (delta, &tenpai) in deltas.iter_mut().zip(&tenpai) {
+                    *delta = if tenpai { gain } else { -loss };
+                }
+                "流局"
+            }
+        }
+    } else {
+        // Paid as a mangan tsumo, without honba.
+        for &who in &nagashi {
+            for payer in (0..SEATS).filter(|&payer| payer != who) {
+                let paid = if who == oya || payer == oya {
+                    4000
+                } else {
+                    2000
+                };
+                deltas[payer] -= paid;
+                deltas[who] += paid;
+            }
+        }
+        "流し満貫"
+    };
+    (json!([status, deltas]), tenpai)
+}
+
+impl Win<'_> {
+    /// The score deltas and hora detail of the win, for the dealer `oya`,
+    /// the dora and ura indicators, and the honba and riichi sticks it
+    /// collects.
+    fn settle(
+        &self,
+        oya: usize,
+        [dora, ura]: [Tile; 2],
+        honba: u8,
+        sticks: u8,
+    ) -> ([Points; 4], Value) {
+        let tsumo = self.who == self.target;
+        let player = self.player;
+        let mut yaku: Vec<(&str, u8)> = vec![];
+        let yakuman = self.tiles.iter().all(|t| t.is_jihai());
+        if !yakuman {
+            let situations = [
+                ("立直", player.riichi),
+                ("一発", player.ippatsu),
+                ("門前清自摸和", tsumo),
+                ("嶺上開花", self.rinshan),
+                ("海底摸月", self.haitei),
+                ("河底撈魚", self.houtei),
+                ("断幺九", self.tiles.iter().all(|t| !t.is_yaokyuu())),
+            ];
+            yaku.extend(
+                situations
+                    .into_iter()
+                    .filter(|&(_, won)| won)
+                    .map(|(name, _)| (name, 1)),
+            );
+            if self.tiles.iter().all(|t| t.is_yaokyuu()) {
+                yaku.push(("混老頭", 2));
+            }
+            yaku.push(("七対子", 2));
+            let mut suits = self
+                .tiles
+                .iter()
+                .filter(|t| !t.is_jihai())
+                .map(|t| suit(*t));
+            let first = suits.next();
+            if first.is_some() && suits.all(|s| Some(s) == first) {
+                if self.tiles.iter().any(|t| t.is_jihai()) {
+                    yaku.push(("混一色", 3));
+                } else {
+                    yaku.push(("清一色", 6));
+                }
+            }
+            // Every kita is worth a han, which tenhou counts with the dora.
+            let dora = count_dora(&self.tiles, dora, player.kitas) + player.kitas;
+            let aka = self.tiles.iter().filter(|t| t.is_aka()).count() as u8;
+            let ura = if player.riichi {
+                count_dora(&self.tiles, ura, player.kitas)
+            } else {
+                0
+            };
+            yaku.extend(
+                [("ドラ", dora), ("赤ドラ", aka), ("裏ドラ", ura)]
+                    .into_iter()
+                    .filter(|&(_, han)| han > 0),
+            );
+        }
+
+        let han: u8 = yaku.iter().map(|&(_, han)| han).sum();
+        let limit = if yakuman {
+            Some(Limit::Yakuman)
+        } else {
+            Limit::of_han(han)
+        };
+        let score = Score {
+            fu: Some(25),
+            han: Some(han),
+            limit,
+            payment: Payment::Ron(0),
+        };
+        let payment = score
+            .expected_payment(
+                self.who == oya,
+                tsumo,
+                u8::from(yakuman),
+                &ScoringRules::default(),
+            )
+            .expect("the score has fu and han");
+
+        let honba = Points::from(honba) * HONBA;
+        let mut deltas = [0; 4];
+        match payment {
+            Payment::Ron(points) => {
+                deltas[self.target] -= points + honba;
+                deltas[self.who] += points + honba;
+            }
+            Payment::Tsumo { ko, oya: points } | Payment::TsumoAll(points @ ko) => {
+                for payer in (0..SEATS).filter(|&payer| payer != self.who) {
+                    let paid = if payer == oya { points } else { ko } + honba / 2;
+                    deltas[payer] -= paid;
+                    deltas[self.who] += paid;
+                }
+            }
+        }
+        deltas[self.who] += Points::from(sticks) * RIICHI_STICK;
+
+        let points = match payment {
+            Payment::Ron(points) => format!("{points}点"),
+            Payment::Tsumo { ko, oya } => format!("{ko}-{oya}点"),
+            Payment::TsumoAll(points) => format!("{points}点∀"),
+        };
+        let label = match limit {
+            None => format!("25符{han}飜{points}"),
+            Some(limit) => format!("{}{points}", limit_name(limit)),
+        };
+        let mut detail = vec![
+            json!(self.who),
+            json!(self.target),
+            json!(self.who),
+            json!(label),
+        ];
+        if yakuman {
+            detail.push(json!("字一色(役満)"));
+        }
+        detail.extend(
+            yaku.into_iter()
+                .map(|(name, han)| json!(format!("{name}({han}飜)"))),
+        );
+        (deltas, Value::Array(detail))
+    }
+}
+
+const fn limit_name(limit: Limit) -> &'static str {
+    match limit {
+        Limit::Mangan => "満貫",
+        Limit::Haneman => "跳満",
+        Limit::Baiman => "倍満",
+        Limit::Sanbaiman => "三倍満",
+        Limit::Yakuman => "役満",
+    }
+}
+
+/// The suit of a number tile, `0` for manzu, `1` for pinzu and `2` for
+/// souzu.
+const fn suit(tile: Tile) -> u8 {
+    match tile.deaka().as_u8() {
+        0..=8 => 1,
+        9..=17 => 2,
+        _ => 0,
+    }
+}
+
+/// The dora `tiles` hold for `indicator`, kitas counting where north is
+/// dora.
+fn count_dora(tiles: &[Tile], indicator: Tile, kitas: u8) -> u8 {
+    let dora = indicator.deaka().next();
+    let held = tiles.iter().filter(|t| t.deaka() == dora).count() as u8;
+    if dora == t!(N) {
+        held + kitas
+    } else {
+        held
+    }
+}
+
+/// Whether `tiles` are seven different pairs that do not also complete as
+/// sets and a pair, which would be scored differently.
+fn is_seven_pairs(tiles: &[Tile]) -> bool {
+    let mut counts = [0_u8; KINDS];
+    for tile in tiles {
+        counts[tile.deaka().as_usize()] += 1;
+    }
+    tiles.len() == 14 && counts.iter().all(|&c| c == 0 || c == 2) && !is_standard_agari(tiles)
+}
+
+/// Whether `hand` waits on a tile it does not hold all four of.
+fn is_tenpai(hand: &[Tile]) -> bool {
+    waits(hand)
+        .into_iter()
+        .any(|wait| hand.iter().filter(|t| t.deaka() == wait).count() < 4)
+}
+
+/// The index of the tile to discard from `hand`, mostly one the hand holds
+/// no other of, and whether to declare riichi with it if `can_riichi`.
+fn choose_discard(rng: &mut Rng, hand: &[Tile], can_riichi: bool) -> (usize, bool) {
+    let mut counts = [0_u8; KINDS];
+    for tile in hand {
+        counts[tile.deaka().as_usize()] += 1;
+    }
+    let count = |index: usize| counts[hand[index].deaka().as_usize()];
+    let index = if rng.chance(10) {
+        rng.below(hand.len())
+    } else {
+        let mut candidates: Vec<_> = (0..hand.len()).filter(|&i| count(i) == 1).collect();
+        if candidates.is_empty() {
+            candidates = (0..hand.len()).filter(|&i| count(i) >= 3).collect();
+        }
+        if candidates.is_empty() {
+            candidates = (0..hand.len()).collect();
+        }
+        candidates[rng.below(candidates.len())]
+    };
+    let declare = can_riichi
+        && {
+            let mut rest = hand.to_vec();
+            rest.remove(index);
+            is_tenpai(&rest)
+        }
+        && rng.chance(70);
+    (index, declare)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tenhou::{EndStatus, Log, RyukyokuReason};
+    use crate::validate::{validate, ValidateOptions};
+
+    #[test]
+    fn games() {
+        let mut generator = Generator::new(1);
+        let (mut horas, mut riichis, mut kitas, mut draws) = (0, 0, 0, 0);
+        for game in generator.by_ref().take(30) {
+            let log = Log::from_json_str(&game.to_string()).unwrap();
+            validate(&log, &ValidateOptions::default()).unwrap();
+            for kyoku in &log.kyokus {
+                match &kyoku.end_status {
+                    EndStatus::Hora { details } => horas += details.len(),
+                    EndStatus::Ryukyoku { reason, .. } => {
+                        draws += usize::from(*reason != RyukyokuReason::NagashiMangan);
+                    }
+                }
+                riichis += kyoku.deposits().iter().sum::<u32>();
+                kitas += kyoku
+                    .action_tables
+                    .iter()
+                    .flat_map(|t| t.discards.iter())
+                    .filter(|d| d.discard().is_none())
+                    .count();
+            }
+        }
+        assert!(horas > 0 && riichis > 0 && kitas > 0 && draws > 0);
+        assert_eq!(generator.played, 30);
+
+        // The same seed plays the same games.
+        assert_eq!(Generator::new(1).game(), Generator::new(1).game());
+        assert_ne!(Generator::new(1).game(), Generator::new(2).game());
+    }
+}
@@ -0,0 +1,146 @@
+//! A tonic-based gRPC service, see `proto/convlog.proto` for the definition.
+
+use crate::conv::tenhou_to_mjai;
+use crate::stats::Summary;
+use crate::tenhou::Log;
+use crate::validate::{validate_all, ValidateOptions};
+use std::net::SocketAddr;
+
+use tonic::{Request, Response, Status};
+
+#[allow(clippy::all, clippy::nursery, clippy::pedantic)]
+pub mod proto {
+    tonic::include_proto!("convlog");
+}
+
+use proto::convlog_server::{Convlog, ConvlogServer};
+use proto::{
+    ConvertRequest, ConvertResponse, StatsRequest, StatsResponse, ValidateRequest, ValidateResponse,
+};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConvlogService;
+
+#[tonic::async_trait]
+impl Convlog for ConvlogService {
+    async fn convert(
+        &self,
+        request: Request<ConvertRequest>,
+    ) -> Result<Response<ConvertResponse>, Status> {
+        let ConvertRequest { log, format } = request.into_inner();
+        if !format.is_empty() && format != "mjai" {
+            return Err(Status::invalid_argument(format!(
+                "unsupported format: {format:?}"
+            )));
+        }
+
+        let log = Log::from_json_str(&log).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let events = tenhou_to_mjai(&log)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?
+            .iter()
+            .map(|ev| serde_json::to_string(ev).expect("events are serializable"))
+            .collect();
+
+        Ok(Response::new(ConvertResponse { events }))
+    }
+
+    async fn validate(
+        &self,
+        request: Request<ValidateRequest>,
+    ) -> Result<Response<ValidateResponse>, Status> {
+        let ValidateRequest { log, ruleset } = request.into_inner();
+        let mut options = ValidateOptions::default();
+        if !ruleset.is_empty() {
+            options.ruleset = ruleset.parse().map_err(Status::invalid_argument)?;
+        }
+
+        let errors = match Log::from_json_str(&log) {
+            Ok(log) => validate_all(&log, &options)
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            Err(err) => vec![err.to_string()],
+        };
+
+        Ok(Response::new(ValidateResponse {
+            valid: errors.is_empty(),
+            errors,
+        }))
+    }
+
+    async fn stats(
+        &self,
+        request: Request<StatsRequest>,
+    ) -> Result<Response<StatsResponse>, Status> {
+        let StatsRequest { log } = request.into_inner();
+        let log = Log::from_json_str(&log).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let mut summary = Summary::default();
+        summary.add(&log);
+
+        Ok(Response::new(StatsResponse {
+            names: log.names.to_vec(),
+            kyokus: summary.kyokus,
+            tsumos: summary.tsumos,
+            rons: summary.rons,
+            ryukyokus: summary.ryukyokus,
+            kans: summary.kans,
+            tsumogiris: summary.tsumogiris,
+            tedashis: summary.tedashis,
+        }))
+    }
+}
+
+/// Serve the gRPC service on `addr` until the process is killed.
+pub async fn serve(addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(ConvlogServer::new(ConvlogService))
+        .serve(addr)
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LOG: &str = r#"{"ver":2.3,"ref":"2024030511gm-00b9-0000-e0c07689","log":[[[0,0,0],[35000,35000,35000,0],[47],[],[26,27,32,33,35,37,37,39,41,42,44,44,46],[45,47,19,39,27,34,21,43,26],[60,60,60,60,60,60,"f44","f44",42],[11,19,21,23,29,31,33,41,42,44,44,45,46],[42,28,22,19,"4242p42",23,22,24],["f44",33,"f44",60,19,31,11,41],[19,21,29,29,29,31,34,34,38,41,43,47,47],[21,"47p4747",42,35,27,26,"34p3434",25],[19,43,60,31,41,38,35],[],[],[],["和了",[-700,-400,1100,0],[2,2,2,"40符1飜400-700点","役牌 中(1飜)"]]]],"rule":{"disp":"三鳳南喰赤","aka53":1,"aka52":1,"aka51":1},"name":["mtk","つくねん3","ひぐお3",""]}"#;
+
+    #[tokio::test]
+    async fn rpcs() {
+        let service = ConvlogService;
+
+        let converted = service
+            .convert(Request::new(ConvertRequest {
+                log: LOG.to_owned(),
+                format: String::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(converted.events[0].contains("start_game"));
+
+        let validate = |log: &str, ruleset: &str| {
+            service.validate(Request::new(ValidateRequest {
+                log: log.to_owned(),
+                ruleset: ruleset.to_owned(),
+            }))
+        };
+        let validated = validate("{}", "").await.unwrap().into_inner();
+        assert!(!validated.valid);
+        assert_eq!(validated.errors.len(), 1);
+        let validated = validate(LOG, "").await.unwrap().into_inner();
+        assert!(validated.valid, "{:?}", validated.errors);
+        let status = validate(LOG, "nope").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let stats = service
+            .stats(Request::new(StatsRequest {
+                log: LOG.to_owned(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((stats.kyokus, stats.tsumos, stats.rons), (1, 1, 0));
+        assert_eq!(stats.ryukyokus, 0);
+    }
+}
@@ -0,0 +1,980 @@
+//! Winning hands as they stood when they won, rebuilt from the action tables
+//! of a kyoku.
+
+use crate::conv::{tenhou_kyoku_to_mjai_events, ConvertError};
+use crate::replay::MeldKind;
+use crate::replay::{DrawKind, KyokuState, Meld, ReplayError};
+use crate::tenhou::{Han, HoraDetail, Kyoku, TenhouTile, Yaku};
+use crate::{t, Event, Tile};
+use std::error::Error;
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, HandError>;
+
+#[derive(Debug)]
+pub enum HandError {
+    Convert(Box<ConvertError>),
+    Replay(ReplayError),
+    NoWinningTile {
+        who: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+    UraWithoutRiichi {
+        who: u8,
+        recorded: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+    DoraMismatch {
+        who: u8,
+        yaku: Yaku,
+        recorded: u8,
+        counted: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+    UraMismatch {
+        who: u8,
+        recorded: u8,
+        counted: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+    SituationMismatch {
+        who: u8,
+        yaku: Yaku,
+        listed: bool,
+        happened: bool,
+        kyoku: u8,
+        honba: u8,
+    },
+}
+
+impl fmt::Display for HandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Convert(err) => fmt::Display::fmt(err, f),
+            Self::Replay(err) => fmt::Display::fmt(err, f),
+            Self::NoWinningTile { who, kyoku, honba } => write!(
+                f,
+                "hora of seat {who} without a tile to win on: at kyoku {kyoku} honba {honba}"
+            ),
+            Self::UraWithoutRiichi {
+                who,
+                recorded,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "seat {who} has {recorded} ura han without riichi: \
+                at kyoku {kyoku} honba {honba}"
+            ),
+            Self::DoraMismatch {
+                who,
+                yaku,
+                recorded,
+                counted,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "seat {who} has {recorded} han of {yaku:?} but holds {counted}: \
+                at kyoku {kyoku} honba {honba}"
+            ),
+            Self::UraMismatch {
+                who,
+                recorded,
+                counted,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "seat {who} has {recorded} ura han but the indicators give {counted}: \
+                at kyoku {kyoku} honba {honba}"
+            ),
+            Self::SituationMismatch {
+                who,
+                yaku,
+                listed,
+                happened,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "seat {who} lists {yaku:?}: {listed} but won that way: {happened}: \
+                at kyoku {kyoku} honba {honba}"
+            ),
+        }
+    }
+}
+
+impl Error for HandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Convert(err) => err.source(),
+            Self::Replay(err) => err.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<Box<ConvertError>> for HandError {
+    fn from(err: Box<ConvertError>) -> Self {
+        Self::Convert(err)
+    }
+}
+
+impl From<ReplayError> for HandError {
+    fn from(err: ReplayError) -> Self {
+        Self::Replay(err)
+    }
+}
+
+/// A hand at the moment it won.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WinningHand {
+    pub who: u8,
+    /// The seat dealt into the hand, `who` itself for tsumo.
+    pub target: u8,
+    pub tsumo: bool,
+    /// The concealed tiles, sorted, the winning tile left out.
+    pub concealed: Vec<Tile>,
+    /// Every call and kan, in the order they were made.
+    pub melds: Vec<Meld>,
+    /// Nukidora set aside.
+    pub kitas: u8,
+    pub winning_tile: Tile,
+    /// Won by ron on the tile added to a pon, which never becomes a kan.
+    pub chankan: bool,
+    /// Won by tsumo on the rinshan draw after a kan or nukidora.
+    pub rinshan: bool,
+    /// Won by tsumo on the last draw of the live wall.
+    pub haitei: bool,
+    /// Won by ron on the discard after the last draw.
+    pub houtei: bool,
+    /// Every tile the hand was waiting on, see [`waits`].
+    pub waits: Vec<Tile>,
+}
+
+/// A hand right after declaring riichi.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiichiHand {
+    pub who: u8,
+    /// The tile discarded to declare riichi.
+    pub discard: Tile,
+    /// The concealed tiles, sorted.
+    pub concealed: Vec<Tile>,
+    /// Every tile the hand waits on, see [`waits`].
+    pub waits: Vec<Tile>,
+}
+
+impl fmt::Display for WinningHand {
+    /// The hand in MPSZ shorthand: the concealed tiles, every meld after a
+    /// space, open ones in brackets and ankan in parentheses, the kitas as
+    /// `4z*` and their count, and the winning tile after `+`, e.g.
+    /// `11m13678p123s [5555z] 4z*1 +2p`. Aka fives are `0`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&mpsz(&self.concealed))?;
+        for meld in &self.melds {
+            match meld.kind {
+                MeldKind::Ankan => write!(f, " ({})", mpsz(&meld.tiles))?,
+                _ => write!(f, " [{}]", mpsz(&meld.tiles))?,
+            }
+        }
+        if self.kitas > 0 {
+            write!(f, " 4z*{}", self.kitas)?;
+        }
+        write!(f, " +{}", mpsz(&[self.winning_tile]))
+    }
+}
+
+/// The yaku that come from how a hand won rather than from its tiles.
+const SITUATIONS: [Yaku; 4] = [Yaku::Chankan, Yaku::Rinshan, Yaku::Haitei, Yaku::Houtei];
+
+impl WinningHand {
+    /// Which of chankan, rinshan kaihou, haitei and houtei the hand won by.
+    pub fn situations(&self) -> impl Iterator<Item = Yaku> + '_ {
+        SITUATIONS.into_iter().filter(|yaku| match yaku {
+            Yaku::Chankan => self.chankan,
+            Yaku::Rinshan => self.rinshan,
+            Yaku::Haitei => self.haitei,
+            _ => self.houtei,
+        })
+    }
+
+    /// Every tile of the hand, the winning tile and the tiles of melds
+    /// included, but not the kitas.
+    pub fn tiles(&self) -> impl Iterator<Item = Tile> + '_ {
+        let melded = self.melds.iter().flat_map(|m| m.tiles.iter().copied());
+        self.concealed
+            .iter()
+            .copied()
+            .chain(melded)
+            .chain(std::iter::once(self.winning_tile))
+    }
+
+    /// The dora the hand holds for `indicators`, kitas counting for north.
+    #[must_use]
+    pub fn count_dora(&self, indicators: &[Tile]) -> u8 {
+        let mut count = 0;
+        for indicator in indicators {
+            let dora = indicator.deaka().next();
+            count += self.tiles().filter(|t| t.deaka() == dora).count() as u8;
+            if dora == t!(N) {
+                count += self.kitas;
+            }
+        }
+        count
+    }
+}
+
+/// `tiles` in MPSZ shorthand, sorted the way tenhou sorts hands, e.g.
+/// `11m234p5067s77z`. Unknown tiles are `?`, at the end.
+#[must_use]
+pub fn mpsz(tiles: &[Tile]) -> String {
+    let mut tiles: Vec<_> = tiles.iter().map(|&t| TenhouTile::from(t)).collect();
+    tiles.sort_by_key(|t| t.sort_key());
+    let mut ret = String::new();
+    let mut suit = None;
+    let mut unknown = 0;
+    for (number, tile_suit) in tiles.into_iter().filter_map(|t| {
+        let mpsz = t.mpsz();
+        unknown += usize::from(mpsz.is_none());
+        mpsz
+    }) {
+        if suit.is_some_and(|s| s != tile_suit) {
+            ret.extend(suit);
+        }
+        suit = Some(tile_suit);
+        ret.push(char::from(b'0' + number));
+    }
+    ret.extend(suit);
+    ret.extend(std::iter::repeat_n('?', unknown));
+    ret
+}
+
+/// The winning hand of every hora of `kyoku`, paired with its detail, in the
+/// order of [`Kyoku::horas`]. Empty on ryukyoku.
+pub fn winning_hands(kyoku: &Kyoku) -> Result<Vec<(&HoraDetail, WinningHand)>> {
+    let events = tenhou_kyoku_to_mjai_events(kyoku).map_err(Box::new)?;
+    let mut state = KyokuState::default();
+    // The last tile drawn, discarded or offered for chankan, and by whom.
+    let mut last_tile = None;
+    let mut kakan = false;
+    let mut hands = vec![];
+    for (index, event) in events.iter().enumerate() {
+        match *event {
+            Event::Tsumo { actor, pai } | Event::Dahai { actor, pai, .. } => {
+                last_tile = Some((actor, pai));
+                kakan = false;
+            }
+            Event::Kakan { actor, pai, .. } => {
+                last_tile = Some((actor, pai));
+                kakan = true;
+            }
+            Event::Nukidora { actor, .. } => {
+                last_tile = Some((actor, t!(N)));
+                kakan = false;
+            }
+            Event::Hora { actor, target, .. } => {
+                let winning_tile = match last_tile {
+                    Some((seat, tile)) if seat == target => tile,
+                    _ => {
+                        return Err(HandError::NoWinningTile {
+                            who: actor,
+                            kyoku: kyoku.meta.kyoku_num,
+                            honba: kyoku.meta.honba,
+                        })
+                    }
+                };
+                let player = &state.players[actor as usize];
+                let tsumo = actor == target;
+                let last = state.wall == 0;
+                let mut concealed = player.tehai.clone();
+                if tsumo {
+                    if let Some(at) = concealed.iter().position(|&t| t == winning_tile) {
+                        concealed.remove(at);
+                    }
+                }
+                hands.push(WinningHand {
+                    who: actor,
+                    target,
+                    tsumo,
+                    waits: waits(&concealed),
+                    concealed,
+                    melds: player.melds.clone(),
+                    kitas: player.kitas,
+                    winning_tile,
+                    chankan: kakan && !tsumo,
+                    rinshan: tsumo && state.last_draw == Some(DrawKind::Rinshan),
+                    haitei: tsumo && last && state.last_draw == Some(DrawKind::Wall),
+                    houtei: !tsumo && last && !kakan,
+                });
+            }
+            _ => (),
+        }
+        state.apply(index, event)?;
+    }
+    Ok(kyoku.horas().into_iter().zip(hands).collect())
+}
+
+/// The ura dora of every riichi winner of `kyoku`, counted from the ura
+/// indicators and paired with its detail, in the order of [`Kyoku::horas`].
+pub fn ura_counts(kyoku: &Kyoku) -> Result<Vec<(&HoraDetail, u8)>> {
+    let indicators = kyoku.ura_indicators.as_deref().unwrap_or_default();
+    Ok(winning_hands(kyoku)?
+        .into_iter()
+        .filter(|(detail, _)| is_riichi(detail))
+        .map(|(detail, hand)| (detail, hand.count_dora(indicators)))
+        .collect())
+}
+
+/// Check that the dora, aka dora and nukidora every hora of `kyoku` lists
+/// are what its hand holds.
+///
+/// Every kita is worth a han, which tenhou adds to the dora while other
+/// sources list it as nukidora; either is accepted. Where north is dora,
+/// kitas count for that as well. Yakuman are not checked, as they do not
+/// list dora.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(kyoku = kyoku.meta.kyoku_num, honba = kyoku.meta.honba),
+    )
+)]
+pub fn check_dora(kyoku: &Kyoku) -> Result<()> {
+    for (detail, hand) in winning_hands(kyoku)? {
+        if detail
+            .yaku
+            .iter()
+            .any(|(_, han)| matches!(han, Han::Yakuman(_)))
+        {
+            continue;
+        }
+        let dora = hand.count_dora(&kyoku.dora_indicators);
+        let (dora, nukidora) = if detail.yaku.iter().any(|(y, _)| *y == Yaku::Nukidora) {
+            (dora, hand.kitas)
+        } else {
+            (dora + hand.kitas, 0)
+        };
+        let aka = hand.tiles().filter(|t| t.is_aka()).count() as u8;
+        let counts = [
+            (Yaku::Dora, dora),
+            (Yaku::Akadora, aka),
+            (Yaku::Nukidora, nukidora),
+        ];
+        for (yaku, counted) in counts {
+            let recorded = recorded_han(detail, &yaku);
+            if recorded != counted {
+                return Err(HandError::DoraMismatch {
+                    who: detail.who,
+                    yaku,
+                    recorded,
+                    counted,
+                    kyoku: kyoku.meta.kyoku_num,
+                    honba: kyoku.meta.honba,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that only riichi winners of `kyoku` have ura han and that theirs
+/// are what the ura indicators give.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(kyoku = kyoku.meta.kyoku_num, honba = kyoku.meta.honba),
+    )
+)]
+pub fn check_ura(kyoku: &Kyoku) -> Result<()> {
+    let (kyoku_num, honba) = (kyoku.meta.kyoku_num, kyoku.meta.honba);
+    for detail in kyoku.horas() {
+        let recorded = recorded_han(detail, &Yaku::Uradora);
+        if !is_riichi(detail) && recorded > 0 {
+            return Err(HandError::UraWithoutRiichi {
+                who: detail.who,
+                recorded,
+                kyoku: kyoku_num,
+                honba,
+            });
+        }
+    }
+    for (detail, counted) in ura_counts(kyoku)? {
+        let recorded = recorded_han(detail, &Yaku::Uradora);
+        if recorded != counted {
+            return Err(HandError::UraMismatch {
+                who: detail.who,
+                recorded,
+                counted,
+                kyoku: kyoku_num,
+                honba,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Check that the horas of `kyoku` list chankan, rinshan kaihou, haitei and
+/// houtei exactly when they won that way, see [`WinningHand::situations`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(kyoku = kyoku.meta.kyoku_num, honba = kyoku.meta.honba),
+    )
+)]
+pub fn check_situations(kyoku: &Kyoku) -> Result<()> {
+    for (detail, hand) in winning_hands(kyoku)? {
+        for yaku in SITUATIONS {
+            let listed = detail.yaku.iter().any(|(y, _)| *y == yaku);
+            let happened = hand.situations().any(|y| y == yaku);
+            if listed != happened {
+                return Err(HandError::SituationMismatch {
+                    who: detail.who,
+                    yaku,
+                    listed,
+                    happened,
+                    kyoku: kyoku.meta.kyoku_num,
+                    honba: kyoku.meta.honba,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_riichi(detail: &HoraDetail) -> bool {
+    detail
+        .yaku
+        .iter()
+        .any(|(yaku, _)| matches!(yaku, Yaku::Riichi | Yaku::DoubleRiichi))
+}
+
+/// The han `detail` lists for `yaku`, `0` if it does not list it.
+fn recorded_han(detail: &HoraDetail, yaku: &Yaku) -> u8 {
+    detail
+        .yaku
+        .iter()
+        .find(|(y, _)| y == yaku)
+        .map_or(0, |(_, han)| match *han {
+            Han::Regular(han) => han,
+            Han::Yakuman(_) => 0,
+        })
+}
+
+/// The hand of every riichi of `kyoku` right after the declaring discard,
+/// in the order they were declared.
+pub fn riichi_hands(kyoku: &Kyoku) -> Result<Vec<RiichiHand>> {
+    let events = tenhou_kyoku_to_mjai_events(kyoku).map_err(Box::new)?;
+    let mut state = KyokuState::default();
+    let mut hands = vec![];
+    for (index, event) in events.iter().enumerate() {
+        state.apply(index, event)?;
+        if let Event::Dahai { actor, pai, .. } = *event {
+            if matches!(events[index - 1], Event::Reach { actor: a } if a == actor) {
+                let concealed = state.players[actor as usize].tehai.clone();
+                hands.push(RiichiHand {
+                    who: actor,
+                    discard: pai,
+                    waits: waits(&concealed),
+                    concealed,
+                });
+            }
+        }
+    }
+    Ok(hands)
+}
+
+/// Every tile that completes `concealed`, a hand of 3n+1 tiles whose other
+/// sets are melded, sorted and without aka. Tiles the hand itself holds all
+/// four of are included. Empty if the hand is not tenpai or holds unknown
+/// tiles.
+#[must_use]
+pub fn waits(concealed: &[Tile]) -> Vec<Tile> {
+    let mut counts = [0_u8; KINDS];
+    for tile in concealed {
+        if tile.is_unknown() {
+            return vec![];
+        }
+        counts[tile.deaka().as_usize()] += 1;
+    }
+    let mut ret = vec![];
+    for kind in 0..KINDS {
+        counts[kind] += 1;
+        if is_agari(&mut counts) {
+            ret.extend(Tile::try_from(kind).ok());
+        }
+        counts[kind] -= 1;
+    }
+    ret.sort_unstable();
+    ret
+}
+
+/// How many tiles `concealed`, a hand of 3n+1 or 3n+2 tiles whose other
+/// sets are melded, is away from tenpai: `0` for tenpai and `-1` for a
+/// complete hand. Seven pairs and kokushi count for closed hands of 13 or
+/// 14 tiles. Unknown tiles count as tiles of no use.
+#[must_use]
+pub fn shanten(concealed: &[Tile]) -> i8 {
+    let mut counts = [0_u8; KINDS];
+    for tile in concealed.iter().filter(|t| !t.is_unknown()) {
+        counts[tile.deaka().as_usize()] += 1;
+    }
+    let sets = (concealed.len() / 3) as i8;
+    let mut best = 2 * sets - blocks(&mut counts, 0, 0, 0, sets);
+    for pair in 0..KINDS {
+        if counts[pair] >= 2 {
+            counts[pair] -= 2;
+            best = best.min(2 * sets - 1 - blocks(&mut counts, 0, 0, 0, sets));
+            counts[pair] += 2;
+        }
+    }
+    if concealed.len() >= 13 {
+        let kinds = counts.iter().filter(|&&c| c > 0).count() as i8;
+        let pairs = counts.iter().filter(|&&c| c >= 2).count() as i8;
+        best = best.min(6 - pairs + (7 - kinds).max(0));
+
+        let yaokyuu = |kind: usize| Tile::try_from(kind).is_ok_and(Tile::is_yaokyuu);
+        let orphans = (0..KINDS).filter(|&k| yaokyuu(k) && counts[k] > 0).count() as i8;
+        let pair = (0..KINDS).any(|k| yaokyuu(k) && counts[k] >= 2);
+        best = best.min(13 - orphans - i8::from(pair));
+    }
+    best
+}
+
+/// The most of two per set and one per pair or partial run that the tiles
+/// counted by `counts` from `kind` on make, with `sets` and `partial`
+/// already made and at most `max` of both. `counts` is left as it was.
+fn blocks(counts: &mut [u8; KINDS], mut kind: usize, sets: i8, partial: i8, max: i8) -> i8 {
+    while kind < KINDS && counts[kind] == 0 {
+        kind += 1;
+    }
+    if kind == KINDS || sets + partial == max {
+        return 2 * sets + partial;
+    }
+    let suited = kind < 18;
+    let mut best = 0;
+    let mut take = |counts: &mut [u8; KINDS], tiles: &[usize], sets, partial| {
+        if tiles.iter().all(|&k| counts[k] > 0) {
+            for &k in tiles {
+                counts[k] -= 1;
+            }
+            best = best.max(blocks(counts, kind, sets, partial, max));
+            for &k in tiles {
+                counts[k] += 1;
+            }
+        }
+    };
+    if counts[kind] >= 3 {
+        take(counts, &[kind; 3], sets + 1, partial);
+    }
+    // Runs only exist in pinzu and souzu, 1m and 9m being the only manzu.
+    if suited && kind % 9 < 7 {
+        take(counts, &[kind, kind + 1, kind + 2], sets + 1, partial);
+        take(counts, &[kind, kind + 2], sets, partial + 1);
+    }
+    if suited && kind % 9 < 8 {
+        take(counts, &[kind, kind + 1], sets, partial + 1);
+    }
+    if counts[kind] >= 2 {
+        take(counts, &[kind; 2], sets, partial + 1);
+    }
+    // Or the tile is of no use.
+    take(counts, &[kind], sets, partial);
+    best
+}
+
+/// The number of kinds of tiles in sanma, 1m to 9s and the honors, indexed
+/// by [`Tile::as_usize`] of the plain tile.
+const KINDS: usize = 27;
+
+/// Whether the tiles counted by `counts` form a complete hand: sets and a
+/// pair, seven pairs, or kokushi. `counts` is left as it was.
+fn is_agari(counts: &mut [u8; KINDS]) -> bool {
+    let total: u8 = counts.iter().sum();
+    if total % 3 != 2 {
+        return false;
+    }
+    if total == 14 {
+        if counts.iter().map(|&c| u8::from(c == 2)).sum::<u8>() == 7 {
+            return true;
+        }
+        let yaokyuu = |kind: usize| Tile::try_from(kind).is_ok_and(Tile::is_yaokyuu);
+        let is_kokushi = (0..KINDS).all(|k| !yaokyuu(k) || counts[k] > 0)
+            && (0..KINDS).all(|k| yaokyuu(k) || counts[k] == 0)
+            && counts.contains(&2);
+        if is_kokushi {
+            return true;
+        }
+    }
+    is_pair_and_sets(counts)
+}
+
+/// Whether `concealed`, a hand of 3n+2 tiles whose other sets are melded,
+/// completes as sets and a pair, leaving seven pairs and kokushi aside.
+pub(crate) fn is_standard_agari(concealed: &[Tile]) -> bool {
+    let mut counts = [0_u8; KINDS];
+    for tile in concealed {
+        if tile.is_unknown() {
+            return false;
+        }
+        counts[tile.deaka().as_usize()] += 1;
+    }
+    concealed.len() % 3 == 2 && is_pair_and_sets(&mut counts)
+}
+
+/// Whether the tiles counted by `counts` form sets and a pair.
+fn is_pair_and_sets(counts: &mut [u8; KINDS]) -> bool {
+    for pair in 0..KINDS {
+        if counts[pair] >= 2 {
+            counts[pair] -= 2;
+            let complete = is_sets(counts);
+            counts[pair] += 2;
+            if complete {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Whether the tiles counted by `counts` form sets only.
+fn is_sets(counts: &mut [u8; KINDS]) -> bool {
+    let Some(kind) = counts.iter().position(|&c| c > 0) else {
+        return true;
+    };
+    if counts[kind] >= 3 {
+        counts[kind] -= 3;
+        let complete = is_sets(counts);
+        counts[kind] += 3;
+        if complete {
+            return true;
+        }
+    }
+    // Runs only exist in pinzu and souzu, 1m and 9m being the only manzu.
+    let runs = kind < 18 && kind % 9 < 7 && counts[kind + 1] > 0 && counts[kind + 2] > 0;
+    if runs {
+        for count in &mut counts[kind..kind + 3] {
+            *count -= 1;
+        }
+        let complete = is_sets(counts);
+        for count in &mut counts[kind..kind + 3] {
+            *count += 1;
+        }
+        return complete;
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tenhou::EndStatus;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn rinshan() {
+        let log = Fixture::KanHeavy.log();
+        let hands = winning_hands(&log.kyokus[0]).unwrap();
+        let [(detail, hand)] = &*hands else {
+            panic!("expected one hand, got {hands:?}");
+        };
+        assert_eq!(detail.who, 0);
+        assert!(hand.tsumo);
+        assert_eq!(hand.winning_tile, t!(2p));
+        assert_eq!(hand.situations().collect::<Vec<_>>(), [Yaku::Rinshan]);
+        check_situations(&log.kyokus[0]).unwrap();
+        let mut concealed = t![1m, 1m, 1p, 3p, 6p, 7p, 8p, 1s, 2s, 3s].to_vec();
+        concealed.sort_unstable();
+        assert_eq!(hand.concealed, concealed);
+        let kinds: Vec<_> = hand.melds.iter().map(|m| m.kind).collect();
+        assert_eq!(kinds, [MeldKind::Kakan]);
+        assert_eq!(hand.melds[0].fu(), 16);
+        assert_eq!(hand.kitas, 0);
+        assert_eq!(hand.to_string(), "11m13678p123s [5555z] +2p");
+        assert_eq!(hand.count_dora(&t![C, 9m, 1p]), 7);
+    }
+
+    #[test]
+    fn waits() {
+        let waits = |tiles: &[Tile]| super::waits(tiles);
+        assert_eq!(
+            waits(&t![1p, 2p, 3p, 4p, 5p, 6p, 7p, 8p, 9p, 1s, 1s, 2s, 3s]),
+            t![1s, 4s]
+        );
+        assert_eq!(
+            waits(&t![2p, 3p, 4p, 5p, 6p, 7p, 8p, E, E, E, S, S, S]),
+            t![2p, 5p, 8p]
+        );
+        assert_eq!(
+            waits(&t![1p, 1p, 1p, 2p, 3p, 4p, 5p, 6p, 7p, 8p, 9p, 9p, 9p]).len(),
+            9
+        );
+        assert_eq!(
+            waits(&t![1m, 9m, 1p, 9p, 1s, 9s, E, S, W, N, P, F, C]).len(),
+            13,
+        );
+        assert_eq!(
+            waits(&t![1m, 1m, 9m, 9m, 1p, 1p, 2p, 2p, E, E, P, P, C]),
+            [t!(C)]
+        );
+        assert_eq!(waits(&t![5pr, 6p, E, E]), t![4p, 7p]);
+        assert_eq!(waits(&t![1m, 2p, 3p, 4p]), [t!(1m)]);
+        assert_eq!(waits(&t![1m, 9m, 1p, 4p]), []);
+        assert_eq!(waits(&t![?, 1p, 1p, 1p]), []);
+    }
+
+    #[test]
+    fn shanten() {
+        let shanten = |tiles: &[Tile]| super::shanten(tiles);
+        assert_eq!(
+            shanten(&t![1p, 2p, 3p, 4p, 5p, 6p, 7p, 8p, 9p, 1s, 1s, 2s, 3s]),
+            0
+        );
+        assert_eq!(
+            shanten(&t![1p, 2p, 3p, 4p, 5p, 6p, 7p, 8p, 9p, 1s, 1s, 2s, 3s, 4s]),
+            -1
+        );
+        assert_eq!(
+            shanten(&t![1p, 2p, 3p, 4p, 5p, 6p, 7p, 8p, 9p, 1s, 1s, 2s, 9s, E]),
+            1
+        );
+        assert_eq!(
+            shanten(&t![1p, 4p, 7p, 1s, 4s, 7s, E, S, W, N, P, F, 1m]),
+            4
+        );
+        assert_eq!(
+            shanten(&t![1m, 9m, 1p, 9p, 1s, 9s, E, S, W, N, P, F, 2p]),
+            1
+        );
+        assert_eq!(
+            shanten(&t![1m, 1m, 9m, 9m, 1p, 1p, 2p, 2p, E, E, P, P, 5s]),
+            0
+        );
+        assert_eq!(
+            shanten(&t![1m, 1m, 9m, 9m, 1p, 1p, 2p, 2p, E, E, P, 5s, 7s]),
+            1
+        );
+        assert_eq!(shanten(&t![5pr, 6p, E, E]), 0);
+        assert_eq!(shanten(&t![1m, 1p, 5s, 9s]), 2);
+        assert_eq!(shanten(&t![?, 1p, 1p, 1p]), 0);
+    }
+
+    #[test]
+    fn riichi() {
+        let log = Fixture::Hanchan.log();
+        let mut riichis = 0;
+        for kyoku in &log.kyokus {
+            let hands = riichi_hands(kyoku).unwrap();
+            for hand in &hands {
+                assert_eq!(hand.concealed.len(), 13);
+                assert_ne!(hand.waits, []);
+            }
+            riichis += hands.len();
+
+            for (_, hand) in winning_hands(kyoku).unwrap() {
+                assert!(hand.waits.contains(&hand.winning_tile.deaka()));
+            }
+        }
+        assert_eq!(riichis, 7);
+    }
+
+    #[test]
+    fn ura() {
+        let mut log = Fixture::Hanchan.log();
+        let mut counted = vec![];
+        for kyoku in &log.kyokus {
+            check_ura(kyoku).unwrap();
+            counted.extend(
+                ura_counts(kyoku)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(d, n)| (d.who, n)),
+            );
+        }
+        // No riichi winner hit an ura dora.
+        assert_eq!(counted, [(2, 0), (1, 0), (1, 0), (0, 0), (2, 0)]);
+
+        // E2-1 is won by seat 1 without riichi, E3 by seat 2 with riichi.
+        for (index, yaku) in [(2, Yaku::Uradora), (4, Yaku::Uradora)] {
+            let EndStatus::Hora { details } = &mut log.kyokus[index].end_status else {
+                unreachable!();
+            };
+            details[0].yaku.push((yaku, Han::Regular(1)));
+        }
+        assert!(matches!(
+            check_ura(&log.kyokus[2]),
+            Err(HandError::UraWithoutRiichi { recorded: 1, .. }),
+        ));
+        assert!(matches!(
+            check_ura(&log.kyokus[4]),
+            Err(HandError::UraMismatch {
+                recorded: 1,
+                counted: 0,
+                ..
+            }),
+        ));
+    }
+
+    #[test]
+    fn dora() {
+        let mut log = Fixture::Hanchan.log();
+        for kyoku in &log.kyokus {
+            check_dora(kyoku).unwrap();
+        }
+
+        // In E2 seat 1 wins with two kitas and no other dora, which tenhou
+        // lists as two han of dora.
+        fn yaku(kyoku: &mut Kyoku) -> &mut Vec<(Yaku, Han)> {
+            match &mut kyoku.end_status {
+                EndStatus::Hora { details } => &mut details[0].yaku,
+                EndStatus::Ryukyoku { .. } => unreachable!(),
+            }
+        }
+        let kyoku = &mut log.kyokus[1];
+        let dora = yaku(kyoku).iter_mut().find(|(y, _)| *y == Yaku::Dora);
+        dora.unwrap().0 = Yaku::Nukidora;
+        check_dora(kyoku).unwrap();
+        yaku(kyoku).push((Yaku::Akadora, Han::Regular(1)));
+        assert!(matches!(
+            check_dora(kyoku),
+            Err(HandError::DoraMismatch {
+                yaku: Yaku::Akadora,
+                recorded: 1,
+                counted: 0,
+                ..
+            }),
+        ));
+    }
+
+    #[test]
+    fn chankan() {
+        let mut log = Fixture::KanHeavy.log();
+        for kyoku in &log.kyokus {
+            check_situations(kyoku).unwrap();
+        }
+
+        // Seat 0 adds P to its pon and seat 1 robs it instead of seat 0
+        // drawing its rinshan 2p.
+        let kyoku = &mut log.kyokus[0];
+        let mut takes = kyoku.action_tables[0].takes.to_vec();
+        takes.pop();
+        kyoku.action_tables[0].takes = takes.into();
+        let EndStatus::Hora { details } = &mut kyoku.end_status else {
+            unreachable!();
+        };
+        details[0].who = 1;
+        details[0].target = 0;
+        let err = check_situations(kyoku).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                HandError::SituationMismatch {
+                    who: 1,
+                    yaku: Yaku::Chankan,
+                    listed: false,
+                    happened: true,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+
+        let EndStatus::Hora { details } = &mut kyoku.end_status else {
+            unreachable!();
+        };
+        let rinshan = details[0]
+            .yaku
+            .iter_mut()
+            .find(|(y, _)| *y == Yaku::Rinshan);
+        rinshan.unwrap().0 = Yaku::Chankan;
+        check_situations(kyoku).unwrap();
+        let hands = winning_hands(kyoku).unwrap();
+        let (_, hand) = &hands[0];
+        assert_eq!(hand.situations().collect::<Vec<_>>(), [Yaku::Chankan]);
+        assert_eq!((hand.target, hand.winning_tile), (0, t!(P)));
+
+        let events = tenhou_kyoku_to_mjai_events(kyoku).unwrap();
+        let [.., kakan, hora, _] = &*events else {
+            unreachable!();
+        };
+        assert!(matches!(kakan, Event::Kakan { actor: 0, .. }));
+        assert!(matches!(
+            hora,
+            Event::Hora {
+                actor: 1,
+                target: 0,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn haitei() {
+        let mut log = Fixture::Hanchan.log();
+        for kyoku in &log.kyokus {
+            check_situations(kyoku).unwrap();
+            for (_, hand) in winning_hands(kyoku).unwrap() {
+                assert!(!hand.haitei && !hand.houtei);
+            }
+        }
+
+        let kyoku = &mut log.kyokus[0];
+        let EndStatus::Hora { details } = &mut kyoku.end_status else {
+            unreachable!();
+        };
+        details[0].yaku.push((Yaku::Haitei, Han::Regular(1)));
+        let err = check_situations(kyoku).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                HandError::SituationMismatch {
+                    yaku: Yaku::Haitei,
+                    listed: true,
+                    happened: false,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn mpsz() {
+        assert_eq!(
+            super::mpsz(&t![C, 5pr, 1s, 5p, 1m, E, 9m, 3p, N]),
+            "19m350p1s147z"
+        );
+        assert_eq!(super::mpsz(&t![6s, ?, 5s, 5sr]), "506s?");
+        assert_eq!(super::mpsz(&[]), "");
+    }
+
+    #[test]
+    fn multi_ron() {
+        let log = Fixture::MultiRon.log();
+        let hands = winning_hands(&log.kyokus[0]).unwrap();
+        assert_eq!(hands.len(), 2);
+        for (detail, hand) in hands {
+            assert_eq!((hand.who, hand.target), (detail.who, detail.target));
+            assert!(!hand.tsumo);
+            assert_eq!(hand.winning_tile, t!(4p));
+            assert_eq!(hand.concealed.len(), 13);
+        }
+
+        let hanchan = Fixture::Hanchan.log();
+        for kyoku in &hanchan.kyokus {
+            for (_, hand) in winning_hands(kyoku).unwrap() {
+                let tiles = hand.concealed.len() + 3 * hand.melds.len();
+                assert_eq!(tiles, 13);
+            }
+        }
+    }
+}
@@ -0,0 +1,357 @@
+//! Notable events of a corpus for highlight reels: yakuman, haneman and
+//! above, big kita hands, double riichi and multi ron, each with the game
+//! and kyoku it happened in and a link to it in tenhou's viewer.
+
+use crate::mjai::Event;
+use crate::stats::csv_field;
+use crate::tenhou::{started_at, EndStatus, Limit, Log};
+use crate::{t, Points};
+use std::io;
+
+use serde::Serialize;
+
+/// The han kitas must be worth as dora for a hora to be a highlight.
+const KITA_HAN: usize = 9;
+
+/// What makes a [`Highlight`] notable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HighlightKind {
+    Yakuman,
+    /// A haneman, baiman or sanbaiman.
+    Haneman,
+    /// A hora whose kitas are worth at least 9 han as dora, each counting
+    /// once for itself and once more for every dora or ura indicator that
+    /// is a west.
+    Kitas,
+    /// A riichi declared with the first discard, before any call.
+    DoubleRiichi,
+    /// More than one seat won off the same discard.
+    MultiRon,
+}
+
+impl HighlightKind {
+    /// The name in CSV and Markdown, as in JSON, e.g. `double_riichi`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Yakuman => "yakuman",
+            Self::Haneman => "haneman",
+            Self::Kitas => "kitas",
+            Self::DoubleRiichi => "double_riichi",
+            Self::MultiRon => "multi_ron",
+        }
+    }
+}
+
+/// A notable event and where it happened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Highlight {
+    pub game_id: Option<String>,
+    /// Counting from 0, as the `ts` of tenhou's viewer does.
+    pub kyoku_index: usize,
+    /// The kyoku, e.g. `E4-0`.
+    pub kyoku: String,
+    pub kind: HighlightKind,
+    /// The winners, or the seat that declared riichi.
+    pub players: Vec<String>,
+    /// What the winners got, honba and riichi sticks included.
+    pub points: Option<Points>,
+    /// What happened, e.g. the yaku of a hora or the seat dealing in to a
+    /// multi ron.
+    pub detail: String,
+    /// The kyoku in tenhou's viewer, for tenhou game IDs.
+    pub link: Option<String>,
+}
+
+/// The [`Highlight`]s of a corpus, game by game in the order they were
+/// added and by kyoku within a game.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Highlights {
+    highlights: Vec<Highlight>,
+}
+
+impl Highlights {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Find the highlights of `log` and `events` converted from it.
+    pub fn add(&mut self, log: &Log, events: &[Event]) {
+        let mut found = vec![];
+        let highlight = |index: usize, kind, seats: &[usize], points, detail| {
+            let link = log
+                .game_id
+                .as_deref()
+                .filter(|id| started_at(id).is_some())
+                .map(|id| format!("https://tenhou.net/0/?log={id}&tw={}&ts={index}", seats[0]));
+            Highlight {
+                game_id: log.game_id.clone(),
+                kyoku_index: index,
+                kyoku: log
+                    .kyokus
+                    .get(index)
+                    .map_or_else(String::new, |kyoku| kyoku.meta.short_name()),
+                kind,
+                players: seats.iter().map(|&seat| log.names[seat].clone()).collect(),
+                points,
+                detail,
+                link,
+            }
+        };
+
+        let mut index = None;
+        let mut kitas = [0; 4];
+        let mut markers = vec![];
+        let mut discarded = [false; 4];
+        let mut called = false;
+        for event in events {
+            match event {
+                Event::StartKyoku { dora_marker, .. } => {
+                    index = Some(index.map_or(0, |i| i + 1));
+                    kitas = [0; 4];
+                    markers = vec![*dora_marker];
+                    discarded = [false; 4];
+                    called = false;
+                }
+                Event::Dora { dora_marker } => markers.push(*dora_marker),
+                Event::Nukidora { actor, .. } => kitas[usize::from(*actor)] += 1,
+                Event::Dahai { actor, .. } => discarded[usize::from(*actor)] = true,
+                Event::Chi { .. }
+                | Event::Pon { .. }
+                | Event::Daiminkan { .. }
+                | Event::Kakan { .. }
+                | Event::Ankan { .. } => called = true,
+                Event::Reach { actor } => {
+                    let seat = usize::from(*actor);
+                    if let (Some(index), false, false) = (index, called, discarded[seat]) {
+                        let kind = HighlightKind::DoubleRiichi;
+                        found.push(highlight(index, kind, &[seat], None, String::new()));
+                    }
+                }
+                Event::Hora {
+                    actor,
+                    deltas,
+                    ura_markers,
+                    ..
+                } => {
+                    let seat = usize::from(*actor);
+                    let wests = markers
+                        .iter()
+                        .chain(ura_markers.iter().flatten())
+                        .filter(|marker| marker.next() == t!(N))
+                        .count();
+                    let han = kitas[seat] * (1 + wests);
+                    if let (Some(index), true) = (index, han >= KITA_HAN) {
+                        let points = deltas.map(|deltas| deltas[seat]);
+                        let detail = format!("{} kitas, {han} han", kitas[seat]);
+                        found.push(highlight(
+                            index,
+                            HighlightKind::Kitas,
+                            &[seat],
+                            points,
+                            detail,
+                        ));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        for (index, kyoku) in log.kyokus.iter().enumerate() {
+            let EndStatus::Hora { details } = &kyoku.end_status else {
+                continue;
+            };
+            for detail in details {
+                let kind = match detail.score.and_then(|score| score.limit) {
+                    Some(Limit::Yakuman) => HighlightKind::Yakuman,
+                    Some(Limit::Haneman | Limit::Baiman | Limit::Sanbaiman) => {
+                        HighlightKind::Haneman
+                    }
+                    _ => continue,
+                };
+                let seat = usize::from(detail.who);
+                let yaku: Vec<_> = detail
+                    .yaku
+                    .iter()
+                    .map(|(yaku, han)| format!("{yaku}({han})"))
+                    .collect();
+                let points = Some(detail.score_deltas[seat]);
+                found.push(highlight(index, kind, &[seat], points, yaku.join(" ")));
+            }
+
+            let rons: Vec<_> = details.iter().filter(|d| !d.is_tsumo()).collect();
+            if rons.len() > 1 {
+                let seats: Vec<_> = rons.iter().map(|d| usize::from(d.who)).collect();
+                let points = rons
+                    .iter()
+                    .map(|d| d.score_deltas[usize::from(d.who)])
+                    .sum();
+                let loser = &log.names[usize::from(rons[0].target)];
+                let detail = format!("{} rons off {loser}", rons.len());
+                found.push(highlight(
+                    index,
+                    HighlightKind::MultiRon,
+                    &seats,
+                    Some(points),
+                    detail,
+                ));
+            }
+        }
+
+        found.sort_by_key(|h| (h.kyoku_index, h.kind));
+        self.highlights.extend(found);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Highlight> {
+        self.highlights.iter()
+    }
+
+    /// Write the highlights as CSV with a header row.
+    pub fn write_csv<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "game_id,kyoku,kind,players,points,detail,link")?;
+        for h in &self.highlights {
+            writeln!(
+                w,
+                "{},{},{},{},{},{},{}",
+                csv_field(h.game_id.as_deref().unwrap_or_default()),
+                h.kyoku,
+                h.kind.name(),
+                csv_field(&h.players.join(" ")),
+                h.points.map(|p| p.to_string()).unwrap_or_default(),
+                csv_field(&h.detail),
+                h.link.as_deref().unwrap_or_default(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the highlights as a Markdown table, kyokus linked to tenhou's
+    /// viewer where they can be.
+    pub fn write_markdown<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "| Game | Kyoku | Kind | Players | Points | Detail |")?;
+        writeln!(w, "| :--- | :--- | :--- | :--- | ---: | :--- |")?;
+        for h in &self.highlights {
+            let kyoku = match &h.link {
+                Some(link) => format!("[{}]({link})", h.kyoku),
+                None => h.kyoku.clone(),
+            };
+            writeln!(
+                w,
+                "| {} | {kyoku} | {} | {} | {} | {} |",
+                h.game_id.as_deref().unwrap_or_default(),
+                h.kind.name(),
+                h.players.join(", ").replace('|', "\\|"),
+                h.points.map(|p| p.to_string()).unwrap_or_default(),
+                h.detail.replace('|', "\\|"),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn highlights() {
+        let mut highlights = Highlights::new();
+        for fixture in Fixture::ALL {
+            let log = fixture.log();
+            highlights.add(&log, &tenhou_to_mjai(&log).unwrap());
+        }
+        let kinds: Vec<_> = highlights.iter().map(|h| h.kind).collect();
+        use HighlightKind::*;
+        assert_eq!(kinds, [Haneman, Haneman, Haneman, MultiRon]);
+        let multi_ron = highlights.iter().last().unwrap();
+        assert_eq!(multi_ron.players, ["Botan", "Chidori"]);
+        assert_eq!(multi_ron.detail, "2 rons off Aoi");
+        assert_eq!(
+            multi_ron.link.as_deref(),
+            Some("https://tenhou.net/0/?log=2024040112gm-00b9-0000-1a2b3c4d&tw=1&ts=0")
+        );
+
+        let mut csv = vec![];
+        highlights.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(
+            csv.lines()
+                .nth(1)
+                .unwrap()
+                .starts_with("2024030511gm-00b9-0000-e0c07689,E2-2,haneman,mtk,12400,"),
+            "{csv}"
+        );
+        let mut markdown = vec![];
+        highlights.write_markdown(&mut markdown).unwrap();
+        let markdown = String::from_utf8(markdown).unwrap();
+        assert!(
+            markdown.contains("| [E1-0](https://tenhou.net/0/"),
+            "{markdown}"
+        );
+    }
+
+    #[test]
+    fn double_riichi_and_kitas() {
+        let log = Fixture::Hanchan.log();
+        let events = [
+            Event::StartKyoku {
+                bakaze: t!(E),
+                dora_marker: t!(W),
+                kyoku: 1,
+                honba: 0,
+                kyotaku: 0,
+                oya: 0,
+                scores: [35000, 35000, 35000, 0],
+                tehais: Default::default(),
+            },
+            Event::Reach { actor: 0 },
+            Event::Dahai {
+                actor: 0,
+                pai: t!(1m),
+                tsumogiri: false,
+            },
+            Event::Pon {
+                actor: 2,
+                target: 0,
+                pai: t!(1m),
+                consumed: t![1m, 1m],
+            },
+            Event::Reach { actor: 1 },
+            Event::Nukidora {
+                actor: 0,
+                consumed: [t!(N)],
+            },
+            Event::Nukidora {
+                actor: 0,
+                consumed: [t!(N)],
+            },
+            Event::Nukidora {
+                actor: 0,
+                consumed: [t!(N)],
+            },
+            Event::Hora {
+                actor: 0,
+                target: 0,
+                deltas: Some([24000, -12000, -12000, 0]),
+                sticks: None,
+                ura_markers: Some(vec![t!(W)]),
+            },
+        ];
+        let mut highlights = Highlights::new();
+        highlights.add(&log, &events);
+        let found: Vec<_> = highlights.iter().filter(|h| h.kyoku_index == 0).collect();
+        // The second riichi comes after a pon, and the hanchan has no
+        // highlight of its own in its first kyoku.
+        assert_eq!(found.len(), 2, "{found:?}");
+        assert_eq!(found[0].kind, HighlightKind::Kitas);
+        assert_eq!(found[0].detail, "3 kitas, 9 han");
+        assert_eq!(found[0].points, Some(24000));
+        assert_eq!(found[1].kind, HighlightKind::DoubleRiichi);
+        assert_eq!(found[1].players, ["mtk"]);
+    }
+}
@@ -0,0 +1,186 @@
+//! Callbacks run on every kyoku of a converted game, for tags of their own
+//! such as "study this hand" markers.
+//!
+//! Where an [`Analyzer`](crate::analyzer::Analyzer) sees one event at a time,
+//! a hook registered with [`KyokuHooks`] sees a whole kyoku at once: the
+//! kyoku as the tenhou log has it, its mjai events and the state after each
+//! of them. What it adds to the metadata it is handed ends up in the
+//! [`Game::annotations`] of the kyoku's `start_kyoku`, which
+//! [`Mjai`](crate::ir::Mjai) writes as its `annotations`.
+
+use crate::ir::{Game, GameMeta};
+use crate::replay::{self, KyokuState};
+use crate::tenhou::{Kyoku, Log};
+use crate::Event;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value;
+
+/// A converted kyoku as a hook sees it.
+#[derive(Debug, Clone, Copy)]
+pub struct KyokuContext<'a> {
+    pub meta: &'a GameMeta,
+    /// The kyoku in the log the game was converted from.
+    pub kyoku: &'a Kyoku,
+    /// Its events, from `start_kyoku` to `end_kyoku`.
+    pub events: &'a [Event],
+    /// The state of the kyoku after each of `events`.
+    pub states: &'a [KyokuState],
+}
+
+/// A hook, given a kyoku and the annotations of its `start_kyoku` to add to.
+pub type KyokuHook = Box<dyn FnMut(&KyokuContext<'_>, &mut BTreeMap<String, Value>)>;
+
+/// The hooks a game is run through, in the order they were registered.
+#[derive(Default)]
+pub struct KyokuHooks {
+    hooks: Vec<KyokuHook>,
+}
+
+impl KyokuHooks {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: FnMut(&KyokuContext<'_>, &mut BTreeMap<String, Value>) + 'static,
+    {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Replay `game`, converted from `log`, and run every hook on each of
+    /// its kyokus, keeping what they add in [`Game::annotations`].
+    ///
+    /// Kyokus are matched with those of the log by their number and honba
+    /// in order, so that games converted with kyokus skipped still match.
+    /// Kyokus without a match in the log are passed over.
+    ///
+    /// # Errors
+    ///
+    /// Fails for events that cannot be replayed, leaving the annotations as
+    /// they were.
+    pub fn run(&mut self, log: &Log, game: &mut Game) -> replay::Result<()> {
+        let mut annotations = game
+            .annotations
+            .clone()
+            .unwrap_or_else(|| vec![BTreeMap::new(); game.events.len()]);
+        annotations.resize_with(game.events.len(), BTreeMap::new);
+
+        let mut kyokus = log.kyokus.iter();
+        let mut state = KyokuState::default();
+        let mut states = vec![];
+        let mut start = 0;
+        for (index, event) in game.events.iter().enumerate() {
+            state.apply(index, event)?;
+            if matches!(event, Event::StartKyoku { .. }) {
+                start = index;
+                states.clear();
+            }
+            states.push(state.clone());
+            if !matches!(event, Event::EndKyoku) {
+                continue;
+            }
+            let Event::StartKyoku { kyoku, honba, .. } = game.events[start] else {
+                continue;
+            };
+            let Some(typed) = kyokus
+                .by_ref()
+                .find(|k| (k.meta.kyoku_num % 4 + 1, k.meta.honba) == (kyoku, honba))
+            else {
+                continue;
+            };
+            let context = KyokuContext {
+                meta: &game.meta,
+                kyoku: typed,
+                events: &game.events[start..=index],
+                states: &states,
+            };
+            for hook in &mut self.hooks {
+                hook(&context, &mut annotations[start]);
+            }
+        }
+        game.annotations = Some(annotations);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for KyokuHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KyokuHooks")
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ir::{Emitter, Mjai, Tenhou6};
+    use crate::tenhou::EndStatus;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn run() {
+        let log = Fixture::Hanchan.log();
+        let mut game = Tenhou6::default().convert(&log).unwrap();
+        let mut hooks = KyokuHooks::new();
+        hooks
+            .register(|context, tags| {
+                assert_eq!(context.events.len(), context.states.len());
+                if matches!(context.kyoku.end_status, EndStatus::Hora { .. }) {
+                    let riichi = context.states.last().unwrap().players[..3]
+                        .iter()
+                        .any(|p| p.riichi);
+                    tags.insert("study".to_owned(), riichi.into());
+                }
+            })
+            .register(|context, tags| {
+                tags.insert("events".to_owned(), context.events.len().into());
+            });
+        assert_eq!(format!("{hooks:?}"), "KyokuHooks { hooks: 2 }");
+        hooks.run(&log, &mut game).unwrap();
+
+        let mut out = vec![];
+        Mjai::default().emit(&game, &mut out).unwrap();
+        let events: Value = serde_json::from_slice(&out).unwrap();
+        let starts: Vec<_> = events
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|e| e["type"] == "start_kyoku")
+            .collect();
+        assert_eq!(starts.len(), log.kyokus.len());
+        assert!(starts.iter().all(|e| e["annotations"]["events"].is_u64()));
+        assert!(starts.iter().any(|e| e["annotations"]["study"] == true));
+
+        // Without the first kyoku, as converted skipping an invalid one.
+        let end = game
+            .events
+            .iter()
+            .position(|e| *e == Event::EndKyoku)
+            .unwrap();
+        let mut game = Tenhou6::default().convert(&log).unwrap();
+        game.events.drain(1..=end);
+        let mut hooks = KyokuHooks::new();
+        hooks.register(|context, tags| {
+            tags.insert("kyoku".to_owned(), context.kyoku.meta.kyoku_num.into());
+        });
+        hooks.run(&log, &mut game).unwrap();
+        let annotations = game.annotations.unwrap();
+        assert_eq!(annotations[1]["kyoku"], log.kyokus[1].meta.kyoku_num);
+    }
+}
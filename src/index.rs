@@ -0,0 +1,230 @@
+//! An index of an archive of logs: what every log says of its game without
+//! its kyokus, so that selections and statistics over the archive need not
+//! parse every log again on every run.
+//!
+//! Entries are keyed by where the log was read from and a fingerprint of
+//! its bytes, so that an entry for a log changed since it was indexed is
+//! never taken for it.
+
+use crate::cache::fingerprint;
+use crate::output::write_atomic;
+use crate::tenhou::{EndStatus, Log, Room};
+use crate::Points;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// What the header of a log says of its game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSummary {
+    pub game_id: Option<String>,
+    /// The day the game started in Japan time, e.g. `2024-03-05`.
+    pub date: Option<String>,
+    pub lobby: Option<i32>,
+    pub players: [String; 3],
+    /// The rule string, or the one the game ID makes if the log has none.
+    pub rule: String,
+    pub room: Option<Room>,
+    pub ratingc: Option<String>,
+}
+
+impl GameSummary {
+    #[must_use]
+    pub fn new(log: &Log) -> Self {
+        let rule = match log.rule_flags {
+            Some(flags) if log.rule_disp.is_empty() => flags.to_string(),
+            _ => log.rule_disp.clone(),
+        };
+        Self {
+            game_id: log.game_id.clone(),
+            date: log.started_at().map(|time| time.date_naive().to_string()),
+            lobby: log.lobby,
+            players: [0, 1, 2].map(|seat| log.names[seat].clone()),
+            rule,
+            room: log.room(),
+            ratingc: log.ratingc.clone(),
+        }
+    }
+}
+
+/// How a game went, in brief.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameDigest {
+    pub kyokus: u32,
+    pub horas: u32,
+    pub ryukyokus: u32,
+    /// The scores at the end, `None` for logs without kyokus or with
+    /// scores that do not add up.
+    pub scores: Option<[Points; 3]>,
+}
+
+impl GameDigest {
+    #[must_use]
+    pub fn new(log: &Log) -> Self {
+        let mut digest = Self {
+            kyokus: 0,
+            horas: 0,
+            ryukyokus: 0,
+            scores: log
+                .final_scores()
+                .ok()
+                .flatten()
+                .map(|scores| [scores[0], scores[1], scores[2]]),
+        };
+        for kyoku in &log.kyokus {
+            digest.kyokus += 1;
+            match kyoku.end_status {
+                EndStatus::Hora { .. } => digest.horas += 1,
+                EndStatus::Ryukyoku { .. } => digest.ryukyokus += 1,
+            }
+        }
+        digest
+    }
+}
+
+/// A log of the archive, one line of a [`CorpusIndex`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Where the log was read from, e.g. `logs/a.jsonl:3`.
+    pub origin: String,
+    /// The [`fingerprint`] of the log as it was read.
+    pub fingerprint: String,
+    #[serde(flatten)]
+    pub game: GameSummary,
+    pub digest: GameDigest,
+}
+
+impl IndexEntry {
+    /// The entry of `log`, read from `origin` as `json`.
+    #[must_use]
+    pub fn new(origin: impl Into<String>, json: &str, log: &Log) -> Self {
+        Self {
+            origin: origin.into(),
+            fingerprint: fingerprint(json.as_bytes()),
+            game: GameSummary::new(log),
+            digest: GameDigest::new(log),
+        }
+    }
+}
+
+/// The entries of an archive as JSON lines, in the order its logs were
+/// read.
+#[derive(Debug, Clone, Default)]
+pub struct CorpusIndex {
+    entries: Vec<IndexEntry>,
+    by_origin: HashMap<String, usize>,
+}
+
+impl CorpusIndex {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The index written to `path`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if it cannot be read or a line of it is not an entry.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut index = Self::new();
+        for line in BufReader::new(fs::File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            index.record(serde_json::from_str(&line)?);
+        }
+        Ok(index)
+    }
+
+    #[must_use]
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entry of the log read from `origin` as `json`, `None` if it was
+    /// not indexed or has changed since.
+    #[must_use]
+    pub fn get(&self, origin: &str, json: &str) -> Option<&IndexEntry> {
+        let entry = &self.entries[*self.by_origin.get(origin)?];
+        (entry.fingerprint == fingerprint(json.as_bytes())).then_some(entry)
+    }
+
+    /// Record `entry`, replacing the entry of a log indexed again.
+    pub fn record(&mut self, entry: IndexEntry) {
+        match self.by_origin.get(&entry.origin) {
+            Some(&i) => self.entries[i] = entry,
+            None => {
+                self.by_origin
+                    .insert(entry.origin.clone(), self.entries.len());
+                self.entries.push(entry);
+            }
+        }
+    }
+
+    /// Write the index to `path`, atomically.
+    ///
+    /// # Errors
+    ///
+    /// Fails if it cannot be written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_atomic(path, true, |w| {
+            for entry in &self.entries {
+                serde_json::to_writer(&mut *w, entry)?;
+                w.write_all(b"\n")?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn index() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.jsonl");
+        CorpusIndex::open(&path).unwrap_err();
+
+        let mut index = CorpusIndex::new();
+        for fixture in Fixture::ALL {
+            let json = fixture.json();
+            let log = fixture.log();
+            index.record(IndexEntry::new(format!("{fixture:?}"), json, &log));
+        }
+        let json = Fixture::Hanchan.json();
+        let entry = index.get("Hanchan", json).unwrap().clone();
+        assert_eq!(entry.game.players[0], "mtk");
+        assert_eq!(entry.digest.scores, Some([48100, 30000, 26900]));
+        assert_eq!(
+            entry.digest.kyokus,
+            entry.digest.horas + entry.digest.ryukyokus
+        );
+        index.record(entry);
+        assert_eq!(index.len(), Fixture::ALL.len());
+
+        index.save(&path).unwrap();
+        let index = CorpusIndex::open(&path).unwrap();
+        assert_eq!(index.entries().len(), Fixture::ALL.len());
+        assert!(index.get("Hanchan", json).is_some());
+        assert!(index.get("Hanchan", &format!("{json} ")).is_none());
+        assert!(index.get("Other", json).is_none());
+    }
+}
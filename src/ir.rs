@@ -0,0 +1,820 @@
+//! The form every game takes between being read and being written.
+//!
+//! Input adapters ([`Adapter`]) turn a source format into a [`Game`] and
+//! output emitters ([`Emitter`]) write a [`Game`] out, so that supporting a
+//! new source or a new output takes one adapter or one emitter rather than
+//! one conversion per pair of them.
+//!
+//! A [`Game`] is the metadata of the game and its mjai [`Event`]s, which
+//! already describe games without reference to where they were played.
+//! Anything one source knows and another does not, such as yaku names, is
+//! left out; [`crate::text::render_text`] still works on the tenhou log for
+//! that reason.
+
+use crate::conv::{tenhou_to_mjai_with_options, ConvertError, ConvertOptions};
+use crate::output::{AkaEncoding, JsonStyle};
+use crate::replay::{split_kyokus, turn_order};
+use crate::tenhou::{
+    LiveConverter, LiveError, Log, RatingChange, Repair, RuleFlags, TenhouParseError,
+};
+use crate::wall::Wall;
+use crate::{Event, Points, Tile};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write as _;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A game in the platform-agnostic form.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Game {
+    pub meta: GameMeta,
+    /// Every event of the game, from `start_game` to `end_game`.
+    pub events: Vec<Event>,
+    /// When each event of `events` happened, in milliseconds from the start
+    /// of the game, if the source records it.
+    pub timestamps: Option<Vec<u64>>,
+    /// What analyzers found about each event of `events`, by the name of the
+    /// analyzer, see [`crate::analyzer`].
+    pub annotations: Option<Vec<BTreeMap<String, Value>>>,
+}
+
+impl Game {
+    /// When the event at `index` happened, see [`Self::timestamps`].
+    #[must_use]
+    pub fn timestamp(&self, index: usize) -> Option<u64> {
+        self.timestamps.as_ref()?.get(index).copied()
+    }
+
+    /// Make up timestamps `step` milliseconds apart, for timing analyses of
+    /// sources that do not record them. Recorded timestamps are kept.
+    pub fn synthesize_timestamps(&mut self, step: u64) {
+        if self.timestamps.is_none() {
+            let timestamps = (0..self.events.len() as u64).map(|i| i * step).collect();
+            self.timestamps = Some(timestamps);
+        }
+    }
+}
+
+/// What is known about a game besides its events. Sources that do not
+/// record a field leave it `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameMeta {
+    /// The ID the source platform knows the game by.
+    pub game_id: Option<String>,
+    /// The names by seat, empty for the fourth seat of sanma.
+    pub names: [String; 4],
+    /// The rank of every seat before the game, e.g. `七段`.
+    pub dans: Option<Vec<String>>,
+    /// The rating of every seat before the game.
+    pub rates: Option<Vec<f64>>,
+    /// The private lobby the game was played in, `0` for the public one.
+    pub lobby: Option<i32>,
+    /// The rules as the source names them, e.g. `三鳳南喰赤`.
+    pub rule: Option<String>,
+    pub rules: Option<RuleFlags>,
+}
+
+impl From<&Log> for GameMeta {
+    fn from(log: &Log) -> Self {
+        Self {
+            game_id: log.game_id.clone(),
+            names: log.names.clone(),
+            dans: log.dan.clone(),
+            rates: log.rate.clone(),
+            lobby: log.lobby,
+            rule: Some(log.rule_disp.clone()).filter(|r| !r.is_empty()),
+            rules: log.rule_flags,
+        }
+    }
+}
+
+/// Reads games from a source format.
+pub trait Adapter {
+    type Error;
+
+    fn read(&self, input: &str) -> Result<Game, Self::Error>;
+}
+
+/// Writes games in an output format.
+pub trait Emitter {
+    /// The file extension of the output, without the dot.
+    fn extension(&self) -> &str;
+
+    fn emit(&self, game: &Game, w: &mut dyn io::Write) -> io::Result<()>;
+}
+
+#[derive(Debug)]
+pub enum Tenhou6Error {
+    Parse(TenhouParseError),
+    Convert(ConvertError),
+}
+
+impl fmt::Display for Tenhou6Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(err) => fmt::Display::fmt(err, f),
+            Self::Convert(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl Error for Tenhou6Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Parse(err) => err.source(),
+            Self::Convert(err) => err.source(),
+        }
+    }
+}
+
+impl From<TenhouParseError> for Tenhou6Error {
+    fn from(err: TenhouParseError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+impl From<ConvertError> for Tenhou6Error {
+    fn from(err: ConvertError) -> Self {
+        Self::Convert(err)
+    }
+}
+
+/// Reads tenhou.net/6 JSON logs.
+#[derive(Debug, Clone, Default)]
+pub struct Tenhou6 {
+    pub options: ConvertOptions,
+}
+
+impl Tenhou6 {
+    /// Convert an already parsed log.
+    pub fn convert(&self, log: &Log) -> Result<Game, ConvertError> {
+        Ok(Game {
+            meta: log.into(),
+            events: tenhou_to_mjai_with_options(log, &self.options)?,
+            timestamps: None,
+            annotations: None,
+        })
+    }
+}
+
+impl Adapter for Tenhou6 {
+    type Error = Tenhou6Error;
+
+    fn read(&self, input: &str) -> Result<Game, Self::Error> {
+        let log = Log::from_json_str(input)?;
+        Ok(self.convert(&log)?)
+    }
+}
+
+/// Reads the tags of mjlog files, or a whole game of the stream tenhou
+/// sends, see [`LiveConverter`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mjlog;
+
+impl Adapter for Mjlog {
+    type Error = LiveError;
+
+    fn read(&self, input: &str) -> Result<Game, Self::Error> {
+        let mut live = LiveConverter::new();
+        let mut events = vec![];
+        live.push_str(input, &mut events)?;
+        live.finish(&mut events);
+
+        let mut meta = GameMeta::default();
+        if let Some(Event::StartGame { names, .. }) = events.first() {
+            meta.names.clone_from(names);
+        }
+        meta.rules = live.rules();
+        Ok(Game {
+            meta,
+            events,
+            timestamps: None,
+            annotations: None,
+        })
+    }
+}
+
+/// What produced a document, written around it by [`Mjai::emit_enveloped`]
+/// so that datasets assembled over months tell how each of their files was
+/// made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    /// The converter, `convlog`.
+    pub converter: String,
+    /// Its version, e.g. `0.2.0`.
+    pub converter_version: String,
+    /// The format of the source, e.g. `tenhou6`.
+    pub source_format: String,
+    /// The rules the game was played by as the source names them, e.g.
+    /// `三鳳南喰赤`.
+    pub rules: String,
+    /// The options of the conversion by their command-line names, only those
+    /// that change the output.
+    pub options: BTreeMap<String, Value>,
+    /// The estimated change of the R and dan points of every seat, see
+    /// [`Log::rating_changes`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ratings: Option<[RatingChange; 3]>,
+    /// What was repaired of a log cut off, whose output is then missing the
+    /// kyokus dropped, see [`Log::repair`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repaired: Option<Repair>,
+}
+
+impl Envelope {
+    /// An envelope for this version of the crate, without options.
+    #[must_use]
+    pub fn new(source_format: &str, rules: &str) -> Self {
+        Self {
+            converter: env!("CARGO_PKG_NAME").to_owned(),
+            converter_version: env!("CARGO_PKG_VERSION").to_owned(),
+            source_format: source_format.to_owned(),
+            rules: rules.to_owned(),
+            options: BTreeMap::new(),
+            ratings: None,
+            repaired: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_option(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.options.insert(name.to_owned(), value.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_ratings(mut self, ratings: Option<[RatingChange; 3]>) -> Self {
+        self.ratings = ratings;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_repaired(mut self, repaired: Option<Repair>) -> Self {
+        self.repaired = repaired;
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct Enveloped<'a, T: ?Sized> {
+    envelope: &'a Envelope,
+    events: &'a T,
+}
+
+/// Reads mjai events, a JSON array of them or one per line as `convert` and
+/// `live` write them, with their `timestamp`s if every event has one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MjaiLog;
+
+impl Adapter for MjaiLog {
+    type Error = serde_json::Error;
+
+    fn read(&self, input: &str) -> Result<Game, Self::Error> {
+        #[derive(Deserialize)]
+        struct Read {
+            #[serde(flatten)]
+            event: Event,
+            timestamp: Option<u64>,
+        }
+
+        let read: Vec<Read> = if input.trim_start().starts_with('[') {
+            serde_json::from_str(input)?
+        } else {
+            serde_json::Deserializer::from_str(input)
+                .into_iter()
+                .collect::<Result<_, _>>()?
+        };
+        let timestamps = read.iter().map(|read| read.timestamp).collect();
+        let events: Vec<_> = read.into_iter().map(|read| read.event).collect();
+        let mut meta = GameMeta::default();
+        if let Some(Event::StartGame { names, .. }) = events.first() {
+            meta.names.clone_from(names);
+        }
+        Ok(Game {
+            meta,
+            events,
+            timestamps,
+            annotations: None,
+        })
+    }
+}
+
+/// Writes the events as a JSON array of mjai events, each with a
+/// `timestamp` field if the game has timestamps and an `annotations` object
+/// if analyzers annotated it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mjai {
+    pub style: JsonStyle,
+    /// Leave the empty fourth seat out of `names`, `scores`, `tehais` and
+    /// `deltas`, for consumers that only know sanma. Only games whose log
+    /// passes [`Log::check_fourth_seat`] lose nothing.
+    pub three_seats: bool,
+    /// Add the `turn`, `seat` and `junme` of [`turn_order`] to every event
+    /// within a turn.
+    pub turn_order: bool,
+    /// How the tiles of events are written, aka dora among them.
+    pub aka: AkaEncoding,
+    /// Add what [`GameMeta`] knows of the game to `start_game`: its
+    /// `game_id`, the `dans` and `rates` of the seats, the `lobby`, the
+    /// `rule` as the source names it and the `rules` it stands for, aka
+    /// dora included.
+    pub game_meta: bool,
+    /// Add to every `start_kyoku` the `wall` of [`Wall::slots`] inferred
+    /// from the kyoku, `null` where a tile never showed, and those tiles as
+    /// `unseen`, for simulators that replay kyokus from their middle.
+    /// Kyokus the wall cannot be inferred from fail the output with an
+    /// [`io::ErrorKind::InvalidData`] error.
+    pub wall: bool,
+}
+
+/// The fields of mjai events with one entry per seat.
+const SEAT_FIELDS: [&str; 8] = [
+    "names",
+    "dans",
+    "rates",
+    "scores",
+    "tehais",
+    "deltas",
+    "ranks",
+    "final_points",
+];
+
+/// An event with when it happened, as [`Mjai`] writes those of games with
+/// timestamps.
+#[derive(Debug, Serialize)]
+pub struct Timed<'a> {
+    #[serde(flatten)]
+    pub event: &'a Event,
+    pub timestamp: u64,
+}
+
+impl Mjai {
+    /// Write the events as [`Emitter::emit`] does, as the `events` of an
+    /// object whose `envelope` is `envelope`.
+    pub fn emit_enveloped(
+        self,
+        game: &Game,
+        envelope: &Envelope,
+        w: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        self.write(game, Some(envelope), w)
+    }
+
+    fn write(
+        self,
+        game: &Game,
+        envelope: Option<&Envelope>,
+        w: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        let timed: Option<Vec<_>> = game.timestamps.as_ref().map(|timestamps| {
+            game.events
+                .iter()
+                .zip(timestamps)
+                .map(|(event, &timestamp)| Timed { event, timestamp })
+                .collect()
+        });
+        let plain = !self.three_seats
+            && !self.turn_order
+            && self.aka == AkaEncoding::Mjai
+            && !self.game_meta
+            && !self.wall;
+        if plain && game.annotations.is_none() {
+            return match &timed {
+                Some(timed) => self.write_events(w, timed, envelope),
+                None => self.write_events(w, &game.events, envelope),
+            };
+        }
+
+        let mut events = match &timed {
+            Some(timed) => serde_json::to_value(timed),
+            None => serde_json::to_value(&game.events),
+        }?;
+        let positions = self.turn_order.then(|| turn_order(&game.events));
+        let mut walls = if self.wall {
+            split_kyokus(&game.events)
+                .map(Wall::infer)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+        } else {
+            vec![]
+        }
+        .into_iter();
+        for (index, event) in events.as_array_mut().into_iter().flatten().enumerate() {
+            if let Value::Object(fields) = event {
+                if self.game_meta && fields["type"] == "start_game" {
+                    add_game_meta(fields, &game.meta);
+                }
+                if fields["type"] == "start_kyoku" {
+                    if let Some(wall) = walls.next() {
+                        fields.insert("wall".to_owned(), serde_json::to_value(wall.slots())?);
+                        fields.insert("unseen".to_owned(), serde_json::to_value(wall.unseen())?);
+                    }
+                }
+            }
+            if self.three_seats {
+                for field in SEAT_FIELDS {
+                    if let Some(Value::Array(seats)) = event.get_mut(field) {
+                        seats.truncate(3);
+                    }
+                }
+            }
+            let Value::Object(fields) = event else {
+                continue;
+            };
+            if self.aka != AkaEncoding::Mjai {
+                for (field, value) in fields.iter_mut() {
+                    if field != "names" {
+                        encode_tiles(value, self.aka);
+                    }
+                }
+            }
+            if let Some(position) = positions.as_ref().and_then(|p| p[index]) {
+                fields.insert("turn".to_owned(), position.turn.into());
+                fields.insert("seat".to_owned(), position.seat.into());
+                fields.insert("junme".to_owned(), position.junme.into());
+            }
+            let annotations = game.annotations.as_ref().and_then(|a| a.get(index));
+            if let Some(annotations) = annotations.filter(|a| !a.is_empty()) {
+                let annotations = annotations.clone().into_iter().collect();
+                fields.insert("annotations".to_owned(), Value::Object(annotations));
+            }
+        }
+        self.write_events(w, &events, envelope)
+    }
+
+    fn write_events<T: Serialize + ?Sized>(
+        self,
+        w: &mut dyn io::Write,
+        events: &T,
+        envelope: Option<&Envelope>,
+    ) -> io::Result<()> {
+        match envelope {
+            Some(envelope) => self.style.write(w, &Enveloped { envelope, events }),
+            None => self.style.write(w, events),
+        }
+    }
+}
+
+/// Add the fields of [`Mjai::game_meta`] that `meta` has to the fields of a
+/// `start_game` event.
+fn add_game_meta(fields: &mut serde_json::Map<String, Value>, meta: &GameMeta) {
+    let mut add = |field: &str, value: Value| {
+        if !value.is_null() {
+            fields.insert(field.to_owned(), value);
+        }
+    };
+    add("game_id", meta.game_id.clone().into());
+    add("dans", meta.dans.clone().into());
+    add("rates", meta.rates.clone().into());
+    add("lobby", meta.lobby.into());
+    add("rule", meta.rule.clone().into());
+    add(
+        "rules",
+        serde_json::to_value(meta.rules).unwrap_or_default(),
+    );
+}
+
+/// Rewrite the mjai tile strings of `value`, a field of an event, in `aka`.
+fn encode_tiles(value: &mut Value, aka: AkaEncoding) {
+    match value {
+        Value::String(s) => {
+            if let Ok(tile) = s.parse::<Tile>() {
+                *s = aka.encode(tile).into_owned();
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                encode_tiles(value, aka);
+            }
+        }
+        _ => (),
+    }
+}
+
+impl Emitter for Mjai {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn emit(&self, game: &Game, w: &mut dyn io::Write) -> io::Result<()> {
+        self.write(game, None, w)
+    }
+}
+
+/// Writes one row per event, for spreadsheets: the index of the kyoku, the
+/// mjai type, the actor and target seats, the tile and the consumed tiles,
+/// separated by spaces, and the score deltas of results. Games with
+/// timestamps get a `timestamp` column in front.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Csv {
+    /// How the tiles of the tile columns are written, aka dora among them.
+    pub aka: AkaEncoding,
+}
+
+impl Emitter for Csv {
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn emit(&self, game: &Game, w: &mut dyn io::Write) -> io::Result<()> {
+        let timed = game.timestamps.is_some();
+        if timed {
+            write!(w, "timestamp,")?;
+        }
+        writeln!(w, "kyoku,type,actor,target,pai,consumed,deltas")?;
+        let mut kyoku = None;
+        for (index, event) in game.events.iter().enumerate() {
+            if matches!(event, Event::StartKyoku { .. }) {
+                kyoku = Some(kyoku.map_or(0, |k| k + 1));
+            }
+            let (target, pai, consumed, deltas) = match event {
+                Event::Tsumo { pai, .. } | Event::Dahai { pai, .. } => {
+                    (None, Some(pai), &[][..], None)
+                }
+                Event::Chi {
+                    target,
+                    pai,
+                    consumed,
+                    ..
+                }
+                | Event::Pon {
+                    target,
+                    pai,
+                    consumed,
+                    ..
+                } => (Some(target), Some(pai), &consumed[..], None),
+                Event::Daiminkan {
+                    target,
+                    pai,
+                    consumed,
+                    ..
+                } => (Some(target), Some(pai), &consumed[..], None),
+                Event::Kakan { pai, consumed, .. } => (None, Some(pai), &consumed[..], None),
+                Event::Ankan { consumed, .. } => (None, None, &consumed[..], None),
+                Event::Nukidora { consumed, .. } => (None, None, &consumed[..], None),
+                Event::Dora { dora_marker } => (None, Some(dora_marker), &[][..], None),
+                Event::Hora { target, deltas, .. } => {
+                    (Some(target), None, &[][..], deltas.as_ref())
+                }
+                Event::Ryukyoku { deltas, .. } => (None, None, &[][..], deltas.as_ref()),
+                _ => (None, None, &[][..], None),
+            };
+
+            let mut row = String::new();
+            let cell = |v: Option<String>| v.unwrap_or_default();
+            if timed {
+                write!(
+                    row,
+                    "{},",
+                    cell(game.timestamp(index).map(|t| t.to_string()))
+                )
+                .ok();
+            }
+            write!(
+                row,
+                "{},{},{},{},{},",
+                cell(kyoku.map(|k: usize| k.to_string())),
+                event.type_name(),
+                cell(event.actor().map(|a| a.to_string())),
+                cell(target.map(u8::to_string)),
+                cell(pai.map(|&pai| self.aka.encode(pai).into_owned())),
+            )
+            .ok();
+            let consumed: Vec<_> = consumed.iter().map(|&t| self.aka.encode(t)).collect();
+            row.push_str(&consumed.join(" "));
+            row.push(',');
+            if let Some(deltas) = deltas {
+                let deltas: Vec<_> = deltas.iter().map(Points::to_string).collect();
+                row.push_str(&deltas.join(" "));
+            }
+            writeln!(w, "{row}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn tenhou6_to_csv() {
+        let game = Tenhou6::default().read(Fixture::MultiRon.json()).unwrap();
+        assert_eq!(game.meta.names[1], "Botan");
+        assert_eq!(
+            game.meta.game_id.as_deref(),
+            Some("2024040112gm-00b9-0000-1a2b3c4d")
+        );
+
+        let mut out = vec![];
+        Csv::default().emit(&game, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let rows: Vec<_> = csv.lines().collect();
+        assert_eq!(
+            rows,
+            [
+                "kyoku,type,actor,target,pai,consumed,deltas",
+                ",start_game,,,,,",
+                "0,start_kyoku,,,,,",
+                "0,tsumo,0,,4p,,",
+                "0,dahai,0,,4p,,",
+                "0,hora,1,0,,,-1300 1300 0 0",
+                "0,hora,2,0,,,-3200 0 3200 0",
+                "0,end_kyoku,,,,,",
+                "0,end_game,,,,,",
+            ],
+        );
+
+        let mut out = vec![];
+        Mjai::default().emit(&game, &mut out).unwrap();
+        let events: Vec<Event> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(events, game.events);
+    }
+
+    #[test]
+    fn timestamps() {
+        let mut game = Tenhou6::default().read(Fixture::MultiRon.json()).unwrap();
+        assert_eq!(game.timestamp(0), None);
+        game.synthesize_timestamps(500);
+        assert_eq!(game.timestamp(3), Some(1500));
+        game.synthesize_timestamps(1);
+        assert_eq!(game.timestamp(3), Some(1500));
+
+        let mut out = vec![];
+        Mjai::default().emit(&game, &mut out).unwrap();
+        let json: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json[3]["type"], "dahai");
+        assert_eq!(json[3]["timestamp"], 1500);
+        assert_eq!(json[7]["type"], "end_game");
+        assert_eq!(json[0]["names"].as_array().unwrap().len(), 4);
+
+        let read = MjaiLog.read(std::str::from_utf8(&out).unwrap()).unwrap();
+        assert_eq!(read.meta.names, game.meta.names);
+        assert_eq!(
+            (&read.events, &read.timestamps),
+            (&game.events, &game.timestamps)
+        );
+        let lines: String = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| format!("{event}\n"))
+            .collect();
+        assert_eq!(MjaiLog.read(&lines).unwrap(), read);
+        let untimed = lines.replacen(r#","timestamp":0"#, "", 1);
+        assert_ne!(untimed, lines);
+        let untimed = MjaiLog.read(&untimed).unwrap();
+        assert_eq!((untimed.events, untimed.timestamps), (read.events, None));
+
+        let mut out = vec![];
+        let three_seats = Mjai {
+            three_seats: true,
+            ..Mjai::default()
+        };
+        three_seats.emit(&game, &mut out).unwrap();
+        let json: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json[0]["names"].as_array().unwrap().len(), 3);
+        assert_eq!(json[3]["timestamp"], 1500);
+        assert!(json[2].get("turn").is_none());
+
+        let mut out = vec![];
+        let turn_order = Mjai {
+            turn_order: true,
+            ..Mjai::default()
+        };
+        turn_order.emit(&game, &mut out).unwrap();
+        let json: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json[0]["names"].as_array().unwrap().len(), 4);
+        assert!(json[1].get("turn").is_none());
+        assert_eq!(json[2]["turn"], 0);
+        assert_eq!(json[2]["junme"], 1);
+        assert_eq!(json[3]["seat"], json[2]["actor"]);
+
+        let mut out = vec![];
+        Csv::default().emit(&game, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let rows: Vec<_> = csv.lines().take(3).collect();
+        assert_eq!(
+            rows,
+            [
+                "timestamp,kyoku,type,actor,target,pai,consumed,deltas",
+                "0,,start_game,,,,,",
+                "500,0,start_kyoku,,,,,",
+            ],
+        );
+    }
+    #[test]
+    fn game_meta() {
+        let game = Tenhou6::default().read(Fixture::Hanchan.json()).unwrap();
+        let mjai = Mjai {
+            game_meta: true,
+            three_seats: true,
+            ..Mjai::default()
+        };
+        let mut out = vec![];
+        mjai.emit(&game, &mut out).unwrap();
+        let json: Value = serde_json::from_slice(&out).unwrap();
+        let start_game = &json[0];
+        assert_eq!(start_game["game_id"], "2024030511gm-00b9-0000-e0c07689");
+        assert_eq!(
+            start_game["dans"],
+            serde_json::json!(["七段", "天鳳", "八段"])
+        );
+        assert_eq!(start_game["rates"][1], 2461.48);
+        assert_eq!(start_game["lobby"], 0);
+        assert_eq!(start_game["rule"], "三鳳南喰赤");
+        assert_eq!(start_game["rules"]["aka"], true);
+        assert_eq!(start_game["aka_flag"], true);
+        assert!(json[1].get("game_id").is_none());
+
+        let mut out = vec![];
+        Mjai::default().emit(&game, &mut out).unwrap();
+        let json: Value = serde_json::from_slice(&out).unwrap();
+        assert!(json[0].get("game_id").is_none());
+    }
+
+    #[test]
+    fn wall() {
+        let game = Tenhou6::default().read(Fixture::KanHeavy.json()).unwrap();
+        let mjai = Mjai {
+            wall: true,
+            aka: AkaEncoding::Mpsz,
+            ..Mjai::default()
+        };
+        let mut out = vec![];
+        mjai.emit(&game, &mut out).unwrap();
+        let json: Value = serde_json::from_slice(&out).unwrap();
+        let start_kyoku = &json[1];
+        let wall = start_kyoku["wall"].as_array().unwrap();
+        assert_eq!(wall.len(), crate::wall::WALL);
+        assert_eq!(wall[..13], start_kyoku["tehais"][0].as_array().unwrap()[..]);
+        let unseen = wall.iter().filter(|tile| tile.is_null()).count();
+        assert_eq!(start_kyoku["unseen"].as_array().unwrap().len(), unseen);
+        assert!(json[2].get("wall").is_none());
+    }
+
+    #[test]
+    fn aka_encoding() {
+        let game = Tenhou6::default().read(Fixture::Hanchan.json()).unwrap();
+        let mpsz = Mjai {
+            aka: AkaEncoding::Mpsz,
+            ..Mjai::default()
+        };
+        let mut out = vec![];
+        mpsz.emit(&game, &mut out).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert!(json.contains(r#""0p""#), "{json}");
+        assert!(!json.contains("5pr") && !json.contains("5sr"));
+        assert!(json.contains(r#""bakaze":"1z""#) && !json.contains(r#""E""#));
+
+        let csv = Csv {
+            aka: AkaEncoding::Tenhou,
+        };
+        let mut out = vec![];
+        csv.emit(&game, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        assert!(csv.lines().any(|row| row.contains(",53,")), "{csv}");
+        assert!(!csv.contains("5sr") && !csv.contains(",5p,"));
+
+        // Leaves the events as they were otherwise.
+        let mut out = vec![];
+        Mjai::default().emit(&game, &mut out).unwrap();
+        let mjai: Value = serde_json::from_slice(&out).unwrap();
+        let mpsz: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            mjai.as_array().unwrap().len(),
+            mpsz.as_array().unwrap().len()
+        );
+        assert_eq!(mjai[1]["scores"], mpsz[1]["scores"]);
+        assert_eq!(mjai[1]["tehais"][0].as_array().unwrap().len(), 13);
+    }
+
+    #[test]
+    fn envelope() {
+        let game = Tenhou6::default().read(Fixture::MultiRon.json()).unwrap();
+        let envelope = Envelope::new("tenhou6", "三鳳南喰赤")
+            .with_option("aka", "keep")
+            .with_ratings(Fixture::Hanchan.log().rating_changes())
+            .with_repaired(Some(Repair { dropped_kyokus: 1 }));
+        let turn_order = Mjai {
+            turn_order: true,
+            ..Mjai::default()
+        };
+        for mjai in [Mjai::default(), turn_order] {
+            let mut out = vec![];
+            mjai.emit_enveloped(&game, &envelope, &mut out).unwrap();
+            let json: Value = serde_json::from_slice(&out).unwrap();
+            let read: Envelope = serde_json::from_value(json["envelope"].clone()).unwrap();
+            assert_eq!(read, envelope);
+            assert_eq!(read.converter, "convlog");
+            assert_eq!(json["envelope"]["ratings"][2]["dan_points"], -150);
+            assert_eq!(json["envelope"]["repaired"]["dropped_kyokus"], 1);
+            assert_eq!(json["events"][3]["type"], "dahai");
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! The journal of a batch run: the inputs it has finished, appended to as
+//! it goes, so that a run interrupted overnight can be resumed where it
+//! stopped instead of starting over.
+
+use crate::cache::fingerprint;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use ahash::AHashSet;
+use serde::{Deserialize, Serialize};
+
+/// The name of the journal in the output directory of a run.
+pub const JOURNAL: &str = ".convlog-journal.jsonl";
+
+/// A line of the journal: the first says what the run was started with,
+/// every other that an input is done.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Line {
+    Options(String),
+    Done(String),
+}
+
+/// The journal of a run, written next to its outputs.
+///
+/// Inputs are recorded once their output is in place, each on a line of its
+/// own written straight to the file, so that a run killed at any point
+/// leaves a journal listing only what it had finished.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    done: AHashSet<String>,
+    resumed: bool,
+}
+
+impl Journal {
+    /// Start the journal of a run into `dir` with `options`, a description of
+    /// everything that changes the output. With `resume`, the journal a run
+    /// with the same options left there is continued, and is an
+    /// [`io::ErrorKind::InvalidInput`] error if it was made with other
+    /// options; otherwise the journal starts empty.
+    pub fn open(dir: &Path, options: &str, resume: bool) -> io::Result<Self> {
+        let path = dir.join(JOURNAL);
+        let options = fingerprint(options.as_bytes());
+        let mut done = AHashSet::new();
+        let mut started = false;
+        let mut cut_off = false;
+        if resume {
+            let bytes = match fs::read(&path) {
+                Ok(bytes) => bytes,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+                Err(err) => return Err(err),
+            };
+            cut_off = bytes.last().is_some_and(|&b| b != b'\n');
+            // A line cut off by the interruption is not done.
+            for line in bytes.split(|&b| b == b'\n') {
+                match serde_json::from_slice(line) {
+                    Ok(Line::Options(o)) if o == options => started = true,
+                    Ok(Line::Options(_)) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!(
+                                "{} was written with other options, start over without --resume",
+                                path.display()
+                            ),
+                        ))
+                    }
+                    Ok(Line::Done(input)) => {
+                        done.insert(input);
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+
+        fs::create_dir_all(dir)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .truncate(false)
+            .open(&path)?;
+        if !started {
+            file.set_len(0)?;
+            done.clear();
+            write_line(&mut file, &Line::Options(options))?;
+        } else if cut_off {
+            file.write_all(b"\n")?;
+        }
+        Ok(Self {
+            path,
+            file,
+            done,
+            resumed: started,
+        })
+    }
+
+    /// Whether the journal continues one a run left, whose outputs may then
+    /// be in place for inputs it had not recorded yet.
+    #[must_use]
+    pub const fn resumed(&self) -> bool {
+        self.resumed
+    }
+
+    /// How many inputs the journal had when opened or were recorded since.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.done.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty()
+    }
+
+    /// Whether `input` was finished by the run or the one it resumes.
+    #[must_use]
+    pub fn is_done(&self, input: &str) -> bool {
+        self.done.contains(input)
+    }
+
+    /// Record that `input` is finished.
+    pub fn record(&mut self, input: &str) -> io::Result<()> {
+        write_line(&mut self.file, &Line::Done(input.to_owned()))?;
+        self.done.insert(input.to_owned());
+        Ok(())
+    }
+
+    /// Remove the journal once the run is complete, so that the next one
+    /// starts over.
+    pub fn finish(self) -> io::Result<()> {
+        fs::remove_file(&self.path)
+    }
+}
+
+fn write_line(file: &mut File, line: &Line) -> io::Result<()> {
+    let mut bytes = serde_json::to_vec(line)?;
+    bytes.push(b'\n');
+    // In one write, so that lines of an interrupted run are whole or cut off
+    // at the end.
+    file.write_all(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut journal = Journal::open(dir.path(), "mjai", false).unwrap();
+        assert!(!journal.resumed());
+        journal.record("logs/a.json").unwrap();
+        journal.record("logs/b.jsonl:2").unwrap();
+        drop(journal);
+        // The run was killed halfway through a line.
+        let path = dir.path().join(JOURNAL);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"done\":\"logs/c").unwrap();
+
+        let mut journal = Journal::open(dir.path(), "mjai", true).unwrap();
+        assert!(journal.resumed());
+        assert_eq!(journal.len(), 2);
+        assert!(journal.is_done("logs/b.jsonl:2"));
+        assert!(!journal.is_done("logs/c.json"));
+        journal.record("logs/c.json").unwrap();
+        drop(journal);
+        let journal = Journal::open(dir.path(), "mjai", true).unwrap();
+        assert_eq!(journal.len(), 3);
+
+        let err = Journal::open(dir.path(), "csv", true).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        // Without --resume, it starts over.
+        let journal = Journal::open(dir.path(), "csv", false).unwrap();
+        assert!(journal.is_empty());
+        journal.finish().unwrap();
+        assert!(!path.exists());
+        let journal = Journal::open(dir.path(), "csv", true).unwrap();
+        assert!(journal.is_empty() && !journal.resumed());
+    }
+}
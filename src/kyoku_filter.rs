@@ -1,30 +1,53 @@
+use crate::tenhou::KyokuMeta;
+use std::error::Error;
+use std::fmt;
+use std::num::ParseIntError;
 use std::str::FromStr;
 
-use thiserror::Error;
-
 #[derive(Debug, Clone)]
 pub struct KyokuFilter {
     whitelist: [Vec<u8>; 16],
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug)]
 pub enum ParseError {
-    #[error("missing bakaze")]
     MissingBakaze,
-    #[error("missing kyoku")]
     MissingKyoku,
 
-    #[error(r#"invalid bakaze: {0:?} (expected one of "E", "S", "W", "N")"#)]
     InvalidBakaze(String),
-    #[error("invalid kyoku: {0:?}")]
-    InvalidKyoku(#[source] <u8 as FromStr>::Err),
-    #[error("invalid honba: {0:?}")]
-    InvalidHonba(#[source] <u8 as FromStr>::Err),
+    InvalidKyoku(ParseIntError),
+    InvalidHonba(ParseIntError),
 
-    #[error("invalid kyoku range: {0:?} (expected within [1, 4])")]
     InvalidKyokuRange(u8),
 }
 
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingBakaze => f.write_str("missing bakaze"),
+            Self::MissingKyoku => f.write_str("missing kyoku"),
+            Self::InvalidBakaze(bakaze) => write!(
+                f,
+                r#"invalid bakaze: {bakaze:?} (expected one of "E", "S", "W", "N")"#
+            ),
+            Self::InvalidKyoku(err) => write!(f, "invalid kyoku: {err:?}"),
+            Self::InvalidHonba(err) => write!(f, "invalid honba: {err:?}"),
+            Self::InvalidKyokuRange(kyoku) => {
+                write!(f, "invalid kyoku range: {kyoku:?} (expected within [1, 4])")
+            }
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidKyoku(err) | Self::InvalidHonba(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
 impl FromStr for KyokuFilter {
     type Err = ParseError;
 
@@ -32,42 +55,8 @@ impl FromStr for KyokuFilter {
         let mut whitelist: [Vec<u8>; 16] = Default::default();
 
         for part in s.split(',') {
-            let mut chars = part.chars();
-
-            let bakaze = chars
-                .next()
-                .map(|p| p.to_uppercase().to_string())
-                .ok_or(ParseError::MissingBakaze)?;
-
-            let offset = match bakaze.as_str() {
-                "E" => 0,
-                "S" => 4,
-                "W" => 8,
-                "N" => 12,
-                _ => return Err(ParseError::InvalidBakaze(bakaze)),
-            };
-            let kyoku_num: u8 = chars
-                .next()
-                .ok_or(ParseError::MissingKyoku)?
-                .to_string()
-                .parse()
-                .map_err(ParseError::InvalidKyoku)?;
-
-            if !(1..=4).contains(&kyoku_num) {
-                return Err(ParseError::InvalidKyokuRange(kyoku_num));
-            }
-
-            let kyoku = offset + kyoku_num - 1;
-            let honba = if chars.next() == Some('.') {
-                chars
-                    .collect::<String>()
-                    .parse()
-                    .map_err(ParseError::InvalidHonba)?
-            } else {
-                0
-            };
-
-            whitelist[kyoku as usize].push(honba);
+            let (kyoku, honba) = KyokuMeta::parse_name(part)?;
+            whitelist[kyoku as usize].push(honba.unwrap_or(0));
         }
 
         Ok(Self { whitelist })
@@ -101,5 +90,9 @@ mod test {
         "e9".parse::<KyokuFilter>().unwrap_err();
         "w0".parse::<KyokuFilter>().unwrap_err();
         "".parse::<KyokuFilter>().unwrap_err();
+
+        let kf: KyokuFilter = "東2局 1本場,S1-2".parse().unwrap();
+        assert!(kf.test(1, 1));
+        assert!(kf.test(4, 2));
     }
 }
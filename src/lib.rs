@@ -1,6 +1,13 @@
 //! Provides methods to transform mahjong logs from tenhou.net/6 format into
 //! mjai format.
+//!
+//! Without the default `std` feature the crate is `no_std` and only provides
+//! [`Tile`], the mjai [`Event`], the [`sprite`] mapping, the action tables of
+//! [`tenhou::ActionTable`] with the parsing of their items and the kyoku
+//! state of [`replay`], with serde support behind the `serde` feature.
+//! Parsing whole tenhou logs and converting them need `std`.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![allow(clippy::manual_range_patterns)] // because of matches_tu8
 #![deny(
     rust_2018_idioms,
@@ -74,13 +81,12 @@
     clippy::string_add,
     clippy::string_add_assign,
     clippy::string_lit_as_bytes,
-    clippy::string_to_string,
     clippy::suboptimal_flops,
     clippy::suspicious_to_owned,
     clippy::trait_duplication_in_bounds,
     clippy::trivially_copy_pass_by_ref,
     clippy::type_repetition_in_bounds,
-    clippy::unchecked_duration_subtraction,
+    clippy::unchecked_time_subtraction,
     clippy::undocumented_unsafe_blocks,
     clippy::unicode_not_nfc,
     clippy::uninlined_format_args,
@@ -96,17 +102,120 @@
     clippy::useless_let_if_seq
 )]
 
+extern crate alloc;
+
+#[cfg(feature = "std")]
+mod fields;
+#[cfg(feature = "std")]
 mod kyoku_filter;
+#[cfg(feature = "std")]
+mod lobby_filter;
 mod macros;
 mod mjai;
 mod tile;
 
+#[cfg(feature = "std")]
+pub mod analyzer;
+#[cfg(feature = "cli")]
+pub mod args;
+#[cfg(feature = "async")]
+pub mod async_conv;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod cancel;
+#[cfg(feature = "charts")]
+pub mod charts;
+#[cfg(feature = "checksums")]
+pub mod checksum;
+#[cfg(feature = "std")]
 pub mod conv;
+#[cfg(feature = "std")]
+pub mod dashboard;
+#[cfg(feature = "std")]
+pub mod dataset;
+#[cfg(feature = "std")]
+pub mod defense;
+#[cfg(feature = "std")]
+pub mod diagnostic;
+#[cfg(feature = "std")]
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod generate;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "std")]
+pub mod hand;
+#[cfg(feature = "std")]
+pub mod highlights;
+#[cfg(feature = "std")]
+pub mod hooks;
+#[cfg(feature = "std")]
+pub mod index;
+#[cfg(feature = "std")]
+pub mod ir;
+#[cfg(feature = "std")]
+pub mod journal;
+#[cfg(feature = "std")]
+pub mod locale;
+#[cfg(feature = "majsoul")]
+pub mod majsoul;
+#[cfg(feature = "std")]
+pub mod output;
+#[cfg(feature = "std")]
+pub mod partition;
+#[cfg(feature = "std")]
+pub mod plugin;
+#[cfg(feature = "std")]
+pub mod quality;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "std")]
+pub mod repl;
+pub mod replay;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+pub mod ruleset;
+#[cfg(feature = "std")]
+pub mod schema;
+#[cfg(feature = "http-server")]
+pub mod server;
+pub mod sprite;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod template;
 pub mod tenhou;
-pub mod args;
+#[cfg(all(feature = "std", any(feature = "testdata", test)))]
+pub mod testdata;
+#[cfg(feature = "std")]
+pub mod text;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "std")]
+pub mod validate;
+#[cfg(feature = "std")]
+pub mod walk;
+#[cfg(feature = "std")]
+pub mod wall;
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
 
-pub use conv::tenhou_to_mjai;
+#[cfg(feature = "std")]
 pub use conv::ConvertError;
+#[cfg(feature = "std")]
+pub use conv::{tenhou_to_mjai, tenhou_to_mjai_skipping_invalid, tenhou_to_mjai_with_options};
+#[cfg(feature = "std")]
+pub use conv::{ConvertOptions, SkippedKyoku};
+#[cfg(feature = "std")]
+pub use fields::{Field, Fields, ParseError as FieldsParseError};
+#[cfg(feature = "std")]
 pub use kyoku_filter::KyokuFilter;
-pub use mjai::Event;
-pub use tile::{tile_set_eq, Tile};
+#[cfg(feature = "std")]
+pub use lobby_filter::LobbyFilter;
+pub use mjai::{Event, Points};
+pub use tile::{tile_set_eq, Tile, TileError};
+
+/// Number of seats taking part in a sanma game.
+pub const SEATS: usize = 3;
@@ -0,0 +1,87 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Selects logs by the private lobby they were played in, e.g. `1234,5678`
+/// to keep only those two or `!0` to drop games from the public lobby.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LobbyFilter {
+    /// Empty to allow every lobby that is not excluded.
+    include: Vec<i32>,
+    exclude: Vec<i32>,
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    InvalidLobby(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLobby(lobby) => write!(f, "invalid lobby: {lobby:?}"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl FromStr for LobbyFilter {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut include = vec![];
+        let mut exclude = vec![];
+
+        for part in s.split(',') {
+            let (list, lobby) = match part.strip_prefix('!') {
+                Some(lobby) => (&mut exclude, lobby),
+                None => (&mut include, part),
+            };
+            let lobby = lobby
+                .trim()
+                .parse()
+                .map_err(|_| ParseError::InvalidLobby(part.to_owned()))?;
+            list.push(lobby);
+        }
+
+        Ok(Self { include, exclude })
+    }
+}
+
+impl LobbyFilter {
+    /// Logs without a lobby only pass filters that exclude lobbies.
+    #[inline]
+    #[must_use]
+    pub fn test(&self, lobby: Option<i32>) -> bool {
+        match lobby {
+            Some(lobby) => {
+                (self.include.is_empty() || self.include.contains(&lobby))
+                    && !self.exclude.contains(&lobby)
+            }
+            None => self.include.is_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filter() {
+        let lf: LobbyFilter = "1234,5678".parse().unwrap();
+        assert!(lf.test(Some(1234)));
+        assert!(!lf.test(Some(0)));
+        assert!(!lf.test(None));
+
+        let lf: LobbyFilter = "!0".parse().unwrap();
+        assert!(lf.test(Some(1234)));
+        assert!(!lf.test(Some(0)));
+        assert!(lf.test(None));
+
+        "".parse::<LobbyFilter>().unwrap_err();
+        "L1234".parse::<LobbyFilter>().unwrap_err();
+        "!".parse::<LobbyFilter>().unwrap_err();
+    }
+}
@@ -0,0 +1,298 @@
+//! Names of tiles, rounds and yaku in rendered output.
+
+use crate::kyoku_filter::ParseError;
+use crate::tenhou::{Han, KyokuMeta, Limit, Yaku};
+use crate::{t, tu8, Tile};
+use std::str::FromStr;
+
+const WINDS: [Tile; 4] = [t!(E), t!(S), t!(W), t!(N)];
+
+/// The language rendered output uses for tiles, rounds and yaku.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    /// mjai tile names and romanized yaku, e.g. `5pr` and `Riichi`.
+    #[default]
+    En,
+    /// The names tenhou uses, e.g. `赤5筒` and `立直`.
+    Ja,
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::En),
+            "ja" => Ok(Self::Ja),
+            _ => Err(format!("unknown locale {s:?}, expected \"en\" or \"ja\"")),
+        }
+    }
+}
+
+impl Locale {
+    #[must_use]
+    pub fn tile(self, tile: Tile) -> String {
+        if self == Self::En {
+            return tile.to_string();
+        }
+
+        let id = tile.as_u8();
+        match id {
+            _ if id <= tu8!(9p) => format!("{}筒", id - tu8!(1p) + 1),
+            _ if id <= tu8!(9s) => format!("{}索", id - tu8!(1s) + 1),
+            tu8!(1m) => "1萬".to_owned(),
+            tu8!(9m) => "9萬".to_owned(),
+            tu8!(5pr) => "赤5筒".to_owned(),
+            tu8!(5sr) => "赤5索".to_owned(),
+            _ => honor_ja(tile).map_or_else(|| "?".to_owned(), |c| c.to_string()),
+        }
+    }
+
+    /// The name of a round, e.g. `E1` or `東1局`.
+    #[must_use]
+    pub fn round(self, bakaze: Tile, kyoku: u8) -> String {
+        match self {
+            Self::En => format!("{bakaze}{kyoku}"),
+            Self::Ja => format!("{}{kyoku}局", self.tile(bakaze)),
+        }
+    }
+
+    #[must_use]
+    pub fn yaku(self, yaku: &Yaku) -> String {
+        if self == Self::Ja {
+            return yaku.to_string();
+        }
+
+        let name = match *yaku {
+            Yaku::SeatWind(tile) => return format!("Seat Wind {}", honor_en(tile)),
+            Yaku::RoundWind(tile) => return format!("Round Wind {}", honor_en(tile)),
+            Yaku::Yakuhai(tile) => return format!("Yakuhai {}", honor_en(tile)),
+            Yaku::Unknown(ref name) => return name.clone(),
+            Yaku::MenzenTsumo => "Menzen Tsumo",
+            Yaku::Riichi => "Riichi",
+            Yaku::Ippatsu => "Ippatsu",
+            Yaku::Chankan => "Chankan",
+            Yaku::Rinshan => "Rinshan Kaihou",
+            Yaku::Haitei => "Haitei Raoyue",
+            Yaku::Houtei => "Houtei Raoyui",
+            Yaku::Pinfu => "Pinfu",
+            Yaku::Tanyao => "Tanyao",
+            Yaku::Iipeikou => "Iipeikou",
+            Yaku::DoubleRiichi => "Double Riichi",
+            Yaku::Chiitoitsu => "Chiitoitsu",
+            Yaku::Chanta => "Chanta",
+            Yaku::Ittsu => "Ittsu",
+            Yaku::SanshokuDoujun => "Sanshoku Doujun",
+            Yaku::SanshokuDoukou => "Sanshoku Doukou",
+            Yaku::Sankantsu => "Sankantsu",
+            Yaku::Toitoi => "Toitoi",
+            Yaku::Sanankou => "Sanankou",
+            Yaku::Shousangen => "Shousangen",
+            Yaku::Honroutou => "Honroutou",
+            Yaku::Ryanpeikou => "Ryanpeikou",
+            Yaku::Junchan => "Junchan",
+            Yaku::Honitsu => "Honitsu",
+            Yaku::Chinitsu => "Chinitsu",
+            Yaku::Renhou => "Renhou",
+            Yaku::Tenhou => "Tenhou",
+            Yaku::Chiihou => "Chiihou",
+            Yaku::Daisangen => "Daisangen",
+            Yaku::Suuankou => "Suuankou",
+            Yaku::SuuankouTanki => "Suuankou Tanki",
+            Yaku::Tsuuiisou => "Tsuuiisou",
+            Yaku::Ryuuiisou => "Ryuuiisou",
+            Yaku::Chinroutou => "Chinroutou",
+            Yaku::Chuuren => "Chuuren Poutou",
+            Yaku::JunseiChuuren => "Junsei Chuuren Poutou",
+            Yaku::Kokushi => "Kokushi Musou",
+            Yaku::Kokushi13 => "Kokushi Musou 13-wait",
+            Yaku::Daisuushi => "Daisuushi",
+            Yaku::Shousuushi => "Shousuushi",
+            Yaku::Suukantsu => "Suukantsu",
+            Yaku::Dora => "Dora",
+            Yaku::Uradora => "Ura Dora",
+            Yaku::Akadora => "Aka Dora",
+            Yaku::Nukidora => "Nuki Dora",
+        };
+        name.to_owned()
+    }
+
+    #[must_use]
+    pub fn han(self, han: Han) -> String {
+        match (self, han) {
+            (Self::Ja, han) => han.to_string(),
+            (Self::En, Han::Regular(n)) => format!("{n} han"),
+            (Self::En, Han::Yakuman(1)) => "yakuman".to_owned(),
+            (Self::En, Han::Yakuman(n)) => format!("{n}x yakuman"),
+        }
+    }
+
+    /// Fu and han of a hora, e.g. `40 fu 3 han` or `40符3飜`.
+    #[must_use]
+    pub fn fu_han(self, fu: u8, han: u8) -> String {
+        match self {
+            Self::En => format!("{fu} fu {han} han"),
+            Self::Ja => format!("{fu}符{han}飜"),
+        }
+    }
+
+    #[must_use]
+    pub const fn limit(self, limit: Limit) -> &'static str {
+        match (self, limit) {
+            (Self::En, Limit::Mangan) => "mangan",
+            (Self::En, Limit::Haneman) => "haneman",
+            (Self::En, Limit::Baiman) => "baiman",
+            (Self::En, Limit::Sanbaiman) => "sanbaiman",
+            (Self::En, Limit::Yakuman) => "yakuman",
+            (Self::Ja, Limit::Mangan) => "満貫",
+            (Self::Ja, Limit::Haneman) => "跳満",
+            (Self::Ja, Limit::Baiman) => "倍満",
+            (Self::Ja, Limit::Sanbaiman) => "三倍満",
+            (Self::Ja, Limit::Yakuman) => "役満",
+        }
+    }
+}
+
+impl KyokuMeta {
+    /// The name of the kyoku with its honba, e.g. `East 1, 0 honba` or
+    /// `東1局 0本場`.
+    #[must_use]
+    pub fn display_name(&self, locale: Locale) -> String {
+        let bakaze = WINDS[usize::from(self.kyoku_num / 4 % 4)];
+        let kyoku = self.kyoku_num % 4 + 1;
+        let honba = self.honba;
+        match locale {
+            Locale::En => format!("{} {kyoku}, {honba} honba", honor_en(bakaze)),
+            Locale::Ja => format!("{}{kyoku}局 {honba}本場", locale.tile(bakaze)),
+        }
+    }
+
+    /// The name of the kyoku in file names and filters, e.g. `E1-0`.
+    #[must_use]
+    pub fn short_name(&self) -> String {
+        let bakaze = WINDS[usize::from(self.kyoku_num / 4 % 4)];
+        format!("{bakaze}{}-{}", self.kyoku_num % 4 + 1, self.honba)
+    }
+
+    /// Read a name of [`Self::display_name`] or [`Self::short_name`] back into
+    /// the kyoku number, counting from 0 for E1, and the honba if the name
+    /// has one. Short names may also separate the honba with `.`, as in
+    /// `S3.1`, and the wind may be lowercase.
+    pub fn parse_name(name: &str) -> Result<(u8, Option<u8>), ParseError> {
+        let name = name.trim();
+        let long = [("East ", 0), ("South ", 1), ("West ", 2), ("North ", 3)];
+        let (wind, rest) = if let Some(&(prefix, wind)) =
+            long.iter().find(|(prefix, _)| name.starts_with(prefix))
+        {
+            (wind, &name[prefix.len()..])
+        } else {
+            let mut chars = name.chars();
+            let c = chars.next().ok_or(ParseError::MissingBakaze)?;
+            let wind = match c {
+                'E' | 'e' | '東' => 0,
+                'S' | 's' | '南' => 1,
+                'W' | 'w' | '西' => 2,
+                'N' | 'n' | '北' => 3,
+                _ => return Err(ParseError::InvalidBakaze(c.to_uppercase().to_string())),
+            };
+            (wind, chars.as_str())
+        };
+
+        let digit = rest.chars().next().ok_or(ParseError::MissingKyoku)?;
+        let kyoku: u8 = digit
+            .to_string()
+            .parse()
+            .map_err(ParseError::InvalidKyoku)?;
+        if !(1..=4).contains(&kyoku) {
+            return Err(ParseError::InvalidKyokuRange(kyoku));
+        }
+        let rest = &rest[digit.len_utf8()..];
+        let rest = rest.strip_prefix('局').unwrap_or(rest);
+
+        let honba = rest
+            .strip_prefix(['.', '-', ','])
+            .unwrap_or(rest)
+            .trim_start();
+        let honba = honba
+            .strip_suffix("本場")
+            .or_else(|| honba.strip_suffix(" honba"))
+            .unwrap_or(honba);
+        let honba = if honba.is_empty() {
+            None
+        } else {
+            Some(honba.parse().map_err(ParseError::InvalidHonba)?)
+        };
+
+        Ok((wind * 4 + kyoku - 1, honba))
+    }
+}
+
+fn honor_ja(tile: Tile) -> Option<char> {
+    const HONORS: [char; 7] = ['東', '南', '西', '北', '白', '發', '中'];
+    HONORS
+        .get(tile.as_usize().checked_sub(t!(E).as_usize())?)
+        .copied()
+}
+
+const fn honor_en(tile: Tile) -> &'static str {
+    match tile.as_u8() {
+        tu8!(E) => "East",
+        tu8!(S) => "South",
+        tu8!(W) => "West",
+        tu8!(N) => "North",
+        tu8!(P) => "Haku",
+        tu8!(F) => "Hatsu",
+        tu8!(C) => "Chun",
+        _ => "?",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(Locale::En.tile(t!(5pr)), "5pr");
+        assert_eq!(Locale::Ja.tile(t!(5pr)), "赤5筒");
+        assert_eq!(Locale::Ja.tile(t!(7s)), "7索");
+        assert_eq!(Locale::Ja.tile(t!(F)), "發");
+        assert_eq!(Locale::Ja.tile(t!(?)), "?");
+
+        assert_eq!(Locale::En.round(t!(S), 2), "S2");
+        assert_eq!(Locale::Ja.round(t!(S), 2), "南2局");
+
+        assert_eq!(Locale::En.yaku(&Yaku::Yakuhai(t!(C))), "Yakuhai Chun");
+        assert_eq!(Locale::Ja.yaku(&Yaku::Yakuhai(t!(C))), "役牌 中");
+        assert_eq!(Locale::En.han(Han::Regular(2)), "2 han");
+        assert_eq!(Locale::Ja.han(Han::Yakuman(1)), "役満");
+    }
+
+    #[test]
+    fn kyoku_names() {
+        let meta = KyokuMeta {
+            kyoku_num: 5,
+            honba: 1,
+            kyotaku: 0,
+        };
+        assert_eq!(meta.display_name(Locale::En), "South 2, 1 honba");
+        assert_eq!(meta.display_name(Locale::Ja), "南2局 1本場");
+        assert_eq!(meta.short_name(), "S2-1");
+
+        for name in [
+            "South 2, 1 honba",
+            "南2局 1本場",
+            "S2-1",
+            "s2.1",
+            "South 2 1 honba",
+        ] {
+            assert_eq!(KyokuMeta::parse_name(name).unwrap(), (5, Some(1)), "{name}");
+        }
+        assert_eq!(KyokuMeta::parse_name("東1局").unwrap(), (0, None));
+        assert_eq!(KyokuMeta::parse_name("East 4").unwrap(), (3, None));
+
+        for name in ["", "X1", "E5", "E", "E1x", "East 1, honba"] {
+            KyokuMeta::parse_name(name).unwrap_err();
+        }
+    }
+}
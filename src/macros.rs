@@ -5,7 +5,7 @@
 /// ```
 /// use convlog::tu8;
 ///
-/// assert_eq!(tu8!(E), 27u8);
+/// assert_eq!(tu8!(E), 18u8);
 /// ```
 #[macro_export]
 macro_rules! tu8 {
@@ -141,7 +141,7 @@ macro_rules! tu8 {
     };
 
     ($($_:tt)*) => {
-        ::std::compile_error!("invalid tile pattern");
+        ::core::compile_error!("invalid tile pattern");
     }
 }
 
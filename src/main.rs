@@ -1,85 +1,2173 @@
-use convlog::args::ConvCli;
+use chrono::{NaiveDate, TimeDelta};
 use clap::Parser;
-use std::path::{Path, PathBuf};
-use convlog::tenhou::Log;
-use convlog::conv::tenhou_to_mjai;
+use convlog::analyzer::{Analyzers, Builtin};
+use convlog::args::{
+    Command, ConvCli, ConvertArgs, DashboardArgs, DatasetArgs, DiffArgs, FilterArgs, GenArgs,
+    HeadToHeadArgs, HighlightsArgs, IndexArgs, LeaderboardArgs, LiveArgs, MarkdownArgs,
+    NormalizeArgs, PointFlowArgs, ProgressionArgs, ReplArgs, SchemaArgs, SelectArgs, SessionsArgs,
+    SplitArgs, StatsArgs, StatsReport, ValidateArgs, ValidateMjaiArgs, VerifyArgs, WalkArgs,
+    XlsxArgs,
+};
+use convlog::cache::ConversionCache;
+use convlog::checksum::{ChecksumEntry, ChecksumManifest, HashingWriter};
+use convlog::conv::{
+    tenhou_to_mjai, tenhou_to_mjai_skipping_invalid, tenhou_to_mjai_with_options, ConvertError,
+    ConvertOptions,
+};
+use convlog::dashboard::Dashboard;
+use convlog::dataset::{discard_rows, opponent_rows, DatasetFormat, DatasetRows, DatasetWriter};
+use convlog::diagnostic::{BatchFailed, CliError, ErrorFormat, ExitStatus, FileError};
+use convlog::diff::{diff_events, unified_diff, DiffOptions};
+use convlog::generate::{GenFormat, Generator};
+use convlog::hand::{mpsz, riichi_hands, winning_hands, HandError};
+use convlog::highlights::Highlights;
+use convlog::index::{CorpusIndex, GameSummary, IndexEntry};
+use convlog::ir::{Adapter, Csv, Emitter, Envelope, Game, GameMeta, Mjai, MjaiLog, Timed};
+use convlog::journal::Journal;
+use convlog::locale::Locale;
+#[cfg(feature = "majsoul")]
+use convlog::majsoul;
+use convlog::output::{
+    write_atomic, AkaEncoding, Bundle, JsonStyle, OutputFormat, Shards, TableFormat,
+};
+use convlog::partition::{Manifest, Partition};
+use convlog::plugin::Subprocess;
+use convlog::quality::quality;
+#[cfg(feature = "remote")]
+use convlog::remote::Fetcher;
+use convlog::repl::{Flow, KyokuIndex, Repl};
+use convlog::report::Report;
+use convlog::schema::schema;
+use convlog::stats::{
+    CallStats, DealerStats, DiscardHeatmap, HeadToHead, Leaderboard, PointFlows, Progression,
+    RiichiStats, Sessions, Summary, ThinkTimes,
+};
+use convlog::template::{OutputTemplate, TemplateContext};
+use convlog::tenhou::{
+    extract_json, Deduper, ExtractError, GameLength, Kyoku, LiveConverter, Log, RawLog, Room,
+    TenhouParseError,
+};
+use convlog::text::render_text;
+use convlog::validate::{validate, validate_mjai, ValidateOptions};
+use convlog::walk::{walk, Entry, WalkOptions};
+use convlog::{Event, Fields, LobbyFilter};
+use flate2::read::GzDecoder;
 use serde_json as json;
-use serde_json::Value;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+use zip::ZipArchive;
+
+fn main() -> ExitCode {
+    let cli = ConvCli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            let err = CliError::from(err);
+            eprintln!("Error: {err}");
+            ExitCode::from(err.exit_status().code())
+        }
+    }
+}
+
+fn run(cli: ConvCli) -> Result<(), Box<dyn std::error::Error>> {
+    match cli.command {
+        Command::Convert(args) => run_convert(&args),
+        Command::Validate(args) => run_validate(&args),
+        Command::ValidateMjai(args) => run_validate_mjai(&args),
+        Command::Stats(args) => run_stats(&args),
+        Command::Filter(args) => run_filter(&args),
+        Command::Index(args) => run_index(&args),
+        Command::Split(args) => run_split(&args),
+        Command::Normalize(args) => run_normalize(&args),
+        Command::Diff(args) => run_diff(&args),
+        Command::Verify(args) => run_verify(&args),
+        Command::Dataset(args) => run_dataset(&args),
+        Command::Repl(args) => run_repl(&args),
+        Command::Schema(args) => run_schema(&args),
+        Command::Gen(args) => run_gen(&args),
+        Command::Live(args) => run_live(&args),
+
+        #[cfg(feature = "tui")]
+        Command::Browse { log } => {
+            let events = read_events(&log)?;
+            convlog::tui::run(convlog::tui::Browser::new(&events)?)?;
+            Ok(())
+        }
+        #[cfg(feature = "tui")]
+        Command::Replay(args) => run_replay(&args),
+
+        #[cfg(feature = "http-server")]
+        Command::Serve { host, port } => {
+            convlog::server::serve((host.as_str(), port))?;
+            Ok(())
+        }
+
+        #[cfg(feature = "grpc")]
+        Command::Grpc { host, port } => {
+            let addr = format!("{host}:{port}").parse()?;
+            tokio::runtime::Runtime::new()?.block_on(convlog::grpc::serve(addr))?;
+            Ok(())
+        }
+    }
+}
+
+fn run_convert(args: &ConvertArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.envelope && args.format != OutputFormat::Mjai {
+        return Err("--envelope needs --format mjai".into());
+    }
+    if args.shard_size.is_some() && args.format != OutputFormat::Mjai {
+        return Err("--shard-size needs --format mjai".into());
+    }
+    if args.plugin.is_some() && args.format != OutputFormat::Mjai {
+        return Err("--plugin needs --format mjai".into());
+    }
+    if args.wall && args.format != OutputFormat::Mjai {
+        return Err("--wall needs --format mjai".into());
+    }
+    if !args.annotate.is_empty() && args.format != OutputFormat::Mjai {
+        return Err("--annotate needs --format mjai".into());
+    }
+    let plugin = args.plugin();
+    let mut job = Job {
+        output: &args.output,
+        format: args.format,
+        json_style: args.json_style(),
+        locale: args.locale,
+        template: args.output_template.as_ref(),
+        partition: args.partition,
+        force: args.force,
+        dry_run: args.dry_run,
+        game_length: args.game_length,
+        skip_invalid_kyokus: args.skip_invalid_kyokus,
+        repair: args.repair,
+        envelope: args.envelope,
+        ratings: args.ratings,
+        pseudo_timestamps: args.pseudo_timestamps,
+        three_seats: args.three_seats,
+        turn_order: args.turn_order,
+        aka_encoding: args.aka_encoding,
+        game_meta: args.game_meta,
+        wall: args.wall,
+        annotate: &args.annotate,
+        fields: args.fields,
+        plugin: plugin.as_ref(),
+        error_format: args.error_format,
+        options: ConvertOptions {
+            aka: args.aka,
+            haipai: args.haipai,
+            parallel_kyokus: args.parallel_kyokus,
+            cancel: None,
+            tie_break: args.tie_break(),
+        },
+    };
+    let mut selection = Selection::new(&args.select)?;
+    let mut manifest = if args.partition && !args.dry_run {
+        Some(Manifest::open(&args.output)?)
+    } else {
+        None
+    };
+    let mut checksums = args
+        .checksums
+        .as_ref()
+        .map(ChecksumManifest::open)
+        .transpose()?;
+    let mut cache = if args.incremental {
+        Some(ConversionCache::open(&args.output, &job.fingerprint())?)
+    } else {
+        None
+    };
+    let mut sink = if args.bundle {
+        Some(Sink::Bundle(Bundle::create(&args.output, args.force)?))
+    } else if let Some(size) = args.shard_size {
+        Some(Sink::Shards(Shards::create(
+            &args.output,
+            size,
+            args.force,
+        )?))
+    } else {
+        None
+    };
+    // Bundles and shards are only complete once finished, so there is
+    // nothing of an interrupted one to resume.
+    let mut journal = if sink.is_none() && !args.dry_run {
+        Some(Journal::open(
+            Path::new(&args.output),
+            &job.fingerprint(),
+            args.resume,
+        )?)
+    } else {
+        None
+    };
+    if let Some(journal) = journal.as_ref().filter(|j| !j.is_empty()) {
+        eprintln!("resuming after {} finished inputs", journal.len());
+    }
+    // The interrupted run may have written an output but not recorded it
+    // yet, which is converted again in its place.
+    job.force |= journal.as_ref().is_some_and(Journal::resumed);
+    let mut converted = 0;
+    let mut failed = 0;
+    // The status of the failures, if they all had the same.
+    let mut failure_status = None;
+    let mut stopped = None;
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if journal.as_ref().is_some_and(|j| j.is_done(&input.origin)) {
+            converted += 1;
+            continue;
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("file", path = %input.origin).entered();
+        // Every log converts independently, a broken one does not stop the
+        // rest of the batch.
+        match convert(
+            &input,
+            &job,
+            &mut selection,
+            manifest.as_mut(),
+            checksums.as_mut(),
+            cache.as_mut(),
+            sink.as_mut(),
+        ) {
+            Ok(()) => {
+                converted += 1;
+                if let Some(journal) = &mut journal {
+                    journal.record(&input.origin)?;
+                }
+            }
+            Err(err) => {
+                job.error_format
+                    .write(io::stderr().lock(), &input.origin, &*err)?;
+                failed += 1;
+                let status = ExitStatus::of(&*err);
+                failure_status = match failure_status {
+                    Some(other) if other != status => Some(ExitStatus::Other),
+                    _ => Some(status),
+                };
+                if args.failure.fail_fast {
+                    stopped = Some(status);
+                    break;
+                }
+            }
+        }
+    }
+    // Saved even if some logs failed, so it lists every file that was written.
+    if let Some(manifest) = &manifest {
+        manifest.save()?;
+    }
+    if let Some(checksums) = &checksums {
+        checksums.save()?;
+    }
+    if let Some(cache) = cache.as_ref().filter(|_| !args.dry_run) {
+        cache.save()?;
+    }
+    if let Some(sink) = sink {
+        sink.finish()?;
+    }
+
+    // The journal is kept, so that --resume retries only the failed logs.
+    if let Some(status) = stopped {
+        let message = "stopped at the first log that failed to convert".to_owned();
+        return Err(BatchFailed { message, status }.into());
+    }
+    if failed > 0 {
+        let message = format!("{failed} logs failed to convert");
+        // Partial only if something was converted; a batch of which every
+        // log failed exits as its failures did.
+        let status = match failure_status {
+            Some(status) if converted == 0 => status,
+            _ => ExitStatus::Partial,
+        };
+        return Err(BatchFailed { message, status }.into());
+    }
+    if let Some(journal) = journal {
+        journal.finish()?;
+    }
+    Ok(())
+}
+
+fn run_validate(args: &ValidateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let options = ValidateOptions {
+        ruleset: args.ruleset(),
+        scoring: args.scoring_rules(),
+        ..Default::default()
+    };
+    let mut selection = Selection::new(&args.select)?;
+    let mut checked = 0;
+    let mut invalid = 0;
+    let mut only_invalid = true;
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("file", path = %input.origin).entered();
+        let result = parse_log(&input, args.repair)
+            .map_err(Box::<dyn std::error::Error>::from)
+            .and_then(|log| {
+                if !selection.keep(&input.origin, &log) {
+                    return Ok(false);
+                }
+                validate(&log, &options)?;
+                Ok(true)
+            });
+        match result {
+            Ok(false) => continue,
+            Ok(true) => (),
+            Err(err) => {
+                args.error_format
+                    .write(io::stderr().lock(), &input.origin, &*err)?;
+                invalid += 1;
+                let status = ExitStatus::of(&*err);
+                if args.failure.fail_fast {
+                    let message = "stopped at the first invalid log".to_owned();
+                    return Err(BatchFailed { message, status }.into());
+                }
+                only_invalid &= status == ExitStatus::Invalid;
+            }
+        }
+        checked += 1;
+    }
+
+    if invalid > 0 {
+        let message = format!("{invalid} of {checked} logs are invalid");
+        // Logs that broke the rules are what validation is for; logs that
+        // could not even be read are told apart.
+        let status = if only_invalid {
+            ExitStatus::Invalid
+        } else {
+            ExitStatus::Partial
+        };
+        return Err(BatchFailed { message, status }.into());
+    }
+    Ok(())
+}
+
+fn run_validate_mjai(args: &ValidateMjaiArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let events = read_mjai(&args.input)?;
+    if let Err(err) = validate_mjai(&events) {
+        args.error_format
+            .write(io::stderr().lock(), &args.input, &err)?;
+        let message = format!("{} is not a legal sanma game", args.input);
+        let status = ExitStatus::of(&err);
+        return Err(BatchFailed { message, status }.into());
+    }
+    Ok(())
+}
+
+fn run_stats(args: &StatsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match &args.report {
+        Some(StatsReport::Leaderboard(report)) => return run_leaderboard(args, report),
+        Some(StatsReport::HeadToHead(report)) => return run_head_to_head(args, report),
+        Some(StatsReport::Progression(report)) => return run_progression(args, report),
+        Some(StatsReport::Sessions(report)) => return run_sessions(args, report),
+        Some(StatsReport::Highlights(report)) => return run_highlights(args, report),
+        Some(StatsReport::PointFlow(report)) => return run_point_flow(args, report),
+        Some(StatsReport::Dashboard(report)) => return run_dashboard(args, report),
+        Some(StatsReport::Markdown(report)) => return run_markdown(args, report),
+        Some(StatsReport::Xlsx(report)) => return run_xlsx(args, report),
+        Some(StatsReport::ThinkTimes) => return run_think_times(args),
+        #[cfg(feature = "charts")]
+        Some(StatsReport::Chart(report)) => return run_chart(args, report),
+        _ => (),
+    }
+    let mut selection = Selection::new(&args.select)?;
+    let mut stats = Summary::default();
+    let mut heatmap = DiscardHeatmap::new();
+    let mut calls = CallStats::new();
+    let mut dealer = DealerStats::new();
+    let mut riichis = RiichiStats::new();
+    match args.report {
+        Some(StatsReport::Hands) => println!("input,kyoku,who,target,hand,waits,situations"),
+        Some(StatsReport::Riichi) => println!("input,kyoku,who,hand,discard,waits"),
+        _ => (),
+    }
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if !selection.keep(&input.origin, &log) {
+            continue;
+        }
+        match args.report {
+            Some(StatsReport::Hands | StatsReport::Riichi) => {
+                let riichi = matches!(args.report, Some(StatsReport::Riichi));
+                for (i, kyoku) in log.kyokus.iter().enumerate() {
+                    print_hands(&input.origin, i, kyoku, riichi)
+                        .map_err(|err| FileError::new(&input.origin, err))?;
+                }
+            }
+            Some(StatsReport::Dealer) => dealer.add(&log),
+            Some(StatsReport::Discards | StatsReport::Calls | StatsReport::RiichiEv) => {
+                let events = selection
+                    .events(&log)
+                    .map_err(|err| FileError::new(&input.origin, err))?;
+                match args.report {
+                    Some(StatsReport::Discards) => heatmap.add(&log, &events),
+                    Some(StatsReport::Calls) => calls.add(&log, &events),
+                    _ => riichis
+                        .add(&log, &events)
+                        .map_err(|err| FileError::new(&input.origin, err))?,
+                }
+            }
+            _ => stats.add(&log),
+        }
+    }
+
+    let stdout = io::stdout().lock();
+    match args.report {
+        Some(StatsReport::Discards) => args.json_style().write(stdout, &heatmap)?,
+        Some(StatsReport::Calls) => args.json_style().write(stdout, &calls)?,
+        Some(StatsReport::Dealer) => args.json_style().write(stdout, &dealer)?,
+        Some(StatsReport::RiichiEv) => args.json_style().write(stdout, &riichis)?,
+        Some(StatsReport::Hands | StatsReport::Riichi) => (),
+        _ => print!("{stats}"),
+    }
+    Ok(())
+}
+
+/// Think times over tenhou logs, which only count as games, and mjai events
+/// with timestamps, which the selection flags do not apply to.
+fn run_think_times(args: &StatsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    let mut times = ThinkTimes::new();
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let game = match input.log() {
+            Ok(log) => {
+                if !selection.keep(&input.origin, &log) {
+                    continue;
+                }
+                let events = selection
+                    .events(&log)
+                    .map_err(|err| FileError::new(&input.origin, err))?;
+                Game {
+                    meta: (&log).into(),
+                    events,
+                    ..Game::default()
+                }
+            }
+            Err(err) => MjaiLog
+                .read(&input.json)
+                .map_err(|_| FileError::new(&input.origin, err))?,
+        };
+        times.add(&game);
+    }
+    args.json_style().write(io::stdout().lock(), &times)?;
+    Ok(())
+}
+
+fn run_leaderboard(
+    args: &StatsArgs,
+    report: &LeaderboardArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    let mut leaderboard = Leaderboard::new(args.ruleset(report.rules));
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if selection.keep(&input.origin, &log) {
+            leaderboard
+                .add(&log)
+                .map_err(|err| FileError::new(&input.origin, err))?;
+        }
+    }
+
+    let stdout = io::stdout().lock();
+    match report.format {
+        TableFormat::Json => args.json_style().write(stdout, &leaderboard)?,
+        TableFormat::Csv => leaderboard.write_csv(stdout)?,
+        TableFormat::Markdown => leaderboard.write_markdown(stdout)?,
+    }
+    Ok(())
+}
+
+fn run_head_to_head(
+    args: &StatsArgs,
+    report: &HeadToHeadArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    let mut h2h = HeadToHead::new([report.player.clone(), report.opponent.clone()]);
+    h2h.tie_break = args.ruleset(report.rules).tie_break;
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if selection.keep(&input.origin, &log) {
+            h2h.add(&log)
+                .map_err(|err| FileError::new(&input.origin, err))?;
+        }
+    }
+
+    let stdout = io::stdout().lock();
+    match report.format {
+        TableFormat::Json => args.json_style().write(stdout, &h2h)?,
+        TableFormat::Csv => h2h.write_csv(stdout)?,
+        TableFormat::Markdown => h2h.write_markdown(stdout)?,
+    }
+    Ok(())
+}
+
+fn run_progression(
+    args: &StatsArgs,
+    report: &ProgressionArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    let mut progression = Progression::new(report.player.clone());
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if selection.keep(&input.origin, &log) {
+            progression.add(&log);
+        }
+    }
+
+    let stdout = io::stdout().lock();
+    match report.format {
+        TableFormat::Json => args.json_style().write(stdout, &progression)?,
+        TableFormat::Csv => progression.write_csv(stdout)?,
+        TableFormat::Markdown => progression.write_markdown(stdout)?,
+    }
+    Ok(())
+}
+
+fn run_sessions(args: &StatsArgs, report: &SessionsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    let gap = TimeDelta::minutes(report.gap.into());
+    let mut sessions = Sessions::new(args.ruleset(report.rules), gap);
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if selection.keep(&input.origin, &log) {
+            sessions
+                .add(&log)
+                .map_err(|err| FileError::new(&input.origin, err))?;
+        }
+    }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let ConvCli { input, output } = ConvCli::parse();
-    let files = get_filename_list(&input);
+    let stdout = io::stdout().lock();
+    match report.format {
+        TableFormat::Json => args.json_style().write(stdout, &sessions)?,
+        TableFormat::Csv => sessions.write_csv(stdout)?,
+        TableFormat::Markdown => sessions.write_markdown(stdout)?,
+    }
+    Ok(())
+}
+
+fn run_highlights(
+    args: &StatsArgs,
+    report: &HighlightsArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    let mut highlights = Highlights::new();
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if !selection.keep(&input.origin, &log) {
+            continue;
+        }
+        let events = selection
+            .events(&log)
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        highlights.add(&log, &events);
+    }
+
+    let stdout = io::stdout().lock();
+    match report.format {
+        TableFormat::Json => args.json_style().write(stdout, &highlights)?,
+        TableFormat::Csv => highlights.write_csv(stdout)?,
+        TableFormat::Markdown => highlights.write_markdown(stdout)?,
+    }
+    Ok(())
+}
+
+fn run_point_flow(
+    args: &StatsArgs,
+    report: &PointFlowArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    let mut flows = PointFlows::new();
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if !selection.keep(&input.origin, &log) {
+            continue;
+        }
+        flows.add(&log);
+    }
+
+    let stdout = io::stdout().lock();
+    match report.format {
+        TableFormat::Json => args.json_style().write(stdout, &flows)?,
+        TableFormat::Csv => flows.write_csv(stdout)?,
+        TableFormat::Markdown => flows.write_markdown(stdout)?,
+    }
+    Ok(())
+}
+
+fn run_dashboard(
+    args: &StatsArgs,
+    report: &DashboardArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    let mut dashboard = Dashboard::new(args.ruleset(report.rules), report.player.clone());
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if !selection.keep(&input.origin, &log) {
+            continue;
+        }
+        let events = selection
+            .events(&log)
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        dashboard
+            .add(&log, &events)
+            .map_err(|err| FileError::new(&input.origin, err))?;
+    }
+
+    io::stdout()
+        .lock()
+        .write_all(dashboard.render().as_bytes())?;
+    Ok(())
+}
+
+fn run_markdown(args: &StatsArgs, report: &MarkdownArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let markdown = collect_report(args, Report::new(args.ruleset(report.rules), report.top))?;
+    markdown.write_markdown(io::stdout().lock())?;
+    Ok(())
+}
+
+fn run_xlsx(args: &StatsArgs, report: &XlsxArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let workbook = collect_report(args, Report::new(args.ruleset(report.rules), 0))?.workbook();
+    write_atomic(Path::new(&report.output), report.force, |w| {
+        workbook.write(w)
+    })?;
+    Ok(())
+}
+
+#[cfg(feature = "charts")]
+fn run_chart(
+    args: &StatsArgs,
+    report: &convlog::args::ChartArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use convlog::charts::{placement_chart, score_chart, ChartKind};
+
+    if report.kind == ChartKind::Scores {
+        std::fs::create_dir_all(&report.output)?;
+    }
+    let mut selection = Selection::new(&args.select)?;
+    let mut leaderboard = Leaderboard::new(args.ruleset(report.rules));
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if !selection.keep(&input.origin, &log) {
+            continue;
+        }
+        if report.kind == ChartKind::Placements {
+            leaderboard
+                .add(&log)
+                .map_err(|err| FileError::new(&input.origin, err))?;
+            continue;
+        }
+        let chart = score_chart(&log).map_err(|err| FileError::new(&input.origin, err))?;
+        let name = log.game_id.as_deref().unwrap_or(&input.stem);
+        let path = Path::new(&report.output).join(format!("{name}.{}", report.format.extension()));
+        write_atomic(&path, report.force, |w| {
+            w.write_all(&chart.encode(report.format))
+        })?;
+    }
+
+    if report.kind == ChartKind::Placements {
+        let chart = placement_chart(&leaderboard);
+        write_atomic(Path::new(&report.output), report.force, |w| {
+            w.write_all(&chart.encode(report.format))
+        })?;
+    }
+    Ok(())
+}
+
+/// Add every selected log of `args` to `report`.
+fn collect_report(
+    args: &StatsArgs,
+    mut report: Report,
+) -> Result<Report, Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if !selection.keep(&input.origin, &log) {
+            continue;
+        }
+        let events = selection
+            .events(&log)
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        report
+            .add(&log, &events)
+            .map_err(|err| FileError::new(&input.origin, err))?;
+    }
+    Ok(report)
+}
+
+/// Print the CSV rows of `stats hands`, or of `stats riichi` if
+/// `riichi`, for the kyoku at `index` of a log read from `origin`.
+fn print_hands(origin: &str, index: usize, kyoku: &Kyoku, riichi: bool) -> Result<(), HandError> {
+    if riichi {
+        for hand in riichi_hands(kyoku)? {
+            println!(
+                "{origin},{index},{},{},{},{}",
+                hand.who,
+                mpsz(&hand.concealed),
+                mpsz(&[hand.discard]),
+                mpsz(&hand.waits),
+            );
+        }
+    } else {
+        for (detail, hand) in winning_hands(kyoku)? {
+            let situations: Vec<_> = hand.situations().map(|yaku| format!("{yaku:?}")).collect();
+            println!(
+                "{origin},{index},{},{},{hand},{},{}",
+                detail.who,
+                detail.target,
+                mpsz(&hand.waits),
+                situations.join(";"),
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_filter(args: &FilterArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        let keep = match selection.indexed(&input) {
+            Some(keep) => keep,
+            None => {
+                let log = input
+                    .log()
+                    .map_err(|err| FileError::new(&input.origin, err))?;
+                selection.keep(&input.origin, &log)
+            }
+        };
+        if !keep {
+            if args.dry_run {
+                print_skip(&input.origin);
+            }
+            continue;
+        }
+        let filepath = Path::new(&args.output).join(format!("{}.json", input.stem));
+        if args.dry_run {
+            print_plan(&input.origin, &filepath, args.force);
+            continue;
+        }
+        std::fs::create_dir_all(&args.output)?;
+        let json = projected_json(&input, args.fields)?;
+        write_atomic(&filepath, args.force, |w| w.write_all(json.as_bytes()))?;
+    }
+    Ok(())
+}
 
-    for file in files {
-        convert(&file, &output)?;
+/// The JSON of the log of `input` with only `fields` kept, as it is if
+/// that is all of them.
+fn projected_json(
+    input: &Input,
+    fields: Fields,
+) -> Result<Cow<'_, str>, Box<dyn std::error::Error>> {
+    let json = input.log_json()?;
+    if fields == Fields::ALL {
+        return Ok(json);
     }
-    Ok(())   
+    let mut raw_log = RawLog::from_json_str(&json)?;
+    raw_log.project(fields);
+    Ok(Cow::Owned(json::to_string(&raw_log)?))
 }
 
-fn convert(input: &str, output: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let input_path = Path::new(input);
-    let json_str = std::fs::read_to_string(input_path).unwrap();
-    let json: Value = json::from_str(&json_str).unwrap();
+fn run_index(args: &IndexArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let previous = match CorpusIndex::open(&args.output) {
+        Ok(index) => index,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => CorpusIndex::new(),
+        Err(err) => return Err(FileError::new(args.output.display().to_string(), err).into()),
+    };
+    let mut index = CorpusIndex::new();
+    let mut parsed = 0;
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if let Some(entry) = previous.get(&input.origin, &input.json) {
+            index.record(entry.clone());
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        index.record(IndexEntry::new(input.origin, &input.json, &log));
+        parsed += 1;
+    }
+    index.save(&args.output)?;
+    eprintln!("indexed {} logs, {parsed} of them parsed", index.len());
+    Ok(())
+}
 
-    let file_id = json.get("ref")
-        .and_then(Value::as_str)
-        .ok_or("Invalid JSON")?;
-    let filepath = Path::new(&output).join(format!("{}.json", file_id));
+fn run_split(args: &SplitArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if !args.dry_run {
+        std::fs::create_dir_all(&args.output)?;
+    }
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        // Parse first so that only logs the other subcommands accept are
+        // split.
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        let mut value: json::Value = json::from_str(&projected_json(&input, args.fields)?)?;
+        let kyokus = match value.get_mut("log").map(json::Value::take) {
+            Some(json::Value::Array(kyokus)) => kyokus,
+            _ => return Err(format!("{}: missing \"log\"", input.origin).into()),
+        };
 
-    let tenhou_log = Log::from_json_str(&json_str).unwrap();
-    let mjai_log = tenhou_to_mjai(&tenhou_log)?;
+        for (i, (kyoku, parsed)) in kyokus.into_iter().zip(&log.kyokus).enumerate() {
+            value["log"] = json::Value::Array(vec![kyoku]);
+            let name = format!("{}-{i:02}-{}.json", input.stem, parsed.meta.short_name());
+            let filepath = Path::new(&args.output).join(name);
+            if args.dry_run {
+                print_plan(&input.origin, &filepath, args.force);
+                continue;
+            }
+            write_atomic(&filepath, args.force, |w| {
+                args.json_style().write(w, &value)
+            })?;
+        }
+    }
+    Ok(())
+}
 
-    let mut w: Box<dyn std::io::Write> = Box::new(std::fs::File::create(filepath)?);
-    writeln!(w, "[")?;
-    for (i, event) in mjai_log.iter().enumerate() {
-        let event_str = json::to_string(event)?;
-        if i < mjai_log.len() - 1 {
-            writeln!(w, "{},", event_str)?;
+fn run_normalize(args: &NormalizeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if !args.dry_run {
+        std::fs::create_dir_all(&args.output)?;
+    }
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        // Only logs the other subcommands accept are normalized.
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        let json = input.log_json()?;
+        let mut raw_log = RawLog::from_json_str(&json)?;
+        raw_log
+            .apply_unknown_keys(args.unknown_keys)
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        raw_log.project(args.fields);
+        raw_log.canonicalize();
+        let filepath = Path::new(&args.output).join(format!("{}.json", input.stem));
+        if args.three_seats {
+            log.check_fourth_seat()
+                .map_err(|err| FileError::new(&input.origin, err))?;
+        }
+        if args.dry_run {
+            print_plan(&input.origin, &filepath, args.force);
+        } else if args.three_seats {
+            let value = raw_log.to_three_seats();
+            write_atomic(&filepath, args.force, |w| {
+                args.json_style().write(w, &value)
+            })?;
         } else {
-            writeln!(w, "{}", event_str)?;
+            write_atomic(&filepath, args.force, |w| {
+                args.json_style().write(w, &raw_log)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn run_diff(args: &DiffArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let left = read_events(&args.left)?;
+    let right = read_events(&args.right)?;
+    let options = DiffOptions {
+        ignore_aka: args.ignore_aka,
+    };
+    let divergences = diff_events(&left, &right, options);
+    for divergence in &divergences {
+        println!("{divergence}");
+    }
+    if !divergences.is_empty() {
+        return Err(format!("{} kyokus differ", divergences.len()).into());
+    }
+    Ok(())
+}
+
+fn run_verify(args: &VerifyArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let input = &args.input;
+    let content = std::fs::read_to_string(input)?;
+    let json =
+        extract_json(&content).map_err(|err| FileError::new(input, TenhouParseError::from(err)))?;
+    let log = Log::from_json_str(&json).map_err(|err| FileError::new(input, err))?;
+    let options = ConvertOptions {
+        aka: args.aka,
+        haipai: args.haipai,
+        ..Default::default()
+    };
+    let actual =
+        tenhou_to_mjai_with_options(&log, &options).map_err(|err| FileError::new(input, err))?;
+    let expected = read_mjai(&args.expected)?;
+
+    let mut diffs = vec![];
+    // Outside of kyokus, which diff_events compares.
+    let game = |events: &[Event]| -> Vec<Event> {
+        events
+            .iter()
+            .filter(|e| matches!(e, Event::StartGame { .. } | Event::EndGame { .. }))
+            .cloned()
+            .collect()
+    };
+    let (expected_game, actual_game) = (game(&expected), game(&actual));
+    if expected_game != actual_game {
+        let mut diff = "@@ start_game and end_game @@\n".to_owned();
+        for i in 0..expected_game.len().max(actual_game.len()) {
+            let (expected, actual) = (expected_game.get(i), actual_game.get(i));
+            let lines = if expected == actual {
+                vec![(' ', expected)]
+            } else {
+                vec![('-', expected), ('+', actual)]
+            };
+            for (prefix, event) in lines {
+                if let Some(event) = event {
+                    diff.push_str(&format!("{prefix}{}\n", json::to_string(event)?));
+                }
+            }
+        }
+        diffs.push(diff);
+    }
+    let diff_options = DiffOptions {
+        ignore_aka: args.ignore_aka,
+    };
+    for divergence in diff_events(&expected, &actual, diff_options) {
+        diffs.push(unified_diff(&expected, &actual, &divergence, args.context));
+    }
+    if diffs.is_empty() {
+        return Ok(());
+    }
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "--- {}\n+++ {input}", args.expected)?;
+    for diff in &diffs {
+        stdout.write_all(diff.as_bytes())?;
+    }
+    Err(format!(
+        "{input}: {} differences from {}",
+        diffs.len(),
+        args.expected
+    )
+    .into())
+}
+
+/// Read a file of mjai events, a JSON array of them or one per line.
+fn read_mjai(path: &str) -> Result<Vec<Event>, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)?;
+    if let Ok(events) = json::from_str(&content) {
+        return Ok(events);
+    }
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            json::from_str(line)
+                .map_err(|err| FileError::new(format!("{path}:{}", i + 1), err).into())
+        })
+        .collect()
+}
+
+/// Read the events of a file of mjai events, or convert a tenhou log.
+fn read_events(path: &str) -> Result<Vec<Event>, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)?;
+    if let Ok(events) = json::from_str(&content) {
+        return Ok(events);
+    }
+    let json =
+        extract_json(&content).map_err(|err| FileError::new(path, TenhouParseError::from(err)))?;
+    let log = Log::from_json_str(&json).map_err(|err| FileError::new(path, err))?;
+    Ok(tenhou_to_mjai(&log).map_err(|err| FileError::new(path, err))?)
+}
+
+#[cfg(feature = "tui")]
+fn run_replay(args: &convlog::args::ReplayArgs) -> Result<(), Box<dyn std::error::Error>> {
+    use convlog::tenhou::KyokuMeta;
+    use convlog::tui::{play, Browser, Player};
+    use std::time::Duration;
+
+    if !(args.speed > 0.0 && args.speed.is_finite()) {
+        return Err("--speed must be a positive number of events per second".into());
+    }
+    let events = read_events(&args.log)?;
+    let mut browser = Browser::new(&events)?;
+    if let Some(name) = &args.kyoku {
+        let (kyoku, honba) = KyokuMeta::parse_name(name)?;
+        if !browser.select(kyoku, honba) {
+            return Err(format!("{}: no kyoku {name}", args.log).into());
+        }
+    }
+    let delay = Duration::try_from_secs_f64(1.0 / args.speed).unwrap_or(Duration::MAX);
+    play(Player::new(browser, delay))?;
+    Ok(())
+}
+
+fn run_dataset(args: &DatasetArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.rows == DatasetRows::Opponents && args.format == DatasetFormat::Parquet {
+        return Err("opponent rows are only written as JSON lines, not --format parquet".into());
+    }
+    match &args.output {
+        Some(output) => write_atomic(Path::new(output), args.force, |w| write_dataset(args, w)),
+        None => write_dataset(args, io::BufWriter::new(io::stdout())),
+    }?;
+    Ok(())
+}
+
+/// Write the rows of every selected log of `args` to `w`, labelled with
+/// their game IDs, or the names of their files for logs without one.
+fn write_dataset<W: Write + Send>(
+    args: &DatasetArgs,
+    w: W,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    let mut writer = DatasetWriter::new(w, args.format)?;
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if !selection.keep(&input.origin, &log) {
+            continue;
+        }
+        let events = selection
+            .events(&log)
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        let game = log.game_id.as_deref().unwrap_or(&input.stem);
+        match args.rows {
+            DatasetRows::Discards => {
+                let rows = discard_rows(game, &events)
+                    .map_err(|err| FileError::new(&input.origin, err))?;
+                writer.write(&rows)?;
+            }
+            DatasetRows::Opponents => {
+                let rows = opponent_rows(game, &events)
+                    .map_err(|err| FileError::new(&input.origin, err))?;
+                writer.write_opponents(&rows)?;
+            }
+        }
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+fn run_repl(args: &ReplArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut selection = Selection::new(&args.select)?;
+    let mut index = KyokuIndex::new();
+    for input in walk_inputs(&args.input, &args.walk) {
+        let input = input?;
+        if selection.indexed(&input) == Some(false) {
+            continue;
+        }
+        let log = input
+            .log()
+            .map_err(|err| FileError::new(&input.origin, err))?;
+        if !selection.keep(&input.origin, &log) {
+            continue;
+        }
+        let json = input.log_json()?;
+        let raw = RawLog::from_json_str(&json)?;
+        index
+            .add(&input.stem, raw, &log)
+            .map_err(|err| FileError::new(&input.origin, err))?;
+    }
+    eprintln!("{} kyokus, type \"help\" for the commands", index.len());
+
+    let mut repl = Repl::new(index);
+    let interactive = io::stdin().is_terminal();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            eprint!("> ");
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        match repl.run(&line, io::stdout().lock()) {
+            Ok(Flow::Continue) => (),
+            Ok(Flow::Quit) => break,
+            Err(err) => eprintln!("error: {err}"),
+        }
+    }
+    Ok(())
+}
+
+fn run_schema(args: &SchemaArgs) -> Result<(), Box<dyn std::error::Error>> {
+    args.json_style()
+        .write(io::stdout().lock(), &schema(args.format))?;
+    Ok(())
+}
+
+fn run_gen(args: &GenArgs) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(&args.output)?;
+    for game in Generator::new(args.seed).take(args.games as usize) {
+        let game_id = game["ref"].as_str().unwrap_or_default();
+        let filepath = Path::new(&args.output).join(format!("{game_id}.json"));
+        match args.format {
+            GenFormat::Tenhou6 => {
+                write_atomic(&filepath, args.force, |w| args.json_style().write(w, &game))?;
+            }
+            GenFormat::Mjai => {
+                let log = Log::from_json_str(&game.to_string())?;
+                let events = tenhou_to_mjai(&log)?;
+                write_atomic(&filepath, args.force, |w| {
+                    args.json_style().write(w, &events)
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_live(args: &LiveArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut live = LiveConverter::new();
+    // When the first event was read, that of `start_game`.
+    let mut start = None;
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    let mut buf = [0; 4096];
+    // Bytes of a UTF-8 sequence cut off by the end of a read.
+    let mut pending = vec![];
+    let mut events = vec![];
+    loop {
+        let n = stdin.read(&mut buf)?;
+        pending.extend_from_slice(&buf[..n]);
+        let chunk = take_utf8(&mut pending, n == 0);
+        if let Err(err) = live.push_str(&chunk, &mut events) {
+            eprintln!("{err}");
+        }
+        if n == 0 {
+            live.finish(&mut events);
+        }
+        for event in events.drain(..) {
+            if args.timestamps {
+                let start = *start.get_or_insert_with(Instant::now);
+                let timestamp = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+                let timed = Timed {
+                    event: &event,
+                    timestamp,
+                };
+                JsonStyle::Compact.write(&mut stdout, &timed)?;
+            } else {
+                JsonStyle::Compact.write(&mut stdout, &event)?;
+            }
+        }
+        stdout.flush()?;
+        if n == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/// Take the text off the front of `pending`, with invalid sequences replaced
+/// by U+FFFD, holding back a sequence cut off at the end unless `eof`.
+fn take_utf8(pending: &mut Vec<u8>, eof: bool) -> String {
+    let mut text = String::new();
+    let mut rest = &pending[..];
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(s) => {
+                text.push_str(s);
+                rest = &[];
+                break;
+            }
+            Err(err) => {
+                let (valid, after) = rest.split_at(err.valid_up_to());
+                text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                match err.error_len() {
+                    Some(len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        rest = &after[len..];
+                    }
+                    None if eof => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        rest = &[];
+                        break;
+                    }
+                    None => {
+                        rest = after;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    let taken = pending.len() - rest.len();
+    pending.drain(..taken);
+    text
+}
+
+/// What `--dry-run` reports for `path`: whether it would be written, would
+/// replace a file, or exists and would fail the run without `force`.
+fn planned_action(path: &Path, force: bool) -> &'static str {
+    if !path.exists() {
+        "write"
+    } else if force {
+        "overwrite"
+    } else {
+        "exists"
+    }
+}
+
+/// Print the line of `--dry-run` for `path`, an output of `origin`.
+fn print_plan(origin: &str, path: &Path, force: bool) {
+    println!(
+        "{}\t{}\t{origin}",
+        planned_action(path, force),
+        path.display()
+    );
+}
+
+/// Print the line of `--dry-run` for `origin`, left out by the selection.
+fn print_skip(origin: &str) {
+    println!("skip\t\t{origin}");
+}
+
+/// The selection flags of a subcommand, along with the games seen so far
+/// for `--dedupe`.
+#[derive(Default)]
+struct Selection<'a> {
+    lobbies: Option<&'a LobbyFilter>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    include_players: &'a [String],
+    exclude_players: &'a [String],
+    rooms: &'a [Room],
+    ratingcs: &'a [String],
+    rule: Option<&'a str>,
+    min_quality: Option<f64>,
+    deduper: Option<Deduper>,
+    index: Option<CorpusIndex>,
+    /// The events of the log last kept, if `--min-quality` converted it.
+    converted: Option<Vec<Event>>,
+}
+
+impl<'a> Selection<'a> {
+    fn new(args: &'a SelectArgs) -> Result<Self, FileError> {
+        let index = match &args.index {
+            Some(path) => Some(
+                CorpusIndex::open(path)
+                    .map_err(|err| FileError::new(path.display().to_string(), err))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            lobbies: args.lobbies.as_ref(),
+            since: args.since,
+            until: args.until,
+            include_players: &args.include_player,
+            exclude_players: &args.exclude_player,
+            rooms: &args.room,
+            ratingcs: &args.ratingc,
+            rule: args.rule.as_deref(),
+            min_quality: args.min_quality,
+            deduper: args.dedupe.then(Deduper::new),
+            index,
+            converted: None,
+        })
+    }
+
+    /// Whether the game of `summary` passes the flags that only need the
+    /// header of its log.
+    fn matches(&self, summary: &GameSummary) -> bool {
+        if self
+            .lobbies
+            .is_some_and(|filter| !filter.test(summary.lobby))
+        {
+            return false;
         }
+        if self.since.is_some() || self.until.is_some() {
+            let date = summary
+                .date
+                .as_deref()
+                .and_then(|date| date.parse::<NaiveDate>().ok());
+            let in_range = date.is_some_and(|date| {
+                self.since.is_none_or(|since| date >= since)
+                    && self.until.is_none_or(|until| date <= until)
+            });
+            if !in_range {
+                return false;
+            }
+        }
+        let has_player = |names: &[String]| summary.players.iter().any(|name| names.contains(name));
+        if !self.include_players.is_empty() && !has_player(self.include_players) {
+            return false;
+        }
+        if has_player(self.exclude_players) {
+            return false;
+        }
+        if !self.rooms.is_empty() && !summary.room.is_some_and(|room| self.rooms.contains(&room)) {
+            return false;
+        }
+        if !self.ratingcs.is_empty()
+            && !summary
+                .ratingc
+                .as_ref()
+                .is_some_and(|seg| self.ratingcs.contains(seg))
+        {
+            return false;
+        }
+        // Logs without a rule string are matched on the one their game ID
+        // makes instead.
+        if self.rule.is_some_and(|rule| !summary.rule.contains(rule)) {
+            return false;
+        }
+        true
     }
-    writeln!(w, "]")?;
-    Ok(())   
+
+    /// What the index says of whether to work on `input`: `Some(false)` if
+    /// its entry fails the selection, `Some(true)` if it passes and nothing
+    /// else needs the log, and `None` if the log has to be parsed to tell.
+    fn indexed(&self, input: &Input) -> Option<bool> {
+        let entry = self.index.as_ref()?.get(&input.origin, &input.json)?;
+        if !self.matches(&entry.game) {
+            return Some(false);
+        }
+        (self.min_quality.is_none() && self.deduper.is_none()).then_some(true)
+    }
+
+    /// Whether to work on `log`, read from `input`.
+    fn keep(&mut self, input: &str, log: &Log) -> bool {
+        self.converted = None;
+        if !self.matches(&GameSummary::new(log)) {
+            return false;
+        }
+        if let Some(min_quality) = self.min_quality {
+            // Logs that do not convert are left to fail where they are
+            // converted.
+            if let Ok(events) = tenhou_to_mjai(log) {
+                let score = quality(log, &events).score();
+                if score < min_quality {
+                    eprintln!("{input}: skipped game of quality {score:.3}");
+                    return false;
+                }
+                self.converted = Some(events);
+            }
+        }
+        if self
+            .deduper
+            .as_mut()
+            .is_some_and(|deduper| !deduper.insert(log))
+        {
+            eprintln!("{input}: skipped duplicate game");
+            return false;
+        }
+        true
+    }
+
+    /// The events of `log`, just kept, as [`tenhou_to_mjai`] converts it:
+    /// those `--min-quality` scored it by if it did, so that it is not
+    /// converted twice.
+    fn events(&mut self, log: &Log) -> Result<Vec<Event>, ConvertError> {
+        match self.converted.take() {
+            Some(events) => Ok(events),
+            None => tenhou_to_mjai(log),
+        }
+    }
+}
+
+/// Parse the log of `input`, with [`Log::from_json_str_repaired`] if
+/// `repair`, reporting on stderr what was repaired.
+fn parse_log(input: &Input, repair: bool) -> Result<Log, TenhouParseError> {
+    if !repair {
+        return input.log();
+    }
+    let log = Log::from_json_str_repaired(&input.log_json()?)?;
+    if let Some(repair) = log.repair {
+        eprintln!("{}: repaired: {repair}", input.origin);
+    }
+    Ok(log)
 }
 
-fn get_filename_list(path: &str) -> Vec<String> {
-    let mut filenames = Vec::new();
-    let path = PathBuf::from(path);
-    for entry in path.read_dir().unwrap() {
-        let entry = entry.unwrap();
-        let path = entry.path();
-        if path.is_file() {
-            if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
-                if filename.ends_with(".json") {
-                    filenames.push(path.to_str().unwrap().to_string());
+/// Settings shared by every file of a batch.
+struct Job<'a> {
+    output: &'a str,
+    format: OutputFormat,
+    json_style: JsonStyle,
+    locale: Locale,
+    template: Option<&'a OutputTemplate>,
+    partition: bool,
+    force: bool,
+    dry_run: bool,
+    game_length: Option<GameLength>,
+    skip_invalid_kyokus: bool,
+    repair: bool,
+    envelope: bool,
+    ratings: bool,
+    pseudo_timestamps: Option<u64>,
+    three_seats: bool,
+    turn_order: bool,
+    aka_encoding: AkaEncoding,
+    game_meta: bool,
+    wall: bool,
+    annotate: &'a [Builtin],
+    fields: Fields,
+    plugin: Option<&'a Subprocess>,
+    error_format: ErrorFormat,
+    options: ConvertOptions,
+}
+
+impl Job<'_> {
+    /// The envelope of the output of a game played by `rules`, with the
+    /// options of the job that change it.
+    fn envelope(&self, rules: &str) -> Envelope {
+        Envelope::new("tenhou6", rules)
+            .with_option("aka", self.options.aka.name())
+            .with_option("aka-encoding", self.aka_encoding.name())
+            .with_option("haipai", self.options.haipai.name())
+            .with_option("tie-break", self.options.tie_break.name())
+            .with_option("game-length", self.game_length.map(GameLength::kyokus))
+            .with_option("skip-invalid-kyokus", self.skip_invalid_kyokus)
+            .with_option("repair", self.repair)
+            .with_option("pseudo-timestamps", self.pseudo_timestamps)
+            .with_option("three-seats", self.three_seats)
+            .with_option("turn-order", self.turn_order)
+            .with_option("game-meta", self.game_meta)
+            .with_option("wall", self.wall)
+            .with_option(
+                "annotate",
+                self.annotate.iter().map(|b| b.name()).collect::<Vec<_>>(),
+            )
+            .with_option("ratings", self.ratings)
+            .with_option("fields", self.fields.to_string())
+    }
+
+    /// The checksum entry of the output of `log`, read from `input`, with
+    /// everything but what only writing it tells.
+    fn checksum_entry(&self, input: &Input, log: &Log) -> ChecksumEntry {
+        let envelope = self.envelope(&log.rule_disp);
+        ChecksumEntry {
+            path: String::new(),
+            source: input.origin.clone(),
+            game_id: log.game_id.clone(),
+            sha256: String::new(),
+            bytes: 0,
+            format: String::new(),
+            converter_version: envelope.converter_version,
+            options: envelope.options,
+        }
+    }
+
+    /// What outputs depend on besides their input, for the cache of
+    /// incremental conversions: the version of the converter and the
+    /// options that change the outputs or where they go.
+    fn fingerprint(&self) -> String {
+        format!(
+            "{} {:?} {:?} {:?} {:?} {:?} partition={} envelope={} {:?}",
+            env!("CARGO_PKG_VERSION"),
+            self.format,
+            self.plugin,
+            self.json_style,
+            self.locale,
+            self.template,
+            self.partition,
+            self.envelope,
+            self.envelope("").options,
+        )
+    }
+}
+
+fn convert(
+    input: &Input,
+    job: &Job<'_>,
+    selection: &mut Selection<'_>,
+    manifest: Option<&mut Manifest>,
+    checksums: Option<&mut ChecksumManifest>,
+    cache: Option<&mut ConversionCache>,
+    sink: Option<&mut Sink>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Job {
+        output,
+        format,
+        json_style,
+        locale,
+        template,
+        partition,
+        force,
+        dry_run,
+        game_length,
+        skip_invalid_kyokus,
+        repair,
+        envelope,
+        ratings,
+        pseudo_timestamps,
+        three_seats,
+        turn_order,
+        aka_encoding,
+        game_meta,
+        wall,
+        annotate,
+        fields,
+        plugin,
+        error_format,
+        ref options,
+    } = *job;
+    if selection.indexed(input) == Some(false) {
+        if dry_run {
+            print_skip(&input.origin);
+        }
+        return Ok(());
+    }
+    let mut tenhou_log = parse_log(input, repair)?;
+    if !selection.keep(&input.origin, &tenhou_log) {
+        if dry_run {
+            print_skip(&input.origin);
+        }
+        return Ok(());
+    }
+    // Taken before anything below changes the kyokus.
+    let cache = cache.map(|cache| (cache, tenhou_log.fingerprint()));
+    if let Some((cache, fingerprint)) = &cache {
+        if cache.is_fresh(fingerprint) {
+            if dry_run {
+                print_skip(&input.origin);
+            }
+            return Ok(());
+        }
+    }
+    if three_seats {
+        tenhou_log.check_fourth_seat()?;
+    }
+    if let Some(game_length) = game_length {
+        tenhou_log.game_length = game_length;
+    }
+
+    let (name, extension) = match plugin {
+        Some(plugin) => (plugin.extension(), plugin.extension()),
+        None => (format.name(), format.extension()),
+    };
+    let mut relative = match template {
+        Some(template) => {
+            let ctx = TemplateContext {
+                stem: &input.stem,
+                format: name,
+            };
+            template.render(&tenhou_log, ctx)
+        }
+        None => {
+            let file_id = tenhou_log.game_id.as_deref().ok_or("Invalid JSON")?;
+            PathBuf::from(format!("{file_id}.{extension}"))
+        }
+    };
+    if partition {
+        relative = Partition::of(&tenhou_log).path().join(relative);
+    }
+    let filepath = Path::new(output).join(&relative);
+    if dry_run {
+        print_plan(&input.origin, &filepath, force);
+        return Ok(());
+    }
+    tenhou_log.project(fields);
+    let mut mjai_log = if skip_invalid_kyokus {
+        let (events, skipped) = tenhou_to_mjai_skipping_invalid(&tenhou_log, options);
+        for skipped in &skipped {
+            match error_format {
+                ErrorFormat::Text => eprintln!(
+                    "{}: skipped kyoku {} honba {}: {}",
+                    input.origin, skipped.kyoku, skipped.honba, skipped.error
+                ),
+                ErrorFormat::Json => {
+                    error_format.write(io::stderr().lock(), &input.origin, &skipped.error)?;
                 }
             }
         }
+        // Keep the kyokus in step with the events for the text transcript,
+        // from the last so that the indices of the others hold.
+        for skipped in skipped.iter().rev() {
+            tenhou_log.kyokus.remove(skipped.index);
+        }
+        events
+    } else {
+        tenhou_to_mjai_with_options(&tenhou_log, options)?
+    };
+    fields.project_events(&mut mjai_log);
+
+    if format == OutputFormat::Text {
+        let text = render_text(&tenhou_log, &mjai_log, locale, aka_encoding);
+        let digest = write_output(&filepath, &relative, force, sink, |w| {
+            w.write_all(text.as_bytes())
+        })?;
+        let checksums = checksums.map(|c| (c, digest, job.checksum_entry(input, &tenhou_log)));
+        record(&relative, &tenhou_log, format, manifest, checksums, cache)?;
+        return Ok(());
+    }
+
+    let mut game = Game {
+        meta: GameMeta::from(&tenhou_log),
+        events: mjai_log,
+        timestamps: None,
+        annotations: None,
+    };
+    if let Some(step) = pseudo_timestamps {
+        game.synthesize_timestamps(step);
+    }
+    if !annotate.is_empty() {
+        let mut analyzers: Analyzers = annotate.iter().copied().collect();
+        analyzers.annotate(&mut game)?;
+    }
+    let mjai = Mjai {
+        style: json_style,
+        three_seats,
+        turn_order,
+        aka: aka_encoding,
+        game_meta,
+        wall,
+    };
+    let envelope = envelope.then(|| {
+        let ratings = ratings.then(|| tenhou_log.rating_changes()).flatten();
+        job.envelope(&tenhou_log.rule_disp)
+            .with_ratings(ratings)
+            .with_repaired(tenhou_log.repair)
+    });
+    let emit = |w: &mut dyn Write| match (format, &envelope) {
+        (OutputFormat::Csv, _) => Csv { aka: aka_encoding }.emit(&game, w),
+        (_, Some(envelope)) => mjai.emit_enveloped(&game, envelope, w),
+        (_, None) => mjai.emit(&game, w),
+    };
+    let digest = write_output(&filepath, &relative, force, sink, |w| match plugin {
+        Some(plugin) => plugin.pipe(w, emit),
+        None => emit(w),
+    })?;
+    let checksums = checksums.map(|c| (c, digest, job.checksum_entry(input, &tenhou_log)));
+    record(&relative, &tenhou_log, format, manifest, checksums, cache)?;
+    Ok(())
+}
+
+/// Where a batch writes its outputs instead of into files of their own.
+enum Sink {
+    /// At their paths in one `.tar.zst` file, for `--bundle`.
+    Bundle(Bundle),
+    /// A line each in numbered shards, for `--shard-size`.
+    Shards(Shards),
+}
+
+impl Sink {
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Bundle(bundle) => bundle.finish(),
+            Self::Shards(shards) => shards.finish().map(|_| ()),
+        }
+    }
+}
+
+/// Write an output to `filepath`, or into the sink of a batch that has one,
+/// at `relative` for a bundle, and return its SHA-256 and size.
+fn write_output<F>(
+    filepath: &Path,
+    relative: &Path,
+    force: bool,
+    sink: Option<&mut Sink>,
+    write: F,
+) -> io::Result<(String, u64)>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let mut digest = None;
+    let write = |w: &mut dyn Write| {
+        let mut w = HashingWriter::new(w);
+        write(&mut w)?;
+        digest = Some(w.finish());
+        Ok(())
+    };
+    match sink {
+        Some(Sink::Bundle(bundle)) => bundle.add(relative, |w| write(w))?,
+        Some(Sink::Shards(shards)) => shards.add(|w| write(w))?,
+        None => {
+            if let Some(parent) = filepath.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            write_atomic(filepath, force, |w| write(w))?;
+        }
+    }
+    Ok(digest.unwrap_or_default())
+}
+
+/// Record the output of `log` at `relative` to the output directory in the
+/// manifest, the checksums and the cache of a batch that keeps them, the
+/// checksums with the SHA-256 and size of the output and the cache by the
+/// fingerprint of the log as it was read.
+fn record(
+    relative: &Path,
+    log: &Log,
+    format: OutputFormat,
+    manifest: Option<&mut Manifest>,
+    checksums: Option<(&mut ChecksumManifest, (String, u64), ChecksumEntry)>,
+    cache: Option<(&mut ConversionCache, String)>,
+) -> io::Result<()> {
+    if let Some(manifest) = manifest {
+        manifest.record(relative, log, format.name())?;
+    }
+    if let Some((checksums, (sha256, bytes), entry)) = checksums {
+        let path = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        checksums.record(ChecksumEntry {
+            path,
+            sha256,
+            bytes,
+            format: format.name().to_owned(),
+            ..entry
+        });
+    }
+    if let Some((cache, fingerprint)) = cache {
+        cache.record(&fingerprint, relative);
+    }
+    Ok(())
+}
+
+/// A log to work on, read from a `.json` file, a line of a `.jsonl` one or
+/// an entry of an archive.
+struct Input {
+    /// Where the log came from in messages, e.g. `logs/a.jsonl:3` or
+    /// `logs/b.zip:2024/x.json`.
+    origin: String,
+    /// The base of output names derived from the input, e.g. `a-3`.
+    stem: String,
+    json: String,
+}
+
+impl Input {
+    /// The JSON of the log, taken out of a pasted link or page if that is
+    /// what the input holds, see [`extract_json`].
+    fn log_json(&self) -> Result<Cow<'_, str>, ExtractError> {
+        extract_json(&self.json)
+    }
+
+    fn log(&self) -> Result<Log, TenhouParseError> {
+        Log::from_json_str(&self.log_json()?)
+    }
+}
+
+/// Read the logs in `files`, one per `.json` file and one per non-empty line
+/// of a `.jsonl` file. `.zip` and `.tar.gz` archives are read entry by entry
+/// in memory, taking the `.json` and `.jsonl` files in them.
+fn read_inputs(files: Vec<String>) -> impl Iterator<Item = io::Result<Input>> {
+    files
+        .into_iter()
+        .flat_map(|file| -> Box<dyn Iterator<Item = _>> {
+            let archive = if file.ends_with(".zip") {
+                read_zip(&file)
+            } else if file.ends_with(".tar.gz") || file.ends_with(".tgz") {
+                read_tar_gz(&file)
+            } else if file.ends_with(".jsonl") {
+                // Streamed, unlike archives, since these can get big.
+                let lines = match File::open(&file) {
+                    Ok(f) => BufReader::new(f).lines(),
+                    Err(err) => return Box::new(std::iter::once(Err(err))),
+                };
+                return Box::new(jsonl_inputs(file.clone(), stem_of(&file), lines));
+            } else {
+                let input = std::fs::read_to_string(&file).map(|json| Input {
+                    stem: stem_of(&file),
+                    origin: file,
+                    json,
+                });
+                return Box::new(std::iter::once(input));
+            };
+            match archive {
+                Ok(inputs) => Box::new(inputs.into_iter().map(Ok)),
+                Err(err) => Box::new(std::iter::once(Err(err))),
+            }
+        })
+}
+
+/// The inputs in the directory or file `path` in the order of their paths,
+/// as [`read_inputs`] reads them, with the directory listed and its `.json`
+/// files read on other threads while earlier inputs are worked on. `.jsonl` files and archives are opened
+/// once their turn comes.
+fn walk_inputs(path: &str, args: &WalkArgs) -> Box<dyn Iterator<Item = io::Result<Input>>> {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return Box::new(std::iter::once(fetch_input(path, args)));
+    }
+    if let Some(records) = path.strip_prefix("majsoul:") {
+        return majsoul_inputs(records, args);
+    }
+    let options = WalkOptions {
+        threads: args.walk_threads,
+        recursive: args.recursive,
+        select: is_input,
+        preload: |path| path.to_str().is_some_and(|path| path.ends_with(".json")),
+    };
+    let walk = walk(path, &options);
+    let meter = walk.meter();
+    let walk_stats = args.walk_stats;
+    let inputs = walk.flat_map(|entry| -> Box<dyn Iterator<Item = _>> {
+        let (file, contents) = match entry {
+            Ok(Entry { path, contents }) => (path.to_string_lossy().into_owned(), contents),
+            Err(err) => return Box::new(std::iter::once(Err(err))),
+        };
+        let Some(contents) = contents else {
+            return Box::new(read_inputs(vec![file]));
+        };
+        let input = String::from_utf8(contents)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            .map(|json| Input {
+                stem: stem_of(&file),
+                origin: file,
+                json,
+            });
+        Box::new(std::iter::once(input))
+    });
+    let report = std::iter::once_with(move || {
+        if walk_stats {
+            eprintln!("{}", meter.stats());
+        }
+    });
+    Box::new(inputs.chain(report.filter_map(|()| None)))
+}
+
+/// The log at `url`, fetched as `args` ask.
+#[cfg(feature = "remote")]
+fn fetch_input(url: &str, args: &WalkArgs) -> io::Result<Input> {
+    let mut fetcher = Fetcher::new().retries(args.retries);
+    if let Some(dir) = &args.cache_dir {
+        fetcher = fetcher.cache_dir(dir);
+    }
+    let json = fetcher.fetch(url).map_err(io::Error::other)?;
+    Ok(Input {
+        origin: url.to_owned(),
+        stem: stem_of(url.split(['?', '#']).next().unwrap_or(url)),
+        json,
+    })
+}
+
+#[cfg(not(feature = "remote"))]
+fn fetch_input(url: &str, _: &WalkArgs) -> io::Result<Input> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{url}: fetching URLs needs the remote feature"),
+    ))
+}
+
+/// The Majsoul records of a `majsoul:` input, UUIDs or paipu links
+/// separated by commas, or `@` and a file listing them, fetched one by one
+/// from `--majsoul-converter`.
+#[cfg(feature = "majsoul")]
+fn majsoul_inputs(records: &str, args: &WalkArgs) -> Box<dyn Iterator<Item = io::Result<Input>>> {
+    let invalid = |err| io::Error::new(io::ErrorKind::InvalidInput, err);
+    let ids = match records.strip_prefix('@') {
+        Some(list) => std::fs::read_to_string(list)
+            .map_err(|err| io::Error::new(err.kind(), format!("{list}: {err}")))
+            .and_then(|text| {
+                majsoul::parse_list(&text).map_err(|err| invalid(format!("{list}: {err}")))
+            }),
+        None => records
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(invalid),
+    };
+    let converter = args
+        .majsoul_converter
+        .as_deref()
+        .ok_or_else(|| invalid("majsoul: inputs need --majsoul-converter".to_owned()));
+    let importer = converter.and_then(|converter| {
+        let mut fetcher = Fetcher::new().retries(args.retries);
+        if let Some(dir) = &args.cache_dir {
+            fetcher = fetcher.cache_dir(dir);
+        }
+        Ok(majsoul::Importer::new(converter)
+            .map_err(invalid)?
+            .fetcher(fetcher))
+    });
+    let (ids, importer) = match (ids, importer) {
+        (Ok(ids), Ok(importer)) => (ids, importer),
+        (Err(err), _) | (_, Err(err)) => return Box::new(std::iter::once(Err(err))),
+    };
+    Box::new(ids.into_iter().map(move |id: majsoul::RecordId| {
+        let json = importer.fetch(&id).map_err(io::Error::other)?;
+        Ok(Input {
+            origin: format!("majsoul:{id}"),
+            stem: id.to_string(),
+            json,
+        })
+    }))
+}
+
+#[cfg(not(feature = "majsoul"))]
+fn majsoul_inputs(records: &str, _: &WalkArgs) -> Box<dyn Iterator<Item = io::Result<Input>>> {
+    Box::new(std::iter::once(Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("majsoul:{records}: fetching Majsoul records needs the majsoul feature"),
+    ))))
+}
+
+fn is_input(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|s| s.to_str())
+        .is_some_and(|filename| {
+            [".json", ".jsonl", ".zip", ".tar.gz", ".tgz"]
+                .iter()
+                .any(|ext| filename.ends_with(ext))
+        })
+}
+
+fn stem_of(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_owned()
+}
+
+fn jsonl_inputs<I>(
+    origin: String,
+    stem: String,
+    lines: I,
+) -> impl Iterator<Item = io::Result<Input>>
+where
+    I: Iterator<Item = io::Result<String>>,
+{
+    lines
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(move |(i, line)| {
+            line.map(|json| Input {
+                origin: format!("{origin}:{}", i + 1),
+                stem: format!("{stem}-{}", i + 1),
+                json,
+            })
+        })
+}
+
+/// Whether the archive entry `name` holds logs, checked before reading it so
+/// that other files, which need not be UTF-8, are never read.
+fn is_log_entry(name: &str) -> bool {
+    name.ends_with(".json") || name.ends_with(".jsonl")
+}
+
+/// Turn the archive entry `name` of `archive`, one that [`is_log_entry`],
+/// into inputs.
+fn archive_entry(archive: &str, name: &str, content: String) -> io::Result<Vec<Input>> {
+    let origin = format!("{archive}:{name}");
+    let stem = stem_of(name);
+    if name.ends_with(".jsonl") {
+        let lines = content.lines().map(|line| Ok(line.to_owned()));
+        jsonl_inputs(origin, stem, lines).collect()
+    } else {
+        Ok(vec![Input {
+            origin,
+            stem,
+            json: content,
+        }])
+    }
+}
+
+fn read_zip(file: &str) -> io::Result<Vec<Input>> {
+    let mut archive = ZipArchive::new(File::open(file)?)?;
+    let mut inputs = vec![];
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if !entry.is_file() || !is_log_entry(entry.name()) {
+            continue;
+        }
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        inputs.extend(archive_entry(file, entry.name(), content)?);
     }
-    filenames
+    Ok(inputs)
+}
+
+fn read_tar_gz(file: &str) -> io::Result<Vec<Input>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(file)?));
+    let mut inputs = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        if !is_log_entry(&name) {
+            continue;
+        }
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        inputs.extend(archive_entry(file, &name, content)?);
+    }
+    Ok(inputs)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
     use tempfile::tempdir;
-    use std::fs::{self, File};
 
     #[test]
-    fn test_get_filename_list() {
+    fn test_walk_inputs() {
         let dir = tempdir().unwrap();
         let dir_path = dir.path();
 
-
         let file = dir_path.join("2024030511gm-00b9-0000-e0c07689.json");
         File::create(&file).unwrap();
         let content = r#"{"ver":2.3,"ref":"2024030511gm-00b9-0000-e0c07689","log":[[[0,0,0],[35000,35000,35000,0],[47],[],[26,27,32,33,35,37,37,39,41,42,44,44,46],[45,47,19,39,27,34,21,43,26],[60,60,60,60,60,60,"f44","f44",42],[11,19,21,23,29,31,33,41,42,44,44,45,46],[42,28,22,19,"4242p42",23,22,24],["f44",33,"f44",60,19,31,11,41],[19,21,29,29,29,31,34,34,38,41,43,47,47],[21,"47p4747",42,35,27,26,"34p3434",25],[19,43,60,31,41,38,35],[],[],[],["和了",[-700,-400,1100,0],[2,2,2,"40符1飜400-700点","役牌 中(1飜)"]]],[[1,0,0],[34300,34600,36100,0],[41],[],[19,24,27,29,31,31,31,32,34,35,39,39,46],[26,22,19,21,28,35,21,39,28],[19,46,60,29,32,21,60,24,39],[23,26,27,32,33,33,37,41,43,44,44,46,47],[23,24,36,46,"p464646",37,41,23,42,32,39,33],["f44","f44",41,43,47,24,60,36,60,32,60,32],[11,21,24,24,25,29,32,33,34,35,36,45,47],[25,38,25,47,27,22,22,37,41,28],[11,21,29,38,60,45,24,"r24",60,60],[],[],[],["和了",[0,8700,-7700,0],[1,2,1,"40符3飜7700点","役牌 發(1飜)","ドラ(2飜)"]]],[[1,1,0],[34300,43300,27400,0],[26],[],[11,25,27,31,33,38,39,41,42,43,43,45,46],[53,11,43,43,42,29,44,37,52,"42p4242",24],[11,60,45,41,43,46,29,"f44",27,31,60],[19,21,23,25,26,28,29,32,33,36,41,45,47],[25,36,27,28,26,21,23,34,46,22],[47,45,19,41,28,60,29,28,60,23],[11,24,28,31,33,34,35,36,37,38,38,42,44],[44,32,38,47,34,39,39,23,24,45,42,47],["f44","f44",11,60,42,28,24,60,60,60,60,60],[],[],[],["和了",[-3100,3100,0,0],[1,0,1,"30符2飜2900点","平和(1飜)","ドラ(1飜)"]]],[[1,2,0],[31200,46400,27400,0],[33],[],[25,31,33,35,36,41,41,44,45,47,47,47,47],[33,44,46,29,"33p3333",44,22,19,"41p4141",42,53,11,24,34,19,29,37,34],["f44","f44",25,60,46,"f44",60,60,47,60,31,60,60,35,60,60,45],[11,21,22,27,27,28,28,31,38,39,41,44,45],[38,26,42,42,45,36,32,43,26,36,11,46,23,27,46,11],["f44",11,60,60,31,60,60,41,22,21,60,60,60,60,60,60],[19,19,21,25,26,28,29,33,38,38,39,39,46],[27,36,28,41,23,37,35,37,23,34,32,42,39,22,43],[21,46,33,36,29,41,25,23,60,28,60,60,19,60,19],[],[],[],["和了",[12400,-8200,-4200,0],[0,0,0,"倍満4000-8000点","役牌 中(1飜)","場風 東(1飜)","混一色(2飜)","ドラ(5飜)","赤ドラ(1飜)"]]],[[2,0,0],[43600,38200,23200,0],[53],[47],[19,21,23,25,52,26,29,35,37,38,41,43,47],[39,34,27,44,28,42,32,45,28,19,42,21],[47,19,41,"f44",43,60,60,29,23,60,27,60],[23,24,31,32,33,36,36,38,41,43,44,45,46],[39,11,21,37,45,31,43,19,"3636p36",27,36,47,34],["f44",60,46,41,21,60,24,60,23,60,60,60,31],[22,24,25,26,28,29,33,33,35,38,38,41,47],[31,46,26,25,37,27,34,36,27,29,11,22,32],[47,60,41,22,29,28,"r37",60,60,60,60,60],[],[],[],["和了",[-1000,-1000,3000,0],[2,2,2,"30符2飜1000点∀","立直(1飜)","門前清自摸和(1飜)"]]],[[2,1,0],[42600,37200,25200,0],[29],[19],[24,26,29,32,33,35,36,37,43,43,44,46,47],[19,37,38,29,11,53,38,43,32,52,47,47,22,38,41],["f44",19,46,47,60,29,29,26,24,60,60,60,60,33,60],[21,22,23,24,25,28,33,34,34,39,42,44,46],[37,42,37,26,36,26,22,31,33,24,34,45,35,27,32],["f44",46,34,28,39,60,60,60,60,60,60,60,"r37",60],[21,22,23,26,31,31,33,35,36,38,39,45,46],[41,28,24,36,27,27,11,39,21,23,28,29,28,31],[45,46,41,39,24,60,60,60,60,60,60,60,60,33],[],[],[],["和了",[-2100,7200,-4100,0],[1,1,1,"満貫2000-4000点","立直(1飜)","門前清自摸和(1飜)","平和(1飜)","ドラ(2飜)"]]],[[4,0,0],[40500,43400,21100,0],[33],[],[11,19,26,29,31,31,32,32,33,35,43,46,47],[37,34,"3131p31",21,24,"32p3232",24,19,26,33,38,43],[43,11,29,60,60,26,60,46,60,19,19,60],[24,25,52,31,34,37,38,39,41,41,43,44,44],[46,11,39,36,21,19,28,22,37,29,53,36,33,39],["f44","f44",43,31,46,60,21,11,28,60,22,39,39,60],[22,23,25,27,28,32,34,36,36,38,42,42,46],[27,26,45,44,21,23,11,28,46,42,27,24],[46,38,60,"f44",32,34,60,36,36,46,23],[],[],[],["和了",[-6000,-3000,9000,0],[2,2,2,"跳満3000-6000点","門前清自摸和(1飜)","場風 南(1飜)","混一色(3飜)","ドラ(1飜)"]]],[[5,0,0],[34500,40400,30100,0],[47],[37],[11,23,24,52,26,32,32,33,36,38,42,44,45],[44,35,38,33,32,43,28,29,27,19,36,53,26],["f44","f44",11,42,45,60,60,60,33,60,60,33,36],[19,22,23,24,26,27,31,36,39,43,43,45,46],[23,25,31,37,35,44,39,46,28,37,31,28,25],[39,31,60,46,19,"f44",45,60,"r39",60,60,60,60],[19,21,22,23,25,27,29,29,29,32,33,34,37],[47,34,19,46,41,21,41,46,28,34,42,26],[60,19,60,60,60,37,60,60,34,60,60,"r21"],[],[],[],["和了",[0,6800,-5800,0],[1,2,1,"30符3飜5800点","立直(1飜)","平和(1飜)","ドラ(1飜)"]]],[[5,1,0],[34500,46200,24300,0],[34],[28],[23,24,25,26,31,32,32,34,38,39,42,44,46],[28,31,24,47,42,44,36,33,26,21,38,31,24,29],["f44",42,46,60,60,"f44",39,28,60,60,32,"r36",60,60],[21,23,33,34,37,38,38,41,41,43,44,45,47],[32,25,33,43,23,45,44,26,29,43,41,19,27],["f44",43,47,60,45,60,"f44",33,60,60,21,41,41],[11,19,19,21,22,27,33,36,37,41,42,42,47],[28,"42p4242",36,28,37,35,"28p2828",46,45,52,39,29,22],[11,41,47,33,22,21,27,60,60,60,60,60,60],[],[],[],["和了",[6400,0,-5400,0],[0,2,0,"40符3飜5200点","立直(1飜)","ドラ(2飜)"]]],[[6,0,0],[39900,46200,18900,0],[41,36],[47,36],[19,25,27,29,33,34,34,36,37,41,45,45,46],[19,22,37,28],[41,46,36,45],[23,24,24,27,31,31,33,37,39,41,42,43,43],[53,19,43,22],[41,42,19,53],[11,11,26,27,28,29,31,32,33,39,39,44,45],[35,47,11,38,27,11,52],["f44",45,47,60,"r35","111111a11"],[],[],[],["和了",[-4000,-4000,9000,0],[2,2,2,"満貫4000点∀","立直(1飜)","嶺上開花(1飜)","門前清自摸和(1飜)","ドラ(1飜)","赤ドラ(1飜)"]]],[[6,1,0],[35900,42200,26900,0],[25],[],[11,19,25,52,26,26,28,32,35,37,38,38,44],[24,33,46,37,47,39,37,31],["f44",19,11,46,28,35,47,38],[21,21,22,26,27,28,28,31,33,33,41,42,47],[32,41,47,27,45,"p474747",24],[41,60,42,33,60,22,60],[11,22,23,24,26,28,29,29,34,38,43,44,46],[41,44,19,36,23,25,23,19,31],["f44","f44",11,19,41,46,43,60,60],[],[],[],["和了",[12200,-12200,0,0],[0,1,0,"跳満12000点","平和(1飜)","一盃口(1飜)","ドラ(3飜)","赤ドラ(1飜)"]]]],"connection":[{"what":0,"log":0,"who":0,"step":3},{"what":1,"log":0,"who":0,"step":40}],"ratingc":"PF3","rule":{"disp":"三鳳南喰赤","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["七段","天鳳","八段","新人"],"rate":[2221.9,2461.48,2227.63,1500],"sx":["M","M","M","C"],"sc":[48100,43.1,30000,-10,26900,-33.1,0,0],"name":["mtk","つくねん3","ひぐお3",""]}
         "#;
         fs::write(&file, content).unwrap();
-        let filenames = get_filename_list(dir_path.to_str().unwrap());
-
+        fs::create_dir(dir_path.join("2024")).unwrap();
+        fs::write(dir_path.join("2024/a.json"), "{}").unwrap();
+        let origins = |args: &WalkArgs| -> Vec<_> {
+            let inputs = walk_inputs(dir_path.to_str().unwrap(), args);
+            inputs.map(|input| input.unwrap().origin).collect()
+        };
+        let filenames = origins(&WalkArgs::default());
         println!("{:?}", filenames);
+        assert_eq!(filenames.len(), 1);
+        let recursive = WalkArgs {
+            recursive: true,
+            ..Default::default()
+        };
+        assert_eq!(origins(&recursive).len(), 2);
+    }
+
+    #[test]
+    fn test_read_inputs() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("batch.jsonl");
+        fs::write(&file, "{\"a\":1}\n\n{\"b\":2}\n").unwrap();
+
+        let inputs: Vec<_> = walk_inputs(dir.path().to_str().unwrap(), &WalkArgs::default())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(inputs.len(), 2);
+        assert!(inputs[1].origin.ends_with("batch.jsonl:3"));
+        assert_eq!(inputs[1].stem, "batch-3");
+        assert_eq!(inputs[1].json, "{\"b\":2}");
+    }
+
+    #[test]
+    fn test_read_archives() {
+        let dir = tempdir().unwrap();
+
+        let mut zip = zip::ZipWriter::new(File::create(dir.path().join("a.zip")).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("logs/x.json", options).unwrap();
+        zip.write_all(b"{}").unwrap();
+        zip.start_file("readme.txt", options).unwrap();
+        zip.start_file(".DS_Store", options).unwrap();
+        zip.write_all(&[0, 0, 0, 1, 0xff, 0xfe]).unwrap();
+        zip.finish().unwrap();
+
+        let gz = flate2::write::GzEncoder::new(
+            File::create(dir.path().join("b.tar.gz")).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut tar = tar::Builder::new(gz);
+        let content = b"{}\n{}\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, "y.jsonl", &content[..])
+            .unwrap();
+        let image = [0x89, b'P', b'N', b'G', 0xff];
+        let mut header = tar::Header::new_gnu();
+        header.set_size(image.len() as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, "logo.png", &image[..])
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let mut origins: Vec<_> = walk_inputs(dir.path().to_str().unwrap(), &WalkArgs::default())
+            .map(|input| input.unwrap().origin)
+            .collect();
+        origins.sort();
+        assert_eq!(origins.len(), 3);
+        assert!(origins[0].ends_with("a.zip:logs/x.json"));
+        assert!(origins[2].ends_with("b.tar.gz:y.jsonl:2"));
+    }
+
+    #[test]
+    fn test_take_utf8() {
+        // A sequence cut off is held back until the rest comes.
+        let mut pending = "東".as_bytes()[..2].to_vec();
+        assert_eq!(take_utf8(&mut pending, false), "");
+        assert_eq!(pending.len(), 2);
+        pending.extend_from_slice(&"東<".as_bytes()[2..]);
+        assert_eq!(take_utf8(&mut pending, false), "東<");
+        assert!(pending.is_empty());
+
+        // Invalid bytes are replaced rather than stalling the stream.
+        let mut pending = b"<a\xff\xfe/>".to_vec();
+        pending.extend_from_slice(&"南".as_bytes()[..1]);
+        assert_eq!(take_utf8(&mut pending, false), "<a\u{fffd}\u{fffd}/>");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(take_utf8(&mut pending, true), "\u{fffd}");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_planned_action() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("a.json");
+        assert_eq!(planned_action(&path, false), "write");
+        fs::write(&path, "[]").unwrap();
+        assert_eq!(planned_action(&path, false), "exists");
+        assert_eq!(planned_action(&path, true), "overwrite");
     }
 
     #[test]
@@ -87,7 +2175,6 @@ mod tests {
         let dir = tempdir().unwrap();
         let dir_path = dir.path();
 
-
         let file = dir_path.join("2024030511gm-00b9-0000-e0c07689.json");
         File::create(&file).unwrap();
         let content = r#"{"ver":2.3,"ref":"2024030511gm-00b9-0000-e0c07689","log":[[[0,0,0],[35000,35000,35000,0],[47],[],[26,27,32,33,35,37,37,39,41,42,44,44,46],[45,47,19,39,27,34,21,43,26],[60,60,60,60,60,60,"f44","f44",42],[11,19,21,23,29,31,33,41,42,44,44,45,46],[42,28,22,19,"4242p42",23,22,24],["f44",33,"f44",60,19,31,11,41],[19,21,29,29,29,31,34,34,38,41,43,47,47],[21,"47p4747",42,35,27,26,"34p3434",25],[19,43,60,31,41,38,35],[],[],[],["和了",[-700,-400,1100,0],[2,2,2,"40符1飜400-700点","役牌 中(1飜)"]]],[[1,0,0],[34300,34600,36100,0],[41],[],[19,24,27,29,31,31,31,32,34,35,39,39,46],[26,22,19,21,28,35,21,39,28],[19,46,60,29,32,21,60,24,39],[23,26,27,32,33,33,37,41,43,44,44,46,47],[23,24,36,46,"p464646",37,41,23,42,32,39,33],["f44","f44",41,43,47,24,60,36,60,32,60,32],[11,21,24,24,25,29,32,33,34,35,36,45,47],[25,38,25,47,27,22,22,37,41,28],[11,21,29,38,60,45,24,"r24",60,60],[],[],[],["和了",[0,8700,-7700,0],[1,2,1,"40符3飜7700点","役牌 發(1飜)","ドラ(2飜)"]]],[[1,1,0],[34300,43300,27400,0],[26],[],[11,25,27,31,33,38,39,41,42,43,43,45,46],[53,11,43,43,42,29,44,37,52,"42p4242",24],[11,60,45,41,43,46,29,"f44",27,31,60],[19,21,23,25,26,28,29,32,33,36,41,45,47],[25,36,27,28,26,21,23,34,46,22],[47,45,19,41,28,60,29,28,60,23],[11,24,28,31,33,34,35,36,37,38,38,42,44],[44,32,38,47,34,39,39,23,24,45,42,47],["f44","f44",11,60,42,28,24,60,60,60,60,60],[],[],[],["和了",[-3100,3100,0,0],[1,0,1,"30符2飜2900点","平和(1飜)","ドラ(1飜)"]]],[[1,2,0],[31200,46400,27400,0],[33],[],[25,31,33,35,36,41,41,44,45,47,47,47,47],[33,44,46,29,"33p3333",44,22,19,"41p4141",42,53,11,24,34,19,29,37,34],["f44","f44",25,60,46,"f44",60,60,47,60,31,60,60,35,60,60,45],[11,21,22,27,27,28,28,31,38,39,41,44,45],[38,26,42,42,45,36,32,43,26,36,11,46,23,27,46,11],["f44",11,60,60,31,60,60,41,22,21,60,60,60,60,60,60],[19,19,21,25,26,28,29,33,38,38,39,39,46],[27,36,28,41,23,37,35,37,23,34,32,42,39,22,43],[21,46,33,36,29,41,25,23,60,28,60,60,19,60,19],[],[],[],["和了",[12400,-8200,-4200,0],[0,0,0,"倍満4000-8000点","役牌 中(1飜)","場風 東(1飜)","混一色(2飜)","ドラ(5飜)","赤ドラ(1飜)"]]],[[2,0,0],[43600,38200,23200,0],[53],[47],[19,21,23,25,52,26,29,35,37,38,41,43,47],[39,34,27,44,28,42,32,45,28,19,42,21],[47,19,41,"f44",43,60,60,29,23,60,27,60],[23,24,31,32,33,36,36,38,41,43,44,45,46],[39,11,21,37,45,31,43,19,"3636p36",27,36,47,34],["f44",60,46,41,21,60,24,60,23,60,60,60,31],[22,24,25,26,28,29,33,33,35,38,38,41,47],[31,46,26,25,37,27,34,36,27,29,11,22,32],[47,60,41,22,29,28,"r37",60,60,60,60,60],[],[],[],["和了",[-1000,-1000,3000,0],[2,2,2,"30符2飜1000点∀","立直(1飜)","門前清自摸和(1飜)"]]],[[2,1,0],[42600,37200,25200,0],[29],[19],[24,26,29,32,33,35,36,37,43,43,44,46,47],[19,37,38,29,11,53,38,43,32,52,47,47,22,38,41],["f44",19,46,47,60,29,29,26,24,60,60,60,60,33,60],[21,22,23,24,25,28,33,34,34,39,42,44,46],[37,42,37,26,36,26,22,31,33,24,34,45,35,27,32],["f44",46,34,28,39,60,60,60,60,60,60,60,"r37",60],[21,22,23,26,31,31,33,35,36,38,39,45,46],[41,28,24,36,27,27,11,39,21,23,28,29,28,31],[45,46,41,39,24,60,60,60,60,60,60,60,60,33],[],[],[],["和了",[-2100,7200,-4100,0],[1,1,1,"満貫2000-4000点","立直(1飜)","門前清自摸和(1飜)","平和(1飜)","ドラ(2飜)"]]],[[4,0,0],[40500,43400,21100,0],[33],[],[11,19,26,29,31,31,32,32,33,35,43,46,47],[37,34,"3131p31",21,24,"32p3232",24,19,26,33,38,43],[43,11,29,60,60,26,60,46,60,19,19,60],[24,25,52,31,34,37,38,39,41,41,43,44,44],[46,11,39,36,21,19,28,22,37,29,53,36,33,39],["f44","f44",43,31,46,60,21,11,28,60,22,39,39,60],[22,23,25,27,28,32,34,36,36,38,42,42,46],[27,26,45,44,21,23,11,28,46,42,27,24],[46,38,60,"f44",32,34,60,36,36,46,23],[],[],[],["和了",[-6000,-3000,9000,0],[2,2,2,"跳満3000-6000点","門前清自摸和(1飜)","場風 南(1飜)","混一色(3飜)","ドラ(1飜)"]]],[[5,0,0],[34500,40400,30100,0],[47],[37],[11,23,24,52,26,32,32,33,36,38,42,44,45],[44,35,38,33,32,43,28,29,27,19,36,53,26],["f44","f44",11,42,45,60,60,60,33,60,60,33,36],[19,22,23,24,26,27,31,36,39,43,43,45,46],[23,25,31,37,35,44,39,46,28,37,31,28,25],[39,31,60,46,19,"f44",45,60,"r39",60,60,60,60],[19,21,22,23,25,27,29,29,29,32,33,34,37],[47,34,19,46,41,21,41,46,28,34,42,26],[60,19,60,60,60,37,60,60,34,60,60,"r21"],[],[],[],["和了",[0,6800,-5800,0],[1,2,1,"30符3飜5800点","立直(1飜)","平和(1飜)","ドラ(1飜)"]]],[[5,1,0],[34500,46200,24300,0],[34],[28],[23,24,25,26,31,32,32,34,38,39,42,44,46],[28,31,24,47,42,44,36,33,26,21,38,31,24,29],["f44",42,46,60,60,"f44",39,28,60,60,32,"r36",60,60],[21,23,33,34,37,38,38,41,41,43,44,45,47],[32,25,33,43,23,45,44,26,29,43,41,19,27],["f44",43,47,60,45,60,"f44",33,60,60,21,41,41],[11,19,19,21,22,27,33,36,37,41,42,42,47],[28,"42p4242",36,28,37,35,"28p2828",46,45,52,39,29,22],[11,41,47,33,22,21,27,60,60,60,60,60,60],[],[],[],["和了",[6400,0,-5400,0],[0,2,0,"40符3飜5200点","立直(1飜)","ドラ(2飜)"]]],[[6,0,0],[39900,46200,18900,0],[41,36],[47,36],[19,25,27,29,33,34,34,36,37,41,45,45,46],[19,22,37,28],[41,46,36,45],[23,24,24,27,31,31,33,37,39,41,42,43,43],[53,19,43,22],[41,42,19,53],[11,11,26,27,28,29,31,32,33,39,39,44,45],[35,47,11,38,27,11,52],["f44",45,47,60,"r35","111111a11"],[],[],[],["和了",[-4000,-4000,9000,0],[2,2,2,"満貫4000点∀","立直(1飜)","嶺上開花(1飜)","門前清自摸和(1飜)","ドラ(1飜)","赤ドラ(1飜)"]]],[[6,1,0],[35900,42200,26900,0],[25],[],[11,19,25,52,26,26,28,32,35,37,38,38,44],[24,33,46,37,47,39,37,31],["f44",19,11,46,28,35,47,38],[21,21,22,26,27,28,28,31,33,33,41,42,47],[32,41,47,27,45,"p474747",24],[41,60,42,33,60,22,60],[11,22,23,24,26,28,29,29,34,38,43,44,46],[41,44,19,36,23,25,23,19,31],["f44","f44",11,19,41,46,43,60,60],[],[],[],["和了",[12200,-12200,0,0],[0,1,0,"跳満12000点","平和(1飜)","一盃口(1飜)","ドラ(3飜)","赤ドラ(1飜)"]]]],"connection":[{"what":0,"log":0,"who":0,"step":3},{"what":1,"log":0,"who":0,"step":40}],"ratingc":"PF3","rule":{"disp":"三鳳南喰赤","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["七段","天鳳","八段","新人"],"rate":[2221.9,2461.48,2227.63,1500],"sx":["M","M","M","C"],"sc":[48100,43.1,30000,-10,26900,-33.1,0,0],"name":["mtk","つくねん3","ひぐお3",""]}
@@ -96,12 +2183,47 @@ mod tests {
 
         let output = dir_path.join("output");
 
-
         fs::create_dir_all(output.clone())?;
 
-        
-
-        convert(&file.to_str().unwrap(), output.to_str().unwrap()).unwrap();
+        let job = Job {
+            output: output.to_str().unwrap(),
+            format: OutputFormat::Mjai,
+            json_style: JsonStyle::default(),
+            locale: Locale::default(),
+            template: None,
+            partition: false,
+            force: false,
+            dry_run: false,
+            game_length: None,
+            skip_invalid_kyokus: false,
+            repair: false,
+            envelope: false,
+            ratings: false,
+            pseudo_timestamps: None,
+            three_seats: false,
+            turn_order: false,
+            aka_encoding: AkaEncoding::default(),
+            game_meta: false,
+            wall: false,
+            annotate: &[],
+            fields: Fields::ALL,
+            plugin: None,
+            error_format: ErrorFormat::Text,
+            options: ConvertOptions::default(),
+        };
+        let input = read_inputs(vec![file.to_str().unwrap().to_owned()])
+            .next()
+            .unwrap()?;
+        convert(
+            &input,
+            &job,
+            &mut Selection::default(),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
 
         let output_file = output.join("2024030511gm-00b9-0000-e0c07689.json");
 
@@ -109,6 +2231,134 @@ mod tests {
 
         println!("{}", content);
 
+        let job = Job {
+            partition: true,
+            dry_run: true,
+            ..job
+        };
+        convert(
+            &input,
+            &job,
+            &mut Selection::default(),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(!output.join("year=2024").exists());
+        let job = Job {
+            dry_run: false,
+            ..job
+        };
+        let mut manifest = Manifest::open(&output)?;
+        convert(
+            &input,
+            &job,
+            &mut Selection::default(),
+            Some(&mut manifest),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(output
+            .join("year=2024/month=03/2024030511gm-00b9-0000-e0c07689.json")
+            .exists());
+        assert_eq!(
+            manifest.entries()[0].path,
+            "year=2024/month=03/2024030511gm-00b9-0000-e0c07689.json",
+        );
+
+        let mut checksums = ChecksumManifest::open(dir_path.join("checksums.jsonl"))?;
+        let job = Job {
+            partition: false,
+            force: true,
+            ..job
+        };
+        convert(
+            &input,
+            &job,
+            &mut Selection::default(),
+            None,
+            Some(&mut checksums),
+            None,
+            None,
+        )
+        .unwrap();
+        let entry = &checksums.entries()[0];
+        assert_eq!(entry.path, "2024030511gm-00b9-0000-e0c07689.json");
+        assert_eq!(
+            entry.game_id.as_deref(),
+            Some("2024030511gm-00b9-0000-e0c07689")
+        );
+        assert_eq!(entry.options["aka"], "keep");
+        assert_eq!(checksums.verify(&output)?, []);
+
+        let bundle_path = dir_path.join("out.tar.zst");
+        let mut sink = Sink::Bundle(Bundle::create(&bundle_path, false)?);
+        let job = Job {
+            partition: false,
+            ..job
+        };
+        convert(
+            &input,
+            &job,
+            &mut Selection::default(),
+            None,
+            None,
+            None,
+            Some(&mut sink),
+        )
+        .unwrap();
+        sink.finish()?;
+        assert!(bundle_path.exists());
+
+        let shards = dir_path.join("shards");
+        let mut sink = Sink::Shards(Shards::create(&shards, "1".parse()?, false)?);
+        for _ in 0..2 {
+            convert(
+                &input,
+                &job,
+                &mut Selection::default(),
+                None,
+                None,
+                None,
+                Some(&mut sink),
+            )
+            .unwrap();
+        }
+        sink.finish()?;
+        let shard = fs::read_to_string(shards.join("shard-00001.jsonl"))?;
+        assert_eq!(shard.lines().count(), 1);
+        assert!(!output
+            .join("2024030511gm-00b9-0000-e0c07689.json.tmp")
+            .exists());
+
+        let job = Job {
+            annotate: &[Builtin::Shanten],
+            ..job
+        };
+        convert(
+            &input,
+            &job,
+            &mut Selection::default(),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let content = fs::read_to_string(output.join("2024030511gm-00b9-0000-e0c07689.json"))?;
+        let events: serde_json::Value = serde_json::from_str(&content)?;
+        let dahai = events
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["type"] == "dahai")
+            .unwrap();
+        assert!(dahai["annotations"]["shanten"].is_i64(), "{dahai}");
+
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,216 @@
+//! Majsoul records fetched in bulk, by their UUIDs or a list exported from
+//! the client, as tenhou.net/6 logs.
+//!
+//! Majsoul only hands out records over its logged-in websocket API, so they
+//! are not fetched from it directly but from a converter service that does,
+//! e.g. a self-hosted one with an account of its own, at a URL template with
+//! `{uuid}` in place of the record: `https://converter.example/{uuid}`. What
+//! it returns is read like any other log; records that are not sanma fail to
+//! parse as such.
+
+use crate::remote::{FetchError, Fetcher};
+use std::fmt;
+use std::str::FromStr;
+
+/// The UUID of a Majsoul record, e.g.
+/// `240101-0123abcd-4567-89ab-cdef-0123456789ab`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RecordId(String);
+
+impl RecordId {
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The URL of the record at `converter`, see the [module](self)
+    /// documentation.
+    #[must_use]
+    pub fn url(&self, converter: &str) -> String {
+        converter.replace("{uuid}", &self.0)
+    }
+}
+
+impl fmt::Display for RecordId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for RecordId {
+    type Err = String;
+
+    /// Parse a record UUID, or a paipu link as the client shares them, e.g.
+    /// `https://game.maj-soul.com/1/?paipu=240101-..._a12345678`. The account
+    /// the link was shared from, after the `_`, is dropped.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let id = match s.split_once("paipu=") {
+            Some((_, query)) => query.split(['&', '#']).next().unwrap_or_default(),
+            None => s.trim(),
+        };
+        let id = id.split('_').next().unwrap_or_default();
+        if !is_record_uuid(id) {
+            return Err(format!(
+                r#"invalid Majsoul record: {s:?} (expected a record UUID such as "240101-0123abcd-4567-89ab-cdef-0123456789ab" or a paipu link)"#
+            ));
+        }
+        Ok(Self(id.to_ascii_lowercase()))
+    }
+}
+
+/// A date of six digits, then a UUID: `yymmdd-xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`.
+fn is_record_uuid(s: &str) -> bool {
+    let mut groups = s.split('-');
+    let date = groups
+        .next()
+        .is_some_and(|date| date.len() == 6 && date.bytes().all(|b| b.is_ascii_digit()));
+    date && groups
+        .map(|group| (group.len(), group.bytes().all(|b| b.is_ascii_hexdigit())))
+        .eq([8, 4, 4, 4, 12].map(|len| (len, true)))
+}
+
+/// Parse a list of records, one UUID or paipu link per line, as the client
+/// or a browser extension exports them. Blank lines and lines starting with
+/// `#` are skipped, and records listed twice are fetched once.
+///
+/// # Errors
+///
+/// Fails on the first line that is not a record, telling its number.
+pub fn parse_list(text: &str) -> Result<Vec<RecordId>, String> {
+    let mut ids: Vec<RecordId> = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let id: RecordId = line
+            .parse()
+            .map_err(|err| format!("line {}: {err}", i + 1))?;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+/// Fetches Majsoul records from a converter service, see the
+/// [module](self) documentation.
+#[derive(Debug, Clone)]
+pub struct Importer {
+    converter: String,
+    fetcher: Fetcher,
+}
+
+impl Importer {
+    /// An importer of the records at `converter`, a URL template with
+    /// `{uuid}` in it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `converter` has no `{uuid}`.
+    pub fn new(converter: impl Into<String>) -> Result<Self, String> {
+        let converter = converter.into();
+        if !converter.contains("{uuid}") {
+            return Err(format!(
+                "invalid Majsoul converter: {converter:?} (expected a URL with {{uuid}} in place of the record)"
+            ));
+        }
+        Ok(Self {
+            converter,
+            fetcher: Fetcher::new(),
+        })
+    }
+
+    /// Fetch with `fetcher`, e.g. to retry more often or keep a cache.
+    #[must_use]
+    pub fn fetcher(mut self, fetcher: Fetcher) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    /// The record as a tenhou.net/6 log.
+    pub fn fetch(&self, id: &RecordId) -> Result<String, FetchError> {
+        self.fetcher.fetch(&id.url(&self.converter))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tenhou::Log;
+    use crate::testdata::Fixture;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    const UUID: &str = "240101-0123abcd-4567-89ab-cdef-0123456789ab";
+
+    #[test]
+    fn record_id() {
+        let id: RecordId = UUID.parse().unwrap();
+        assert_eq!(id.as_str(), UUID);
+        for link in [
+            format!("https://game.maj-soul.com/1/?paipu={UUID}_a12345678"),
+            format!("https://game.maj-soul.com/1/?paipu={UUID}&lang=ja"),
+            format!(" {} ", UUID.to_uppercase()),
+        ] {
+            assert_eq!(link.parse(), Ok(id.clone()), "{link}");
+        }
+        assert_eq!(
+            id.url("http://localhost/convert?id={uuid}"),
+            format!("http://localhost/convert?id={UUID}")
+        );
+        for s in [
+            "",
+            "240101",
+            "24010-0123abcd-4567-89ab-cdef-0123456789ab",
+            "240101-0123abcg-4567-89ab-cdef-0123456789ab",
+            "240101-0123abcd-4567-89ab-cdef-0123456789ab-00",
+        ] {
+            s.parse::<RecordId>().unwrap_err();
+        }
+
+        let list = format!("# exported\n{UUID}\n\nhttps://game.maj-soul.com/1/?paipu={UUID}_a1\n");
+        assert_eq!(parse_list(&list), Ok(vec![id]));
+        let err = parse_list(&format!("{UUID}\nnot a record\n")).unwrap_err();
+        assert!(err.starts_with("line 2: invalid Majsoul record"), "{err}");
+    }
+
+    #[test]
+    fn import() {
+        Importer::new("http://localhost/convert").unwrap_err();
+
+        // A converter answering with a sanma log for the record it is asked.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let converter = format!(
+            "http://{}/convert?id={{uuid}}",
+            listener.local_addr().unwrap()
+        );
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let body = Fixture::Hanchan.json();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            request
+        });
+
+        let importer = Importer::new(converter).unwrap();
+        let json = importer.fetch(&UUID.parse().unwrap()).unwrap();
+        assert!(server
+            .join()
+            .unwrap()
+            .starts_with(&format!("GET /convert?id={UUID} ")));
+        assert_eq!(Log::from_json_str(&json).unwrap().kyokus.len(), 11);
+    }
+}
@@ -1,14 +1,24 @@
 use crate::Tile;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::slice;
 
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
 use serde_with::{serde_as, skip_serializing_none};
 
+/// Points on a scoreboard or in a score change. 64 bits wide, so that custom
+/// lobbies with large starting points and sums over long merged sessions,
+/// uma and oka included, stay far from overflow.
+pub type Points = i64;
+
 /// Describes an event in mjai format.
-#[serde_as]
-#[skip_serializing_none]
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(tag = "type")]
-#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "serde", serde_as)]
+#[cfg_attr(feature = "serde", skip_serializing_none)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
 pub enum Event {
     None,
 
@@ -26,7 +36,7 @@ pub enum Event {
         honba: u8,
         kyotaku: u8,
         oya: u8,
-        scores: [i32; 4],
+        scores: [Points; 4],
         tehais: [Vec<Tile>; 4],
     },
 
@@ -80,21 +90,42 @@ pub enum Event {
     },
     ReachAccepted {
         actor: u8,
+        /// The riichi stick the actor puts on the table.
+        deltas: Option<[Points; 4]>,
     },
 
     Hora {
         actor: u8,
         target: u8,
 
-        deltas: Option<[i32; 4]>,
+        deltas: Option<[Points; 4]>,
+        /// The riichi sticks the winner collects, already part of `deltas`.
+        /// Only the first winner of a multi ron collects any.
+        sticks: Option<[Points; 4]>,
         ura_markers: Option<Vec<Tile>>,
     },
     Ryukyoku {
-        deltas: Option<[i32; 4]>,
+        /// Why the kyoku was drawn, `fanpai` for an exhausted wall.
+        reason: Option<String>,
+        deltas: Option<[Points; 4]>,
+        /// Which seats were tenpai, when the wall ran out.
+        tenpais: Option<[bool; 4]>,
+        /// The riichi sticks left on the table for the next kyoku.
+        kyotaku: Option<u8>,
     },
 
     EndKyoku,
-    EndGame,
+    EndGame {
+        /// The final scores, the riichi sticks left on the table going to
+        /// the top.
+        scores: Option<[Points; 4]>,
+        /// The placement of every seat counting from 1, 0 for the empty
+        /// fourth seat of sanma.
+        ranks: Option<[u8; 4]>,
+        /// The placement points of every seat with uma and oka, in points,
+        /// e.g. `43100` for `+43.1`, as recorded by the server.
+        final_points: Option<[Points; 4]>,
+    },
 }
 
 impl Event {
@@ -117,6 +148,73 @@ impl Event {
         }
     }
 
+    /// The `type` of the event in mjai JSON, e.g. `start_kyoku`.
+    #[must_use]
+    pub const fn type_name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::StartGame { .. } => "start_game",
+            Self::StartKyoku { .. } => "start_kyoku",
+            Self::Tsumo { .. } => "tsumo",
+            Self::Dahai { .. } => "dahai",
+            Self::Chi { .. } => "chi",
+            Self::Pon { .. } => "pon",
+            Self::Daiminkan { .. } => "daiminkan",
+            Self::Kakan { .. } => "kakan",
+            Self::Ankan { .. } => "ankan",
+            Self::Nukidora { .. } => "nukidora",
+            Self::Dora { .. } => "dora",
+            Self::Reach { .. } => "reach",
+            Self::ReachAccepted { .. } => "reach_accepted",
+            Self::Hora { .. } => "hora",
+            Self::Ryukyoku { .. } => "ryukyoku",
+            Self::EndKyoku => "end_kyoku",
+            Self::EndGame { .. } => "end_game",
+        }
+    }
+
+    /// Apply `f` to every tile carried by the event.
+    pub fn map_tiles(&mut self, mut f: impl FnMut(Tile) -> Tile) {
+        let mut apply = |tiles: &mut [Tile]| {
+            for t in tiles {
+                *t = f(*t);
+            }
+        };
+
+        match self {
+            Self::StartKyoku {
+                bakaze,
+                dora_marker,
+                tehais,
+                ..
+            } => {
+                apply(slice::from_mut(bakaze));
+                apply(slice::from_mut(dora_marker));
+                for tehai in tehais {
+                    apply(tehai);
+                }
+            }
+            Self::Tsumo { pai, .. } | Self::Dahai { pai, .. } => apply(slice::from_mut(pai)),
+            Self::Chi { pai, consumed, .. } | Self::Pon { pai, consumed, .. } => {
+                apply(slice::from_mut(pai));
+                apply(consumed);
+            }
+            Self::Daiminkan { pai, consumed, .. } | Self::Kakan { pai, consumed, .. } => {
+                apply(slice::from_mut(pai));
+                apply(consumed);
+            }
+            Self::Ankan { consumed, .. } => apply(consumed),
+            Self::Nukidora { consumed, .. } => apply(consumed),
+            Self::Dora { dora_marker } => apply(slice::from_mut(dora_marker)),
+            Self::Hora {
+                ura_markers: Some(ura_markers),
+                ..
+            } => apply(ura_markers),
+            _ => (),
+        }
+    }
+
+    #[cfg(feature = "std")]
     #[inline]
     pub(crate) const fn naki_info(&self) -> Option<(u8, Tile)> {
         match *self {
@@ -127,6 +225,7 @@ impl Event {
         }
     }
 
+    #[cfg(feature = "std")]
     #[inline]
     pub(crate) const fn naki_to_ord(&self) -> i8 {
         match *self {
@@ -140,10 +239,52 @@ impl Event {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::t;
 
+    #[cfg(feature = "serde")]
     #[test]
     fn optional_field_deser() {
         let a = r#"{"type":"hora","actor":0,"target":0}"#;
         serde_json::from_str::<Event>(a).unwrap();
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn type_name() {
+        let events = [
+            Event::ReachAccepted {
+                actor: 1,
+                deltas: None,
+            },
+            Event::Nukidora {
+                actor: 0,
+                consumed: [t!(N)],
+            },
+            Event::EndKyoku,
+        ];
+        for ev in events {
+            let json = serde_json::to_value(&ev).unwrap();
+            assert_eq!(json["type"], ev.type_name());
+        }
+    }
+
+    #[test]
+    fn map_tiles() {
+        let mut ev = Event::Pon {
+            actor: 0,
+            target: 2,
+            pai: t!(5pr),
+            consumed: t![5p, 5p],
+        };
+        ev.map_tiles(Tile::deaka);
+        assert_eq!(
+            ev,
+            Event::Pon {
+                actor: 0,
+                target: 2,
+                pai: t!(5p),
+                consumed: t![5p, 5p],
+            },
+        );
+    }
 }
@@ -0,0 +1,651 @@
+use crate::hand::mpsz;
+use crate::tenhou::TenhouTile;
+use crate::Tile;
+use std::borrow::Cow;
+#[cfg(feature = "bundle")]
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "bundle")]
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+/// What converted logs are written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// A JSON array of mjai events.
+    #[default]
+    Mjai,
+    /// A human-readable transcript, see [`crate::text::render_text`].
+    Text,
+    /// One row per event, see [`crate::ir::Csv`].
+    Csv,
+}
+
+impl OutputFormat {
+    /// The name used on the command line and in output templates.
+    #[inline]
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Mjai => "mjai",
+            Self::Text => "text",
+            Self::Csv => "csv",
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Mjai => "json",
+            Self::Text => "txt",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mjai" => Ok(Self::Mjai),
+            "text" => Ok(Self::Text),
+            "csv" => Ok(Self::Csv),
+            _ => Err(format!(
+                "unknown format {s:?}, expected \"mjai\", \"text\" or \"csv\""
+            )),
+        }
+    }
+}
+
+/// How aka dora, and with them every other tile, are written in the output
+/// of emitters, since every tool downstream expects a convention of its own.
+/// An encoding writes every tile its way, so that no output mixes two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AkaEncoding {
+    /// `5pr` and `5sr`, `E` and `P`, as mjai does.
+    #[default]
+    Mjai,
+    /// `0p` and `0s`, `1z` and `5z`, as MPSZ notation does.
+    Mpsz,
+    /// `52` and `53`, `41` and `45`, the numeric codes of tenhou.net/6 logs.
+    Tenhou,
+}
+
+impl AkaEncoding {
+    /// The name used on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Mjai => "mjai",
+            Self::Mpsz => "mpsz",
+            Self::Tenhou => "tenhou",
+        }
+    }
+
+    /// `tile` as a string in this encoding. The unknown tile is `?` in MPSZ
+    /// notation, as in mjai, and `0` in tenhou's codes.
+    #[must_use]
+    pub fn encode(self, tile: Tile) -> Cow<'static, str> {
+        match self {
+            Self::Mjai => tile.as_str().into(),
+            Self::Mpsz => mpsz(&[tile]).into(),
+            Self::Tenhou => (TenhouTile::from(tile) as u8).to_string().into(),
+        }
+    }
+}
+
+impl FromStr for AkaEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mjai" => Ok(Self::Mjai),
+            "mpsz" => Ok(Self::Mpsz),
+            "tenhou" => Ok(Self::Tenhou),
+            _ => Err(format!(
+                "unknown aka encoding {s:?}, expected \"mjai\", \"mpsz\" or \"tenhou\""
+            )),
+        }
+    }
+}
+
+/// What tables of statistics, e.g. a [`crate::stats::Leaderboard`], are
+/// written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableFormat {
+    /// A JSON array of rows.
+    Json,
+    #[default]
+    Csv,
+    /// A Markdown table, e.g. to paste into a league's announcements.
+    Markdown,
+}
+
+impl FromStr for TableFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => Err(format!(
+                "unknown format {s:?}, expected \"json\", \"csv\" or \"markdown\""
+            )),
+        }
+    }
+}
+
+/// How JSON output, of mjai events and of tenhou logs alike, is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonStyle {
+    /// Everything on a single line.
+    #[default]
+    Compact,
+    /// Indented, for inspection by humans.
+    Pretty,
+}
+
+impl JsonStyle {
+    /// Write `value` in this style, followed by a newline.
+    pub fn write<W, T>(self, mut w: W, value: &T) -> io::Result<()>
+    where
+        W: io::Write,
+        T: Serialize + ?Sized,
+    {
+        match self {
+            Self::Compact => serde_json::to_writer(&mut w, value)?,
+            Self::Pretty => serde_json::to_writer_pretty(&mut w, value)?,
+        }
+        writeln!(w)
+    }
+}
+
+/// Write a file atomically: the content is first written to a temporary file
+/// next to `path` and only moved into place once `write` has succeeded, so a
+/// crash never leaves a truncated output behind.
+///
+/// Unless `overwrite` is set, an existing file at `path` is never replaced and
+/// an [`io::ErrorKind::AlreadyExists`] error is returned instead. Errors
+/// of `write` other than [`io::Error`]s are returned as they are.
+pub fn write_atomic<F, E>(path: &Path, overwrite: bool, write: F) -> Result<(), E>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), E>,
+    E: From<io::Error>,
+{
+    if !overwrite && path.exists() {
+        return Err(already_exists(path).into());
+    }
+
+    let tmp_path = temp_path_for(path);
+    let result = File::create(&tmp_path).map_err(E::from).and_then(|file| {
+        let mut w = BufWriter::new(file);
+        write(&mut w)?;
+        w.into_inner().map_err(io::Error::from)?.sync_all()?;
+        Ok(persist(&tmp_path, path, overwrite)?)
+    });
+
+    if result.is_err() {
+        fs::remove_file(&tmp_path).ok();
+    }
+    result
+}
+
+/// Move the finished `tmp_path` to `path`.
+fn persist(tmp_path: &Path, path: &Path, overwrite: bool) -> io::Result<()> {
+    if overwrite {
+        fs::rename(tmp_path, path)
+    } else {
+        // Unlike `rename`, `hard_link` fails if the destination exists, so
+        // a file created concurrently is never clobbered.
+        fs::hard_link(tmp_path, path).map_err(|err| {
+            if err.kind() == io::ErrorKind::AlreadyExists {
+                already_exists(path)
+            } else {
+                err
+            }
+        })?;
+        fs::remove_file(tmp_path)
+    }
+}
+
+/// How much a shard of [`Shards`] holds: a number of games, e.g. `10000`, or
+/// of bytes, given with a unit, e.g. `256MiB` or `1GB`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShardSize {
+    Games(u64),
+    Bytes(u64),
+}
+
+impl FromStr for ShardSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(digits);
+        let invalid = || format!("invalid shard size {s:?}, expected e.g. 10000 or 256MiB");
+        let number: u64 = number.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?;
+        let scale: u64 = match unit.trim() {
+            "" => return Ok(Self::Games(number)),
+            "B" => 1,
+            "KB" => 1000,
+            "MB" => 1000 * 1000,
+            "GB" => 1000 * 1000 * 1000,
+            "KiB" => 1 << 10,
+            "MiB" => 1 << 20,
+            "GiB" => 1 << 30,
+            unit => {
+                return Err(format!(
+                    "unknown unit {unit:?} of shard size, expected B, KB, MB, GB, KiB, MiB or GiB"
+                ))
+            }
+        };
+        number
+            .checked_mul(scale)
+            .map(Self::Bytes)
+            .ok_or_else(|| format!("shard size {s:?} is too big"))
+    }
+}
+
+/// Numbered files `shard-00000.jsonl`, `shard-00001.jsonl` and so on in a
+/// directory, which outputs of a line each are written into in turn, the
+/// layout the dataloaders of most training pipelines expect. A shard ends
+/// once it holds as much as its [`ShardSize`] allows, and a single output
+/// bigger than that gets a shard of its own.
+///
+/// Shards are written like [`write_atomic`] writes a file, so that a shard
+/// is either complete or not there.
+#[derive(Debug)]
+pub struct Shards {
+    dir: PathBuf,
+    size: ShardSize,
+    overwrite: bool,
+    /// The number of the next shard to start.
+    next: usize,
+    current: Option<Shard>,
+    buf: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Shard {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    w: BufWriter<File>,
+    games: u64,
+    bytes: u64,
+}
+
+impl Shards {
+    /// Start writing shards into `dir`, creating it if needed. Unless
+    /// `overwrite` is set, existing shards are never replaced and an
+    /// [`io::ErrorKind::AlreadyExists`] error is returned instead.
+    pub fn create(dir: impl Into<PathBuf>, size: ShardSize, overwrite: bool) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            size,
+            overwrite,
+            next: 0,
+            current: None,
+            buf: vec![],
+        })
+    }
+
+    /// Add an output with what `write` writes, which should be a line.
+    pub fn add<F>(&mut self, write: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Vec<u8>) -> io::Result<()>,
+    {
+        self.buf.clear();
+        write(&mut self.buf)?;
+        let len = self.buf.len() as u64;
+        let full = self.current.as_ref().is_some_and(|shard| match self.size {
+            ShardSize::Games(games) => shard.games >= games,
+            ShardSize::Bytes(bytes) => shard.bytes + len > bytes,
+        });
+        if full {
+            self.close()?;
+        }
+        if self.current.is_none() {
+            self.current = Some(self.open()?);
+        }
+        let shard = self.current.as_mut().expect("a shard is open");
+        shard.w.write_all(&self.buf)?;
+        shard.games += 1;
+        shard.bytes += len;
+        Ok(())
+    }
+
+    /// Finish the last shard, returning the number of shards written.
+    pub fn finish(mut self) -> io::Result<usize> {
+        self.close()?;
+        Ok(self.next)
+    }
+
+    fn open(&mut self) -> io::Result<Shard> {
+        let path = self.dir.join(format!("shard-{:05}.jsonl", self.next));
+        if !self.overwrite && path.exists() {
+            return Err(already_exists(&path));
+        }
+        let tmp_path = temp_path_for(&path);
+        let w = BufWriter::new(File::create(&tmp_path)?);
+        self.next += 1;
+        Ok(Shard {
+            path,
+            tmp_path,
+            w,
+            games: 0,
+            bytes: 0,
+        })
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        let Some(shard) = self.current.take() else {
+            return Ok(());
+        };
+        let result = shard
+            .w
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+            .and_then(|file| file.sync_all())
+            .and_then(|()| persist(&shard.tmp_path, &shard.path, self.overwrite));
+        if result.is_err() {
+            fs::remove_file(&shard.tmp_path).ok();
+        }
+        result
+    }
+}
+
+impl Drop for Shards {
+    fn drop(&mut self) {
+        if let Some(shard) = self.current.take() {
+            drop(shard.w);
+            fs::remove_file(&shard.tmp_path).ok();
+        }
+    }
+}
+
+/// A `.tar.zst` file outputs are streamed into one after the other, for
+/// batches whose millions of small files would be slow to write one by one,
+/// as on network filesystems.
+///
+/// Like [`write_atomic`] it is written next to its path and only moved into
+/// place by [`Bundle::finish`]; dropped before that, it leaves nothing behind.
+#[cfg(feature = "bundle")]
+pub struct Bundle {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    overwrite: bool,
+    tar: Option<tar::Builder<zstd::Encoder<'static, BufWriter<File>>>>,
+    /// Every entry so far, to refuse a second one of the same name.
+    names: HashSet<PathBuf>,
+    mtime: u64,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "bundle")]
+impl Bundle {
+    /// Start a bundle at `path`. Unless `overwrite` is set, an existing file
+    /// there is never replaced and an [`io::ErrorKind::AlreadyExists`] error
+    /// is returned instead, as are entries added twice.
+    pub fn create(path: impl Into<PathBuf>, overwrite: bool) -> io::Result<Self> {
+        let path = path.into();
+        if !overwrite && path.exists() {
+            return Err(already_exists(&path));
+        }
+        let tmp_path = temp_path_for(&path);
+        let file = BufWriter::new(File::create(&tmp_path)?);
+        let encoder = zstd::Encoder::new(file, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+        let mtime = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Ok(Self {
+            path,
+            tmp_path,
+            overwrite,
+            tar: Some(tar::Builder::new(encoder)),
+            names: HashSet::new(),
+            mtime,
+            buf: vec![],
+        })
+    }
+
+    /// Add a file at `name` in the bundle with what `write` writes.
+    pub fn add<F>(&mut self, name: &Path, write: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Vec<u8>) -> io::Result<()>,
+    {
+        if !self.overwrite && self.names.contains(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "{} is already in {}, pass --force to overwrite",
+                    name.display(),
+                    self.path.display()
+                ),
+            ));
+        }
+        self.buf.clear();
+        write(&mut self.buf)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(self.buf.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(self.mtime);
+        let tar = self.tar.as_mut().expect("bundle is not finished");
+        tar.append_data(&mut header, name, self.buf.as_slice())?;
+        self.names.insert(name.to_owned());
+        Ok(())
+    }
+
+    /// Finish the archive and move it into place.
+    pub fn finish(mut self) -> io::Result<()> {
+        let tar = self.tar.take().expect("bundle is not finished");
+        let result = tar
+            .into_inner()
+            .and_then(zstd::Encoder::finish)
+            .and_then(|w| w.into_inner().map_err(io::IntoInnerError::into_error))
+            .and_then(|file| file.sync_all())
+            .and_then(|()| persist(&self.tmp_path, &self.path, self.overwrite));
+        if result.is_err() {
+            fs::remove_file(&self.tmp_path).ok();
+        }
+        result
+    }
+}
+
+#[cfg(feature = "bundle")]
+impl Drop for Bundle {
+    fn drop(&mut self) {
+        if self.tar.take().is_some() {
+            fs::remove_file(&self.tmp_path).ok();
+        }
+    }
+}
+
+/// A temporary file next to `path`, unique across processes by the pid and
+/// across threads and writers of one process by a counter.
+fn temp_path_for(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let name = path
+        .file_name()
+        .map_or_else(Default::default, |s| s.to_string_lossy());
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{name}.{}.{n}.tmp", std::process::id()))
+}
+
+fn already_exists(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!(
+            "{} already exists, pass --force to overwrite",
+            path.display()
+        ),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn json_style() {
+        let value = serde_json::json!([{"actor": 0}]);
+        let mut out = vec![];
+        JsonStyle::Compact.write(&mut out, &value).unwrap();
+        assert_eq!(out, b"[{\"actor\":0}]\n");
+
+        let mut out = vec![];
+        JsonStyle::Pretty.write(&mut out, &value).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[\n  {\n    \"actor\": 0\n  }\n]\n"
+        );
+    }
+
+    #[test]
+    fn aka_encoding() {
+        use crate::t;
+        let encodings = ["mjai", "mpsz", "tenhou"].map(|s| s.parse::<AkaEncoding>().unwrap());
+        let encode = |tile| encodings.map(|aka| aka.encode(tile).into_owned());
+        assert_eq!(encode(t!(5sr)), ["5sr", "0s", "53"]);
+        assert_eq!(encode(t!(5p)), ["5p", "5p", "25"]);
+        assert_eq!(encode(t!(1m)), ["1m", "1m", "11"]);
+        assert_eq!(encode(t!(P)), ["P", "5z", "45"]);
+        assert_eq!(encode(t!(?)), ["?", "?", "0"]);
+        assert_eq!(encodings.map(AkaEncoding::name), ["mjai", "mpsz", "tenhou"]);
+        "0p".parse::<AkaEncoding>().unwrap_err();
+    }
+
+    #[test]
+    fn atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.json");
+
+        write_atomic(&path, false, |w| w.write_all(b"first")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+
+        let err = write_atomic(&path, false, |w| w.write_all(b"second")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+
+        write_atomic(&path, true, |w| w.write_all(b"third")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third");
+
+        write_atomic(&path, true, |_| Err(io::Error::other("boom"))).unwrap_err();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        let err = write_atomic(&path, true, |_| -> Result<(), Box<dyn std::error::Error>> {
+            Err("not a log".into())
+        })
+        .unwrap_err();
+        assert!(!err.is::<io::Error>(), "{err}");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // Writers of one process to the same path get temporary files of
+        // their own.
+        assert_ne!(temp_path_for(&path), temp_path_for(&path));
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    write_atomic(path, true, |w| write!(w, "writer {i}")).unwrap();
+                });
+            }
+        });
+        assert!(fs::read_to_string(&path).unwrap().starts_with("writer "));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn shards() {
+        assert_eq!("10000".parse(), Ok(ShardSize::Games(10000)));
+        assert_eq!("256MiB".parse(), Ok(ShardSize::Bytes(256 << 20)));
+        assert_eq!("2 KB".parse(), Ok(ShardSize::Bytes(2000)));
+        "0".parse::<ShardSize>().unwrap_err();
+        "1TB".parse::<ShardSize>().unwrap_err();
+        "MiB".parse::<ShardSize>().unwrap_err();
+
+        let dir = tempfile::tempdir().unwrap();
+        let line = |n: usize| format!("{}\n", "x".repeat(n));
+        let mut shards = Shards::create(dir.path(), ShardSize::Games(2), false).unwrap();
+        for n in 0..5 {
+            shards.add(|w| w.write_all(line(n).as_bytes())).unwrap();
+        }
+        // Only finished shards are there.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+        assert_eq!(shards.finish().unwrap(), 3);
+        let read = |dir: &Path, i: usize| {
+            fs::read_to_string(dir.join(format!("shard-{i:05}.jsonl"))).unwrap()
+        };
+        assert_eq!(read(dir.path(), 0), "\nx\n");
+        assert_eq!(read(dir.path(), 2), "xxxx\n");
+        // Existing shards are not replaced.
+        let mut shards = Shards::create(dir.path(), ShardSize::Games(2), false).unwrap();
+        let err = shards.add(|w| w.write_all(b"\n")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut shards = Shards::create(dir.path(), ShardSize::Bytes(6), false).unwrap();
+        for n in [2, 2, 9, 1] {
+            shards.add(|w| w.write_all(line(n).as_bytes())).unwrap();
+        }
+        assert_eq!(shards.finish().unwrap(), 3);
+        assert_eq!(read(dir.path(), 0), "xx\nxx\n");
+        assert_eq!(read(dir.path(), 1), "xxxxxxxxx\n");
+        assert_eq!(read(dir.path(), 2), "x\n");
+    }
+
+    #[cfg(feature = "bundle")]
+    #[test]
+    fn bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.tar.zst");
+
+        let mut bundle = Bundle::create(&path, false).unwrap();
+        bundle
+            .add(Path::new("a.json"), |w| w.write_all(b"[]"))
+            .unwrap();
+        let name = Path::new("2024/b.json");
+        bundle.add(name, |w| w.write_all(b"[1]")).unwrap();
+        let err = bundle.add(name, |w| w.write_all(b"[2]")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        // Nothing is there until it is finished.
+        assert!(!path.exists());
+        bundle.finish().unwrap();
+
+        let decoder = zstd::Decoder::new(File::open(&path).unwrap()).unwrap();
+        let mut archive = tar::Archive::new(decoder);
+        let entries: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let name = entry.path().unwrap().to_string_lossy().into_owned();
+                let mut content = String::new();
+                io::Read::read_to_string(&mut entry, &mut content).unwrap();
+                (name, content)
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("a.json".to_owned(), "[]".to_owned()),
+                ("2024/b.json".to_owned(), "[1]".to_owned()),
+            ],
+        );
+
+        let err = Bundle::create(&path, false).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        drop(Bundle::create(&path, true).unwrap());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
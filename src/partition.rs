@@ -0,0 +1,202 @@
+//! Hive-style partitioned output for corpus-scale exports: every output goes
+//! under `year=2024/month=03/` by the month its game started, and a manifest
+//! at the root lists every file written, so data-lake tools can prune by
+//! partition and pick up only what a later run added.
+
+use crate::output::write_atomic;
+use crate::tenhou::Log;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// The name of the manifest at the root of a partitioned directory. Like
+/// every name starting with `_`, readers of Hive layouts skip it as data.
+pub const MANIFEST: &str = "_manifest.jsonl";
+
+/// The partition value of logs whose game ID tells no date, as Hive names
+/// null partitions.
+pub const DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// The year and month of a log, the keys it is partitioned by.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Partition {
+    pub year: String,
+    pub month: String,
+}
+
+impl Partition {
+    /// The partition of `log` by the month its game started in Japan time,
+    /// or [`DEFAULT_PARTITION`] for both keys without a dated game ID.
+    #[must_use]
+    pub fn of(log: &Log) -> Self {
+        match log.started_at() {
+            Some(time) => Self {
+                year: time.format("%Y").to_string(),
+                month: time.format("%m").to_string(),
+            },
+            None => Self {
+                year: DEFAULT_PARTITION.to_owned(),
+                month: DEFAULT_PARTITION.to_owned(),
+            },
+        }
+    }
+
+    /// The directory of the partition relative to the root, e.g.
+    /// `year=2024/month=03`.
+    #[must_use]
+    pub fn path(&self) -> PathBuf {
+        Path::new(&format!("year={}", self.year)).join(format!("month={}", self.month))
+    }
+}
+
+/// A file of a partitioned directory, one line of the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Relative to the root, with `/` separators on every platform.
+    pub path: String,
+    #[serde(flatten)]
+    pub partition: Partition,
+    pub game_id: Option<String>,
+    /// The output format, e.g. `mjai`.
+    pub format: String,
+    pub bytes: u64,
+    /// When the file was written, in seconds since the Unix epoch. Readers
+    /// ingest incrementally by taking the entries newer than their last run.
+    pub converted_at: u64,
+}
+
+/// The manifest of a partitioned directory, read when opened and updated by
+/// each run.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    root: PathBuf,
+    entries: Vec<ManifestEntry>,
+    by_path: HashMap<String, usize>,
+}
+
+impl Manifest {
+    /// The manifest at `root`, empty if there is none yet.
+    pub fn open(root: impl Into<PathBuf>) -> io::Result<Self> {
+        let mut manifest = Self {
+            root: root.into(),
+            entries: vec![],
+            by_path: HashMap::new(),
+        };
+        match fs::File::open(manifest.root.join(MANIFEST)) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    manifest.insert(serde_json::from_str(&line)?);
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err),
+        }
+        Ok(manifest)
+    }
+
+    #[must_use]
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+
+    /// Record the file at `path`, relative to the root, written now for
+    /// `log`. A file written again replaces its entry.
+    pub fn record(&mut self, path: &Path, log: &Log, format: &str) -> io::Result<()> {
+        let bytes = fs::metadata(self.root.join(path))?.len();
+        let converted_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let path = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let entry = ManifestEntry {
+            path,
+            partition: Partition::of(log),
+            game_id: log.game_id.clone(),
+            format: format.to_owned(),
+            bytes,
+            converted_at,
+        };
+        self.insert(entry);
+        Ok(())
+    }
+
+    /// Add `entry`, replacing the entry of its path if there is one.
+    fn insert(&mut self, entry: ManifestEntry) {
+        match self.by_path.get(&entry.path) {
+            Some(&i) => self.entries[i] = entry,
+            None => {
+                self.by_path.insert(entry.path.clone(), self.entries.len());
+                self.entries.push(entry);
+            }
+        }
+    }
+
+    /// Write the manifest back to the root, atomically.
+    pub fn save(&self) -> io::Result<()> {
+        write_atomic(&self.root.join(MANIFEST), true, |w| {
+            for entry in &self.entries {
+                serde_json::to_writer(&mut *w, entry)?;
+                w.write_all(b"\n")?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = Fixture::Hanchan.log();
+        let partition = Partition::of(&log);
+        let path = partition.path().join("a.json");
+        assert_eq!(path, Path::new("year=2024/month=03/a.json"));
+
+        fs::create_dir_all(dir.path().join(partition.path())).unwrap();
+        fs::write(dir.path().join(&path), "[]").unwrap();
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+        assert!(manifest.entries().is_empty());
+        manifest.record(&path, &log, "mjai").unwrap();
+        manifest.save().unwrap();
+
+        // A later run keeps what is there and replaces files written again.
+        fs::write(dir.path().join(&path), "[{}]").unwrap();
+        log.game_id = None;
+        let undated = Partition::of(&log).path().join("b.json");
+        fs::create_dir_all(dir.path().join(undated.parent().unwrap())).unwrap();
+        fs::write(dir.path().join(&undated), "[]").unwrap();
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+        manifest
+            .record(&path, &Fixture::Hanchan.log(), "mjai")
+            .unwrap();
+        manifest.record(&undated, &log, "mjai").unwrap();
+        manifest.save().unwrap();
+
+        let entries = Manifest::open(dir.path()).unwrap().entries;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "year=2024/month=03/a.json");
+        assert_eq!(entries[0].bytes, 4);
+        assert_eq!(entries[0].partition.month, "03");
+        assert_eq!(
+            entries[1].path,
+            "year=__HIVE_DEFAULT_PARTITION__/month=__HIVE_DEFAULT_PARTITION__/b.json",
+        );
+        let manifest = fs::read_to_string(dir.path().join(MANIFEST)).unwrap();
+        assert!(manifest.starts_with(r#"{"path":"year=2024/month=03/a.json","year":"2024","month":"03","game_id":"2024030511gm-00b9-0000-e0c07689","format":"mjai","bytes":4,"converted_at":"#));
+    }
+}
@@ -0,0 +1,127 @@
+//! Output emitters outside of the crate, run as programs, so that formats of
+//! their own plug into batches without a fork of the crate.
+//!
+//! The protocol is a pipe: a [`Subprocess`] is started once per game, gets
+//! the game as the mjai JSON [`Mjai`] writes on its stdin, and whatever it
+//! writes to its stdout until it exits is the output. Its stderr is left to
+//! the terminal for its own diagnostics, and an exit status other than
+//! success fails the game.
+
+use crate::ir::{Emitter, Game, Mjai};
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// An emitter run as a program, see the [module](self) documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subprocess {
+    pub program: OsString,
+    pub args: Vec<OsString>,
+    /// The file extension of its output, without the dot.
+    pub extension: String,
+}
+
+impl Subprocess {
+    #[must_use]
+    pub fn new(program: impl Into<OsString>, extension: &str) -> Self {
+        Self {
+            program: program.into(),
+            args: vec![],
+            extension: extension.to_owned(),
+        }
+    }
+
+    #[must_use]
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Run the program on the input `write` writes and copy its output to
+    /// `w`, for inputs other than the plain mjai [`Emitter::emit`] writes,
+    /// e.g. one with an envelope.
+    pub fn pipe<F>(&self, w: &mut dyn Write, write: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut dyn Write) -> io::Result<()>,
+    {
+        let mut input = vec![];
+        write(&mut input)?;
+
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| self.error(format_args!("cannot start: {err}")))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        // Fed from a thread of its own, as a program writing before it has
+        // read all of its input would otherwise block on a full pipe.
+        let copied = thread::scope(|scope| {
+            let feeder = scope.spawn(move || stdin.write_all(&input));
+            let copied = io::copy(&mut stdout, w);
+            let fed = feeder.join().expect("feeding the plugin panicked");
+            // A program that exits without reading all of its input is fine
+            // as long as it succeeds.
+            match fed {
+                Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(err),
+                _ => copied,
+            }
+        });
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(self.error(format_args!("failed with {status}")));
+        }
+        copied.map(|_| ())
+    }
+
+    fn error(&self, message: std::fmt::Arguments<'_>) -> io::Error {
+        io::Error::other(format!(
+            "plugin {}: {message}",
+            self.program.to_string_lossy()
+        ))
+    }
+}
+
+impl Emitter for Subprocess {
+    fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    fn emit(&self, game: &Game, w: &mut dyn Write) -> io::Result<()> {
+        self.pipe(w, |input| Mjai::default().emit(game, input))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use crate::ir::{Adapter, Tenhou6};
+    use crate::testdata::Fixture;
+    use crate::Event;
+
+    #[test]
+    fn subprocess() {
+        let game = Tenhou6::default().read(Fixture::Hanchan.json()).unwrap();
+        let cat = Subprocess::new("cat", "json");
+        let mut out = vec![];
+        cat.emit(&game, &mut out).unwrap();
+        let events: Vec<Event> = serde_json::from_slice(&out).unwrap();
+        assert_eq!(events, game.events);
+
+        let head = Subprocess::new("head", "txt").arg("-c").arg("5");
+        let mut out = vec![];
+        head.emit(&game, &mut out).unwrap();
+        assert_eq!(out, b"[{\"ty");
+
+        let err = Subprocess::new("false", "txt")
+            .emit(&game, &mut vec![])
+            .unwrap_err();
+        assert!(err.to_string().starts_with("plugin false: failed"), "{err}");
+        let err = Subprocess::new("/nonexistent/plugin", "txt")
+            .emit(&game, &mut vec![])
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot start"), "{err}");
+    }
+}
@@ -0,0 +1,114 @@
+//! How much of a game its players played themselves, for leaving games
+//! played largely by tenhou on behalf of disconnected seats out of datasets.
+//!
+//! Tenhou plays the turns of a disconnected seat itself, discarding every
+//! tile it draws, until the seat reconnects; the [`Connection`]s of a log
+//! tell when. The [`Quality`] of a game is the share of its actions made by
+//! connected seats.
+//!
+//! [`Connection`]: crate::tenhou::Connection
+
+use crate::tenhou::Log;
+use crate::Event;
+
+/// The actions of a game and those of them tenhou made for disconnected
+/// seats. Draws and accepted riichi sticks are not actions, as no one
+/// decides them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quality {
+    pub actions: usize,
+    /// By seat.
+    pub auto_actions: [usize; 4],
+}
+
+impl Quality {
+    /// The share of the actions made by connected seats, from `0.0` to
+    /// `1.0`, which a game without actions has.
+    #[must_use]
+    pub fn score(&self) -> f64 {
+        let auto: usize = self.auto_actions.iter().sum();
+        if self.actions == 0 {
+            1.0
+        } else {
+            1.0 - auto as f64 / self.actions as f64
+        }
+    }
+}
+
+/// The quality of the game of `log`, whose events converted are `events`.
+#[must_use]
+pub fn quality(log: &Log, events: &[Event]) -> Quality {
+    let mut connections = log.connections.clone();
+    connections.sort_by_key(|c| (c.kyoku, c.step));
+    let mut connections = connections.into_iter().peekable();
+
+    let mut quality = Quality::default();
+    let mut disconnected = [false; 4];
+    // The index of the kyoku and of the event in it, after `start_kyoku`.
+    let mut kyoku = None;
+    let mut step = 0;
+    for event in events {
+        if let Event::StartKyoku { .. } = event {
+            kyoku = Some(kyoku.map_or(0, |k| k + 1));
+            step = 0;
+            continue;
+        }
+        let Some(kyoku) = kyoku else {
+            continue;
+        };
+        while let Some(c) = connections.next_if(|c| (c.kyoku, c.step) <= (kyoku, step)) {
+            if let Some(seat) = disconnected.get_mut(usize::from(c.seat)) {
+                *seat = c.is_disconnect();
+            }
+        }
+        step += 1;
+
+        if matches!(event, Event::Tsumo { .. } | Event::ReachAccepted { .. }) {
+            continue;
+        }
+        if let Some(actor) = event.actor() {
+            quality.actions += 1;
+            if disconnected[usize::from(actor % 4)] {
+                quality.auto_actions[usize::from(actor % 4)] += 1;
+            }
+        }
+    }
+    quality
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tenhou::Connection;
+    use crate::tenhou_to_mjai;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn quality() {
+        let mut log = Fixture::Hanchan.log();
+        let events = tenhou_to_mjai(&log).unwrap();
+        let hanchan = super::quality(&log, &events);
+        // The first seat is disconnected after its first discard and back
+        // for the last of the first kyoku, the ones between all tsumogiri.
+        assert_eq!(log.connections.len(), 2);
+        assert_eq!(hanchan.auto_actions, [5, 0, 0, 0]);
+        assert!((0.9..1.0).contains(&hanchan.score()), "{hanchan:?}");
+
+        log.connections.clear();
+        let connected = super::quality(&log, &events);
+        assert_eq!(connected.actions, hanchan.actions);
+        assert_eq!(connected.auto_actions, [0; 4]);
+        assert!(connected.score() > hanchan.score());
+
+        // Never reconnecting, the second seat plays nothing of its own
+        // from the third kyoku on.
+        log.connections.push(Connection {
+            what: 0,
+            kyoku: 2,
+            seat: 1,
+            step: 0,
+        });
+        let dropped = super::quality(&log, &events);
+        assert!(dropped.auto_actions[1] > 20, "{dropped:?}");
+        assert!(dropped.score() < hanchan.score());
+    }
+}
@@ -0,0 +1,227 @@
+//! Logs fetched over HTTP(S), so that one-off conversions of shared links do
+//! not need downloading by hand.
+//!
+//! A [`Fetcher`] retries failures that may pass, such as timeouts and `5xx`
+//! responses, and keeps what it fetched in a cache directory if given one,
+//! by a hash of the URL, so that the same link is only downloaded once.
+
+use crate::cache::fingerprint;
+use crate::output::write_atomic;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+
+#[derive(Debug)]
+pub enum FetchError {
+    Http { url: String, source: reqwest::Error },
+    Status { url: String, status: StatusCode },
+    Io(io::Error),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http { url, source } => write!(f, "{url}: {source}"),
+            Self::Status { url, status } => write!(f, "{url}: {status}"),
+            Self::Io(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl Error for FetchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Http { source, .. } => Some(source),
+            Self::Status { .. } => None,
+            Self::Io(err) => err.source(),
+        }
+    }
+}
+
+impl From<io::Error> for FetchError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Fetches logs, see the [module](self) documentation.
+#[derive(Debug, Clone)]
+pub struct Fetcher {
+    client: Client,
+    retries: u32,
+    backoff: Duration,
+    cache_dir: Option<PathBuf>,
+}
+
+impl Default for Fetcher {
+    fn default() -> Self {
+        Self {
+            client: Client::new(),
+            retries: 3,
+            backoff: Duration::from_millis(500),
+            cache_dir: None,
+        }
+    }
+}
+
+impl Fetcher {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many times to try again after a failure that may pass, `3` by
+    /// default, waiting twice as long before each.
+    #[must_use]
+    pub const fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// How long to wait before the first retry, half a second by default.
+    #[must_use]
+    pub const fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Keep fetched logs in `dir`, and take them from there instead of
+    /// fetching them again.
+    #[must_use]
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// The body of `url`, from the cache directory if it was fetched before.
+    pub fn fetch(&self, url: &str) -> Result<String, FetchError> {
+        let cached = self
+            .cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", fingerprint(url.as_bytes()))));
+        if let Some(path) = &cached {
+            match fs::read_to_string(path) {
+                Ok(body) => return Ok(body),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let body = self.fetch_with_retries(url)?;
+        if let Some(path) = &cached {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            write_atomic(path, true, |w| w.write_all(body.as_bytes()))?;
+        }
+        Ok(body)
+    }
+
+    fn fetch_with_retries(&self, url: &str) -> Result<String, FetchError> {
+        let mut backoff = self.backoff;
+        let mut attempt = 0;
+        loop {
+            let result = self.fetch_once(url);
+            match result {
+                Err(err) if attempt < self.retries && is_transient(&err) => {
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn fetch_once(&self, url: &str) -> Result<String, FetchError> {
+        let http = |source| FetchError::Http {
+            url: url.to_owned(),
+            source,
+        };
+        let response = self.client.get(url).send().map_err(http)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(FetchError::Status {
+                url: url.to_owned(),
+                status,
+            });
+        }
+        response.text().map_err(http)
+    }
+}
+
+/// Whether trying again may help: timeouts, failed connections, rate limits
+/// and server errors.
+fn is_transient(err: &FetchError) -> bool {
+    match err {
+        FetchError::Http { source, .. } => source.is_timeout() || source.is_connect(),
+        FetchError::Status { status, .. } => {
+            status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+        }
+        FetchError::Io(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    /// Serve one response per status of `statuses`, in order, on a port of
+    /// localhost, and return the URL.
+    fn serve(statuses: &'static [u16]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/log.json", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for &status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let body = if status == 200 { "{}" } else { "" };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} X\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn fetch() {
+        let dir = tempfile::tempdir().unwrap();
+        let fetcher = Fetcher::new().backoff(Duration::ZERO).cache_dir(dir.path());
+
+        let url = serve(&[503, 429, 200]);
+        assert_eq!(fetcher.fetch(&url).unwrap(), "{}");
+        // From the cache, as the server is gone.
+        assert_eq!(fetcher.fetch(&url).unwrap(), "{}");
+
+        let url = serve(&[404]);
+        let err = fetcher.fetch(&url).unwrap_err();
+        assert!(
+            matches!(err, FetchError::Status { status, .. } if status == 404),
+            "{err}"
+        );
+
+        let url = serve(&[500, 500]);
+        let err = fetcher.clone().retries(1).fetch(&url).unwrap_err();
+        assert!(
+            matches!(err, FetchError::Status { status, .. } if status == 500),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,467 @@
+//! The kyokus of a corpus behind filters composed a command at a time, for
+//! `convlog repl` and exploratory study sessions without a one-off command
+//! per question.
+//!
+//! A [`KyokuIndex`] holds every kyoku with its players, the outcome for each
+//! of them and the yaku won with; a [`Repl`] keeps the [`Filters`] set so far
+//! and runs the commands of [`HELP`] against it.
+
+use crate::conv::{tenhou_to_mjai, ConvertError};
+use crate::dataset::{Labels, Outcome};
+use crate::output::write_atomic;
+use crate::replay::split_kyokus;
+use crate::tenhou::{EndStatus, Log, RawLog, Yaku};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+/// The commands of a [`Repl`].
+pub const HELP: &str = "\
+player [NAME]      only kyokus NAME played in, or any player
+yaku [YAKU]        only kyokus won with YAKU, e.g. 立直 or 役牌 中, by the player if set
+outcome [OUTCOME]  only kyokus that ended so for the player, or for any seat:
+                   won, dealt_in, tsumo_paid, tenpai, noten, drawn or uninvolved
+since [DATE]       only games played on or after DATE, e.g. 2024-03-01
+until [DATE]       only games played on or before DATE
+clear              drop every filter
+filters            show the filters and how many kyokus match
+list [N]           show the first N matching kyokus, 20 by default
+export DIR [--force]
+                   write every matching kyoku to DIR as a log of its own
+help               show this
+quit               leave";
+
+/// A kyoku of a [`KyokuIndex`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KyokuEntry {
+    /// The index of the game in the [`KyokuIndex`].
+    pub game: usize,
+    /// The index of the kyoku in the game.
+    pub index: usize,
+    /// The kyoku, e.g. `E4-0`.
+    pub name: String,
+    /// The date the game started in Japan time, for tenhou game IDs.
+    pub date: Option<NaiveDate>,
+    pub players: [String; 3],
+    pub outcomes: [Outcome; 3],
+    /// The yaku of every hora, with the seat that won with them.
+    pub yaku: Vec<(u8, Yaku)>,
+}
+
+/// The kyokus of a corpus, game by game.
+#[derive(Debug, Clone, Default)]
+pub struct KyokuIndex {
+    /// The base of the names of exported logs and the log of every game.
+    games: Vec<(String, RawLog<'static>)>,
+    entries: Vec<KyokuEntry>,
+}
+
+impl KyokuIndex {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index the kyokus of `log`, parsed from `raw`, which exported kyokus
+    /// are taken from and which is kept as an owned copy. Their files are named after `stem`, e.g. the name of
+    /// the file the log was read from.
+    ///
+    /// # Errors
+    ///
+    /// Fails for logs that do not convert.
+    pub fn add(&mut self, stem: &str, raw: RawLog<'_>, log: &Log) -> Result<(), ConvertError> {
+        let events = tenhou_to_mjai(log)?;
+        let game = self.games.len();
+        let date = log.started_at().map(|time| time.date_naive());
+        let players: [String; 3] = std::array::from_fn(|seat| log.names[seat].clone());
+        for ((index, kyoku), events) in log.kyokus.iter().enumerate().zip(split_kyokus(&events)) {
+            let mut labels = Labels::default();
+            for event in events {
+                labels.add(event);
+            }
+            let yaku = match &kyoku.end_status {
+                EndStatus::Hora { details } => details
+                    .iter()
+                    .flat_map(|detail| {
+                        detail
+                            .yaku
+                            .iter()
+                            .map(|(yaku, _)| (detail.who, yaku.clone()))
+                    })
+                    .collect(),
+                EndStatus::Ryukyoku { .. } => vec![],
+            };
+            self.entries.push(KyokuEntry {
+                game,
+                index,
+                name: kyoku.meta.short_name(),
+                date,
+                players: players.clone(),
+                outcomes: [labels.outcomes[0], labels.outcomes[1], labels.outcomes[2]],
+                yaku,
+            });
+        }
+        self.games.push((stem.to_owned(), raw.into_owned()));
+        Ok(())
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The kyokus `filters` let through, in the order they were added.
+    pub fn matches<'a>(&'a self, filters: &'a Filters) -> impl Iterator<Item = &'a KyokuEntry> {
+        self.entries.iter().filter(|entry| filters.test(entry))
+    }
+
+    /// The name the kyoku of `entry` is exported under, as `split` names
+    /// it, e.g. `a-03-E4-0.json`.
+    #[must_use]
+    pub fn file_name(&self, entry: &KyokuEntry) -> String {
+        format!(
+            "{}-{:02}-{}.json",
+            self.games[entry.game].0, entry.index, entry.name
+        )
+    }
+
+    /// The log of the game of `entry` with only its kyoku.
+    #[must_use]
+    pub fn kyoku_log(&self, entry: &KyokuEntry) -> RawLog<'static> {
+        let mut raw = self.games[entry.game].1.clone();
+        let kyoku = raw.kyokus_mut().swap_remove(entry.index);
+        *raw.kyokus_mut() = vec![kyoku];
+        raw
+    }
+}
+
+/// What the kyokus of a [`Repl`] are filtered by, every filter set needing
+/// to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filters {
+    pub player: Option<String>,
+    pub yaku: Option<Yaku>,
+    pub outcome: Option<Outcome>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+impl Filters {
+    /// Whether `entry` matches. The yaku and outcome filters apply to the
+    /// player if one is set and to any seat otherwise.
+    #[must_use]
+    pub fn test(&self, entry: &KyokuEntry) -> bool {
+        let seat = match &self.player {
+            Some(player) => match entry.players.iter().position(|name| name == player) {
+                Some(seat) => Some(seat),
+                None => return false,
+            },
+            None => None,
+        };
+        let of_seat = |s: usize| seat.is_none_or(|seat| seat == s);
+        if let Some(yaku) = &self.yaku {
+            let won_with = |(who, y): &(u8, Yaku)| y == yaku && of_seat(usize::from(*who));
+            if !entry.yaku.iter().any(won_with) {
+                return false;
+            }
+        }
+        if let Some(outcome) = self.outcome {
+            let ended = |(s, &o): (usize, &Outcome)| o == outcome && of_seat(s);
+            if !entry.outcomes.iter().enumerate().any(ended) {
+                return false;
+            }
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let in_range = entry.date.is_some_and(|date| {
+                self.since.is_none_or(|since| date >= since)
+                    && self.until.is_none_or(|until| date <= until)
+            });
+            if !in_range {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug)]
+pub enum CommandError {
+    UnknownCommand(String),
+    MissingArgument(&'static str),
+    Invalid { what: &'static str, value: String },
+    Io(io::Error),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownCommand(command) => {
+                write!(f, "unknown command {command:?}, see \"help\"")
+            }
+            Self::MissingArgument(command) => {
+                write!(f, "{command} needs an argument, see \"help\"")
+            }
+            Self::Invalid { what, value } => write!(f, "invalid {what}: {value:?}"),
+            Self::Io(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl Error for CommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => err.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CommandError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Whether a [`Repl`] takes more commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+/// Runs commands against a [`KyokuIndex`].
+#[derive(Debug, Clone)]
+pub struct Repl {
+    index: KyokuIndex,
+    pub filters: Filters,
+}
+
+/// How many kyokus `list` shows without an argument.
+const LIST: usize = 20;
+
+impl Repl {
+    #[must_use]
+    pub fn new(index: KyokuIndex) -> Self {
+        Self {
+            index,
+            filters: Filters::default(),
+        }
+    }
+
+    /// Run the command `line`, writing what it shows to `out`. Empty lines
+    /// do nothing.
+    ///
+    /// # Errors
+    ///
+    /// Fails for commands that are unknown or miss or have an invalid
+    /// argument, and if writing fails. The filters stay as they were.
+    pub fn run<W: Write>(&mut self, line: &str, mut out: W) -> Result<Flow, CommandError> {
+        let line = line.trim();
+        let (command, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let arg = Some(arg.trim()).filter(|arg| !arg.is_empty());
+        let invalid = |what, value: &str| CommandError::Invalid {
+            what,
+            value: value.to_owned(),
+        };
+        let date = |arg: Option<&str>| {
+            arg.map(|d| d.parse().map_err(|_| invalid("date", d)))
+                .transpose()
+        };
+        match command {
+            "" => return Ok(Flow::Continue),
+            "player" => self.filters.player = arg.map(str::to_owned),
+            "yaku" => {
+                self.filters.yaku = arg
+                    .map(|y| y.parse().map_err(|_| invalid("yaku", y)))
+                    .transpose()?;
+            }
+            "outcome" => {
+                self.filters.outcome = arg
+                    .map(|o| o.parse().map_err(|_| invalid("outcome", o)))
+                    .transpose()?;
+            }
+            "since" => self.filters.since = date(arg)?,
+            "until" => self.filters.until = date(arg)?,
+            "clear" => self.filters = Filters::default(),
+            "filters" => (),
+            "list" => {
+                let n = match arg {
+                    Some(n) => n.parse().map_err(|_| invalid("count", n))?,
+                    None => LIST,
+                };
+                return self.list(n, out).map(|()| Flow::Continue);
+            }
+            "export" => {
+                let arg = arg.ok_or(CommandError::MissingArgument("export"))?;
+                let (dir, force) = match arg.strip_suffix("--force") {
+                    Some(dir) => (dir.trim_end(), true),
+                    None => (arg, false),
+                };
+                return self
+                    .export(Path::new(dir), force, out)
+                    .map(|()| Flow::Continue);
+            }
+            "help" => {
+                writeln!(out, "{HELP}")?;
+                return Ok(Flow::Continue);
+            }
+            "quit" | "exit" => return Ok(Flow::Quit),
+            _ => return Err(CommandError::UnknownCommand(command.to_owned())),
+        }
+        self.show_filters(out)?;
+        Ok(Flow::Continue)
+    }
+
+    fn show_filters<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let f = &self.filters;
+        let mut set = vec![];
+        set.extend(f.player.as_ref().map(|p| format!("player {p}")));
+        set.extend(f.yaku.as_ref().map(|y| format!("yaku {y}")));
+        set.extend(f.outcome.map(|o| format!("outcome {}", o.name())));
+        set.extend(f.since.map(|d| format!("since {d}")));
+        set.extend(f.until.map(|d| format!("until {d}")));
+        if set.is_empty() {
+            set.push("no filters".to_owned());
+        }
+        let matching = self.index.matches(f).count();
+        writeln!(
+            out,
+            "{}: {matching} of {} kyokus",
+            set.join(", "),
+            self.index.len()
+        )
+    }
+
+    /// Write a line per matching kyoku, at most `n`: the file it would be
+    /// exported to and every player with their outcome.
+    fn list<W: Write>(&self, n: usize, mut out: W) -> Result<(), CommandError> {
+        let mut matching = self.index.matches(&self.filters);
+        for entry in matching.by_ref().take(n) {
+            let seats: Vec<_> = entry
+                .players
+                .iter()
+                .zip(entry.outcomes)
+                .map(|(name, outcome)| format!("{name} {}", outcome.name()))
+                .collect();
+            writeln!(out, "{}\t{}", self.index.file_name(entry), seats.join(", "))?;
+        }
+        let more = matching.count();
+        if more > 0 {
+            writeln!(out, "... and {more} more")?;
+        }
+        Ok(())
+    }
+
+    fn export<W: Write>(&self, dir: &Path, force: bool, mut out: W) -> Result<(), CommandError> {
+        std::fs::create_dir_all(dir)?;
+        let mut written = 0;
+        for entry in self.index.matches(&self.filters) {
+            let raw = self.index.kyoku_log(entry);
+            let path = dir.join(self.index.file_name(entry));
+            write_atomic(&path, force, |w| {
+                serde_json::to_writer(w, &raw).map_err(io::Error::from)
+            })?;
+            written += 1;
+        }
+        writeln!(out, "wrote {written} kyokus to {}", dir.display())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testdata::Fixture;
+
+    fn repl() -> Repl {
+        let mut index = KyokuIndex::new();
+        for (fixture, stem) in Fixture::ALL.into_iter().zip(["a", "b", "c", "d"]) {
+            let raw = RawLog::from_json_str(fixture.json()).unwrap();
+            index.add(stem, raw, &fixture.log()).unwrap();
+        }
+        Repl::new(index)
+    }
+
+    fn run(repl: &mut Repl, line: &str) -> String {
+        let mut out = vec![];
+        assert_eq!(repl.run(line, &mut out).unwrap(), Flow::Continue);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn filters() {
+        let mut repl = repl();
+        let total = repl.index.len();
+        assert_eq!(
+            run(&mut repl, "filters"),
+            format!("no filters: {total} of {total} kyokus\n")
+        );
+        let shown = run(&mut repl, "player mtk");
+        assert!(shown.starts_with("player mtk: 11 of"), "{shown}");
+
+        run(&mut repl, "outcome won");
+        let won = repl.index.matches(&repl.filters).count();
+        assert!(won > 0);
+        assert!(repl
+            .index
+            .matches(&repl.filters)
+            .all(|e| e.outcomes[0] == Outcome::Won));
+        run(&mut repl, "yaku 立直");
+        assert!(repl.index.matches(&repl.filters).count() <= won);
+
+        run(&mut repl, "clear");
+        run(&mut repl, "since 2024-04-01");
+        run(&mut repl, "until 2024-04-01");
+        assert!(repl
+            .index
+            .matches(&repl.filters)
+            .all(|e| e.players.contains(&"Aoi".to_owned())));
+
+        let listed = run(&mut repl, "list 1");
+        assert!(listed.starts_with("b-00-E1-0.json\t"), "{listed}");
+        assert!(listed.contains("... and "));
+
+        // A bad argument leaves the filters as they were.
+        let filters = repl.filters.clone();
+        let mut out = vec![];
+        repl.run("outcome lost", &mut out).unwrap_err();
+        repl.run("since yesterday", &mut out).unwrap_err();
+        repl.run("sort", &mut out).unwrap_err();
+        repl.run("export", &mut out).unwrap_err();
+        assert_eq!(repl.filters, filters);
+        assert_eq!(repl.run("quit", &mut out).unwrap(), Flow::Quit);
+    }
+
+    #[test]
+    fn export() {
+        let mut repl = repl();
+        run(&mut repl, "player mtk");
+        run(&mut repl, "outcome dealt_in");
+        let matching: Vec<_> = repl.index.matches(&repl.filters).cloned().collect();
+        assert!(!matching.is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        let line = format!("export {}", dir.path().display());
+        let shown = run(&mut repl, &line);
+        assert!(shown.starts_with(&format!("wrote {} kyokus", matching.len())));
+        for entry in &matching {
+            let path = dir.path().join(repl.index.file_name(entry));
+            let log = Log::from_json_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+            assert_eq!(log.kyokus.len(), 1);
+            assert_eq!(log.kyokus[0].meta.short_name(), entry.name);
+        }
+
+        // Refused without --force, then overwritten.
+        let mut out = vec![];
+        repl.run(&line, &mut out).unwrap_err();
+        run(&mut repl, &format!("{line} --force"));
+    }
+}
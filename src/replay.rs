@@ -0,0 +1,1121 @@
+//! The state of a kyoku at any point of its events, for viewers and analyses
+//! built on converted logs.
+
+use crate::tenhou::RIICHI_STICK;
+use crate::{t, Event, Points, Tile};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+
+pub type Result<T> = core::result::Result<T, ReplayError>;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    NotStarted {
+        index: usize,
+    },
+    MissingTile {
+        tile: Tile,
+        actor: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+    NoPonToAddTo {
+        tile: Tile,
+        actor: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+    WallExhausted {
+        actor: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+    NotSanma {
+        kyoku: u8,
+        honba: u8,
+    },
+    TooManyTiles {
+        tile: Tile,
+        kyoku: u8,
+        honba: u8,
+    },
+    OutOfTurn {
+        index: usize,
+        actor: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+    NotDiscarded {
+        tile: Tile,
+        actor: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+    Chi {
+        tile: Tile,
+        actor: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+    NotKita {
+        tile: Tile,
+        actor: u8,
+        kyoku: u8,
+        honba: u8,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotStarted { index } => write!(f, "event {index} comes before start_kyoku"),
+            Self::MissingTile {
+                tile,
+                actor,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "tile {tile} is not in the hand of seat {actor}: \
+                at kyoku {kyoku} honba {honba}"
+            ),
+            Self::NoPonToAddTo {
+                tile,
+                actor,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "kakan of {tile} without a pon to add it to: \
+                at kyoku {kyoku} honba {honba} for actor {actor}"
+            ),
+            Self::WallExhausted {
+                actor,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "draw from an exhausted wall: \
+                at kyoku {kyoku} honba {honba} for actor {actor}"
+            ),
+            Self::NotSanma { kyoku, honba } => write!(
+                f,
+                "the kyoku is not dealt to three seats: at kyoku {kyoku} honba {honba}"
+            ),
+            Self::TooManyTiles { tile, kyoku, honba } => write!(
+                f,
+                "more than four of {tile} show: at kyoku {kyoku} honba {honba}"
+            ),
+            Self::OutOfTurn {
+                index,
+                actor,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "event {index} is out of turn: \
+                at kyoku {kyoku} honba {honba} for actor {actor}"
+            ),
+            Self::NotDiscarded {
+                tile,
+                actor,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "call of {tile}, which was not just discarded by the target: \
+                at kyoku {kyoku} honba {honba} for actor {actor}"
+            ),
+            Self::Chi {
+                tile,
+                actor,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "chi of {tile}, which sanma does not allow: \
+                at kyoku {kyoku} honba {honba} for actor {actor}"
+            ),
+            Self::NotKita {
+                tile,
+                actor,
+                kyoku,
+                honba,
+            } => write!(
+                f,
+                "nukidora of {tile}, which is not a north: \
+                at kyoku {kyoku} honba {honba} for actor {actor}"
+            ),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+/// Where a draw came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawKind {
+    Wall,
+    /// The replacement draw after a kan or nukidora.
+    Rinshan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeldKind {
+    Chi,
+    Pon,
+    Daiminkan,
+    /// A pon upgraded with the fourth tile.
+    Kakan,
+    Ankan,
+}
+
+impl MeldKind {
+    /// The fu of a meld of this kind in a winning hand, of terminals or
+    /// honors if `yaokyuu`: none for chi, 2 for pon, four times that for an
+    /// open kan and eight for ankan, each doubled for `yaokyuu`.
+    #[must_use]
+    pub const fn fu(self, yaokyuu: bool) -> u8 {
+        let fu = match self {
+            Self::Chi => return 0,
+            Self::Pon => 2,
+            Self::Daiminkan | Self::Kakan => 8,
+            Self::Ankan => 16,
+        };
+        if yaokyuu {
+            fu * 2
+        } else {
+            fu
+        }
+    }
+}
+
+/// A called or declared set of tiles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Meld {
+    pub kind: MeldKind,
+    /// Every tile of the meld, the called and added ones included.
+    pub tiles: Vec<Tile>,
+    /// The seat called from, `None` for ankan.
+    pub target: Option<u8>,
+    /// The tile taken from `target`, aka or not. For kakan this stays the
+    /// tile of the pon.
+    pub called: Option<Tile>,
+}
+
+impl Meld {
+    /// See [`MeldKind::fu`].
+    #[must_use]
+    pub fn fu(&self) -> u8 {
+        let yaokyuu = self.tiles.first().is_some_and(|t| t.is_yaokyuu());
+        self.kind.fu(yaokyuu)
+    }
+}
+
+/// A tile in a discard river.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Discard {
+    pub pai: Tile,
+    pub tsumogiri: bool,
+    /// The discard declaring riichi.
+    pub riichi: bool,
+    /// Taken by another seat's call, so no longer shown in the river.
+    pub called: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlayerState {
+    /// The concealed tiles, sorted, the one just drawn included.
+    pub tehai: Vec<Tile>,
+    pub melds: Vec<Meld>,
+    pub river: Vec<Discard>,
+    /// Declared riichi, accepted or not.
+    pub riichi_declared: bool,
+    /// Riichi was accepted, its stick paid.
+    pub riichi: bool,
+    /// Nukidora set aside.
+    pub kitas: u8,
+    /// The tiles safe against this player, sorted and without aka: its own
+    /// discards and, once it declared riichi, every tile discarded since.
+    pub safe_tiles: Vec<Tile>,
+}
+
+impl PlayerState {
+    fn add_safe_tile(&mut self, tile: Tile) {
+        let tile = tile.deaka();
+        if let Err(at) = self.safe_tiles.binary_search(&tile) {
+            self.safe_tiles.insert(at, tile);
+        }
+    }
+}
+
+/// Everything known about a kyoku after some of its events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KyokuState {
+    pub bakaze: Tile,
+    /// Counts from 1.
+    pub kyoku: u8,
+    pub honba: u8,
+    pub kyotaku: u8,
+    pub oya: u8,
+    pub scores: [Points; 4],
+    pub dora_markers: Vec<Tile>,
+    pub players: [PlayerState; 4],
+    /// The seat that acted last.
+    pub last_actor: Option<u8>,
+    /// The draws left in the live wall. Rinshan draws count against it too,
+    /// as the dead wall is topped up from its end.
+    pub wall: u8,
+    /// Where the latest draw came from.
+    pub last_draw: Option<DrawKind>,
+    /// A kan or nukidora was made and its rinshan draw is next.
+    rinshan: bool,
+    started: bool,
+}
+
+/// Tiles in a sanma set, without 2m to 8m.
+const SANMA_TILES: u8 = 108;
+const YONMA_TILES: u8 = 136;
+const DEAD_WALL: u8 = 14;
+
+impl Default for KyokuState {
+    fn default() -> Self {
+        Self {
+            bakaze: t!(E),
+            kyoku: 1,
+            honba: 0,
+            kyotaku: 0,
+            oya: 0,
+            scores: [0; 4],
+            dora_markers: vec![],
+            players: Default::default(),
+            last_actor: None,
+            wall: 0,
+            last_draw: None,
+            rinshan: false,
+            started: false,
+        }
+    }
+}
+
+impl KyokuState {
+    /// Apply `event`, the one at `index` of the kyoku.
+    pub fn apply(&mut self, index: usize, event: &Event) -> Result<()> {
+        if let Event::StartKyoku {
+            bakaze,
+            dora_marker,
+            kyoku,
+            honba,
+            kyotaku,
+            oya,
+            scores,
+            tehais,
+        } = event
+        {
+            let seats = tehais.iter().filter(|tehai| !tehai.is_empty()).count() as u8;
+            let tiles = if seats == 3 { SANMA_TILES } else { YONMA_TILES };
+            *self = Self {
+                bakaze: *bakaze,
+                kyoku: *kyoku,
+                honba: *honba,
+                kyotaku: *kyotaku,
+                oya: *oya,
+                scores: *scores,
+                dora_markers: vec![*dora_marker],
+                players: Default::default(),
+                last_actor: None,
+                wall: tiles - DEAD_WALL - 13 * seats,
+                last_draw: None,
+                rinshan: false,
+                started: true,
+            };
+            for (player, tehai) in self.players.iter_mut().zip(tehais) {
+                player.tehai.clone_from(tehai);
+                player.tehai.sort_unstable();
+            }
+            return Ok(());
+        }
+        if !self.started {
+            return match event {
+                Event::None | Event::StartGame { .. } | Event::EndGame { .. } => Ok(()),
+                _ => Err(ReplayError::NotStarted { index }),
+            };
+        }
+
+        if let Some(actor) = event.actor() {
+            self.last_actor = Some(actor);
+        }
+        match *event {
+            Event::Tsumo { actor, pai } => {
+                self.wall = self.wall.checked_sub(1).ok_or(ReplayError::WallExhausted {
+                    actor,
+                    kyoku: self.kyoku,
+                    honba: self.honba,
+                })?;
+                self.last_draw = Some(if self.rinshan {
+                    DrawKind::Rinshan
+                } else {
+                    DrawKind::Wall
+                });
+                self.rinshan = false;
+                let tehai = &mut self.players[actor as usize].tehai;
+                let at = tehai.partition_point(|&t| t <= pai);
+                tehai.insert(at, pai);
+            }
+            Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                self.take(actor, &[pai])?;
+                let player = &mut self.players[actor as usize];
+                let riichi = player.riichi_declared && !player.river.iter().any(|d| d.riichi);
+                player.river.push(Discard {
+                    pai,
+                    tsumogiri,
+                    riichi,
+                    called: false,
+                });
+                for (seat, player) in self.players.iter_mut().enumerate() {
+                    if seat == actor as usize || player.riichi_declared {
+                        player.add_safe_tile(pai);
+                    }
+                }
+            }
+            Event::Chi {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => self.call(actor, target, pai, &consumed, MeldKind::Chi)?,
+            Event::Pon {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => self.call(actor, target, pai, &consumed, MeldKind::Pon)?,
+            Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                self.call(actor, target, pai, &consumed, MeldKind::Daiminkan)?;
+                self.rinshan = true;
+            }
+            Event::Kakan { actor, pai, .. } => {
+                self.rinshan = true;
+                self.take(actor, &[pai])?;
+                let (kyoku, honba) = (self.kyoku, self.honba);
+                let meld = self.players[actor as usize]
+                    .melds
+                    .iter_mut()
+                    .find(|m| m.kind == MeldKind::Pon && m.tiles[0].deaka() == pai.deaka())
+                    .ok_or(ReplayError::NoPonToAddTo {
+                        tile: pai,
+                        actor,
+                        kyoku,
+                        honba,
+                    })?;
+                meld.kind = MeldKind::Kakan;
+                meld.tiles.push(pai);
+            }
+            Event::Ankan { actor, consumed } => {
+                self.rinshan = true;
+                self.take(actor, &consumed)?;
+                self.players[actor as usize].melds.push(Meld {
+                    kind: MeldKind::Ankan,
+                    tiles: consumed.to_vec(),
+                    target: None,
+                    called: None,
+                });
+            }
+            Event::Nukidora { actor, consumed } => {
+                self.rinshan = true;
+                self.take(actor, &consumed)?;
+                self.players[actor as usize].kitas += 1;
+            }
+            Event::Dora { dora_marker } => self.dora_markers.push(dora_marker),
+            Event::Reach { actor } => self.players[actor as usize].riichi_declared = true,
+            Event::ReachAccepted { actor, deltas } => {
+                self.players[actor as usize].riichi = true;
+                match deltas {
+                    Some(deltas) => self.add_deltas(deltas),
+                    None => self.scores[actor as usize] -= RIICHI_STICK,
+                }
+                self.kyotaku += 1;
+            }
+            Event::Hora { deltas, .. } => {
+                if let Some(deltas) = deltas {
+                    self.add_deltas(deltas);
+                }
+                // Only the first winner of a multi ron takes the sticks.
+                self.kyotaku = 0;
+            }
+            Event::Ryukyoku {
+                deltas: Some(deltas),
+                ..
+            } => self.add_deltas(deltas),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn add_deltas(&mut self, deltas: [Points; 4]) {
+        for (score, delta) in self.scores.iter_mut().zip(deltas) {
+            *score += delta;
+        }
+    }
+
+    /// Remove `tiles` from the hand of `actor`.
+    fn take(&mut self, actor: u8, tiles: &[Tile]) -> Result<()> {
+        let tehai = &mut self.players[actor as usize].tehai;
+        for &tile in tiles {
+            // Hidden hands only hold unknown tiles.
+            let at = tehai
+                .iter()
+                .position(|&t| t == tile)
+                .or_else(|| tehai.iter().position(|t| t.is_unknown()))
+                .ok_or(ReplayError::MissingTile {
+                    tile,
+                    actor,
+                    kyoku: self.kyoku,
+                    honba: self.honba,
+                })?;
+            tehai.remove(at);
+        }
+        Ok(())
+    }
+
+    fn call(
+        &mut self,
+        actor: u8,
+        target: u8,
+        pai: Tile,
+        consumed: &[Tile],
+        kind: MeldKind,
+    ) -> Result<()> {
+        self.take(actor, consumed)?;
+        if let Some(discard) = self.players[target as usize].river.last_mut() {
+            discard.called = true;
+        }
+        let mut tiles = consumed.to_vec();
+        tiles.push(pai);
+        self.players[actor as usize].melds.push(Meld {
+            kind,
+            tiles,
+            target: Some(target),
+            called: Some(pai),
+        });
+        Ok(())
+    }
+}
+
+/// The events of a kyoku, along with the state after every
+/// [`Self::interval`]-th of them, so that any point can be reached without
+/// replaying from the start.
+#[derive(Debug, Clone)]
+pub struct KyokuReplay {
+    events: Vec<Event>,
+    interval: usize,
+    /// The state after `i * interval` events at index `i`.
+    checkpoints: Vec<KyokuState>,
+}
+
+impl KyokuReplay {
+    pub const DEFAULT_INTERVAL: usize = 16;
+
+    /// Replay `events`, from `start_kyoku` on, checking that they apply.
+    pub fn new(events: Vec<Event>) -> Result<Self> {
+        Self::with_interval(events, Self::DEFAULT_INTERVAL)
+    }
+
+    /// Like [`Self::new`], keeping a checkpoint every `interval` events,
+    /// which trades memory for faster seeking. An interval of 0 counts as 1.
+    pub fn with_interval(events: Vec<Event>, interval: usize) -> Result<Self> {
+        let interval = interval.max(1);
+        let mut state = KyokuState::default();
+        let mut checkpoints = vec![state.clone()];
+        for (index, event) in events.iter().enumerate() {
+            state.apply(index, event)?;
+            if (index + 1) % interval == 0 {
+                checkpoints.push(state.clone());
+            }
+        }
+        Ok(Self {
+            events,
+            interval,
+            checkpoints,
+        })
+    }
+
+    #[must_use]
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    #[must_use]
+    pub const fn interval(&self) -> usize {
+        self.interval
+    }
+
+    /// The number of events.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// A cursor at the start of the kyoku, before `start_kyoku`.
+    #[must_use]
+    pub fn cursor(&self) -> KyokuCursor<'_> {
+        KyokuCursor {
+            replay: self,
+            position: 0,
+            state: self.checkpoints[0].clone(),
+        }
+    }
+
+    /// The state after the first `index` events, `None` past the last one.
+    #[must_use]
+    pub fn seek(&self, index: usize) -> Option<KyokuState> {
+        if index > self.events.len() {
+            return None;
+        }
+        let checkpoint = index / self.interval;
+        let mut state = self.checkpoints[checkpoint].clone();
+        let start = checkpoint * self.interval;
+        for (i, event) in self.events[start..index].iter().enumerate() {
+            state
+                .apply(start + i, event)
+                .expect("events are checked to apply in new");
+        }
+        Some(state)
+    }
+}
+
+/// Steps through a [`KyokuReplay`] one event at a time in either direction,
+/// e.g. for the forward and back buttons of a viewer.
+#[derive(Debug, Clone)]
+pub struct KyokuCursor<'a> {
+    replay: &'a KyokuReplay,
+    /// The number of events applied to `state`.
+    position: usize,
+    state: KyokuState,
+}
+
+impl<'a> KyokuCursor<'a> {
+    /// The number of events applied so far.
+    #[must_use]
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+
+    #[must_use]
+    pub const fn state(&self) -> &KyokuState {
+        &self.state
+    }
+
+    /// The event the next [`Self::step_forward`] applies.
+    #[must_use]
+    pub fn next_event(&self) -> Option<&'a Event> {
+        self.replay.events.get(self.position)
+    }
+
+    /// Apply the next event and return it, or `None` at the end.
+    pub fn step_forward(&mut self) -> Option<&'a Event> {
+        let event = self.next_event()?;
+        self.state
+            .apply(self.position, event)
+            .expect("events are checked to apply in new");
+        self.position += 1;
+        Some(event)
+    }
+
+    /// Undo the last applied event and return it, or `None` at the start.
+    ///
+    /// The state is rebuilt from the closest checkpoint, so this takes at
+    /// most [`KyokuReplay::interval`] steps.
+    pub fn step_back(&mut self) -> Option<&'a Event> {
+        let position = self.position.checked_sub(1)?;
+        self.seek(position);
+        self.replay.events.get(position)
+    }
+
+    /// Move to the state after the first `position` events, clamped to the
+    /// end.
+    pub fn seek(&mut self, position: usize) {
+        let position = position.min(self.replay.len());
+        if let Some(state) = self.replay.seek(position) {
+            self.state = state;
+            self.position = position;
+        }
+    }
+}
+
+/// Split the events of a game into those of each kyoku, from `start_kyoku`
+/// to `end_kyoku`.
+pub fn split_kyokus(events: &[Event]) -> impl Iterator<Item = &[Event]> {
+    let mut rest = events;
+    core::iter::from_fn(move || {
+        let start = rest
+            .iter()
+            .position(|e| matches!(e, Event::StartKyoku { .. }))?;
+        let len = rest[start..]
+            .iter()
+            .position(|e| matches!(e, Event::EndKyoku))
+            .map_or(rest.len() - start, |end| end + 1);
+        let kyoku = &rest[start..start + len];
+        rest = &rest[start + len..];
+        Some(kyoku)
+    })
+}
+
+/// Check that `events` are a legal sanma game, the predicate
+/// [`validate`](crate::validate::validate) checks converted logs with, so
+/// that simulators can check the games they generate against the same rules.
+///
+/// Every kyoku is dealt to three seats, each seat draws in turn with 13 tiles
+/// and discards, declares riichi or makes a kan or nukidora with 14, rinshan
+/// draws follow kans and nukidora, nukidora set aside norths, calls take the
+/// tile just discarded by their target, chi is not made, and the tiles played
+/// are in the hand.
+pub fn check_sanma_game(events: &[Event]) -> Result<()> {
+    let mut state = KyokuState::default();
+    let mut discarder = None;
+    // The last discard while it can still be called.
+    let mut claimable = None;
+    for (index, event) in events.iter().enumerate() {
+        if let Event::StartKyoku {
+            ref tehais,
+            kyoku,
+            honba,
+            ..
+        } = *event
+        {
+            if tehais[..3].iter().any(Vec::is_empty) || !tehais[3].is_empty() {
+                return Err(ReplayError::NotSanma { kyoku, honba });
+            }
+            (discarder, claimable) = (None, None);
+        } else if state.started {
+            state.check_turn(index, event, discarder, claimable)?;
+        }
+        state.apply(index, event)?;
+
+        match *event {
+            Event::Dahai { actor, pai, .. } => {
+                discarder = Some(actor);
+                claimable = Some((actor, pai));
+            }
+            Event::ReachAccepted { .. } | Event::Dora { .. } => (),
+            _ => claimable = None,
+        }
+    }
+    Ok(())
+}
+
+impl KyokuState {
+    /// The tiles `seat` holds, counting every meld as three.
+    fn held(&self, seat: u8) -> usize {
+        let player = &self.players[usize::from(seat)];
+        player.tehai.len() + 3 * player.melds.len()
+    }
+
+    /// Check that `event`, the one at `index`, may be played now, after the
+    /// last discard of `discarder` and with `claimable` the discard a call
+    /// could take.
+    fn check_turn(
+        &self,
+        index: usize,
+        event: &Event,
+        discarder: Option<u8>,
+        claimable: Option<(u8, Tile)>,
+    ) -> Result<()> {
+        let Some(actor) = event.actor() else {
+            return Ok(());
+        };
+        let out_of_turn = ReplayError::OutOfTurn {
+            index,
+            actor,
+            kyoku: self.kyoku,
+            honba: self.honba,
+        };
+        if actor >= 3 {
+            return Err(out_of_turn);
+        }
+        let in_turn = match *event {
+            Event::Tsumo { .. } => {
+                let next = if self.rinshan {
+                    self.last_actor
+                } else {
+                    Some(discarder.map_or(self.oya, |seat| (seat + 1) % 3))
+                };
+                next == Some(actor) && self.held(actor) == 13
+            }
+            Event::Nukidora {
+                consumed: [tile], ..
+            } if tile.deaka() != t!(N) => {
+                return Err(ReplayError::NotKita {
+                    tile,
+                    actor,
+                    kyoku: self.kyoku,
+                    honba: self.honba,
+                });
+            }
+            Event::Dahai { .. }
+            | Event::Reach { .. }
+            | Event::Ankan { .. }
+            | Event::Kakan { .. }
+            | Event::Nukidora { .. } => self.held(actor) == 14,
+            Event::Chi { pai, .. } => {
+                return Err(ReplayError::Chi {
+                    tile: pai,
+                    actor,
+                    kyoku: self.kyoku,
+                    honba: self.honba,
+                });
+            }
+            Event::Pon { target, pai, .. } | Event::Daiminkan { target, pai, .. } => {
+                if claimable != Some((target, pai)) {
+                    return Err(ReplayError::NotDiscarded {
+                        tile: pai,
+                        actor,
+                        kyoku: self.kyoku,
+                        honba: self.honba,
+                    });
+                }
+                actor != target && self.held(actor) == 13
+            }
+            _ => true,
+        };
+        if in_turn {
+            Ok(())
+        } else {
+            Err(out_of_turn)
+        }
+    }
+}
+
+/// Where an event falls in the order of play of its kyoku, see
+/// [`turn_order`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TurnPosition {
+    /// The turn within the kyoku, counting from 0 at the first draw of the
+    /// dealer. A chi, pon or daiminkan starts a turn of the caller, so the
+    /// seats it skips have none.
+    pub turn: u16,
+    /// The seat whose turn it is, which is not the actor of a ron.
+    pub seat: u8,
+    /// The turns of `seat` so far in the kyoku, counting from 1 (巡目).
+    pub junme: u8,
+}
+
+/// The position of every event of `events` in the order of play, `None` for
+/// those outside of turns: `start_game`, `start_kyoku`, `end_kyoku` and
+/// `end_game`.
+///
+/// A turn starts with a draw, chi, pon or daiminkan. The rinshan draw after
+/// a kan or nukidora goes on the turn of the kan, and results belong to the
+/// turn they end.
+#[must_use]
+pub fn turn_order(events: &[Event]) -> Vec<Option<TurnPosition>> {
+    let mut current: Option<TurnPosition> = None;
+    let mut junme = [0_u8; 4];
+    let mut rinshan = false;
+    events
+        .iter()
+        .map(|event| {
+            match *event {
+                Event::StartGame { .. } | Event::EndGame { .. } | Event::EndKyoku => return None,
+                Event::StartKyoku { .. } => {
+                    current = None;
+                    junme = [0; 4];
+                    rinshan = false;
+                    return None;
+                }
+                Event::Tsumo { .. } if rinshan => rinshan = false,
+                Event::Tsumo { actor, .. }
+                | Event::Chi { actor, .. }
+                | Event::Pon { actor, .. }
+                | Event::Daiminkan { actor, .. } => {
+                    let seat_junme = &mut junme[usize::from(actor % 4)];
+                    *seat_junme = seat_junme.saturating_add(1);
+                    current = Some(TurnPosition {
+                        turn: current.map_or(0, |p| p.turn.saturating_add(1)),
+                        seat: actor,
+                        junme: *seat_junme,
+                    });
+                    rinshan = matches!(event, Event::Daiminkan { .. });
+                }
+                Event::Ankan { .. } | Event::Kakan { .. } | Event::Nukidora { .. } => {
+                    rinshan = true;
+                }
+                _ => (),
+            }
+            current
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn wall() {
+        let events = tenhou_to_mjai(&Fixture::KanHeavy.log()).unwrap();
+        let kyoku = split_kyokus(&events).next().unwrap();
+        let mut state = KyokuState::default();
+        state.apply(0, &kyoku[0]).unwrap();
+        assert_eq!(state.wall, 55);
+        let mut draws = vec![];
+        for (index, event) in kyoku.iter().enumerate().skip(1) {
+            state.apply(index, event).unwrap();
+            if matches!(event, Event::Tsumo { .. }) {
+                draws.push(state.last_draw.unwrap());
+            }
+        }
+        // The daiminkan, nukidora, ankan and kakan each take a rinshan draw.
+        let rinshan = draws.iter().filter(|&&d| d == DrawKind::Rinshan).count();
+        assert_eq!((draws.len(), rinshan), (9, 4));
+        assert_eq!(state.wall, 46);
+        assert_eq!(state.last_draw, Some(DrawKind::Rinshan));
+
+        state.wall = 0;
+        let err = state
+            .apply(
+                kyoku.len(),
+                &Event::Tsumo {
+                    actor: 1,
+                    pai: t!(1m),
+                },
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err, ReplayError::WallExhausted { actor: 1, .. }),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn seek() {
+        let events = tenhou_to_mjai(&Fixture::KanHeavy.log()).unwrap();
+        let kyoku = split_kyokus(&events).next().unwrap().to_vec();
+        assert_eq!(kyoku.len(), 25);
+        let replay = KyokuReplay::with_interval(kyoku.clone(), 4).unwrap();
+
+        let mut state = KyokuState::default();
+        assert_eq!(replay.seek(0), Some(state.clone()));
+        for (index, event) in kyoku.iter().enumerate() {
+            state.apply(index, event).unwrap();
+            assert_eq!(replay.seek(index + 1).as_ref(), Some(&state));
+        }
+        assert_eq!(replay.seek(kyoku.len() + 1), None);
+
+        let end = replay.seek(replay.len()).unwrap();
+        let [aoi, botan, chidori, _] = &end.players;
+        assert_eq!(aoi.melds.len(), 1);
+        assert_eq!(aoi.melds[0].kind, MeldKind::Kakan);
+        assert_eq!(aoi.melds[0].tiles, [t!(P); 4]);
+        assert_eq!(aoi.tehai.len(), 11);
+        assert_eq!(botan.melds[0].kind, MeldKind::Daiminkan);
+        assert_eq!(botan.kitas, 1);
+        assert_eq!(chidori.melds[0].kind, MeldKind::Ankan);
+        assert!(chidori.river.iter().any(|d| d.called && d.pai == t!(P)));
+        assert_eq!(end.dora_markers, [t!(S), t!(6s), t!(8s)]);
+        assert_eq!(end.scores, [37600, 33700, 33700, 0]);
+    }
+
+    #[test]
+    fn cursor() {
+        let events = tenhou_to_mjai(&Fixture::KanHeavy.log()).unwrap();
+        let kyoku = split_kyokus(&events).next().unwrap().to_vec();
+        let replay = KyokuReplay::with_interval(kyoku, 5).unwrap();
+        let mut cursor = replay.cursor();
+        assert_eq!(cursor.step_back(), None);
+
+        let mut states = vec![cursor.state().clone()];
+        while let Some(event) = cursor.step_forward() {
+            assert_eq!(Some(event), replay.events().get(cursor.position() - 1));
+            states.push(cursor.state().clone());
+        }
+        assert_eq!(cursor.position(), replay.len());
+        assert_eq!(cursor.next_event(), None);
+
+        while let Some(event) = cursor.step_back() {
+            assert_eq!(Some(event), replay.events().get(cursor.position()));
+            assert_eq!(cursor.state(), &states[cursor.position()]);
+        }
+        assert_eq!(cursor.position(), 0);
+
+        cursor.seek(100);
+        assert_eq!(cursor.position(), replay.len());
+        cursor.seek(12);
+        assert_eq!(cursor.state().players[1].kitas, 0);
+        cursor.step_forward();
+        assert_eq!(cursor.state().players[1].kitas, 1);
+    }
+
+    #[test]
+    fn safe_tiles() {
+        let events = tenhou_to_mjai(&Fixture::Hanchan.log()).unwrap();
+        // Seat 2 declares riichi on its eighth discard in E2.
+        let kyoku = split_kyokus(&events).nth(1).unwrap().to_vec();
+        let replay = KyokuReplay::new(kyoku).unwrap();
+        let mut cursor = replay.cursor();
+        while let Some(event) = cursor.step_forward() {
+            if matches!(event, Event::Reach { actor: 2 }) {
+                break;
+            }
+        }
+        let before = cursor.state().players[2].safe_tiles.clone();
+        let own: Vec<_> = cursor.state().players[2]
+            .river
+            .iter()
+            .map(|d| d.pai.deaka())
+            .collect();
+        assert!(own.iter().all(|t| before.contains(t)));
+        assert!(before.windows(2).all(|w| w[0] < w[1]));
+
+        let mut passed = vec![];
+        while let Some(event) = cursor.step_forward() {
+            if let Event::Dahai { actor, pai, .. } = *event {
+                if actor != 2 {
+                    passed.push(pai.deaka());
+                }
+            }
+        }
+        let after = &cursor.state().players[2].safe_tiles;
+        assert!(passed.iter().all(|t| after.contains(t)));
+        assert!(after.len() > before.len());
+        // Seat 0 never declared riichi, so only its own discards are safe.
+        let seat_0 = &cursor.state().players[0];
+        assert!(seat_0
+            .safe_tiles
+            .iter()
+            .all(|&t| seat_0.river.iter().any(|d| d.pai.deaka() == t)));
+    }
+
+    #[test]
+    fn errors() {
+        let events = tenhou_to_mjai(&Fixture::MultiRon.log()).unwrap();
+        let mut kyoku = split_kyokus(&events).next().unwrap().to_vec();
+        kyoku[2] = Event::Dahai {
+            actor: 0,
+            pai: t!(C),
+            tsumogiri: false,
+        };
+        KyokuReplay::new(kyoku).unwrap_err();
+        KyokuReplay::new(events[1..].to_vec()).unwrap();
+        KyokuReplay::new(vec![Event::EndKyoku]).unwrap_err();
+
+        let events = tenhou_to_mjai(&Fixture::Hanchan.log()).unwrap();
+        assert_eq!(split_kyokus(&events).count(), 11);
+        for kyoku in split_kyokus(&events) {
+            KyokuReplay::new(kyoku.to_vec()).unwrap();
+        }
+    }
+
+    #[test]
+    fn sanma_game() {
+        for fixture in Fixture::ALL {
+            check_sanma_game(&tenhou_to_mjai(&fixture.log()).unwrap()).unwrap();
+        }
+
+        let events = tenhou_to_mjai(&Fixture::KanHeavy.log()).unwrap();
+        let check = |at: usize, event: Event| {
+            let mut events = events.clone();
+            events[at] = event;
+            check_sanma_game(&events).unwrap_err()
+        };
+        // The first draw of the dealer given to seat 2.
+        let Event::Tsumo { pai, .. } = events[2] else {
+            panic!("{:?}", events[2]);
+        };
+        let err = check(2, Event::Tsumo { actor: 2, pai });
+        assert!(
+            matches!(err, ReplayError::OutOfTurn { index: 2, .. }),
+            "{err}"
+        );
+        // The daiminkan of seat 1 called from a seat that did not discard.
+        let Event::Daiminkan {
+            actor,
+            target,
+            consumed,
+            ..
+        } = events[4]
+        else {
+            panic!("{:?}", events[4]);
+        };
+        let daiminkan = Event::Daiminkan {
+            actor,
+            target: (target + 1) % 3,
+            pai: consumed[0],
+            consumed,
+        };
+        let err = check(4, daiminkan);
+        assert!(matches!(err, ReplayError::NotDiscarded { .. }), "{err}");
+        let chi = Event::Chi {
+            actor,
+            target,
+            pai: consumed[0],
+            consumed: [consumed[1], consumed[2]],
+        };
+        let err = check(4, chi);
+        assert!(matches!(err, ReplayError::Chi { .. }), "{err}");
+        let at = events
+            .iter()
+            .position(|e| matches!(e, Event::Nukidora { .. }))
+            .unwrap();
+        let Event::Nukidora { actor, .. } = events[at] else {
+            unreachable!();
+        };
+        let nukidora = Event::Nukidora {
+            actor,
+            consumed: [t!(P)],
+        };
+        let err = check(at, nukidora);
+        assert!(matches!(err, ReplayError::NotKita { .. }), "{err}");
+
+        let mut events = events.clone();
+        if let Event::StartKyoku { tehais, .. } = &mut events[1] {
+            tehais[3] = tehais[0].clone();
+        }
+        let err = check_sanma_game(&events).unwrap_err();
+        assert!(matches!(err, ReplayError::NotSanma { .. }), "{err}");
+    }
+
+    #[test]
+    fn turns() {
+        let events = tenhou_to_mjai(&Fixture::KanHeavy.log()).unwrap();
+        let positions = turn_order(&events);
+        assert_eq!(positions.len(), events.len());
+        assert_eq!(positions[0], None);
+
+        let kyoku = split_kyokus(&events).next().unwrap();
+        let positions = turn_order(kyoku);
+        let at = |turn, seat, junme| Some(TurnPosition { turn, seat, junme });
+        assert_eq!(positions[0], None);
+        assert_eq!(positions[1], at(0, 0, 1));
+        // The daiminkan of seat 1 and its rinshan draw.
+        assert!(matches!(kyoku[3], Event::Daiminkan { actor: 1, .. }));
+        assert_eq!(positions[3], at(1, 1, 1));
+        assert_eq!(positions[4], at(1, 1, 1));
+        // The pon of seat 0 skips seat 1.
+        assert!(matches!(kyoku[9], Event::Pon { actor: 0, .. }));
+        assert_eq!(positions[9], at(3, 0, 2));
+        // The nukidora of seat 1 and its replacement draw.
+        assert!(matches!(kyoku[12], Event::Nukidora { actor: 1, .. }));
+        assert_eq!(positions[13], at(4, 1, 2));
+        assert_eq!(positions[15], at(5, 2, 2));
+        assert_eq!(positions.last(), Some(&None));
+    }
+}
@@ -0,0 +1,359 @@
+//! A report over a corpus: the leaderboard, metrics per player, notable
+//! hands and the biggest wins, as GitHub-flavored Markdown to paste into a
+//! league's Discord or wiki, or as an Excel workbook for organizers who keep
+//! their league in spreadsheets.
+
+use crate::dashboard::Totals;
+use crate::mjai::Event;
+use crate::ruleset::Ruleset;
+use crate::tenhou::{EndStatus, Limit, Log, RuleError};
+#[cfg(feature = "xlsx")]
+use crate::xlsx::{Cell, Sheet, Workbook};
+use crate::Points;
+use std::collections::BTreeMap;
+use std::io;
+
+/// How often a player won, dealt in and declared riichi.
+#[derive(Debug, Clone, Copy, Default)]
+struct Record {
+    kyokus: u32,
+    wins: u32,
+    deal_ins: u32,
+    riichis: u32,
+}
+
+/// A hora as the report lists it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Win {
+    /// The game ID, if any, and the kyoku, e.g. `2024030511gm-00b9-0000-e0c07689 E4-0`.
+    pub kyoku: String,
+    pub winner: String,
+    /// Who dealt in, `None` for a tsumo.
+    pub loser: Option<String>,
+    /// What the winner got, honba and riichi sticks included.
+    pub points: Points,
+    pub limit: Option<Limit>,
+    /// The yaku as the log lists them, dora included, e.g. `立直(1飜)`.
+    pub yaku: Vec<String>,
+}
+
+impl Win {
+    /// Haneman and above.
+    const fn is_notable(&self) -> bool {
+        matches!(
+            self.limit,
+            Some(Limit::Haneman | Limit::Baiman | Limit::Sanbaiman | Limit::Yakuman)
+        )
+    }
+}
+
+/// The totals of a [`Dashboard`](crate::dashboard::Dashboard), with the
+/// records of the players and every win on top.
+#[derive(Debug, Clone)]
+pub struct Report {
+    totals: Totals,
+    records: BTreeMap<String, Record>,
+    wins: Vec<Win>,
+    /// How many notable hands and biggest wins are listed.
+    top: usize,
+}
+
+impl Report {
+    #[must_use]
+    pub fn new(ruleset: Ruleset, top: usize) -> Self {
+        Self {
+            totals: Totals::new(ruleset),
+            records: BTreeMap::new(),
+            wins: vec![],
+            top,
+        }
+    }
+
+    /// Count `log` and `events` converted from it, and list its wins.
+    ///
+    /// # Errors
+    ///
+    /// Fails for logs whose final scores overflow, see [`Log::final_scores`].
+    pub fn add(&mut self, log: &Log, events: &[Event]) -> Result<(), RuleError> {
+        self.totals.add(log, events)?;
+        let seats = if log.names[3].is_empty() { 3 } else { 4 };
+        for kyoku in &log.kyokus {
+            let mut won = [false; 4];
+            let mut dealt_in = [false; 4];
+            if let EndStatus::Hora { details } = &kyoku.end_status {
+                for detail in details {
+                    let loser = (!detail.is_tsumo()).then(|| usize::from(detail.target));
+                    won[usize::from(detail.who)] = true;
+                    if let Some(loser) = loser {
+                        dealt_in[loser] = true;
+                    }
+                    self.wins.push(Win {
+                        kyoku: match &log.game_id {
+                            Some(id) => format!("{id} {}", kyoku.meta.short_name()),
+                            None => kyoku.meta.short_name(),
+                        },
+                        winner: log.names[usize::from(detail.who)].clone(),
+                        loser: loser.map(|seat| log.names[seat].clone()),
+                        points: detail.score_deltas[usize::from(detail.who)],
+                        limit: detail.score.and_then(|score| score.limit),
+                        yaku: detail
+                            .yaku
+                            .iter()
+                            .map(|(yaku, han)| format!("{yaku}({han})"))
+                            .collect(),
+                    });
+                }
+            }
+            for seat in 0..seats {
+                let record = self.records.entry(log.names[seat].clone()).or_default();
+                record.kyokus += 1;
+                record.wins += u32::from(won[seat]);
+                record.deal_ins += u32::from(dealt_in[seat]);
+                record.riichis += u32::from(kyoku.action_tables[seat].riichi_index().is_some());
+            }
+        }
+        Ok(())
+    }
+
+    /// Haneman and above, from the most points down.
+    #[must_use]
+    pub fn notable_hands(&self) -> Vec<&Win> {
+        let mut wins: Vec<_> = self.wins.iter().filter(|win| win.is_notable()).collect();
+        wins.sort_by_key(|win| -win.points);
+        wins.truncate(self.top);
+        wins
+    }
+
+    /// The horas that got the most points, from the most down.
+    #[must_use]
+    pub fn biggest_wins(&self) -> Vec<&Win> {
+        let mut wins: Vec<_> = self.wins.iter().collect();
+        wins.sort_by_key(|win| -win.points);
+        wins.truncate(self.top);
+        wins
+    }
+
+    /// Write the report as Markdown.
+    pub fn write_markdown<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "# convlog report\n")?;
+        writeln!(w, "{}\n", self.totals.summary())?;
+
+        writeln!(w, "## Leaderboard\n")?;
+        self.totals.leaderboard.write_markdown(&mut w)?;
+
+        writeln!(w, "\n## Players\n")?;
+        writeln!(
+            w,
+            "| Name | Kyokus | Win rate | Deal-in rate | Riichi rate | Calls per kyoku |"
+        )?;
+        writeln!(w, "| :--- | ---: | ---: | ---: | ---: | ---: |")?;
+        for (name, record) in &self.records {
+            let rate = |n: u32| percent(n, record.kyokus);
+            writeln!(
+                w,
+                "| {} | {} | {} | {} | {} | {:.2} |",
+                cell(name),
+                record.kyokus,
+                rate(record.wins),
+                rate(record.deal_ins),
+                rate(record.riichis),
+                self.totals
+                    .calls
+                    .get(name)
+                    .map_or(0.0, |calls| calls.calls_per_kyoku()),
+            )?;
+        }
+
+        writeln!(w, "\n## Notable hands\n")?;
+        write_wins(&mut w, &self.notable_hands())?;
+        writeln!(w, "\n## Biggest wins\n")?;
+        write_wins(&mut w, &self.biggest_wins())
+    }
+
+    /// The report as a workbook with a sheet per table: the leaderboard, the
+    /// players, their calls and every win, from the most points down.
+    #[cfg(feature = "xlsx")]
+    #[must_use]
+    pub fn workbook(&self) -> Workbook {
+        let mut leaderboard = Sheet::new("Leaderboard");
+        leaderboard.push_row([
+            "Rank",
+            "Name",
+            "Games",
+            "1st",
+            "2nd",
+            "3rd",
+            "Avg. placement",
+            "Points",
+        ]);
+        for (rank, (name, standing)) in (1_u32..).zip(self.totals.leaderboard.ranked()) {
+            let [first, second, third] = standing.placements;
+            leaderboard.push_row([
+                Cell::from(rank),
+                name.into(),
+                standing.games.into(),
+                first.into(),
+                second.into(),
+                third.into(),
+                standing.average_placement().into(),
+                standing.placement_points().into(),
+            ]);
+        }
+
+        let mut players = Sheet::new("Players");
+        players.push_row(["Name", "Kyokus", "Wins", "Deal-ins", "Riichi"]);
+        for (name, record) in &self.records {
+            players.push_row([
+                Cell::from(name.as_str()),
+                record.kyokus.into(),
+                record.wins.into(),
+                record.deal_ins.into(),
+                record.riichis.into(),
+            ]);
+        }
+
+        let mut calls = Sheet::new("Calls");
+        calls.push_row([
+            "Name",
+            "Kyokus",
+            "Chi",
+            "Pon",
+            "Daiminkan",
+            "Kakan",
+            "Ankan",
+            "Kita",
+            "Fed",
+            "Open kyokus",
+            "Open wins",
+            "Closed wins",
+            "Calls per kyoku",
+        ]);
+        for (name, player) in self.totals.calls.iter() {
+            calls.push_row([
+                Cell::from(name),
+                player.kyokus.into(),
+                player.chis.into(),
+                player.pons.into(),
+                player.daiminkans.into(),
+                player.kakans.into(),
+                player.ankans.into(),
+                player.kitas.into(),
+                player.fed.into(),
+                player.open_kyokus.into(),
+                player.open_wins.into(),
+                player.closed_wins.into(),
+                player.calls_per_kyoku().into(),
+            ]);
+        }
+
+        let mut wins = Sheet::new("Wins");
+        wins.push_row(["Kyoku", "Winner", "From", "Points", "Limit", "Yaku"]);
+        let mut sorted: Vec<_> = self.wins.iter().collect();
+        sorted.sort_by_key(|win| -win.points);
+        for win in sorted {
+            wins.push_row([
+                Cell::from(win.kyoku.as_str()),
+                win.winner.as_str().into(),
+                win.loser.as_deref().unwrap_or("tsumo").into(),
+                win.points.into(),
+                win.limit.map(|limit| format!("{limit:?}")).into(),
+                win.yaku.join(", ").into(),
+            ]);
+        }
+
+        let mut workbook = Workbook::new();
+        for sheet in [leaderboard, players, calls, wins] {
+            workbook.push(sheet);
+        }
+        workbook
+    }
+}
+
+/// Write `wins` as a Markdown table, or a line saying there are none.
+fn write_wins<W: io::Write>(mut w: W, wins: &[&Win]) -> io::Result<()> {
+    if wins.is_empty() {
+        return writeln!(w, "None.");
+    }
+    writeln!(w, "| Kyoku | Winner | From | Points | Limit | Yaku |")?;
+    writeln!(w, "| :--- | :--- | :--- | ---: | :--- | :--- |")?;
+    for win in wins {
+        writeln!(
+            w,
+            "| {} | {} | {} | {} | {} | {} |",
+            cell(&win.kyoku),
+            cell(&win.winner),
+            win.loser
+                .as_deref()
+                .map_or_else(|| "tsumo".to_owned(), cell),
+            win.points,
+            win.limit
+                .map(|limit| format!("{limit:?}"))
+                .unwrap_or_default(),
+            cell(&win.yaku.join(", ")),
+        )?;
+    }
+    Ok(())
+}
+
+/// `n` out of `of` as a percentage, `-` if `of` is 0.
+fn percent(n: u32, of: u32) -> String {
+    if of == 0 {
+        return "-".to_owned();
+    }
+    format!("{:.1}%", f64::from(n) / f64::from(of) * 100.0)
+}
+
+/// Escape `s` for a cell of a Markdown table.
+fn cell(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn write_markdown() {
+        let log = Fixture::Hanchan.log();
+        let events = tenhou_to_mjai(&log).unwrap();
+        let mut report = Report::new(Ruleset::TENHOU_SANMA, 3);
+        report.add(&log, &events).unwrap();
+        let mut out = vec![];
+        report.write_markdown(&mut out).unwrap();
+        let markdown = String::from_utf8(out).unwrap();
+
+        assert!(markdown.starts_with("# convlog report\n\n1 games, 11 kyokus"));
+        assert!(markdown.contains("| 1 | mtk | 1 | 1 | 0 | 0 | 1.00 | 43.1 |"));
+        assert_eq!(report.biggest_wins().len(), 3);
+        let points: Vec<_> = report
+            .notable_hands()
+            .iter()
+            .map(|win| win.points)
+            .collect();
+        assert_eq!(points, [12400, 12200, 9000]);
+        assert_eq!(cell("a|b"), "a\\|b");
+    }
+
+    #[cfg(feature = "xlsx")]
+    #[test]
+    fn workbook() {
+        let log = Fixture::Hanchan.log();
+        let events = tenhou_to_mjai(&log).unwrap();
+        let mut report = Report::new(Ruleset::TENHOU_SANMA, 3);
+        report.add(&log, &events).unwrap();
+        let workbook = report.workbook();
+
+        let names: Vec<_> = workbook.sheets().iter().map(Sheet::name).collect();
+        assert_eq!(names, ["Leaderboard", "Players", "Calls", "Wins"]);
+        let leaderboard = workbook.sheets()[0].rows();
+        assert_eq!(leaderboard.len(), 4);
+        assert_eq!(leaderboard[1][1], Cell::from("mtk"));
+        assert_eq!(leaderboard[1][7], Cell::from(43.1));
+        // Every hora is listed, not just the top ones.
+        let wins = workbook.sheets()[3].rows();
+        assert_eq!(wins.len(), 1 + 11);
+        assert_eq!(wins[1][3], Cell::from(12400_i64));
+    }
+}
@@ -0,0 +1,278 @@
+//! Named bundles of the rules a sanma environment plays by, so that one
+//! `--rules` picks them all.
+
+use crate::tenhou::{Rounding, ScoringRules, TsumoPayment};
+use crate::{Points, SEATS};
+use std::fmt;
+use std::str::FromStr;
+
+/// How seats finishing on the same score are placed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// The seat closest to the first dealer, seat 0, places higher, as on
+    /// tenhou and Mahjong Soul.
+    #[default]
+    SeatOrder,
+    /// Tied seats share the higher placement, and each gets its uma and oka
+    /// in full.
+    Shared,
+    /// Tied seats share the higher placement and split the uma and oka of
+    /// the placements they take up evenly, as in M.League.
+    Split,
+}
+
+impl TieBreak {
+    /// The name used on the command line.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::SeatOrder => "seat-order",
+            Self::Shared => "shared",
+            Self::Split => "split",
+        }
+    }
+}
+
+impl FromStr for TieBreak {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "seat-order" => Ok(Self::SeatOrder),
+            "shared" => Ok(Self::Shared),
+            "split" => Ok(Self::Split),
+            _ => Err(format!(
+                r#"invalid tie-break: {s:?} (expected "seat-order", "shared" or "split")"#
+            )),
+        }
+    }
+}
+
+/// The placement of every seat for its final `scores`, counting from 0, with
+/// ties placed by `tie_break`. With [`TieBreak::Shared`] and
+/// [`TieBreak::Split`], tied seats have the same placement and the next one
+/// is skipped, e.g. `[0, 0, 2]`.
+#[must_use]
+pub fn compute_placements(scores: [Points; SEATS], tie_break: TieBreak) -> [usize; SEATS] {
+    let mut seats: [usize; SEATS] = std::array::from_fn(|i| i);
+    seats.sort_by_key(|&s| (-scores[s], s));
+    let mut placements = [0; SEATS];
+    for (placement, seat) in seats.into_iter().enumerate() {
+        placements[seat] = placement;
+    }
+    if tie_break != TieBreak::SeatOrder {
+        for seat in 0..SEATS {
+            placements[seat] = seats
+                .iter()
+                .position(|&s| scores[s] == scores[seat])
+                .unwrap_or(placements[seat]);
+        }
+    }
+    placements
+}
+
+/// The rules of a sanma environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ruleset {
+    pub name: &'static str,
+    /// The points every seat starts with (配給原点).
+    pub start_points: Points,
+    /// The points placement points are counted from (返し); what every seat
+    /// is short of them goes to the top as oka.
+    pub return_points: Points,
+    /// The uma of every placement, in thousands of points.
+    pub uma: [Points; SEATS],
+    /// Aka fives in play, all of them pinzu and souzu.
+    pub aka: u8,
+    pub scoring: ScoringRules,
+    pub tie_break: TieBreak,
+}
+
+impl Ruleset {
+    /// Ranked games on tenhou.
+    pub const TENHOU_SANMA: Self = Self {
+        name: "tenhou-sanma",
+        start_points: 35000,
+        return_points: 40000,
+        uma: [20, 0, -20],
+        aka: 2,
+        scoring: ScoringRules {
+            kiriage_mangan: false,
+            tsumo: TsumoPayment::Loss,
+            rounding: Rounding::Payment,
+        },
+        tie_break: TieBreak::SeatOrder,
+    };
+
+    /// The gold room of Mahjong Soul.
+    pub const MAJSOUL_SANMA_GOLD: Self = Self {
+        name: "majsoul-sanma-gold",
+        start_points: 35000,
+        return_points: 40000,
+        uma: [15, 0, -15],
+        aka: 2,
+        scoring: ScoringRules {
+            kiriage_mangan: false,
+            tsumo: TsumoPayment::Split,
+            rounding: Rounding::Payment,
+        },
+        tie_break: TieBreak::SeatOrder,
+    };
+
+    /// Competition rules after M.League, brought to three players.
+    pub const MLEAGUE_STYLE_3P: Self = Self {
+        name: "mleague-style-3p",
+        start_points: 35000,
+        return_points: 40000,
+        uma: [30, 0, -30],
+        aka: 2,
+        scoring: ScoringRules {
+            kiriage_mangan: true,
+            tsumo: TsumoPayment::Loss,
+            rounding: Rounding::Payment,
+        },
+        tie_break: TieBreak::Split,
+    };
+
+    pub const PRESETS: &'static [Self] = &[
+        Self::TENHOU_SANMA,
+        Self::MAJSOUL_SANMA_GOLD,
+        Self::MLEAGUE_STYLE_3P,
+    ];
+
+    /// The oka, in points, that goes to the top.
+    #[must_use]
+    pub const fn oka(&self) -> Points {
+        (self.return_points - self.start_points) * SEATS as Points
+    }
+
+    /// The placement of every seat for its final `scores`, counting from 0,
+    /// see [`compute_placements`].
+    #[must_use]
+    pub fn placements(&self, scores: [Points; SEATS]) -> [usize; SEATS] {
+        compute_placements(scores, self.tie_break)
+    }
+
+    /// The final points of every seat for its final `scores`, with uma and
+    /// oka, e.g. `43100` for a top of 48100 on tenhou.
+    #[must_use]
+    pub fn final_points(&self, scores: [Points; SEATS]) -> [Points; SEATS] {
+        let placements = self.placements(scores);
+        let bonus = |placement| {
+            let oka = if placement == 0 { self.oka() } else { 0 };
+            self.uma[placement] * 1000 + oka
+        };
+        std::array::from_fn(|seat| {
+            let placement = placements[seat];
+            let bonus = match self.tie_break {
+                TieBreak::SeatOrder | TieBreak::Shared => bonus(placement),
+                TieBreak::Split => {
+                    let tied = scores.iter().filter(|&&s| s == scores[seat]).count();
+                    (placement..placement + tied).map(bonus).sum::<Points>() / tied as Points
+                }
+            };
+            scores[seat] - self.return_points + bonus
+        })
+    }
+
+    /// [`Self::final_points`] in thousands of points, the way placement
+    /// points are usually written, e.g. `43.1`.
+    #[must_use]
+    pub fn placement_points(&self, scores: [Points; SEATS]) -> [f64; SEATS] {
+        self.final_points(scores)
+            .map(|points| points as f64 / 1000.0)
+    }
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self::TENHOU_SANMA
+    }
+}
+
+impl fmt::Display for Ruleset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+impl FromStr for Ruleset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::PRESETS
+            .iter()
+            .find(|preset| preset.name == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::PRESETS
+                    .iter()
+                    .map(|p| format!("{:?}", p.name))
+                    .collect();
+                format!(
+                    "invalid rules: {s:?} (expected one of {})",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn placement_points() {
+        // The final scores of the hanchan fixture, which tenhou records as
+        // 43.1, -10 and -33.1.
+        let scores = [48100, 30000, 26900];
+        let points = Ruleset::TENHOU_SANMA.placement_points(scores);
+        let tenths = points.map(|p| (p * 10.0).round() as i32);
+        assert_eq!(tenths, [431, -100, -331]);
+        assert_eq!(
+            Ruleset::MLEAGUE_STYLE_3P.final_points(scores),
+            [53100, -10000, -43100],
+        );
+
+        let tenhou = Ruleset::TENHOU_SANMA;
+        assert_eq!(tenhou.placements([30000, 40000, 35000]), [2, 0, 1]);
+        assert_eq!(tenhou.placements([35000, 35000, 35000]), [0, 1, 2]);
+    }
+
+    #[test]
+    fn tie_break() {
+        let scores = [30000, 45000, 30000];
+        assert_eq!(compute_placements(scores, TieBreak::SeatOrder), [1, 0, 2]);
+        assert_eq!(compute_placements(scores, TieBreak::Shared), [1, 0, 1]);
+        assert_eq!(compute_placements(scores, TieBreak::Split), [1, 0, 1]);
+        let even = [35000; 3];
+        assert_eq!(compute_placements(even, TieBreak::Split), [0; 3]);
+
+        let mleague = Ruleset::MLEAGUE_STYLE_3P;
+        // Second and third split 0 and -30.
+        assert_eq!(mleague.final_points(scores), [-25000, 50000, -25000]);
+        let tied_top = [40000, 40000, 25000];
+        assert_eq!(mleague.final_points(tied_top), [22500, 22500, -45000]);
+        let shared = Ruleset {
+            tie_break: TieBreak::Shared,
+            ..mleague
+        };
+        assert_eq!(shared.final_points(tied_top), [45000, 45000, -45000]);
+        // All tied: the uma cancels out and the oka is shared.
+        assert_eq!(mleague.final_points(even), [0; 3]);
+
+        for tie_break in [TieBreak::SeatOrder, TieBreak::Shared, TieBreak::Split] {
+            assert_eq!(tie_break.name().parse(), Ok(tie_break));
+        }
+        "dealer".parse::<TieBreak>().unwrap_err();
+    }
+
+    #[test]
+    fn parse() {
+        for preset in Ruleset::PRESETS {
+            assert_eq!(preset.name.parse::<Ruleset>().unwrap(), *preset);
+            assert_eq!(preset.to_string(), preset.name);
+        }
+        "tenhou".parse::<Ruleset>().unwrap_err();
+    }
+}
@@ -0,0 +1,729 @@
+//! JSON Schemas of what the crate writes, so that downstream pipelines can
+//! validate and generate code against them.
+//!
+//! The schemas are written by hand after the serde attributes of the types
+//! and are checked against actual output in the tests below.
+
+use crate::Tile;
+use std::str::FromStr;
+
+use serde_json::{json, Map, Value};
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Which output a schema describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFormat {
+    /// The JSON arrays of mjai events of `convert --format mjai`.
+    Mjai,
+    /// Logs in tenhou.net/6 format as `split` and `normalize` write them.
+    Tenhou6,
+    /// The JSON reports of `stats`: `discards`, `calls`, `dealer`,
+    /// `riichi-ev`, `think-times`, `leaderboard`, `head-to-head`,
+    /// `progression`, `sessions`, `highlights` and `point-flow`.
+    Stats,
+}
+
+impl FromStr for SchemaFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mjai" => Ok(Self::Mjai),
+            "tenhou6" => Ok(Self::Tenhou6),
+            "stats" => Ok(Self::Stats),
+            _ => Err(format!(
+                "unknown format {s:?}, expected \"mjai\", \"tenhou6\" or \"stats\""
+            )),
+        }
+    }
+}
+
+/// The JSON Schema of `format`.
+#[must_use]
+pub fn schema(format: SchemaFormat) -> Value {
+    match format {
+        SchemaFormat::Mjai => mjai(),
+        SchemaFormat::Tenhou6 => tenhou6(),
+        SchemaFormat::Stats => stats(),
+    }
+}
+
+/// An object with exactly `properties`, of which those not in `optional`
+/// are required.
+fn object(properties: &[(&str, Value)], optional: &[&str]) -> Value {
+    let required: Vec<_> = properties
+        .iter()
+        .map(|&(name, _)| name)
+        .filter(|name| !optional.contains(name))
+        .collect();
+    let properties: Map<_, _> = properties
+        .iter()
+        .map(|(name, schema)| ((*name).to_owned(), schema.clone()))
+        .collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn array_of(items: &Value, len: Option<usize>) -> Value {
+    let mut schema = json!({ "type": "array", "items": items });
+    if let Some(len) = len {
+        schema["minItems"] = len.into();
+        schema["maxItems"] = len.into();
+    }
+    schema
+}
+
+/// An array of one item per seat, four or, for `--three-seats` output,
+/// three.
+fn per_seat(items: &Value) -> Value {
+    let mut schema = array_of(items, None);
+    schema["minItems"] = 3.into();
+    schema["maxItems"] = 4.into();
+    schema
+}
+
+fn integer(minimum: Option<i64>) -> Value {
+    minimum.map_or_else(
+        || json!({ "type": "integer" }),
+        |minimum| json!({ "type": "integer", "minimum": minimum }),
+    )
+}
+
+fn mjai() -> Value {
+    let tiles: Vec<_> = (0..=usize::from(crate::tu8!(?)))
+        .map(|id| {
+            Tile::try_from(id)
+                .expect("every ID up to the unknown tile is a tile")
+                .to_string()
+        })
+        .collect();
+    let tile = json!({ "$ref": "#/$defs/tile" });
+    let seat = json!({ "$ref": "#/$defs/seat" });
+    let scores = per_seat(&integer(None));
+
+    let event = |name: &str, mut properties: Vec<(&str, Value)>, optional: &[&str]| {
+        properties.insert(0, ("type", json!({ "const": name })));
+        // Present on every event of games with timestamps.
+        properties.push(("timestamp", integer(Some(0))));
+        // Present on events within a turn in `--turn-order` output.
+        properties.push(("turn", integer(Some(0))));
+        properties.push(("seat", seat.clone()));
+        properties.push(("junme", integer(Some(1))));
+        let optional = [optional, &["timestamp", "turn", "seat", "junme"]].concat();
+        object(&properties, &optional)
+    };
+    let actor = || ("actor", seat.clone());
+    let target = || ("target", seat.clone());
+    let pai = || ("pai", tile.clone());
+    let consumed = |len| ("consumed", array_of(&tile, Some(len)));
+
+    let events = vec![
+        event("none", vec![], &[]),
+        event(
+            "start_game",
+            vec![
+                ("names", per_seat(&json!({ "type": "string" }))),
+                ("kyoku_first", integer(Some(0))),
+                ("aka_flag", json!({ "type": "boolean" })),
+            ],
+            &[],
+        ),
+        event(
+            "start_kyoku",
+            vec![
+                ("bakaze", tile.clone()),
+                ("dora_marker", tile.clone()),
+                ("kyoku", integer(Some(1))),
+                ("honba", integer(Some(0))),
+                ("kyotaku", integer(Some(0))),
+                ("oya", seat.clone()),
+                ("scores", scores.clone()),
+                ("tehais", per_seat(&array_of(&tile, None))),
+            ],
+            &[],
+        ),
+        event("tsumo", vec![actor(), pai()], &[]),
+        event(
+            "dahai",
+            vec![actor(), pai(), ("tsumogiri", json!({ "type": "boolean" }))],
+            &[],
+        ),
+        event("chi", vec![actor(), target(), pai(), consumed(2)], &[]),
+        event("pon", vec![actor(), target(), pai(), consumed(2)], &[]),
+        event(
+            "daiminkan",
+            vec![actor(), target(), pai(), consumed(3)],
+            &[],
+        ),
+        event("kakan", vec![actor(), pai(), consumed(3)], &[]),
+        event("ankan", vec![actor(), consumed(4)], &[]),
+        event("nukidora", vec![actor(), consumed(1)], &[]),
+        event("dora", vec![("dora_marker", tile.clone())], &[]),
+        event("reach", vec![actor()], &[]),
+        event(
+            "reach_accepted",
+            vec![actor(), ("deltas", scores.clone())],
+            &["deltas"],
+        ),
+        event(
+            "hora",
+            vec![
+                actor(),
+                target(),
+                ("deltas", scores.clone()),
+                ("sticks", scores.clone()),
+                ("ura_markers", array_of(&tile, None)),
+            ],
+            &["deltas", "sticks", "ura_markers"],
+        ),
+        event(
+            "ryukyoku",
+            vec![
+                ("reason", json!({ "type": "string" })),
+                ("deltas", scores.clone()),
+                ("tenpais", per_seat(&json!({ "type": "boolean" }))),
+                ("kyotaku", integer(Some(0))),
+            ],
+            &["reason", "deltas", "tenpais", "kyotaku"],
+        ),
+        event("end_kyoku", vec![], &[]),
+        event(
+            "end_game",
+            vec![
+                ("scores", scores.clone()),
+                ("ranks", per_seat(&integer(Some(0)))),
+                ("final_points", scores),
+            ],
+            &["scores", "ranks", "final_points"],
+        ),
+    ];
+
+    json!({
+        "$schema": DRAFT,
+        "title": "mjai events",
+        "type": "array",
+        "items": { "$ref": "#/$defs/event" },
+        "$defs": {
+            "tile": { "enum": tiles },
+            "seat": { "type": "integer", "minimum": 0, "maximum": 3 },
+            "event": { "oneOf": events },
+        },
+    })
+}
+
+fn tenhou6() -> Value {
+    let tile = json!({ "$ref": "#/$defs/tile" });
+    let tiles = array_of(&tile, None);
+    // Tiles, 60 for tsumogiri and naki strings such as `p474747`.
+    let actions = array_of(
+        &json!({ "anyOf": [tile, { "const": 60 }, { "type": "string" }] }),
+        None,
+    );
+    let scores = array_of(&integer(None), Some(4));
+
+    let mut kyoku = vec![
+        array_of(&integer(Some(0)), Some(3)),
+        scores.clone(),
+        tiles.clone(),
+        tiles.clone(),
+    ];
+    for _ in 0..4 {
+        kyoku.extend([tiles.clone(), actions.clone(), actions.clone()]);
+    }
+    // The status, e.g. `和了`, then the score deltas and details of every
+    // hora, or the deltas of a ryukyoku.
+    kyoku.push(json!({
+        "type": "array",
+        "prefixItems": [{ "type": "string" }],
+        "items": { "anyOf": [scores, { "type": "array" }] },
+        "minItems": 1,
+    }));
+    let kyoku_len = kyoku.len();
+
+    let strings = array_of(&json!({ "type": "string" }), None);
+    let properties = [
+        (
+            "log",
+            array_of(
+                &json!({
+                    "type": "array",
+                    "prefixItems": kyoku,
+                    "items": false,
+                    "minItems": kyoku_len,
+                }),
+                None,
+            ),
+        ),
+        ("name", array_of(&json!({ "type": "string" }), Some(4))),
+        (
+            "rule",
+            json!({
+                "type": "object",
+                "properties": {
+                    "disp": { "type": "string" },
+                    "aka": integer(Some(0)),
+                    "aka51": integer(Some(0)),
+                    "aka52": integer(Some(0)),
+                    "aka53": integer(Some(0)),
+                },
+            }),
+        ),
+        ("ver", json!({ "type": ["number", "string"] })),
+        ("ref", json!({ "type": "string" })),
+        ("ratingc", json!({ "type": "string" })),
+        ("lobby", integer(None)),
+        ("dan", strings.clone()),
+        ("rate", array_of(&json!({ "type": "number" }), None)),
+        ("sx", strings),
+        ("sc", array_of(&json!({ "type": "number" }), None)),
+    ];
+    let mut schema = object(
+        &properties,
+        &["ver", "ref", "ratingc", "lobby", "dan", "rate", "sx", "sc"],
+    );
+    // Sources add fields of their own, which readers skip.
+    schema["additionalProperties"] = true.into();
+    schema["$schema"] = DRAFT.into();
+    schema["title"] = "tenhou.net/6 log".into();
+    schema["$defs"] = json!({
+        "tile": {
+            "enum": [
+                11, 12, 13, 14, 15, 16, 17, 18, 19,
+                21, 22, 23, 24, 25, 26, 27, 28, 29,
+                31, 32, 33, 34, 35, 36, 37, 38, 39,
+                41, 42, 43, 44, 45, 46, 47,
+                51, 52, 53,
+            ],
+        },
+    });
+    schema
+}
+
+fn stats() -> Value {
+    let count = integer(Some(0));
+    let rate = json!({ "type": ["number", "null"] });
+    let placements = array_of(&count, Some(3));
+
+    let heatmap = object(
+        &[
+            ("tiles", array_of(&json!({ "type": "string" }), None)),
+            (
+                "players",
+                json!({
+                    "type": "object",
+                    "additionalProperties": array_of(&array_of(&count, None), None),
+                }),
+            ),
+        ],
+        &[],
+    );
+    let calls = object(
+        &[
+            ("kyokus", count.clone()),
+            ("chis", count.clone()),
+            ("pons", count.clone()),
+            ("daiminkans", count.clone()),
+            ("kakans", count.clone()),
+            ("ankans", count.clone()),
+            ("kitas", count.clone()),
+            ("fed", count.clone()),
+            ("open_kyokus", count.clone()),
+            ("open_wins", count.clone()),
+            ("closed_wins", count.clone()),
+            ("calls_per_kyoku", json!({ "type": "number" })),
+            ("open_win_rate", rate.clone()),
+            ("closed_win_rate", rate.clone()),
+        ],
+        &[],
+    );
+    let leaderboard = array_of(
+        &object(
+            &[
+                ("rank", integer(Some(1))),
+                ("name", json!({ "type": "string" })),
+                ("games", count.clone()),
+                ("placements", placements.clone()),
+                ("average_placement", rate),
+                ("points", json!({ "type": "number" })),
+            ],
+            &[],
+        ),
+        None,
+    );
+    let matchup = object(
+        &[
+            ("placements", placements.clone()),
+            ("ahead", count.clone()),
+            ("rons", count.clone()),
+            ("points", integer(None)),
+        ],
+        &[],
+    );
+    let head_to_head = object(
+        &[
+            ("names", array_of(&json!({ "type": "string" }), Some(2))),
+            ("games", count.clone()),
+            ("records", array_of(&matchup, Some(2))),
+        ],
+        &[],
+    );
+    let record = object(
+        &[
+            ("name", json!({ "type": "string" })),
+            ("placements", placements.clone()),
+            ("points", integer(None)),
+            ("trailed", count.clone()),
+            ("comebacks", count.clone()),
+            ("after_last", placements),
+        ],
+        &[],
+    );
+    let sessions = array_of(
+        &object(
+            &[
+                ("started_at", json!({ "type": "string" })),
+                ("last_started_at", json!({ "type": "string" })),
+                ("game_ids", array_of(&json!({ "type": "string" }), None)),
+                ("records", array_of(&record, Some(3))),
+            ],
+            &[],
+        ),
+        None,
+    );
+    let text = json!({ "type": ["string", "null"] });
+    let points = json!({ "type": ["integer", "null"] });
+    let progression = array_of(
+        &object(
+            &[
+                ("game_id", text.clone()),
+                ("started_at", text.clone()),
+                (
+                    "room",
+                    json!({ "enum": ["Ippan", "Joukyuu", "Tokujou", "Houou", null] }),
+                ),
+                ("placement", integer(Some(1))),
+                ("dan", text),
+                ("dan_points", points.clone()),
+                ("dan_points_total", points),
+                ("rate", json!({ "type": ["number", "null"] })),
+                ("rate_change", json!({ "type": ["number", "null"] })),
+            ],
+            &[],
+        ),
+        None,
+    );
+
+    let dealing = object(
+        &[
+            ("turns", count.clone()),
+            ("kyokus", count.clone()),
+            ("renchans", count.clone()),
+            ("longest_renchan", count.clone()),
+            ("points", integer(None)),
+            ("deal_ins", count.clone()),
+            (
+                "deal_ins_to",
+                json!({ "type": "object", "additionalProperties": count }),
+            ),
+        ],
+        &[],
+    );
+    let number = json!({ "type": ["number", "null"] });
+    let riichi_summary = object(
+        &[
+            ("riichis", integer(Some(0))),
+            ("wins", integer(Some(0))),
+            ("deal_ins", integer(Some(0))),
+            ("points", integer(None)),
+            ("ev", number.clone()),
+            ("win_rate", number.clone()),
+            ("deal_in_rate", number.clone()),
+            ("average_junme", number.clone()),
+            ("average_wait_tiles", number),
+        ],
+        &[],
+    );
+    let riichi = object(
+        &[
+            ("player", json!({ "type": "string" })),
+            ("seat", integer(Some(0))),
+            ("game_id", json!({ "type": ["string", "null"] })),
+            ("kyoku", json!({ "type": "string" })),
+            ("junme", integer(Some(1))),
+            ("wait_kinds", integer(Some(0))),
+            ("wait_tiles", integer(Some(0))),
+            (
+                "outcome",
+                json!({ "enum": ["won", "dealt_in", "tsumo_paid", "tenpai", "noten", "drawn", "uninvolved"] }),
+            ),
+            ("points", integer(None)),
+        ],
+        &[],
+    );
+    let riichi_stats = object(
+        &[
+            (
+                "players",
+                json!({ "type": "object", "additionalProperties": riichi_summary }),
+            ),
+            ("riichis", array_of(&riichi, None)),
+        ],
+        &[],
+    );
+    let highlights = array_of(
+        &object(
+            &[
+                ("game_id", json!({ "type": ["string", "null"] })),
+                ("kyoku_index", integer(Some(0))),
+                ("kyoku", json!({ "type": "string" })),
+                (
+                    "kind",
+                    json!({ "enum": ["yakuman", "haneman", "kitas", "double_riichi", "multi_ron"] }),
+                ),
+                ("players", array_of(&json!({ "type": "string" }), None)),
+                ("points", json!({ "type": ["integer", "null"] })),
+                ("detail", json!({ "type": "string" })),
+                ("link", json!({ "type": ["string", "null"] })),
+            ],
+            &[],
+        ),
+        None,
+    );
+    let flow = object(
+        &[("ron", integer(Some(0))), ("tsumo", integer(Some(0)))],
+        &[],
+    );
+    let point_flow = object(
+        &[
+            (
+                "games",
+                array_of(
+                    &object(
+                        &[
+                            ("game_id", json!({ "type": ["string", "null"] })),
+                            ("names", array_of(&json!({ "type": "string" }), Some(3))),
+                            ("matrix", array_of(&array_of(&flow, Some(3)), Some(3))),
+                        ],
+                        &[],
+                    ),
+                    None,
+                ),
+            ),
+            (
+                "totals",
+                json!({
+                    "type": "object",
+                    "additionalProperties": { "type": "object", "additionalProperties": flow },
+                }),
+            ),
+        ],
+        &[],
+    );
+    let think_time = object(
+        &[
+            ("count", integer(Some(0))),
+            ("mean_ms", json!({ "type": ["number", "null"] })),
+            ("median_ms", json!({ "type": ["integer", "null"] })),
+            ("max_ms", json!({ "type": ["integer", "null"] })),
+        ],
+        &[],
+    );
+    let think_times = object(
+        &[
+            ("games", integer(Some(0))),
+            ("timed_games", integer(Some(0))),
+            (
+                "players",
+                json!({
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "propertyNames": { "enum": ["discard", "riichi", "call", "kan", "kita", "win"] },
+                        "additionalProperties": think_time,
+                    },
+                }),
+            ),
+        ],
+        &["players"],
+    );
+
+    json!({
+        "$schema": DRAFT,
+        "title": "convlog stats",
+        "anyOf": [
+            { "$ref": "#/$defs/discard_heatmap" },
+            { "$ref": "#/$defs/call_stats" },
+            { "$ref": "#/$defs/dealer_stats" },
+            { "$ref": "#/$defs/riichi_stats" },
+            { "$ref": "#/$defs/leaderboard" },
+            { "$ref": "#/$defs/head_to_head" },
+            { "$ref": "#/$defs/progression" },
+            { "$ref": "#/$defs/sessions" },
+            { "$ref": "#/$defs/highlights" },
+            { "$ref": "#/$defs/point_flow" },
+            { "$ref": "#/$defs/think_times" },
+        ],
+        "$defs": {
+            "discard_heatmap": heatmap,
+            "call_stats": { "type": "object", "additionalProperties": calls },
+            "dealer_stats": { "type": "object", "additionalProperties": dealing },
+            "riichi_stats": riichi_stats,
+            "leaderboard": leaderboard,
+            "head_to_head": head_to_head,
+            "progression": progression,
+            "sessions": sessions,
+            "highlights": highlights,
+            "point_flow": point_flow,
+            "think_times": think_times,
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::highlights::Highlights;
+    use crate::ruleset::Ruleset;
+    use crate::stats::{
+        CallStats, DealerStats, Leaderboard, PointFlows, Progression, RiichiStats, Sessions,
+        ThinkTimes,
+    };
+    use crate::testdata::Fixture;
+
+    /// Check the keys of `value` against the object schema `schema`.
+    fn check_keys(schema: &Value, value: &Value) {
+        let properties = schema["properties"].as_object().unwrap();
+        let value = value.as_object().unwrap();
+        for key in value.keys() {
+            assert!(properties.contains_key(key), "{key} is not in the schema");
+        }
+        for key in schema["required"].as_array().unwrap() {
+            assert!(
+                value.contains_key(key.as_str().unwrap()),
+                "{key} is missing"
+            );
+        }
+    }
+
+    #[test]
+    fn mjai_events() {
+        let schema = schema(SchemaFormat::Mjai);
+        let events = schema["$defs"]["event"]["oneOf"].as_array().unwrap();
+        let tiles = schema["$defs"]["tile"]["enum"].as_array().unwrap();
+        assert!(tiles.contains(&json!("5pr")));
+
+        let converted = tenhou_to_mjai(&Fixture::KanHeavy.log()).unwrap();
+        for event in serde_json::to_value(converted).unwrap().as_array().unwrap() {
+            let event_schema = events
+                .iter()
+                .find(|s| s["properties"]["type"]["const"] == event["type"])
+                .unwrap_or_else(|| panic!("no schema for {event}"));
+            check_keys(event_schema, event);
+        }
+    }
+
+    #[test]
+    fn tenhou6_logs() {
+        let schema = schema(SchemaFormat::Tenhou6);
+        let log: Value = serde_json::from_str(Fixture::Hanchan.json()).unwrap();
+        for key in schema["required"].as_array().unwrap() {
+            assert!(log.get(key.as_str().unwrap()).is_some(), "{key} is missing");
+        }
+        let kyoku = &schema["properties"]["log"]["items"];
+        let len = kyoku["prefixItems"].as_array().unwrap().len();
+        assert_eq!(log["log"][0].as_array().unwrap().len(), len);
+    }
+
+    #[test]
+    fn stats_reports() {
+        let schema = schema(SchemaFormat::Stats);
+        let defs = &schema["$defs"];
+        let log = Fixture::Hanchan.log();
+        let events = tenhou_to_mjai(&log).unwrap();
+
+        let mut calls = CallStats::new();
+        calls.add(&log, &events);
+        let calls = serde_json::to_value(&calls).unwrap();
+        check_keys(&defs["call_stats"]["additionalProperties"], &calls["mtk"]);
+
+        let mut dealer = DealerStats::new();
+        dealer.add(&log);
+        let dealer = serde_json::to_value(&dealer).unwrap();
+        check_keys(
+            &defs["dealer_stats"]["additionalProperties"],
+            &dealer["mtk"],
+        );
+
+        let mut riichis = RiichiStats::new();
+        riichis.add(&log, &events).unwrap();
+        let riichis = serde_json::to_value(&riichis).unwrap();
+        let riichi_stats = &defs["riichi_stats"];
+        check_keys(riichi_stats, &riichis);
+        check_keys(
+            &riichi_stats["properties"]["players"]["additionalProperties"],
+            &riichis["players"]["mtk"],
+        );
+        check_keys(
+            &riichi_stats["properties"]["riichis"]["items"],
+            &riichis["riichis"][0],
+        );
+
+        let mut leaderboard = Leaderboard::new(Ruleset::default());
+        leaderboard.add(&log).unwrap();
+        let leaderboard = serde_json::to_value(&leaderboard).unwrap();
+        check_keys(&defs["leaderboard"]["items"], &leaderboard[0]);
+
+        let mut progression = Progression::new("mtk".to_owned());
+        progression.add(&log);
+        let progression = serde_json::to_value(&progression).unwrap();
+        check_keys(&defs["progression"]["items"], &progression[0]);
+
+        let mut sessions = Sessions::new(Ruleset::default(), chrono::TimeDelta::hours(2));
+        sessions.add(&log).unwrap();
+        let sessions = serde_json::to_value(&sessions).unwrap();
+        check_keys(&defs["sessions"]["items"], &sessions[0]);
+        check_keys(
+            &defs["sessions"]["items"]["properties"]["records"]["items"],
+            &sessions[0]["records"][0],
+        );
+
+        let mut highlights = Highlights::new();
+        highlights.add(&log, &events);
+        let highlights = serde_json::to_value(&highlights).unwrap();
+        check_keys(&defs["highlights"]["items"], &highlights[0]);
+
+        let mut flows = PointFlows::new();
+        flows.add(&log);
+        let flows = serde_json::to_value(&flows).unwrap();
+        let point_flow = &defs["point_flow"];
+        check_keys(point_flow, &flows);
+        check_keys(
+            &point_flow["properties"]["games"]["items"],
+            &flows["games"][0],
+        );
+        check_keys(
+            &point_flow["properties"]["games"]["items"]["properties"]["matrix"]["items"]["items"],
+            &flows["games"][0]["matrix"][0][1],
+        );
+
+        let mut game = crate::ir::Tenhou6::default().convert(&log).unwrap();
+        game.synthesize_timestamps(500);
+        let mut times = ThinkTimes::new();
+        times.add(&game);
+        let times = serde_json::to_value(&times).unwrap();
+        let think_times = &defs["think_times"];
+        check_keys(think_times, &times);
+        check_keys(
+            &think_times["properties"]["players"]["additionalProperties"]["additionalProperties"],
+            &times["players"]["mtk"]["discard"],
+        );
+
+        "yaml".parse::<SchemaFormat>().unwrap_err();
+    }
+}
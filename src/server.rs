@@ -0,0 +1,113 @@
+//! A minimal HTTP front-end so web clients can call the converter directly.
+
+use crate::conv::tenhou_to_mjai;
+use crate::tenhou::Log;
+use std::io;
+use std::net::ToSocketAddrs;
+
+use serde_json::json;
+use tiny_http::{Header, Method, Response, Server};
+
+/// The status and JSON body of a response, kept separate from `tiny_http` so
+/// that routing can be tested without opening a socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Reply {
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message }).to_string(),
+        }
+    }
+}
+
+/// Serve `POST /convert?format=mjai` on `addr` until the process is killed.
+pub fn serve(addr: impl ToSocketAddrs) -> io::Result<()> {
+    let server = Server::http(addr).map_err(io::Error::other)?;
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let reply = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => handle(request.method(), request.url(), &body),
+            Err(err) => Reply::error(400, &err.to_string()),
+        };
+
+        let response = Response::from_string(reply.body)
+            .with_status_code(reply.status)
+            .with_header(content_type.clone());
+        if let Err(err) = request.respond(response) {
+            eprintln!("failed to send response: {err}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Route a single request.
+#[must_use]
+pub fn handle(method: &Method, url: &str, body: &str) -> Reply {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    if path != "/convert" {
+        return Reply::error(404, "not found");
+    }
+    if *method != Method::Post {
+        return Reply::error(405, "method not allowed");
+    }
+
+    let format = query
+        .split('&')
+        .find_map(|kv| kv.strip_prefix("format="))
+        .unwrap_or("mjai");
+    if format != "mjai" {
+        return Reply::error(400, &format!("unsupported format: {format:?}"));
+    }
+
+    let log = match Log::from_json_str(body) {
+        Ok(log) => log,
+        Err(err) => return Reply::error(422, &err.to_string()),
+    };
+    match tenhou_to_mjai(&log) {
+        Ok(events) => Reply {
+            status: 200,
+            body: serde_json::to_string(&events).expect("events are serializable"),
+        },
+        Err(err) => Reply::error(422, &err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Event;
+
+    const LOG: &str = r#"{"ver":2.3,"ref":"2024030511gm-00b9-0000-e0c07689","log":[[[0,0,0],[35000,35000,35000,0],[47],[],[26,27,32,33,35,37,37,39,41,42,44,44,46],[45,47,19,39,27,34,21,43,26],[60,60,60,60,60,60,"f44","f44",42],[11,19,21,23,29,31,33,41,42,44,44,45,46],[42,28,22,19,"4242p42",23,22,24],["f44",33,"f44",60,19,31,11,41],[19,21,29,29,29,31,34,34,38,41,43,47,47],[21,"47p4747",42,35,27,26,"34p3434",25],[19,43,60,31,41,38,35],[],[],[],["和了",[-700,-400,1100,0],[2,2,2,"40符1飜400-700点","役牌 中(1飜)"]]]],"rule":{"disp":"三鳳南喰赤","aka53":1,"aka52":1,"aka51":1},"name":["mtk","つくねん3","ひぐお3",""]}"#;
+
+    #[test]
+    fn convert() {
+        let reply = handle(&Method::Post, "/convert?format=mjai", LOG);
+        assert_eq!(reply.status, 200);
+        let events: Vec<Event> = serde_json::from_str(&reply.body).unwrap();
+        assert_eq!(events.first().map(Event::actor), Some(None));
+        assert!(matches!(events.last(), Some(Event::EndGame { .. })));
+
+        let reply = handle(&Method::Post, "/convert", LOG);
+        assert_eq!(reply.status, 200);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(handle(&Method::Post, "/", LOG).status, 404);
+        assert_eq!(handle(&Method::Get, "/convert", LOG).status, 405);
+        assert_eq!(
+            handle(&Method::Post, "/convert?format=csv", LOG).status,
+            400
+        );
+        assert_eq!(handle(&Method::Post, "/convert", "{").status, 422);
+    }
+}
@@ -0,0 +1,148 @@
+//! Where tiles are found in sprite sheets and tile sets, so that renderers
+//! and GUIs built on this crate draw every tile from the same place.
+
+use crate::{tu8, Tile};
+
+/// How the tiles are arranged in a sprite sheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AtlasLayout {
+    /// One row per suit in the order man, pin, sou and honors. Suited tiles
+    /// sit at the column of their number and aka fives at column 0, as in the
+    /// `0p` notation. Honors are in the order E S W N P F C from column 1,
+    /// with the back of a tile at column 0 for unknown tiles.
+    #[default]
+    SuitRows,
+    /// A single row in the order of tile IDs, see [`Tile::as_u8`].
+    Strip,
+}
+
+impl AtlasLayout {
+    /// The number of columns and rows of the sheet.
+    #[must_use]
+    pub const fn grid(self) -> (u8, u8) {
+        match self {
+            Self::SuitRows => (10, 4),
+            Self::Strip => (tu8!(?) + 1, 1),
+        }
+    }
+
+    /// The column and row of `tile`.
+    #[must_use]
+    pub const fn cell(self, tile: Tile) -> (u8, u8) {
+        let id = tile.as_u8();
+        if matches!(self, Self::Strip) {
+            return (id, 0);
+        }
+
+        match id {
+            tu8!(1m) => (1, 0),
+            tu8!(9m) => (9, 0),
+            tu8!(5pr) => (0, 1),
+            tu8!(5sr) => (0, 2),
+            tu8!(?) => (0, 3),
+            _ if id <= tu8!(9p) => (id - tu8!(1p) + 1, 1),
+            _ if id <= tu8!(9s) => (id - tu8!(1s) + 1, 2),
+            _ => (id - tu8!(E) + 1, 3),
+        }
+    }
+}
+
+/// A sprite sheet of tiles of the same size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Atlas {
+    pub layout: AtlasLayout,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Pixels between neighbouring tiles, but not around the sheet.
+    pub spacing: u32,
+}
+
+/// The part of a sprite sheet showing one tile, in pixels from the top left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Atlas {
+    #[must_use]
+    pub const fn rect(&self, tile: Tile) -> SpriteRect {
+        let (col, row) = self.layout.cell(tile);
+        SpriteRect {
+            x: col as u32 * (self.tile_width + self.spacing),
+            y: row as u32 * (self.tile_height + self.spacing),
+            width: self.tile_width,
+            height: self.tile_height,
+        }
+    }
+
+    /// The width and height of the whole sheet.
+    #[must_use]
+    pub const fn size(&self) -> (u32, u32) {
+        let (cols, rows) = self.layout.grid();
+        (
+            extent(cols, self.tile_width, self.spacing),
+            extent(rows, self.tile_height, self.spacing),
+        )
+    }
+}
+
+const fn extent(count: u8, len: u32, spacing: u32) -> u32 {
+    count as u32 * (len + spacing) - spacing
+}
+
+const ASSET_NAMES: [&str; tu8!(?) as usize + 1] = [
+    "1p", "2p", "3p", "4p", "5p", "6p", "7p", "8p", "9p", //
+    "1s", "2s", "3s", "4s", "5s", "6s", "7s", "8s", "9s", //
+    "1z", "2z", "3z", "4z", "5z", "6z", "7z", //
+    "1m", "9m", "0p", "0s", "back",
+];
+
+/// The name of the image of `tile` in tile sets with one file per tile, e.g.
+/// `3s.svg`. Suited tiles are named as in `3s` with `0` for aka fives, honors
+/// from `1z` for E to `7z` for C, and unknown tiles `back`.
+#[must_use]
+pub const fn asset_name(tile: Tile) -> &'static str {
+    ASSET_NAMES[tile.as_usize()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::t;
+
+    #[test]
+    fn cells() {
+        let layout = AtlasLayout::SuitRows;
+        assert_eq!(layout.cell(t!(1m)), (1, 0));
+        assert_eq!(layout.cell(t!(3p)), (3, 1));
+        assert_eq!(layout.cell(t!(5sr)), (0, 2));
+        assert_eq!(layout.cell(t!(C)), (7, 3));
+        assert_eq!(layout.cell(t!(?)), (0, 3));
+        assert_eq!(AtlasLayout::Strip.cell(t!(9s)), (17, 0));
+
+        let atlas = Atlas {
+            layout,
+            tile_width: 30,
+            tile_height: 40,
+            spacing: 2,
+        };
+        assert_eq!(
+            atlas.rect(t!(4s)),
+            SpriteRect {
+                x: 128,
+                y: 84,
+                width: 30,
+                height: 40,
+            },
+        );
+        assert_eq!(atlas.size(), (318, 166));
+
+        assert_eq!(asset_name(t!(5pr)), "0p");
+        assert_eq!(asset_name(t!(N)), "4z");
+        assert_eq!(asset_name(t!(9m)), "9m");
+        assert_eq!(asset_name(t!(?)), "back");
+    }
+}
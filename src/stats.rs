@@ -0,0 +1,1906 @@
+//! Aggregates over a corpus of converted logs.
+
+use crate::dataset::{Labels, Outcome};
+use crate::ir::Game;
+use crate::mjai::Event;
+use crate::replay::{self, KyokuState};
+use crate::ruleset::{compute_placements, Ruleset, TieBreak};
+use crate::tenhou::{ActionTable, EndStatus, Kyoku, Log, Room, RuleError};
+use crate::{defense, hand, tu8, Points, Tile, SEATS};
+use std::collections::BTreeMap;
+use std::{fmt, io};
+
+use chrono::{DateTime, FixedOffset, TimeDelta};
+
+use serde::ser::{SerializeSeq, SerializeStruct};
+use serde::{Serialize, Serializer};
+
+/// Number of distinct tile IDs, including aka fives and the unknown tile.
+const TILE_KINDS: usize = tu8!(?) as usize + 1;
+
+/// Totals over a corpus: how its kyokus ended, and how many kans and
+/// discards it had.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    pub games: u32,
+    pub kyokus: u32,
+    pub tsumos: u32,
+    pub rons: u32,
+    pub ryukyokus: u32,
+    pub kans: u32,
+    pub tsumogiris: u32,
+    pub tedashis: u32,
+}
+
+impl Summary {
+    pub fn add(&mut self, log: &Log) {
+        self.games += 1;
+        self.kyokus += log.kyokus.len() as u32;
+        for kyoku in &log.kyokus {
+            match &kyoku.end_status {
+                EndStatus::Hora { details } => {
+                    let tsumos = details.iter().filter(|d| d.is_tsumo()).count() as u32;
+                    self.tsumos += tsumos;
+                    self.rons += details.len() as u32 - tsumos;
+                }
+                EndStatus::Ryukyoku { .. } => self.ryukyokus += 1,
+            }
+            self.kans += kyoku.kan_count() as u32;
+            let turns = kyoku.action_tables.iter().flat_map(ActionTable::turns);
+            for tsumogiri in turns.filter_map(|turn| turn.tsumogiri()) {
+                if tsumogiri {
+                    self.tsumogiris += 1;
+                } else {
+                    self.tedashis += 1;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "games: {}", self.games)?;
+        writeln!(f, "kyokus: {}", self.kyokus)?;
+        writeln!(f, "tsumo: {}", self.tsumos)?;
+        writeln!(f, "ron: {}", self.rons)?;
+        writeln!(f, "ryukyoku: {}", self.ryukyokus)?;
+        writeln!(f, "kans: {}", self.kans)?;
+        writeln!(f, "tsumogiri: {}", self.tsumogiris)?;
+        writeln!(f, "tedashi: {}", self.tedashis)
+    }
+}
+
+/// How often every player discarded every tile at every turn, e.g. to draw
+/// heatmaps of discard rivers.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscardHeatmap {
+    /// The tiles the counts of a turn are listed by.
+    tiles: Vec<Tile>,
+    /// The counts by player name, then by turn, starting from the first
+    /// discard of a kyoku, then by tile in the order of `tiles`.
+    players: BTreeMap<String, Vec<[u32; TILE_KINDS]>>,
+}
+
+impl Default for DiscardHeatmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiscardHeatmap {
+    #[must_use]
+    pub fn new() -> Self {
+        let tiles = (0..TILE_KINDS)
+            .map(|id| Tile::try_from(id).expect("every ID below the count is a tile"))
+            .collect();
+        Self {
+            tiles,
+            players: BTreeMap::new(),
+        }
+    }
+
+    /// Count the discards in `events`, converted from `log`.
+    pub fn add(&mut self, log: &Log, events: &[Event]) {
+        let mut turns = [0_usize; 4];
+        for event in events {
+            match *event {
+                Event::StartKyoku { .. } => turns = [0; 4],
+                Event::Dahai { actor, pai, .. } => {
+                    let name = &log.names[actor as usize];
+                    let counts = self.players.entry(name.clone()).or_default();
+                    let turn = &mut turns[actor as usize];
+                    if counts.len() <= *turn {
+                        counts.resize(*turn + 1, [0; TILE_KINDS]);
+                    }
+                    counts[*turn][pai.as_usize()] += 1;
+                    *turn += 1;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// How often `player` discarded `tile` at `turn`, counting from 0.
+    #[must_use]
+    pub fn count(&self, player: &str, turn: usize, tile: Tile) -> u32 {
+        self.players
+            .get(player)
+            .and_then(|turns| turns.get(turn))
+            .map_or(0, |counts| counts[tile.as_usize()])
+    }
+}
+
+/// How often a player called, and how often they won with an open or a
+/// closed hand.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlayerCalls {
+    pub kyokus: u32,
+    /// Chi, which only show up in yonma logs.
+    pub chis: u32,
+    pub pons: u32,
+    pub daiminkans: u32,
+    pub kakans: u32,
+    pub ankans: u32,
+    /// Nukidora of the north tile.
+    pub kitas: u32,
+    /// Chi, pon and daiminkan others made on the player's discards.
+    pub fed: u32,
+    /// Kyokus the player ended with a called meld, ankan aside.
+    pub open_kyokus: u32,
+    pub open_wins: u32,
+    pub closed_wins: u32,
+}
+
+impl PlayerCalls {
+    /// Chi, pon and daiminkan per kyoku, the calls that take a discard.
+    #[must_use]
+    pub fn calls_per_kyoku(&self) -> f64 {
+        let calls = self.chis + self.pons + self.daiminkans;
+        ratio(calls, self.kyokus).unwrap_or_default()
+    }
+
+    /// The share of kyokus ended with an open hand that the player won.
+    #[must_use]
+    pub fn open_win_rate(&self) -> Option<f64> {
+        ratio(self.open_wins, self.open_kyokus)
+    }
+
+    #[must_use]
+    pub fn closed_win_rate(&self) -> Option<f64> {
+        ratio(self.closed_wins, self.kyokus - self.open_kyokus)
+    }
+
+    fn add_kyoku(&mut self, kyoku: &Self, open: bool, won: bool) {
+        self.kyokus += 1;
+        self.chis += kyoku.chis;
+        self.pons += kyoku.pons;
+        self.daiminkans += kyoku.daiminkans;
+        self.kakans += kyoku.kakans;
+        self.ankans += kyoku.ankans;
+        self.kitas += kyoku.kitas;
+        self.fed += kyoku.fed;
+        self.open_kyokus += u32::from(open);
+        match (won, open) {
+            (true, true) => self.open_wins += 1,
+            (true, false) => self.closed_wins += 1,
+            (false, _) => (),
+        }
+    }
+}
+
+fn ratio(n: u32, total: u32) -> Option<f64> {
+    (total > 0).then(|| f64::from(n) / f64::from(total))
+}
+
+impl Serialize for PlayerCalls {
+    /// The counts along with the rates derived from them.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("PlayerCalls", 14)?;
+        s.serialize_field("kyokus", &self.kyokus)?;
+        s.serialize_field("chis", &self.chis)?;
+        s.serialize_field("pons", &self.pons)?;
+        s.serialize_field("daiminkans", &self.daiminkans)?;
+        s.serialize_field("kakans", &self.kakans)?;
+        s.serialize_field("ankans", &self.ankans)?;
+        s.serialize_field("kitas", &self.kitas)?;
+        s.serialize_field("fed", &self.fed)?;
+        s.serialize_field("open_kyokus", &self.open_kyokus)?;
+        s.serialize_field("open_wins", &self.open_wins)?;
+        s.serialize_field("closed_wins", &self.closed_wins)?;
+        s.serialize_field("calls_per_kyoku", &self.calls_per_kyoku())?;
+        s.serialize_field("open_win_rate", &self.open_win_rate())?;
+        s.serialize_field("closed_win_rate", &self.closed_win_rate())?;
+        s.end()
+    }
+}
+
+/// [`PlayerCalls`] of every player in a corpus, by name.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct CallStats {
+    players: BTreeMap<String, PlayerCalls>,
+}
+
+impl CallStats {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the calls and wins in `events`, converted from `log`.
+    pub fn add(&mut self, log: &Log, events: &[Event]) {
+        let mut kyoku = [PlayerCalls::default(); SEATS];
+        let mut open = [false; SEATS];
+        let mut won = [false; SEATS];
+
+        for event in events {
+            match *event {
+                Event::StartKyoku { .. } => {
+                    kyoku = [PlayerCalls::default(); SEATS];
+                    open = [false; SEATS];
+                    won = [false; SEATS];
+                }
+                Event::Chi { actor, target, .. } => {
+                    kyoku[actor as usize].chis += 1;
+                    kyoku[target as usize].fed += 1;
+                    open[actor as usize] = true;
+                }
+                Event::Pon { actor, target, .. } => {
+                    kyoku[actor as usize].pons += 1;
+                    kyoku[target as usize].fed += 1;
+                    open[actor as usize] = true;
+                }
+                Event::Daiminkan { actor, target, .. } => {
+                    kyoku[actor as usize].daiminkans += 1;
+                    kyoku[target as usize].fed += 1;
+                    open[actor as usize] = true;
+                }
+                Event::Kakan { actor, .. } => kyoku[actor as usize].kakans += 1,
+                Event::Ankan { actor, .. } => kyoku[actor as usize].ankans += 1,
+                Event::Nukidora { actor, .. } => kyoku[actor as usize].kitas += 1,
+                Event::Hora { actor, .. } => won[actor as usize] = true,
+                Event::EndKyoku => {
+                    for seat in 0..SEATS {
+                        let calls = self.players.entry(log.names[seat].clone()).or_default();
+                        calls.add_kyoku(&kyoku[seat], open[seat], won[seat]);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, player: &str) -> Option<&PlayerCalls> {
+        self.players.get(player)
+    }
+
+    /// Every player and their calls, by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PlayerCalls)> {
+        self.players
+            .iter()
+            .map(|(name, calls)| (name.as_str(), calls))
+    }
+}
+
+/// How a player did as the dealer: how often and how long they kept the
+/// seat, what they won or lost meanwhile and whom they dealt in to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PlayerDealing {
+    /// How often the player took the dealer seat.
+    pub turns: u32,
+    /// Kyokus the player dealt, repeats included.
+    pub kyokus: u32,
+    /// Kyokus the player dealt again, by winning or staying tenpai.
+    pub renchans: u32,
+    /// The most repeats of a turn.
+    pub longest_renchan: u32,
+    /// The points the player got and paid over the kyokus they dealt,
+    /// riichi sticks they put on the table included.
+    pub points: Points,
+    /// Rons off the player's discards, one per winner of a multi ron.
+    pub deal_ins: u32,
+    /// The deal-ins by whom they went to.
+    pub deal_ins_to: BTreeMap<String, u32>,
+}
+
+/// [`PlayerDealing`] of every player in a corpus, by name.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct DealerStats {
+    players: BTreeMap<String, PlayerDealing>,
+}
+
+impl DealerStats {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the dealer turns of `log`, told apart by the kyoku number, the
+    /// honba of a repeat counting up.
+    pub fn add(&mut self, log: &Log) {
+        let mut previous = None;
+        let mut streak = 0;
+        for kyoku in &log.kyokus {
+            let oya = usize::from(kyoku.oya());
+            let dealing = self.players.entry(log.names[oya].clone()).or_default();
+            if previous == Some(kyoku.meta.kyoku_num) {
+                streak += 1;
+                dealing.renchans += 1;
+                dealing.longest_renchan = dealing.longest_renchan.max(streak);
+            } else {
+                streak = 0;
+                dealing.turns += 1;
+            }
+            previous = Some(kyoku.meta.kyoku_num);
+            dealing.kyokus += 1;
+
+            dealing.points -= Points::from(kyoku.deposits()[oya]) * 1000;
+            match &kyoku.end_status {
+                EndStatus::Hora { details } => {
+                    for detail in details {
+                        dealing.points += detail.score_deltas[oya];
+                        if usize::from(detail.target) == oya && !detail.is_tsumo() {
+                            dealing.deal_ins += 1;
+                            let winner = log.names[usize::from(detail.who)].clone();
+                            *dealing.deal_ins_to.entry(winner).or_default() += 1;
+                        }
+                    }
+                }
+                EndStatus::Ryukyoku { score_deltas, .. } => dealing.points += score_deltas[oya],
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, player: &str) -> Option<&PlayerDealing> {
+        self.players.get(player)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PlayerDealing)> {
+        self.players
+            .iter()
+            .map(|(name, dealing)| (name.as_str(), dealing))
+    }
+}
+
+/// A riichi of [`RiichiStats`] and how it turned out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RiichiRecord {
+    pub player: String,
+    pub seat: u8,
+    pub game_id: Option<String>,
+    /// The kyoku, e.g. `E4-0`.
+    pub kyoku: String,
+    /// The declaring discard among those of the player, counting from 1.
+    pub junme: u32,
+    /// The kinds of tiles waited on, e.g. `2` for a ryanmen.
+    pub wait_kinds: u32,
+    /// The tiles waited on that the player could not see when declaring,
+    /// those in the wall and in other hands.
+    pub wait_tiles: u32,
+    pub outcome: Outcome,
+    /// The points the player got and paid over the kyoku, the riichi stick
+    /// included.
+    pub points: Points,
+}
+
+/// The riichi of a player summed up, see [`RiichiStats::summaries`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RiichiSummary {
+    pub riichis: u32,
+    pub wins: u32,
+    pub deal_ins: u32,
+    /// The sum of [`RiichiRecord::points`].
+    pub points: Points,
+    /// The sum of [`RiichiRecord::junme`].
+    pub junme: u32,
+    /// The sum of [`RiichiRecord::wait_tiles`].
+    pub wait_tiles: u32,
+}
+
+impl RiichiSummary {
+    /// The average points of a riichi, the riichi stick included.
+    #[must_use]
+    pub fn ev(&self) -> Option<f64> {
+        (self.riichis > 0).then(|| self.points as f64 / f64::from(self.riichis))
+    }
+
+    #[must_use]
+    pub fn win_rate(&self) -> Option<f64> {
+        ratio(self.wins, self.riichis)
+    }
+
+    #[must_use]
+    pub fn deal_in_rate(&self) -> Option<f64> {
+        ratio(self.deal_ins, self.riichis)
+    }
+
+    #[must_use]
+    pub fn average_junme(&self) -> Option<f64> {
+        ratio(self.junme, self.riichis)
+    }
+
+    #[must_use]
+    pub fn average_wait_tiles(&self) -> Option<f64> {
+        ratio(self.wait_tiles, self.riichis)
+    }
+}
+
+impl Serialize for RiichiSummary {
+    /// The counts along with the rates derived from them.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("RiichiSummary", 9)?;
+        s.serialize_field("riichis", &self.riichis)?;
+        s.serialize_field("wins", &self.wins)?;
+        s.serialize_field("deal_ins", &self.deal_ins)?;
+        s.serialize_field("points", &self.points)?;
+        s.serialize_field("ev", &self.ev())?;
+        s.serialize_field("win_rate", &self.win_rate())?;
+        s.serialize_field("deal_in_rate", &self.deal_in_rate())?;
+        s.serialize_field("average_junme", &self.average_junme())?;
+        s.serialize_field("average_wait_tiles", &self.average_wait_tiles())?;
+        s.end()
+    }
+}
+
+/// Every riichi of a corpus with its turn, its wait and how it turned out,
+/// to tell what the riichi of a player are worth.
+#[derive(Debug, Clone, Default)]
+pub struct RiichiStats {
+    records: Vec<RiichiRecord>,
+}
+
+impl RiichiStats {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the riichi in `events`, converted from `log`.
+    ///
+    /// # Errors
+    ///
+    /// Fails for events that cannot be replayed.
+    pub fn add(&mut self, log: &Log, events: &[Event]) -> replay::Result<()> {
+        let mut state = KyokuState::default();
+        let mut kyoku = None;
+        let mut junme = [0; 4];
+        let mut kyoku_start = self.records.len();
+        let mut labels = Labels::default();
+        for (index, event) in events.iter().enumerate() {
+            if let Event::StartKyoku { .. } = event {
+                label(&mut self.records[kyoku_start..], &labels);
+                kyoku_start = self.records.len();
+                labels = Labels::default();
+                kyoku = Some(kyoku.map_or(0, |k| k + 1));
+                junme = [0; 4];
+            }
+            labels.add(event);
+            state.apply(index, event)?;
+            let Event::Dahai { actor, .. } = *event else {
+                continue;
+            };
+            junme[usize::from(actor)] += 1;
+            if !matches!(events[index - 1], Event::Reach { actor: a } if a == actor) {
+                continue;
+            }
+            let waits = hand::waits(&state.players[usize::from(actor)].tehai);
+            let visible = defense::visible(&state, actor);
+            let wait_tiles = waits
+                .iter()
+                .map(|tile| u32::from(4 - visible[tile.as_usize()].min(4)))
+                .sum();
+            self.records.push(RiichiRecord {
+                player: log.names[usize::from(actor)].clone(),
+                seat: actor,
+                game_id: log.game_id.clone(),
+                kyoku: kyoku
+                    .and_then(|k| log.kyokus.get(k))
+                    .map_or_else(String::new, |k| k.meta.short_name()),
+                junme: junme[usize::from(actor)],
+                wait_kinds: waits.len() as u32,
+                wait_tiles,
+                outcome: Outcome::default(),
+                points: 0,
+            });
+        }
+        label(&mut self.records[kyoku_start..], &labels);
+        Ok(())
+    }
+
+    /// Every riichi, in the order they were declared.
+    #[must_use]
+    pub fn records(&self) -> &[RiichiRecord] {
+        &self.records
+    }
+
+    /// The riichi of every player summed up, by name.
+    #[must_use]
+    pub fn summaries(&self) -> BTreeMap<&str, RiichiSummary> {
+        let mut summaries = BTreeMap::<_, RiichiSummary>::new();
+        for record in &self.records {
+            let summary = summaries.entry(record.player.as_str()).or_default();
+            summary.riichis += 1;
+            summary.wins += u32::from(record.outcome == Outcome::Won);
+            summary.deal_ins += u32::from(record.outcome == Outcome::DealtIn);
+            summary.points += record.points;
+            summary.junme += record.junme;
+            summary.wait_tiles += record.wait_tiles;
+        }
+        summaries
+    }
+}
+
+/// Label `records`, the riichi of a kyoku, with how it ended.
+fn label(records: &mut [RiichiRecord], labels: &Labels) {
+    for record in records {
+        record.outcome = labels.outcomes[usize::from(record.seat)];
+        record.points = labels.deltas[usize::from(record.seat)];
+    }
+}
+
+impl Serialize for RiichiStats {
+    /// The summaries by name under `players`, and every riichi under
+    /// `riichis`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("RiichiStats", 2)?;
+        s.serialize_field("players", &self.summaries())?;
+        s.serialize_field("riichis", &self.records)?;
+        s.end()
+    }
+}
+
+/// The placements and final points of a player over a corpus.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Standing {
+    pub games: u32,
+    /// How often the player placed first, second and third.
+    pub placements: [u32; SEATS],
+    /// The sum of the final points of every game, with uma and oka.
+    pub points: Points,
+}
+
+impl Standing {
+    /// The average placement, counting from 1.
+    #[must_use]
+    pub fn average_placement(&self) -> Option<f64> {
+        let sum: u32 = (1..).zip(self.placements).map(|(p, n)| p * n).sum();
+        ratio(sum, self.games)
+    }
+
+    /// [`Self::points`] in thousands of points, e.g. `43.1`.
+    #[must_use]
+    pub fn placement_points(&self) -> f64 {
+        self.points as f64 / 1000.0
+    }
+}
+
+/// The [`Standing`] of every player in a corpus, ranked by their total
+/// placement points under a [`Ruleset`].
+#[derive(Debug, Clone, Default)]
+pub struct Leaderboard {
+    ruleset: Ruleset,
+    players: BTreeMap<String, Standing>,
+}
+
+impl Leaderboard {
+    #[must_use]
+    pub const fn new(ruleset: Ruleset) -> Self {
+        Self {
+            ruleset,
+            players: BTreeMap::new(),
+        }
+    }
+
+    /// Count the final result of `log`, which must be a complete game. Logs
+    /// without kyokus are left out.
+    ///
+    /// # Errors
+    ///
+    /// Fails for logs whose final scores overflow, see [`Log::final_scores`].
+    pub fn add(&mut self, log: &Log) -> Result<(), RuleError> {
+        let Some(scores) = log.final_scores()? else {
+            return Ok(());
+        };
+        let scores = [scores[0], scores[1], scores[2]];
+        let placements = self.ruleset.placements(scores);
+        let points = self.ruleset.final_points(scores);
+        for seat in 0..SEATS {
+            let standing = self.players.entry(log.names[seat].clone()).or_default();
+            standing.games += 1;
+            standing.placements[placements[seat]] += 1;
+            standing.points += points[seat];
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub const fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+
+    #[must_use]
+    pub fn get(&self, player: &str) -> Option<&Standing> {
+        self.players.get(player)
+    }
+
+    /// Every player and their standing, from the most placement points down.
+    /// Players on the same points are listed by name.
+    #[must_use]
+    pub fn ranked(&self) -> Vec<(&str, &Standing)> {
+        let mut ranked: Vec<_> = self
+            .players
+            .iter()
+            .map(|(name, standing)| (name.as_str(), standing))
+            .collect();
+        ranked.sort_by_key(|(_, standing)| -standing.points);
+        ranked
+    }
+
+    /// Write the ranking as CSV with a header row.
+    pub fn write_csv<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(
+            w,
+            "rank,name,games,first,second,third,average_placement,points"
+        )?;
+        for (rank, (name, standing)) in (1..).zip(self.ranked()) {
+            let [first, second, third] = standing.placements;
+            writeln!(
+                w,
+                "{rank},{},{},{first},{second},{third},{:.2},{:.1}",
+                csv_field(name),
+                standing.games,
+                standing.average_placement().unwrap_or_default(),
+                standing.placement_points(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the ranking as a Markdown table.
+    pub fn write_markdown<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(
+            w,
+            "| Rank | Name | Games | 1st | 2nd | 3rd | Avg. placement | Points |"
+        )?;
+        writeln!(
+            w,
+            "| ---: | :--- | ---: | ---: | ---: | ---: | ---: | ---: |"
+        )?;
+        for (rank, (name, standing)) in (1..).zip(self.ranked()) {
+            let [first, second, third] = standing.placements;
+            writeln!(
+                w,
+                "| {rank} | {} | {} | {first} | {second} | {third} | {:.2} | {:.1} |",
+                name.replace('|', "\\|"),
+                standing.games,
+                standing.average_placement().unwrap_or_default(),
+                standing.placement_points(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The record of one player of a [`HeadToHead`] against the other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Matchup {
+    /// How often the player placed first, second and third in the shared
+    /// games.
+    pub placements: [u32; SEATS],
+    /// Shared games the player finished above the other.
+    pub ahead: u32,
+    /// Rons the player won off a discard of the other.
+    pub rons: u32,
+    /// The points the other paid for those rons, honba included.
+    pub points: Points,
+}
+
+impl Matchup {
+    /// The average placement in the shared games, counting from 1.
+    #[must_use]
+    pub fn average_placement(&self) -> Option<f64> {
+        let sum: u32 = (1..).zip(self.placements).map(|(p, n)| p * n).sum();
+        ratio(sum, self.placements.iter().sum())
+    }
+}
+
+/// The direct matchup record of two players over the games they both
+/// played in.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HeadToHead {
+    pub names: [String; 2],
+    pub games: u32,
+    /// The record of every player against the other, in the order of
+    /// `names`.
+    pub records: [Matchup; 2],
+    /// How games the two finished tied in are placed.
+    #[serde(skip)]
+    pub tie_break: TieBreak,
+}
+
+impl HeadToHead {
+    #[must_use]
+    pub const fn new(names: [String; 2]) -> Self {
+        Self {
+            names,
+            games: 0,
+            tie_break: TieBreak::SeatOrder,
+            records: [Matchup {
+                placements: [0; SEATS],
+                ahead: 0,
+                rons: 0,
+                points: 0,
+            }; 2],
+        }
+    }
+
+    /// Count `log` if both players played in it. It must be a complete
+    /// game for the placements to mean anything.
+    ///
+    /// # Errors
+    ///
+    /// Fails for logs whose final scores overflow, see [`Log::final_scores`].
+    pub fn add(&mut self, log: &Log) -> Result<(), RuleError> {
+        let seat_of = |name: &str| log.players().iter().position(|n| n == name);
+        let (Some(a), Some(b)) = (seat_of(&self.names[0]), seat_of(&self.names[1])) else {
+            return Ok(());
+        };
+        if a == b {
+            return Ok(());
+        }
+        let Some(scores) = log.final_scores()? else {
+            return Ok(());
+        };
+        let seats = [a, b];
+        let placements = compute_placements([scores[0], scores[1], scores[2]], self.tie_break);
+        self.games += 1;
+        for (i, record) in self.records.iter_mut().enumerate() {
+            let (seat, other) = (seats[i], seats[1 - i]);
+            record.placements[placements[seat]] += 1;
+            record.ahead += u32::from(placements[seat] < placements[other]);
+            for hora in log.kyokus.iter().flat_map(Kyoku::horas) {
+                if hora.who as usize == seat && hora.target as usize == other {
+                    record.rons += 1;
+                    record.points -= hora.score_deltas[other];
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the records as CSV with a header row, one row per player.
+    pub fn write_csv<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "name,games,ahead,average_placement,rons,points")?;
+        for (name, record) in self.names.iter().zip(&self.records) {
+            writeln!(
+                w,
+                "{},{},{},{:.2},{},{}",
+                csv_field(name),
+                self.games,
+                record.ahead,
+                record.average_placement().unwrap_or_default(),
+                record.rons,
+                record.points,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the records as a Markdown table.
+    pub fn write_markdown<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(
+            w,
+            "| Name | Games | Ahead | Avg. placement | Rons | Points |"
+        )?;
+        writeln!(w, "| :--- | ---: | ---: | ---: | ---: | ---: |")?;
+        for (name, record) in self.names.iter().zip(&self.records) {
+            writeln!(
+                w,
+                "| {} | {} | {} | {:.2} | {} | {} |",
+                name.replace('|', "\\|"),
+                self.games,
+                record.ahead,
+                record.average_placement().unwrap_or_default(),
+                record.rons,
+                record.points,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A game of a [`Progression`], with the rank and R the player went into it
+/// with and their estimated change, see [`Log::rating_changes`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GameProgress {
+    pub game_id: Option<String>,
+    /// When the game started, e.g. `2024-03-05T11:00:00+09:00`, from its ID.
+    pub started_at: Option<String>,
+    pub room: Option<Room>,
+    /// Counting from 1.
+    pub placement: u8,
+    pub dan: Option<String>,
+    pub dan_points: Option<i32>,
+    /// The estimated dan points since the player reached `dan`, as far as
+    /// the games of the corpus go back, this game included.
+    pub dan_points_total: Option<i32>,
+    pub rate: Option<f64>,
+    pub rate_change: Option<f64>,
+}
+
+/// How the dan and R of a player evolved over the games of a corpus.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progression {
+    pub name: String,
+    games: Vec<GameProgress>,
+}
+
+impl Progression {
+    #[must_use]
+    pub const fn new(name: String) -> Self {
+        Self {
+            name,
+            games: vec![],
+        }
+    }
+
+    /// Count `log` if the player played in it and it records their rank or
+    /// R, as tenhou's do.
+    pub fn add(&mut self, log: &Log) {
+        let Some(seat) = log.players().iter().position(|n| *n == self.name) else {
+            return;
+        };
+        let Some(changes) = log.rating_changes() else {
+            return;
+        };
+        let change = &changes[seat];
+        self.games.push(GameProgress {
+            game_id: log.game_id.clone(),
+            started_at: log.started_at().map(|t| t.to_rfc3339()),
+            room: log.room(),
+            placement: change.placement,
+            dan: change.dan.clone(),
+            dan_points: change.dan_points,
+            dan_points_total: None,
+            rate: change.rate,
+            rate_change: change.rate_change,
+        });
+    }
+
+    /// The games in the order they were played, those without an ID last,
+    /// with the dan points added up.
+    #[must_use]
+    pub fn timeline(&self) -> Vec<GameProgress> {
+        let mut games = self.games.clone();
+        games.sort_by(|a, b| {
+            let key = |g: &GameProgress| (g.started_at.is_none(), g.started_at.clone());
+            key(a).cmp(&key(b)).then_with(|| a.game_id.cmp(&b.game_id))
+        });
+        let mut total = None;
+        let mut dan = None;
+        for game in &mut games {
+            if game.dan != dan {
+                dan.clone_from(&game.dan);
+                total = None;
+            }
+            if let Some(points) = game.dan_points {
+                total = Some(total.unwrap_or(0) + points);
+            }
+            game.dan_points_total = total;
+        }
+        games
+    }
+
+    /// Write the timeline as CSV with a header row, one row per game.
+    pub fn write_csv<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(
+            w,
+            "started_at,game_id,placement,dan,dan_points,dan_points_total,rate,rate_change"
+        )?;
+        for game in self.timeline() {
+            writeln!(
+                w,
+                "{},{},{},{},{},{},{},{}",
+                game.started_at.unwrap_or_default(),
+                csv_field(game.game_id.as_deref().unwrap_or_default()),
+                game.placement,
+                game.dan.unwrap_or_default(),
+                optional(game.dan_points),
+                optional(game.dan_points_total),
+                optional(game.rate.map(|r| format!("{r:.2}"))),
+                optional(game.rate_change.map(|r| format!("{r:+.2}"))),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the timeline as a Markdown table.
+    pub fn write_markdown<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(
+            w,
+            "| Started | Game | Placement | Dan | Dan points | Total | R | R change |"
+        )?;
+        writeln!(
+            w,
+            "| :--- | :--- | ---: | :--- | ---: | ---: | ---: | ---: |"
+        )?;
+        for game in self.timeline() {
+            writeln!(
+                w,
+                "| {} | {} | {} | {} | {} | {} | {} | {} |",
+                game.started_at.unwrap_or_default(),
+                game.game_id.unwrap_or_default(),
+                game.placement,
+                game.dan.unwrap_or_default(),
+                optional(game.dan_points),
+                optional(game.dan_points_total),
+                optional(game.rate.map(|r| format!("{r:.2}"))),
+                optional(game.rate_change.map(|r| format!("{r:+.2}"))),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Progression {
+    /// [`Progression::timeline`] as an array.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.timeline().serialize(serializer)
+    }
+}
+
+/// The record of a player over the games of a [`Session`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SessionRecord {
+    pub name: String,
+    /// How often the player placed first, second and third.
+    pub placements: [u32; SEATS],
+    /// The sum of the final points of every game, with uma and oka.
+    pub points: Points,
+    /// Games the player was last in at the start of a kyoku after the
+    /// first.
+    pub trailed: u32,
+    /// Those of the games trailed the player went on to win.
+    pub comebacks: u32,
+    /// How often the player placed first, second and third in the games
+    /// right after one they placed last in, to tell tilt.
+    pub after_last: [u32; SEATS],
+}
+
+impl SessionRecord {
+    /// The average placement, counting from 1.
+    #[must_use]
+    pub fn average_placement(&self) -> Option<f64> {
+        let sum: u32 = (1..).zip(self.placements).map(|(p, n)| p * n).sum();
+        ratio(sum, self.placements.iter().sum())
+    }
+
+    /// The share of the games trailed the player won.
+    #[must_use]
+    pub fn comeback_rate(&self) -> Option<f64> {
+        ratio(self.comebacks, self.trailed)
+    }
+
+    /// The average placement in the games right after a last place,
+    /// counting from 1.
+    #[must_use]
+    pub fn average_placement_after_last(&self) -> Option<f64> {
+        let sum: u32 = (1..).zip(self.after_last).map(|(p, n)| p * n).sum();
+        ratio(sum, self.after_last.iter().sum())
+    }
+}
+
+/// Consecutive games of the same three players, e.g. an evening of a
+/// league table, see [`Sessions`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Session {
+    /// When the first game started, e.g. `2024-03-05T11:00:00+09:00`.
+    pub started_at: String,
+    /// When the last game started.
+    pub last_started_at: String,
+    /// In the order they were played.
+    pub game_ids: Vec<String>,
+    /// The record of every player, by name.
+    pub records: [SessionRecord; SEATS],
+}
+
+/// A game of [`Sessions`], with what its sessions are made of.
+#[derive(Debug, Clone)]
+struct SessionGame {
+    started_at: DateTime<FixedOffset>,
+    game_id: String,
+    names: [String; SEATS],
+    placements: [usize; SEATS],
+    points: [Points; SEATS],
+    trailed: [bool; SEATS],
+}
+
+/// A session of [`Sessions::sessions`] that the next game may be part of.
+struct OpenSession {
+    /// Sorted.
+    names: [String; SEATS],
+    last_started_at: DateTime<FixedOffset>,
+    index: usize,
+    /// The placements in the last game, in the order of `names`.
+    last_placements: Option<[usize; SEATS]>,
+}
+
+/// The games of a corpus grouped into sessions: games of the same three
+/// players, whatever their seats, each started at most a gap after the
+/// one before.
+///
+/// Tenhou only records the hour a game started in its ID, so the gap should
+/// be more than an hour for back to back games to make a session.
+#[derive(Debug, Clone)]
+pub struct Sessions {
+    ruleset: Ruleset,
+    gap: TimeDelta,
+    games: Vec<SessionGame>,
+}
+
+impl Sessions {
+    #[must_use]
+    pub const fn new(ruleset: Ruleset, gap: TimeDelta) -> Self {
+        Self {
+            ruleset,
+            gap,
+            games: vec![],
+        }
+    }
+
+    /// Count `log`, which must be a complete game. Logs without kyokus or
+    /// without an ID telling when they started are left out.
+    ///
+    /// # Errors
+    ///
+    /// Fails for logs whose final scores overflow, see [`Log::final_scores`].
+    pub fn add(&mut self, log: &Log) -> Result<(), RuleError> {
+        let (Some(started_at), Some(game_id)) = (log.started_at(), &log.game_id) else {
+            return Ok(());
+        };
+        let Some(scores) = log.final_scores()? else {
+            return Ok(());
+        };
+        let scores = [scores[0], scores[1], scores[2]];
+        let mut trailed = [false; SEATS];
+        for kyoku in log.kyokus.iter().skip(1) {
+            let placements = self.ruleset.placements(kyoku.scores3());
+            for (trailed, placement) in trailed.iter_mut().zip(placements) {
+                *trailed |= placement == SEATS - 1;
+            }
+        }
+        self.games.push(SessionGame {
+            started_at,
+            game_id: game_id.clone(),
+            names: std::array::from_fn(|seat| log.names[seat].clone()),
+            placements: self.ruleset.placements(scores),
+            points: self.ruleset.final_points(scores),
+            trailed,
+        });
+        Ok(())
+    }
+
+    /// Every session, in the order they were played.
+    #[must_use]
+    pub fn sessions(&self) -> Vec<Session> {
+        let mut games: Vec<_> = self.games.iter().collect();
+        games.sort_by(|a, b| (a.started_at, &a.game_id).cmp(&(b.started_at, &b.game_id)));
+        let players = |game: &SessionGame| {
+            let mut names = game.names.clone();
+            names.sort();
+            names
+        };
+
+        let mut sessions: Vec<Session> = vec![];
+        // The sessions the next game may still be part of.
+        let mut open: Vec<OpenSession> = vec![];
+        for game in games {
+            let names = players(game);
+            open.retain(|o| game.started_at - o.last_started_at <= self.gap);
+            let o = if let Some(o) = open.iter_mut().find(|o| o.names == names) {
+                o
+            } else {
+                sessions.push(Session {
+                    started_at: game.started_at.to_rfc3339(),
+                    last_started_at: String::new(),
+                    game_ids: vec![],
+                    records: names.clone().map(|name| SessionRecord {
+                        name,
+                        ..SessionRecord::default()
+                    }),
+                });
+                open.push(OpenSession {
+                    names,
+                    last_started_at: game.started_at,
+                    index: sessions.len() - 1,
+                    last_placements: None,
+                });
+                open.last_mut().expect("just pushed")
+            };
+
+            let session = &mut sessions[o.index];
+            session.last_started_at = game.started_at.to_rfc3339();
+            session.game_ids.push(game.game_id.clone());
+            let mut placements = [0; SEATS];
+            // Seats sharing a name, e.g. guests all named `NoName`, take the
+            // records of that name in turn.
+            let mut taken = [false; SEATS];
+            for seat in 0..SEATS {
+                let i = (0..SEATS)
+                    .find(|&i| !taken[i] && o.names[i] == game.names[seat])
+                    .unwrap_or(seat);
+                taken[i] = true;
+                let placement = game.placements[seat];
+                let record = &mut session.records[i];
+                if o.last_placements.is_some_and(|last| last[i] == SEATS - 1) {
+                    record.after_last[placement] += 1;
+                }
+                record.placements[placement] += 1;
+                record.points += game.points[seat];
+                record.trailed += u32::from(game.trailed[seat]);
+                record.comebacks += u32::from(game.trailed[seat] && placement == 0);
+                placements[i] = placement;
+            }
+            o.last_started_at = game.started_at;
+            o.last_placements = Some(placements);
+        }
+        sessions
+    }
+
+    /// Write the sessions as CSV with a header row, one row per player of
+    /// every session, numbered from 1.
+    pub fn write_csv<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(
+            w,
+            "session,started_at,games,name,first,second,third,points,trailed,comebacks,average_placement_after_last"
+        )?;
+        for (number, session) in (1..).zip(self.sessions()) {
+            for record in &session.records {
+                let [first, second, third] = record.placements;
+                writeln!(
+                    w,
+                    "{number},{},{},{},{first},{second},{third},{:.1},{},{},{}",
+                    session.started_at,
+                    session.game_ids.len(),
+                    csv_field(&record.name),
+                    record.points as f64 / 1000.0,
+                    record.trailed,
+                    record.comebacks,
+                    optional(
+                        record
+                            .average_placement_after_last()
+                            .map(|p| format!("{p:.2}"))
+                    ),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the sessions as a Markdown table.
+    pub fn write_markdown<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(
+            w,
+            "| Session | Started | Games | Name | 1st | 2nd | 3rd | Points | Trailed | Comebacks | Avg. after 3rd |"
+        )?;
+        writeln!(
+            w,
+            "| ---: | :--- | ---: | :--- | ---: | ---: | ---: | ---: | ---: | ---: | ---: |"
+        )?;
+        for (number, session) in (1..).zip(self.sessions()) {
+            for record in &session.records {
+                let [first, second, third] = record.placements;
+                writeln!(
+                    w,
+                    "| {number} | {} | {} | {} | {first} | {second} | {third} | {:.1} | {} | {} | {} |",
+                    session.started_at,
+                    session.game_ids.len(),
+                    record.name.replace('|', "\\|"),
+                    record.points as f64 / 1000.0,
+                    record.trailed,
+                    record.comebacks,
+                    optional(record.average_placement_after_last().map(|p| format!("{p:.2}"))),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+impl Serialize for Sessions {
+    /// [`Sessions::sessions`] as an array.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.sessions().serialize(serializer)
+    }
+}
+
+/// The points one player paid another for their horas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Flow {
+    /// Paid for rons, as the discarder or the seat liable for the hora.
+    pub ron: Points,
+    /// Paid as a share of tsumos.
+    pub tsumo: Points,
+}
+
+impl Flow {
+    #[must_use]
+    pub const fn total(&self) -> Points {
+        self.ron + self.tsumo
+    }
+}
+
+/// The points the seats of one game paid each other.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameFlow {
+    pub game_id: Option<String>,
+    pub names: [String; SEATS],
+    /// What the seat of the row paid the seat of the column.
+    pub matrix: [[Flow; SEATS]; SEATS],
+}
+
+/// Who paid whom how many points, game by game and over the whole corpus,
+/// to tell who feeds whom in a regular group.
+///
+/// Only what the horas moved counts, honba included: the noten payments of
+/// a ryukyoku and riichi sticks have no one receiving them from a
+/// particular player.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PointFlows {
+    games: Vec<GameFlow>,
+    /// By the name of the player paying, then of the one receiving.
+    totals: BTreeMap<String, BTreeMap<String, Flow>>,
+}
+
+impl PointFlows {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the horas of `log`.
+    pub fn add(&mut self, log: &Log) {
+        let mut matrix = [[Flow::default(); SEATS]; SEATS];
+        for hora in log.kyokus.iter().flat_map(Kyoku::horas) {
+            let winner = usize::from(hora.who);
+            for (payer, &delta) in hora.score_deltas[..SEATS].iter().enumerate() {
+                if payer == winner || delta >= 0 {
+                    continue;
+                }
+                let flow = &mut matrix[payer][winner];
+                if hora.is_tsumo() {
+                    flow.tsumo -= delta;
+                } else {
+                    flow.ron -= delta;
+                }
+            }
+        }
+
+        let names: [String; SEATS] = std::array::from_fn(|seat| log.names[seat].clone());
+        for (payer, row) in matrix.iter().enumerate() {
+            for (receiver, flow) in row.iter().enumerate() {
+                if flow.total() == 0 {
+                    continue;
+                }
+                let total = self
+                    .totals
+                    .entry(names[payer].clone())
+                    .or_default()
+                    .entry(names[receiver].clone())
+                    .or_default();
+                total.ron += flow.ron;
+                total.tsumo += flow.tsumo;
+            }
+        }
+        self.games.push(GameFlow {
+            game_id: log.game_id.clone(),
+            names,
+            matrix,
+        });
+    }
+
+    /// The games in the order they were added.
+    #[must_use]
+    pub fn games(&self) -> &[GameFlow] {
+        &self.games
+    }
+
+    /// What `payer` paid `receiver` over all games.
+    #[must_use]
+    pub fn get(&self, payer: &str, receiver: &str) -> Flow {
+        self.totals
+            .get(payer)
+            .and_then(|received| received.get(receiver))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Every pair that paid anything, by payer and then receiver, with
+    /// what the receiver paid back.
+    fn pairs(&self) -> impl Iterator<Item = (&str, &str, Flow, Points)> {
+        self.totals.iter().flat_map(move |(payer, received)| {
+            received.iter().map(move |(receiver, flow)| {
+                let back = self.get(receiver, payer).total();
+                (payer.as_str(), receiver.as_str(), *flow, back)
+            })
+        })
+    }
+
+    /// Write the totals as CSV with a header row, one row per payer and
+    /// receiver. `net` is what the payer paid less what they got back.
+    pub fn write_csv<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "payer,receiver,ron,tsumo,total,net")?;
+        for (payer, receiver, flow, back) in self.pairs() {
+            writeln!(
+                w,
+                "{},{},{},{},{},{}",
+                csv_field(payer),
+                csv_field(receiver),
+                flow.ron,
+                flow.tsumo,
+                flow.total(),
+                flow.total() - back,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the totals as a Markdown table.
+    pub fn write_markdown<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "| Payer | Receiver | Ron | Tsumo | Total | Net |")?;
+        writeln!(w, "| :--- | :--- | ---: | ---: | ---: | ---: |")?;
+        for (payer, receiver, flow, back) in self.pairs() {
+            writeln!(
+                w,
+                "| {} | {} | {} | {} | {} | {} |",
+                payer.replace('|', "\\|"),
+                receiver.replace('|', "\\|"),
+                flow.ron,
+                flow.tsumo,
+                flow.total(),
+                flow.total() - back,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A kind of decision a player takes time over, see [`ThinkTimes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Discard,
+    Riichi,
+    /// Pon, daiminkan and chi, the calls that take a discard.
+    Call,
+    /// Ankan and kakan.
+    Kan,
+    Kita,
+    Win,
+}
+
+impl Decision {
+    /// The decision `event` was the outcome of, if any.
+    #[must_use]
+    pub const fn of(event: &Event) -> Option<Self> {
+        match event {
+            Event::Dahai { .. } => Some(Self::Discard),
+            Event::Reach { .. } => Some(Self::Riichi),
+            Event::Chi { .. } | Event::Pon { .. } | Event::Daiminkan { .. } => Some(Self::Call),
+            Event::Ankan { .. } | Event::Kakan { .. } => Some(Self::Kan),
+            Event::Nukidora { .. } => Some(Self::Kita),
+            Event::Hora { .. } => Some(Self::Win),
+            _ => None,
+        }
+    }
+}
+
+/// The think times of a player over one kind of decision, in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThinkTime {
+    /// Sorted.
+    times: Vec<u64>,
+}
+
+impl ThinkTime {
+    #[must_use]
+    pub const fn count(&self) -> usize {
+        self.times.len()
+    }
+
+    #[must_use]
+    pub fn mean(&self) -> Option<f64> {
+        let total: u64 = self.times.iter().sum();
+        (!self.times.is_empty()).then(|| total as f64 / self.times.len() as f64)
+    }
+
+    #[must_use]
+    pub fn median(&self) -> Option<u64> {
+        self.times.get(self.times.len() / 2).copied()
+    }
+
+    #[must_use]
+    pub fn max(&self) -> Option<u64> {
+        self.times.last().copied()
+    }
+
+    fn add(&mut self, time: u64) {
+        let at = self.times.partition_point(|&t| t <= time);
+        self.times.insert(at, time);
+    }
+}
+
+impl Serialize for ThinkTime {
+    /// The count along with the mean, median and longest time.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("ThinkTime", 4)?;
+        s.serialize_field("count", &self.count())?;
+        s.serialize_field("mean_ms", &self.mean())?;
+        s.serialize_field("median_ms", &self.median())?;
+        s.serialize_field("max_ms", &self.max())?;
+        s.end()
+    }
+}
+
+/// How long every player took over each kind of decision, from the event
+/// before it to the event it made, in games whose source recorded when
+/// events happened, see [`Game::timestamps`], such as the output of `live
+/// --timestamps` read by [`MjaiLog`]. Tenhou logs do not, so a corpus of
+/// them has no `players`.
+///
+/// [`MjaiLog`]: crate::ir::MjaiLog
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ThinkTimes {
+    pub games: u32,
+    /// Those of `games` with timestamps.
+    pub timed_games: u32,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    players: BTreeMap<String, BTreeMap<Decision, ThinkTime>>,
+}
+
+impl ThinkTimes {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the decisions of `game`, if it has timestamps.
+    pub fn add(&mut self, game: &Game) {
+        self.games += 1;
+        let Some(timestamps) = &game.timestamps else {
+            return;
+        };
+        self.timed_games += 1;
+        for (i, event) in game.events.iter().enumerate().skip(1) {
+            let (Some(decision), Some(actor)) = (Decision::of(event), event.actor()) else {
+                continue;
+            };
+            let (Some(&before), Some(&at)) = (timestamps.get(i - 1), timestamps.get(i)) else {
+                continue;
+            };
+            self.players
+                .entry(game.meta.names[usize::from(actor)].clone())
+                .or_default()
+                .entry(decision)
+                .or_default()
+                .add(at.saturating_sub(before));
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, player: &str, decision: Decision) -> Option<&ThinkTime> {
+        self.players.get(player)?.get(&decision)
+    }
+}
+
+/// `value`, or nothing for a missing one.
+fn optional(value: Option<impl ToString>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Quote `field` if it would otherwise break the row.
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+impl Serialize for Leaderboard {
+    /// An array of the rows of the ranking.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Row<'a> {
+            rank: usize,
+            name: &'a str,
+            games: u32,
+            placements: [u32; SEATS],
+            average_placement: Option<f64>,
+            points: f64,
+        }
+
+        let ranked = self.ranked();
+        let mut seq = serializer.serialize_seq(Some(ranked.len()))?;
+        for (rank, (name, standing)) in (1..).zip(ranked) {
+            seq.serialize_element(&Row {
+                rank,
+                name,
+                games: standing.games,
+                placements: standing.placements,
+                average_placement: standing.average_placement(),
+                points: standing.placement_points(),
+            })?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::t;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn summary() {
+        let mut summary = Summary::default();
+        for fixture in Fixture::ALL {
+            summary.add(&fixture.log());
+        }
+        assert_eq!(summary.games, Fixture::ALL.len() as u32);
+        // The double ron of the multi-ron fixture ends a single kyoku.
+        assert_eq!(
+            summary.kyokus,
+            summary.tsumos + summary.rons + summary.ryukyokus - 1,
+            "{summary}"
+        );
+        assert!(summary.kans > 0 && summary.tsumogiris > 0 && summary.tedashis > 0);
+    }
+
+    #[test]
+    fn discard_heatmap() {
+        let log = Fixture::Hanchan.log();
+        let events = tenhou_to_mjai(&log).unwrap();
+        let mut heatmap = DiscardHeatmap::new();
+        heatmap.add(&log, &events);
+
+        // The first discards of mtk over the 11 kyokus.
+        assert_eq!(heatmap.count("mtk", 0, t!(9m)), 3);
+        assert_eq!(heatmap.count("mtk", 0, t!(E)), 1);
+        assert_eq!(heatmap.count("mtk", 0, t!(N)), 0);
+        assert_eq!(heatmap.count("nobody", 0, t!(P)), 0);
+
+        let discards: u32 = heatmap.players["mtk"].iter().flatten().sum();
+        let expected = events
+            .iter()
+            .filter(|e| matches!(e, Event::Dahai { actor: 0, .. }))
+            .count();
+        assert_eq!(discards as usize, expected);
+
+        let json = serde_json::to_value(&heatmap).unwrap();
+        assert_eq!(json["tiles"][27], "5pr");
+    }
+
+    #[test]
+    fn call_stats() {
+        let log = Fixture::Hanchan.log();
+        let events = tenhou_to_mjai(&log).unwrap();
+        let mut stats = CallStats::new();
+        stats.add(&log, &events);
+
+        let calls = stats.get("ひぐお3").unwrap();
+        assert_eq!(calls.kyokus, 11);
+        assert_eq!(calls.pons, 4);
+        assert_eq!(calls.ankans, 1);
+        assert_eq!(calls.kitas, 6);
+        assert_eq!(calls.open_kyokus, 2);
+        assert_eq!(calls.open_win_rate(), Some(0.5));
+        assert_eq!(calls.closed_wins, 3);
+        assert_eq!(stats.get("nobody"), None);
+        assert_eq!(calls.fed, 6);
+        let fed: u32 = stats.iter().map(|(_, c)| c.fed).sum();
+        let pons: u32 = stats.iter().map(|(_, c)| c.pons).sum();
+        assert_eq!(fed, pons);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["mtk"]["kyokus"], 11);
+    }
+
+    #[test]
+    fn dealer_stats() {
+        let mut stats = DealerStats::new();
+        stats.add(&Fixture::Hanchan.log());
+        stats.add(&Fixture::AllLastRenchan.log());
+
+        // E2 with two repeats and S3 with one.
+        let tsukunen = stats.get("つくねん3").unwrap();
+        assert_eq!((tsukunen.turns, tsukunen.kyokus), (2, 5));
+        assert_eq!((tsukunen.renchans, tsukunen.longest_renchan), (3, 2));
+        assert_eq!(tsukunen.points, 9400);
+        let mtk = stats.get("mtk").unwrap();
+        assert_eq!((mtk.turns, mtk.renchans, mtk.points), (2, 0, -6700));
+        // Dealt in to Aoi on the third kyoku of the last dealer turn.
+        let chidori = stats.get("Chidori").unwrap();
+        assert_eq!(chidori.longest_renchan, 2);
+        assert_eq!(chidori.deal_ins_to.get("Aoi"), Some(&1));
+        assert_eq!(stats.get("nobody"), None);
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["Chidori"]["deal_ins"], 1);
+    }
+
+    #[test]
+    fn riichi_stats() {
+        let mut stats = RiichiStats::new();
+        for fixture in Fixture::ALL {
+            let log = fixture.log();
+            stats.add(&log, &tenhou_to_mjai(&log).unwrap()).unwrap();
+        }
+
+        let records = stats.records();
+        assert_eq!(records.len(), 7);
+        // Dealt in to a haneman of mtk, the stick gone as well.
+        let first = &records[0];
+        assert_eq!(
+            (first.player.as_str(), first.kyoku.as_str()),
+            ("ひぐお3", "E2-0")
+        );
+        assert_eq!((first.junme, first.wait_kinds), (8, 2));
+        assert_eq!((first.outcome, first.points), (Outcome::DealtIn, -8700));
+
+        let summaries = stats.summaries();
+        let higuo = summaries["ひぐお3"];
+        assert_eq!((higuo.riichis, higuo.wins, higuo.deal_ins), (4, 2, 2));
+        assert_eq!(higuo.ev(), Some(-1125.0));
+        assert_eq!(higuo.average_junme(), Some(7.75));
+        assert_eq!(summaries["つくねん3"].ev(), Some(6000.0));
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["players"]["mtk"]["win_rate"], 1.0);
+        assert_eq!(json["riichis"][0]["outcome"], "dealt_in");
+    }
+
+    #[test]
+    fn leaderboard() {
+        let mut leaderboard = Leaderboard::new(Ruleset::TENHOU_SANMA);
+        leaderboard.add(&Fixture::Hanchan.log()).unwrap();
+        leaderboard.add(&Fixture::Hanchan.log()).unwrap();
+
+        let ranked = leaderboard.ranked();
+        let names: Vec<_> = ranked.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["mtk", "つくねん3", "ひぐお3"]);
+        let mtk = ranked[0].1;
+        assert_eq!(mtk.games, 2);
+        assert_eq!(mtk.placements, [2, 0, 0]);
+        assert_eq!(mtk.points, 86200);
+        assert_eq!(mtk.average_placement(), Some(1.0));
+        assert_eq!(leaderboard.get("nobody"), None);
+
+        let mut csv = vec![];
+        leaderboard.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().nth(1), Some("1,mtk,2,2,0,0,1.00,86.2"));
+        assert_eq!(csv.lines().nth(3), Some("3,ひぐお3,2,0,0,2,3.00,-66.2"));
+
+        let mut markdown = vec![];
+        leaderboard.write_markdown(&mut markdown).unwrap();
+        let markdown = String::from_utf8(markdown).unwrap();
+        assert_eq!(
+            markdown.lines().nth(3),
+            Some("| 2 | つくねん3 | 2 | 0 | 2 | 0 | 2.00 | -20.0 |")
+        );
+
+        let json = serde_json::to_value(&leaderboard).unwrap();
+        assert_eq!(json[0]["name"], "mtk");
+        assert_eq!(json[0]["points"], 86.2);
+        assert_eq!(json[2]["rank"], 3);
+
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+    }
+
+    #[test]
+    fn progression() {
+        let json = Fixture::Hanchan.json();
+        let id = "2024030511gm-00b9-0000-e0c07689";
+        // The next day, and the day before at one dan lower.
+        let next = json.replace(id, "2024030612gm-00b9-0000-11111111");
+        let before = json
+            .replace(id, "2024030410gm-00b9-0000-22222222")
+            .replace("\"dan\":[\"七段\"", "\"dan\":[\"六段\"");
+        let mut progression = Progression::new("mtk".to_owned());
+        for json in [&next, json, &before] {
+            progression.add(&Log::from_json_str(json).unwrap());
+        }
+        progression.add(&Fixture::MultiRon.log());
+        // A game without dan points in between keeps the total.
+        let mut unscored =
+            Log::from_json_str(&json.replace(id, "2024030513gm-00b9-0000-33333333")).unwrap();
+        unscored.game_length = crate::tenhou::GameLength::Ikkyoku;
+        progression.add(&unscored);
+
+        let timeline = progression.timeline();
+        let dans: Vec<_> = timeline.iter().map(|g| g.dan.as_deref().unwrap()).collect();
+        assert_eq!(dans, ["六段", "七段", "七段", "七段"]);
+        let totals: Vec<_> = timeline.iter().map(|g| g.dan_points_total).collect();
+        assert_eq!(totals, [Some(85), Some(85), Some(85), Some(170)]);
+        assert_eq!(timeline[2].dan_points, None);
+        assert_eq!(
+            timeline[1].started_at.as_deref(),
+            Some("2024-03-05T11:00:00+09:00")
+        );
+
+        let mut csv = vec![];
+        progression.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(2),
+            Some("2024-03-05T11:00:00+09:00,2024030511gm-00b9-0000-e0c07689,1,七段,85,85,2221.90,+6.41")
+        );
+        let json = serde_json::to_value(&progression).unwrap();
+        assert_eq!(json[3]["dan_points_total"], 170);
+    }
+
+    #[test]
+    fn sessions() {
+        let add = |sessions: &mut Sessions| {
+            for fixture in Fixture::ALL {
+                sessions.add(&fixture.log()).unwrap();
+            }
+        };
+        let mut sessions = Sessions::new(Ruleset::TENHOU_SANMA, TimeDelta::hours(2));
+        add(&mut sessions);
+        let played = sessions.sessions();
+        // The hanchan, then three games of Aoi, Botan and Chidori an hour
+        // apart.
+        assert_eq!(played.len(), 2);
+        assert_eq!(played[1].game_ids.len(), 3);
+        assert_eq!(played[1].last_started_at, "2024-04-01T14:00:00+09:00");
+        let mtk = &played[0].records[0];
+        assert_eq!(
+            (mtk.name.as_str(), mtk.trailed, mtk.comebacks),
+            ("mtk", 1, 1)
+        );
+        assert_eq!(mtk.comeback_rate(), Some(1.0));
+        let [aoi, botan, chidori] = &played[1].records;
+        assert_eq!(aoi.placements, [1, 1, 1]);
+        assert_eq!(chidori.average_placement_after_last(), Some(1.0));
+        assert_eq!(botan.after_last, [0; SEATS]);
+        assert_eq!(botan.comeback_rate(), Some(0.0));
+
+        let mut csv = vec![];
+        sessions.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(6),
+            Some("2,2024-04-01T12:00:00+09:00,3,Chidori,2,0,1,45.6,0,0,1.00")
+        );
+        let json = serde_json::to_value(&sessions).unwrap();
+        assert_eq!(json[1]["records"][0]["name"], "Aoi");
+
+        // Too short a gap for games an hour apart.
+        let mut sessions = Sessions::new(Ruleset::TENHOU_SANMA, TimeDelta::minutes(30));
+        add(&mut sessions);
+        assert_eq!(sessions.sessions().len(), 4);
+
+        // Two guests of the same name keep a record each.
+        let mut log = Fixture::Hanchan.log();
+        log.names[1] = "NoName".to_owned();
+        log.names[2] = "NoName".to_owned();
+        let mut sessions = Sessions::new(Ruleset::TENHOU_SANMA, TimeDelta::hours(2));
+        sessions.add(&log).unwrap();
+        let played = sessions.sessions();
+        for record in &played[0].records {
+            assert_eq!(record.placements.iter().sum::<u32>(), 1, "{record:?}");
+        }
+    }
+
+    #[test]
+    fn head_to_head() {
+        let names = ["mtk".to_owned(), "つくねん3".to_owned()];
+        let mut h2h = HeadToHead::new(names);
+        h2h.add(&Fixture::Hanchan.log()).unwrap();
+        h2h.add(&Fixture::MultiRon.log()).unwrap();
+        assert_eq!(h2h.games, 1);
+
+        let [mtk, tsukunen] = h2h.records;
+        assert_eq!(mtk.placements, [1, 0, 0]);
+        assert_eq!(mtk.ahead, 1);
+        assert_eq!(tsukunen.ahead, 0);
+        assert_eq!(tsukunen.average_placement(), Some(2.0));
+        // A haneman of mtk in the last kyoku, and a 2900 with a honba of
+        // つくねん3 in the third.
+        assert_eq!((mtk.rons, mtk.points), (1, 12200));
+        assert_eq!((tsukunen.rons, tsukunen.points), (1, 3100));
+
+        let mut csv = vec![];
+        h2h.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().nth(1), Some("mtk,1,1,1.00,1,12200"));
+    }
+
+    #[test]
+    fn think_times() {
+        let log = Fixture::Hanchan.log();
+        let mut game = crate::ir::Tenhou6::default().convert(&log).unwrap();
+        let mut times = ThinkTimes::new();
+        times.add(&game);
+        assert_eq!((times.games, times.timed_games), (1, 0));
+        assert_eq!(times.get("mtk", Decision::Discard), None);
+        let json = serde_json::to_value(&times).unwrap();
+        assert!(json.get("players").is_none(), "{json}");
+
+        game.synthesize_timestamps(500);
+        // The first discard of the game took two seconds.
+        let first = game
+            .events
+            .iter()
+            .position(|e| matches!(e, Event::Dahai { .. }))
+            .unwrap();
+        for timestamp in &mut game.timestamps.as_mut().unwrap()[first..] {
+            *timestamp += 1500;
+        }
+        times.add(&game);
+        assert_eq!((times.games, times.timed_games), (2, 1));
+        let discards = times.get(&log.names[0], Decision::Discard).unwrap();
+        let dahais = game
+            .events
+            .iter()
+            .filter(|e| matches!(e, Event::Dahai { actor: 0, .. }))
+            .count();
+        assert_eq!(discards.count(), dahais);
+        assert_eq!((discards.median(), discards.max()), (Some(500), Some(2000)));
+        assert!(discards.mean().unwrap() > 500.0);
+        assert!(times.get("mtk", Decision::Win).is_some());
+        let json = serde_json::to_value(&times).unwrap();
+        assert_eq!(json["players"]["mtk"]["discard"]["max_ms"], 2000);
+    }
+
+    #[test]
+    fn point_flow() {
+        let mut flows = PointFlows::new();
+        for fixture in Fixture::ALL {
+            flows.add(&fixture.log());
+        }
+        assert_eq!(flows.games().len(), Fixture::ALL.len());
+        // The rons of the head-to-head of mtk and つくねん3.
+        assert_eq!(flows.get("つくねん3", "mtk").ron, 12200);
+        assert_eq!(flows.get("mtk", "つくねん3").ron, 3100);
+        assert_eq!(flows.get("mtk", "Aoi"), Flow::default());
+
+        // Everything the winners got but the riichi sticks.
+        let log = Fixture::Hanchan.log();
+        let game = &flows.games()[0];
+        assert_eq!(game.names[0], "mtk");
+        let paid: Points = game.matrix.iter().flatten().map(Flow::total).sum();
+        let won: Points = log
+            .kyokus
+            .iter()
+            .flat_map(Kyoku::horas)
+            .map(|h| h.score_deltas[usize::from(h.who)])
+            .sum();
+        let sticks: Points = log
+            .kyokus
+            .iter()
+            .flat_map(Kyoku::horas)
+            .map(|h| h.score_deltas.iter().sum::<Points>())
+            .sum();
+        assert_eq!(paid, won - sticks);
+
+        let mut csv = vec![];
+        flows.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().next(),
+            Some("payer,receiver,ron,tsumo,total,net")
+        );
+        assert!(
+            csv.contains("\nつくねん3,mtk,12200,8200,20400,15200\n"),
+            "{csv}"
+        );
+    }
+}
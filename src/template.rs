@@ -0,0 +1,209 @@
+use crate::tenhou::Log;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A path template such as `{date}/{game_id}.{format}`, rendered per log to
+/// decide where its output goes.
+///
+/// Supported placeholders are `{game_id}`, `{date}` (`YYYY-MM-DD`, taken from
+/// the game ID), `{lobby}`, `{name0}` to `{name3}`, `{stem}` (the input file
+/// name without extension) and `{format}`. Values that are not available in
+/// the log are rendered as `unknown`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    GameId,
+    Date,
+    Lobby,
+    Name(usize),
+    Stem,
+    Format,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug)]
+pub enum TemplateError {
+    UnknownPlaceholder(String),
+    Unclosed(usize),
+    Empty,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownPlaceholder(name) => write!(f, "unknown placeholder: {{{name}}}"),
+            Self::Unclosed(at) => write!(f, "unclosed placeholder at byte {at}"),
+            Self::Empty => f.write_str("empty template"),
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+/// Values a template is rendered with besides the log itself.
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateContext<'a> {
+    pub stem: &'a str,
+    pub format: &'a str,
+}
+
+impl FromStr for OutputTemplate {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(TemplateError::Empty);
+        }
+
+        let mut pieces = vec![];
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                pieces.push(Piece::Literal(rest[..start].to_owned()));
+            }
+            let len = rest[start..]
+                .find('}')
+                .ok_or(TemplateError::Unclosed(s.len() - rest.len() + start))?;
+            let name = &rest[start + 1..start + len];
+            let placeholder = match name {
+                "game_id" => Placeholder::GameId,
+                "date" => Placeholder::Date,
+                "lobby" => Placeholder::Lobby,
+                "name0" => Placeholder::Name(0),
+                "name1" => Placeholder::Name(1),
+                "name2" => Placeholder::Name(2),
+                "name3" => Placeholder::Name(3),
+                "stem" => Placeholder::Stem,
+                "format" => Placeholder::Format,
+                _ => return Err(TemplateError::UnknownPlaceholder(name.to_owned())),
+            };
+            pieces.push(Piece::Placeholder(placeholder));
+            rest = &rest[start + len + 1..];
+        }
+        if !rest.is_empty() {
+            pieces.push(Piece::Literal(rest.to_owned()));
+        }
+
+        Ok(Self { pieces })
+    }
+}
+
+impl OutputTemplate {
+    /// Render the template into a relative path.
+    ///
+    /// Substituted values are sanitized so that they can never introduce
+    /// extra path components.
+    #[must_use]
+    pub fn render(&self, log: &Log, ctx: TemplateContext<'_>) -> PathBuf {
+        let mut path = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(s) => path.push_str(s),
+                Piece::Placeholder(p) => {
+                    let value = match *p {
+                        Placeholder::GameId => log.game_id.clone(),
+                        Placeholder::Date => log
+                            .started_at()
+                            .map(|time| time.format("%Y-%m-%d").to_string()),
+                        Placeholder::Lobby => log.lobby.map(|l| l.to_string()),
+                        Placeholder::Name(i) => {
+                            Some(log.names[i].clone()).filter(|n| !n.is_empty())
+                        }
+                        Placeholder::Stem => Some(ctx.stem.to_owned()),
+                        Placeholder::Format => Some(ctx.format.to_owned()),
+                    };
+                    path.push_str(&sanitize(value.as_deref().unwrap_or("unknown")));
+                }
+            }
+        }
+        PathBuf::from(path)
+    }
+}
+
+fn sanitize(value: &str) -> String {
+    let s: String = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    if s == "." || s == ".." {
+        "_".to_owned()
+    } else {
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tenhou::GameLength;
+
+    fn log() -> Log {
+        Log {
+            version: None,
+            game_id: Some("2024030511gm-00b9-0000-e0c07689".to_owned()),
+            lobby: Some(1234),
+            names: [
+                "mtk".to_owned(),
+                "a/b".to_owned(),
+                "..".to_owned(),
+                String::new(),
+            ],
+            rule_disp: "三鳳南喰赤".to_owned(),
+            rule_flags: None,
+            ratingc: None,
+            dan: None,
+            rate: None,
+            game_length: GameLength::Hanchan,
+            has_aka: true,
+            kyokus: vec![],
+            final_results: None,
+            connections: vec![],
+            repair: None,
+        }
+    }
+
+    #[test]
+    fn render() {
+        let ctx = TemplateContext {
+            stem: "input",
+            format: "mjai",
+        };
+
+        let t: OutputTemplate = "{date}/{game_id}.{format}".parse().unwrap();
+        assert_eq!(
+            t.render(&log(), ctx),
+            PathBuf::from("2024-03-05/2024030511gm-00b9-0000-e0c07689.mjai"),
+        );
+
+        let t: OutputTemplate = "L{lobby}/{name0}-{name1}-{name2}-{name3}/{stem}.json"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            t.render(&log(), ctx),
+            PathBuf::from("L1234/mtk-a_b-_-unknown/input.json"),
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        "{nope}".parse::<OutputTemplate>().unwrap_err();
+        "{date".parse::<OutputTemplate>().unwrap_err();
+        "".parse::<OutputTemplate>().unwrap_err();
+        "plain.json".parse::<OutputTemplate>().unwrap();
+    }
+}
@@ -0,0 +1,374 @@
+//! The items of the action tables of a kyoku, "配牌", "取" and "出", and
+//! their parsing, which only need `alloc`.
+
+use super::TenhouTile;
+use crate::{Tile, TileError};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use core::fmt;
+
+#[cfg(feature = "serde")]
+use serde::de::{self, Visitor};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use smallvec::SmallVec;
+
+/// An item corresponding to each elements in "配牌", "取" and "出".
+///
+/// Deserialization is hand-written rather than `#[serde(untagged)]`, which
+/// would buffer every item before trying each variant in turn.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum ActionItem<'a> {
+    Tile(Tile),
+    /// The tile just drawn, written as `60`; [`Turn::discarded_tile`] tells
+    /// which tile it is.
+    ///
+    Tsumogiri,
+    /// A riichi declared with the discard of the tile, `None` for the tile
+    /// just drawn, written as e.g. `r35` or `r60`.
+    Riichi(Option<Tile>),
+    /// Borrowed from the JSON while it is read, see [`Self::parse`].
+    Naki(Cow<'a, str>),
+}
+
+/// A discard of an action table, riichi declaration or not. See
+/// [`ActionItem::discard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Discard {
+    /// `None` for tsumogiri, the tile just drawn.
+    pub pai: Option<Tile>,
+    pub riichi_declared: bool,
+}
+
+impl Discard {
+    #[must_use]
+    pub const fn is_tsumogiri(&self) -> bool {
+        self.pai.is_none()
+    }
+}
+
+impl ActionItem<'_> {
+    /// The discard this item is, `None` for kakan, ankan and nukidora, which
+    /// take the place of a discard, and for chi, pon and daiminkan.
+    #[must_use]
+    pub const fn discard(&self) -> Option<Discard> {
+        let (pai, riichi_declared) = match *self {
+            Self::Tile(pai) => (Some(pai), false),
+            Self::Tsumogiri => (None, false),
+            Self::Riichi(pai) => (pai, true),
+            Self::Naki(_) => return None,
+        };
+        Some(Discard {
+            pai,
+            riichi_declared,
+        })
+    }
+
+    /// Whether this discard is of the tile just drawn, which tenhou writes as
+    /// `60`, or `r60` for a riichi, instead of the tile.
+    #[must_use]
+    pub const fn is_tsumogiri(&self) -> bool {
+        matches!(self, Self::Tsumogiri | Self::Riichi(None))
+    }
+
+    #[must_use]
+    pub const fn is_riichi(&self) -> bool {
+        matches!(self, Self::Riichi(_))
+    }
+
+    /// The item with its naki string, if any, copied out of the JSON.
+    #[must_use]
+    pub fn into_owned(self) -> ActionItem<'static> {
+        match self {
+            Self::Tile(pai) => ActionItem::Tile(pai),
+            Self::Tsumogiri => ActionItem::Tsumogiri,
+            Self::Riichi(pai) => ActionItem::Riichi(pai),
+            Self::Naki(naki) => ActionItem::Naki(Cow::Owned(naki.into_owned())),
+        }
+    }
+}
+
+/// What tenhou writes in place of the tile for tsumogiri.
+const TSUMOGIRI_CODE: u8 = 60;
+
+#[cfg(feature = "serde")]
+impl Serialize for ActionItem<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            &Self::Tile(tile) => TenhouTile::from(tile).serialize(serializer),
+            Self::Tsumogiri => serializer.serialize_u8(TSUMOGIRI_CODE),
+            &Self::Riichi(pai) => {
+                let code = pai.map_or(TSUMOGIRI_CODE, |pai| TenhouTile::from(pai) as u8);
+                serializer.collect_str(&format_args!("r{code}"))
+            }
+            Self::Naki(naki) => serializer.serialize_str(naki),
+        }
+    }
+}
+
+impl fmt::Display for ActionItem<'_> {
+    /// The tile in mjai notation, `tsumogiri`, the riichi discard or the naki
+    /// string as in the log, e.g. `5pr`, `tsumogiri`, `riichi 5s` or
+    /// `p474747`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tile(tile) => write!(f, "{tile}"),
+            Self::Tsumogiri => f.write_str("tsumogiri"),
+            Self::Riichi(Some(tile)) => write!(f, "riichi {tile}"),
+            Self::Riichi(None) => f.write_str("riichi tsumogiri"),
+            Self::Naki(naki) => f.write_str(naki),
+        }
+    }
+}
+
+impl fmt::Debug for ActionItem<'_> {
+    /// As [`fmt::Display`], so that action tables print as
+    /// `[5pr, tsumogiri, p474747]` rather than `[Tile(5pr), Tsumogiri,
+    /// Naki("p474747")]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<'a> ActionItem<'a> {
+    /// The item a number of an action table stands for: `60` for tsumogiri,
+    /// otherwise the tile of a tenhou tile code.
+    ///
+    /// # Errors
+    ///
+    /// Fails for numbers that are neither.
+    pub fn from_code(code: u8) -> Result<Self, TileError> {
+        if code == TSUMOGIRI_CODE {
+            return Ok(Self::Tsumogiri);
+        }
+        TenhouTile::from_code(code).map(|pai| Self::Tile(pai.into()))
+    }
+
+    /// The item a string of an action table stands for: a riichi such as
+    /// `r35`, or `r60` for one with the tile just drawn, and otherwise a
+    /// naki string, which is borrowed from `s`.
+    ///
+    /// # Errors
+    ///
+    /// Fails for riichi strings without a valid tile code.
+    pub fn parse(s: &'a str) -> Result<Self, TileError> {
+        const CONTEXT: &str = "tenhou tile code in a riichi";
+        let Some(code) = s.strip_prefix('r') else {
+            return Ok(Self::Naki(Cow::Borrowed(s)));
+        };
+        let code: u8 = code.parse().map_err(|_| TileError::string(code, CONTEXT))?;
+        if code == TSUMOGIRI_CODE {
+            return Ok(Self::Riichi(None));
+        }
+        TenhouTile::from_code(code)
+            .map(|pai| Self::Riichi(Some(pai.into())))
+            .map_err(|e| e.with_context(CONTEXT))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de: 'a, 'a> Deserialize<'de> for ActionItem<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ActionItemVisitor;
+
+        impl<'de> Visitor<'de> for ActionItemVisitor {
+            type Value = ActionItem<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a tile number, 60 or a naki string")
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                let n = u8::try_from(v)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))?;
+                ActionItem::from_code(n).map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                let v = u64::try_from(v)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))?;
+                self.visit_u64(v)
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                ActionItem::parse(v).map_err(E::custom)
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                ActionItem::parse(v)
+                    .map(ActionItem::into_owned)
+                    .map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(ActionItemVisitor)
+    }
+}
+
+/// A group of "配牌", "取" and "出", describing a player's
+/// gaming status and actions throughout a kyoku.
+///
+/// The haipai is stored inline and takes and discards are sized exactly, as
+/// corpora of many thousands of games are often held in memory at once.
+#[derive(Debug, Clone)]
+pub struct ActionTable {
+    pub haipai: SmallVec<[Tile; 13]>,
+    pub takes: Box<[ActionItem<'static>]>,
+    pub discards: Box<[ActionItem<'static>]>,
+}
+
+/// A take and the discard that follows it, see [`ActionTable::turns`].
+#[derive(Debug, Clone, Copy)]
+pub struct Turn<'a> {
+    /// A draw, or a chi, pon or daiminkan.
+    pub take: &'a ActionItem<'static>,
+    /// A discard, riichi, kakan, ankan or nukidora. `None` after a
+    /// daiminkan, whose rinshan draw is the next turn, and after the last
+    /// take of a kyoku won by tsumo or aborted.
+    pub discard: Option<&'a ActionItem<'static>>,
+}
+
+impl Turn<'_> {
+    /// `Some(true)` if the discard is tsumogiri, of the tile just drawn,
+    /// `Some(false)` if it is tedashi, from the hand, and `None` for turns
+    /// without a discard or ending in a kan or nukidora.
+    #[must_use]
+    pub fn tsumogiri(&self) -> Option<bool> {
+        self.discard?
+            .discard()
+            .map(|discard| discard.is_tsumogiri())
+    }
+
+    /// The tile discarded, the tile drawn for tsumogiri, `None` for turns
+    /// without a discard or ending in a kan or nukidora.
+    #[must_use]
+    pub fn discarded_tile(&self) -> Option<Tile> {
+        match self.discard?.discard()?.pai {
+            Some(pai) => Some(pai),
+            None => match *self.take {
+                ActionItem::Tile(pai) => Some(pai),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl ActionTable {
+    /// Pair every take with the discard that follows it.
+    ///
+    /// Zipping `takes` with `discards` only lines up until the first
+    /// daiminkan, which has no discard of its own; tenhou writes a `0` in
+    /// its place, which is skipped, other sources nothing. Kakan, ankan and
+    /// nukidora do take the place of a discard, and the draw after them is a
+    /// turn of its own.
+    pub fn turns(&self) -> impl Iterator<Item = Turn<'_>> {
+        let mut discards = self.discards.iter();
+        self.takes.iter().map(move |take| {
+            let daiminkan = matches!(take, ActionItem::Naki(naki) if naki.contains('m'));
+            let discard = if daiminkan {
+                if let Some(ActionItem::Tile(pai)) = discards.as_slice().first() {
+                    if pai.is_unknown() {
+                        discards.next();
+                    }
+                }
+                None
+            } else {
+                discards.next()
+            };
+            Turn { take, discard }
+        })
+    }
+
+    /// The index into `discards` of the riichi declaration, if any.
+    #[must_use]
+    pub fn riichi_index(&self) -> Option<usize> {
+        self.discards.iter().position(ActionItem::is_riichi)
+    }
+
+    /// Whether riichi was declared on the very first discard, a double
+    /// riichi.
+    #[must_use]
+    pub fn is_double_riichi(&self) -> bool {
+        self.riichi_index() == Some(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::t;
+
+    #[test]
+    fn parse() {
+        assert_eq!(ActionItem::from_code(52), Ok(ActionItem::Tile(t!(5pr))));
+        assert_eq!(ActionItem::from_code(60), Ok(ActionItem::Tsumogiri));
+        ActionItem::from_code(99).unwrap_err();
+        assert_eq!(
+            ActionItem::parse("r35"),
+            Ok(ActionItem::Riichi(Some(t!(5s))))
+        );
+        assert_eq!(ActionItem::parse("r60"), Ok(ActionItem::Riichi(None)));
+        let naki = ActionItem::parse("p474747").unwrap();
+        assert!(matches!(naki, ActionItem::Naki(Cow::Borrowed("p474747"))));
+        ActionItem::parse("r99").unwrap_err();
+
+        assert_eq!(core::mem::size_of::<ActionItem<'_>>(), 24);
+    }
+
+    #[test]
+    fn serde() {
+        let items: Vec<ActionItem<'_>> = serde_json::from_str(r#"[52,60,"p474747"]"#).unwrap();
+        assert!(matches!(items[0], ActionItem::Tile(pai) if pai == crate::t!(5pr)));
+        assert!(matches!(items[1], ActionItem::Tsumogiri));
+        // Borrowed from the JSON, unless it has to be unescaped.
+        assert!(matches!(
+            &items[2],
+            ActionItem::Naki(Cow::Borrowed("p474747"))
+        ));
+        let item: ActionItem<'_> = serde_json::from_str(r#""p4\u00374747""#).unwrap();
+        assert!(matches!(item, ActionItem::Naki(Cow::Owned(naki)) if naki == "p474747"));
+        assert!(!items[0].is_tsumogiri());
+        assert!(items[1].is_tsumogiri());
+        assert_eq!(
+            serde_json::to_string(&items).unwrap(),
+            r#"[52,60,"p474747"]"#
+        );
+
+        let items: Vec<ActionItem<'_>> = serde_json::from_str(r#"["r35","r60","r52"]"#).unwrap();
+        assert_eq!(
+            items,
+            [
+                ActionItem::Riichi(Some(crate::t!(5s))),
+                ActionItem::Riichi(None),
+                ActionItem::Riichi(Some(crate::t!(5pr))),
+            ]
+        );
+        let discard = items[0].discard().unwrap();
+        assert!(discard.riichi_declared && !discard.is_tsumogiri());
+        assert!(items[1].is_tsumogiri() && items[1].is_riichi());
+        assert_eq!(
+            serde_json::to_string(&items).unwrap(),
+            r#"["r35","r60","r52"]"#
+        );
+        let err = serde_json::from_str::<ActionItem<'_>>(r#""r99""#).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("not a valid tenhou tile code in a riichi: 99"),
+            "{err}"
+        );
+        serde_json::from_str::<ActionItem<'_>>(r#""rx""#).unwrap_err();
+
+        serde_json::from_str::<ActionItem<'_>>("-1").unwrap_err();
+        serde_json::from_str::<ActionItem<'_>>("256").unwrap_err();
+        let err = serde_json::from_str::<ActionItem<'_>>("99").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("not a valid tenhou tile code: 99"),
+            "{err}"
+        );
+        serde_json::from_str::<ActionItem<'_>>("[]").unwrap_err();
+    }
+}
@@ -0,0 +1,161 @@
+//! A canonical form of raw logs, so that logs of the same game from
+//! different scrapers and exporters serialize to the same bytes.
+
+use super::compat::{self, Version};
+use super::json_scheme::{HoraField, RawLog, ResultItem};
+use super::log::TenhouParseError;
+use super::TenhouTile;
+
+use std::borrow::Cow;
+
+use serde::Deserialize;
+use serde_json::{self as json, Value};
+
+impl<'a> RawLog<'a> {
+    /// Parse a raw log, borrowing its text from `json_string`, and bring
+    /// older versions into the current schema.
+    ///
+    /// Logs in the current schema, which are most of them, parse directly
+    /// without building a `Value` tree first. Only those that fail to and
+    /// that [`compat::normalize`] patches are read a second time; the error
+    /// of the first read is kept for the others.
+    pub fn from_json_str(json_string: &'a str) -> Result<Self, TenhouParseError> {
+        let err = match json::from_str(json_string) {
+            Ok(raw_log) => return Ok(raw_log),
+            Err(err) if err.is_syntax() || err.is_eof() => return Err(err.into()),
+            Err(err) => err,
+        };
+        let mut value: Value = json::from_str(json_string)?;
+        if !compat::normalize(&mut value) {
+            return Err(err.into());
+        }
+        Ok(Self::deserialize(value)?)
+    }
+
+    /// Rewrite the log in its canonical form:
+    ///
+    /// - `ver` is the current version, the schema being normalized on parse;
+    /// - haipai are sorted the way tenhou sorts them, aka fives right after
+    ///   the plain ones;
+    /// - a combined `aka` count in the rules is spread over `aka51` to
+    ///   `aka53`;
+    /// - the rule string and the strings of results are trimmed.
+    ///
+    /// Keys the crate does not know are kept as they are, see
+    /// [`Self::apply_unknown_keys`].
+    pub fn canonicalize(&mut self) {
+        self.ver = Version::CURRENT.to_string().parse().ok();
+
+        for kyoku in &mut self.logs {
+            for haipai in [
+                &mut kyoku.haipai_0,
+                &mut kyoku.haipai_1,
+                &mut kyoku.haipai_2,
+                &mut kyoku.haipai_3,
+            ] {
+                haipai.sort_by_key(|&tile| TenhouTile::from(tile).sort_key());
+            }
+            for item in &mut kyoku.results {
+                match item {
+                    ResultItem::Status(status) => trim(status),
+                    ResultItem::HoraDetail(detail) => {
+                        for field in detail {
+                            if let HoraField::Text(text) = field {
+                                trim(text);
+                            }
+                        }
+                    }
+                    ResultItem::ScoreDeltas(_) => (),
+                }
+            }
+        }
+
+        let rule = &mut self.rule;
+        if rule.aka > 0 && rule.aka51 + rule.aka52 + rule.aka53 == 0 {
+            rule.aka51 = rule.aka;
+            rule.aka52 = rule.aka;
+            rule.aka53 = rule.aka;
+        }
+        rule.aka = 0;
+        trim(&mut rule.disp);
+    }
+}
+
+impl RawLog<'_> {
+    /// The log as JSON without the empty fourth seat: three names, and three
+    /// scores, score deltas and action tables per kyoku, the layout of early
+    /// sanma logs that [`Self::from_json_str`] reads back.
+    ///
+    /// Only logs that pass [`super::Log::check_fourth_seat`] lose nothing.
+    #[must_use]
+    pub fn to_three_seats(&self) -> Value {
+        let mut value = json::to_value(self).expect("raw logs are serializable");
+        compat::drop_fourth_seat(&mut value);
+        value
+    }
+}
+
+fn trim(s: &mut Cow<'_, str>) {
+    match s {
+        Cow::Borrowed(borrowed) => *borrowed = borrowed.trim(),
+        Cow::Owned(owned) => {
+            let trimmed = owned.trim();
+            if trimmed.len() != owned.len() {
+                *owned = trimmed.to_owned();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn canonicalize() {
+        let mut canonical = RawLog::from_json_str(Fixture::Hanchan.json()).unwrap();
+        canonical.canonicalize();
+        let expected = json::to_string(&canonical).unwrap();
+
+        // The same game as another scraper might write it.
+        let mut value: Value = json::from_str(Fixture::Hanchan.json()).unwrap();
+        value["ver"] = Value::from("2.1");
+        value["rule"] = json::json!({"disp": " 三鳳南喰赤 ", "aka": 1});
+        value["log"][0][4].as_array_mut().unwrap().reverse();
+        value["log"][0][16][0] = Value::from("和了 ");
+        value["log"][1][16][2][3] = Value::from("40符3飜7700点 ");
+        let json = value.to_string();
+        let mut raw = RawLog::from_json_str(&json).unwrap();
+        raw.canonicalize();
+        assert_eq!(json::to_string(&raw).unwrap(), expected);
+
+        // Canonical logs stay as they are.
+        raw.canonicalize();
+        assert_eq!(json::to_string(&raw).unwrap(), expected);
+        assert!(expected.contains(r#""ver":2.3"#));
+    }
+
+    #[test]
+    fn three_seats() {
+        let raw = RawLog::from_json_str(Fixture::Hanchan.json()).unwrap();
+        let value = raw.to_three_seats();
+        assert_eq!(value["name"].as_array().unwrap().len(), 3);
+        assert_eq!(value["log"][0].as_array().unwrap().len(), 14);
+        assert_eq!(value["log"][0][1].as_array().unwrap().len(), 3);
+
+        let json = value.to_string();
+        let back = RawLog::from_json_str(&json).unwrap();
+        assert_eq!(
+            json::to_value(&back).unwrap(),
+            json::to_value(&raw).unwrap()
+        );
+    }
+
+    #[test]
+    fn order() {
+        let mut tiles = crate::t![5pr, 6p, 5p, E, 1m, 4p];
+        tiles.sort_by_key(|&t| TenhouTile::from(t).sort_key());
+        assert_eq!(tiles, crate::t![1m, 4p, 5p, 5pr, 6p, E]);
+    }
+}
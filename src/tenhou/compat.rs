@@ -0,0 +1,188 @@
+use super::log::TenhouParseError;
+use std::fmt;
+
+use serde_json::Value;
+
+/// Number of entries in a kyoku with four action tables: meta, scoreboard,
+/// dora, ura, 4 * (haipai, takes, discards) and results.
+pub(super) const KYOKU_LEN: usize = 4 + 4 * 3 + 1;
+
+/// The `ver` field of a tenhou.net/6 log, e.g. `2.3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl Version {
+    /// The newest version this crate knows about.
+    pub const CURRENT: Self = Self { major: 2, minor: 3 };
+
+    /// Parse the `ver` field of a raw log. Logs written before the field was
+    /// introduced have none.
+    pub(super) fn detect(ver: Option<&Value>) -> Result<Option<Self>, TenhouParseError> {
+        let text = match ver {
+            None | Some(Value::Null) => return Ok(None),
+            Some(Value::Number(n)) => n.to_string(),
+            Some(Value::String(s)) => s.clone(),
+            Some(v) => return Err(TenhouParseError::InvalidVersion(v.to_string())),
+        };
+
+        let (major, minor) = text.split_once('.').unwrap_or((&text, "0"));
+        let version = match (major.parse(), minor.parse()) {
+            (Ok(major), Ok(minor)) => Self { major, minor },
+            _ => return Err(TenhouParseError::InvalidVersion(text)),
+        };
+        if version.major > Self::CURRENT.major {
+            return Err(TenhouParseError::UnsupportedVersion(version));
+        }
+
+        Ok(Some(version))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Rewrite a raw log in place so that it matches the current schema.
+///
+/// Older and third-party exports of sanma logs differ from what tenhou.net/6
+/// writes today in a few structural ways, all of which are patched here:
+///
+/// - `name`, scoreboards and score deltas with only three entries get the
+///   empty fourth seat appended;
+/// - kyokus with only three action tables get an empty fourth one;
+/// - a missing `rule` object is treated as an empty one.
+///
+/// Logs already in the current shape are left untouched, and `false` is
+/// returned for them.
+pub(super) fn normalize(raw: &mut Value) -> bool {
+    let Some(obj) = raw.as_object_mut() else {
+        return false;
+    };
+
+    let mut patched = false;
+    obj.entry("rule").or_insert_with(|| {
+        patched = true;
+        Value::Object(Default::default())
+    });
+    if let Some(Value::Array(names)) = obj.get_mut("name") {
+        patched |= pad_seats(names, Value::String(String::new()));
+    }
+
+    let Some(Value::Array(kyokus)) = obj.get_mut("log") else {
+        return patched;
+    };
+    for kyoku in kyokus.iter_mut().filter_map(Value::as_array_mut) {
+        if kyoku.len() == KYOKU_LEN - 3 {
+            // Insert the haipai, takes and discards of the fourth seat right
+            // before the results.
+            let at = kyoku.len() - 1;
+            for _ in 0..3 {
+                kyoku.insert(at, Value::Array(vec![]));
+            }
+            patched = true;
+        }
+
+        if let Some(Value::Array(scoreboard)) = kyoku.get_mut(1) {
+            patched |= pad_seats(scoreboard, Value::from(0));
+        }
+        if let Some(Value::Array(results)) = kyoku.last_mut() {
+            for item in results.iter_mut() {
+                // Score deltas are the only all-number arrays in results.
+                if let Value::Array(deltas) = item {
+                    if deltas.iter().all(Value::is_i64) {
+                        patched |= pad_seats(deltas, Value::from(0));
+                    }
+                }
+            }
+        }
+    }
+    patched
+}
+
+/// The reverse of [`normalize`]: leave the fourth seat out of `name`, the
+/// scoreboards, the score deltas and the action tables of a raw log in the
+/// current schema, which gives the layout of early sanma logs.
+pub(super) fn drop_fourth_seat(raw: &mut Value) {
+    if let Some(Value::Array(names)) = raw.get_mut("name") {
+        names.truncate(3);
+    }
+    let Some(Value::Array(kyokus)) = raw.get_mut("log") else {
+        return;
+    };
+    for kyoku in kyokus.iter_mut().filter_map(Value::as_array_mut) {
+        if kyoku.len() == KYOKU_LEN {
+            let at = KYOKU_LEN - 1 - 3;
+            kyoku.drain(at..at + 3);
+        }
+
+        if let Some(Value::Array(scoreboard)) = kyoku.get_mut(1) {
+            scoreboard.truncate(3);
+        }
+        if let Some(Value::Array(results)) = kyoku.last_mut() {
+            for item in results.iter_mut() {
+                if let Value::Array(deltas) = item {
+                    if deltas.iter().all(Value::is_i64) {
+                        deltas.truncate(3);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn pad_seats(seats: &mut Vec<Value>, empty: Value) -> bool {
+    let pad = seats.len() == 3;
+    if pad {
+        seats.push(empty);
+    }
+    pad
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn detect() {
+        assert_eq!(
+            Version::detect(Some(&json!(2.3))).unwrap(),
+            Some(Version::CURRENT),
+        );
+        assert_eq!(
+            Version::detect(Some(&json!("2.1"))).unwrap(),
+            Some(Version { major: 2, minor: 1 }),
+        );
+        assert_eq!(Version::detect(None).unwrap(), None);
+        Version::detect(Some(&json!(3.0))).unwrap_err();
+        Version::detect(Some(&json!("x"))).unwrap_err();
+    }
+
+    #[test]
+    fn pad_three_seats() {
+        let mut raw = json!({
+            "name": ["a", "b", "c"],
+            "log": [[
+                [0, 0, 0], [35000, 35000, 35000], [47], [],
+                [], [], [],
+                [], [], [],
+                [], [], [],
+                ["流局", [0, 0, 0]],
+            ]],
+        });
+        assert!(normalize(&mut raw));
+
+        assert_eq!(raw["name"], json!(["a", "b", "c", ""]));
+        assert_eq!(raw["rule"], json!({}));
+        let kyoku = raw["log"][0].as_array().unwrap();
+        assert_eq!(kyoku.len(), KYOKU_LEN);
+        assert_eq!(kyoku[1], json!([35000, 35000, 35000, 0]));
+        assert_eq!(kyoku[16], json!(["流局", [0, 0, 0, 0]]));
+        assert!(!normalize(&mut raw));
+    }
+}
@@ -0,0 +1,171 @@
+//! Detection of the same game appearing more than once in a corpus, e.g.
+//! after merging downloads from several sources.
+
+use super::log::{EndStatus, Log};
+use super::TenhouTile;
+use crate::cache::fingerprint;
+use crate::Tile;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use ahash::AHashSet;
+use serde_json::{json, Value};
+
+impl Log {
+    /// A hash of the deals and actions of every kyoku, which identifies a
+    /// game regardless of player names, the game ID and JSON formatting.
+    ///
+    /// Only stable within one build of this crate; see [`Self::fingerprint`]
+    /// for a hash to keep.
+    #[must_use]
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for kyoku in &self.kyokus {
+            kyoku.meta.hash(&mut hasher);
+            kyoku.scoreboard.hash(&mut hasher);
+            kyoku.dora_indicators.hash(&mut hasher);
+            for table in &kyoku.action_tables {
+                table.haipai.hash(&mut hasher);
+                table.takes.hash(&mut hasher);
+                table.discards.hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+
+    /// A hash of what happened in the game, in hex: the deals, actions,
+    /// indicators and results of every kyoku, which stays the same across
+    /// builds and platforms and whatever the source of the log. Player
+    /// names, the game ID, JSON formatting, the order of haipai, the
+    /// encoding of aka and three or four seat layouts do not change it.
+    #[must_use]
+    pub fn fingerprint(&self) -> String {
+        let codes = |tiles: &[Tile]| -> Vec<u8> {
+            tiles.iter().map(|&t| TenhouTile::from(t) as u8).collect()
+        };
+        let kyokus: Vec<Value> = self
+            .kyokus
+            .iter()
+            .map(|kyoku| {
+                let tables: Vec<_> = kyoku
+                    .action_tables
+                    .iter()
+                    .map(|table| {
+                        let mut haipai = table.haipai.to_vec();
+                        haipai.sort_by_key(|&t| TenhouTile::from(t).sort_key());
+                        json!([codes(&haipai), table.takes, table.discards])
+                    })
+                    .collect();
+                let results = match &kyoku.end_status {
+                    EndStatus::Hora { details } => {
+                        let details: Vec<_> = details
+                            .iter()
+                            .map(|d| json!([d.who, d.target, d.pao, d.score_deltas]))
+                            .collect();
+                        json!(["和了", details])
+                    }
+                    EndStatus::Ryukyoku {
+                        score_deltas,
+                        reason,
+                    } => json!([reason.status(), score_deltas]),
+                };
+                let meta = &kyoku.meta;
+                json!([
+                    [meta.kyoku_num, meta.honba, meta.kyotaku],
+                    kyoku.scoreboard,
+                    codes(&kyoku.dora_indicators),
+                    codes(kyoku.ura_indicators.as_deref().unwrap_or_default()),
+                    tables,
+                    results,
+                ])
+            })
+            .collect();
+        let content = serde_json::to_vec(&kyokus).expect("kyokus are serializable");
+        fingerprint(&content)
+    }
+}
+
+/// Remembers the games seen so far.
+///
+/// A log is a duplicate if its game ID or its [`Log::fingerprint`] has been
+/// seen before, so copies with and without a game ID are caught too.
+#[derive(Debug, Clone, Default)]
+pub struct Deduper {
+    game_ids: AHashSet<String>,
+    contents: AHashSet<String>,
+}
+
+impl Deduper {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `log`, returning whether it is the first time it is seen.
+    pub fn insert(&mut self, log: &Log) -> bool {
+        let new_id = log
+            .game_id
+            .as_ref()
+            .is_none_or(|id| self.game_ids.insert(id.clone()));
+        let new_content = self.contents.insert(log.fingerprint());
+        new_id && new_content
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testdata::Fixture;
+
+    const SAMPLE: &str = Fixture::Hanchan.json();
+
+    #[test]
+    fn dedupe() {
+        let log = Log::from_json_str(SAMPLE).unwrap();
+        let mut deduper = Deduper::new();
+        assert!(deduper.insert(&log));
+        assert!(!deduper.insert(&log));
+
+        // The same game from a source without IDs or names.
+        let mut anonymous = log.clone();
+        anonymous.game_id = None;
+        anonymous.names = Default::default();
+        assert!(!deduper.insert(&anonymous));
+
+        // Another game filed under the same ID.
+        let mut other = log.clone();
+        other.kyokus.pop();
+        assert_ne!(other.content_hash(), log.content_hash());
+        assert_ne!(other.fingerprint(), log.fingerprint());
+        assert!(!deduper.insert(&other));
+
+        other.kyokus.pop();
+        other.game_id = Some("2024030512gm-00b9-0000-00000000".to_owned());
+        assert!(deduper.insert(&other));
+    }
+
+    #[test]
+    fn fingerprint() {
+        let log = Log::from_json_str(SAMPLE).unwrap();
+        assert_eq!(log.fingerprint(), log.fingerprint());
+        assert_eq!(log.fingerprint().len(), 16);
+
+        // The same game reformatted, with haipai in another order, three
+        // seats and another name.
+        let mut value: Value = serde_json::from_str(SAMPLE).unwrap();
+        value["name"] = json!(["a", "b", "c"]);
+        for kyoku in value["log"].as_array_mut().unwrap() {
+            let kyoku = kyoku.as_array_mut().unwrap();
+            kyoku[4].as_array_mut().unwrap().reverse();
+            kyoku.drain(13..16);
+            let scoreboard = kyoku[1].as_array_mut().unwrap();
+            scoreboard.pop();
+        }
+        let other = Log::from_json_str(&serde_json::to_string_pretty(&value).unwrap()).unwrap();
+        assert_eq!(other.fingerprint(), log.fingerprint());
+
+        let mut other = log.clone();
+        other.kyokus[0].meta.honba += 1;
+        assert_ne!(other.fingerprint(), log.fingerprint());
+    }
+}
@@ -0,0 +1,238 @@
+//! Finding the JSON of a log in what users paste: viewer links with the log
+//! in a `#json=` fragment, HTML-escaped blobs and whole viewer pages.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractError {
+    NotFound,
+    InvalidPercentEncoding(usize),
+    NotUtf8,
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => f.write_str(
+                "no tenhou log found, expected its JSON, a link with `#json=` or a page with one",
+            ),
+            Self::InvalidPercentEncoding(at) => write!(
+                f,
+                "invalid percent-encoding at byte {at} of the `json=` fragment"
+            ),
+            Self::NotUtf8 => f.write_str("the `json=` fragment is not UTF-8 once decoded"),
+        }
+    }
+}
+
+impl Error for ExtractError {}
+
+/// How many layers of encoding are taken off, e.g. a page with an
+/// HTML-escaped link whose fragment is percent-encoded.
+const MAX_DEPTH: usize = 4;
+
+/// The JSON of the log in `input`, which is either that JSON, a link whose
+/// `json=` fragment holds it, percent-encoded or not, the same escaped for
+/// HTML, or a page with the log somewhere in it as an object with a `log`
+/// field. An object, as files of logs have, is returned as it is without
+/// looking further.
+///
+/// What is returned is not checked to be a valid log beyond that.
+pub fn extract_json(input: &str) -> Result<Cow<'_, str>, ExtractError> {
+    extract(input, 0)
+}
+
+fn extract(input: &str, depth: usize) -> Result<Cow<'_, str>, ExtractError> {
+    let s = input.trim_start_matches('\u{feff}').trim();
+    let object = s.strip_prefix('{').map(str::trim_start);
+    if object.is_some_and(|rest| rest.starts_with(['"', '}'])) {
+        return Ok(Cow::Borrowed(s));
+    }
+    if depth == MAX_DEPTH {
+        return Err(ExtractError::NotFound);
+    }
+
+    let mut error = ExtractError::NotFound;
+    match fragment(s).map(percent_decode) {
+        Some(Ok(decoded)) => {
+            if let Ok(json) = extract(&decoded, depth + 1) {
+                return Ok(Cow::Owned(json.into_owned()));
+            }
+        }
+        // A broken fragment says more than not finding anything.
+        Some(Err(err)) => error = err,
+        None => {}
+    }
+    if let Some(unescaped) = html_unescape(s) {
+        if let Ok(json) = extract(&unescaped, depth + 1) {
+            return Ok(Cow::Owned(json.into_owned()));
+        }
+    }
+    match embedded(s) {
+        Some(json) => Ok(Cow::Borrowed(json)),
+        None => Err(error),
+    }
+}
+
+/// The value of the first `json=` parameter of `s`, up to where a link in
+/// text or markup would end.
+fn fragment(s: &str) -> Option<&str> {
+    let start = s.find("json=")? + "json=".len();
+    let rest = &s[start..];
+    let end = rest
+        .find(|c: char| c == '&' || c == '"' || c == '\'' || c == '<' || c.is_whitespace())
+        .unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+fn percent_decode(s: &str) -> Result<String, ExtractError> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or(ExtractError::InvalidPercentEncoding(i))?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| ExtractError::NotUtf8)
+}
+
+/// `s` with its HTML character references replaced, if it has quotes
+/// escaped as JSON in HTML attributes and text has.
+fn html_unescape(s: &str) -> Option<String> {
+    if !["&quot;", "&#34;", "&#x22;"].iter().any(|q| s.contains(q)) {
+        return None;
+    }
+    let mut unescaped = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        unescaped.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let reference = rest
+            .get(1..rest.len().min(12))
+            .and_then(|r| r.find(';').map(|end| &r[..end]));
+        let c = reference.and_then(|r| match r {
+            "quot" => Some('"'),
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = match r.strip_prefix("#x").or_else(|| r.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => r.strip_prefix('#')?.parse().ok(),
+                };
+                code.and_then(char::from_u32)
+            }
+        });
+        match (c, reference) {
+            (Some(c), Some(r)) => {
+                unescaped.push(c);
+                rest = &rest[r.len() + 2..];
+            }
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    Some(unescaped)
+}
+
+/// The first JSON object in `s` with a `log` field, e.g. in a `<script>` or
+/// `<textarea>` of a viewer page.
+fn embedded(s: &str) -> Option<&str> {
+    #[derive(Deserialize)]
+    struct Probe {
+        #[serde(rename = "log")]
+        _log: IgnoredAny,
+    }
+
+    s.match_indices('{').find_map(|(start, _)| {
+        let rest = &s[start..];
+        if !rest[1..].trim_start().starts_with('"') {
+            return None;
+        }
+        let mut values = serde_json::Deserializer::from_str(rest).into_iter::<Probe>();
+        match values.next() {
+            Some(Ok(_)) => Some(&rest[..values.byte_offset()]),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tenhou::{Log, TenhouParseError};
+    use crate::testdata::Fixture;
+
+    const LOG: &str = r#"{"ref":"x","log":[[1,"a&b"]],"name":["甲","乙","丙",""]}"#;
+
+    #[test]
+    fn extract() {
+        assert_eq!(extract_json(LOG), Ok(Cow::Borrowed(LOG)));
+        let padded = format!("\u{feff}\n {LOG}\n");
+        assert_eq!(extract_json(&padded).unwrap(), LOG);
+
+        let link = format!("https://tenhou.net/6/#json={LOG}");
+        assert_eq!(extract_json(&link).unwrap(), LOG);
+        let encoded: String = LOG
+            .bytes()
+            .map(|b| match b {
+                b'a'..=b'z' | b'0'..=b'9' => char::from(b).to_string(),
+                b => format!("%{b:02X}"),
+            })
+            .collect();
+        let link = format!("https://tenhou.net/6/#json={encoded}&ts=3");
+        assert_eq!(extract_json(&link).unwrap(), LOG);
+
+        let escaped = LOG.replace('&', "&amp;").replace('"', "&quot;");
+        assert_eq!(extract_json(&escaped).unwrap(), LOG);
+        let page = format!(
+            "<html><style>p {{ color: red }}</style><script>var x = {{\"a\": 1}};\n\
+             var game = {LOG};</script><a href=\"/6/#json={encoded}\">view</a></html>"
+        );
+        assert_eq!(extract_json(&page).unwrap(), LOG);
+        let attribute = format!("<div data-log=\"{escaped}\"></div>");
+        assert_eq!(extract_json(&attribute).unwrap(), LOG);
+
+        assert_eq!(
+            extract_json("https://tenhou.net/6/"),
+            Err(ExtractError::NotFound)
+        );
+        assert_eq!(
+            extract_json("<p>{\"a\": 1}</p>"),
+            Err(ExtractError::NotFound)
+        );
+        assert_eq!(
+            extract_json("#json=%7B%2"),
+            Err(ExtractError::InvalidPercentEncoding(3)),
+        );
+        assert_eq!(extract_json("#json=%FF"), Err(ExtractError::NotUtf8));
+
+        // Into the error of parsing, for `?`.
+        let page = format!("<textarea>{}</textarea>", Fixture::Hanchan.json());
+        Log::from_json_str(&extract_json(&page).unwrap()).unwrap();
+        let parse = || -> Result<Log, TenhouParseError> { Log::from_json_str(&extract_json("")?) };
+        assert!(matches!(
+            parse(),
+            Err(TenhouParseError::Extract(ExtractError::NotFound))
+        ));
+    }
+}
@@ -0,0 +1,261 @@
+//! Information encoded in tenhou game IDs such as
+//! `2024030511gm-00b9-0000-e0c07689`.
+
+use super::log::{GameLength, Log};
+use super::rule_disp::RuleDisp;
+use super::rules::RuleError;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+
+/// Offset of Japan Standard Time, which game IDs are written in.
+const JST: i32 = 9 * 3600;
+
+/// The rules of a game, decoded from the hex field after `gm-` in its ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RuleFlags {
+    /// Played against humans rather than bots.
+    pub vs_human: bool,
+    pub aka: bool,
+    /// Open tanyao (喰断) is allowed.
+    pub kuitan: bool,
+    pub hanchan: bool,
+    pub sanma: bool,
+    pub speed: bool,
+    pub room: Room,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Room {
+    /// 一般
+    Ippan,
+    /// 上級
+    Joukyuu,
+    /// 特上
+    Tokujou,
+    /// 鳳凰
+    Houou,
+}
+
+impl Room {
+    /// The room a rule string such as `三鳳南喰赤` names, see
+    /// [`RuleDisp::parse`].
+    #[must_use]
+    pub fn from_disp(disp: &str) -> Option<Self> {
+        RuleDisp::parse(disp).room
+    }
+
+    /// The character of the room in rule strings, e.g. `鳳`.
+    #[must_use]
+    pub const fn kanji(self) -> char {
+        match self {
+            Self::Ippan => '般',
+            Self::Joukyuu => '上',
+            Self::Tokujou => '特',
+            Self::Houou => '鳳',
+        }
+    }
+}
+
+impl FromStr for Room {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ippan" | "般" => Ok(Self::Ippan),
+            "joukyuu" | "上" => Ok(Self::Joukyuu),
+            "tokujou" | "特" => Ok(Self::Tokujou),
+            "houou" | "鳳" => Ok(Self::Houou),
+            _ => Err(format!(
+                "unknown room {s:?}, expected \"ippan\", \"joukyuu\", \"tokujou\" or \"houou\""
+            )),
+        }
+    }
+}
+
+const VS_HUMAN: u16 = 0x01;
+const NO_AKA: u16 = 0x02;
+const NO_KUITAN: u16 = 0x04;
+const HANCHAN: u16 = 0x08;
+const SANMA: u16 = 0x10;
+const SPEED: u16 = 0x40;
+const ROOM: u16 = 0xa0;
+
+impl RuleFlags {
+    /// Decode the rule field of a game ID, e.g. `00b9` in
+    /// `2024030511gm-00b9-0000-e0c07689`.
+    #[must_use]
+    pub fn from_game_id(game_id: &str) -> Option<Self> {
+        let (_, rest) = game_id.split_once("gm-")?;
+        let hex = rest.get(..4)?;
+        Some(Self::from_bits(u16::from_str_radix(hex, 16).ok()?))
+    }
+
+    #[must_use]
+    pub const fn from_bits(bits: u16) -> Self {
+        let room = match bits & ROOM {
+            0x00 => Room::Ippan,
+            0x80 => Room::Joukyuu,
+            0x20 => Room::Tokujou,
+            _ => Room::Houou,
+        };
+        Self {
+            vs_human: bits & VS_HUMAN != 0,
+            aka: bits & NO_AKA == 0,
+            kuitan: bits & NO_KUITAN == 0,
+            hanchan: bits & HANCHAN != 0,
+            sanma: bits & SANMA != 0,
+            speed: bits & SPEED != 0,
+            room,
+        }
+    }
+
+    /// Read the flags a rule string such as `三鳳南喰赤` spells out, if it
+    /// tells every one of them, as tenhou's own do. `vs_human` is not part
+    /// of it and is taken from `self`.
+    fn with_disp(self, disp: &str) -> Option<Self> {
+        let rules = RuleDisp::parse(disp);
+        Some(Self {
+            vs_human: self.vs_human,
+            aka: rules.aka?,
+            kuitan: rules.kuitan?,
+            hanchan: rules.length? == GameLength::Hanchan,
+            sanma: rules.sanma?,
+            speed: rules.speed?,
+            room: rules.room?,
+        })
+    }
+}
+
+impl fmt::Display for RuleFlags {
+    /// Write the flags as tenhou does in `rule.disp`, e.g. `三鳳南喰赤`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let room = self.room.kanji();
+        let players = if self.sanma { '三' } else { '四' };
+        let length = if self.hanchan { '南' } else { '東' };
+        write!(f, "{players}{room}{length}")?;
+        for (set, c) in [(self.kuitan, '喰'), (self.aka, '赤'), (self.speed, '速')] {
+            if set {
+                write!(f, "{c}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The hour a game started, from the `2024030511` part of its ID.
+#[must_use]
+pub fn started_at(game_id: &str) -> Option<DateTime<FixedOffset>> {
+    let digits = game_id.get(..10)?;
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |range: Range<usize>| digits[range].parse::<u32>().ok();
+    // At most four digits, so always in range.
+    let year = field(0..4)? as i32;
+    let date = NaiveDate::from_ymd_opt(year, field(4..6)?, field(6..8)?)?;
+    let time = date.and_hms_opt(field(8..10)?, 0, 0)?;
+    FixedOffset::east_opt(JST)?
+        .from_local_datetime(&time)
+        .single()
+}
+
+impl Log {
+    /// The hour the game started in Japan time, if the log has a game ID.
+    #[must_use]
+    pub fn started_at(&self) -> Option<DateTime<FixedOffset>> {
+        self.game_id.as_deref().and_then(started_at)
+    }
+
+    /// The room the game was played in, from the rule string or else the
+    /// game ID. `None` for custom lobbies with rule strings of their own.
+    #[must_use]
+    pub fn room(&self) -> Option<Room> {
+        Room::from_disp(&self.rule_disp).or_else(|| self.rule_flags.map(|flags| flags.room))
+    }
+
+    /// Check that the rule string agrees with the rule flags in the game ID.
+    ///
+    /// Logs without flags and rule strings not in the form tenhou writes,
+    /// e.g. custom lobby names, pass.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn check_rule_flags(&self) -> Result<(), RuleError> {
+        let Some(flags) = self.rule_flags else {
+            return Ok(());
+        };
+        match flags.with_disp(&self.rule_disp) {
+            Some(disp_flags) if disp_flags != flags => Err(RuleError::RuleFlagsMismatch {
+                disp: self.rule_disp.clone(),
+                expected: flags.to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rule_flags() {
+        let flags = RuleFlags::from_game_id("2024030511gm-00b9-0000-e0c07689").unwrap();
+        assert_eq!(
+            flags,
+            RuleFlags {
+                vs_human: true,
+                aka: true,
+                kuitan: true,
+                hanchan: true,
+                sanma: true,
+                speed: false,
+                room: Room::Houou,
+            },
+        );
+        assert_eq!(flags.to_string(), "三鳳南喰赤");
+        assert_eq!(flags.with_disp("三鳳南喰赤"), Some(flags));
+        assert_eq!(flags.with_disp("Custom lobby"), None);
+
+        let flags = RuleFlags::from_bits(0x00d1);
+        assert_eq!(flags.to_string(), "三上東喰赤速");
+        assert_eq!(RuleFlags::from_bits(0x0017).to_string(), "三般東");
+
+        assert_eq!(
+            RuleFlags::from_game_id("2024030511gm-zzzz-0000-e0c07689"),
+            None
+        );
+        assert_eq!(RuleFlags::from_game_id("abc"), None);
+    }
+
+    #[test]
+    fn room() {
+        assert_eq!(Room::from_disp("三鳳南喰赤"), Some(Room::Houou));
+        assert_eq!(Room::from_disp("四特東喰赤"), Some(Room::Tokujou));
+        assert_eq!(Room::from_disp("三般南喰赤"), Some(Room::Ippan));
+        assert_eq!(Room::from_disp("Custom lobby"), None);
+        assert_eq!("houou".parse(), Ok(Room::Houou));
+        assert_eq!("上".parse(), Ok(Room::Joukyuu));
+        "phoenix".parse::<Room>().unwrap_err();
+
+        let mut log = Log::from_json_str(crate::testdata::Fixture::Hanchan.json()).unwrap();
+        assert_eq!(log.room(), Some(Room::Houou));
+        assert_eq!(log.ratingc.as_deref(), Some("PF3"));
+        log.rule_disp = "Custom lobby".to_owned();
+        assert_eq!(log.room(), Some(Room::Houou));
+        log.rule_flags = None;
+        assert_eq!(log.room(), None);
+    }
+
+    #[test]
+    fn timestamp() {
+        let time = started_at("2024030511gm-00b9-0000-e0c07689").unwrap();
+        assert_eq!(time.to_rfc3339(), "2024-03-05T11:00:00+09:00");
+
+        assert_eq!(started_at("2024023011gm-00b9-0000-e0c07689"), None);
+        assert_eq!(started_at("20240305gm-00b9-0000-e0c07689"), None);
+        assert_eq!(started_at("abc"), None);
+    }
+}
@@ -1,124 +1,460 @@
+use super::action::ActionItem;
+use super::log::TenhouParseError;
+use super::rule_disp::RuleDisp;
 use super::TenhouTile;
-use crate::{KyokuFilter, Tile};
+use crate::{Field, Fields, KyokuFilter, Points, Tile};
 
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Number, Value};
 use serde_tuple::{Deserialize_tuple as DeserializeTuple, Serialize_tuple as SerializeTuple};
-use serde_with::{serde_as, FromInto};
+use serde_with::{serde_as, BorrowCow, DefaultOnNull, FromInto};
 
+/// A tenhou.net/6 log as it is written. Its text, names and naki strings
+/// included, is borrowed from the JSON it is read from where it needs no
+/// unescaping; [`Self::into_owned`] copies it out.
+#[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RawLog {
-    #[serde(rename = "log")]
-    pub(super) logs: Vec<RawKyoku>,
+pub struct RawLog<'a> {
+    #[serde(rename = "log", borrow)]
+    pub(super) logs: Vec<RawKyoku<'a>>,
     #[serde(rename = "name")]
-    pub(super) names: [String; 4],
-    pub(super) rule: Rule,
+    #[serde_as(as = "[BorrowCow; 4]")]
+    pub(super) names: [Cow<'a, str>; 4],
+    #[serde(borrow)]
+    pub(super) rule: Rule<'a>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub(super) ratingc: Option<String>,
+    pub(super) ver: Option<Value>,
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<BorrowCow>")]
+    pub(super) game_id: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<BorrowCow>")]
+    pub(super) ratingc: Option<Cow<'a, str>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(super) lobby: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub(super) dan: Option<Vec<String>>,
+    #[serde_as(as = "Option<Vec<BorrowCow>>")]
+    pub(super) dan: Option<Vec<Cow<'a, str>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(super) rate: Option<Vec<f64>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub(super) sx: Option<Vec<String>>,
+    #[serde_as(as = "Option<Vec<BorrowCow>>")]
+    pub(super) sx: Option<Vec<Cow<'a, str>>>,
+    /// The final points and placement points of every seat, interleaved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) sc: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) connection: Option<Vec<Connection>>,
+    /// Keys the crate does not know, e.g. ones tenhou added since, kept so
+    /// that they are written back as they were.
+    #[serde(flatten)]
+    pub(super) extra: Map<String, Value>,
+}
+
+/// What to do with the keys of a log that [`RawLog`] does not know, see
+/// [`RawLog::apply_unknown_keys`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownKeys {
+    /// Keep them and write them back, the default.
+    #[default]
+    Keep,
+    /// Leave them out of what is written.
+    Drop,
+    /// Fail, e.g. to find logs of a newer format.
+    Deny,
+}
+
+impl FromStr for UnknownKeys {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "drop" => Ok(Self::Drop),
+            "deny" => Ok(Self::Deny),
+            _ => Err(format!(
+                "unknown policy {s:?}, expected \"keep\", \"drop\" or \"deny\""
+            )),
+        }
+    }
+}
+
+/// A seat losing or regaining its connection to the server, an item of the
+/// "connection" field. Tenhou plays the turns of a disconnected seat itself,
+/// discarding every tile it draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Connection {
+    /// `0` for a disconnect and `1` for a reconnect.
+    pub what: u8,
+    /// The index of the kyoku in the log.
+    #[serde(rename = "log")]
+    pub kyoku: usize,
+    #[serde(rename = "who")]
+    pub seat: u8,
+    /// The tags of the kyoku in the mjlog of the game before it, the
+    /// `INIT` starting the kyoku not counted: one per event of the kyoku in
+    /// mjai, as converted.
+    pub step: usize,
+}
+
+impl Connection {
+    #[must_use]
+    pub const fn is_disconnect(&self) -> bool {
+        self.what == 0
+    }
 }
 
 #[derive(Debug, Serialize)]
 pub struct RawPartialLog<'a> {
     #[serde(flatten)]
-    pub(super) parent: &'a RawLog,
+    pub(super) parent: &'a RawLog<'a>,
 
     #[serde(rename = "log")]
-    pub(super) logs: &'a [RawKyoku],
-}
-
-/// An item corresponding to each elements in "配牌", "取" and "出".
-#[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(untagged)]
-pub enum ActionItem {
-    Tile(#[serde_as(as = "FromInto<TenhouTile>")] Tile),
-    Tsumogiri(u8), // must be 60
-    Naki(String),
+    pub(super) logs: &'a [RawKyoku<'a>],
 }
 
+/// A kyoku of a [`RawLog`] as tenhou writes it, see [`RawLog::kyokus_mut`].
 #[serde_as]
 #[derive(Debug, Clone, SerializeTuple, DeserializeTuple)]
-pub(super) struct RawKyoku {
+pub struct RawKyoku<'a> {
     pub(super) meta: KyokuMeta,
-    pub(super) scoreboard: [i32; 4],
-    #[serde_as(as = "Vec<FromInto<TenhouTile>>")]
+    pub(super) scoreboard: [Points; 4],
+    // Some exporters write `null` instead of an empty array.
+    #[serde_as(as = "DefaultOnNull<Vec<FromInto<TenhouTile>>>")]
     pub(super) dora_indicators: Vec<Tile>,
-    #[serde_as(as = "Vec<FromInto<TenhouTile>>")]
+    #[serde_as(as = "DefaultOnNull<Vec<FromInto<TenhouTile>>>")]
     pub(super) ura_indicators: Vec<Tile>,
 
     #[serde_as(as = "Vec<FromInto<TenhouTile>>")]
     pub(super) haipai_0: Vec<Tile>,
-    pub(super) takes_0: Vec<ActionItem>,
-    pub(super) discards_0: Vec<ActionItem>,
+    #[serde(borrow)]
+    pub(super) takes_0: Vec<ActionItem<'a>>,
+    #[serde(borrow)]
+    pub(super) discards_0: Vec<ActionItem<'a>>,
 
     #[serde_as(as = "Vec<FromInto<TenhouTile>>")]
     pub(super) haipai_1: Vec<Tile>,
-    pub(super) takes_1: Vec<ActionItem>,
-    pub(super) discards_1: Vec<ActionItem>,
+    #[serde(borrow)]
+    pub(super) takes_1: Vec<ActionItem<'a>>,
+    #[serde(borrow)]
+    pub(super) discards_1: Vec<ActionItem<'a>>,
 
     #[serde_as(as = "Vec<FromInto<TenhouTile>>")]
     pub(super) haipai_2: Vec<Tile>,
-    pub(super) takes_2: Vec<ActionItem>,
-    pub(super) discards_2: Vec<ActionItem>,
+    #[serde(borrow)]
+    pub(super) takes_2: Vec<ActionItem<'a>>,
+    #[serde(borrow)]
+    pub(super) discards_2: Vec<ActionItem<'a>>,
 
     #[serde_as(as = "Vec<FromInto<TenhouTile>>")]
     pub(super) haipai_3: Vec<Tile>,
-    pub(super) takes_3: Vec<ActionItem>,
-    pub(super) discards_3: Vec<ActionItem>,
+    #[serde(borrow)]
+    pub(super) takes_3: Vec<ActionItem<'a>>,
+    #[serde(borrow)]
+    pub(super) discards_3: Vec<ActionItem<'a>>,
 
-    pub(super) results: Vec<ResultItem>,
+    #[serde(borrow)]
+    pub(super) results: Vec<ResultItem<'a>>,
 }
 
-#[derive(Debug, Clone, SerializeTuple, DeserializeTuple)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SerializeTuple, DeserializeTuple)]
 pub struct KyokuMeta {
     pub kyoku_num: u8,
     pub honba: u8,
     pub kyotaku: u8,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// An item of the result of a kyoku: its status, then the score deltas and
+/// the detail of every hora, or the score deltas of a ryukyoku.
+///
+/// Deserialization is hand-written rather than `#[serde(untagged)]`, which
+/// would buffer every item before trying each variant in turn.
+#[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
-pub(super) enum ResultItem {
-    Status(String),
-    ScoreDeltas([i32; 4]),
-    HoraDetail(Vec<Value>),
+pub(super) enum ResultItem<'a> {
+    Status(Cow<'a, str>),
+    ScoreDeltas([Points; 4]),
+    HoraDetail(Vec<HoraField<'a>>),
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for ResultItem<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ResultItemVisitor;
+
+        impl<'de> Visitor<'de> for ResultItemVisitor {
+            type Value = ResultItem<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a status, score deltas or a hora detail")
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                Ok(ResultItem::Status(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(ResultItem::Status(Cow::Owned(v.to_owned())))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(ResultItem::Status(Cow::Owned(v)))
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut fields = Vec::with_capacity(seq.size_hint().unwrap_or(4));
+                while let Some(field) = seq.next_element::<HoraField<'de>>()? {
+                    fields.push(field);
+                }
+                // Score deltas are the only arrays of four integers.
+                let deltas: Option<Vec<Points>> = fields
+                    .iter()
+                    .map(|field| match field {
+                        HoraField::Number(n) => n.as_i64(),
+                        HoraField::Text(_) => None,
+                    })
+                    .collect();
+                Ok(
+                    match deltas.and_then(|deltas| <[Points; 4]>::try_from(deltas).ok()) {
+                        Some(deltas) => ResultItem::ScoreDeltas(deltas),
+                        None => ResultItem::HoraDetail(fields),
+                    },
+                )
+            }
+        }
+
+        deserializer.deserialize_any(ResultItemVisitor)
+    }
+}
+
+/// An item of the hora detail of a result: the seats of the winner, of the
+/// one paying and of the one liable, then the score text and the yaku.
+///
+/// Deserialization is hand-written like that of [`ActionItem`], as with
+/// `#[serde(untagged)]` every text would first fail to be read as a number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum HoraField<'a> {
+    Number(Number),
+    Text(Cow<'a, str>),
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for HoraField<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HoraFieldVisitor;
+
+        impl<'de> Visitor<'de> for HoraFieldVisitor {
+            type Value = HoraField<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a seat, a score text or a yaku")
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(HoraField::Number(v.into()))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(HoraField::Number(v.into()))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                Number::from_f64(v)
+                    .map(HoraField::Number)
+                    .ok_or_else(|| E::invalid_value(de::Unexpected::Float(v), &self))
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                Ok(HoraField::Text(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(HoraField::Text(Cow::Owned(v.to_owned())))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(HoraField::Text(Cow::Owned(v)))
+            }
+        }
+
+        deserializer.deserialize_any(HoraFieldVisitor)
+    }
+}
+
+impl HoraField<'_> {
+    #[must_use]
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Number(n) => n.as_u64(),
+            Self::Text(_) => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Number(_) => None,
+            Self::Text(text) => Some(text),
+        }
+    }
+
+    fn into_owned(self) -> HoraField<'static> {
+        match self {
+            Self::Number(n) => HoraField::Number(n),
+            Self::Text(text) => HoraField::Text(Cow::Owned(text.into_owned())),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
-pub(super) struct Rule {
-    pub(super) disp: String,
+pub(super) struct Rule<'a> {
+    #[serde(borrow)]
+    pub(super) disp: Cow<'a, str>,
     pub(super) aka: u8,
     pub(super) aka51: u8,
     pub(super) aka52: u8,
     pub(super) aka53: u8,
+    /// Rule keys the crate does not know, kept like [`RawLog::extra`].
+    #[serde(flatten)]
+    pub(super) extra: Map<String, Value>,
 }
 
-impl RawLog {
+impl<'a> RawLog<'a> {
     #[must_use]
-    pub const fn get_names(&self) -> &[String; 4] {
+    pub const fn get_names(&self) -> &[Cow<'a, str>; 4] {
         &self.names
     }
 
+    /// The names, e.g. to fix one, the empty fourth seat of sanma included.
+    pub const fn names_mut(&mut self) -> &mut [Cow<'a, str>; 4] {
+        &mut self.names
+    }
+
+    /// The "ref" field.
+    #[must_use]
+    pub fn game_id(&self) -> Option<&str> {
+        self.game_id.as_deref()
+    }
+
+    pub fn set_game_id(&mut self, game_id: Option<String>) {
+        self.game_id = game_id.map(Cow::Owned);
+    }
+
+    /// The rule string, e.g. `三鳳南喰赤`.
+    #[must_use]
+    pub fn rule_disp(&self) -> &str {
+        &self.rule.disp
+    }
+
+    pub fn set_rule_disp(&mut self, disp: String) {
+        self.rule.disp = Cow::Owned(disp);
+    }
+
+    /// The "sc" field: the final points and placement points of every seat,
+    /// interleaved.
+    #[must_use]
+    pub fn final_results(&self) -> Option<&[f64]> {
+        self.sc.as_deref()
+    }
+
+    pub fn set_final_results(&mut self, sc: Option<Vec<f64>>) {
+        self.sc = sc;
+    }
+
+    #[must_use]
+    pub fn kyokus(&self) -> &[RawKyoku<'a>] {
+        &self.logs
+    }
+
+    /// The kyokus, e.g. to correct a score or drop a kyoku. Nothing checks
+    /// that they still add up; convert with [`Log::try_from`] and validate
+    /// for that.
+    ///
+    /// [`Log::try_from`]: super::Log
+    pub const fn kyokus_mut(&mut self) -> &mut Vec<RawKyoku<'a>> {
+        &mut self.logs
+    }
+
+    /// The keys of [`Self::unknown_keys`] at the top level and their values.
+    pub const fn extra_mut(&mut self) -> &mut Map<String, Value> {
+        &mut self.extra
+    }
+
+    /// The keys of the log the crate does not know, those of the rules as
+    /// `rule.<key>`.
+    #[must_use]
+    pub fn unknown_keys(&self) -> Vec<String> {
+        let rule = self.rule.extra.keys().map(|key| format!("rule.{key}"));
+        self.extra.keys().cloned().chain(rule).collect()
+    }
+
+    /// Keep, drop or deny the keys of [`Self::unknown_keys`].
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`TenhouParseError::UnknownKeys`] under [`UnknownKeys::Deny`]
+    /// if the log has any.
+    pub fn apply_unknown_keys(&mut self, policy: UnknownKeys) -> Result<(), TenhouParseError> {
+        match policy {
+            UnknownKeys::Keep => (),
+            UnknownKeys::Drop => {
+                self.extra.clear();
+                self.rule.extra.clear();
+            }
+            UnknownKeys::Deny => {
+                let keys = self.unknown_keys();
+                if !keys.is_empty() {
+                    return Err(TenhouParseError::UnknownKeys(keys));
+                }
+            }
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn hide_names(&mut self) {
         self.names
             .iter_mut()
             .zip('A'..='D')
-            .for_each(|(name, alias)| {
-                name.clear();
-                name.push(alias);
-                name.push_str("さん");
-            });
+            .for_each(|(name, alias)| *name = Cow::Owned(alias_name(alias)));
+    }
+
+    /// Keep only the groups of metadata `fields` has, see [`Fields`].
+    pub fn project(&mut self, fields: Fields) {
+        if !fields.contains(Field::Results) {
+            self.sc = None;
+        }
+        if !fields.contains(Field::Names) {
+            alias_names(&mut self.names);
+            self.sx = None;
+        }
+        if !fields.contains(Field::Ratings) {
+            self.dan = None;
+            self.rate = None;
+            self.ratingc = None;
+        }
+        if !fields.contains(Field::Lobby) {
+            self.lobby = None;
+            hide_lobby_name(&mut self.rule.disp);
+        }
+        if !fields.contains(Field::Connections) {
+            self.connection = None;
+        }
+        if !fields.contains(Field::GameId) {
+            self.game_id = None;
+        }
     }
 
     #[inline]
@@ -146,19 +482,204 @@ impl RawLog {
 
     #[inline]
     #[must_use]
-    pub fn is_empty(&self) -> bool {
+    pub const fn is_empty(&self) -> bool {
         self.logs.is_empty()
     }
 
     #[inline]
     #[must_use]
-    pub fn len(&self) -> usize {
+    pub const fn len(&self) -> usize {
         self.logs.len()
     }
+
+    /// The log with all its text copied out of the JSON it was read from,
+    /// e.g. to keep it after the JSON is dropped.
+    #[must_use]
+    pub fn into_owned(self) -> RawLog<'static> {
+        let owned = |text: Cow<'_, str>| Cow::Owned(text.into_owned());
+        RawLog {
+            logs: self.logs.into_iter().map(RawKyoku::into_owned).collect(),
+            names: self.names.map(owned),
+            rule: Rule {
+                disp: owned(self.rule.disp),
+                aka: self.rule.aka,
+                aka51: self.rule.aka51,
+                aka52: self.rule.aka52,
+                aka53: self.rule.aka53,
+                extra: self.rule.extra,
+            },
+            ver: self.ver,
+            game_id: self.game_id.map(owned),
+            ratingc: self.ratingc.map(owned),
+            lobby: self.lobby,
+            dan: self.dan.map(|dan| dan.into_iter().map(owned).collect()),
+            rate: self.rate,
+            sx: self.sx.map(|sx| sx.into_iter().map(owned).collect()),
+            sc: self.sc,
+            connection: self.connection,
+            extra: self.extra,
+        }
+    }
+}
+
+/// Replace the names of the seats that have one by `Aさん` to `Cさん`, like
+/// [`RawLog::hide_names`], leaving the empty fourth seat of sanma empty.
+pub(super) fn alias_names<S: AsRef<str> + From<String>>(names: &mut [S; 4]) {
+    for (name, alias) in names.iter_mut().zip('A'..='D') {
+        if !name.as_ref().is_empty() {
+            *name = alias_name(alias).into();
+        }
+    }
+}
+
+fn alias_name(alias: char) -> String {
+    format!("{alias}さん")
+}
+
+/// Clear a rule string that is not made of rules, e.g. the name of a
+/// private lobby, and keep those like `三鳳南喰赤` that only tell the rules.
+pub(super) fn hide_lobby_name<S: AsRef<str> + Default>(disp: &mut S) {
+    if RuleDisp::parse(disp.as_ref()).is_unknown() {
+        *disp = S::default();
+    }
+}
+
+impl<'a> RawKyoku<'a> {
+    #[must_use]
+    pub const fn meta(&self) -> &KyokuMeta {
+        &self.meta
+    }
+
+    pub const fn meta_mut(&mut self) -> &mut KyokuMeta {
+        &mut self.meta
+    }
+
+    /// The scores of every seat at the start of the kyoku.
+    #[must_use]
+    pub const fn scoreboard(&self) -> &[Points; 4] {
+        &self.scoreboard
+    }
+
+    pub const fn scoreboard_mut(&mut self) -> &mut [Points; 4] {
+        &mut self.scoreboard
+    }
+
+    pub const fn dora_indicators_mut(&mut self) -> &mut Vec<Tile> {
+        &mut self.dora_indicators
+    }
+
+    pub const fn ura_indicators_mut(&mut self) -> &mut Vec<Tile> {
+        &mut self.ura_indicators
+    }
+
+    /// The haipai, takes and discards of `seat`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `seat` is not below 4.
+    #[must_use]
+    pub fn action_table(&self, seat: usize) -> (&[Tile], &[ActionItem<'a>], &[ActionItem<'a>]) {
+        match seat {
+            0 => (&self.haipai_0, &self.takes_0, &self.discards_0),
+            1 => (&self.haipai_1, &self.takes_1, &self.discards_1),
+            2 => (&self.haipai_2, &self.takes_2, &self.discards_2),
+            3 => (&self.haipai_3, &self.takes_3, &self.discards_3),
+            _ => panic!("no seat {seat}"),
+        }
+    }
+
+    /// The haipai, takes and discards of `seat`, to patch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `seat` is not below 4.
+    pub fn action_table_mut(
+        &mut self,
+        seat: usize,
+    ) -> (
+        &mut Vec<Tile>,
+        &mut Vec<ActionItem<'a>>,
+        &mut Vec<ActionItem<'a>>,
+    ) {
+        match seat {
+            0 => (&mut self.haipai_0, &mut self.takes_0, &mut self.discards_0),
+            1 => (&mut self.haipai_1, &mut self.takes_1, &mut self.discards_1),
+            2 => (&mut self.haipai_2, &mut self.takes_2, &mut self.discards_2),
+            3 => (&mut self.haipai_3, &mut self.takes_3, &mut self.discards_3),
+            _ => panic!("no seat {seat}"),
+        }
+    }
+
+    /// The status of the result, e.g. `和了` or `流局`.
+    #[must_use]
+    pub fn status(&self) -> Option<&str> {
+        self.results.iter().find_map(|item| match item {
+            ResultItem::Status(status) => Some(status.as_ref()),
+            _ => None,
+        })
+    }
+
+    /// The score deltas of the result, one per hora or one for a ryukyoku,
+    /// e.g. to correct a score.
+    pub fn score_deltas_mut(&mut self) -> impl Iterator<Item = &mut [Points; 4]> + use<'_, 'a> {
+        self.results.iter_mut().filter_map(|item| match item {
+            ResultItem::ScoreDeltas(deltas) => Some(deltas),
+            _ => None,
+        })
+    }
+
+    /// The hora details of the result as tenhou writes them: the winner,
+    /// the seat paying, the seat liable, the score text and the yaku.
+    pub fn hora_details_mut(&mut self) -> impl Iterator<Item = &mut Vec<HoraField<'a>>> {
+        self.results.iter_mut().filter_map(|item| match item {
+            ResultItem::HoraDetail(detail) => Some(detail),
+            _ => None,
+        })
+    }
+
+    fn into_owned(self) -> RawKyoku<'static> {
+        let owned =
+            |items: Vec<ActionItem<'_>>| items.into_iter().map(ActionItem::into_owned).collect();
+        RawKyoku {
+            meta: self.meta,
+            scoreboard: self.scoreboard,
+            dora_indicators: self.dora_indicators,
+            ura_indicators: self.ura_indicators,
+            haipai_0: self.haipai_0,
+            takes_0: owned(self.takes_0),
+            discards_0: owned(self.discards_0),
+            haipai_1: self.haipai_1,
+            takes_1: owned(self.takes_1),
+            discards_1: owned(self.discards_1),
+            haipai_2: self.haipai_2,
+            takes_2: owned(self.takes_2),
+            discards_2: owned(self.discards_2),
+            haipai_3: self.haipai_3,
+            takes_3: owned(self.takes_3),
+            discards_3: owned(self.discards_3),
+            results: self
+                .results
+                .into_iter()
+                .map(ResultItem::into_owned)
+                .collect(),
+        }
+    }
 }
 
-impl From<RawPartialLog<'_>> for RawLog {
-    fn from(partial_log: RawPartialLog<'_>) -> Self {
+impl ResultItem<'_> {
+    fn into_owned(self) -> ResultItem<'static> {
+        match self {
+            Self::Status(status) => ResultItem::Status(Cow::Owned(status.into_owned())),
+            Self::ScoreDeltas(deltas) => ResultItem::ScoreDeltas(deltas),
+            Self::HoraDetail(detail) => {
+                ResultItem::HoraDetail(detail.into_iter().map(HoraField::into_owned).collect())
+            }
+        }
+    }
+}
+
+impl<'a> From<RawPartialLog<'a>> for RawLog<'a> {
+    fn from(partial_log: RawPartialLog<'a>) -> Self {
         Self {
             logs: partial_log.logs.to_vec(),
             ..partial_log.parent.clone()
@@ -167,7 +688,6 @@ impl From<RawPartialLog<'_>> for RawLog {
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
 
@@ -175,16 +695,82 @@ mod tests {
     fn test_get_names() {
         let json_str = r#"{"ver":2.3,"ref":"2024030511gm-00b9-0000-e0c07689","log":[[[0,0,0],[35000,35000,35000,0],[47],[],[26,27,32,33,35,37,37,39,41,42,44,44,46],[45,47,19,39,27,34,21,43,26],[60,60,60,60,60,60,"f44","f44",42],[11,19,21,23,29,31,33,41,42,44,44,45,46],[42,28,22,19,"4242p42",23,22,24],["f44",33,"f44",60,19,31,11,41],[19,21,29,29,29,31,34,34,38,41,43,47,47],[21,"47p4747",42,35,27,26,"34p3434",25],[19,43,60,31,41,38,35],[],[],[],["和了",[-700,-400,1100,0],[2,2,2,"40符1飜400-700点","役牌 中(1飜)"]]],[[1,0,0],[34300,34600,36100,0],[41],[],[19,24,27,29,31,31,31,32,34,35,39,39,46],[26,22,19,21,28,35,21,39,28],[19,46,60,29,32,21,60,24,39],[23,26,27,32,33,33,37,41,43,44,44,46,47],[23,24,36,46,"p464646",37,41,23,42,32,39,33],["f44","f44",41,43,47,24,60,36,60,32,60,32],[11,21,24,24,25,29,32,33,34,35,36,45,47],[25,38,25,47,27,22,22,37,41,28],[11,21,29,38,60,45,24,"r24",60,60],[],[],[],["和了",[0,8700,-7700,0],[1,2,1,"40符3飜7700点","役牌 發(1飜)","ドラ(2飜)"]]],[[1,1,0],[34300,43300,27400,0],[26],[],[11,25,27,31,33,38,39,41,42,43,43,45,46],[53,11,43,43,42,29,44,37,52,"42p4242",24],[11,60,45,41,43,46,29,"f44",27,31,60],[19,21,23,25,26,28,29,32,33,36,41,45,47],[25,36,27,28,26,21,23,34,46,22],[47,45,19,41,28,60,29,28,60,23],[11,24,28,31,33,34,35,36,37,38,38,42,44],[44,32,38,47,34,39,39,23,24,45,42,47],["f44","f44",11,60,42,28,24,60,60,60,60,60],[],[],[],["和了",[-3100,3100,0,0],[1,0,1,"30符2飜2900点","平和(1飜)","ドラ(1飜)"]]],[[1,2,0],[31200,46400,27400,0],[33],[],[25,31,33,35,36,41,41,44,45,47,47,47,47],[33,44,46,29,"33p3333",44,22,19,"41p4141",42,53,11,24,34,19,29,37,34],["f44","f44",25,60,46,"f44",60,60,47,60,31,60,60,35,60,60,45],[11,21,22,27,27,28,28,31,38,39,41,44,45],[38,26,42,42,45,36,32,43,26,36,11,46,23,27,46,11],["f44",11,60,60,31,60,60,41,22,21,60,60,60,60,60,60],[19,19,21,25,26,28,29,33,38,38,39,39,46],[27,36,28,41,23,37,35,37,23,34,32,42,39,22,43],[21,46,33,36,29,41,25,23,60,28,60,60,19,60,19],[],[],[],["和了",[12400,-8200,-4200,0],[0,0,0,"倍満4000-8000点","役牌 中(1飜)","場風 東(1飜)","混一色(2飜)","ドラ(5飜)","赤ドラ(1飜)"]]],[[2,0,0],[43600,38200,23200,0],[53],[47],[19,21,23,25,52,26,29,35,37,38,41,43,47],[39,34,27,44,28,42,32,45,28,19,42,21],[47,19,41,"f44",43,60,60,29,23,60,27,60],[23,24,31,32,33,36,36,38,41,43,44,45,46],[39,11,21,37,45,31,43,19,"3636p36",27,36,47,34],["f44",60,46,41,21,60,24,60,23,60,60,60,31],[22,24,25,26,28,29,33,33,35,38,38,41,47],[31,46,26,25,37,27,34,36,27,29,11,22,32],[47,60,41,22,29,28,"r37",60,60,60,60,60],[],[],[],["和了",[-1000,-1000,3000,0],[2,2,2,"30符2飜1000点∀","立直(1飜)","門前清自摸和(1飜)"]]],[[2,1,0],[42600,37200,25200,0],[29],[19],[24,26,29,32,33,35,36,37,43,43,44,46,47],[19,37,38,29,11,53,38,43,32,52,47,47,22,38,41],["f44",19,46,47,60,29,29,26,24,60,60,60,60,33,60],[21,22,23,24,25,28,33,34,34,39,42,44,46],[37,42,37,26,36,26,22,31,33,24,34,45,35,27,32],["f44",46,34,28,39,60,60,60,60,60,60,60,"r37",60],[21,22,23,26,31,31,33,35,36,38,39,45,46],[41,28,24,36,27,27,11,39,21,23,28,29,28,31],[45,46,41,39,24,60,60,60,60,60,60,60,60,33],[],[],[],["和了",[-2100,7200,-4100,0],[1,1,1,"満貫2000-4000点","立直(1飜)","門前清自摸和(1飜)","平和(1飜)","ドラ(2飜)"]]],[[4,0,0],[40500,43400,21100,0],[33],[],[11,19,26,29,31,31,32,32,33,35,43,46,47],[37,34,"3131p31",21,24,"32p3232",24,19,26,33,38,43],[43,11,29,60,60,26,60,46,60,19,19,60],[24,25,52,31,34,37,38,39,41,41,43,44,44],[46,11,39,36,21,19,28,22,37,29,53,36,33,39],["f44","f44",43,31,46,60,21,11,28,60,22,39,39,60],[22,23,25,27,28,32,34,36,36,38,42,42,46],[27,26,45,44,21,23,11,28,46,42,27,24],[46,38,60,"f44",32,34,60,36,36,46,23],[],[],[],["和了",[-6000,-3000,9000,0],[2,2,2,"跳満3000-6000点","門前清自摸和(1飜)","場風 南(1飜)","混一色(3飜)","ドラ(1飜)"]]],[[5,0,0],[34500,40400,30100,0],[47],[37],[11,23,24,52,26,32,32,33,36,38,42,44,45],[44,35,38,33,32,43,28,29,27,19,36,53,26],["f44","f44",11,42,45,60,60,60,33,60,60,33,36],[19,22,23,24,26,27,31,36,39,43,43,45,46],[23,25,31,37,35,44,39,46,28,37,31,28,25],[39,31,60,46,19,"f44",45,60,"r39",60,60,60,60],[19,21,22,23,25,27,29,29,29,32,33,34,37],[47,34,19,46,41,21,41,46,28,34,42,26],[60,19,60,60,60,37,60,60,34,60,60,"r21"],[],[],[],["和了",[0,6800,-5800,0],[1,2,1,"30符3飜5800点","立直(1飜)","平和(1飜)","ドラ(1飜)"]]],[[5,1,0],[34500,46200,24300,0],[34],[28],[23,24,25,26,31,32,32,34,38,39,42,44,46],[28,31,24,47,42,44,36,33,26,21,38,31,24,29],["f44",42,46,60,60,"f44",39,28,60,60,32,"r36",60,60],[21,23,33,34,37,38,38,41,41,43,44,45,47],[32,25,33,43,23,45,44,26,29,43,41,19,27],["f44",43,47,60,45,60,"f44",33,60,60,21,41,41],[11,19,19,21,22,27,33,36,37,41,42,42,47],[28,"42p4242",36,28,37,35,"28p2828",46,45,52,39,29,22],[11,41,47,33,22,21,27,60,60,60,60,60,60],[],[],[],["和了",[6400,0,-5400,0],[0,2,0,"40符3飜5200点","立直(1飜)","ドラ(2飜)"]]],[[6,0,0],[39900,46200,18900,0],[41,36],[47,36],[19,25,27,29,33,34,34,36,37,41,45,45,46],[19,22,37,28],[41,46,36,45],[23,24,24,27,31,31,33,37,39,41,42,43,43],[53,19,43,22],[41,42,19,53],[11,11,26,27,28,29,31,32,33,39,39,44,45],[35,47,11,38,27,11,52],["f44",45,47,60,"r35","111111a11"],[],[],[],["和了",[-4000,-4000,9000,0],[2,2,2,"満貫4000点∀","立直(1飜)","嶺上開花(1飜)","門前清自摸和(1飜)","ドラ(1飜)","赤ドラ(1飜)"]]],[[6,1,0],[35900,42200,26900,0],[25],[],[11,19,25,52,26,26,28,32,35,37,38,38,44],[24,33,46,37,47,39,37,31],["f44",19,11,46,28,35,47,38],[21,21,22,26,27,28,28,31,33,33,41,42,47],[32,41,47,27,45,"p474747",24],[41,60,42,33,60,22,60],[11,22,23,24,26,28,29,29,34,38,43,44,46],[41,44,19,36,23,25,23,19,31],["f44","f44",11,19,41,46,43,60,60],[],[],[],["和了",[12200,-12200,0,0],[0,1,0,"跳満12000点","平和(1飜)","一盃口(1飜)","ドラ(3飜)","赤ドラ(1飜)"]]]],"connection":[{"what":0,"log":0,"who":0,"step":3},{"what":1,"log":0,"who":0,"step":40}],"ratingc":"PF3","rule":{"disp":"三鳳南喰赤","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["七段","天鳳","八段","新人"],"rate":[2221.9,2461.48,2227.63,1500],"sx":["M","M","M","C"],"sc":[48100,43.1,30000,-10,26900,-33.1,0,0],"name":["mtk","つくねん3","ひぐお3",""]}
     "#;
-        let raw_log: RawLog = serde_json::from_str(json_str).unwrap();
+        let raw_log: RawLog<'_> = serde_json::from_str(json_str).unwrap();
         assert_eq!(raw_log.get_names(), &["mtk", "つくねん3", "ひぐお3", ""]);
+        // Borrowed from the JSON, and copied out of it.
+        assert!(matches!(raw_log.get_names()[0], Cow::Borrowed(_)));
+        assert!(matches!(raw_log.rule.disp, Cow::Borrowed(_)));
+        let owned = raw_log.into_owned();
+        assert!(matches!(owned.get_names()[0], Cow::Owned(_)));
+        let (_, takes, _) = owned.kyokus()[0].action_table(1);
+        assert!(matches!(&takes[4], ActionItem::Naki(Cow::Owned(naki)) if naki == "4242p42"));
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let json = crate::testdata::Fixture::Hanchan.json();
+        let mut value: Value = serde_json::from_str(json).unwrap();
+        value["title"] = Value::from(["", ""].as_slice());
+        value["rule"]["ruleset"] = Value::from(3);
+        let mut raw = RawLog::deserialize(value.clone()).unwrap();
+        assert_eq!(raw.unknown_keys(), ["title", "rule.ruleset"]);
+        // Written back as they were.
+        let written = serde_json::to_value(&raw).unwrap();
+        assert_eq!(written["title"], value["title"]);
+        assert_eq!(written["rule"]["ruleset"], 3);
+
+        raw.clone().apply_unknown_keys(UnknownKeys::Keep).unwrap();
+        let err = raw
+            .clone()
+            .apply_unknown_keys(UnknownKeys::Deny)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "unknown keys: title, rule.ruleset");
+        raw.apply_unknown_keys(UnknownKeys::Drop).unwrap();
+        assert!(raw.unknown_keys().is_empty());
+        let written = serde_json::to_value(&raw).unwrap();
+        assert!(written.get("title").is_none() && written["rule"].get("ruleset").is_none());
+        raw.apply_unknown_keys(UnknownKeys::Deny).unwrap();
+
+        assert_eq!("drop".parse(), Ok(UnknownKeys::Drop));
+        "ignore".parse::<UnknownKeys>().unwrap_err();
+    }
+
+    #[test]
+    fn patch() {
+        let mut raw = RawLog::from_json_str(crate::testdata::Fixture::Hanchan.json()).unwrap();
+        raw.names_mut()[0] = "mtk2".into();
+        assert_eq!(raw.game_id(), Some("2024030511gm-00b9-0000-e0c07689"));
+        assert_eq!(raw.rule_disp(), "三鳳南喰赤");
+        assert_eq!(raw.final_results().map(<[f64]>::len), Some(8));
+
+        // The first hora was a 400-700 tsumo of the third seat; make it a
+        // 500-1000 one.
+        let kyoku = &mut raw.kyokus_mut()[0];
+        assert_eq!(kyoku.status(), Some("和了"));
+        assert_eq!(kyoku.meta().kyoku_num, 0);
+        assert_eq!(kyoku.scoreboard()[0], 35000);
+        assert_eq!(kyoku.action_table(2).0.len(), 13);
+        let deltas = kyoku.score_deltas_mut().next().unwrap();
+        *deltas = [-1000, -500, 1500, 0];
+        kyoku.hora_details_mut().next().unwrap()[3] = HoraField::Text("30符2飜500-1000点".into());
+        kyoku.action_table_mut(3).0.clear();
+        assert_eq!(raw.kyokus().len(), 11);
+
+        let json = serde_json::to_string(&raw).unwrap();
+        let log = crate::tenhou::Log::from_json_str(&json).unwrap();
+        assert_eq!(log.names[0], "mtk2");
+        assert_eq!(
+            log.kyokus[0].horas()[0].score_deltas,
+            [-1000, -500, 1500, 0]
+        );
     }
 
     #[test]
     fn test_split() {
         let json_str = r#"{"ver":2.3,"ref":"2024030511gm-00b9-0000-e0c07689","log":[[[0,0,0],[35000,35000,35000,0],[47],[],[26,27,32,33,35,37,37,39,41,42,44,44,46],[45,47,19,39,27,34,21,43,26],[60,60,60,60,60,60,"f44","f44",42],[11,19,21,23,29,31,33,41,42,44,44,45,46],[42,28,22,19,"4242p42",23,22,24],["f44",33,"f44",60,19,31,11,41],[19,21,29,29,29,31,34,34,38,41,43,47,47],[21,"47p4747",42,35,27,26,"34p3434",25],[19,43,60,31,41,38,35],[],[],[],["和了",[-700,-400,1100,0],[2,2,2,"40符1飜400-700点","役牌 中(1飜)"]]],[[1,0,0],[34300,34600,36100,0],[41],[],[19,24,27,29,31,31,31,32,34,35,39,39,46],[26,22,19,21,28,35,21,39,28],[19,46,60,29,32,21,60,24,39],[23,26,27,32,33,33,37,41,43,44,44,46,47],[23,24,36,46,"p464646",37,41,23,42,32,39,33],["f44","f44",41,43,47,24,60,36,60,32,60,32],[11,21,24,24,25,29,32,33,34,35,36,45,47],[25,38,25,47,27,22,22,37,41,28],[11,21,29,38,60,45,24,"r24",60,60],[],[],[],["和了",[0,8700,-7700,0],[1,2,1,"40符3飜7700点","役牌 發(1飜)","ドラ(2飜)"]]],[[1,1,0],[34300,43300,27400,0],[26],[],[11,25,27,31,33,38,39,41,42,43,43,45,46],[53,11,43,43,42,29,44,37,52,"42p4242",24],[11,60,45,41,43,46,29,"f44",27,31,60],[19,21,23,25,26,28,29,32,33,36,41,45,47],[25,36,27,28,26,21,23,34,46,22],[47,45,19,41,28,60,29,28,60,23],[11,24,28,31,33,34,35,36,37,38,38,42,44],[44,32,38,47,34,39,39,23,24,45,42,47],["f44","f44",11,60,42,28,24,60,60,60,60,60],[],[],[],["和了",[-3100,3100,0,0],[1,0,1,"30符2飜2900点","平和(1飜)","ドラ(1飜)"]]],[[1,2,0],[31200,46400,27400,0],[33],[],[25,31,33,35,36,41,41,44,45,47,47,47,47],[33,44,46,29,"33p3333",44,22,19,"41p4141",42,53,11,24,34,19,29,37,34],["f44","f44",25,60,46,"f44",60,60,47,60,31,60,60,35,60,60,45],[11,21,22,27,27,28,28,31,38,39,41,44,45],[38,26,42,42,45,36,32,43,26,36,11,46,23,27,46,11],["f44",11,60,60,31,60,60,41,22,21,60,60,60,60,60,60],[19,19,21,25,26,28,29,33,38,38,39,39,46],[27,36,28,41,23,37,35,37,23,34,32,42,39,22,43],[21,46,33,36,29,41,25,23,60,28,60,60,19,60,19],[],[],[],["和了",[12400,-8200,-4200,0],[0,0,0,"倍満4000-8000点","役牌 中(1飜)","場風 東(1飜)","混一色(2飜)","ドラ(5飜)","赤ドラ(1飜)"]]],[[2,0,0],[43600,38200,23200,0],[53],[47],[19,21,23,25,52,26,29,35,37,38,41,43,47],[39,34,27,44,28,42,32,45,28,19,42,21],[47,19,41,"f44",43,60,60,29,23,60,27,60],[23,24,31,32,33,36,36,38,41,43,44,45,46],[39,11,21,37,45,31,43,19,"3636p36",27,36,47,34],["f44",60,46,41,21,60,24,60,23,60,60,60,31],[22,24,25,26,28,29,33,33,35,38,38,41,47],[31,46,26,25,37,27,34,36,27,29,11,22,32],[47,60,41,22,29,28,"r37",60,60,60,60,60],[],[],[],["和了",[-1000,-1000,3000,0],[2,2,2,"30符2飜1000点∀","立直(1飜)","門前清自摸和(1飜)"]]],[[2,1,0],[42600,37200,25200,0],[29],[19],[24,26,29,32,33,35,36,37,43,43,44,46,47],[19,37,38,29,11,53,38,43,32,52,47,47,22,38,41],["f44",19,46,47,60,29,29,26,24,60,60,60,60,33,60],[21,22,23,24,25,28,33,34,34,39,42,44,46],[37,42,37,26,36,26,22,31,33,24,34,45,35,27,32],["f44",46,34,28,39,60,60,60,60,60,60,60,"r37",60],[21,22,23,26,31,31,33,35,36,38,39,45,46],[41,28,24,36,27,27,11,39,21,23,28,29,28,31],[45,46,41,39,24,60,60,60,60,60,60,60,60,33],[],[],[],["和了",[-2100,7200,-4100,0],[1,1,1,"満貫2000-4000点","立直(1飜)","門前清自摸和(1飜)","平和(1飜)","ドラ(2飜)"]]],[[4,0,0],[40500,43400,21100,0],[33],[],[11,19,26,29,31,31,32,32,33,35,43,46,47],[37,34,"3131p31",21,24,"32p3232",24,19,26,33,38,43],[43,11,29,60,60,26,60,46,60,19,19,60],[24,25,52,31,34,37,38,39,41,41,43,44,44],[46,11,39,36,21,19,28,22,37,29,53,36,33,39],["f44","f44",43,31,46,60,21,11,28,60,22,39,39,60],[22,23,25,27,28,32,34,36,36,38,42,42,46],[27,26,45,44,21,23,11,28,46,42,27,24],[46,38,60,"f44",32,34,60,36,36,46,23],[],[],[],["和了",[-6000,-3000,9000,0],[2,2,2,"跳満3000-6000点","門前清自摸和(1飜)","場風 南(1飜)","混一色(3飜)","ドラ(1飜)"]]],[[5,0,0],[34500,40400,30100,0],[47],[37],[11,23,24,52,26,32,32,33,36,38,42,44,45],[44,35,38,33,32,43,28,29,27,19,36,53,26],["f44","f44",11,42,45,60,60,60,33,60,60,33,36],[19,22,23,24,26,27,31,36,39,43,43,45,46],[23,25,31,37,35,44,39,46,28,37,31,28,25],[39,31,60,46,19,"f44",45,60,"r39",60,60,60,60],[19,21,22,23,25,27,29,29,29,32,33,34,37],[47,34,19,46,41,21,41,46,28,34,42,26],[60,19,60,60,60,37,60,60,34,60,60,"r21"],[],[],[],["和了",[0,6800,-5800,0],[1,2,1,"30符3飜5800点","立直(1飜)","平和(1飜)","ドラ(1飜)"]]],[[5,1,0],[34500,46200,24300,0],[34],[28],[23,24,25,26,31,32,32,34,38,39,42,44,46],[28,31,24,47,42,44,36,33,26,21,38,31,24,29],["f44",42,46,60,60,"f44",39,28,60,60,32,"r36",60,60],[21,23,33,34,37,38,38,41,41,43,44,45,47],[32,25,33,43,23,45,44,26,29,43,41,19,27],["f44",43,47,60,45,60,"f44",33,60,60,21,41,41],[11,19,19,21,22,27,33,36,37,41,42,42,47],[28,"42p4242",36,28,37,35,"28p2828",46,45,52,39,29,22],[11,41,47,33,22,21,27,60,60,60,60,60,60],[],[],[],["和了",[6400,0,-5400,0],[0,2,0,"40符3飜5200点","立直(1飜)","ドラ(2飜)"]]],[[6,0,0],[39900,46200,18900,0],[41,36],[47,36],[19,25,27,29,33,34,34,36,37,41,45,45,46],[19,22,37,28],[41,46,36,45],[23,24,24,27,31,31,33,37,39,41,42,43,43],[53,19,43,22],[41,42,19,53],[11,11,26,27,28,29,31,32,33,39,39,44,45],[35,47,11,38,27,11,52],["f44",45,47,60,"r35","111111a11"],[],[],[],["和了",[-4000,-4000,9000,0],[2,2,2,"満貫4000点∀","立直(1飜)","嶺上開花(1飜)","門前清自摸和(1飜)","ドラ(1飜)","赤ドラ(1飜)"]]],[[6,1,0],[35900,42200,26900,0],[25],[],[11,19,25,52,26,26,28,32,35,37,38,38,44],[24,33,46,37,47,39,37,31],["f44",19,11,46,28,35,47,38],[21,21,22,26,27,28,28,31,33,33,41,42,47],[32,41,47,27,45,"p474747",24],[41,60,42,33,60,22,60],[11,22,23,24,26,28,29,29,34,38,43,44,46],[41,44,19,36,23,25,23,19,31],["f44","f44",11,19,41,46,43,60,60],[],[],[],["和了",[12200,-12200,0,0],[0,1,0,"跳満12000点","平和(1飜)","一盃口(1飜)","ドラ(3飜)","赤ドラ(1飜)"]]]],"connection":[{"what":0,"log":0,"who":0,"step":3},{"what":1,"log":0,"who":0,"step":40}],"ratingc":"PF3","rule":{"disp":"三鳳南喰赤","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["七段","天鳳","八段","新人"],"rate":[2221.9,2461.48,2227.63,1500],"sx":["M","M","M","C"],"sc":[48100,43.1,30000,-10,26900,-33.1,0,0],"name":["mtk","つくねん3","ひぐお3",""]}
     "#;
-        let raw_log: RawLog = serde_json::from_str(json_str).unwrap();
+        let raw_log: RawLog<'_> = serde_json::from_str(json_str).unwrap();
         let partial_logs = raw_log.split_by_kyoku();
         println!("{:?}", partial_logs[0].logs);
     }
-}
\ No newline at end of file
+}
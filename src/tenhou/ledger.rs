@@ -0,0 +1,270 @@
+use super::log::{EndStatus, FinalResult, Kyoku, Log};
+use super::rules::RuleError;
+use crate::ruleset::Ruleset;
+use crate::Points;
+
+use serde::Serialize;
+
+/// The riichi sticks and honba at the end of a kyoku.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LedgerEntry {
+    pub kyoku: u8,
+    pub honba: u8,
+    /// Riichi sticks deposited during this kyoku.
+    pub deposited: u32,
+    /// Riichi sticks on the table when the kyoku ended.
+    pub on_table: u32,
+    /// Riichi sticks carried into the next kyoku, or to the winner of the
+    /// game after the last one.
+    pub carried: u32,
+    /// Honba counter of the next kyoku.
+    pub next_honba: u8,
+}
+
+impl Kyoku {
+    /// The seat of the dealer.
+    #[inline]
+    #[must_use]
+    pub const fn oya(&self) -> u8 {
+        self.meta.kyoku_num % 4
+    }
+
+    /// Work out the sticks and honba this kyoku leaves behind.
+    #[must_use]
+    pub fn ledger_entry(&self) -> LedgerEntry {
+        let on_table = self.riichi_sticks();
+        let (carried, next_honba) = match &self.end_status {
+            EndStatus::Hora { details } => {
+                let renchan = details.iter().any(|d| d.who == self.oya());
+                (0, if renchan { self.meta.honba + 1 } else { 0 })
+            }
+            // Honba are added on every ryukyoku, whether the dealer keeps the
+            // seat or not.
+            EndStatus::Ryukyoku { .. } => (on_table, self.meta.honba + 1),
+        };
+
+        LedgerEntry {
+            kyoku: self.meta.kyoku_num,
+            honba: self.meta.honba,
+            deposited: on_table - u32::from(self.meta.kyotaku),
+            on_table,
+            carried,
+            next_honba,
+        }
+    }
+}
+
+impl Log {
+    /// The ledger entry of every kyoku, in order.
+    #[must_use]
+    pub fn ledger(&self) -> Vec<LedgerEntry> {
+        self.kyokus.iter().map(Kyoku::ledger_entry).collect()
+    }
+
+    /// The scores of every seat at the end of the game, the riichi sticks
+    /// left on the table going to the top. `None` for logs without kyokus.
+    ///
+    /// Like [`Self::check_ledger`], this only makes sense for complete logs.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`RuleError::ScoreOverflow`] for corrupt logs whose scores
+    /// do not fit [`Points`].
+    pub fn final_scores(&self) -> Result<Option<[Points; 4]>, RuleError> {
+        let Some(last) = self.kyokus.last() else {
+            return Ok(None);
+        };
+        let deltas: Vec<_> = match &last.end_status {
+            EndStatus::Hora { details } => details.iter().map(|d| d.score_deltas).collect(),
+            EndStatus::Ryukyoku { score_deltas, .. } => vec![*score_deltas],
+        };
+        let deposits = last.deposits();
+        let mut scores = last.scoreboard;
+        for (seat, score) in (0..).zip(&mut scores) {
+            let mut checked = score.checked_sub(Points::from(deposits[seat as usize]) * 1000);
+            for deltas in &deltas {
+                checked = checked.and_then(|s| s.checked_add(deltas[seat as usize]));
+            }
+            *score = last.checked_score(seat, checked)?;
+        }
+        // Ties go to the seat closest to the first dealer.
+        let seats = if self.names[3].is_empty() { 3 } else { 4 };
+        let top = (0..seats)
+            .min_by_key(|&seat| -scores[seat])
+            .unwrap_or_default();
+        let carried = Points::from(last.ledger_entry().carried) * 1000;
+        scores[top] = last.checked_score(top as u8, scores[top].checked_add(carried))?;
+        Ok(Some(scores))
+    }
+
+    /// Check the recorded [`Self::final_results`] against the scores the
+    /// kyokus add up to, see [`Self::final_scores`], and the placement points
+    /// `ruleset` gives for them. Logs without final results pass.
+    pub fn check_final_results(&self, ruleset: &Ruleset) -> Result<(), RuleError> {
+        let (Some(results), Some(scores)) = (self.final_results, self.final_scores()?) else {
+            return Ok(());
+        };
+        let points = ruleset.placement_points([scores[0], scores[1], scores[2]]);
+        // Placement points are recorded to one decimal.
+        let tenths = |pt: f64| (pt * 10.0).round() as i64;
+        for (seat, got) in (0..).zip(&results[..3]) {
+            let expected = FinalResult {
+                points: scores[usize::from(seat)],
+                pt_delta: points[usize::from(seat)],
+            };
+            if got.points != expected.points || tenths(got.pt_delta) != tenths(expected.pt_delta) {
+                return Err(RuleError::FinalResultMismatch {
+                    who: seat,
+                    expected,
+                    got: *got,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every kyoku starts with the honba and kyotaku the previous
+    /// one left behind.
+    ///
+    /// This only makes sense for complete logs, not ones that went through a
+    /// [`crate::KyokuFilter`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn check_ledger(&self) -> Result<(), RuleError> {
+        for (prev, next) in self.kyokus.iter().zip(self.kyokus.iter().skip(1)) {
+            let entry = prev.ledger_entry();
+            if next.meta.honba != entry.next_honba || u32::from(next.meta.kyotaku) != entry.carried
+            {
+                return Err(RuleError::LedgerMismatch {
+                    kyoku: next.meta.kyoku_num,
+                    honba: next.meta.honba,
+                    kyotaku: next.meta.kyotaku,
+                    expected_honba: entry.next_honba,
+                    expected_kyotaku: entry.carried,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tenhou::RyukyokuReason;
+    use crate::testdata::Fixture;
+
+    /// A ryukyoku with a riichi by seat 1, followed by a kyoku with `meta`.
+    fn log_of(meta: &str) -> Log {
+        let json_str = format!(
+            r#"{{"log":[[[0,0,0],[35000,35000,35000,0],[47],[],[],[],[],[],[],["r47"],[],[],[],[],[],[],["流局",[-1000,1500,-500,0]]],[{meta},[34000,35500,34500,0],[47],[],[],[],[],[],[],[],[],[],[],[],[],[],["流局",[0,0,0,0]]]],"name":["","","",""]}}"#
+        );
+        Log::from_json_str(&json_str).unwrap()
+    }
+
+    #[test]
+    fn ledger() {
+        let log = log_of("[1,1,1]");
+        assert_eq!(
+            log.ledger()[0],
+            LedgerEntry {
+                kyoku: 0,
+                honba: 0,
+                deposited: 1,
+                on_table: 1,
+                carried: 1,
+                next_honba: 1,
+            },
+        );
+        assert_eq!(log.ledger()[1].carried, 1);
+        log.check_ledger().unwrap();
+
+        let err = log_of("[1,0,1]").check_ledger().unwrap_err();
+        assert!(
+            matches!(
+                err,
+                RuleError::LedgerMismatch {
+                    expected_honba: 1,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn final_results() {
+        let mut log = Fixture::Hanchan.log();
+        let results = log.final_results.unwrap();
+        assert_eq!(
+            results[0],
+            FinalResult {
+                points: 48100,
+                pt_delta: 43.1,
+            }
+        );
+        log.check_final_results(&Ruleset::TENHOU_SANMA).unwrap();
+
+        let err = log
+            .check_final_results(&Ruleset::MLEAGUE_STYLE_3P)
+            .unwrap_err();
+        assert!(
+            matches!(err, RuleError::FinalResultMismatch { who: 0, .. }),
+            "{err}"
+        );
+        log.final_results.as_mut().unwrap()[1].points = 31000;
+        let err = log.check_final_results(&Ruleset::TENHOU_SANMA).unwrap_err();
+        assert!(
+            matches!(err, RuleError::FinalResultMismatch { who: 1, .. }),
+            "{err}"
+        );
+        log.final_results = None;
+        log.check_final_results(&Ruleset::MLEAGUE_STYLE_3P).unwrap();
+
+        let json = Fixture::Hanchan
+            .json()
+            .replace("\"sc\":[48100,", "\"sc\":[48100.5,");
+        let log = Log::from_json_str(&json).unwrap();
+        assert!(log.final_results.is_none());
+        assert_eq!(log.kyokus.len(), Fixture::Hanchan.log().kyokus.len());
+    }
+
+    #[test]
+    fn final_scores() {
+        let log = Fixture::Hanchan.log();
+        assert_eq!(log.final_scores().unwrap(), Some([48100, 30000, 26900, 0]));
+
+        // The riichi stick left after the last ryukyoku goes to the top.
+        assert_eq!(
+            log_of("[1,1,1]").final_scores().unwrap(),
+            Some([34000, 36500, 34500, 0]),
+        );
+
+        // Custom lobbies can start far beyond the range of an `i32`.
+        let mut log = log_of("[1,1,1]");
+        log.kyokus[1].scoreboard[1] = 3_000_000_000;
+        assert_eq!(
+            log.final_scores().unwrap(),
+            Some([34000, 3_000_001_000, 34500, 0]),
+        );
+
+        let mut log = log_of("[1,1,1]");
+        log.kyokus[1].scoreboard[2] = Points::MAX;
+        log.kyokus[1].end_status = EndStatus::Ryukyoku {
+            score_deltas: [-1000, -1000, 2000, 0],
+            reason: RyukyokuReason::Exhaustive,
+        };
+        let err = log.final_scores().unwrap_err();
+        assert!(
+            matches!(
+                err,
+                RuleError::ScoreOverflow {
+                    kyoku: 1,
+                    honba: 1,
+                    who: 2
+                }
+            ),
+            "{err}"
+        );
+    }
+}
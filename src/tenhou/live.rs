@@ -0,0 +1,778 @@
+//! Conversion of the tag stream tenhou sends while a game is played or
+//! spectated, the same tags as in mjlog files, e.g.
+//! `<INIT seed="0,0,0,1,2,108" .../><T50/><D50/>`.
+//!
+//! Tags can arrive split over any number of chunks and are converted as soon
+//! as they are complete, so that bots can follow a game in real time.
+
+use super::game_id::RuleFlags;
+use super::RIICHI_STICK;
+use crate::conv::end_game;
+use crate::ruleset::TieBreak;
+use crate::{t, tu8, Event, Points, Tile, TileError};
+use std::array;
+use std::error::Error;
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, LiveError>;
+
+#[derive(Debug)]
+pub enum LiveError {
+    MissingAttr {
+        tag: String,
+        attr: &'static str,
+    },
+    InvalidAttr {
+        tag: String,
+        attr: &'static str,
+        value: String,
+    },
+    InvalidTile(TileError),
+    InvalidMeld(u16),
+    OutsideKyoku(String),
+}
+
+impl fmt::Display for LiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingAttr { tag, attr } => {
+                write!(f, "missing attribute {attr:?} in tag {tag:?}")
+            }
+            Self::InvalidAttr { tag, attr, value } => {
+                write!(f, "invalid attribute {attr:?} in tag {tag:?}: {value:?}")
+            }
+            Self::InvalidTile(err) => fmt::Display::fmt(err, f),
+            Self::InvalidMeld(code) => write!(f, "invalid meld code: {code}"),
+            Self::OutsideKyoku(tag) => write!(f, "tag {tag:?} outside of a kyoku"),
+        }
+    }
+}
+
+impl Error for LiveError {}
+
+impl From<TileError> for LiveError {
+    fn from(err: TileError) -> Self {
+        Self::InvalidTile(err)
+    }
+}
+
+/// Turns tags into mjai events as they come in.
+///
+/// `end_kyoku` is held back until the next `INIT` or the end of the game, as
+/// a double ron sends one `AGARI` per winner.
+#[derive(Debug, Clone, Default)]
+pub struct LiveConverter {
+    /// The start of a tag not yet complete.
+    buf: String,
+    rules: Option<RuleFlags>,
+    names: [String; 4],
+    started: bool,
+    in_kyoku: bool,
+    /// An `AGARI` or `RYUUKYOKU` was seen in the current kyoku.
+    ended_kyoku: bool,
+    ended: bool,
+    /// The tile each seat drew last, to tell tsumogiri.
+    last_draw: [Option<u16>; 4],
+}
+
+impl LiveConverter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rules announced by the `GO` tag, if one was seen.
+    #[must_use]
+    pub const fn rules(&self) -> Option<RuleFlags> {
+        self.rules
+    }
+
+    /// Convert every tag completed by `chunk` and push the events to
+    /// `events`.
+    ///
+    /// On error the events of the tags before the invalid one are already
+    /// pushed, and the tags after it are kept for the next call.
+    pub fn push_str(&mut self, chunk: &str, events: &mut Vec<Event>) -> Result<()> {
+        self.buf.push_str(chunk);
+        while let Some(end) = self.buf.find('>') {
+            let tag: String = self.buf.drain(..=end).collect();
+            let Some(start) = tag.rfind('<') else {
+                continue;
+            };
+            self.feed_tag(&tag[start + 1..tag.len() - 1], events)?;
+        }
+        Ok(())
+    }
+
+    /// Convert a single tag, given without its angle brackets, e.g.
+    /// `DORA hai="65" /`.
+    pub fn feed_tag(&mut self, tag: &str, events: &mut Vec<Event>) -> Result<()> {
+        let tag = Tag::parse(tag);
+        match tag.name {
+            "GO" => {
+                let bits = tag.num("type")?;
+                self.rules = Some(RuleFlags::from_bits(bits));
+            }
+            "UN" if !self.started => {
+                for (seat, name) in self.names.iter_mut().enumerate() {
+                    let attr = ["n0", "n1", "n2", "n3"][seat];
+                    if let Some(value) = tag.attr(attr) {
+                        *name = percent_decode(value).ok_or_else(|| tag.invalid(attr, value))?;
+                    }
+                }
+            }
+            "INIT" => self.init(&tag, events)?,
+            "N" => {
+                self.check_in_kyoku(&tag)?;
+                let actor = tag.num("who")?;
+                let m = tag.num("m")?;
+                events.push(decode_meld(actor, m)?);
+            }
+            "REACH" => {
+                self.check_in_kyoku(&tag)?;
+                let actor = tag.num("who")?;
+                match tag.attr("step") {
+                    Some("1") => events.push(Event::Reach { actor }),
+                    Some("2") => {
+                        let mut deltas = [0; 4];
+                        deltas[usize::from(actor)] = -RIICHI_STICK;
+                        events.push(Event::ReachAccepted {
+                            actor,
+                            deltas: Some(deltas),
+                        });
+                    }
+                    _ => (),
+                }
+            }
+            "DORA" => {
+                self.check_in_kyoku(&tag)?;
+                let dora_marker = tile(tag.num("hai")?)?;
+                events.push(Event::Dora { dora_marker });
+            }
+            "AGARI" => {
+                self.check_in_kyoku(&tag)?;
+                let ura_markers = tag
+                    .attr("doraHaiUra")
+                    .map(|ids| tiles(&tag, "doraHaiUra", ids))
+                    .transpose()?
+                    .unwrap_or_default();
+                let actor: u8 = tag.num("who")?;
+                // Further winners of a double ron do not get the sticks again.
+                let pot = if self.ended_kyoku {
+                    0
+                } else {
+                    Points::from(tag.kyotaku()?) * RIICHI_STICK
+                };
+                let sticks = (pot > 0).then(|| {
+                    let mut sticks = [0; 4];
+                    sticks[usize::from(actor)] = pot;
+                    sticks
+                });
+                events.push(Event::Hora {
+                    actor,
+                    target: tag.num("fromWho")?,
+                    deltas: Some(tag.deltas()?),
+                    sticks,
+                    ura_markers: Some(ura_markers),
+                });
+                self.end_kyoku(&tag, events)?;
+            }
+            "RYUUKYOKU" => {
+                self.check_in_kyoku(&tag)?;
+                let reason = match tag.attr("type") {
+                    None => "fanpai",
+                    Some("nm") => "nagashimangan",
+                    Some("yao9") => "kyushukyuhai",
+                    Some("reach4") => "suchareach",
+                    Some("ron3") => "sanchaho",
+                    Some("kan4") => "sukaikan",
+                    Some("kaze4") => "sufonteki",
+                    Some(other) => return Err(tag.invalid("type", other)),
+                };
+                // Only the hands of tenpai seats are shown when the wall runs out.
+                let tenpais = (reason == "fanpai")
+                    .then(|| array::from_fn(|i| tag.attr(&format!("hai{i}")).is_some()));
+                events.push(Event::Ryukyoku {
+                    reason: Some(reason.to_owned()),
+                    deltas: Some(tag.deltas()?),
+                    tenpais,
+                    kyotaku: Some(tag.kyotaku()?),
+                });
+                self.end_kyoku(&tag, events)?;
+            }
+            name => {
+                let mut letters = name.chars();
+                if let (Some(c), None) = (letters.next(), letters.next()) {
+                    self.draw_or_discard(&tag, c, events)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Close a game cut off before its final `AGARI` or `RYUUKYOKU`, e.g.
+    /// because the connection dropped.
+    pub fn finish(&mut self, events: &mut Vec<Event>) {
+        if !self.started || self.ended {
+            return;
+        }
+        if self.in_kyoku {
+            events.push(Event::EndKyoku);
+            self.in_kyoku = false;
+        }
+        events.push(end_game(None, None, TieBreak::SeatOrder));
+        self.ended = true;
+    }
+
+    fn init(&mut self, tag: &Tag<'_>, events: &mut Vec<Event>) -> Result<()> {
+        if !self.started {
+            let rules = self.rules;
+            events.push(Event::StartGame {
+                names: self.names.clone(),
+                kyoku_first: if rules.is_some_and(|r| !r.hanchan) {
+                    4
+                } else {
+                    0
+                },
+                aka_flag: rules.is_none_or(|r| r.aka),
+            });
+            self.started = true;
+        }
+        if self.in_kyoku {
+            events.push(Event::EndKyoku);
+        }
+
+        let seed = tag.attr("seed").ok_or_else(|| tag.missing("seed"))?;
+        let seed: Vec<u16> = seed
+            .split(',')
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| tag.invalid("seed", seed))?;
+        let &[round, honba, kyotaku, .., dora] = seed.as_slice() else {
+            return Err(tag.invalid("seed", tag.attr("seed").unwrap_or_default()));
+        };
+        let bakaze = match round / 4 {
+            0 => t!(E),
+            1 => t!(S),
+            2 => t!(W),
+            _ => t!(N),
+        };
+
+        let ten = tag.attr("ten").ok_or_else(|| tag.missing("ten"))?;
+        let mut scores = [0; 4];
+        for (score, s) in scores.iter_mut().zip(ten.split(',')) {
+            let hundreds: Points = s.parse().map_err(|_| tag.invalid("ten", ten))?;
+            *score = hundreds * 100;
+        }
+
+        let mut tehais: [Vec<Tile>; 4] = Default::default();
+        for (seat, tehai) in tehais.iter_mut().enumerate() {
+            let attr = ["hai0", "hai1", "hai2", "hai3"][seat];
+            if let Some(ids) = tag.attr(attr) {
+                *tehai = tiles(tag, attr, ids)?;
+            }
+        }
+
+        events.push(Event::StartKyoku {
+            bakaze,
+            dora_marker: tile(dora)?,
+            kyoku: (round % 4) as u8 + 1,
+            honba: small(tag, "seed", honba)?,
+            kyotaku: small(tag, "seed", kyotaku)?,
+            oya: tag.num("oya")?,
+            scores,
+            tehais,
+        });
+        self.in_kyoku = true;
+        self.ended_kyoku = false;
+        self.last_draw = [None; 4];
+        Ok(())
+    }
+
+    /// `T`, `U`, `V` and `W` are draws and `D`, `E`, `F` and `G` discards of
+    /// seats 0 to 3, followed by the tile id unless it is hidden. Lowercase
+    /// discards are tsumogiri.
+    fn draw_or_discard(&mut self, tag: &Tag<'_>, c: char, events: &mut Vec<Event>) -> Result<()> {
+        let (actor, draw) = match c {
+            'T' | 'U' | 'V' | 'W' => (c as u8 - b'T', true),
+            'D' | 'E' | 'F' | 'G' => (c as u8 - b'D', false),
+            'd' | 'e' | 'f' | 'g' => (c as u8 - b'd', false),
+            _ => return Ok(()),
+        };
+        self.check_in_kyoku(tag)?;
+        let id = if tag.digits.is_empty() {
+            None
+        } else {
+            Some(
+                tag.digits
+                    .parse()
+                    .map_err(|_| tag.invalid("tile", tag.digits))?,
+            )
+        };
+        let pai = id.map_or(Ok(t!(?)), tile)?;
+
+        let last_draw = &mut self.last_draw[actor as usize];
+        if draw {
+            *last_draw = id;
+            events.push(Event::Tsumo { actor, pai });
+        } else {
+            let tsumogiri = c.is_ascii_lowercase() || (id.is_some() && id == *last_draw);
+            *last_draw = None;
+            events.push(Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            });
+        }
+        Ok(())
+    }
+
+    fn end_kyoku(&mut self, tag: &Tag<'_>, events: &mut Vec<Event>) -> Result<()> {
+        self.ended_kyoku = true;
+        if let Some(owari) = tag.attr("owari") {
+            // The final score, in hundreds, and placement points of every seat.
+            let values: Vec<f64> = owari
+                .split(',')
+                .map(str::parse)
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|_| tag.invalid("owari", owari))?;
+            let mut scores = [0; 4];
+            let mut final_points = [0; 4];
+            for (seat, pair) in values.chunks_exact(2).take(4).enumerate() {
+                scores[seat] = (pair[0] * 100.0).round() as Points;
+                final_points[seat] = (pair[1] * 1000.0).round() as Points;
+            }
+            events.push(Event::EndKyoku);
+            // Tenhou ranks ties by seat.
+            events.push(end_game(
+                Some(scores),
+                Some(final_points),
+                TieBreak::SeatOrder,
+            ));
+            self.in_kyoku = false;
+            self.ended = true;
+        }
+        Ok(())
+    }
+
+    fn check_in_kyoku(&self, tag: &Tag<'_>) -> Result<()> {
+        // Further winners of a double ron still belong to the kyoku.
+        if self.in_kyoku && (!self.ended_kyoku || tag.name == "AGARI") {
+            Ok(())
+        } else {
+            Err(LiveError::OutsideKyoku(tag.name.to_owned()))
+        }
+    }
+}
+
+/// A tag split into its name, the digits that directly follow it as in
+/// `T50`, and its attributes.
+struct Tag<'a> {
+    name: &'a str,
+    digits: &'a str,
+    attrs: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Tag<'a> {
+    fn parse(s: &'a str) -> Self {
+        let s = s.trim().trim_end_matches('/');
+        let name_len = s
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(s.len());
+        let (name, rest) = s.split_at(name_len);
+        let digits_len = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (digits, mut rest) = rest.split_at(digits_len);
+
+        let mut attrs = vec![];
+        while let Some((key, value)) = rest.split_once("=\"") {
+            let Some((value, tail)) = value.split_once('"') else {
+                break;
+            };
+            attrs.push((key.trim(), value));
+            rest = tail;
+        }
+        Self {
+            name,
+            digits,
+            attrs,
+        }
+    }
+
+    fn attr(&self, key: &str) -> Option<&'a str> {
+        self.attrs.iter().find(|(k, _)| *k == key).map(|&(_, v)| v)
+    }
+
+    fn num<T: std::str::FromStr>(&self, attr: &'static str) -> Result<T> {
+        let value = self.attr(attr).ok_or_else(|| self.missing(attr))?;
+        value.parse().map_err(|_| self.invalid(attr, value))
+    }
+
+    /// The score changes of `sc`, given as pairs of the score before and the
+    /// change, in hundreds.
+    fn deltas(&self) -> Result<[Points; 4]> {
+        let sc = self.attr("sc").ok_or_else(|| self.missing("sc"))?;
+        let values: Vec<Points> = sc
+            .split(',')
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| self.invalid("sc", sc))?;
+        let mut deltas = [0; 4];
+        for (delta, pair) in deltas.iter_mut().zip(values.chunks_exact(2)) {
+            *delta = pair[1] * 100;
+        }
+        Ok(deltas)
+    }
+
+    /// The riichi sticks on the table at the end of the kyoku, the second
+    /// value of `ba`.
+    fn kyotaku(&self) -> Result<u8> {
+        let ba = self.attr("ba").ok_or_else(|| self.missing("ba"))?;
+        ba.split(',')
+            .nth(1)
+            .and_then(|sticks| sticks.parse().ok())
+            .ok_or_else(|| self.invalid("ba", ba))
+    }
+
+    fn missing(&self, attr: &'static str) -> LiveError {
+        LiveError::MissingAttr {
+            tag: self.name.to_owned(),
+            attr,
+        }
+    }
+
+    fn invalid(&self, attr: &'static str, value: &str) -> LiveError {
+        LiveError::InvalidAttr {
+            tag: self.name.to_owned(),
+            attr,
+            value: value.to_owned(),
+        }
+    }
+}
+
+fn small(tag: &Tag<'_>, attr: &'static str, n: u16) -> Result<u8> {
+    u8::try_from(n).map_err(|_| tag.invalid(attr, &n.to_string()))
+}
+
+fn tiles(tag: &Tag<'_>, attr: &'static str, ids: &str) -> Result<Vec<Tile>> {
+    ids.split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| tile(s.parse().map_err(|_| tag.invalid(attr, ids))?))
+        .collect()
+}
+
+/// Map a tile id, which counts the four copies of every kind from 0 in the
+/// order man, pin, sou and honors, to a sanma tile.
+fn tile(id: u16) -> Result<Tile> {
+    let kind = id / 4;
+    let tile_id = match id {
+        52 => tu8!(5pr),
+        88 => tu8!(5sr),
+        _ if kind == 0 => tu8!(1m),
+        _ if kind == 8 => tu8!(9m),
+        _ if (9..34).contains(&kind) => (kind - 9) as u8,
+        _ => return Err(TileError::number(id, "tenhou live tile ID").into()),
+    };
+    Ok(Tile::try_from(tile_id)?)
+}
+
+/// Decode the `m` field of an `N` tag, which packs the kind of the meld, the
+/// tiles it is made of and, relative to `actor`, the seat called from.
+fn decode_meld(actor: u8, m: u16) -> Result<Event> {
+    let target = (actor + (m & 3) as u8) % 4;
+    let invalid = || LiveError::InvalidMeld(m);
+
+    if m & 0x4 != 0 {
+        // Chi: the lowest kind of the run and which of the three was called,
+        // then the copy of each tile.
+        let t = m >> 10;
+        let called = t % 3;
+        let base = t / 3 / 7 * 9 + t / 3 % 7;
+        let ids: [u16; 3] = array::from_fn(|i| (base + i as u16) * 4 + ((m >> (3 + 2 * i)) & 3));
+        let [a, b] = others(ids, called as usize);
+        return Ok(Event::Chi {
+            actor,
+            target,
+            pai: tile(ids[called as usize])?,
+            consumed: [tile(a)?, tile(b)?],
+        });
+    }
+
+    if m & 0x18 != 0 {
+        // Pon and kakan: the kind and which of the three was called, and the
+        // copy left out of the pon.
+        let t = m >> 9;
+        let called = (t % 3) as usize;
+        let kind = t / 3;
+        if kind >= 34 {
+            return Err(invalid());
+        }
+        let unused = (m >> 5) & 3;
+        let mut ids = (0..4)
+            .filter(|&copy| copy != unused)
+            .map(|copy| kind * 4 + copy);
+        let ids: [u16; 3] = array::from_fn(|_| ids.next().unwrap_or_default());
+        let consumed = ids.map(tile);
+        let [a, b, c] = consumed;
+        if m & 0x8 != 0 {
+            let [x, y] = others(ids, called);
+            return Ok(Event::Pon {
+                actor,
+                target,
+                pai: tile(ids[called])?,
+                consumed: [tile(x)?, tile(y)?],
+            });
+        }
+        return Ok(Event::Kakan {
+            actor,
+            pai: tile(kind * 4 + unused)?,
+            consumed: [a?, b?, c?],
+        });
+    }
+
+    if m & 0x20 != 0 {
+        tile(m >> 8)?;
+        return Ok(Event::Nukidora {
+            actor,
+            consumed: [t!(N)],
+        });
+    }
+
+    let called = m >> 8;
+    let kind = called / 4;
+    if kind >= 34 {
+        return Err(invalid());
+    }
+    if m & 3 == 0 {
+        let ids: [u16; 4] = array::from_fn(|copy| kind * 4 + copy as u16);
+        let [a, b, c, d] = ids.map(tile);
+        return Ok(Event::Ankan {
+            actor,
+            consumed: [a?, b?, c?, d?],
+        });
+    }
+    let mut ids = (0..4)
+        .map(|copy| kind * 4 + copy)
+        .filter(|&id| id != called);
+    let ids: [u16; 3] = array::from_fn(|_| ids.next().unwrap_or_default());
+    let [a, b, c] = ids.map(tile);
+    Ok(Event::Daiminkan {
+        actor,
+        target,
+        pai: tile(called)?,
+        consumed: [a?, b?, c?],
+    })
+}
+
+/// The two of three tiles other than the one at `called`.
+fn others(ids: [u16; 3], called: usize) -> [u16; 2] {
+    let mut rest = ids.into_iter().enumerate().filter(|&(i, _)| i != called);
+    array::from_fn(|_| rest.next().map(|(_, id)| id).unwrap_or_default())
+}
+
+/// Decode the `%E3%81%82` escapes names are sent with.
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::tenhou::FinalResult;
+    use crate::testdata::Fixture;
+
+    /// [`Fixture::MultiRon`] as tenhou streams it.
+    const MULTI_RON: &str = concat!(
+        r#"<GO type="185" lobby="0"/><UN n0="Aoi" n1="Botan" n2="Chidori" n3="" "#,
+        r#"dan="16,16,16,0"/><TAIKYOKU oya="0"/>"#,
+        r#"<INIT seed="0,0,0,1,2,108" ten="350,350,350,0" oya="0" "#,
+        r#"hai0="0,1,32,33,64,68,72,76,104,112,116,120,121" "#,
+        r#"hai1="36,40,44,84,89,92,96,100,105,132,133,134,48" "#,
+        r#"hai2="53,56,60,73,77,80,128,129,130,124,125,126,49" hai3=""/>"#,
+        r#"<T50/><D50/>"#,
+        r#"<AGARI ba="0,0" hai="48,50" ten="40,1300,0" who="1" fromWho="0" "#,
+        r#"sc="350,-13,350,13,350,0,0,0"/>"#,
+        r#"<AGARI ba="0,0" hai="49,50" ten="50,3200,0" who="2" fromWho="0" "#,
+        r#"sc="337,-32,363,0,350,32,0,0" owari="305,-19.5,363,6.3,382,13.2,0,0"/>"#,
+    );
+
+    #[test]
+    fn multi_ron() {
+        let mut log = Fixture::MultiRon.log();
+        // The JSON of the fixture does not record the `owari` of the stream.
+        let owari = [(30500, -19.5), (36300, 6.3), (38200, 13.2), (0, 0.0)];
+        log.final_results = Some(owari.map(|(points, pt_delta)| FinalResult { points, pt_delta }));
+        let expected = tenhou_to_mjai(&log).unwrap();
+
+        // Split in the middle of tags.
+        let mut live = LiveConverter::new();
+        let mut events = vec![];
+        for chunk in MULTI_RON.as_bytes().chunks(7) {
+            live.push_str(std::str::from_utf8(chunk).unwrap(), &mut events)
+                .unwrap();
+        }
+        assert_eq!(events, expected);
+
+        live.finish(&mut events);
+        assert_eq!(events.len(), expected.len());
+    }
+
+    #[test]
+    fn melds() {
+        // Pon of the second of 1s, 2s and 3s from kamicha, 4s left out.
+        let m = (18 * 3 + 1) << 9 | 3 << 5 | 0x8 | 3;
+        assert_eq!(
+            decode_meld(1, m).unwrap(),
+            Event::Pon {
+                actor: 1,
+                target: 0,
+                pai: t!(1s),
+                consumed: [t!(1s), t!(1s)],
+            },
+        );
+        // The same pon upgraded with the aka five.
+        let m = (22 * 3) << 9 | 0x10;
+        assert!(matches!(
+            decode_meld(0, m).unwrap(),
+            Event::Kakan { pai, .. } if pai == t!(5sr)
+        ));
+        assert_eq!(
+            decode_meld(2, 123 << 8 | 0x20).unwrap(),
+            Event::Nukidora {
+                actor: 2,
+                consumed: [t!(N)],
+            },
+        );
+        assert_eq!(
+            decode_meld(0, 125 << 8 | 2).unwrap(),
+            Event::Daiminkan {
+                actor: 0,
+                target: 2,
+                pai: t!(P),
+                consumed: [t!(P), t!(P), t!(P)],
+            },
+        );
+        assert!(matches!(
+            decode_meld(0, 1 << 8).unwrap(),
+            Event::Ankan { consumed, .. } if consumed == [t!(1m); 4]
+        ));
+        decode_meld(0, 20 << 8).unwrap_err();
+    }
+
+    #[test]
+    fn tags() {
+        let mut live = LiveConverter::new();
+        let mut events = vec![];
+        live.push_str(r#"<UN n0="%E3%81%82" n1="b" n2="c" n3=""/>"#, &mut events)
+            .unwrap();
+        live.push_str("<T50/>", &mut events).unwrap_err();
+        live.push_str(
+            r#"<INIT seed="4,1,2,0,0,0" ten="350,350,350,0" oya="1" hai0="" hai1="" hai2=""/><U/><e/>"#,
+            &mut events,
+        )
+        .unwrap();
+        assert!(matches!(&events[0], Event::StartGame { names, .. } if names[0] == "あ"));
+        assert!(matches!(
+            events[1],
+            Event::StartKyoku {
+                bakaze,
+                kyoku: 1,
+                honba: 1,
+                kyotaku: 2,
+                ..
+            } if bakaze == t!(S)
+        ));
+        assert_eq!(
+            events[2..],
+            [
+                Event::Tsumo {
+                    actor: 1,
+                    pai: t!(?),
+                },
+                Event::Dahai {
+                    actor: 1,
+                    pai: t!(?),
+                    tsumogiri: true,
+                },
+            ],
+        );
+
+        events.clear();
+        live.finish(&mut events);
+        assert_eq!(
+            events,
+            [
+                Event::EndKyoku,
+                Event::EndGame {
+                    scores: None,
+                    ranks: None,
+                    final_points: None,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn ryukyoku() {
+        let mut live = LiveConverter::new();
+        let mut events = vec![];
+        live.push_str(
+            r#"<INIT seed="0,0,0,0,0,0" ten="350,350,350,0" oya="0" hai0="" hai1="" hai2=""/>"#,
+            &mut events,
+        )
+        .unwrap();
+        live.push_str(
+            r#"<RYUUKYOKU ba="0,0" sc="350,-10,350,20,350,-10,0,0" hai1="0,1,2"/>"#,
+            &mut events,
+        )
+        .unwrap();
+        assert_eq!(
+            events.last(),
+            Some(&Event::Ryukyoku {
+                reason: Some("fanpai".to_owned()),
+                deltas: Some([-1000, 2000, -1000, 0]),
+                tenpais: Some([false, true, false, false]),
+                kyotaku: Some(0),
+            }),
+        );
+
+        live.push_str(
+            r#"<INIT seed="0,1,0,0,0,0" ten="340,370,340,0" oya="0" hai0="" hai1="" hai2=""/>"#,
+            &mut events,
+        )
+        .unwrap();
+        live.push_str(
+            r#"<RYUUKYOKU type="yao9" ba="1,0" sc="340,0,370,0,340,0,0,0"/>"#,
+            &mut events,
+        )
+        .unwrap();
+        assert!(matches!(
+            events.last(),
+            Some(Event::Ryukyoku { reason: Some(reason), tenpais: None, .. }) if reason == "kyushukyuhai"
+        ));
+        let err = live
+            .push_str(
+                r#"<INIT seed="0,2,0,0,0,0" ten="340,370,340,0" oya="0" hai0="" hai1="" hai2=""/><RYUUKYOKU type="nine" sc=""/>"#,
+                &mut events,
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err, LiveError::InvalidAttr { attr: "type", .. }),
+            "{err}"
+        );
+    }
+}
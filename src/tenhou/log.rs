@@ -1,77 +1,391 @@
-use super::json_scheme::{ActionItem, KyokuMeta, RawLog, ResultItem};
-use crate::{KyokuFilter, Tile};
+use super::action::{ActionItem, ActionTable};
+use super::compat::{self, Version};
+use super::extract::ExtractError;
+use super::game_id::RuleFlags;
+use super::json_scheme::{
+    alias_names, hide_lobby_name, Connection, HoraField, KyokuMeta, RawLog, ResultItem,
+};
+use super::repair::{self, Repair};
+use super::rule_disp::RuleDisp;
+use super::score::Score;
+use super::yaku::{Han, InvalidYaku, Yaku};
+use crate::{Field, Fields, KyokuFilter, Points, Tile};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{self as json, Value};
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-pub enum ParseError {
-    #[error("invalid json: {source}")]
-    InvalidJSON {
-        #[from]
-        source: json::Error,
-    },
-    #[error("not three-player game")]
+
+/// A log that could not be read as a tenhou.net/6 log.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TenhouParseError {
+    InvalidJSON { source: json::Error },
     NotThreePlayer,
-    #[error("invalid hora detail")]
     InvalidHoraDetail,
+    InvalidYaku(InvalidYaku),
+    InvalidVersion(String),
+    UnsupportedVersion(Version),
+    Extract(ExtractError),
+    UnknownKeys(Vec<String>),
+}
+
+impl TenhouParseError {
+    /// A stable identifier of the kind of error, e.g.
+    /// `parse.invalid_json`, to branch on instead of the message.
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidJSON { .. } => "parse.invalid_json",
+            Self::NotThreePlayer => "parse.not_three_player",
+            Self::InvalidHoraDetail => "parse.invalid_hora_detail",
+            Self::InvalidYaku(_) => "parse.invalid_yaku",
+            Self::InvalidVersion(_) => "parse.invalid_version",
+            Self::UnsupportedVersion(_) => "parse.unsupported_version",
+            Self::Extract(_) => "parse.extract",
+            Self::UnknownKeys(_) => "parse.unknown_keys",
+        }
+    }
+}
+
+impl fmt::Display for TenhouParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidJSON { source } => write!(f, "invalid json: {source}"),
+            Self::NotThreePlayer => f.write_str("not three-player game"),
+            Self::InvalidHoraDetail => f.write_str("invalid hora detail"),
+            Self::InvalidYaku(err) => fmt::Display::fmt(err, f),
+            Self::InvalidVersion(version) => write!(f, "invalid version: {version}"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported version: {version} (newest supported is {})",
+                Version::CURRENT
+            ),
+            Self::Extract(err) => fmt::Display::fmt(err, f),
+            Self::UnknownKeys(keys) => write!(f, "unknown keys: {}", keys.join(", ")),
+        }
+    }
+}
+
+impl Error for TenhouParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidJSON { source } => Some(source),
+            Self::InvalidYaku(err) => err.source(),
+            Self::Extract(err) => err.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<json::Error> for TenhouParseError {
+    fn from(source: json::Error) -> Self {
+        Self::InvalidJSON { source }
+    }
+}
+
+impl From<InvalidYaku> for TenhouParseError {
+    fn from(err: InvalidYaku) -> Self {
+        Self::InvalidYaku(err)
+    }
+}
+
+impl From<ExtractError> for TenhouParseError {
+    fn from(err: ExtractError) -> Self {
+        Self::Extract(err)
+    }
 }
 
 /// The overview structure of log in tenhou.net/6 format.
 #[derive(Debug, Clone)]
 pub struct Log {
+    /// The "ver" field, absent in logs predating it.
+    pub version: Option<Version>,
+    /// The "ref" field, e.g. `2024030511gm-00b9-0000-e0c07689`.
+    pub game_id: Option<String>,
+    /// The private lobby the game was played in, `0` for the public one.
+    pub lobby: Option<i32>,
     pub names: [String; 4],
+    /// The "disp" field of "rule", e.g. `三鳳南喰赤`.
+    pub rule_disp: String,
+    /// Decoded from the game ID, if it has one.
+    pub rule_flags: Option<RuleFlags>,
+    /// The "ratingc" field, the rating segment of the lobby, e.g. `PF3`.
+    pub ratingc: Option<String>,
+    /// The "dan" field, the rank of every seat before the game, e.g. `七段`.
+    pub dan: Option<Vec<String>>,
+    /// The "rate" field, the R of every seat before the game.
+    pub rate: Option<Vec<f64>>,
     pub game_length: GameLength,
     pub has_aka: bool,
     pub kyokus: Vec<Kyoku>,
+    /// The "sc" field, absent in logs of games that did not finish.
+    pub final_results: Option<[FinalResult; 4]>,
+    /// The "connection" field, the disconnects and reconnects of the seats
+    /// in the order they happened.
+    pub connections: Vec<Connection>,
+    /// Set by [`Self::from_json_str_repaired`] if the log was cut off.
+    pub repair: Option<Repair>,
+}
+
+/// The result of a seat at the end of the game, as recorded by the server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct FinalResult {
+    /// The score, with the riichi sticks left on the table given to the top.
+    pub points: Points,
+    /// The placement points, uma and oka included, e.g. `43.1`.
+    pub pt_delta: f64,
 }
 
+impl FinalResult {
+    /// Parse the "sc" field, points and placement points interleaved for
+    /// every seat. Some exporters leave out the fourth seat. `None` if it is
+    /// malformed, which leaves the rest of the log readable.
+    fn parse_all(sc: &[f64]) -> Option<[Self; 4]> {
+        let integral = |p: f64| p.fract() == 0.0 && p.abs() < 2_f64.powi(53);
+        if !matches!(sc.len(), 6 | 8) || !sc.iter().step_by(2).all(|&p| integral(p)) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(?sc, "ignoring malformed final results");
+            return None;
+        }
+        let mut results = [Self::default(); 4];
+        for (result, pair) in results.iter_mut().zip(sc.chunks_exact(2)) {
+            *result = Self {
+                points: pair[0] as Points,
+                pt_delta: pair[1],
+            };
+        }
+        Some(results)
+    }
+}
+
+/// How many kyokus a game is scheduled to last, not counting renchan and
+/// repeats after ryukyoku.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum GameLength {
-    Hanchan = 0,
-    Tonpuu = 4,
+    /// East and south rounds.
+    Hanchan,
+    /// The east round only.
+    Tonpuu,
+    /// A single kyoku, e.g. a practice hand.
+    Ikkyoku,
+    /// Any other number of kyokus, as set up in custom lobbies.
+    Custom { kyokus: u8 },
+}
+
+impl GameLength {
+    /// The number of kyokus the game is scheduled to last in sanma.
+    #[inline]
+    #[must_use]
+    pub const fn kyokus(self) -> u8 {
+        match self {
+            Self::Hanchan => 6,
+            Self::Tonpuu => 3,
+            Self::Ikkyoku => 1,
+            Self::Custom { kyokus } => kyokus,
+        }
+    }
+
+    /// The `kyoku_first` field of the mjai `start_game` event, which only
+    /// knows hanchan (`0`) and tonpuu (`4`). Games that fit in the east round
+    /// count as tonpuu.
+    #[inline]
+    #[must_use]
+    pub const fn kyoku_first(self) -> u8 {
+        if self.kyokus() <= Self::Tonpuu.kyokus() {
+            4
+        } else {
+            0
+        }
+    }
+
+    /// Infer the game length from the kyokus played, for rule strings that
+    /// do not tell, e.g. custom lobby names.
+    ///
+    /// A game that reached the south round is taken to be a hanchan, though
+    /// a tonpuu can be extended into it too.
+    fn infer(kyokus: &[Kyoku]) -> Self {
+        if kyokus.iter().any(|k| k.meta.kyoku_num >= 4) {
+            Self::Hanchan
+        } else {
+            Self::Tonpuu
+        }
+    }
+}
+
+impl FromStr for GameLength {
+    type Err = String;
+
+    /// Parse `hanchan`, `tonpuu`, `ikkyoku` or a number of kyokus.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hanchan" => Ok(Self::Hanchan),
+            "tonpuu" => Ok(Self::Tonpuu),
+            "ikkyoku" => Ok(Self::Ikkyoku),
+            _ => match s.parse() {
+                Ok(1) => Ok(Self::Ikkyoku),
+                Ok(kyokus) if kyokus > 0 => Ok(Self::Custom { kyokus }),
+                _ => Err(format!(
+                    r#"invalid game length: {s:?} (expected "hanchan", "tonpuu", "ikkyoku" or a number of kyokus)"#
+                )),
+            },
+        }
+    }
 }
 
 /// Contains information about a kyoku.
 #[derive(Debug, Clone)]
 pub struct Kyoku {
     pub meta: KyokuMeta,
-    pub scoreboard: [i32; 4],
+    pub scoreboard: [Points; 4],
+    /// Always at least one, plus at most one more for each kan.
     pub dora_indicators: Vec<Tile>,
-    pub ura_indicators: Vec<Tile>,
+    /// `None` if no ura indicators were revealed, which is the case unless
+    /// the kyoku ended with a riichi hora. Never longer than
+    /// `dora_indicators` when present.
+    pub ura_indicators: Option<Vec<Tile>>,
     pub action_tables: [ActionTable; 4],
     pub end_status: EndStatus,
 }
 
 #[derive(Debug, Clone)]
 pub enum EndStatus {
-    Hora { details: Vec<HoraDetail> },
-    Ryukyoku { score_deltas: [i32; 4] },
+    Hora {
+        details: Vec<HoraDetail>,
+    },
+    Ryukyoku {
+        score_deltas: [Points; 4],
+        reason: RyukyokuReason,
+    },
+}
+
+/// Why a kyoku ended without a hora, from the status of its result.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum RyukyokuReason {
+    /// 流局, the wall ran out.
+    #[default]
+    Exhaustive,
+    /// 全員聴牌, the wall ran out with every seat tenpai.
+    AllTenpai,
+    /// 全員不聴, the wall ran out with no seat tenpai.
+    AllNoten,
+    /// 流し満貫
+    NagashiMangan,
+    /// 九種九牌
+    KyuushuKyuuhai,
+    /// 四家立直
+    SuuchaRiichi,
+    /// 三家和了
+    Sanchahou,
+    /// 四槓散了
+    Suukaikan,
+    /// 四風連打
+    Suufonrenda,
+    /// A status this crate does not know, as it is written.
+    Unknown(String),
+}
+
+const RYUKYOKU_STATUSES: [(&str, RyukyokuReason); 9] = [
+    ("流局", RyukyokuReason::Exhaustive),
+    ("全員聴牌", RyukyokuReason::AllTenpai),
+    ("全員不聴", RyukyokuReason::AllNoten),
+    ("流し満貫", RyukyokuReason::NagashiMangan),
+    ("九種九牌", RyukyokuReason::KyuushuKyuuhai),
+    ("四家立直", RyukyokuReason::SuuchaRiichi),
+    ("三家和了", RyukyokuReason::Sanchahou),
+    ("四槓散了", RyukyokuReason::Suukaikan),
+    ("四風連打", RyukyokuReason::Suufonrenda),
+];
+
+impl RyukyokuReason {
+    /// The reason of a result status such as `九種九牌`.
+    #[must_use]
+    pub fn from_status(status: &str) -> Option<Self> {
+        RYUKYOKU_STATUSES
+            .iter()
+            .find(|&&(s, _)| s == status)
+            .map(|(_, reason)| reason.clone())
+    }
+
+    /// The result status tenhou writes for the reason.
+    #[must_use]
+    pub fn status(&self) -> &str {
+        if let Self::Unknown(status) = self {
+            return status;
+        }
+        RYUKYOKU_STATUSES
+            .iter()
+            .find(|(_, r)| r == self)
+            .map_or("流局", |&(s, _)| s)
+    }
+
+    /// The `reason` of an mjai `ryukyoku` event, `fanpai` for an exhausted
+    /// wall and `None` for an unknown status.
+    #[must_use]
+    pub const fn mjai_reason(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::Exhaustive | Self::AllTenpai | Self::AllNoten => "fanpai",
+            Self::NagashiMangan => "nagashimangan",
+            Self::KyuushuKyuuhai => "kyushukyuhai",
+            Self::SuuchaRiichi => "suchareach",
+            Self::Sanchahou => "sanchaho",
+            Self::Suukaikan => "sukaikan",
+            Self::Suufonrenda => "sufonteki",
+            Self::Unknown(_) => return None,
+        })
+    }
+}
+
+impl fmt::Display for RyukyokuReason {
+    /// The result status, e.g. `九種九牌`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.status())
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct HoraDetail {
     pub who: u8,
     pub target: u8,
-    pub score_deltas: [i32; 4],
-}
-
-/// A group of "配牌", "取" and "出", describing a player's
-/// gaming status and actions throughout a kyoku.
-#[derive(Debug, Clone)]
-pub struct ActionTable {
-    pub haipai: Vec<Tile>,
-    pub takes: Vec<ActionItem>,
-    pub discards: Vec<ActionItem>,
+    pub score_deltas: [Points; 4],
+    /// The seat liable for the hora (責任払い), e.g. for feeding the last
+    /// dragon of a daisangen.
+    pub pao: Option<u8>,
+    /// The score text, e.g. `40符1飜400-700点` or `満貫8000点`.
+    pub label: String,
+    /// The parsed `label`, absent if the log has none or one that does not
+    /// parse, which [`Kyoku::check_scores`] reports.
+    pub score: Option<Score>,
+    /// The yaku in the order they are listed, dora included.
+    pub yaku: Vec<(Yaku, Han)>,
 }
 
 impl Log {
     /// Parse a tenhou.net/6 log from JSON string.
+    ///
+    /// Logs of older versions are normalized into the current schema first.
     #[inline]
-    pub fn from_json_str(json_string: &str) -> Result<Self, ParseError> {
-        let raw_log: RawLog = json::from_str(json_string)?;
-        Self::try_from(raw_log)
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "parse", skip_all, fields(bytes = json_string.len()))
+    )]
+    pub fn from_json_str(json_string: &str) -> Result<Self, TenhouParseError> {
+        Self::try_from(RawLog::from_json_str(json_string)?)
+    }
+
+    /// Parse a log like [`Self::from_json_str`], but first drop the kyokus at
+    /// its end that did not finish, as logs of games cut off by a disconnect
+    /// have, and record that in [`Self::repair`].
+    pub fn from_json_str_repaired(json_string: &str) -> Result<Self, TenhouParseError> {
+        let mut value: Value = json::from_str(json_string)?;
+        let repair = repair::drop_unfinished(&mut value);
+        compat::normalize(&mut value);
+        let raw_log = RawLog::deserialize(&value)?;
+        let mut log = Self::try_from(raw_log)?;
+        log.repair = repair;
+        Ok(log)
     }
 
     #[inline]
@@ -79,24 +393,191 @@ impl Log {
         self.kyokus
             .retain(|l| kyoku_filter.test(l.meta.kyoku_num, l.meta.honba));
     }
+
+    /// Keep only the groups of metadata `fields` has, see [`Fields`].
+    pub fn project(&mut self, fields: Fields) {
+        if !fields.contains(Field::Results) {
+            self.final_results = None;
+        }
+        if !fields.contains(Field::Names) {
+            alias_names(&mut self.names);
+        }
+        if !fields.contains(Field::Ratings) {
+            self.dan = None;
+            self.rate = None;
+            self.ratingc = None;
+        }
+        if !fields.contains(Field::Lobby) {
+            self.lobby = None;
+            hide_lobby_name(&mut self.rule_disp);
+        }
+        if !fields.contains(Field::Connections) {
+            self.connections.clear();
+        }
+        if !fields.contains(Field::GameId) {
+            self.game_id = None;
+        }
+    }
+
+    /// The names of the three seats, without the empty fourth of
+    /// [`Self::names`].
+    #[inline]
+    #[must_use]
+    pub fn players(&self) -> &[String] {
+        &self.names[..3]
+    }
 }
 
-impl TryFrom<RawLog> for Log {
-    type Error = ParseError;
+impl Kyoku {
+    /// [`Self::scoreboard`] without the empty fourth seat.
+    #[inline]
+    #[must_use]
+    pub const fn scores3(&self) -> [Points; 3] {
+        let [a, b, c, _] = self.scoreboard;
+        [a, b, c]
+    }
+
+    /// Count the kans declared in this kyoku, including the ones robbed by
+    /// chankan.
+    #[must_use]
+    pub fn kan_count(&self) -> usize {
+        let is_kan = |item: &ActionItem<'_>, marks: &[char]| matches!(item, ActionItem::Naki(naki) if naki.contains(marks));
+
+        self.action_tables
+            .iter()
+            .map(|table| {
+                let daiminkans = table.takes.iter().filter(|t| is_kan(t, &['m'])).count();
+                let other_kans = table
+                    .discards
+                    .iter()
+                    .filter(|d| is_kan(d, &['k', 'a']))
+                    .count();
+                daiminkans + other_kans
+            })
+            .sum()
+    }
+
+    /// Which seats were tenpai when the wall ran out, `None` for horas,
+    /// abortive draws, nagashi mangan and unknown statuses, whose deltas do
+    /// not tell.
+    ///
+    /// Noten payments go to the tenpai seats, so the seats that gained are
+    /// the tenpai ones, unless nobody paid.
+    #[must_use]
+    pub fn tenpais(&self) -> Option<[bool; 4]> {
+        let EndStatus::Ryukyoku {
+            score_deltas,
+            reason,
+        } = &self.end_status
+        else {
+            return None;
+        };
+        let seated = self.action_tables.each_ref().map(|t| !t.haipai.is_empty());
+        match reason {
+            RyukyokuReason::Exhaustive if score_deltas.iter().any(|&d| d != 0) => {
+                Some(score_deltas.map(|d| d > 0))
+            }
+            RyukyokuReason::AllTenpai => Some(seated),
+            RyukyokuReason::AllNoten => Some([false; 4]),
+            _ => None,
+        }
+    }
+}
 
-    fn try_from(raw_log: RawLog) -> Result<Self, Self::Error> {
+impl fmt::Display for Kyoku {
+    /// The round, dealer and result, e.g. `E1-0: dealer P0, ron 7700 P2←P0`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: dealer P{}, {}",
+            self.meta.short_name(),
+            self.meta.kyoku_num % 4,
+            self.end_status,
+        )
+    }
+}
+
+impl fmt::Display for EndStatus {
+    /// Every hora, separated by commas, or `ryukyoku`, its status unless the
+    /// wall ran out, and the seats that paid or were paid, e.g.
+    /// `ryukyoku P0 +1500, P1 -1500`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hora { details } => {
+                for (i, detail) in details.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{detail}")?;
+                }
+                Ok(())
+            }
+            Self::Ryukyoku {
+                score_deltas,
+                reason,
+            } => {
+                f.write_str("ryukyoku")?;
+                if *reason != RyukyokuReason::Exhaustive {
+                    write!(f, " {reason}")?;
+                }
+                let paid = score_deltas.iter().enumerate().filter(|&(_, &d)| d != 0);
+                for (i, (who, delta)) in paid.enumerate() {
+                    let sep = if i > 0 { "," } else { "" };
+                    write!(f, "{sep} P{who} {delta:+}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for HoraDetail {
+    /// The kind of hora, what the winner got and from whom, e.g.
+    /// `ron 7700 P2←P0`, `tsumo 12000 P1` or `ron 32000 P0←P2 (pao P1)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { who, target, .. } = *self;
+        let gain = self
+            .score_deltas
+            .get(usize::from(who))
+            .copied()
+            .unwrap_or_default();
+        if who == target {
+            write!(f, "tsumo {gain} P{who}")?;
+        } else {
+            write!(f, "ron {gain} P{who}←P{target}")?;
+        }
+        if let Some(pao) = self.pao {
+            write!(f, " (pao P{pao})")?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<RawLog<'_>> for Log {
+    type Error = TenhouParseError;
+
+    fn try_from(raw_log: RawLog<'_>) -> Result<Self, Self::Error> {
         let RawLog {
-            logs, names, rule, ..
+            logs,
+            names,
+            rule,
+            ver,
+            game_id,
+            lobby,
+            ratingc,
+            dan,
+            rate,
+            sc,
+            connection,
+            ..
         } = raw_log;
 
-        if rule.disp.contains('四') || rule.disp.contains("4-Player") {
-            return Err(ParseError::NotThreePlayer);
+        let version = Version::detect(ver.as_ref())?;
+
+        let rules = RuleDisp::parse(&rule.disp);
+        if rules.sanma == Some(false) {
+            return Err(TenhouParseError::NotThreePlayer);
         }
-        let game_length = if rule.disp.contains('東') || rule.disp.contains("East") {
-            GameLength::Tonpuu
-        } else {
-            GameLength::Hanchan
-        };
         let has_aka = rule.aka + rule.aka51 + rule.aka52 + rule.aka53 > 0;
 
         let mut kyokus = Vec::with_capacity(logs.len());
@@ -105,31 +586,32 @@ impl TryFrom<RawLog> for Log {
                 meta: log.meta,
                 scoreboard: log.scoreboard,
                 dora_indicators: log.dora_indicators,
-                ura_indicators: log.ura_indicators,
+                ura_indicators: Some(log.ura_indicators).filter(|ura| !ura.is_empty()),
                 action_tables: [
                     ActionTable {
-                        haipai: log.haipai_0,
-                        takes: log.takes_0,
-                        discards: log.discards_0,
+                        haipai: log.haipai_0.into_iter().collect(),
+                        takes: owned_items(log.takes_0),
+                        discards: owned_items(log.discards_0),
                     },
                     ActionTable {
-                        haipai: log.haipai_1,
-                        takes: log.takes_1,
-                        discards: log.discards_1,
+                        haipai: log.haipai_1.into_iter().collect(),
+                        takes: owned_items(log.takes_1),
+                        discards: owned_items(log.discards_1),
                     },
                     ActionTable {
-                        haipai: log.haipai_2,
-                        takes: log.takes_2,
-                        discards: log.discards_2,
+                        haipai: log.haipai_2.into_iter().collect(),
+                        takes: owned_items(log.takes_2),
+                        discards: owned_items(log.discards_2),
                     },
                     ActionTable {
-                        haipai: log.haipai_3,
-                        takes: log.takes_3,
-                        discards: log.discards_3,
+                        haipai: log.haipai_3.into_iter().collect(),
+                        takes: owned_items(log.takes_3),
+                        discards: owned_items(log.discards_3),
                     },
                 ],
                 end_status: EndStatus::Ryukyoku {
                     score_deltas: [0; 4], // default
+                    reason: RyukyokuReason::Exhaustive,
                 },
             };
 
@@ -140,53 +622,100 @@ impl TryFrom<RawLog> for Log {
                         if let [ResultItem::ScoreDeltas(score_deltas), ResultItem::HoraDetail(who_target_tuple)] =
                             detail_tuple
                         {
-                            let who = if let Some(Value::Number(n)) = who_target_tuple.first() {
-                                n.as_u64().unwrap_or(0) as u8
-                            } else {
-                                return Err(ParseError::InvalidHoraDetail);
-                            };
-                            let target = if let Some(Value::Number(n)) = who_target_tuple.get(1) {
-                                n.as_u64().unwrap_or(0) as u8
-                            } else {
-                                return Err(ParseError::InvalidHoraDetail);
-                            };
+                            let who = hora_seat(who_target_tuple.first())?;
+                            let target = hora_seat(who_target_tuple.get(1))?;
+                            // The third number is the liable seat, which is the
+                            // winner itself if there is none.
+                            let pao = who_target_tuple
+                                .get(2)
+                                .and_then(HoraField::as_u64)
+                                .map(|n| n as u8)
+                                .filter(|&p| p != who);
+                            let label = who_target_tuple
+                                .get(3)
+                                .and_then(HoraField::as_str)
+                                .unwrap_or_default()
+                                .to_owned();
+                            let score = label.parse().ok();
+                            let yaku = who_target_tuple
+                                .iter()
+                                .skip(4)
+                                .filter_map(HoraField::as_str)
+                                .map(Yaku::parse_with_han)
+                                .collect::<Result<_, _>>()?;
                             let hora_detail = HoraDetail {
                                 score_deltas: *score_deltas,
                                 who,
                                 target,
+                                pao,
+                                label,
+                                score,
+                                yaku,
                             };
                             details.push(hora_detail);
                         }
                     }
                     kyoku.end_status = EndStatus::Hora { details };
                 } else {
+                    let reason = RyukyokuReason::from_status(status_text)
+                        .unwrap_or_else(|| RyukyokuReason::Unknown(status_text.to_string()));
                     let score_deltas =
                         if let Some(ResultItem::ScoreDeltas(dts)) = log.results.get(1) {
                             *dts
                         } else {
                             [0; 4]
                         };
-                    kyoku.end_status = EndStatus::Ryukyoku { score_deltas };
+                    kyoku.end_status = EndStatus::Ryukyoku {
+                        score_deltas,
+                        reason,
+                    };
                 }
             }
 
             kyokus.push(kyoku);
         }
 
+        let game_length = rules.length.unwrap_or_else(|| GameLength::infer(&kyokus));
+        let final_results = sc.as_deref().and_then(FinalResult::parse_all);
+
         Ok(Self {
-            names,
+            version,
+            rule_flags: game_id.as_deref().and_then(RuleFlags::from_game_id),
+            game_id: game_id.map(Cow::into_owned),
+            lobby,
+            names: names.map(Cow::into_owned),
+            rule_disp: rule.disp.into_owned(),
+            ratingc: ratingc.map(Cow::into_owned),
+            dan: dan.map(|dan| dan.into_iter().map(Cow::into_owned).collect()),
+            rate,
             game_length,
             has_aka,
             kyokus,
+            final_results,
+            connections: connection.unwrap_or_default(),
+            repair: None,
         })
     }
 }
 
-#[cfg(test)]
+/// The items of an action table of a raw log, copied out of the JSON.
+fn owned_items(items: Vec<ActionItem<'_>>) -> Box<[ActionItem<'static>]> {
+    items.into_iter().map(ActionItem::into_owned).collect()
+}
 
-mod test
-{
+/// The seat of the winner or of the one paying in a hora detail.
+fn hora_seat(field: Option<&HoraField<'_>>) -> Result<u8, TenhouParseError> {
+    field
+        .and_then(HoraField::as_u64)
+        .filter(|&seat| seat < 4)
+        .map(|seat| seat as u8)
+        .ok_or(TenhouParseError::InvalidHoraDetail)
+}
+
+#[cfg(test)]
+mod test {
     use super::*;
+    use crate::tenhou::{StickRule, Turn};
 
     #[test]
     fn test_parse_log() {
@@ -197,6 +726,207 @@ mod test
         assert_eq!(log.names[1], "つくねん3");
         assert_eq!(log.names[2], "ひぐお3");
         assert_eq!(log.names[3], "");
+        assert_eq!(
+            log.game_id.as_deref(),
+            Some("2024030511gm-00b9-0000-e0c07689")
+        );
+        assert_eq!(log.lobby, Some(0));
         assert_eq!(log.kyokus.len(), 11);
+        assert_eq!(log.version, Some(Version::CURRENT));
+
+        assert_eq!(log.kyokus[0].ura_indicators, None);
+        assert_eq!(log.kyokus[4].ura_indicators.as_ref().map(Vec::len), Some(1));
+        assert_eq!(log.kyokus[9].kan_count(), 1);
+        assert_eq!(log.kyokus[9].dora_indicators.len(), 2);
+        assert_eq!(
+            log.kyokus[3].horas()[0].yaku[2],
+            (Yaku::Honitsu, Han::Regular(2)),
+        );
+        let json = json_str.replace("混一色(2飜)", "焼き鳥(2飜)");
+        let log = Log::from_json_str(&json).unwrap();
+        assert_eq!(
+            log.kyokus[3].horas()[0].yaku[2],
+            (Yaku::Unknown("焼き鳥".to_owned()), Han::Regular(2)),
+        );
+        log.check_sticks(StickRule::default()).unwrap();
+        log.check_payers().unwrap();
+        log.check_ledger().unwrap();
+
+        assert_eq!(
+            log.rule_flags.map(|f| f.room),
+            Some(crate::tenhou::Room::Houou)
+        );
+        log.check_rule_flags().unwrap();
+        let mut edited = log.clone();
+        edited.rule_disp = "三鳳東喰赤".to_owned();
+        edited.check_rule_flags().unwrap_err();
+    }
+
+    #[test]
+    fn game_length() {
+        let log = |disp: &str, kyoku_num: u8| {
+            let json_str = format!(
+                r#"{{"log":[[[{kyoku_num},0,0],[35000,35000,35000,0],[47],[],[],[],[],[],[],[],[],[],[],[],[],[],["流局",[0,0,0,0]]]],"name":["","","",""],"rule":{{"disp":"{disp}"}}}}"#
+            );
+            Log::from_json_str(&json_str).unwrap().game_length
+        };
+
+        assert_eq!(log("三鳳南喰赤", 0), GameLength::Hanchan);
+        assert_eq!(log("三般東喰赤", 4), GameLength::Tonpuu);
+        assert_eq!(log("Phoenix South", 0), GameLength::Hanchan);
+        // Custom lobby names naming both or neither.
+        assert_eq!(log("東京南部リーグ", 4), GameLength::Hanchan);
+        assert_eq!(log("東京南部リーグ", 2), GameLength::Tonpuu);
+        assert_eq!(log("", 5), GameLength::Hanchan);
+
+        assert_eq!(log("一局戦", 0), GameLength::Ikkyoku);
+        // A sanma lobby whose name happens to hold 四 and 東.
+        assert_eq!(log("四川東部🀄", 4), GameLength::Hanchan);
+        let yonma = r#"{"log":[],"name":["","","",""],"rule":{"disp":"四鳳南喰赤"}}"#;
+        let err = Log::from_json_str(yonma).unwrap_err();
+        assert!(matches!(err, TenhouParseError::NotThreePlayer), "{err}");
+
+        assert_eq!("tonpuu".parse(), Ok(GameLength::Tonpuu));
+        assert_eq!("1".parse(), Ok(GameLength::Ikkyoku));
+        assert_eq!("9".parse(), Ok(GameLength::Custom { kyokus: 9 }));
+        "east".parse::<GameLength>().unwrap_err();
+        "0".parse::<GameLength>().unwrap_err();
+
+        assert_eq!(GameLength::Hanchan.kyoku_first(), 0);
+        assert_eq!(GameLength::Tonpuu.kyoku_first(), 4);
+        assert_eq!(GameLength::Ikkyoku.kyoku_first(), 4);
+        assert_eq!(GameLength::Custom { kyokus: 9 }.kyoku_first(), 0);
+    }
+
+    #[test]
+    fn parse_legacy_log() {
+        // No "ver" and "rule", three names and three action tables.
+        let json_str = r#"{"log":[[[0,0,0],[35000,35000,35000],[47],[],[26,27,32,33,35,37,37,39,41,42,44,44,46],[45,47,19,39,27,34,21,43,26],[60,60,60,60,60,60,"f44","f44",42],[11,19,21,23,29,31,33,41,42,44,44,45,46],[42,28,22,19,"4242p42",23,22,24],["f44",33,"f44",60,19,31,11,41],[19,21,29,29,29,31,34,34,38,41,43,47,47],[21,"47p4747",42,35,27,26,"34p3434",25],[19,43,60,31,41,38,35],["和了",[-700,-400,1100],[2,2,2,"40符1飜400-700点","役牌 中(1飜)"]]]],"name":["mtk","つくねん3","ひぐお3"]}"#;
+        let log = Log::from_json_str(json_str).unwrap();
+        assert_eq!(log.version, None);
+        assert_eq!(log.names[3], "");
+        assert_eq!(log.kyokus[0].scoreboard, [35000, 35000, 35000, 0]);
+        assert_eq!(log.kyokus[0].scores3(), [35000; 3]);
+        assert_eq!(log.players(), ["mtk", "つくねん3", "ひぐお3"]);
+        assert!(log.kyokus[0].action_tables[3].haipai.is_empty());
+
+        let err = Log::from_json_str(r#"{"ver":3.0,"log":[],"name":["","","",""]}"#).unwrap_err();
+        assert!(matches!(err, TenhouParseError::UnsupportedVersion(_)));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let json = crate::testdata::Fixture::Hanchan.json();
+
+        // Logs that need no normalizing fail with the error of the first
+        // read, which tells where.
+        let err = Log::from_json_str(&json.replace(r#""lobby":0"#, r#""lobby":"x""#)).unwrap_err();
+        assert!(err.to_string().contains("at line 1 column"), "{err}");
+
+        // The winner and the seat paying must be seats.
+        for who in ["4", "-1", "1.5", r#""2""#] {
+            let json = json.replace(
+                r#"[2,2,2,"40符1飜400-700点""#,
+                &format!(r#"[{who},2,2,"40符1飜400-700点""#),
+            );
+            let err = Log::from_json_str(&json).unwrap_err();
+            assert!(
+                matches!(err, TenhouParseError::InvalidHoraDetail),
+                "{who}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn turns() {
+        // Tenhou writes a `0` in place of the discard of a daiminkan, other
+        // sources nothing.
+        let placeholder = crate::testdata::Fixture::KanHeavy.json();
+        let json = placeholder.replace(r#"[0,38,"f44",29]"#, r#"[38,"f44",29]"#);
+        assert_ne!(placeholder, json);
+        let mut tables = vec![];
+        for json in [&json, placeholder] {
+            let log = Log::from_json_str(json).unwrap();
+            let mut daiminkans = 0;
+            for table in &log.kyokus[0].action_tables {
+                let turns: Vec<_> = table.turns().collect();
+                assert_eq!(turns.len(), table.takes.len());
+                for turn in &turns {
+                    if matches!(turn.take, ActionItem::Naki(naki) if naki.contains('m')) {
+                        assert!(turn.discard.is_none());
+                        daiminkans += 1;
+                    }
+                }
+                let discards: Vec<_> = turns
+                    .iter()
+                    .filter_map(|turn| turn.discard.cloned())
+                    .collect();
+                let tsumogiris: Vec<_> = turns.iter().map(Turn::tsumogiri).collect();
+                let tiles: Vec<_> = turns.iter().map(Turn::discarded_tile).collect();
+                tables.push((discards, tsumogiris, tiles));
+            }
+            assert_eq!(daiminkans, 1);
+        }
+        let (plain, with_placeholder) = tables.split_at(4);
+        assert_eq!(plain, with_placeholder);
+        assert_eq!(
+            crate::conv::tenhou_to_mjai(&Log::from_json_str(&json).unwrap()).unwrap(),
+            crate::conv::tenhou_to_mjai(&Log::from_json_str(placeholder).unwrap()).unwrap()
+        );
+
+        let log = Log::from_json_str(crate::testdata::Fixture::Hanchan.json()).unwrap();
+        let tsumogiri: Vec<_> = log.kyokus[0].action_tables[0]
+            .turns()
+            .map(|turn| turn.tsumogiri())
+            .collect();
+        let mut expected = vec![Some(true); 6];
+        expected.extend([None, None, Some(false)]);
+        assert_eq!(tsumogiri, expected);
+        let discarded: Vec<_> = log.kyokus[0].action_tables[0]
+            .turns()
+            .filter_map(|turn| turn.discarded_tile().map(|pai| pai.to_string()))
+            .collect();
+        assert_eq!(discarded, ["P", "C", "9m", "9s", "7p", "4s", "S"]);
+
+        assert_eq!(log.kyokus[4].action_tables[2].riichi_index(), Some(6));
+        let mut table = log.kyokus[4].action_tables[2].clone();
+        assert!(!table.is_double_riichi());
+        table.discards[6] = ActionItem::Tile(crate::t!(7s));
+        table.discards[0] = ActionItem::Riichi(None);
+        assert!(table.is_double_riichi());
+        assert!(!log.kyokus[0].action_tables[0].is_double_riichi());
+    }
+
+    #[test]
+    fn display() {
+        let mut log = Log::from_json_str(crate::testdata::Fixture::Hanchan.json()).unwrap();
+        assert_eq!(log.kyokus[0].to_string(), "E1-0: dealer P0, tsumo 1100 P2");
+        assert_eq!(log.kyokus[1].to_string(), "E2-0: dealer P1, ron 8700 P1←P2");
+
+        let tables = &log.kyokus[0].action_tables;
+        assert_eq!(
+            format!("{:?}", &tables[1].takes[..5]),
+            "[S, 8p, 2p, 9m, 4242p42]"
+        );
+        assert_eq!(tables[0].discards[0].to_string(), "tsumogiri");
+
+        log.kyokus[0].end_status = EndStatus::Ryukyoku {
+            score_deltas: [1500, -1500, 0, 0],
+            reason: RyukyokuReason::Exhaustive,
+        };
+        assert_eq!(
+            log.kyokus[0].to_string(),
+            "E1-0: dealer P0, ryukyoku P0 +1500, P1 -1500"
+        );
+        assert_eq!(log.kyokus[0].tenpais(), Some([true, false, false, false]));
+        log.kyokus[0].end_status = EndStatus::Ryukyoku {
+            score_deltas: [0; 4],
+            reason: RyukyokuReason::KyuushuKyuuhai,
+        };
+        assert_eq!(
+            log.kyokus[0].to_string(),
+            "E1-0: dealer P0, ryukyoku 九種九牌"
+        );
+        assert_eq!(log.kyokus[0].tenpais(), None);
     }
 }
@@ -1,7 +1,79 @@
+mod action;
+#[cfg(feature = "std")]
+mod canonical;
+#[cfg(feature = "std")]
+mod compat;
+#[cfg(feature = "std")]
+mod dedupe;
+#[cfg(feature = "std")]
+mod extract;
+#[cfg(feature = "std")]
+mod game_id;
+#[cfg(feature = "std")]
 mod json_scheme;
+#[cfg(feature = "std")]
+mod ledger;
+#[cfg(feature = "std")]
+mod live;
+#[cfg(feature = "std")]
 mod log;
+#[cfg(feature = "std")]
+mod rating;
+#[cfg(feature = "std")]
+mod repair;
+#[cfg(feature = "std")]
+mod rule_disp;
+#[cfg(feature = "std")]
+mod rules;
+#[cfg(feature = "std")]
+mod score;
 mod tile;
+#[cfg(feature = "std")]
+mod yaku;
 
-pub use json_scheme::{ActionItem, KyokuMeta, RawLog, RawPartialLog};
-pub use log::{ActionTable, EndStatus, GameLength, HoraDetail, Kyoku, Log};
+use crate::Points;
+
+pub use action::{ActionItem, ActionTable, Discard, Turn};
+#[cfg(feature = "std")]
+pub use compat::Version;
+#[cfg(feature = "std")]
+pub use dedupe::Deduper;
+#[cfg(feature = "std")]
+pub use extract::{extract_json, ExtractError};
+#[cfg(feature = "std")]
+pub use game_id::{started_at, Room, RuleFlags};
+#[cfg(feature = "std")]
+pub use json_scheme::{
+    Connection, HoraField, KyokuMeta, RawKyoku, RawLog, RawPartialLog, UnknownKeys,
+};
+#[cfg(feature = "std")]
+pub use ledger::LedgerEntry;
+#[cfg(feature = "std")]
+pub use live::{LiveConverter, LiveError};
+#[cfg(feature = "std")]
+pub use log::{
+    EndStatus, FinalResult, GameLength, HoraDetail, Kyoku, Log, RyukyokuReason, TenhouParseError,
+};
+#[cfg(feature = "std")]
+pub use rating::{dan_points, rate_change, Dan, InvalidDan, RatingChange};
+#[cfg(feature = "std")]
+pub use repair::Repair;
+#[cfg(feature = "std")]
+pub use rule_disp::RuleDisp;
+#[cfg(feature = "std")]
+pub(crate) use rules::HONBA;
+#[cfg(feature = "std")]
+pub use rules::{RuleError, ScoreComparison, StickRule};
+#[cfg(feature = "std")]
+pub use score::{InvalidScore, Limit, Payment, Rounding, Score, ScoringRules, TsumoPayment};
 pub(crate) use tile::TenhouTile;
+#[cfg(feature = "std")]
+pub use yaku::{Han, InvalidYaku, Yaku};
+
+/// Value of a single riichi stick.
+pub(crate) const RIICHI_STICK: Points = 1000;
+
+/// The error of reading a tenhou log, under its old name.
+#[cfg(feature = "std")]
+#[deprecated(note = "renamed to `TenhouParseError`")]
+pub type ParseError = TenhouParseError;
@@ -0,0 +1,210 @@
+//! Estimates of how a game moved the rating (R) and dan points of its
+//! players on tenhou, by the formulas of sanma ranked games.
+
+use super::game_id::Room;
+use super::log::{GameLength, Log};
+use crate::ruleset::Ruleset;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// A rank on tenhou, counted up from 新人: 9級 to 1級 are 1 to 9, 初段 to
+/// 十段 10 to 19 and 天鳳 20.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Dan(u8);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDan(pub String);
+
+impl fmt::Display for InvalidDan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid dan: {:?}", self.0)
+    }
+}
+
+impl Error for InvalidDan {}
+
+const DAN_NAMES: [&str; 21] = [
+    "新人", "9級", "8級", "7級", "6級", "5級", "4級", "3級", "2級", "1級", "初段", "二段", "三段",
+    "四段", "五段", "六段", "七段", "八段", "九段", "十段", "天鳳",
+];
+
+impl Dan {
+    pub const SHODAN: Self = Self(10);
+    pub const TENHOU: Self = Self(20);
+
+    /// The name tenhou writes in `dan`, e.g. `七段`.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        DAN_NAMES[self.0 as usize]
+    }
+}
+
+impl FromStr for Dan {
+    type Err = InvalidDan;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Some exporters write kyu with full-width digits.
+        let s: String = s
+            .chars()
+            .map(|c| match c {
+                '０'..='９' => char::from_u32(u32::from(c) - 0xfee0).unwrap_or(c),
+                c => c,
+            })
+            .collect();
+        DAN_NAMES
+            .iter()
+            .position(|&name| name == s)
+            .map(|i| Self(i as u8))
+            .ok_or(InvalidDan(s))
+    }
+}
+
+/// What a placement, counting from 0, is worth in R before corrections.
+const PLACEMENT_RATE: [f64; 3] = [30.0, 0.0, -30.0];
+
+/// The estimated change of the R of a player rated `rate` who finished at
+/// `placement`, counting from 0, at a table rated `table_average` on
+/// average, themselves included. `games` is how many ranked games they had
+/// played before, which damps the change once there are many; unknown
+/// counts are taken as 400 or more, where it stops changing.
+#[must_use]
+pub fn rate_change(rate: f64, table_average: f64, placement: usize, games: Option<u32>) -> f64 {
+    let correction = match games {
+        Some(games) if games < 400 => f64::from(games).mul_add(-0.002, 1.0),
+        _ => 0.2,
+    };
+    correction * (PLACEMENT_RATE[placement] + (table_average - rate) / 40.0)
+}
+
+/// The dan points a top earns, by room from 一般 to 鳳凰.
+const TOP_POINTS_TONPUU: [i32; 4] = [25, 35, 45, 55];
+const TOP_POINTS_HANCHAN: [i32; 4] = [40, 55, 70, 85];
+/// The dan points the last loses, by rank from 新人; ranks up to 4級 lose
+/// none.
+const LAST_POINTS_TONPUU: [i32; 20] = [
+    0, 0, 0, 0, 0, 0, 0, 10, 15, 20, 30, 40, 50, 60, 70, 80, 90, 100, 110, 120,
+];
+const LAST_POINTS_HANCHAN: [i32; 20] = [
+    0, 0, 0, 0, 0, 0, 0, 15, 30, 30, 45, 60, 75, 90, 105, 120, 135, 150, 165, 180,
+];
+
+/// The dan points a player of rank `dan` gets for finishing at `placement`,
+/// counting from 0, in a ranked game in `room`. `None` for 天鳳, which has no
+/// points to gain or lose, and for games neither tonpuu nor hanchan.
+#[must_use]
+pub fn dan_points(dan: Dan, room: Room, length: GameLength, placement: usize) -> Option<i32> {
+    if dan >= Dan::TENHOU {
+        return None;
+    }
+    let (top, last) = match length {
+        GameLength::Tonpuu => (&TOP_POINTS_TONPUU, &LAST_POINTS_TONPUU),
+        GameLength::Hanchan => (&TOP_POINTS_HANCHAN, &LAST_POINTS_HANCHAN),
+        GameLength::Ikkyoku | GameLength::Custom { .. } => return None,
+    };
+    let room = match room {
+        Room::Ippan => 0,
+        Room::Joukyuu => 1,
+        Room::Tokujou => 2,
+        Room::Houou => 3,
+    };
+    Some(match placement {
+        0 => top[room],
+        1 => 0,
+        _ => -last[usize::from(dan.0)],
+    })
+}
+
+/// How a game moved a seat on tenhou, as far as the log tells.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RatingChange {
+    pub seat: u8,
+    /// Counting from 1.
+    pub placement: u8,
+    /// The R before the game, from `rate`.
+    pub rate: Option<f64>,
+    /// See [`rate_change`], with the number of games unknown.
+    pub rate_change: Option<f64>,
+    /// The rank before the game, from `dan`.
+    pub dan: Option<String>,
+    /// See [`dan_points`].
+    pub dan_points: Option<i32>,
+}
+
+impl Log {
+    /// The estimated [`RatingChange`] of every seat, placed by their final
+    /// scores as tenhou places them. `None` for games without final scores
+    /// and logs of other platforms, which record neither `rate` nor `dan`.
+    #[must_use]
+    pub fn rating_changes(&self) -> Option<[RatingChange; 3]> {
+        if self.rate.is_none() && self.dan.is_none() {
+            return None;
+        }
+        let scores = match self.final_results {
+            Some(results) => results.map(|r| r.points),
+            None => self.final_scores().ok()??,
+        };
+        let placements = Ruleset::TENHOU_SANMA.placements([scores[0], scores[1], scores[2]]);
+        let rates = self.rate.as_deref().filter(|rates| rates.len() >= 3);
+        let table_average = rates.map(|rates| rates[..3].iter().sum::<f64>() / 3.0);
+        let room = self.room();
+        Some(std::array::from_fn(|seat| {
+            let placement = placements[seat];
+            let rate = rates.map(|rates| rates[seat]);
+            let dan = self.dan.as_ref().and_then(|dan| dan.get(seat));
+            let dan_points = dan
+                .and_then(|dan| dan.parse().ok())
+                .zip(room)
+                .and_then(|(dan, room)| dan_points(dan, room, self.game_length, placement));
+            RatingChange {
+                seat: seat as u8,
+                placement: placement as u8 + 1,
+                rate,
+                rate_change: rate
+                    .zip(table_average)
+                    .map(|(rate, average)| rate_change(rate, average, placement, None)),
+                dan: dan.cloned(),
+                dan_points,
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn rating_changes() {
+        assert_eq!("七段".parse(), Ok(Dan(16)));
+        assert_eq!("３級".parse::<Dan>().unwrap().name(), "3級");
+        assert_eq!("天鳳".parse(), Ok(Dan::TENHOU));
+        "名人".parse::<Dan>().unwrap_err();
+
+        assert!((rate_change(1500.0, 1500.0, 0, Some(0)) - 30.0).abs() < 1e-9);
+        assert!((rate_change(1700.0, 1500.0, 2, None) - -7.0).abs() < 1e-9);
+        let hanchan = |dan, placement| dan_points(dan, Room::Houou, GameLength::Hanchan, placement);
+        assert_eq!(hanchan(Dan::SHODAN, 0), Some(85));
+        assert_eq!(hanchan(Dan::SHODAN, 2), Some(-45));
+        assert_eq!(hanchan(Dan::TENHOU, 2), None);
+        assert_eq!(
+            dan_points(Dan(3), Room::Ippan, GameLength::Tonpuu, 2),
+            Some(0)
+        );
+
+        // 七段, 天鳳 and 八段, finishing in seat order.
+        let changes = Fixture::Hanchan.log().rating_changes().unwrap();
+        let placements = changes.each_ref().map(|c| c.placement);
+        assert_eq!(placements, [1, 2, 3]);
+        let tenths = changes
+            .each_ref()
+            .map(|c| (c.rate_change.unwrap() * 10.0).round() as i32);
+        assert_eq!(tenths, [64, -8, -56]);
+        let points = changes.each_ref().map(|c| c.dan_points);
+        assert_eq!(points, [Some(85), None, Some(-150)]);
+        assert_eq!(changes[0].dan.as_deref(), Some("七段"));
+    }
+}
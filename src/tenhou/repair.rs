@@ -0,0 +1,95 @@
+//! Repair of logs of games cut off mid-kyoku, e.g. by a disconnect, which
+//! otherwise fail to parse or convert.
+
+use super::compat::KYOKU_LEN;
+use super::log::RyukyokuReason;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What [`Log::from_json_str_repaired`](super::Log::from_json_str_repaired)
+/// did to a log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Repair {
+    /// The kyokus dropped from the end of the log because they did not
+    /// finish.
+    pub dropped_kyokus: usize,
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.dropped_kyokus {
+            1 => write!(f, "dropped 1 unfinished kyoku"),
+            n => write!(f, "dropped {n} unfinished kyokus"),
+        }
+    }
+}
+
+/// Drop the kyokus at the end of the `log` array of a raw log that did not
+/// finish: those missing entries or whose results have no status. Earlier
+/// kyokus are left as they are, broken or not.
+pub(super) fn drop_unfinished(raw: &mut Value) -> Option<Repair> {
+    let Some(Value::Array(kyokus)) = raw.get_mut("log") else {
+        return None;
+    };
+    let finished = kyokus
+        .iter()
+        .rposition(is_finished)
+        .map_or(0, |last| last + 1);
+    let dropped_kyokus = kyokus.len() - finished;
+    kyokus.truncate(finished);
+    Some(Repair { dropped_kyokus }).filter(|r| r.dropped_kyokus > 0)
+}
+
+/// Whether a raw kyoku has all its entries, with three action tables or
+/// four, and results starting with a status.
+fn is_finished(kyoku: &Value) -> bool {
+    let Some(kyoku) = kyoku.as_array() else {
+        return false;
+    };
+    if kyoku.len() != KYOKU_LEN && kyoku.len() != KYOKU_LEN - 3 {
+        return false;
+    }
+    let status = kyoku
+        .last()
+        .and_then(Value::as_array)
+        .and_then(|results| results.first())
+        .and_then(Value::as_str);
+    status.is_some_and(|s| s == "和了" || RyukyokuReason::from_status(s).is_some())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tenhou::Log;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn repair() {
+        let log = Log::from_json_str_repaired(Fixture::Hanchan.json()).unwrap();
+        assert_eq!(log.repair, None);
+        assert_eq!(log.kyokus.len(), 11);
+
+        // The last kyoku cut off before its results, the one before with
+        // none, and the final results missing.
+        let mut value: Value = serde_json::from_str(Fixture::Hanchan.json()).unwrap();
+        let kyokus = value["log"].as_array_mut().unwrap();
+        kyokus.last_mut().unwrap().as_array_mut().unwrap().pop();
+        let at = kyokus.len() - 2;
+        kyokus[at][KYOKU_LEN - 1] = Value::Array(vec![]);
+        value.as_object_mut().unwrap().remove("sc");
+        let json = value.to_string();
+        Log::from_json_str(&json).unwrap_err();
+
+        let log = Log::from_json_str_repaired(&json).unwrap();
+        assert_eq!(log.repair, Some(Repair { dropped_kyokus: 2 }));
+        assert_eq!(log.kyokus.len(), 9);
+        assert!(log.final_results.is_none());
+        crate::conv::tenhou_to_mjai(&log).unwrap();
+        assert_eq!(
+            log.repair.unwrap().to_string(),
+            "dropped 2 unfinished kyokus"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! The rules a `rule.disp` string tells, whether tenhou wrote it, e.g.
+//! `三鳳南喰赤`, or a private lobby named its rule set itself, e.g.
+//! `3-Player South Red 🀄`.
+//!
+//! The string is split into words at anything that is neither a letter nor a
+//! digit nor `-`, so that emoji and decorations do not matter. Every word
+//! must then be one this module knows, either in tenhou's compact form or a
+//! name such as `Phoenix` or `東風戦`; a single word it does not know makes
+//! the whole string unknown, so that lobby names such as `四川麻雀部` or
+//! `East Side Club` are not read as rules they only happen to contain.
+
+use super::game_id::Room;
+use super::log::GameLength;
+
+/// What a rule string tells of the rules, each `None` where it does not.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuleDisp {
+    pub sanma: Option<bool>,
+    pub room: Option<Room>,
+    pub length: Option<GameLength>,
+    pub aka: Option<bool>,
+    /// Open tanyao (喰断) is allowed.
+    pub kuitan: Option<bool>,
+    pub speed: Option<bool>,
+}
+
+impl RuleDisp {
+    /// Parse `disp`, all unknown if any of its words is not a rule or two
+    /// of them disagree.
+    #[must_use]
+    pub fn parse(disp: &str) -> Self {
+        let mut rules = Self::default();
+        let words = disp
+            .split(|c: char| !c.is_alphanumeric() && c != '-')
+            .filter(|w| !w.is_empty());
+        for word in words {
+            let Some(parsed) = Self::word(word).or_else(|| Self::compact(word)) else {
+                return Self::default();
+            };
+            let Some(merged) = rules.merge(parsed) else {
+                return Self::default();
+            };
+            rules = merged;
+        }
+        rules
+    }
+
+    /// Whether the string told nothing at all, e.g. a custom lobby name.
+    #[must_use]
+    pub fn is_unknown(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// A word naming a single rule, in English or Japanese.
+    fn word(word: &str) -> Option<Self> {
+        let rules = Self::default();
+        let rules = match word.to_ascii_lowercase().as_str() {
+            "3-player" | "3p" | "sanma" | "三麻" | "サンマ" | "三人打ち" => Self {
+                sanma: Some(true),
+                ..rules
+            },
+            "4-player" | "4p" | "yonma" | "四麻" | "四人打ち" => Self {
+                sanma: Some(false),
+                ..rules
+            },
+            "east" | "tonpuu" | "東風" | "東風戦" => Self {
+                length: Some(GameLength::Tonpuu),
+                ..rules
+            },
+            "south" | "hanchan" | "半荘" | "半荘戦" => Self {
+                length: Some(GameLength::Hanchan),
+                ..rules
+            },
+            "ikkyoku" => Self {
+                length: Some(GameLength::Ikkyoku),
+                ..rules
+            },
+            "ippan" | "general" => Self {
+                room: Some(Room::Ippan),
+                ..rules
+            },
+            "joukyuu" | "upper" => Self {
+                room: Some(Room::Joukyuu),
+                ..rules
+            },
+            "tokujou" | "superior" => Self {
+                room: Some(Room::Tokujou),
+                ..rules
+            },
+            "houou" | "phoenix" => Self {
+                room: Some(Room::Houou),
+                ..rules
+            },
+            "red" | "aka" => Self {
+                aka: Some(true),
+                ..rules
+            },
+            "kuitan" => Self {
+                kuitan: Some(true),
+                ..rules
+            },
+            "fast" | "speed" => Self {
+                speed: Some(true),
+                ..rules
+            },
+            _ => return None,
+        };
+        Some(rules)
+    }
+
+    /// A word in the form tenhou writes: the players, the room, the length
+    /// and the flags, e.g. `三鳳南喰赤`, any of them left out, and perhaps
+    /// `戦` after them as in `一局戦`. Tenhou spells out every flag, so that
+    /// those missing are off, but only where the players lead.
+    fn compact(word: &str) -> Option<Self> {
+        let mut rest = word.strip_suffix('戦').unwrap_or(word);
+        let mut take = |prefix: &str| match rest.strip_prefix(prefix) {
+            Some(after) => {
+                rest = after;
+                true
+            }
+            None => false,
+        };
+
+        let mut rules = Self::default();
+        if take("三") {
+            rules.sanma = Some(true);
+        } else if take("四") {
+            rules.sanma = Some(false);
+        }
+        for room in [Room::Ippan, Room::Joukyuu, Room::Tokujou, Room::Houou] {
+            if take(room.kanji().encode_utf8(&mut [0; 4])) {
+                rules.room = Some(room);
+                break;
+            }
+        }
+        if take("一局") {
+            rules.length = Some(GameLength::Ikkyoku);
+        } else if take("東") {
+            rules.length = Some(GameLength::Tonpuu);
+        } else if take("南") {
+            rules.length = Some(GameLength::Hanchan);
+        }
+        let (mut kuitan, mut aka, mut speed) = (false, false, false);
+        for c in rest.chars() {
+            match c {
+                '喰' => kuitan = true,
+                '赤' => aka = true,
+                '速' => speed = true,
+                _ => return None,
+            }
+        }
+
+        if rules.sanma.is_some() {
+            // Tenhou writes no room for the general room.
+            rules.room = rules.room.or(Some(Room::Ippan));
+            rules.kuitan = Some(kuitan);
+            rules.aka = Some(aka);
+            rules.speed = Some(speed);
+        } else {
+            rules.kuitan = kuitan.then_some(true);
+            rules.aka = aka.then_some(true);
+            rules.speed = speed.then_some(true);
+        }
+        (!rules.is_unknown()).then_some(rules)
+    }
+
+    /// Combine the rules of two words, `None` if they disagree.
+    fn merge(self, other: Self) -> Option<Self> {
+        fn pick<T: PartialEq>(a: Option<T>, b: Option<T>) -> Option<Option<T>> {
+            match (a, b) {
+                (Some(a), Some(b)) if a != b => None,
+                (a, b) => Some(a.or(b)),
+            }
+        }
+        Some(Self {
+            sanma: pick(self.sanma, other.sanma)?,
+            room: pick(self.room, other.room)?,
+            length: pick(self.length, other.length)?,
+            aka: pick(self.aka, other.aka)?,
+            kuitan: pick(self.kuitan, other.kuitan)?,
+            speed: pick(self.speed, other.speed)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let rules = RuleDisp::parse("三鳳南喰赤");
+        assert_eq!(
+            rules,
+            RuleDisp {
+                sanma: Some(true),
+                room: Some(Room::Houou),
+                length: Some(GameLength::Hanchan),
+                aka: Some(true),
+                kuitan: Some(true),
+                speed: Some(false),
+            }
+        );
+        assert_eq!(RuleDisp::parse("🀄 三鳳南喰赤 🀄"), rules);
+        assert_eq!(RuleDisp::parse("三南喰赤").room, Some(Room::Ippan));
+        assert_eq!(RuleDisp::parse("四特東喰赤").sanma, Some(false));
+        assert_eq!(RuleDisp::parse("一局戦").length, Some(GameLength::Ikkyoku));
+
+        let rules = RuleDisp::parse("3-Player South Red");
+        assert_eq!(rules.sanma, Some(true));
+        assert_eq!(rules.length, Some(GameLength::Hanchan));
+        assert_eq!(rules.aka, Some(true));
+        assert_eq!((rules.room, rules.kuitan), (None, None));
+        assert_eq!(
+            RuleDisp::parse("【サンマ】東風戦・赤✨").length,
+            Some(GameLength::Tonpuu)
+        );
+
+        // Lobby names that only contain rules, and words that disagree.
+        for disp in [
+            "",
+            "Custom lobby",
+            "四川麻雀部🀄",
+            "East Side Club",
+            "東京南部リーグ",
+            "三人麻雀",
+            "3-Player 4-Player",
+        ] {
+            assert!(RuleDisp::parse(disp).is_unknown(), "{disp}");
+        }
+    }
+}
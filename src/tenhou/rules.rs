@@ -0,0 +1,812 @@
+//! Checks of recorded results against the sanma rules.
+
+use super::action::ActionItem;
+use super::log::{EndStatus, FinalResult, HoraDetail, Kyoku, Log};
+use super::score::{Limit, Payment, Score, ScoringRules};
+use super::yaku::{Han, Yaku};
+use super::RIICHI_STICK;
+use crate::locale::Locale;
+use crate::{Points, SEATS};
+use std::error::Error;
+use std::fmt;
+
+use serde::Serialize;
+
+/// Paid per honba by the discarder on ron, or split among the payers on
+/// tsumo.
+pub(crate) const HONBA: Points = 200;
+
+/// How sticks are shared when several players ron the same discard.
+///
+/// Riichi sticks always go to the winner closest to the discarder in turn
+/// order, the variants only differ in who receives the honba.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StickRule {
+    /// Honba go to the closest winner only, this is what tenhou does.
+    #[default]
+    Atamahane,
+    /// Every winner receives the honba.
+    HonbaToEach,
+}
+
+#[derive(Debug)]
+pub enum RuleError {
+    MisawardedSticks {
+        kyoku: u8,
+        honba: u8,
+        expected: u8,
+        got: Vec<u8>,
+    },
+    MisawardedHonba {
+        kyoku: u8,
+        honba: u8,
+        who: u8,
+        expected: Points,
+        got: Points,
+    },
+    InvalidPayers {
+        kyoku: u8,
+        honba: u8,
+        who: u8,
+        payers: Vec<u8>,
+    },
+    LedgerMismatch {
+        kyoku: u8,
+        honba: u8,
+        kyotaku: u8,
+        expected_honba: u8,
+        expected_kyotaku: u32,
+    },
+    ScoreMismatch {
+        kyoku: u8,
+        honba: u8,
+        who: u8,
+        expected: Payment,
+        got: Payment,
+        comparison: Box<ScoreComparison>,
+    },
+    InvalidScore {
+        kyoku: u8,
+        honba: u8,
+        who: u8,
+        label: String,
+    },
+    RuleFlagsMismatch {
+        disp: String,
+        expected: String,
+    },
+    ScoreOverflow {
+        kyoku: u8,
+        honba: u8,
+        who: u8,
+    },
+    FinalResultMismatch {
+        who: u8,
+        expected: FinalResult,
+        got: FinalResult,
+    },
+    InvalidIndicators {
+        kyoku: u8,
+        honba: u8,
+        dora: usize,
+        ura: usize,
+        kans: usize,
+    },
+    FourthSeatNamed {
+        name: String,
+    },
+    FourthSeatActs {
+        kyoku: u8,
+        honba: u8,
+    },
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MisawardedSticks {
+                kyoku,
+                honba,
+                expected,
+                got,
+            } => write!(
+                f,
+                "riichi sticks awarded to {got:?} instead of {expected}: \
+                at kyoku {kyoku} honba {honba}"
+            ),
+            Self::MisawardedHonba {
+                kyoku,
+                honba,
+                who,
+                expected,
+                got,
+            } => write!(
+                f,
+                "seat {who} received {got} for honba instead of {expected}: \
+                at kyoku {kyoku} honba {honba}"
+            ),
+            Self::InvalidPayers {
+                kyoku,
+                honba,
+                who,
+                payers,
+            } => write!(
+                f,
+                "unexpected payers {payers:?} for the hora of seat {who}: \
+                at kyoku {kyoku} honba {honba}"
+            ),
+            Self::LedgerMismatch {
+                kyoku,
+                honba,
+                kyotaku,
+                expected_honba,
+                expected_kyotaku,
+            } => write!(
+                f,
+                "expected {expected_honba} honba and {expected_kyotaku} kyotaku: \
+                at kyoku {kyoku} honba {honba} kyotaku {kyotaku}"
+            ),
+            Self::ScoreMismatch {
+                kyoku,
+                honba,
+                who,
+                expected,
+                got,
+                ..
+            } => write!(
+                f,
+                "seat {who} was paid {got:?} instead of {expected:?}: \
+                at kyoku {kyoku} honba {honba}"
+            ),
+            Self::InvalidScore {
+                kyoku,
+                honba,
+                who,
+                label,
+            } => write!(
+                f,
+                "invalid score {label:?} of seat {who}: at kyoku {kyoku} honba {honba}"
+            ),
+            Self::RuleFlagsMismatch { disp, expected } => write!(
+                f,
+                "rule {disp:?} does not match {expected:?} encoded in the game ID"
+            ),
+            Self::ScoreOverflow { kyoku, honba, who } => write!(
+                f,
+                "score of seat {who} overflows: at kyoku {kyoku} honba {honba}"
+            ),
+            Self::FinalResultMismatch { who, expected, got } => write!(
+                f,
+                "seat {who} finished with {got:?} instead of {expected:?}"
+            ),
+            Self::InvalidIndicators {
+                kyoku,
+                honba,
+                dora,
+                ura,
+                kans,
+            } => write!(
+                f,
+                "{dora} dora and {ura} ura indicators do not match {kans} kan(s): \
+                at kyoku {kyoku} honba {honba}"
+            ),
+            Self::FourthSeatNamed { name } => {
+                write!(f, "the fourth seat is named {name:?}")
+            }
+            Self::FourthSeatActs { kyoku, honba } => write!(
+                f,
+                "the fourth seat takes part: at kyoku {kyoku} honba {honba}"
+            ),
+        }
+    }
+}
+
+impl Error for RuleError {}
+
+/// A hora whose recorded payment differs from the one recomputed from its fu
+/// and han, side by side with what went into it, to tell scoring rules other
+/// than those checked against from a corrupt log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScoreComparison {
+    pub who: u8,
+    /// The discarder, `None` for tsumo.
+    pub target: Option<u8>,
+    pub dealer: bool,
+    pub fu: Option<u8>,
+    pub han: Option<u8>,
+    pub limit: Option<Limit>,
+    pub yaku: Vec<(Yaku, Han)>,
+    pub recorded: Payment,
+    pub recomputed: Payment,
+    /// What every seat gets from either payment, without honba, sticks or
+    /// liability.
+    pub recorded_deltas: [Points; 4],
+    pub recomputed_deltas: [Points; 4],
+}
+
+impl ScoreComparison {
+    fn new(hora: &HoraDetail, oya: u8, score: &Score, recomputed: Payment) -> Self {
+        let target = (!hora.is_tsumo()).then_some(hora.target);
+        let deltas = |payment| payment_deltas(payment, hora.who, target, oya);
+        Self {
+            who: hora.who,
+            target,
+            dealer: hora.who == oya,
+            fu: score.fu,
+            han: score.han,
+            limit: score.limit,
+            yaku: hora.yaku.clone(),
+            recorded: score.payment,
+            recomputed,
+            recorded_deltas: deltas(score.payment),
+            recomputed_deltas: deltas(recomputed),
+        }
+    }
+}
+
+/// What every seat gets from `payment` to `who`, paid by `target` on ron.
+fn payment_deltas(payment: Payment, who: u8, target: Option<u8>, oya: u8) -> [Points; 4] {
+    let mut deltas = [0; 4];
+    for payer in (0..SEATS as u8).filter(|&s| s != who) {
+        let paid = match (payment, target) {
+            (Payment::Ron(x), Some(target)) if payer == target => x,
+            (Payment::Ron(_), _) => 0,
+            (Payment::Tsumo { oya: x, .. }, _) if payer == oya => x,
+            (Payment::Tsumo { ko, .. }, _) => ko,
+            (Payment::TsumoAll(x), _) => x,
+        };
+        deltas[usize::from(payer)] -= paid;
+        deltas[usize::from(who)] += paid;
+    }
+    deltas
+}
+
+impl fmt::Display for ScoreComparison {
+    /// The hand and its yaku on a line, then a row per seat of the recorded
+    /// and recomputed points and their difference.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let locale = Locale::En;
+        write!(f, "seat {}", self.who)?;
+        if self.dealer {
+            write!(f, " (dealer)")?;
+        }
+        match self.target {
+            Some(target) => write!(f, " ron off seat {target}")?,
+            None => write!(f, " tsumo")?,
+        }
+        match (self.fu, self.han, self.limit) {
+            (Some(fu), Some(han), _) => write!(f, ", {}", locale.fu_han(fu, han))?,
+            (_, _, Some(limit)) => write!(f, ", {}", locale.limit(limit))?,
+            _ => {}
+        }
+        for (i, (yaku, han)) in self.yaku.iter().enumerate() {
+            let sep = if i == 0 { ": " } else { ", " };
+            write!(f, "{sep}{} {}", locale.yaku(yaku), locale.han(*han))?;
+        }
+        writeln!(f)?;
+        write!(f, "seat  recorded  recomputed  difference")?;
+        for seat in 0..SEATS as u8 {
+            let recorded = self.recorded_deltas[usize::from(seat)];
+            let recomputed = self.recomputed_deltas[usize::from(seat)];
+            write!(
+                f,
+                "\n{seat:>4}  {recorded:>+8}  {recomputed:>+10}  {:>+10}",
+                recomputed - recorded
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl HoraDetail {
+    /// [`Self::score_deltas`] without the empty fourth seat.
+    #[inline]
+    #[must_use]
+    pub const fn deltas3(&self) -> [Points; 3] {
+        let [a, b, c, _] = self.score_deltas;
+        [a, b, c]
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn is_tsumo(&self) -> bool {
+        self.who == self.target
+    }
+
+    /// How many seats after the discarder the winner sits in turn order, `0`
+    /// for tsumo.
+    #[inline]
+    #[must_use]
+    pub const fn distance(&self) -> u8 {
+        (self.who + SEATS as u8 - self.target) % SEATS as u8
+    }
+
+    /// The seats that have to pay for this hora: the discarder and the liable
+    /// seat on ron, the liable seat alone or else everyone else on tsumo.
+    #[must_use]
+    pub fn payers(&self) -> Vec<u8> {
+        match (self.is_tsumo(), self.pao) {
+            (true, Some(pao)) => vec![pao],
+            (true, None) => (0..SEATS as u8).filter(|&s| s != self.who).collect(),
+            (false, pao) => {
+                let mut payers = vec![self.target];
+                payers.extend(pao);
+                payers.sort_unstable();
+                payers.dedup();
+                payers
+            }
+        }
+    }
+}
+
+impl Kyoku {
+    /// The horas of this kyoku in resolution order, i.e. starting from the
+    /// winner closest to the discarder. Empty on ryukyoku.
+    #[must_use]
+    pub fn horas(&self) -> Vec<&HoraDetail> {
+        let mut horas: Vec<_> = match &self.end_status {
+            EndStatus::Hora { details } => details.iter().collect(),
+            EndStatus::Ryukyoku { .. } => vec![],
+        };
+        horas.sort_by_key(|d| d.distance());
+        horas
+    }
+
+    /// The result of checked score arithmetic about seat `who`, with `None`
+    /// turned into [`RuleError::ScoreOverflow`]. Only corrupt logs get
+    /// anywhere near the limits.
+    pub(super) const fn checked_score(
+        &self,
+        who: u8,
+        score: Option<Points>,
+    ) -> Result<Points, RuleError> {
+        match score {
+            Some(score) => Ok(score),
+            None => Err(RuleError::ScoreOverflow {
+                kyoku: self.meta.kyoku_num,
+                honba: self.meta.honba,
+                who,
+            }),
+        }
+    }
+
+    /// Number of riichi sticks on the table when the kyoku ended, that is the
+    /// kyotaku it started with plus the riichi declared in it.
+    #[must_use]
+    pub fn riichi_sticks(&self) -> u32 {
+        u32::from(self.meta.kyotaku) + self.deposits().iter().sum::<u32>()
+    }
+
+    /// Number of riichi sticks every seat put on the table in this kyoku,
+    /// which the score deltas of the kyoku leave out.
+    #[must_use]
+    pub fn deposits(&self) -> [u32; 4] {
+        let mut deposits = self
+            .action_tables
+            .each_ref()
+            .map(|table| table.discards.iter().filter(|d| d.is_riichi()).count() as u32);
+        // A riichi declaration tile that is ronned does not put a stick.
+        if let Some(hora) = self.horas().first() {
+            let target = hora.target as usize;
+            if !hora.is_tsumo()
+                && self.action_tables[target]
+                    .discards
+                    .last()
+                    .is_some_and(ActionItem::is_riichi)
+            {
+                deposits[target] -= 1;
+            }
+        }
+        deposits
+    }
+
+    /// Check that the riichi sticks and honba of a hora were awarded to the
+    /// right winners under `rule`.
+    ///
+    /// Honba can only be checked for horas that have a score text, which
+    /// tells the points without them.
+    pub fn check_sticks(&self, rule: StickRule) -> Result<(), RuleError> {
+        let horas = self.horas();
+        let Some(closest) = horas.first() else {
+            return Ok(());
+        };
+
+        // Score deltas include the sticks the winner collects but not the
+        // ones deposited, so what is left over is the sticks.
+        let pot = Points::from(self.riichi_sticks()) * RIICHI_STICK;
+        let sticks = horas
+            .iter()
+            .map(|d| {
+                d.score_deltas.iter().try_fold(0, |sum: Points, &delta| {
+                    self.checked_score(d.who, sum.checked_add(delta))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let misawarded = sticks
+            .iter()
+            .enumerate()
+            .any(|(i, &s)| s != if i == 0 { pot } else { 0 });
+        if misawarded {
+            return Err(RuleError::MisawardedSticks {
+                kyoku: self.meta.kyoku_num,
+                honba: self.meta.honba,
+                expected: closest.who,
+                got: horas
+                    .iter()
+                    .zip(&sticks)
+                    .filter(|&(_, &s)| s != 0)
+                    .map(|(d, _)| d.who)
+                    .collect(),
+            });
+        }
+
+        for (i, hora) in horas.iter().enumerate() {
+            let Some(score) = hora.score else {
+                continue;
+            };
+
+            // With pao the payment is shared, so count everything paid.
+            let paid = hora.payers().iter().try_fold(0, |paid: Points, &p| {
+                self.checked_score(hora.who, paid.checked_sub(hora.score_deltas[p as usize]))
+            })?;
+            let got = self.checked_score(
+                hora.who,
+                paid.checked_sub(score.payment.total(SEATS as Points - 1)),
+            )?;
+            let expected = if i == 0 || rule == StickRule::HonbaToEach {
+                Points::from(self.meta.honba) * HONBA
+            } else {
+                0
+            };
+            if got != expected {
+                return Err(RuleError::MisawardedHonba {
+                    kyoku: self.meta.kyoku_num,
+                    honba: self.meta.honba,
+                    who: hora.who,
+                    expected,
+                    got,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that exactly the seats returned by [`HoraDetail::payers`] lost
+    /// points in every hora of this kyoku.
+    pub fn check_payers(&self) -> Result<(), RuleError> {
+        for hora in self.horas() {
+            let payers: Vec<_> = (0..SEATS as u8)
+                .filter(|&s| hora.score_deltas[s as usize] < 0)
+                .collect();
+            if payers != hora.payers() {
+                return Err(RuleError::InvalidPayers {
+                    kyoku: self.meta.kyoku_num,
+                    honba: self.meta.honba,
+                    who: hora.who,
+                    payers,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that what every hora with a score text was paid is what its fu
+    /// and han or its limit are worth under `rules`, and that every score text
+    /// could be read.
+    pub fn check_scores(&self, rules: &ScoringRules) -> Result<(), RuleError> {
+        let oya = self.meta.kyoku_num % 4;
+        for hora in self.horas() {
+            let Some(score) = hora.score else {
+                if !hora.label.is_empty() {
+                    return Err(RuleError::InvalidScore {
+                        kyoku: self.meta.kyoku_num,
+                        honba: self.meta.honba,
+                        who: hora.who,
+                        label: hora.label.clone(),
+                    });
+                }
+                continue;
+            };
+            let yakuman = hora
+                .yaku
+                .iter()
+                .map(|(_, han)| match *han {
+                    Han::Yakuman(n) => n,
+                    Han::Regular(_) => 0,
+                })
+                .sum();
+            let expected = score.expected_payment(hora.who == oya, hora.is_tsumo(), yakuman, rules);
+            if let Some(expected) = expected.filter(|&e| e != score.payment) {
+                return Err(RuleError::ScoreMismatch {
+                    kyoku: self.meta.kyoku_num,
+                    honba: self.meta.honba,
+                    who: hora.who,
+                    expected,
+                    got: score.payment,
+                    comparison: Box::new(ScoreComparison::new(hora, oya, &score, expected)),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that there is a dora indicator, at most one more for every
+    /// kan, and no more ura indicators than dora indicators.
+    pub fn check_indicators(&self) -> Result<(), RuleError> {
+        let kans = self.kan_count();
+        let dora = self.dora_indicators.len();
+        let ura = self.ura_indicators.as_ref().map_or(0, Vec::len);
+
+        // A kan dora may not have been revealed yet when the kyoku ends, e.g.
+        // on rinshan kaihou or chankan, so only the upper bound is exact.
+        if dora == 0 || dora > kans + 1 || ura > dora {
+            return Err(RuleError::InvalidIndicators {
+                kyoku: self.meta.kyoku_num,
+                honba: self.meta.honba,
+                dora,
+                ura,
+                kans,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check that the fourth seat has no points, tiles or actions and is
+    /// never paid, paying or liable, so that leaving it out loses nothing.
+    pub fn check_fourth_seat(&self) -> Result<(), RuleError> {
+        const FOURTH: u8 = 3;
+        let table = &self.action_tables[usize::from(FOURTH)];
+        let hora_involves = |d: &HoraDetail| {
+            d.who == FOURTH || d.target == FOURTH || d.pao == Some(FOURTH) || d.score_deltas[3] != 0
+        };
+        let involved = match &self.end_status {
+            EndStatus::Hora { details } => details.iter().any(hora_involves),
+            EndStatus::Ryukyoku { score_deltas, .. } => score_deltas[3] != 0,
+        };
+        if involved
+            || self.scoreboard[3] != 0
+            || !table.haipai.is_empty()
+            || !table.takes.is_empty()
+            || !table.discards.is_empty()
+        {
+            return Err(RuleError::FourthSeatActs {
+                kyoku: self.meta.kyoku_num,
+                honba: self.meta.honba,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Log {
+    /// Check that the fourth seat is unnamed and [`Kyoku::check_fourth_seat`]
+    /// on every kyoku, before output that leaves the seat out.
+    pub fn check_fourth_seat(&self) -> Result<(), RuleError> {
+        if !self.names[3].is_empty() {
+            return Err(RuleError::FourthSeatNamed {
+                name: self.names[3].clone(),
+            });
+        }
+        self.kyokus.iter().try_for_each(Kyoku::check_fourth_seat)
+    }
+
+    /// Run [`Kyoku::check_sticks`] on every kyoku.
+    #[inline]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn check_sticks(&self, rule: StickRule) -> Result<(), RuleError> {
+        self.kyokus.iter().try_for_each(|k| k.check_sticks(rule))
+    }
+
+    /// Run [`Kyoku::check_payers`] on every kyoku.
+    #[inline]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn check_payers(&self) -> Result<(), RuleError> {
+        self.kyokus.iter().try_for_each(Kyoku::check_payers)
+    }
+
+    /// Run [`Kyoku::check_scores`] on every kyoku.
+    #[inline]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn check_scores(&self, rules: &ScoringRules) -> Result<(), RuleError> {
+        self.kyokus.iter().try_for_each(|k| k.check_scores(rules))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testdata::Fixture;
+
+    fn log_of(meta: &str, results: &str) -> Log {
+        let json_str = format!(
+            r#"{{"log":[[{meta},[35000,35000,35000,0],[47],[],[],[],[],[],[],[],[],[],[],[],[],[],["和了",{results}]]],"name":["","","",""]}}"#
+        );
+        Log::from_json_str(&json_str).unwrap()
+    }
+
+    /// Seat 0 deals into seats 1 and 2 at 1 honba with 1 kyotaku, the results
+    /// are deliberately not in resolution order.
+    fn double_ron(results: &str) -> Log {
+        log_of("[0,1,1]", results)
+    }
+
+    #[test]
+    fn multi_ron() {
+        let log = double_ron(
+            r#"[-1000,0,1000,0],[2,0,2,"30符1飜1000点"],[-3100,4100,0,0],[1,0,1,"30符2飜2900点"]"#,
+        );
+        let kyoku = &log.kyokus[0];
+        let order: Vec<_> = kyoku.horas().iter().map(|d| d.who).collect();
+        assert_eq!(order, [1, 2]);
+        assert_eq!(kyoku.riichi_sticks(), 1);
+        log.check_sticks(StickRule::Atamahane).unwrap();
+        let err = log.check_sticks(StickRule::HonbaToEach).unwrap_err();
+        assert!(
+            matches!(err, RuleError::MisawardedHonba { who: 2, .. }),
+            "{err}"
+        );
+
+        let log = double_ron(
+            r#"[-1000,0,2000,0],[2,0,2,"30符1飜1000点"],[-3100,3100,0,0],[1,0,1,"30符2飜2900点"]"#,
+        );
+        let err = log.check_sticks(StickRule::Atamahane).unwrap_err();
+        assert!(
+            matches!(err, RuleError::MisawardedSticks { expected: 1, ref got, .. } if got == &[2]),
+            "{err}",
+        );
+    }
+
+    #[test]
+    fn tsumo_honba() {
+        let log = log_of(
+            "[1,2,0]",
+            r#"[-2200,6400,-4200,0],[1,1,1,"満貫2000-4000点","立直(1飜)"]"#,
+        );
+        log.check_sticks(StickRule::default()).unwrap();
+
+        let log = log_of(
+            "[1,2,0]",
+            r#"[-2100,6200,-4100,0],[1,1,1,"満貫2000-4000点","立直(1飜)"]"#,
+        );
+        log.check_sticks(StickRule::default()).unwrap_err();
+    }
+
+    #[test]
+    fn scores() {
+        let log = Fixture::Hanchan.log();
+        log.check_scores(&ScoringRules::default()).unwrap();
+
+        // A dealer ron of 30 fu 4 han.
+        let log = log_of(
+            "[0,0,0]",
+            r#"[11600,-11600,0,0],[0,1,0,"30符4飜11600点","立直(1飜)"]"#,
+        );
+        log.check_scores(&ScoringRules::default()).unwrap();
+        let kiriage = ScoringRules {
+            kiriage_mangan: true,
+            ..ScoringRules::default()
+        };
+        let err = log.check_scores(&kiriage).unwrap_err();
+        let RuleError::ScoreMismatch {
+            expected: Payment::Ron(12000),
+            got: Payment::Ron(11600),
+            comparison,
+            ..
+        } = err
+        else {
+            panic!("{err}");
+        };
+        assert_eq!(comparison.recorded_deltas, [11600, -11600, 0, 0]);
+        assert_eq!(comparison.recomputed_deltas, [12000, -12000, 0, 0]);
+        assert_eq!(
+            comparison.to_string(),
+            "seat 0 (dealer) ron off seat 1, 30 fu 4 han: Riichi 1 han\n\
+             seat  recorded  recomputed  difference\n   \
+                0    +11600      +12000        +400\n   \
+                1    -11600      -12000        -400\n   \
+                2        +0          +0          +0"
+        );
+
+        // A non-dealer tsumo recorded as if the dealer paid as much as the
+        // others.
+        let log = log_of(
+            "[0,0,0]",
+            r#"[-1000,2000,-1000,0],[1,1,1,"30符3飜1000-1000点","門前清自摸和(1飜)","ドラ(2飜)"]"#,
+        );
+        let err = log.check_scores(&ScoringRules::default()).unwrap_err();
+        let RuleError::ScoreMismatch { comparison, .. } = err else {
+            panic!("{err}");
+        };
+        assert_eq!(comparison.target, None);
+        assert_eq!(comparison.recorded_deltas, [-1000, 2000, -1000, 0]);
+        assert_eq!(comparison.recomputed_deltas, [-2000, 3000, -1000, 0]);
+
+        // A score text of another client still parses, without a score.
+        let log = log_of(
+            "[0,0,0]",
+            r#"[1000,-1000,0,0],[1,0,1,"30符1飜1000点(点数)","立直(1飜)"]"#,
+        );
+        let hora = &log.kyokus[0].horas()[0];
+        assert_eq!(
+            (hora.score, hora.label.as_str()),
+            (None, "30符1飜1000点(点数)")
+        );
+        let err = log.check_scores(&ScoringRules::default()).unwrap_err();
+        assert!(
+            matches!(err, RuleError::InvalidScore { who: 1, .. }),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn pao() {
+        // Seat 2 fed the last dragon of a daisangen.
+        let log = log_of(
+            "[0,0,0]",
+            r#"[0,24000,-24000,0],[1,1,2,"役満8000-16000点","大三元(役満)"]"#,
+        );
+        assert_eq!(log.kyokus[0].horas()[0].pao, Some(2));
+        log.check_payers().unwrap();
+        log.check_sticks(StickRule::default()).unwrap();
+
+        let log = log_of(
+            "[0,1,0]",
+            r#"[-16000,32200,-16200,0],[1,0,2,"役満32000点","大三元(役満)"]"#,
+        );
+        assert_eq!(log.kyokus[0].horas()[0].payers(), [0, 2]);
+        log.check_payers().unwrap();
+        log.check_sticks(StickRule::default()).unwrap();
+
+        let log = log_of(
+            "[0,0,0]",
+            r#"[0,24000,-24000,0],[1,1,1,"役満8000-16000点","大三元(役満)"]"#,
+        );
+        let err = log.check_payers().unwrap_err();
+        assert!(
+            matches!(err, RuleError::InvalidPayers { ref payers, .. } if payers == &[2]),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn fourth_seat() {
+        let mut log = Fixture::Hanchan.log();
+        log.check_fourth_seat().unwrap();
+
+        log.kyokus[2].action_tables[3].discards = vec![ActionItem::Tsumogiri].into();
+        let err = log.check_fourth_seat().unwrap_err();
+        assert!(
+            matches!(err, RuleError::FourthSeatActs { kyoku: 1, honba: 1 }),
+            "{err}"
+        );
+
+        let log = log_of("[0,0,0]", r#"[1000,0,0,-1000],[0,3,0,"30符1飜1000点"]"#);
+        log.check_fourth_seat().unwrap_err();
+        let mut log = Fixture::Hanchan.log();
+        log.names[3] = "nobody".into();
+        let err = log.check_fourth_seat().unwrap_err();
+        assert!(matches!(err, RuleError::FourthSeatNamed { .. }), "{err}");
+    }
+
+    #[test]
+    fn invalid_indicators() {
+        let kyoku = |dora: &str, ura: &str| {
+            format!(
+                r#"{{"log":[[[0,0,0],[35000,35000,35000,0],{dora},{ura},[],[],[],[],[],[],[],[],[],[],[],[],["流局",[0,0,0,0]]]],"name":["","","",""]}}"#
+            )
+        };
+
+        let check = |dora, ura| {
+            let log = Log::from_json_str(&kyoku(dora, ura)).unwrap();
+            log.kyokus[0].check_indicators()
+        };
+        check("[47]", "null").unwrap();
+        for (dora, ura) in [("[]", "[]"), ("[47,36]", "[]"), ("[47]", "[47,36]")] {
+            let err = check(dora, ura).unwrap_err();
+            assert!(matches!(err, RuleError::InvalidIndicators { .. }), "{err}");
+        }
+    }
+}
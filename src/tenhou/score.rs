@@ -0,0 +1,366 @@
+use crate::Points;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// The score text of a hora, e.g. `40符3飜7700点` or `満貫2000-4000点`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Score {
+    /// Absent for most limit hands.
+    pub fu: Option<u8>,
+    /// Absent for most limit hands.
+    pub han: Option<u8>,
+    pub limit: Option<Limit>,
+    /// What is paid excluding honba and riichi sticks.
+    pub payment: Payment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Limit {
+    Mangan,
+    Haneman,
+    Baiman,
+    Sanbaiman,
+    Yakuman,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Payment {
+    /// `X点`, paid by the discarder.
+    Ron(Points),
+    /// `X-Y点`, a non-dealer tsumo: non-dealers pay `ko` and the dealer pays
+    /// `oya`.
+    Tsumo { ko: Points, oya: Points },
+    /// `X点∀`, a dealer tsumo where everyone pays the same.
+    TsumoAll(Points),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidScore(pub String);
+
+impl fmt::Display for InvalidScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid score: {:?}", self.0)
+    }
+}
+
+impl Error for InvalidScore {}
+
+const LIMITS: &[(&str, Limit)] = &[
+    // 三倍満 has to come before 倍満.
+    ("三倍満", Limit::Sanbaiman),
+    ("満貫", Limit::Mangan),
+    ("跳満", Limit::Haneman),
+    ("倍満", Limit::Baiman),
+    ("役満", Limit::Yakuman),
+];
+
+/// How the points of a hand are worked out, which differs between platforms
+/// and more so in sanma. The default is what tenhou does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScoringRules {
+    /// Round 4 han 30 fu and 3 han 60 fu, 7700 and 11600 points for ron,
+    /// up to mangan (切り上げ満貫).
+    pub kiriage_mangan: bool,
+    pub tsumo: TsumoPayment,
+    pub rounding: Rounding,
+}
+
+/// What becomes of the share the missing fourth seat would pay on tsumo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TsumoPayment {
+    /// Nobody pays it (ツモ損), as on tenhou.
+    #[default]
+    Loss,
+    /// The two payers pay half of it each (北家折半).
+    Split,
+}
+
+/// When payments are rounded up to 100 points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rounding {
+    /// Once every payment is worked out.
+    #[default]
+    Payment,
+    /// As for four players first, then again for the halves of the missing
+    /// seat's share under [`TsumoPayment::Split`].
+    Share,
+}
+
+impl FromStr for TsumoPayment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "loss" => Ok(Self::Loss),
+            "split" => Ok(Self::Split),
+            _ => Err(format!(
+                r#"invalid tsumo payment: {s:?} (expected "loss" or "split")"#
+            )),
+        }
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "payment" => Ok(Self::Payment),
+            "share" => Ok(Self::Share),
+            _ => Err(format!(
+                r#"invalid rounding: {s:?} (expected "payment" or "share")"#
+            )),
+        }
+    }
+}
+
+impl Limit {
+    /// The base points of the limit, for `yakuman` times yakuman.
+    #[must_use]
+    pub fn base_points(self, yakuman: u8) -> Points {
+        match self {
+            Self::Mangan => 2000,
+            Self::Haneman => 3000,
+            Self::Baiman => 4000,
+            Self::Sanbaiman => 6000,
+            Self::Yakuman => 8000 * Points::from(yakuman.max(1)),
+        }
+    }
+
+    /// The limit `han` han reach regardless of fu, if any.
+    #[must_use]
+    pub const fn of_han(han: u8) -> Option<Self> {
+        match han {
+            0..=4 => None,
+            5 => Some(Self::Mangan),
+            6 | 7 => Some(Self::Haneman),
+            8..=10 => Some(Self::Baiman),
+            11 | 12 => Some(Self::Sanbaiman),
+            _ => Some(Self::Yakuman),
+        }
+    }
+}
+
+impl Score {
+    /// The base points of the hand (基本点), `None` if the text tells
+    /// neither fu and han nor a limit.
+    #[must_use]
+    pub fn base_points(&self, yakuman: u8, rules: &ScoringRules) -> Option<Points> {
+        if let Some(limit) = self.limit {
+            return Some(limit.base_points(yakuman));
+        }
+        let (fu, han) = (self.fu?, self.han?);
+        if let Some(limit) = Limit::of_han(han) {
+            return Some(limit.base_points(yakuman));
+        }
+        let base = Points::from(fu) << (han + 2);
+        Some(if base >= 2000 || (rules.kiriage_mangan && base >= 1920) {
+            2000
+        } else {
+            base
+        })
+    }
+
+    /// What the hand should be paid under `rules`, worked out from its fu
+    /// and han or limit, for `yakuman` times yakuman.
+    #[must_use]
+    pub fn expected_payment(
+        &self,
+        oya: bool,
+        tsumo: bool,
+        yakuman: u8,
+        rules: &ScoringRules,
+    ) -> Option<Payment> {
+        let base = self.base_points(yakuman, rules)?;
+        // In halves of points, so that halves of shares need no rounding
+        // until the end.
+        let round = |halves: Points| (halves + 199) / 200 * 100;
+        let share = |points: Points| match rules.rounding {
+            Rounding::Payment => 2 * points,
+            Rounding::Share => 2 * round(2 * points),
+        };
+        let split = |own: Points, missing: Points| match rules.tsumo {
+            TsumoPayment::Loss => round(share(own)),
+            TsumoPayment::Split => round(share(own) + share(missing) / 2),
+        };
+        Some(match (tsumo, oya) {
+            (false, true) => Payment::Ron(round(2 * 6 * base)),
+            (false, false) => Payment::Ron(round(2 * 4 * base)),
+            (true, true) => Payment::TsumoAll(split(2 * base, 2 * base)),
+            (true, false) => Payment::Tsumo {
+                ko: split(base, base),
+                oya: split(2 * base, base),
+            },
+        })
+    }
+}
+
+impl Payment {
+    /// The sum of what all `payers` pay together.
+    #[inline]
+    #[must_use]
+    pub const fn total(self, payers: Points) -> Points {
+        match self {
+            Self::Ron(x) => x,
+            Self::Tsumo { ko, oya } => ko * (payers - 1) + oya,
+            Self::TsumoAll(x) => x * payers,
+        }
+    }
+}
+
+impl FromStr for Score {
+    type Err = InvalidScore;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidScore(s.to_owned());
+
+        let (rest, all) = s.strip_suffix('∀').map_or((s, false), |r| (r, true));
+        let mut rest = rest.strip_suffix('点').ok_or_else(invalid)?;
+
+        let mut fu = None;
+        let mut han = None;
+        if let Some((n, r)) = rest.split_once('符') {
+            fu = Some(n.parse().map_err(|_| invalid())?);
+            let (n, r) = r.split_once('飜').ok_or_else(invalid)?;
+            han = Some(n.parse().map_err(|_| invalid())?);
+            rest = r;
+        }
+        let limit = LIMITS.iter().find_map(|&(name, limit)| {
+            let r = rest.strip_prefix(name)?;
+            rest = r;
+            Some(limit)
+        });
+
+        let parse = |n: &str| n.parse::<Points>().map_err(|_| invalid());
+        let payment = match rest.split_once('-') {
+            Some(_) if all => return Err(invalid()),
+            Some((ko, oya)) => Payment::Tsumo {
+                ko: parse(ko)?,
+                oya: parse(oya)?,
+            },
+            None if all => Payment::TsumoAll(parse(rest)?),
+            None => Payment::Ron(parse(rest)?),
+        };
+
+        Ok(Self {
+            fu,
+            han,
+            limit,
+            payment,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "40符3飜7700点".parse::<Score>().unwrap(),
+            Score {
+                fu: Some(40),
+                han: Some(3),
+                limit: None,
+                payment: Payment::Ron(7700),
+            },
+        );
+        assert_eq!(
+            "40符1飜400-700点".parse::<Score>().unwrap().payment,
+            Payment::Tsumo { ko: 400, oya: 700 },
+        );
+        assert_eq!(
+            "30符2飜1000点∀".parse::<Score>().unwrap().payment,
+            Payment::TsumoAll(1000),
+        );
+
+        let score: Score = "倍満4000-8000点".parse().unwrap();
+        assert_eq!((score.fu, score.limit), (None, Some(Limit::Baiman)));
+        let score: Score = "三倍満36000点".parse().unwrap();
+        assert_eq!(score.limit, Some(Limit::Sanbaiman));
+        assert_eq!(score.payment.total(1), 36000);
+        let score: Score = "満貫4000点∀".parse().unwrap();
+        assert_eq!(score.payment.total(2), 8000);
+
+        "".parse::<Score>().unwrap_err();
+        "30符2飜".parse::<Score>().unwrap_err();
+        "1000-2000点∀".parse::<Score>().unwrap_err();
+    }
+
+    #[test]
+    fn expected_payment() {
+        let tenhou = ScoringRules::default();
+        let payment = |s: &str, oya, tsumo, rules: &ScoringRules| {
+            s.parse::<Score>()
+                .unwrap()
+                .expected_payment(oya, tsumo, 1, rules)
+                .unwrap()
+        };
+        for (s, oya) in [
+            ("40符3飜7700点", true),
+            ("30符2飜2900点", true),
+            ("40符3飜5200点", false),
+            ("満貫8000点", false),
+            ("跳満12000点", false),
+        ] {
+            let score: Score = s.parse().unwrap();
+            assert_eq!(payment(s, oya, false, &tenhou), score.payment);
+        }
+        assert_eq!(
+            payment("40符1飜400-700点", false, true, &tenhou),
+            Payment::Tsumo { ko: 400, oya: 700 },
+        );
+        assert_eq!(
+            payment("30符2飜1000点∀", true, true, &tenhou),
+            Payment::TsumoAll(1000),
+        );
+        assert_eq!(
+            payment("30符4飜11600点", true, false, &tenhou),
+            Payment::Ron(11600)
+        );
+
+        let kiriage = ScoringRules {
+            kiriage_mangan: true,
+            ..tenhou
+        };
+        assert_eq!(
+            payment("30符4飜11600点", true, false, &kiriage),
+            Payment::Ron(12000)
+        );
+        assert_eq!(
+            payment("60符3飜7700点", false, false, &kiriage),
+            Payment::Ron(8000)
+        );
+        assert_eq!(
+            payment("30符3飜3900点", false, false, &kiriage),
+            Payment::Ron(3900)
+        );
+
+        // 30 fu 1 han, 240 base points: the missing seat's 300 of the
+        // non-dealer tsumo are split.
+        let split = ScoringRules {
+            tsumo: TsumoPayment::Split,
+            ..tenhou
+        };
+        assert_eq!(
+            payment("30符1飜300-500点", false, true, &split),
+            Payment::Tsumo { ko: 400, oya: 600 },
+        );
+        let share = ScoringRules {
+            rounding: Rounding::Share,
+            ..split
+        };
+        assert_eq!(
+            payment("30符1飜300-500点", false, true, &share),
+            Payment::Tsumo { ko: 500, oya: 700 },
+        );
+        assert_eq!(
+            payment("満貫4000点∀", true, true, &split),
+            Payment::TsumoAll(6000)
+        );
+    }
+}
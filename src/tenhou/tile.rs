@@ -1,9 +1,13 @@
-use crate::Tile;
+use crate::{Tile, TileError};
 
 use num_enum::TryFromPrimitive;
-use serde_repr::{Deserialize_repr as DeserializeRepr, Serialize_repr as SerializeRepr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer};
+#[cfg(feature = "serde")]
+use serde_repr::Serialize_repr as SerializeRepr;
 
-#[derive(Debug, Clone, Copy, SerializeRepr, DeserializeRepr, TryFromPrimitive)]
+#[derive(Debug, Clone, Copy, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(SerializeRepr))]
 #[repr(u8)]
 pub enum TenhouTile {
     Unknown = 0,
@@ -51,6 +55,50 @@ pub enum TenhouTile {
     AkaSou5 = 53,
 }
 
+impl TenhouTile {
+    /// The tile of a code such as `52` in tenhou.net/6 logs.
+    pub fn from_code(code: u8) -> Result<Self, TileError> {
+        Self::try_from(code).map_err(|_| TileError::number(code, "tenhou tile code"))
+    }
+
+    /// The suit and number, `0` for aka fives, in MPSZ shorthand, `None` for
+    /// the unknown tile.
+    #[cfg(feature = "std")]
+    pub(crate) const fn mpsz(self) -> Option<(u8, char)> {
+        let id = self as u8;
+        match id {
+            0 => None,
+            51..=53 => Some((0, [b'm', b'p', b's'][(id - 51) as usize] as char)),
+            _ => Some((
+                id % 10,
+                [b'm', b'p', b's', b'z'][(id / 10 - 1) as usize] as char,
+            )),
+        }
+    }
+
+    /// The position of the tile in hands sorted by tenhou: by code, an aka
+    /// five right after the plain fives of its suit.
+    #[cfg(feature = "std")]
+    pub(crate) const fn sort_key(self) -> (u8, bool) {
+        let id = self as u8;
+        match id {
+            51..=53 => ((id - 50) * 10 + 5, true),
+            _ => (id, false),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for TenhouTile {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let code = u8::deserialize(deserializer)?;
+        Self::from_code(code).map_err(serde::de::Error::custom)
+    }
+}
+
 impl From<TenhouTile> for Tile {
     fn from(pai: TenhouTile) -> Self {
         let n = match pai {
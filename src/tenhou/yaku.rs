@@ -0,0 +1,284 @@
+use crate::{t, Tile};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+/// A yaku as listed in the hora details of a tenhou.net/6 log, dora included.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum Yaku {
+    MenzenTsumo,
+    Riichi,
+    Ippatsu,
+    Chankan,
+    Rinshan,
+    Haitei,
+    Houtei,
+    Pinfu,
+    Tanyao,
+    Iipeikou,
+    /// 自風, with the wind tile.
+    SeatWind(Tile),
+    /// 場風, with the wind tile.
+    RoundWind(Tile),
+    /// 役牌, with the dragon tile.
+    Yakuhai(Tile),
+    DoubleRiichi,
+    Chiitoitsu,
+    Chanta,
+    Ittsu,
+    SanshokuDoujun,
+    SanshokuDoukou,
+    Sankantsu,
+    Toitoi,
+    Sanankou,
+    Shousangen,
+    Honroutou,
+    Ryanpeikou,
+    Junchan,
+    Honitsu,
+    Chinitsu,
+    Renhou,
+    Tenhou,
+    Chiihou,
+    Daisangen,
+    Suuankou,
+    SuuankouTanki,
+    Tsuuiisou,
+    Ryuuiisou,
+    Chinroutou,
+    Chuuren,
+    JunseiChuuren,
+    Kokushi,
+    Kokushi13,
+    Daisuushi,
+    Shousuushi,
+    Suukantsu,
+    Dora,
+    Uradora,
+    Akadora,
+    Nukidora,
+    /// A name this crate does not know, e.g. a local yaku of another
+    /// client, as it is written.
+    Unknown(String),
+}
+
+/// The value of a yaku.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum Han {
+    Regular(u8),
+    /// Multiples of yakuman, `1` for a single one.
+    Yakuman(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidYaku(pub String);
+
+impl fmt::Display for InvalidYaku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid yaku: {:?}", self.0)
+    }
+}
+
+impl Error for InvalidYaku {}
+
+const NAMES: &[(&str, Yaku)] = &[
+    ("門前清自摸和", Yaku::MenzenTsumo),
+    ("立直", Yaku::Riichi),
+    ("一発", Yaku::Ippatsu),
+    ("槍槓", Yaku::Chankan),
+    ("嶺上開花", Yaku::Rinshan),
+    ("海底摸月", Yaku::Haitei),
+    ("河底撈魚", Yaku::Houtei),
+    ("平和", Yaku::Pinfu),
+    ("断幺九", Yaku::Tanyao),
+    ("一盃口", Yaku::Iipeikou),
+    ("両立直", Yaku::DoubleRiichi),
+    ("七対子", Yaku::Chiitoitsu),
+    ("混全帯幺九", Yaku::Chanta),
+    ("一気通貫", Yaku::Ittsu),
+    ("三色同順", Yaku::SanshokuDoujun),
+    ("三色同刻", Yaku::SanshokuDoukou),
+    ("三槓子", Yaku::Sankantsu),
+    ("対々和", Yaku::Toitoi),
+    ("三暗刻", Yaku::Sanankou),
+    ("小三元", Yaku::Shousangen),
+    ("混老頭", Yaku::Honroutou),
+    ("二盃口", Yaku::Ryanpeikou),
+    ("純全帯幺九", Yaku::Junchan),
+    ("混一色", Yaku::Honitsu),
+    ("清一色", Yaku::Chinitsu),
+    ("人和", Yaku::Renhou),
+    ("天和", Yaku::Tenhou),
+    ("地和", Yaku::Chiihou),
+    ("大三元", Yaku::Daisangen),
+    ("四暗刻", Yaku::Suuankou),
+    ("四暗刻単騎", Yaku::SuuankouTanki),
+    ("字一色", Yaku::Tsuuiisou),
+    ("緑一色", Yaku::Ryuuiisou),
+    ("清老頭", Yaku::Chinroutou),
+    ("九蓮宝燈", Yaku::Chuuren),
+    ("純正九蓮宝燈", Yaku::JunseiChuuren),
+    ("国士無双", Yaku::Kokushi),
+    ("国士無双１３面", Yaku::Kokushi13),
+    ("大四喜", Yaku::Daisuushi),
+    ("小四喜", Yaku::Shousuushi),
+    ("四槓子", Yaku::Suukantsu),
+    ("ドラ", Yaku::Dora),
+    ("裏ドラ", Yaku::Uradora),
+    ("赤ドラ", Yaku::Akadora),
+    ("抜きドラ", Yaku::Nukidora),
+];
+
+const HONORS: &[(char, Tile)] = &[
+    ('東', t!(E)),
+    ('南', t!(S)),
+    ('西', t!(W)),
+    ('北', t!(N)),
+    ('白', t!(P)),
+    ('發', t!(F)),
+    ('中', t!(C)),
+];
+
+impl Yaku {
+    #[inline]
+    #[must_use]
+    pub const fn is_dora(&self) -> bool {
+        matches!(
+            self,
+            Self::Dora | Self::Uradora | Self::Akadora | Self::Nukidora
+        )
+    }
+
+    /// Parse a yaku together with its value, e.g. `役牌 中(1飜)` or
+    /// `大三元(役満)`. A name not known is kept as [`Yaku::Unknown`]; only a
+    /// value that does not parse fails.
+    pub fn parse_with_han(s: &str) -> Result<(Self, Han), InvalidYaku> {
+        let invalid = || InvalidYaku(s.to_owned());
+
+        let (name, value) = s
+            .strip_suffix(')')
+            .and_then(|s| s.rsplit_once('('))
+            .ok_or_else(invalid)?;
+        let han = match value {
+            "役満" => Han::Yakuman(1),
+            "ダブル役満" => Han::Yakuman(2),
+            "トリプル役満" => Han::Yakuman(3),
+            _ => value
+                .strip_suffix('飜')
+                .and_then(|n| n.parse().ok())
+                .map(Han::Regular)
+                .ok_or_else(invalid)?,
+        };
+
+        let yaku = name
+            .parse()
+            .unwrap_or_else(|_| Self::Unknown(name.to_owned()));
+        Ok((yaku, han))
+    }
+}
+
+impl FromStr for Yaku {
+    type Err = InvalidYaku;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, yaku)) = NAMES.iter().find(|(name, _)| *name == s) {
+            return Ok(yaku.clone());
+        }
+
+        let invalid = || InvalidYaku(s.to_owned());
+        let (kind, honor) = s.split_once(' ').ok_or_else(invalid)?;
+        let tile = HONORS
+            .iter()
+            .find(|(c, _)| honor.chars().eq([*c]))
+            .map(|&(_, tile)| tile)
+            .ok_or_else(invalid)?;
+        match kind {
+            "自風" => Ok(Self::SeatWind(tile)),
+            "場風" => Ok(Self::RoundWind(tile)),
+            "役牌" => Ok(Self::Yakuhai(tile)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Yaku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, tile) = match *self {
+            Self::SeatWind(tile) => ("自風", tile),
+            Self::RoundWind(tile) => ("場風", tile),
+            Self::Yakuhai(tile) => ("役牌", tile),
+            Self::Unknown(ref name) => return f.write_str(name),
+            ref yaku => {
+                let (name, _) = NAMES
+                    .iter()
+                    .find(|(_, y)| y == yaku)
+                    .expect("every other yaku has a name");
+                return f.write_str(name);
+            }
+        };
+        let honor = HONORS
+            .iter()
+            .find(|(_, t)| *t == tile)
+            .map_or('?', |&(c, _)| c);
+        write!(f, "{kind} {honor}")
+    }
+}
+
+impl fmt::Display for Han {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Regular(n) => write!(f, "{n}飜"),
+            Self::Yakuman(1) => f.write_str("役満"),
+            Self::Yakuman(2) => f.write_str("ダブル役満"),
+            Self::Yakuman(3) => f.write_str("トリプル役満"),
+            Self::Yakuman(n) => write!(f, "{n}倍役満"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Yaku::parse_with_han("役牌 中(1飜)").unwrap(),
+            (Yaku::Yakuhai(t!(C)), Han::Regular(1)),
+        );
+        assert_eq!(
+            Yaku::parse_with_han("ドラ(2飜)").unwrap(),
+            (Yaku::Dora, Han::Regular(2)),
+        );
+        assert_eq!(
+            Yaku::parse_with_han("混一色(3飜)").unwrap(),
+            (Yaku::Honitsu, Han::Regular(3)),
+        );
+        assert_eq!(
+            Yaku::parse_with_han("大三元(役満)").unwrap(),
+            (Yaku::Daisangen, Han::Yakuman(1)),
+        );
+        assert_eq!(
+            Yaku::parse_with_han("焼き鳥(1飜)").unwrap(),
+            (Yaku::Unknown("焼き鳥".to_owned()), Han::Regular(1)),
+        );
+        "焼き鳥".parse::<Yaku>().unwrap_err();
+        Yaku::parse_with_han("立直").unwrap_err();
+        Yaku::parse_with_han("立直(一飜)").unwrap_err();
+    }
+
+    #[test]
+    fn display() {
+        for s in [
+            "場風 東(1飜)",
+            "門前清自摸和(1飜)",
+            "四暗刻単騎(ダブル役満)",
+            "焼き鳥(1飜)",
+        ] {
+            let (yaku, han) = Yaku::parse_with_han(s).unwrap();
+            assert_eq!(format!("{yaku}({han})"), s);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! A handful of representative sanma logs for tests, of this crate and of the
+//! ones built on it.
+
+use crate::tenhou::Log;
+
+/// A log shipped with the crate, see [`Fixture::ALL`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fixture {
+    /// A complete 鳳凰 hanchan with a disconnection and a reconnection.
+    Hanchan,
+    /// A kyoku ending in a double ron on the dealer's first discard.
+    MultiRon,
+    /// A kyoku with a daiminkan, with the `0` tenhou writes in place of its
+    /// discard, an ankan, a pon upgraded to a kakan and a nukidora, won by
+    /// rinshan kaihou.
+    KanHeavy,
+    /// The last kyokus of a game, where the dealer of S3 wins twice before
+    /// losing the seat.
+    AllLastRenchan,
+}
+
+impl Fixture {
+    pub const ALL: [Self; 4] = [
+        Self::Hanchan,
+        Self::MultiRon,
+        Self::KanHeavy,
+        Self::AllLastRenchan,
+    ];
+
+    /// The log as tenhou.net/6 JSON.
+    #[must_use]
+    pub const fn json(self) -> &'static str {
+        match self {
+            Self::Hanchan => include_str!("../testdata/hanchan.json"),
+            Self::MultiRon => include_str!("../testdata/multi_ron.json"),
+            Self::KanHeavy => include_str!("../testdata/kan_heavy.json"),
+            Self::AllLastRenchan => include_str!("../testdata/all_last_renchan.json"),
+        }
+    }
+
+    /// Parse the log.
+    ///
+    /// # Panics
+    /// Never, every fixture is checked to parse by the tests of this crate.
+    #[must_use]
+    pub fn log(self) -> Log {
+        Log::from_json_str(self.json()).expect("fixtures parse")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::tenhou::{EndStatus, StickRule};
+
+    #[test]
+    fn fixtures() {
+        for fixture in Fixture::ALL {
+            let log = fixture.log();
+            tenhou_to_mjai(&log).unwrap();
+            log.check_rule_flags().unwrap();
+            log.check_ledger().unwrap();
+            log.check_sticks(StickRule::default()).unwrap();
+            log.check_payers().unwrap();
+        }
+
+        let log = Fixture::MultiRon.log();
+        assert!(matches!(
+            &log.kyokus[0].end_status,
+            EndStatus::Hora { details } if details.len() == 2
+        ));
+        assert_eq!(Fixture::KanHeavy.log().kyokus[0].kan_count(), 3);
+        let renchan: Vec<_> = Fixture::AllLastRenchan
+            .log()
+            .kyokus
+            .iter()
+            .map(|k| (k.meta.kyoku_num, k.meta.honba))
+            .collect();
+        assert_eq!(renchan, [(6, 0), (6, 1), (6, 2)]);
+    }
+}
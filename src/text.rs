@@ -0,0 +1,394 @@
+//! A human-readable transcript of a converted log, for quick review in a
+//! terminal.
+
+use crate::defense::{relation, visible, wall, Relation, Wall};
+use crate::locale::Locale;
+use crate::mjai::Event;
+use crate::output::AkaEncoding;
+use crate::replay::KyokuState;
+use crate::tenhou::{HoraDetail, KyokuMeta, Log, Payment};
+use crate::{tu8, Points, Tile};
+use std::fmt::Write;
+
+/// Render `events` converted from `log` as one paragraph per kyoku, with one
+/// line per turn, e.g. `mtk draws 5p, discards W`. Discards against a
+/// riichi note whether they are genbutsu or suji to it and the walls in
+/// front of them, e.g. `discards 1s (suji to mtk, no chance)`. Tiles, rounds
+/// and yaku are named according to `locale`, the tiles of [`Locale::En`] in
+/// `aka`.
+#[must_use]
+pub fn render_text(log: &Log, events: &[Event], locale: Locale, aka: AkaEncoding) -> String {
+    let names: Vec<_> = log
+        .names
+        .iter()
+        .zip('A'..='D')
+        .map(|(name, alias)| {
+            if name.is_empty() {
+                format!("Player {alias}")
+            } else {
+                name.clone()
+            }
+        })
+        .collect();
+
+    let mut out = String::new();
+    let mut kyokus = log.kyokus.iter();
+    let mut horas = vec![].into_iter();
+    // The turn being written, by actor.
+    let mut turn: Option<(u8, String)> = None;
+    // The state of the kyoku, for notes on discards, until an event fails
+    // to replay.
+    let mut state = Some(KyokuState::default());
+
+    for (index, event) in events.iter().enumerate() {
+        let actor = event.actor();
+        if let Some((prev, line)) = turn.take() {
+            let continues = actor == Some(prev)
+                && matches!(
+                    event,
+                    Event::Dahai { .. }
+                        | Event::Reach { .. }
+                        | Event::ReachAccepted { .. }
+                        | Event::Kakan { .. }
+                        | Event::Ankan { .. }
+                        | Event::Nukidora { .. }
+                );
+            if continues {
+                turn = Some((prev, line));
+            } else {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+        let name = |seat: u8| names[seat as usize].as_str();
+        let tile = |tile: &Tile| tile_name(*tile, locale, aka);
+        let tiles = |tiles: &[Tile]| tiles_of(tiles, locale, aka);
+
+        match event {
+            Event::StartGame { .. } => {
+                let players = names[..3].join(", ");
+                writeln!(out, "{players}").ok();
+            }
+            Event::StartKyoku {
+                bakaze,
+                dora_marker,
+                kyoku,
+                honba,
+                kyotaku,
+                oya,
+                scores,
+                ..
+            } => {
+                horas = kyokus
+                    .next()
+                    .map(|k| k.horas().into_iter().cloned().collect::<Vec<_>>())
+                    .unwrap_or_default()
+                    .into_iter();
+                let meta = KyokuMeta {
+                    kyoku_num: bakaze.as_u8().saturating_sub(tu8!(E)) % 4 * 4
+                        + kyoku.saturating_sub(1) % 4,
+                    honba: *honba,
+                    kyotaku: *kyotaku,
+                };
+                writeln!(
+                    out,
+                    "\n{}, {kyotaku} riichi sticks, dealer {}, dora indicator {}",
+                    meta.display_name(locale),
+                    name(*oya),
+                    tile(dora_marker),
+                )
+                .ok();
+                writeln!(out, "  {}", seat_values(&names, scores, false)).ok();
+            }
+
+            Event::Tsumo { actor, pai } => {
+                turn = Some((*actor, format!("  {} draws {}", name(*actor), tile(pai))));
+            }
+            Event::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                let mut notes = vec![];
+                if *tsumogiri {
+                    notes.push("tsumogiri".to_owned());
+                }
+                if let Some(state) = &state {
+                    notes.extend(defense_notes(state, *actor, *pai, &names));
+                }
+                let what = if notes.is_empty() {
+                    format!("discards {}", tile(pai))
+                } else {
+                    format!("discards {} ({})", tile(pai), notes.join(", "))
+                };
+                append(&mut turn, *actor, name(*actor), &what);
+            }
+            Event::Reach { actor } => append(&mut turn, *actor, name(*actor), "declares riichi"),
+            Event::ReachAccepted { .. } | Event::None | Event::EndGame { .. } | Event::EndKyoku => {
+            }
+
+            Event::Chi {
+                actor,
+                target,
+                pai,
+                consumed,
+            }
+            | Event::Pon {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                let kind = if matches!(event, Event::Chi { .. }) {
+                    "chi"
+                } else {
+                    "pon"
+                };
+                let line = format!(
+                    "  {} calls {kind} on {} with {} from {}",
+                    name(*actor),
+                    tile(pai),
+                    tiles(consumed),
+                    name(*target),
+                );
+                turn = Some((*actor, line));
+            }
+            Event::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                let line = format!(
+                    "  {} calls kan on {} with {} from {}",
+                    name(*actor),
+                    tile(pai),
+                    tiles(consumed),
+                    name(*target),
+                );
+                turn = Some((*actor, line));
+            }
+            Event::Kakan { actor, pai, .. } => {
+                append(
+                    &mut turn,
+                    *actor,
+                    name(*actor),
+                    &format!("declares kakan {}", tile(pai)),
+                );
+            }
+            Event::Ankan { actor, consumed } => {
+                let what = format!("declares ankan {}", tiles(consumed));
+                append(&mut turn, *actor, name(*actor), &what);
+            }
+            Event::Nukidora { actor, consumed } => {
+                let what = format!("declares nukidora {}", tiles(consumed));
+                append(&mut turn, *actor, name(*actor), &what);
+            }
+            Event::Dora { dora_marker } => {
+                writeln!(out, "  new dora indicator {}", tile(dora_marker)).ok();
+            }
+
+            Event::Hora {
+                actor,
+                target,
+                deltas,
+                ura_markers,
+                ..
+            } => {
+                let detail = horas.next();
+                let mut line = format!("  {}: ", name(*actor));
+                match detail.as_ref().and_then(|d| d.score) {
+                    Some(score) => {
+                        match score.payment {
+                            Payment::Ron(x) => write!(line, "Ron {x} off {}", name(*target)),
+                            Payment::Tsumo { ko, oya } => write!(line, "Tsumo {ko}-{oya}"),
+                            Payment::TsumoAll(x) => write!(line, "Tsumo {x} all"),
+                        }
+                        .ok();
+                        if let (Some(fu), Some(han)) = (score.fu, score.han) {
+                            write!(line, " ({})", locale.fu_han(fu, han)).ok();
+                        }
+                        if let Some(limit) = score.limit {
+                            write!(line, " {}", locale.limit(limit)).ok();
+                        }
+                    }
+                    None if actor == target => line.push_str("Tsumo"),
+                    None => {
+                        write!(line, "Ron off {}", name(*target)).ok();
+                    }
+                }
+                if let Some(detail) = &detail {
+                    line.push_str(&yaku_list(detail, locale));
+                }
+                if let Some(ura) = ura_markers.as_ref().filter(|ura| !ura.is_empty()) {
+                    write!(line, ", ura indicators {}", tiles(ura)).ok();
+                }
+                writeln!(out, "{line}").ok();
+                if let Some(deltas) = deltas {
+                    writeln!(out, "  {}", seat_values(&names, deltas, true)).ok();
+                }
+            }
+            Event::Ryukyoku {
+                reason,
+                deltas,
+                tenpais,
+                ..
+            } => {
+                let mut line = "  Ryukyoku".to_owned();
+                if let Some(reason) = reason {
+                    write!(line, " ({reason})").ok();
+                }
+                if let Some(tenpais) = tenpais {
+                    let tenpai: Vec<_> = names
+                        .iter()
+                        .zip(tenpais)
+                        .filter(|&(_, &t)| t)
+                        .map(|(name, _)| name.as_str())
+                        .collect();
+                    if tenpai.is_empty() {
+                        line.push_str(", all noten");
+                    } else {
+                        write!(line, ", tenpai {}", tenpai.join(", ")).ok();
+                    }
+                }
+                writeln!(out, "{line}").ok();
+                if let Some(deltas) = deltas {
+                    writeln!(out, "  {}", seat_values(&names, deltas, true)).ok();
+                }
+            }
+        }
+        if let Some(kyoku) = &mut state {
+            if kyoku.apply(index, event).is_err() {
+                state = None;
+            }
+        }
+    }
+    if let Some((_, line)) = turn {
+        writeln!(out, "{line}").ok();
+    }
+
+    out
+}
+
+/// How the discard of `pai` by `actor` relates to every other seat that
+/// declared riichi, and the wall in front of it if any of them did.
+fn defense_notes(state: &KyokuState, actor: u8, pai: Tile, names: &[String]) -> Vec<String> {
+    let mut notes = vec![];
+    let mut riichi = false;
+    for (seat, player) in (0..).zip(&state.players) {
+        if seat == actor || !player.riichi_declared {
+            continue;
+        }
+        riichi = true;
+        let name = &names[usize::from(seat)];
+        match relation(state, seat, pai) {
+            Relation::Genbutsu => notes.push(format!("genbutsu to {name}")),
+            Relation::Suji => notes.push(format!("suji to {name}")),
+            Relation::Unrelated => (),
+        }
+    }
+    if riichi {
+        match wall(&visible(state, actor), pai) {
+            Some(Wall::NoChance) => notes.push("no chance".to_owned()),
+            Some(Wall::OneChance) => notes.push("one chance".to_owned()),
+            None => (),
+        }
+    }
+    notes
+}
+
+/// Continue the turn of `actor` with `what`, or start a new one.
+fn append(turn: &mut Option<(u8, String)>, actor: u8, name: &str, what: &str) {
+    match turn {
+        Some((_, line)) => {
+            line.push_str(", ");
+            line.push_str(what);
+        }
+        None => *turn = Some((actor, format!("  {name} {what}"))),
+    }
+}
+
+fn tile_name(tile: Tile, locale: Locale, aka: AkaEncoding) -> String {
+    match locale {
+        Locale::En => aka.encode(tile).into_owned(),
+        Locale::Ja => locale.tile(tile),
+    }
+}
+
+fn tiles_of(tiles: &[Tile], locale: Locale, aka: AkaEncoding) -> String {
+    tiles
+        .iter()
+        .map(|&t| tile_name(t, locale, aka))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn seat_values(names: &[String], values: &[Points; 4], signed: bool) -> String {
+    names[..3]
+        .iter()
+        .zip(values)
+        .map(|(name, v)| {
+            if signed {
+                format!("{name} {v:+}")
+            } else {
+                format!("{name} {v}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn yaku_list(detail: &HoraDetail, locale: Locale) -> String {
+    detail
+        .yaku
+        .iter()
+        .map(|(yaku, han)| format!(", {} {}", locale.yaku(yaku), locale.han(*han)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+
+    const LOG: &str = r#"{"ver":2.3,"ref":"2024030511gm-00b9-0000-e0c07689","log":[[[0,0,0],[35000,35000,35000,0],[47],[],[26,27,32,33,35,37,37,39,41,42,44,44,46],[45,47,19,39,27,34,21,43,26],[60,60,60,60,60,60,"f44","f44",42],[11,19,21,23,29,31,33,41,42,44,44,45,46],[42,28,22,19,"4242p42",23,22,24],["f44",33,"f44",60,19,31,11,41],[19,21,29,29,29,31,34,34,38,41,43,47,47],[21,"47p4747",42,35,27,26,"34p3434",25],[19,43,60,31,41,38,35],[],[],[],["和了",[-700,-400,1100,0],[2,2,2,"40符1飜400-700点","役牌 中(1飜)"]]]],"rule":{"disp":"三鳳南喰赤","aka53":1,"aka52":1,"aka51":1},"name":["mtk","つくねん3",""," "]}"#;
+
+    #[test]
+    fn transcript() {
+        let log = Log::from_json_str(LOG).unwrap();
+        let events = tenhou_to_mjai(&log).unwrap();
+        let text = render_text(&log, &events, Locale::En, AkaEncoding::Mjai);
+        let lines: Vec<_> = text.lines().collect();
+
+        assert_eq!(lines[0], "mtk, つくねん3, Player C");
+        assert_eq!(
+            lines[2],
+            "East 1, 0 honba, 0 riichi sticks, dealer mtk, dora indicator C",
+        );
+        assert_eq!(lines[4], "  mtk draws P, discards P (tsumogiri)");
+        assert!(lines.contains(&"  Player C calls pon on C with C C from mtk, discards W"));
+        assert_eq!(
+            lines[lines.len() - 2],
+            "  Player C: Tsumo 400-700 (40 fu 1 han), Yakuhai Chun 1 han",
+        );
+        assert_eq!(
+            lines[lines.len() - 1],
+            "  mtk -700, つくねん3 -400, Player C +1100"
+        );
+
+        let text = render_text(&log, &events, Locale::En, AkaEncoding::Mpsz);
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines[4], "  mtk draws 5z, discards 5z (tsumogiri)");
+
+        let text = render_text(&log, &events, Locale::Ja, AkaEncoding::Tenhou);
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(
+            lines[2],
+            "東1局 0本場, 0 riichi sticks, dealer mtk, dora indicator 中",
+        );
+        assert_eq!(
+            lines[lines.len() - 2],
+            "  Player C: Tsumo 400-700 (40符1飜), 役牌 中 1飜",
+        );
+    }
+}
@@ -1,12 +1,13 @@
 // This file is a derived version of Mortal:/libriichi/src/tile.rs
 
 use crate::{matches_tu8, t, tu8};
-use std::error::Error;
-use std::fmt;
-use std::str::FromStr;
-use std::sync::LazyLock;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
 
-use ahash::AHashMap;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 const MJAI_PAI_STRINGS_LEN: usize = 2 * 9 + 4 + 3 + 2 + 2 + 1;
@@ -18,21 +19,48 @@ const MJAI_PAI_STRINGS: [&str; MJAI_PAI_STRINGS_LEN] = [
     "?",   // unknown
 ];
 
-static MJAI_PAI_STRINGS_MAP: LazyLock<AHashMap<&'static str, Tile>> = LazyLock::new(|| {
-    MJAI_PAI_STRINGS
-        .iter()
-        .enumerate()
-        .map(|(id, &s)| (s, Tile::try_from(id).unwrap()))
-        .collect()
-});
-
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Tile(u8);
 
-#[derive(Debug)]
-pub enum InvalidTile {
-    Number(usize),
-    String(String),
+/// A value that is not a tile, and what it was read as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TileError {
+    /// The value as written, e.g. `99` or `"0s"`.
+    pub value: String,
+    /// What the value was read as, e.g. `mjai tile`.
+    pub context: &'static str,
+}
+
+impl TileError {
+    #[must_use]
+    pub fn number(n: impl fmt::Display, context: &'static str) -> Self {
+        Self {
+            value: n.to_string(),
+            context,
+        }
+    }
+
+    #[must_use]
+    pub fn string(s: &str, context: &'static str) -> Self {
+        Self {
+            value: format!("{s:?}"),
+            context,
+        }
+    }
+
+    /// Say where the value was read, e.g. `tenhou tile code in a naki`.
+    #[must_use]
+    pub fn with_context(self, context: &'static str) -> Self {
+        Self { context, ..self }
+    }
+
+    /// A stable identifier of the error, `tile.invalid`, to branch on
+    /// instead of the message.
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        "tile.invalid"
+    }
 }
 
 impl Tile {
@@ -55,6 +83,15 @@ impl Tile {
         self.0 as usize
     }
 
+    /// The mjai string of the tile, e.g. `5pr`, as [`fmt::Display`] writes
+    /// it.
+    #[inline]
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        // SAFETY: `Tile` is in-bound iff it is constructed safely.
+        unsafe { MJAI_PAI_STRINGS.get_unchecked(self.0 as usize) }
+    }
+
     #[inline]
     #[must_use]
     pub const fn deaka(self) -> Self {
@@ -105,6 +142,28 @@ impl Tile {
         self.0 >= tu8!(?)
     }
 
+    /// The tile as a character of the Mahjong Tiles block of Unicode, e.g.
+    /// `🀙` for `1p`. Aka tiles have no character of their own and are their
+    /// plain 5; unknown tiles are the back of a tile.
+    #[must_use]
+    pub const fn to_unicode(self) -> char {
+        let code = match self.deaka().0 {
+            id if id <= tu8!(9p) => 0x1f019 + (id - tu8!(1p)) as u32,
+            id if id <= tu8!(9s) => 0x1f010 + (id - tu8!(1s)) as u32,
+            id if id <= tu8!(N) => 0x1f000 + (id - tu8!(E)) as u32,
+            tu8!(P) => 0x1f006,
+            tu8!(F) => 0x1f005,
+            tu8!(C) => 0x1f004,
+            tu8!(1m) => 0x1f007,
+            tu8!(9m) => 0x1f00f,
+            _ => 0x1f02b,
+        };
+        match char::from_u32(code) {
+            Some(c) => c,
+            None => '?',
+        }
+    }
+
     #[inline]
     #[must_use]
     pub const fn next(self) -> Self {
@@ -139,7 +198,7 @@ impl Tile {
             Self(kind * 9 + (num + 9 - 1) % 9)
         } else if num < 4 {
             Self(2 * 9 + (num + 4 - 1) % 4)
-        } else if num < 7{
+        } else if num < 7 {
             Self(2 * 9 + 4 + (num - 4 + 3 - 1) % 3)
         } else {
             Self(2 * 9 + 7 + (num - 7 + 2 - 1) % 2)
@@ -154,7 +213,7 @@ impl Default for Tile {
 }
 
 impl TryFrom<u8> for Tile {
-    type Error = InvalidTile;
+    type Error = TileError;
 
     fn try_from(v: u8) -> Result<Self, Self::Error> {
         Self::try_from(v as usize)
@@ -162,12 +221,13 @@ impl TryFrom<u8> for Tile {
 }
 
 impl TryFrom<usize> for Tile {
-    type Error = InvalidTile;
+    type Error = TileError;
 
     fn try_from(v: usize) -> Result<Self, Self::Error> {
         if v >= MJAI_PAI_STRINGS_LEN {
-            Err(InvalidTile::Number(v))
+            Err(TileError::number(v, "tile ID"))
         } else {
+            // SAFETY: `v` has been checked to be in-bound.
             let tile = unsafe { Self::new_unchecked(v as u8) };
             Ok(tile)
         }
@@ -175,13 +235,24 @@ impl TryFrom<usize> for Tile {
 }
 
 impl FromStr for Tile {
-    type Err = InvalidTile;
+    type Err = TileError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        MJAI_PAI_STRINGS_MAP
-            .get(s)
-            .copied()
-            .ok_or_else(|| InvalidTile::String(s.to_owned()))
+        // A linear scan over 30 short strings, which needs no allocation or
+        // lazily built map and is about as fast.
+        MJAI_PAI_STRINGS
+            .iter()
+            .position(|&pai| pai == s)
+            .and_then(|id| Self::try_from(id).ok())
+            .ok_or_else(|| TileError::string(s, "mjai tile"))
+    }
+}
+
+impl TryFrom<&str> for Tile {
+    type Error = TileError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
     }
 }
 
@@ -193,12 +264,11 @@ impl fmt::Debug for Tile {
 
 impl fmt::Display for Tile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // SAFETY: `Tile` is in-bound iff it is constructed safely.
-        let s = unsafe { MJAI_PAI_STRINGS.get_unchecked(self.0 as usize) };
-        f.write_str(s)
+        f.write_str(self.as_str())
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for Tile {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -211,6 +281,7 @@ impl<'de> Deserialize<'de> for Tile {
     }
 }
 
+#[cfg(feature = "serde")]
 impl Serialize for Tile {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -220,22 +291,18 @@ impl Serialize for Tile {
     }
 }
 
-impl fmt::Display for InvalidTile {
+impl fmt::Display for TileError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("not a valid tile: ")?;
-        match self {
-            Self::Number(n) => fmt::Display::fmt(n, f),
-            Self::String(s) => write!(f, "not a valid tile: \"{s}\""),
-        }
+        write!(f, "not a valid {}: {}", self.context, self.value)
     }
 }
 
-impl Error for InvalidTile {}
+impl Error for TileError {}
 
 /**
  * Added in mjai-reviewer
  */
-use std::cmp::Ordering;
+use core::cmp::Ordering;
 
 impl Tile {
     const fn as_ord(self) -> impl Ord {
@@ -288,7 +355,7 @@ pub fn tile_set_eq(a: &[Tile], b: &[Tile], ignore_aka: bool) -> bool {
 
     a_bits == b_bits
 }
-/**
+/*
  * End
  */
 
@@ -310,6 +377,35 @@ mod test {
         "!".parse::<Tile>().unwrap_err();
         Tile::try_from(34_u8).unwrap_err();
         Tile::try_from(u8::MAX).unwrap_err();
+        assert_eq!(Tile::try_from("C"), Ok(t!(C)));
+
+        let err = "0s".parse::<Tile>().unwrap_err();
+        assert_eq!(err.to_string(), r#"not a valid mjai tile: "0s""#);
+        let err = Tile::try_from(34_u8).unwrap_err();
+        assert_eq!(err.to_string(), "not a valid tile ID: 34");
+    }
+
+    #[test]
+    fn aka() {
+        assert_eq!(t!(5pr).deaka(), t!(5p));
+        assert_eq!(t!(5s).akaize(), t!(5sr));
+        assert_eq!(t!(E).akaize(), t!(E));
+        assert!(t!(5sr).is_aka());
+        assert!(!t!(5s).is_aka());
+        assert!(tile_set_eq(&t![5pr, 5s], &t![5sr, 5p], true));
+        assert!(!tile_set_eq(&t![5pr, 5s], &t![5sr, 5p], false));
+    }
+
+    #[test]
+    fn unicode() {
+        assert_eq!(t!(1p).to_unicode(), '🀙');
+        assert_eq!(t!(9s).to_unicode(), '🀘');
+        assert_eq!(t!(5pr).to_unicode(), t!(5p).to_unicode());
+        assert_eq!(t!(N).to_unicode(), '🀃');
+        assert_eq!(t!(P).to_unicode(), '🀆');
+        assert_eq!(t!(C).to_unicode(), '🀄');
+        assert_eq!(t!(9m).to_unicode(), '🀏');
+        assert_eq!(t!(?).to_unicode(), '🀫');
     }
 
     #[test]
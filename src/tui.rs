@@ -0,0 +1,501 @@
+//! A browser of a converted log in the terminal, for checking conversions by
+//! eye: the kyokus of the log on the left, and the table as of the chosen
+//! event on the right, with every hand, meld and river as
+//! [`replay`](crate::replay) reconstructs them. A [`Player`] plays a kyoku
+//! back on its own instead, for demos.
+
+use crate::mjai::Event;
+use crate::replay::{self, split_kyokus, KyokuReplay, KyokuState};
+use crate::{tu8, Tile};
+use std::io;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self as term, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+/// A log being browsed: which kyoku and which of its events are chosen, and
+/// how tiles are drawn.
+#[derive(Debug, Clone)]
+pub struct Browser {
+    names: [String; 4],
+    kyokus: Vec<KyokuReplay>,
+    kyoku: usize,
+    /// The events of the kyoku applied, its `start_kyoku` at least.
+    position: usize,
+    unicode: bool,
+}
+
+impl Browser {
+    /// Browse the mjai `events` of a game, from its first kyoku on. Fails if
+    /// the events of a kyoku do not replay.
+    pub fn new(events: &[Event]) -> replay::Result<Self> {
+        let names = events
+            .iter()
+            .find_map(|event| match event {
+                Event::StartGame { names, .. } => Some(names.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let kyokus = split_kyokus(events)
+            .map(|events| KyokuReplay::new(events.to_vec()))
+            .collect::<replay::Result<_>>()?;
+        Ok(Self {
+            names,
+            kyokus,
+            kyoku: 0,
+            position: 1,
+            unicode: true,
+        })
+    }
+
+    /// The chosen kyoku, counting from 0.
+    #[must_use]
+    pub const fn kyoku(&self) -> usize {
+        self.kyoku
+    }
+
+    /// The chosen event of the kyoku, which the table is shown as of.
+    #[must_use]
+    pub fn event(&self) -> Option<&Event> {
+        self.kyokus.get(self.kyoku)?.events().get(self.position - 1)
+    }
+
+    /// The table as of the chosen event.
+    #[must_use]
+    pub fn state(&self) -> Option<KyokuState> {
+        self.kyokus.get(self.kyoku)?.seek(self.position)
+    }
+
+    /// Choose the first kyoku numbered `kyoku`, counting from 0 for E1 as
+    /// [`KyokuMeta::parse_name`] does, with `honba` if given, at its
+    /// `start_kyoku`. Returns false if there is none.
+    ///
+    /// [`KyokuMeta::parse_name`]: crate::tenhou::KyokuMeta::parse_name
+    pub fn select(&mut self, kyoku: u8, honba: Option<u8>) -> bool {
+        let found = self.kyokus.iter().position(|replay| {
+            matches!(
+                replay.events().first(),
+                Some(Event::StartKyoku { bakaze, kyoku: k, honba: h, .. })
+                    if bakaze.as_u8().wrapping_sub(tu8!(E)) * 4 + k.saturating_sub(1) == kyoku
+                        && honba.is_none_or(|honba| honba == *h)
+            )
+        });
+        if let Some(found) = found {
+            self.kyoku = found;
+            self.position = 1;
+        }
+        found.is_some()
+    }
+
+    /// Act on a key press, returning false for the keys that quit.
+    pub fn handle(&mut self, key: KeyCode) -> bool {
+        let last = self.kyokus.get(self.kyoku).map_or(1, KyokuReplay::len);
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Right | KeyCode::Char('l' | ' ') => {
+                self.position = (self.position + 1).min(last);
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.position = (self.position - 1).max(1),
+            KeyCode::Home | KeyCode::Char('g') => self.position = 1,
+            KeyCode::End | KeyCode::Char('G') => self.position = last,
+            KeyCode::Down | KeyCode::Char('j') if self.kyoku + 1 < self.kyokus.len() => {
+                self.kyoku += 1;
+                self.position = 1;
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.kyoku > 0 => {
+                self.kyoku -= 1;
+                self.position = 1;
+            }
+            KeyCode::Char('u') => self.unicode = !self.unicode,
+            _ => {}
+        }
+        true
+    }
+
+    pub fn render(&self, frame: &mut Frame<'_>) {
+        let [list, main] =
+            Layout::horizontal([Constraint::Length(12), Constraint::Min(0)]).areas(frame.area());
+
+        let titles = self
+            .kyokus
+            .iter()
+            .map(|replay| match replay.events().first() {
+                Some(Event::StartKyoku {
+                    bakaze,
+                    kyoku,
+                    honba,
+                    ..
+                }) => format!("{bakaze}{kyoku}-{honba}"),
+                _ => String::new(),
+            });
+        let kyokus = List::new(titles)
+            .block(Block::bordered().title("Kyokus"))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut selected = ListState::default().with_selected(Some(self.kyoku));
+        frame.render_stateful_widget(kyokus, list, &mut selected);
+
+        if let Some(state) = self.state() {
+            let len = self.kyokus[self.kyoku].len();
+            let table = TableView {
+                names: &self.names,
+                state: &state,
+                event: self.event(),
+                progress: (self.position, len),
+                unicode: self.unicode,
+                help: "←/→ event  ↑/↓ kyoku  home/end first/last  u tile names  q quit",
+            };
+            table.render(frame, main);
+        }
+    }
+}
+
+/// The kyoku chosen in a [`Browser`] played from its start, an event every
+/// so often, which can be paused, stepped through and sped up.
+#[derive(Debug, Clone)]
+pub struct Player {
+    browser: Browser,
+    delay: Duration,
+    paused: bool,
+}
+
+impl Player {
+    /// The delays between events are kept within these.
+    const MIN_DELAY: Duration = Duration::from_millis(10);
+    const MAX_DELAY: Duration = Duration::from_secs(10);
+
+    /// Play the chosen kyoku of `browser` with `delay` between events.
+    #[must_use]
+    pub fn new(browser: Browser, delay: Duration) -> Self {
+        Self {
+            browser,
+            delay: delay.clamp(Self::MIN_DELAY, Self::MAX_DELAY),
+            paused: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn browser(&self) -> &Browser {
+        &self.browser
+    }
+
+    #[must_use]
+    pub const fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Whether the last event has been played.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        let len = self
+            .browser
+            .kyokus
+            .get(self.browser.kyoku)
+            .map_or(1, KyokuReplay::len);
+        self.browser.position >= len
+    }
+
+    /// Play the next event, unless paused.
+    pub fn tick(&mut self) {
+        if !self.paused {
+            self.browser.handle(KeyCode::Right);
+        }
+    }
+
+    /// Act on a key press, returning false for the keys that quit.
+    pub fn handle(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char(' ' | 'p') => self.paused = !self.paused,
+            KeyCode::Char('+' | '=') => self.delay = (self.delay / 2).max(Self::MIN_DELAY),
+            KeyCode::Char('-') => self.delay = (self.delay * 2).min(Self::MAX_DELAY),
+            // Stepping by hand pauses, so that the step can be looked at.
+            KeyCode::Right | KeyCode::Left | KeyCode::Char('l' | 'h') => {
+                self.paused = true;
+                self.browser.handle(key);
+            }
+            KeyCode::Home | KeyCode::Char('g' | 'u') => {
+                self.browser.handle(key);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            _ => {}
+        }
+        true
+    }
+
+    pub fn render(&self, frame: &mut Frame<'_>) {
+        let browser = &self.browser;
+        let Some(state) = browser.state() else {
+            return;
+        };
+        let status = if self.is_finished() {
+            "finished"
+        } else if self.paused {
+            "paused"
+        } else {
+            "playing"
+        };
+        let help = format!(
+            "{status} at {:.1} events/s  space pause  ←/→ step  +/- speed  home restart  q quit",
+            1.0 / self.delay.as_secs_f64()
+        );
+        let table = TableView {
+            names: &browser.names,
+            state: &state,
+            event: browser.event(),
+            progress: (browser.position, browser.kyokus[browser.kyoku].len()),
+            unicode: browser.unicode,
+            help: &help,
+        };
+        table.render(frame, frame.area());
+    }
+}
+
+/// The table of a kyoku as of an event, drawn into part of a frame.
+struct TableView<'a> {
+    names: &'a [String; 4],
+    state: &'a KyokuState,
+    event: Option<&'a Event>,
+    /// The events applied of how many.
+    progress: (usize, usize),
+    unicode: bool,
+    help: &'a str,
+}
+
+impl TableView<'_> {
+    fn render(&self, frame: &mut Frame<'_>, area: Rect) {
+        let state = self.state;
+        let [header, seats, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(4),
+        ])
+        .areas(area);
+
+        let mut line = vec![Span::raw(format!(
+            " {}{}, {} honba, {} riichi sticks, {} draws left, dora indicators ",
+            state.bakaze, state.kyoku, state.honba, state.kyotaku, state.wall
+        ))];
+        line.extend(self.tiles(&state.dora_markers));
+        let (position, len) = self.progress;
+        line.push(Span::raw(format!("  event {position}/{len}")));
+        frame.render_widget(Line::from(line), header);
+
+        // The empty fourth seat of sanma is left out.
+        let taken: Vec<_> = (0..4)
+            .filter(|&seat| !self.names[seat].is_empty() || !state.players[seat].tehai.is_empty())
+            .collect();
+        let areas = Layout::vertical(vec![Constraint::Fill(1); taken.len()]).split(seats);
+        let actor = self.event.and_then(Event::actor).map(usize::from);
+        for (&seat, &area) in taken.iter().zip(areas.iter()) {
+            let player = &state.players[seat];
+            let mut title = format!(" {} {}", self.names[seat], state.scores[seat]);
+            if usize::from(state.oya) == seat {
+                title.push_str(" dealer");
+            }
+            if player.riichi_declared {
+                title.push_str(" riichi");
+            }
+            title.push(' ');
+            let mut block = Block::bordered().title(title);
+            if actor == Some(seat) {
+                block = block.border_style(Style::new().fg(Color::Yellow));
+            }
+
+            let mut hand = self.tiles(&player.tehai);
+            for meld in &player.melds {
+                hand.push(Span::raw("  "));
+                hand.extend(self.tiles(&meld.tiles));
+            }
+            if player.kitas > 0 {
+                hand.push(Span::raw(format!("  nuki {}", player.kitas)));
+            }
+            let river = player.river.iter().map(|discard| {
+                let mut style = Style::new();
+                if discard.tsumogiri {
+                    style = style.add_modifier(Modifier::DIM);
+                }
+                if discard.riichi {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                if discard.called {
+                    style = style.add_modifier(Modifier::CROSSED_OUT);
+                }
+                self.tile(discard.pai).patch_style(style)
+            });
+            let paragraph = Paragraph::new(vec![Line::from(hand), river.collect::<Line<'_>>()])
+                .block(block)
+                .wrap(Wrap { trim: false });
+            frame.render_widget(paragraph, area);
+        }
+
+        let event = self
+            .event
+            .and_then(|event| serde_json::to_string(event).ok())
+            .unwrap_or_default();
+        let text = vec![Line::raw(event), Line::raw(self.help).dim()];
+        let paragraph = Paragraph::new(text)
+            .block(Block::bordered())
+            .wrap(Wrap { trim: true });
+        frame.render_widget(paragraph, footer);
+    }
+
+    fn tile(&self, tile: Tile) -> Span<'static> {
+        let text = if self.unicode {
+            tile.to_unicode().to_string()
+        } else {
+            format!("{tile} ")
+        };
+        let span = Span::raw(text);
+        if tile.is_aka() {
+            span.red()
+        } else {
+            span
+        }
+    }
+
+    fn tiles(&self, tiles: &[Tile]) -> Vec<Span<'static>> {
+        tiles.iter().map(|&tile| self.tile(tile)).collect()
+    }
+}
+
+/// Browse in the terminal until a key that quits is pressed.
+pub fn run(mut browser: Browser) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = browse(&mut terminal, &mut browser);
+    ratatui::restore();
+    result
+}
+
+fn browse(terminal: &mut DefaultTerminal, browser: &mut Browser) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| browser.render(frame))?;
+        if let term::Event::Key(key) = term::read()? {
+            if key.kind == KeyEventKind::Press && !browser.handle(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Play in the terminal until a key that quits is pressed. The last state
+/// stays up once the kyoku is over.
+pub fn play(mut player: Player) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = play_in(&mut terminal, &mut player);
+    ratatui::restore();
+    result
+}
+
+fn play_in(terminal: &mut DefaultTerminal, player: &mut Player) -> io::Result<()> {
+    let mut next = Instant::now() + player.delay();
+    loop {
+        terminal.draw(|frame| player.render(frame))?;
+        let timeout = next.saturating_duration_since(Instant::now());
+        if !term::poll(timeout)? {
+            player.tick();
+            next = Instant::now() + player.delay();
+            continue;
+        }
+        if let term::Event::Key(key) = term::read()? {
+            if key.kind == KeyEventKind::Press && !player.handle(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::tenhou::Log;
+    use crate::testdata::Fixture;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn browse() {
+        let log = Log::from_json_str(Fixture::Hanchan.json()).unwrap();
+        let events = tenhou_to_mjai(&log).unwrap();
+        let mut browser = Browser::new(&events).unwrap();
+        assert!(matches!(browser.event(), Some(Event::StartKyoku { .. })));
+        let state = browser.state().unwrap();
+        assert!(state.players[..3].iter().all(|p| p.tehai.len() == 13));
+
+        assert!(browser.handle(KeyCode::End));
+        assert_eq!(browser.event(), Some(&Event::EndKyoku));
+        let state = browser.state().unwrap();
+        assert!(state.players[..3].iter().all(|p| !p.river.is_empty()));
+        // Not past the end, nor before start_kyoku.
+        browser.handle(KeyCode::Right);
+        assert_eq!(browser.event(), Some(&Event::EndKyoku));
+        browser.handle(KeyCode::Home);
+        browser.handle(KeyCode::Left);
+        assert!(matches!(browser.event(), Some(Event::StartKyoku { .. })));
+
+        assert!(browser.handle(KeyCode::Down));
+        assert_eq!(browser.kyoku(), 1);
+        assert!(browser.handle(KeyCode::Up));
+        assert!(browser.handle(KeyCode::Up));
+        assert_eq!(browser.kyoku(), 0);
+        assert!(!browser.handle(KeyCode::Char('q')));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        browser.handle(KeyCode::Char('u'));
+        browser.handle(KeyCode::Right);
+        terminal.draw(|frame| browser.render(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains(&log.names[0]));
+        assert!(screen.contains("E1-0"));
+        assert!(screen.contains("\"type\":\"tsumo\""));
+    }
+
+    #[test]
+    fn play() {
+        let log = Log::from_json_str(Fixture::Hanchan.json()).unwrap();
+        let events = tenhou_to_mjai(&log).unwrap();
+        let mut browser = Browser::new(&events).unwrap();
+        assert!(!browser.select(15, None));
+        assert!(browser.select(1, None));
+        assert_eq!(browser.kyoku(), 1);
+        assert!(matches!(
+            browser.event(),
+            Some(Event::StartKyoku { kyoku: 2, .. })
+        ));
+
+        let mut player = Player::new(browser, Duration::from_millis(400));
+        player.tick();
+        assert!(matches!(
+            player.browser().event(),
+            Some(Event::Tsumo { .. })
+        ));
+        player.handle(KeyCode::Char('+'));
+        assert_eq!(player.delay(), Duration::from_millis(200));
+        player.handle(KeyCode::Char(' '));
+        player.tick();
+        assert!(matches!(
+            player.browser().event(),
+            Some(Event::Tsumo { .. })
+        ));
+        player.handle(KeyCode::Char(' '));
+        while !player.is_finished() {
+            player.tick();
+        }
+        assert_eq!(player.browser().event(), Some(&Event::EndKyoku));
+        // Stays at the end.
+        player.tick();
+        assert_eq!(player.browser().event(), Some(&Event::EndKyoku));
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| player.render(frame)).unwrap();
+        assert!(!player.handle(KeyCode::Char('q')));
+    }
+}
@@ -0,0 +1,299 @@
+//! Every check the library has on a log, run one after the other.
+
+use crate::cancel::{self, CancelToken};
+use crate::conv::{tenhou_to_mjai_with_options, ConvertError, ConvertOptions};
+use crate::hand::{check_dora, check_situations, check_ura, HandError};
+use crate::replay::{check_sanma_game, KyokuState, ReplayError};
+use crate::ruleset::Ruleset;
+use crate::tenhou::{Kyoku, Log, RuleError, ScoringRules, StickRule};
+use crate::{Event, Points};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ValidateError {
+    Rule(RuleError),
+    Hand(HandError),
+    Convert(Box<ConvertError>),
+    Replay(ReplayError),
+    ScoreMismatch {
+        kyoku: u8,
+        honba: u8,
+        expected: [Points; 4],
+        scores: [Points; 4],
+    },
+    Cancelled,
+}
+
+impl fmt::Display for ValidateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rule(err) => fmt::Display::fmt(err, f),
+            Self::Hand(err) => fmt::Display::fmt(err, f),
+            Self::Convert(err) => fmt::Display::fmt(err, f),
+            Self::Replay(err) => fmt::Display::fmt(err, f),
+            Self::ScoreMismatch {
+                kyoku,
+                honba,
+                expected,
+                scores,
+            } => write!(
+                f,
+                "scores {scores:?} at the start of kyoku {kyoku} honba {honba} do not \
+                follow from the kyoku before, which ended with {expected:?}"
+            ),
+            Self::Cancelled => f.write_str("validation cancelled"),
+        }
+    }
+}
+
+impl Error for ValidateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Rule(err) => err.source(),
+            Self::Hand(err) => err.source(),
+            Self::Convert(err) => err.source(),
+            Self::Replay(err) => err.source(),
+            Self::ScoreMismatch { .. } | Self::Cancelled => None,
+        }
+    }
+}
+
+impl From<RuleError> for ValidateError {
+    fn from(err: RuleError) -> Self {
+        Self::Rule(err)
+    }
+}
+
+impl From<HandError> for ValidateError {
+    fn from(err: HandError) -> Self {
+        Self::Hand(err)
+    }
+}
+
+impl From<Box<ConvertError>> for ValidateError {
+    fn from(err: Box<ConvertError>) -> Self {
+        Self::Convert(err)
+    }
+}
+
+impl From<ReplayError> for ValidateError {
+    fn from(err: ReplayError) -> Self {
+        Self::Replay(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ValidateError>;
+
+/// Options for [`validate`].
+#[derive(Debug, Clone, Default)]
+pub struct ValidateOptions {
+    /// What the final results of logs that record them are checked against.
+    pub ruleset: Ruleset,
+    pub scoring: ScoringRules,
+    pub sticks: StickRule,
+    /// Checked before every check of every kyoku; once cancelled, validation
+    /// stops with [`ValidateError::Cancelled`].
+    pub cancel: Option<CancelToken>,
+}
+
+impl ValidateOptions {
+    fn check_cancelled(&self) -> Result<()> {
+        if cancel::is_cancelled(self.cancel.as_ref()) {
+            return Err(ValidateError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// The issues found by the checks run so far.
+struct Issues {
+    /// Whether to go on after a failed check.
+    all: bool,
+    found: Vec<ValidateError>,
+}
+
+impl Issues {
+    /// Record the outcome of a check, returning whether to run the next one.
+    fn record<E>(&mut self, result: std::result::Result<(), E>) -> bool
+    where
+        ValidateError: From<E>,
+    {
+        let Err(err) = result else {
+            return true;
+        };
+        let err = ValidateError::from(err);
+        let next = self.all && !matches!(err, ValidateError::Cancelled);
+        self.found.push(err);
+        next
+    }
+}
+
+type KyokuCheck = fn(&Kyoku, &ValidateOptions) -> Result<()>;
+
+fn run(log: &Log, options: &ValidateOptions, all: bool) -> Vec<ValidateError> {
+    let mut issues = Issues {
+        all,
+        found: Vec::new(),
+    };
+    let next = issues.record(log.check_rule_flags())
+        && issues.record(options.check_cancelled())
+        && issues.record(log.check_ledger())
+        && issues.record(log.check_final_results(&options.ruleset));
+    if !next {
+        return issues.found;
+    }
+
+    let kyoku_checks: [KyokuCheck; 7] = [
+        |k, options| Ok(k.check_sticks(options.sticks)?),
+        |k, _| Ok(k.check_payers()?),
+        |k, options| Ok(k.check_scores(&options.scoring)?),
+        |k, _| Ok(k.check_indicators()?),
+        |k, _| Ok(check_ura(k)?),
+        |k, _| Ok(check_dora(k)?),
+        |k, _| Ok(check_situations(k)?),
+    ];
+    for check in kyoku_checks {
+        for kyoku in &log.kyokus {
+            if !(issues.record(options.check_cancelled()) && issues.record(check(kyoku, options))) {
+                return issues.found;
+            }
+        }
+    }
+
+    let convert = ConvertOptions {
+        cancel: options.cancel.clone(),
+        ..Default::default()
+    };
+    match tenhou_to_mjai_with_options(log, &convert) {
+        Ok(events) => {
+            issues.record(check_sanma_game(&events));
+        }
+        Err(ConvertError::Cancelled) => issues.found.push(ValidateError::Cancelled),
+        Err(err) => {
+            issues.record(Err(Box::new(err)));
+        }
+    }
+    issues.found
+}
+
+/// Check the rule flags, ledger, final results, sticks, payers, scores,
+/// indicators, dora, ura and chankan of `log`, and that it converts to a legal
+/// sanma game (see [`check_sanma_game`]), stopping at the first failure.
+pub fn validate(log: &Log, options: &ValidateOptions) -> Result<()> {
+    run(log, options, false).pop().map_or(Ok(()), Err)
+}
+
+/// Run the checks of [`validate`] without stopping at a failure and return
+/// every issue found, in the order the checks ran. Cancellation stops the run
+/// and is then the last issue.
+pub fn validate_all(log: &Log, options: &ValidateOptions) -> Vec<ValidateError> {
+    run(log, options, true)
+}
+
+/// Check mjai events written by another tool: that they are a legal sanma
+/// game (see [`check_sanma_game`]), without chi and with nukidora only of
+/// norths, and that every kyoku starts with the scores the one before ended
+/// with, adding up the deltas of its riichi, wins and draws.
+///
+/// A kyoku with a win or draw without deltas, as some tools write them, is
+/// taken to end with the scores the next starts with.
+pub fn validate_mjai(events: &[Event]) -> Result<()> {
+    check_sanma_game(events)?;
+    let mut state = KyokuState::default();
+    let mut known = false;
+    for (index, event) in events.iter().enumerate() {
+        match *event {
+            Event::StartKyoku {
+                kyoku,
+                honba,
+                scores,
+                ..
+            } => {
+                if known && scores != state.scores {
+                    return Err(ValidateError::ScoreMismatch {
+                        kyoku,
+                        honba,
+                        expected: state.scores,
+                        scores,
+                    });
+                }
+                known = true;
+            }
+            Event::Hora { deltas: None, .. } | Event::Ryukyoku { deltas: None, .. } => {
+                known = false;
+            }
+            _ => (),
+        }
+        state.apply(index, event)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conv::tenhou_to_mjai;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn cancel() {
+        let log = Log::from_json_str(Fixture::Hanchan.json()).unwrap();
+        let mut options = ValidateOptions::default();
+        validate(&log, &options).unwrap();
+
+        let token = CancelToken::new();
+        options.cancel = Some(token.clone());
+        validate(&log, &options).unwrap();
+        token.cancel();
+        let err = validate(&log, &options).unwrap_err();
+        assert!(matches!(err, ValidateError::Cancelled), "{err}");
+    }
+
+    #[test]
+    fn all_issues() {
+        let mut log = Fixture::Hanchan.log();
+        log.kyokus[2].dora_indicators.clear();
+        log.kyokus[5].dora_indicators.clear();
+        let options = ValidateOptions::default();
+        let issues = validate_all(&log, &options);
+        let first = validate(&log, &options).unwrap_err();
+        assert_eq!(issues[0].to_string(), first.to_string());
+        // The indicators of both kyokus are reported, not only the first.
+        assert!(issues[1].to_string().contains("kyoku 2"), "{}", issues[1]);
+
+        assert!(validate_all(&Fixture::Hanchan.log(), &options).is_empty());
+    }
+
+    #[test]
+    fn mjai() {
+        for fixture in Fixture::ALL {
+            validate_mjai(&tenhou_to_mjai(&fixture.log()).unwrap()).unwrap();
+        }
+
+        let mut events = tenhou_to_mjai(&Fixture::Hanchan.log()).unwrap();
+        let hora = events
+            .iter()
+            .position(|e| matches!(e, Event::Hora { .. }))
+            .unwrap();
+        if let Event::Hora {
+            deltas: Some(deltas),
+            ..
+        } = &mut events[hora]
+        {
+            deltas[0] += 1000;
+            deltas[1] -= 1000;
+        }
+        let err = validate_mjai(&events).unwrap_err();
+        assert!(
+            matches!(err, ValidateError::ScoreMismatch { kyoku: 2, .. }),
+            "{err}"
+        );
+
+        // Without the deltas of the win the scores cannot be told.
+        if let Event::Hora { deltas, .. } = &mut events[hora] {
+            *deltas = None;
+        }
+        validate_mjai(&events).unwrap();
+    }
+}
@@ -0,0 +1,465 @@
+//! Listing and reading the files of a directory on several threads, for
+//! archives of millions of small logs where going through them one by one
+//! takes longer than converting them.
+//!
+//! Every thread has a queue of its own of directories to list, and takes
+//! work from the others once it runs out, so that one big tree is shared out
+//! rather than left to the thread that listed it. Once everything is listed,
+//! the files are read on all the threads ahead of the caller and handed over
+//! in the order of their paths, so that reading overlaps with whatever the
+//! caller does with them and runs give the same order every time.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Options for [`walk`].
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    /// Threads listing and reading; 0 for one per CPU.
+    pub threads: usize,
+    /// Whether to go into subdirectories.
+    pub recursive: bool,
+    /// Which files to yield.
+    pub select: fn(&Path) -> bool,
+    /// Which of the files yielded to read on the walking threads. Others are
+    /// yielded without contents, e.g. files too big to hold in memory.
+    pub preload: fn(&Path) -> bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            threads: 0,
+            recursive: false,
+            select: |_| true,
+            preload: |_| true,
+        }
+    }
+}
+
+/// A file found by [`walk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// What the file holds, if [`WalkOptions::preload`] took it.
+    pub contents: Option<Vec<u8>>,
+}
+
+/// How far a walk has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalkStats {
+    pub dirs: u64,
+    pub files: u64,
+    /// The bytes of the files read.
+    pub bytes: u64,
+    /// Since the walk started.
+    pub elapsed: Duration,
+}
+
+impl WalkStats {
+    #[must_use]
+    pub fn files_per_sec(&self) -> f64 {
+        self.files as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    #[must_use]
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for WalkStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MIB: f64 = 1024.0 * 1024.0;
+        write!(
+            f,
+            "walked {} directories and {} files, {:.1} MiB, in {:.2}s ({:.0} files/s, {:.1} MiB/s)",
+            self.dirs,
+            self.files,
+            self.bytes as f64 / MIB,
+            self.elapsed.as_secs_f64(),
+            self.files_per_sec(),
+            self.bytes_per_sec() / MIB,
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Counters {
+    started: Instant,
+    dirs: AtomicU64,
+    files: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// A handle on the [`WalkStats`] of a walk, still usable once the [`Walk`]
+/// itself is consumed.
+#[derive(Debug, Clone)]
+pub struct Meter(Arc<Counters>);
+
+impl Meter {
+    #[must_use]
+    pub fn stats(&self) -> WalkStats {
+        let counters = &self.0;
+        WalkStats {
+            dirs: counters.dirs.load(Ordering::Relaxed),
+            files: counters.files.load(Ordering::Relaxed),
+            bytes: counters.bytes.load(Ordering::Relaxed),
+            elapsed: counters.started.elapsed(),
+        }
+    }
+}
+
+/// The files under a directory in the order of their paths. Errors listing
+/// or reading something are yielded in place of it and do not stop the walk.
+///
+/// The threads stop once everything is walked or the `Walk` is dropped.
+#[derive(Debug)]
+pub struct Walk {
+    entries: Receiver<(usize, io::Result<Entry>)>,
+    /// Entries received ahead of those before them, by their position.
+    ahead: BTreeMap<usize, io::Result<Entry>>,
+    shared: Arc<Shared>,
+    meter: Meter,
+}
+
+impl Walk {
+    #[must_use]
+    pub fn meter(&self) -> Meter {
+        self.meter.clone()
+    }
+}
+
+impl Iterator for Walk {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.shared.yielded.load(Ordering::Relaxed);
+        loop {
+            if let Some(entry) = self.ahead.remove(&next) {
+                self.shared.yielded.store(next + 1, Ordering::Relaxed);
+                return Some(entry);
+            }
+            let (position, entry) = self.entries.recv().ok()?;
+            self.ahead.insert(position, entry);
+        }
+    }
+}
+
+impl Drop for Walk {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Walk `root` with `options`. A `root` that is a file is yielded whatever
+/// [`WalkOptions::select`] says of it.
+#[must_use]
+pub fn walk(root: impl Into<PathBuf>, options: &WalkOptions) -> Walk {
+    let root = root.into();
+    let threads = match options.threads {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        threads => threads,
+    };
+    // Enough to keep the caller busy without reading far ahead of it.
+    let ahead = threads * 64;
+    let (sender, entries) = mpsc::sync_channel(ahead);
+    let meter = Meter(Arc::new(Counters {
+        started: Instant::now(),
+        dirs: AtomicU64::new(0),
+        files: AtomicU64::new(0),
+        bytes: AtomicU64::new(0),
+    }));
+    let shared = Arc::new(Shared {
+        queues: (0..threads).map(|_| Mutex::default()).collect(),
+        pending: AtomicUsize::new(0),
+        listed: Mutex::default(),
+        found: OnceLock::new(),
+        next: AtomicUsize::new(0),
+        yielded: AtomicUsize::new(0),
+        ahead,
+        stopped: AtomicBool::new(false),
+        counters: Arc::clone(&meter.0),
+        options: options.clone(),
+    });
+    if fs::metadata(&root).is_ok_and(|m| !m.is_dir()) {
+        shared.listed.lock().unwrap().push(Found::File(root));
+    } else {
+        shared.pending.store(1, Ordering::Relaxed);
+        shared.queues[0].lock().unwrap().push_back(root);
+    }
+    for id in 0..threads {
+        let worker = Worker {
+            id,
+            shared: Arc::clone(&shared),
+            sender: sender.clone(),
+        };
+        thread::spawn(move || worker.run());
+    }
+    Walk {
+        entries,
+        ahead: BTreeMap::new(),
+        shared,
+        meter,
+    }
+}
+
+/// Something found listing the directories.
+#[derive(Debug)]
+enum Found {
+    File(PathBuf),
+    /// An error listing the directory, kept by its kind and message since
+    /// [`io::Error`] cannot be shared.
+    Error(PathBuf, io::ErrorKind, String),
+}
+
+impl Found {
+    fn path(&self) -> &Path {
+        match self {
+            Self::File(path) | Self::Error(path, ..) => path,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    /// The directories to list.
+    queues: Vec<Mutex<VecDeque<PathBuf>>>,
+    /// Directories queued or being listed. Directories a listing finds are
+    /// counted before it is done, so this only gets to 0 once all of them
+    /// are.
+    pending: AtomicUsize,
+    listed: Mutex<Vec<Found>>,
+    /// What was listed, in the order of the paths, once everything is.
+    found: OnceLock<Vec<Found>>,
+    /// The position in `found` of the next entry to read.
+    next: AtomicUsize,
+    /// The entries the [`Walk`] has yielded.
+    yielded: AtomicUsize,
+    /// How far ahead of `yielded` entries are read.
+    ahead: usize,
+    stopped: AtomicBool,
+    counters: Arc<Counters>,
+    options: WalkOptions,
+}
+
+struct Worker {
+    id: usize,
+    shared: Arc<Shared>,
+    sender: SyncSender<(usize, io::Result<Entry>)>,
+}
+
+impl Worker {
+    fn run(&self) {
+        let shared = &*self.shared;
+        while !shared.stopped.load(Ordering::Relaxed) {
+            let Some(dir) = self.next_dir() else {
+                if shared.pending.load(Ordering::Acquire) == 0 {
+                    break;
+                }
+                thread::sleep(Duration::from_micros(50));
+                continue;
+            };
+            self.list(&dir);
+            shared.pending.fetch_sub(1, Ordering::Release);
+        }
+
+        let found = shared.found.get_or_init(|| {
+            let mut found = mem::take(&mut *shared.listed.lock().unwrap());
+            found.sort_by(|a, b| a.path().cmp(b.path()));
+            found
+        });
+        while !shared.stopped.load(Ordering::Relaxed) {
+            let position = shared.next.fetch_add(1, Ordering::Relaxed);
+            let Some(found) = found.get(position) else {
+                return;
+            };
+            while position >= shared.yielded.load(Ordering::Relaxed) + shared.ahead {
+                if shared.stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                thread::sleep(Duration::from_micros(50));
+            }
+            if self.sender.send((position, self.entry(found))).is_err() {
+                // The walk was dropped; nobody is waiting for the rest.
+                shared.stopped.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// The newest directory of our own queue, or else the oldest of
+    /// another's.
+    fn next_dir(&self) -> Option<PathBuf> {
+        let queues = &self.shared.queues;
+        let own = queues[self.id].lock().unwrap().pop_back();
+        own.or_else(|| {
+            (1..queues.len())
+                .map(|offset| (self.id + offset) % queues.len())
+                .find_map(|victim| queues[victim].lock().unwrap().pop_front())
+        })
+    }
+
+    fn push(&self, dir: PathBuf) {
+        self.shared.pending.fetch_add(1, Ordering::AcqRel);
+        self.shared.queues[self.id].lock().unwrap().push_back(dir);
+    }
+
+    fn list(&self, dir: &Path) {
+        let shared = &*self.shared;
+        let mut found = vec![];
+        let error = |err: io::Error| {
+            let err = with_path(err, dir);
+            Found::Error(dir.to_owned(), err.kind(), err.to_string())
+        };
+        match fs::read_dir(dir) {
+            Ok(entries) => {
+                shared.counters.dirs.fetch_add(1, Ordering::Relaxed);
+                for entry in entries {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(err) => {
+                            found.push(error(err));
+                            continue;
+                        }
+                    };
+                    let path = entry.path();
+                    // Links are followed, like `Path::is_file` does.
+                    let file_type = match entry.file_type() {
+                        Ok(t) if t.is_symlink() => fs::metadata(&path).map(|m| m.file_type()),
+                        result => result,
+                    };
+                    let is_dir = match file_type {
+                        Ok(t) => t.is_dir(),
+                        // Dangling links and the like are not inputs.
+                        Err(_) => continue,
+                    };
+                    if is_dir {
+                        if shared.options.recursive {
+                            self.push(path);
+                        }
+                    } else if (shared.options.select)(&path) {
+                        found.push(Found::File(path));
+                    }
+                }
+            }
+            Err(err) => found.push(error(err)),
+        }
+        shared.listed.lock().unwrap().append(&mut found);
+    }
+
+    fn entry(&self, found: &Found) -> io::Result<Entry> {
+        let counters = &self.shared.counters;
+        let path = match found {
+            Found::Error(_, kind, message) => return Err(io::Error::new(*kind, message.clone())),
+            Found::File(path) if !(self.shared.options.preload)(path) => path,
+            Found::File(path) => {
+                let contents = fs::read(path).map_err(|err| with_path(err, path))?;
+                counters.files.fetch_add(1, Ordering::Relaxed);
+                counters
+                    .bytes
+                    .fetch_add(contents.len() as u64, Ordering::Relaxed);
+                return Ok(Entry {
+                    path: path.clone(),
+                    contents: Some(contents),
+                });
+            }
+        };
+        counters.files.fetch_add(1, Ordering::Relaxed);
+        Ok(Entry {
+            path: path.clone(),
+            contents: None,
+        })
+    }
+}
+
+/// `err` with `path` in its message, which the errors of `fs` leave out.
+fn with_path(err: io::Error, path: &Path) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn walk() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut expected = vec![];
+        for shard in 0..4 {
+            let shard = dir.path().join(format!("{shard:02}"));
+            fs::create_dir_all(&shard).unwrap();
+            for i in 0..50 {
+                let path = shard.join(format!("{i}.json"));
+                fs::write(&path, i.to_string()).unwrap();
+                expected.push(path);
+            }
+            fs::write(shard.join("big.jsonl"), "{}").unwrap();
+            fs::write(shard.join("readme.txt"), "").unwrap();
+        }
+        fs::write(dir.path().join("top.json"), "top").unwrap();
+
+        let options = WalkOptions {
+            threads: 3,
+            recursive: true,
+            select: |path| {
+                path.extension()
+                    .is_some_and(|e| e == "json" || e == "jsonl")
+            },
+            preload: |path| path.extension().is_some_and(|e| e == "json"),
+        };
+        let walk = super::walk(dir.path(), &options);
+        let meter = walk.meter();
+        let entries: Vec<_> = walk.map(Result::unwrap).collect();
+        assert!(entries.is_sorted_by(|a, b| a.path < b.path));
+        let (lazy, read): (Vec<_>, Vec<_>) =
+            entries.into_iter().partition(|e| e.contents.is_none());
+        assert_eq!(lazy.len(), 4);
+        assert!(lazy.iter().all(|e| e.path.ends_with("big.jsonl")));
+        expected.push(dir.path().join("top.json"));
+        expected.sort();
+        assert_eq!(
+            read.iter().map(|e| &e.path).collect::<Vec<_>>(),
+            expected.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(read.last().unwrap().contents.as_deref(), Some(&b"top"[..]));
+
+        let stats = meter.stats();
+        assert_eq!((stats.dirs, stats.files), (5, 205));
+        assert_eq!(stats.bytes, 4 * (10 + 2 * 40) + 3);
+
+        // Without recursion only the top level, and a missing directory is
+        // an error of the walk rather than a panic.
+        let options = WalkOptions {
+            recursive: false,
+            ..options
+        };
+        let paths: Vec<_> = super::walk(dir.path(), &options)
+            .map(|e| e.unwrap().path)
+            .collect();
+        assert_eq!(paths, [dir.path().join("top.json")]);
+        let mut missing = super::walk(dir.path().join("missing"), &options);
+        missing.next().unwrap().unwrap_err();
+        assert!(missing.next().is_none());
+
+        // A file is walked as itself.
+        let readme = dir.path().join("00/readme.txt");
+        let entries: Vec<_> = super::walk(&readme, &options).map(Result::unwrap).collect();
+        assert_eq!(
+            entries,
+            [Entry {
+                path: readme,
+                contents: None,
+            }]
+        );
+    }
+}
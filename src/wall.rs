@@ -0,0 +1,214 @@
+//! The wall of a sanma kyoku as far as its events tell, for simulators that
+//! replay a kyoku from its middle and need the draws still to come.
+//!
+//! Logs record the tiles every seat drew but not where in the wall they lay,
+//! so the wall is laid out in the order tiles leave it rather than as tenhou
+//! stacks it: the haipai of the three seats, the draws from the live wall,
+//! the rinshan draws and the dead wall. Every rinshan draw takes a tile from
+//! the end of the live wall, with which the dead wall is topped up, so the
+//! live wall and the rinshan draws always add up to the same number of tiles.
+
+use crate::replay::{self, DrawKind, KyokuState, ReplayError};
+use crate::{Event, Tile};
+
+/// Four of each of the 27 kinds of sanma.
+pub const WALL: usize = 108;
+/// The tiles dealt and left in the live wall, of which the rinshan draws
+/// take from its end.
+const LIVE: usize = WALL - DEAD_WALL - 3 * HAIPAI;
+const DEAD_WALL: usize = 14;
+const HAIPAI: usize = 13;
+/// The places of dora indicators in the dead wall, and of ura indicators.
+const INDICATORS: usize = 5;
+/// The places in the dead wall the rinshan draws come from, however many
+/// there were before.
+const RINSHAN: usize = 4;
+/// The number of kinds of tiles, indexed by [`Tile::as_usize`].
+pub(crate) const KINDS: usize = 27;
+
+/// The tiles a kyoku dealt and drew, in the order they left the wall.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Wall {
+    /// As dealt, the fourth seat's empty.
+    pub haipai: [Vec<Tile>; 4],
+    /// The draws from the live wall, in order.
+    pub live: Vec<Tile>,
+    /// The draws the live wall still had when the kyoku ended, the last of
+    /// them the haitei.
+    pub live_left: usize,
+    /// The draws after kans and nukidora, in order.
+    pub rinshan: Vec<Tile>,
+    pub dora_markers: Vec<Tile>,
+    /// Those a riichi winner revealed, if any.
+    pub ura_markers: Vec<Tile>,
+}
+
+impl Wall {
+    /// Infer the wall from `events`, those of one kyoku from `start_kyoku`,
+    /// as [`replay::split_kyokus`] gives them.
+    pub fn infer(events: &[Event]) -> replay::Result<Self> {
+        let mut state = KyokuState::default();
+        let mut wall = Self::default();
+        for (index, event) in events.iter().enumerate() {
+            state.apply(index, event)?;
+            match event {
+                Event::StartKyoku {
+                    dora_marker,
+                    tehais,
+                    kyoku,
+                    honba,
+                    ..
+                } => {
+                    if tehais[..3].iter().any(|t| t.len() != HAIPAI) || !tehais[3].is_empty() {
+                        return Err(ReplayError::NotSanma {
+                            kyoku: *kyoku,
+                            honba: *honba,
+                        });
+                    }
+                    wall = Self {
+                        haipai: tehais.clone(),
+                        dora_markers: vec![*dora_marker],
+                        ..Self::default()
+                    };
+                }
+                Event::Tsumo { pai, .. } => match state.last_draw {
+                    Some(DrawKind::Rinshan) => wall.rinshan.push(*pai),
+                    _ => wall.live.push(*pai),
+                },
+                Event::Dora { dora_marker } => wall.dora_markers.push(*dora_marker),
+                Event::Hora {
+                    ura_markers: Some(ura_markers),
+                    ..
+                } if wall.ura_markers.is_empty() => wall.ura_markers.clone_from(ura_markers),
+                _ => (),
+            }
+        }
+        wall.live_left = usize::from(state.wall);
+
+        let counts = wall.counts();
+        if let Some(kind) = counts.iter().position(|&count| count > 4) {
+            return Err(ReplayError::TooManyTiles {
+                tile: Tile::try_from(kind).unwrap_or_default(),
+                kyoku: state.kyoku,
+                honba: state.honba,
+            });
+        }
+        Ok(wall)
+    }
+
+    /// The tiles of the set that never showed, sorted and without aka, as
+    /// the log does not tell which of the fives they are: those left in the
+    /// live wall and the hidden ones of the dead wall.
+    #[must_use]
+    pub fn unseen(&self) -> Vec<Tile> {
+        let counts = self.counts();
+        (0..KINDS)
+            .filter_map(|kind| Some((Tile::try_from(kind).ok()?, counts[kind])))
+            .flat_map(|(tile, count)| std::iter::repeat_n(tile, 4 - usize::from(count)))
+            .collect()
+    }
+
+    /// The whole wall of [`WALL`] places in the order tiles leave it, `None`
+    /// for the places of the [`Self::unseen`] tiles: the haipai of the first,
+    /// second and third seat, the live wall, the rinshan draws followed by
+    /// the places left for them, and the places of the dora indicators and
+    /// of the ura indicators.
+    #[must_use]
+    pub fn slots(&self) -> Vec<Option<Tile>> {
+        let mut slots = Vec::with_capacity(WALL);
+        let mut place = |tiles: &[Tile], places: usize| {
+            let end = slots.len() + places.max(tiles.len());
+            slots.extend(tiles.iter().copied().map(Some));
+            slots.resize(end, None);
+        };
+        for haipai in &self.haipai[..3] {
+            place(haipai, HAIPAI);
+        }
+        place(&self.live, LIVE - self.rinshan.len());
+        place(&self.rinshan, self.rinshan.len() + RINSHAN);
+        place(&self.dora_markers, INDICATORS);
+        place(&self.ura_markers, INDICATORS);
+        slots
+    }
+
+    /// How many of every kind of tile showed, by [`Tile::as_usize`] of the
+    /// plain tile.
+    fn counts(&self) -> [u8; KINDS] {
+        let mut counts = [0_u8; KINDS];
+        let tiles = self
+            .haipai
+            .iter()
+            .flatten()
+            .chain(&self.live)
+            .chain(&self.rinshan)
+            .chain(&self.dora_markers)
+            .chain(&self.ura_markers);
+        for tile in tiles.filter(|t| !t.is_unknown()) {
+            counts[tile.deaka().as_usize()] = counts[tile.deaka().as_usize()].saturating_add(1);
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::replay::split_kyokus;
+    use crate::tenhou_to_mjai;
+    use crate::testdata::Fixture;
+
+    #[test]
+    fn infer() {
+        for fixture in Fixture::ALL {
+            let events = tenhou_to_mjai(&fixture.log()).unwrap();
+            for kyoku in split_kyokus(&events) {
+                let wall = Wall::infer(kyoku).unwrap();
+                let slots = wall.slots();
+                assert_eq!(slots.len(), WALL, "{fixture:?}");
+                let mut tiles: Vec<_> = slots.iter().flatten().map(|t| t.deaka()).collect();
+                assert_eq!(tiles.len() + wall.unseen().len(), WALL);
+                tiles.extend(wall.unseen());
+                tiles.sort_unstable();
+                // Four of every kind.
+                assert!(tiles.chunks(4).all(|c| c.iter().all(|&t| t == c[0])));
+                assert_eq!(
+                    wall.live.len() + wall.live_left + wall.rinshan.len(),
+                    LIVE,
+                    "{fixture:?}"
+                );
+            }
+        }
+
+        let events = tenhou_to_mjai(&Fixture::KanHeavy.log()).unwrap();
+        let kyoku = split_kyokus(&events).next().unwrap();
+        let wall = Wall::infer(kyoku).unwrap();
+        // Three kans and a kita, the last kan won on before its dora showed.
+        assert_eq!(wall.rinshan.len(), 4);
+        assert_eq!(wall.dora_markers.len(), 3);
+        let slots = wall.slots();
+        // The first draw of the dealer follows the haipai.
+        assert_eq!(slots[3 * HAIPAI], Some(wall.live[0]));
+        assert_eq!(
+            slots[LIVE + 3 * HAIPAI - wall.rinshan.len()],
+            Some(wall.rinshan[0])
+        );
+        assert_eq!(slots[WALL - 2 * INDICATORS], Some(wall.dora_markers[0]));
+    }
+
+    #[test]
+    fn too_many_tiles() {
+        let events = tenhou_to_mjai(&Fixture::Hanchan.log()).unwrap();
+        // Only the deal, so that the hand plays no part.
+        let mut kyoku = split_kyokus(&events).next().unwrap()[..1].to_vec();
+        if let Event::StartKyoku {
+            dora_marker,
+            tehais,
+            ..
+        } = &mut kyoku[0]
+        {
+            tehais[0][..5].fill(*dora_marker);
+        }
+        let err = Wall::infer(&kyoku).unwrap_err();
+        assert!(matches!(err, ReplayError::TooManyTiles { .. }), "{err}");
+    }
+}
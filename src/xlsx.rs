@@ -0,0 +1,290 @@
+//! A minimal writer of Excel workbooks, enough for tables of statistics: one
+//! sheet per table of text and number cells, with no styles or formulas.
+
+use std::fmt::Write as _;
+use std::io::{self, Seek, Write};
+
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// The value of a cell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Empty,
+    Text(String),
+    /// Written empty if not finite, as workbooks cannot hold NaN.
+    Number(f64),
+}
+
+impl From<&str> for Cell {
+    fn from(s: &str) -> Self {
+        Self::Text(s.to_owned())
+    }
+}
+
+impl From<String> for Cell {
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<u32> for Cell {
+    fn from(n: u32) -> Self {
+        Self::Number(f64::from(n))
+    }
+}
+
+impl From<i64> for Cell {
+    fn from(n: i64) -> Self {
+        Self::Number(n as f64)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(n: f64) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl<T: Into<Self>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Empty, Into::into)
+    }
+}
+
+/// A sheet of rows, the first of which is usually a header.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sheet {
+    name: String,
+    rows: Vec<Vec<Cell>>,
+}
+
+/// Excel refuses longer sheet names.
+const MAX_SHEET_NAME: usize = 31;
+
+impl Sheet {
+    /// A sheet called `name`, with the characters Excel does not allow in
+    /// sheet names replaced by `_` and cut to 31 characters.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        let name = name
+            .chars()
+            .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+            .take(MAX_SHEET_NAME)
+            .collect();
+        Self { name, rows: vec![] }
+    }
+
+    pub fn push_row<I>(&mut self, row: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<Cell>,
+    {
+        self.rows.push(row.into_iter().map(Into::into).collect());
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn rows(&self) -> &[Vec<Cell>] {
+        &self.rows
+    }
+
+    fn to_xml(&self) -> String {
+        let mut xml = String::from(XML_DECLARATION);
+        xml.push_str(r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#);
+        for (r, row) in (1..).zip(&self.rows) {
+            let _ = write!(xml, r#"<row r="{r}">"#);
+            for (c, cell) in row.iter().enumerate() {
+                let reference = format!("{}{r}", column_name(c));
+                match cell {
+                    Cell::Text(s) => {
+                        let _ = write!(
+                            xml,
+                            r#"<c r="{reference}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                            escape(s),
+                        );
+                    }
+                    Cell::Number(n) if n.is_finite() => {
+                        let _ = write!(xml, r#"<c r="{reference}"><v>{n}</v></c>"#);
+                    }
+                    Cell::Number(_) | Cell::Empty => (),
+                }
+            }
+            xml.push_str("</row>");
+        }
+        xml.push_str("</sheetData></worksheet>");
+        xml
+    }
+}
+
+/// Sheets written together as one `.xlsx` file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Workbook {
+    sheets: Vec<Sheet>,
+}
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+impl Workbook {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, sheet: Sheet) {
+        self.sheets.push(sheet);
+    }
+
+    #[must_use]
+    pub fn sheets(&self) -> &[Sheet] {
+        &self.sheets
+    }
+
+    /// Write the workbook as an Office Open XML (`.xlsx`) file.
+    pub fn write<W: Write + Seek>(&self, w: W) -> io::Result<()> {
+        let mut zip = ZipWriter::new(w);
+        let options = SimpleFileOptions::default();
+        let mut part = |name: &str, content: &str| -> io::Result<()> {
+            zip.start_file(name, options)?;
+            zip.write_all(content.as_bytes())
+        };
+
+        let mut content_types = String::from(XML_DECLARATION);
+        content_types.push_str(concat!(
+            r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+            r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+            r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+            r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+        ));
+        for i in 1..=self.sheets.len() {
+            let _ = write!(
+                content_types,
+                r#"<Override PartName="/xl/worksheets/sheet{i}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+            );
+        }
+        content_types.push_str("</Types>");
+        part("[Content_Types].xml", &content_types)?;
+
+        part(
+            "_rels/.rels",
+            &format!(
+                "{XML_DECLARATION}{}{}{}",
+                r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+                r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>"#,
+                "</Relationships>",
+            ),
+        )?;
+
+        let mut workbook = String::from(XML_DECLARATION);
+        workbook.push_str(concat!(
+            r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>"#,
+        ));
+        let mut rels = String::from(XML_DECLARATION);
+        rels.push_str(r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#);
+        for (i, sheet) in (1..).zip(&self.sheets) {
+            let _ = write!(
+                workbook,
+                r#"<sheet name="{}" sheetId="{i}" r:id="rId{i}"/>"#,
+                escape(&sheet.name),
+            );
+            let _ = write!(
+                rels,
+                r#"<Relationship Id="rId{i}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{i}.xml"/>"#,
+            );
+        }
+        workbook.push_str("</sheets></workbook>");
+        rels.push_str("</Relationships>");
+        part("xl/workbook.xml", &workbook)?;
+        part("xl/_rels/workbook.xml.rels", &rels)?;
+
+        for (i, sheet) in (1..).zip(&self.sheets) {
+            part(&format!("xl/worksheets/sheet{i}.xml"), &sheet.to_xml())?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+/// The letters of the column at `index`, counting from 0: `A`, ..., `Z`,
+/// `AA`, ...
+fn column_name(mut index: usize) -> String {
+    let mut letters = vec![];
+    loop {
+        letters.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    letters.reverse();
+    String::from_utf8(letters).expect("column names are ASCII")
+}
+
+/// Escape `s` for XML text and attributes, dropping the control characters
+/// XML cannot hold.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => (),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Cursor, Read};
+    use zip::ZipArchive;
+
+    #[test]
+    fn write() {
+        let mut sheet = Sheet::new("Calls: per player");
+        sheet.push_row(["name", "kyokus"]);
+        sheet.push_row([
+            Cell::from("a & b"),
+            Cell::from(11_u32),
+            Cell::from(None::<f64>),
+        ]);
+        let mut workbook = Workbook::new();
+        workbook.push(sheet);
+
+        let mut out = Cursor::new(vec![]);
+        workbook.write(&mut out).unwrap();
+        let mut archive = ZipArchive::new(out).unwrap();
+        let names: Vec<_> = archive.file_names().collect();
+        assert!(names.contains(&"xl/worksheets/sheet1.xml"), "{names:?}");
+
+        let mut xml = String::new();
+        archive
+            .by_name("xl/workbook.xml")
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        assert!(xml.contains(r#"<sheet name="Calls_ per player" sheetId="1" r:id="rId1"/>"#));
+        xml.clear();
+        archive
+            .by_name("xl/worksheets/sheet1.xml")
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        assert!(xml.contains(r#"<c r="A2" t="inlineStr"><is><t xml:space="preserve">a &amp; b</t></is></c><c r="B2"><v>11</v></c></row>"#));
+
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(27 * 26), "AAA");
+    }
+}